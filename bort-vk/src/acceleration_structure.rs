@@ -0,0 +1,385 @@
+use crate::{
+    allocation_info_cpu_accessible, allocation_info_device_local, read_mapped_bytes,
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, BufferProperties, CommandBuffer,
+    DeletionQueue, Device, DeviceOwned, MemoryError, ALLOCATION_CALLBACK_NONE,
+};
+use ash::{
+    khr,
+    prelude::VkResult,
+    vk::{self, Handle},
+};
+use std::{error, fmt, sync::Arc};
+
+/// Device addresses of acceleration structure serialization buffers must be 256 byte aligned.
+const SERIALIZATION_BUFFER_ALIGNMENT: vk::DeviceSize = 256;
+/// Driver uuid, compatibility uuid, serialized size, deserialized size and handle count.
+const SERIALIZED_HEADER_SIZE: usize = 2 * vk::UUID_SIZE + 3 * 8;
+
+/// A `VK_KHR_acceleration_structure` acceleration structure and the buffer backing it.
+///
+/// Compaction:
+/// 1. build with `ALLOW_COMPACTION` and record
+///    [`CommandBuffer::write_acceleration_structures_properties`] to a query pool created with
+///    [`QueryPoolProperties::new_acceleration_structure_compacted_size`](crate::QueryPoolProperties::new_acceleration_structure_compacted_size).
+/// 2. once complete, read the compacted size with
+///    [`QueryPool::get_results`](crate::QueryPool::get_results) and record
+///    [`Self::new_compacted`]. The original can be dropped once the copy has completed.
+///
+/// Serialization (e.g. to cache BLASes across runs):
+/// 1. query the serialization size the same way with
+///    [`QueryPoolProperties::new_acceleration_structure_serialization_size`](crate::QueryPoolProperties::new_acceleration_structure_serialization_size)
+///    and record [`Self::record_serialize`].
+/// 2. once complete, read the data with [`SerializedAccelerationStructure::read_from_buffer`].
+/// 3. next run, check [`SerializedAccelerationStructure::is_compatible`] and record
+///    [`Self::new_deserialized`].
+pub struct AccelerationStructure {
+    handle: vk::AccelerationStructureKHR,
+    ty: vk::AccelerationStructureTypeKHR,
+    buffer: Buffer,
+    loader: Arc<khr::acceleration_structure::Device>,
+}
+
+impl AccelerationStructure {
+    /// Creates a device local buffer of `size` bytes and an acceleration structure covering it.
+    /// `size` usually comes from `vkGetAccelerationStructureBuildSizesKHR`.
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        ty: vk::AccelerationStructureTypeKHR,
+        size: vk::DeviceSize,
+    ) -> VkResult<Self> {
+        let loader = alloc_access
+            .device()
+            .extension_loader::<khr::acceleration_structure::Device>();
+        let buffer = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(
+                size,
+                vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            ),
+            allocation_info_device_local(),
+        )?;
+
+        let create_info = vk::AccelerationStructureCreateInfoKHR::default()
+            .buffer(buffer.handle())
+            .size(size)
+            .ty(ty);
+        let handle = unsafe {
+            loader.create_acceleration_structure(&create_info, ALLOCATION_CALLBACK_NONE)
+        }?;
+
+        Ok(Self {
+            handle,
+            ty,
+            buffer,
+            loader,
+        })
+    }
+
+    /// Creates an acceleration structure of `compacted_size` bytes and records a `COMPACT` copy
+    /// of this one into it. `compacted_size` is the `ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR`
+    /// query result of this acceleration structure, which must have been built with
+    /// `ALLOW_COMPACTION`.
+    pub fn new_compacted(
+        &self,
+        command_buffer: &CommandBuffer,
+        compacted_size: vk::DeviceSize,
+    ) -> VkResult<Self> {
+        let compacted = Self::new(
+            self.buffer.allocator_access().clone(),
+            self.ty,
+            compacted_size,
+        )?;
+        command_buffer.copy_acceleration_structure(
+            self,
+            &compacted,
+            vk::CopyAccelerationStructureModeKHR::COMPACT,
+        );
+        Ok(compacted)
+    }
+
+    /// Creates a host visible buffer of `serialization_size` bytes and records a serialization
+    /// of this acceleration structure into it. `serialization_size` is the
+    /// `ACCELERATION_STRUCTURE_SERIALIZATION_SIZE_KHR` query result. Read the buffer with
+    /// [`SerializedAccelerationStructure::read_from_buffer`] once the command buffer has
+    /// completed.
+    pub fn record_serialize(
+        &self,
+        command_buffer: &CommandBuffer,
+        serialization_size: vk::DeviceSize,
+    ) -> VkResult<Buffer> {
+        let buffer =
+            new_serialization_buffer(self.buffer.allocator_access().clone(), serialization_size)?;
+        let copy_info = vk::CopyAccelerationStructureToMemoryInfoKHR::default()
+            .src(self.handle)
+            .dst(vk::DeviceOrHostAddressKHR {
+                device_address: buffer.device_address(),
+            })
+            .mode(vk::CopyAccelerationStructureModeKHR::SERIALIZE);
+        unsafe {
+            self.loader
+                .cmd_copy_acceleration_structure_to_memory(command_buffer.handle(), &copy_info)
+        };
+        Ok(buffer)
+    }
+
+    /// Records the deserialization of `serialized` into a new acceleration structure. The
+    /// serialized data is copied to a staging buffer which is pushed to `deletion_queue`.
+    pub fn new_deserialized(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        ty: vk::AccelerationStructureTypeKHR,
+        serialized: &SerializedAccelerationStructure,
+        command_buffer: &CommandBuffer,
+        deletion_queue: &mut DeletionQueue,
+    ) -> Result<Self, AccelerationStructureError> {
+        if !serialized.is_compatible(alloc_access.device()) {
+            return Err(AccelerationStructureError::Incompatible);
+        }
+
+        let mut staging_buffer =
+            new_serialization_buffer(alloc_access.clone(), serialized.data.len() as u64)
+                .map_err(AccelerationStructureError::BufferCreation)?;
+        staging_buffer
+            .memory_allocation_mut()
+            .write_bytes(&serialized.data, 0)
+            .map_err(AccelerationStructureError::Memory)?;
+
+        let acceleration_structure = Self::new(alloc_access, ty, serialized.deserialized_size())
+            .map_err(AccelerationStructureError::Creation)?;
+        let copy_info = vk::CopyMemoryToAccelerationStructureInfoKHR::default()
+            .src(vk::DeviceOrHostAddressConstKHR {
+                device_address: staging_buffer.device_address(),
+            })
+            .dst(acceleration_structure.handle)
+            .mode(vk::CopyAccelerationStructureModeKHR::DESERIALIZE);
+        unsafe {
+            acceleration_structure
+                .loader
+                .cmd_copy_memory_to_acceleration_structure(command_buffer.handle(), &copy_info)
+        };
+        deletion_queue.push(staging_buffer);
+
+        Ok(acceleration_structure)
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetAccelerationStructureDeviceAddressKHR.html>
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        let address_info = vk::AccelerationStructureDeviceAddressInfoKHR::default()
+            .acceleration_structure(self.handle);
+        unsafe {
+            self.loader
+                .get_acceleration_structure_device_address(&address_info)
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> vk::AccelerationStructureKHR {
+        self.handle
+    }
+
+    #[inline]
+    pub fn ty(&self) -> vk::AccelerationStructureTypeKHR {
+        self.ty
+    }
+
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.buffer.properties().size
+    }
+
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+}
+
+impl DeviceOwned for AccelerationStructure {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+impl Drop for AccelerationStructure {
+    fn drop(&mut self) {
+        unsafe {
+            self.loader
+                .destroy_acceleration_structure(self.handle, ALLOCATION_CALLBACK_NONE)
+        }
+    }
+}
+
+/// Host copy of a serialized acceleration structure. See [`AccelerationStructure`].
+///
+/// The data starts with a header containing the driver and compatibility uuids, which are
+/// checked by [`Self::is_compatible`] before deserializing e.g. after a driver update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializedAccelerationStructure {
+    data: Vec<u8>,
+}
+
+impl SerializedAccelerationStructure {
+    /// e.g. data previously returned by [`Self::data`] loaded from disk.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, AccelerationStructureError> {
+        if data.len() < SERIALIZED_HEADER_SIZE {
+            return Err(AccelerationStructureError::InvalidSerializedData { size: data.len() });
+        }
+        let serialized = Self { data };
+        if serialized.serialized_size() > serialized.data.len() as u64 {
+            return Err(AccelerationStructureError::InvalidSerializedData {
+                size: serialized.data.len(),
+            });
+        }
+        Ok(serialized)
+    }
+
+    /// Reads the output buffer of [`AccelerationStructure::record_serialize`]. The serialization
+    /// must have completed.
+    pub fn read_from_buffer(buffer: &mut Buffer) -> Result<Self, AccelerationStructureError> {
+        let size = buffer.properties().size as usize;
+        let data = read_mapped_bytes(buffer.memory_allocation_mut(), 0, size)
+            .map_err(AccelerationStructureError::Memory)?;
+        Self::from_bytes(data)
+    }
+
+    /// Whether `device` can deserialize this data i.e. `vkGetDeviceAccelerationStructureCompatibilityKHR`
+    /// returns `COMPATIBLE`.
+    pub fn is_compatible(&self, device: &Device) -> bool {
+        let loader = device.extension_loader::<khr::acceleration_structure::Device>();
+        let version_info =
+            vk::AccelerationStructureVersionInfoKHR::default().version_data(self.version_data());
+        let compatibility =
+            unsafe { loader.get_device_acceleration_structure_compatibility(&version_info) };
+        compatibility == vk::AccelerationStructureCompatibilityKHR::COMPATIBLE
+    }
+
+    /// The driver and compatibility uuids at the start of the header.
+    pub fn version_data(&self) -> &[u8; 2 * vk::UUID_SIZE] {
+        self.data[..2 * vk::UUID_SIZE]
+            .try_into()
+            .expect("serialized data is at least the header size")
+    }
+
+    /// Total size of the serialized data including the header.
+    pub fn serialized_size(&self) -> u64 {
+        self.header_u64(0)
+    }
+
+    /// Size of the acceleration structure to deserialize into.
+    pub fn deserialized_size(&self) -> u64 {
+        self.header_u64(1)
+    }
+
+    /// Number of bottom level acceleration structure handles following the header (top level
+    /// acceleration structures only). The referenced BLASes must be deserialized first.
+    pub fn handle_count(&self) -> u64 {
+        self.header_u64(2)
+    }
+
+    fn header_u64(&self, index: usize) -> u64 {
+        let start = 2 * vk::UUID_SIZE + index * 8;
+        u64::from_ne_bytes(self.data[start..start + 8].try_into().unwrap())
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+// Helper Functions
+
+fn new_serialization_buffer(
+    alloc_access: Arc<dyn AllocatorAccess>,
+    size: vk::DeviceSize,
+) -> Result<Buffer, AllocationError> {
+    Buffer::new_with_alignment(
+        alloc_access,
+        BufferProperties::new_default(size, vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS),
+        allocation_info_cpu_accessible(),
+        SERIALIZATION_BUFFER_ALIGNMENT,
+    )
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum AccelerationStructureError {
+    Creation(vk::Result),
+    BufferCreation(AllocationError),
+    Memory(MemoryError),
+    /// The serialized data was created by an incompatible driver or device.
+    Incompatible,
+    /// The data is smaller than the serialization header or its serialized size.
+    InvalidSerializedData {
+        size: usize,
+    },
+}
+
+impl fmt::Display for AccelerationStructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Creation(e) => write!(f, "failed to create acceleration structure: {}", e),
+            Self::BufferCreation(e) => write!(
+                f,
+                "failed to create acceleration structure serialization buffer: {}",
+                e
+            ),
+            Self::Memory(e) => write!(
+                f,
+                "failed to access acceleration structure serialization buffer: {}",
+                e
+            ),
+            Self::Incompatible => write!(
+                f,
+                "serialized acceleration structure isn't compatible with this device"
+            ),
+            Self::InvalidSerializedData { size } => write!(
+                f,
+                "invalid serialized acceleration structure data of {} bytes",
+                size
+            ),
+        }
+    }
+}
+
+impl error::Error for AccelerationStructureError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Creation(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn serialized_acceleration_structure_header() {
+    let mut data = vec![0xab_u8; 2 * vk::UUID_SIZE];
+    for value in [72_u64, 512, 1] {
+        data.extend_from_slice(&value.to_ne_bytes());
+    }
+    data.extend_from_slice(&[0; 16]);
+
+    let serialized = SerializedAccelerationStructure::from_bytes(data.clone()).unwrap();
+    assert_eq!(serialized.serialized_size(), 72);
+    assert_eq!(serialized.deserialized_size(), 512);
+    assert_eq!(serialized.handle_count(), 1);
+    assert_eq!(serialized.version_data(), &[0xab; 2 * vk::UUID_SIZE]);
+
+    assert!(SerializedAccelerationStructure::from_bytes(data[..40].to_vec()).is_err());
+    // serialized size larger than the data
+    assert!(SerializedAccelerationStructure::from_bytes(data[..64].to_vec()).is_err());
+}
@@ -0,0 +1,283 @@
+use crate::{CommandBuffer, CommandPools, DeviceOwned, Queue, Semaphore};
+use ash::vk;
+use std::{error, fmt, sync::Arc};
+
+/// Submits compute work to a dedicated compute queue so it can overlap with graphics work on
+/// another queue. Synchronization between the two uses a pair of timeline semaphores:
+/// - graphics submissions signal [`Self::graphics_semaphore`] with increasing values of your
+///   choosing, and compute submissions can wait on one of those values.
+/// - each compute submission signals [`Self::compute_semaphore`] with the value returned by
+///   [`Self::submit_compute`] which graphics submissions can wait on.
+///
+/// Each of the `frames_in_flight` slots has its own [`CommandPools`] which are reset when the
+/// slot is reused, after waiting on the host for the slot's previous submission to complete.
+///
+/// Requires Vulkan 1.2 and the `timelineSemaphore` feature. Queue family ownership transfers of
+/// resources shared between the compute and graphics queue families are up to the caller.
+pub struct AsyncComputeScheduler {
+    queue: Arc<Queue>,
+    frames: Vec<AsyncComputeFrame>,
+    frame_index: usize,
+    graphics_semaphore: Semaphore,
+    compute_semaphore: Semaphore,
+    last_compute_value: u64,
+}
+
+struct AsyncComputeFrame {
+    command_pools: CommandPools,
+    command_buffer: CommandBuffer,
+    /// Compute semaphore value signalled by the last submission recorded with this frame.
+    signal_value: u64,
+}
+
+impl AsyncComputeScheduler {
+    /// `queue` should ideally belong to a compute-only queue family (see
+    /// [`async_compute_queue_family_index`]) but any family supporting compute works.
+    pub fn new(queue: Arc<Queue>, frames_in_flight: usize) -> Result<Self, AsyncComputeError> {
+        let device = queue.device().clone();
+
+        let family_index = queue.family_index();
+        let supports_compute = device
+            .physical_device()
+            .queue_family_properties()
+            .get(family_index as usize)
+            .map(|properties| properties.queue_flags.contains(vk::QueueFlags::COMPUTE))
+            .unwrap_or(false);
+        if !supports_compute {
+            return Err(AsyncComputeError::QueueFamilyNotCompute { family_index });
+        }
+        debug_assert!(
+            device.enabled_features().features_1_2.timeline_semaphore == vk::TRUE,
+            "AsyncComputeScheduler requires the timelineSemaphore feature to be enabled"
+        );
+
+        let mut frames = Vec::with_capacity(frames_in_flight.max(1));
+        for _ in 0..frames_in_flight.max(1) {
+            let command_pools =
+                CommandPools::new(device.clone(), vk::CommandPoolCreateFlags::TRANSIENT);
+            let command_buffer = command_pools
+                .for_queue(&queue)
+                .and_then(|command_pool| {
+                    command_pool.allocate_command_buffer(vk::CommandBufferLevel::PRIMARY)
+                })
+                .map_err(AsyncComputeError::Vulkan)?;
+            frames.push(AsyncComputeFrame {
+                command_pools,
+                command_buffer,
+                signal_value: 0,
+            });
+        }
+
+        let graphics_semaphore =
+            Semaphore::new_timeline(device.clone(), 0).map_err(AsyncComputeError::Vulkan)?;
+        let compute_semaphore =
+            Semaphore::new_timeline(device, 0).map_err(AsyncComputeError::Vulkan)?;
+
+        Ok(Self {
+            queue,
+            frames,
+            frame_index: 0,
+            graphics_semaphore,
+            compute_semaphore,
+            last_compute_value: 0,
+        })
+    }
+
+    /// Records compute work with `record_commands` and submits it to the compute queue. If
+    /// `wait_graphics_value` is `Some`, the compute shader stage waits for the graphics semaphore
+    /// to reach that value.
+    ///
+    /// Returns the compute semaphore value signalled when the work completes. Pass it to
+    /// [`Self::compute_wait_info`] to make a graphics submission wait on it.
+    ///
+    /// Blocks if the frame slot being reused still has work pending.
+    pub fn submit_compute<F>(
+        &mut self,
+        record_commands: F,
+        wait_graphics_value: Option<u64>,
+    ) -> Result<u64, AsyncComputeError>
+    where
+        F: FnOnce(&CommandBuffer) -> Result<(), vk::Result>,
+    {
+        let frame = &mut self.frames[self.frame_index];
+
+        if frame.signal_value > 0 {
+            self.compute_semaphore
+                .wait_value(frame.signal_value, u64::MAX)
+                .map_err(AsyncComputeError::Vulkan)?;
+        }
+        frame
+            .command_pools
+            .reset_all(vk::CommandPoolResetFlags::empty())
+            .map_err(AsyncComputeError::Vulkan)?;
+
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        frame
+            .command_buffer
+            .begin(&begin_info)
+            .map_err(AsyncComputeError::Vulkan)?;
+        record_commands(&frame.command_buffer).map_err(AsyncComputeError::Recording)?;
+        frame
+            .command_buffer
+            .end()
+            .map_err(AsyncComputeError::Vulkan)?;
+
+        let signal_value = self.last_compute_value + 1;
+
+        let command_buffer_handles = [frame.command_buffer.handle()];
+        let signal_semaphores = [self.compute_semaphore.handle()];
+        let signal_values = [signal_value];
+        let wait_semaphores = [self.graphics_semaphore.handle()];
+        let wait_values = [wait_graphics_value.unwrap_or(0)];
+        let wait_stages = [vk::PipelineStageFlags::COMPUTE_SHADER];
+        let wait_count = if wait_graphics_value.is_some() { 1 } else { 0 };
+
+        let mut timeline_submit_info = vk::TimelineSemaphoreSubmitInfo::default()
+            .wait_semaphore_values(&wait_values[..wait_count])
+            .signal_semaphore_values(&signal_values);
+        let submit_info = vk::SubmitInfo::default()
+            .command_buffers(&command_buffer_handles)
+            .wait_semaphores(&wait_semaphores[..wait_count])
+            .wait_dst_stage_mask(&wait_stages[..wait_count])
+            .signal_semaphores(&signal_semaphores)
+            .push_next(&mut timeline_submit_info);
+
+        self.queue
+            .submit(&[submit_info], None)
+            .map_err(AsyncComputeError::Vulkan)?;
+
+        frame.signal_value = signal_value;
+        self.last_compute_value = signal_value;
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+        Ok(signal_value)
+    }
+
+    /// Semaphore handle and value for a graphics submission to wait on the compute work that
+    /// signals `compute_value`. Use with `vk::TimelineSemaphoreSubmitInfo::wait_semaphore_values`.
+    pub fn compute_wait_info(&self, compute_value: u64) -> (vk::Semaphore, u64) {
+        (self.compute_semaphore.handle(), compute_value)
+    }
+
+    /// Semaphore handle and value for a graphics submission to signal once it is done with
+    /// resources the compute work depends on. Use with
+    /// `vk::TimelineSemaphoreSubmitInfo::signal_semaphore_values`.
+    pub fn graphics_signal_info(&self, graphics_value: u64) -> (vk::Semaphore, u64) {
+        (self.graphics_semaphore.handle(), graphics_value)
+    }
+
+    /// Blocks until all submitted compute work has completed.
+    pub fn wait_idle(&self, timeout_nanoseconds: u64) -> Result<(), AsyncComputeError> {
+        self.compute_semaphore
+            .wait_value(self.last_compute_value, timeout_nanoseconds)
+            .map_err(AsyncComputeError::Vulkan)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    #[inline]
+    pub fn graphics_semaphore(&self) -> &Semaphore {
+        &self.graphics_semaphore
+    }
+
+    #[inline]
+    pub fn compute_semaphore(&self) -> &Semaphore {
+        &self.compute_semaphore
+    }
+
+    /// Compute semaphore value signalled by the most recent submission (0 if nothing has been
+    /// submitted).
+    #[inline]
+    pub fn last_compute_value(&self) -> u64 {
+        self.last_compute_value
+    }
+
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+// Helper Functions
+
+/// Index of a queue family suitable for async compute: a compute-only family if there is one,
+/// otherwise any family supporting compute.
+pub fn async_compute_queue_family_index(
+    queue_family_properties: &[vk::QueueFamilyProperties],
+) -> Option<u32> {
+    let supports_compute = |properties: &vk::QueueFamilyProperties| {
+        properties.queue_flags.contains(vk::QueueFlags::COMPUTE)
+    };
+
+    let dedicated = queue_family_properties.iter().position(|properties| {
+        supports_compute(properties) && !properties.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+    });
+    dedicated
+        .or_else(|| queue_family_properties.iter().position(supports_compute))
+        .map(|index| index as u32)
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum AsyncComputeError {
+    QueueFamilyNotCompute {
+        family_index: u32,
+    },
+    /// Returned by the `record_commands` callback.
+    Recording(vk::Result),
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for AsyncComputeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::QueueFamilyNotCompute { family_index } => write!(
+                f,
+                "queue family {} doesn't support compute operations",
+                family_index
+            ),
+            Self::Recording(e) => write!(f, "failed to record async compute commands: {}", e),
+            Self::Vulkan(e) => write!(f, "async compute vulkan call failed: {}", e),
+        }
+    }
+}
+
+impl error::Error for AsyncComputeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::QueueFamilyNotCompute { .. } => None,
+            Self::Recording(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn async_compute_queue_family_prefers_compute_only() {
+    let family = |queue_flags| vk::QueueFamilyProperties {
+        queue_flags,
+        queue_count: 1,
+        ..Default::default()
+    };
+    let graphics_compute = family(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE);
+    let compute = family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER);
+    let transfer = family(vk::QueueFlags::TRANSFER);
+
+    assert_eq!(
+        async_compute_queue_family_index(&[graphics_compute, transfer, compute]),
+        Some(2)
+    );
+    assert_eq!(
+        async_compute_queue_family_index(&[transfer, graphics_compute]),
+        Some(1)
+    );
+    assert_eq!(async_compute_queue_family_index(&[transfer]), None);
+}
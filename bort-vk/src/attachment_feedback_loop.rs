@@ -0,0 +1,181 @@
+use crate::PhysicalDevice;
+use ash::vk;
+use std::{error, fmt};
+
+/// `VK_EXT_attachment_feedback_loop_layout` (and optionally
+/// `VK_EXT_attachment_feedback_loop_dynamic_state`) support of a physical device. An attachment
+/// feedback loop allows an image to be sampled (or read as an input attachment) in a fragment
+/// shader while it's bound as a color or depth/stencil attachment of the same render pass
+/// instance, e.g. for programmable blending or post effects reading the pixel being shaded.
+///
+/// Setup:
+/// - enable the extension(s) and features with
+///   [`PhysicalDeviceFeatures::with_attachment_feedback_loop`](crate::PhysicalDeviceFeatures::with_attachment_feedback_loop)
+/// - create the image with [`ImageProperties::with_attachment_feedback_loop`](crate::ImageProperties::with_attachment_feedback_loop)
+/// - use [`feedback_loop_attachment_reference`] for the attachment and
+///   [`feedback_loop_self_dependency`] so writes are visible to later fragment shader reads
+/// - set [`feedback_loop_pipeline_flags`] on the pipeline, or add
+///   `vk::DynamicState::ATTACHMENT_FEEDBACK_LOOP_ENABLE_EXT` and call
+///   [`CommandBuffer::set_attachment_feedback_loop_enable`](crate::CommandBuffer::set_attachment_feedback_loop_enable)
+#[derive(Debug, Clone, Copy)]
+pub struct AttachmentFeedbackLoopSupport {
+    pub layout_features: vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT<'static>,
+    /// `None` if `VK_EXT_attachment_feedback_loop_dynamic_state` isn't supported.
+    pub dynamic_state_features:
+        Option<vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT<'static>>,
+}
+
+impl AttachmentFeedbackLoopSupport {
+    /// `None` if `physical_device` doesn't support `VK_EXT_attachment_feedback_loop_layout`.
+    pub fn query(physical_device: &PhysicalDevice) -> Option<Self> {
+        let instance = physical_device.instance();
+        let layout_features =
+            instance.physical_device_attachment_feedback_loop_layout_features(physical_device)?;
+        let dynamic_state_features = instance
+            .physical_device_attachment_feedback_loop_dynamic_state_features(physical_device);
+        Some(Self {
+            layout_features,
+            dynamic_state_features,
+        })
+    }
+
+    #[inline]
+    pub fn is_supported(&self) -> bool {
+        self.layout_features.attachment_feedback_loop_layout == vk::TRUE
+    }
+
+    #[inline]
+    pub fn dynamic_state_supported(&self) -> bool {
+        self.dynamic_state_features
+            .is_some_and(|features| features.attachment_feedback_loop_dynamic_state == vk::TRUE)
+    }
+
+    /// Checks that attachment feedback loops (and the dynamic state if `dynamic_state`) are
+    /// supported.
+    pub fn validate(&self, dynamic_state: bool) -> Result<(), AttachmentFeedbackLoopError> {
+        if !self.is_supported() {
+            return Err(AttachmentFeedbackLoopError::NotSupported);
+        }
+        if dynamic_state && !self.dynamic_state_supported() {
+            return Err(AttachmentFeedbackLoopError::DynamicStateNotSupported);
+        }
+        Ok(())
+    }
+}
+
+/// Reference to the attachment at `attachment_index` in the
+/// `ATTACHMENT_FEEDBACK_LOOP_OPTIMAL_EXT` layout. Use it for both the color/depth attachment
+/// reference and the input attachment reference (if read as an input attachment) of the subpass.
+pub fn feedback_loop_attachment_reference(attachment_index: u32) -> vk::AttachmentReference {
+    vk::AttachmentReference {
+        attachment: attachment_index,
+        layout: vk::ImageLayout::ATTACHMENT_FEEDBACK_LOOP_OPTIMAL_EXT,
+    }
+}
+
+/// Pipeline create flags declaring which aspects of the bound attachments are in a feedback loop.
+/// Only `COLOR`, `DEPTH` and `STENCIL` in `aspect_mask` are considered.
+pub fn feedback_loop_pipeline_flags(aspect_mask: vk::ImageAspectFlags) -> vk::PipelineCreateFlags {
+    let mut flags = vk::PipelineCreateFlags::empty();
+    if aspect_mask.contains(vk::ImageAspectFlags::COLOR) {
+        flags |= vk::PipelineCreateFlags::COLOR_ATTACHMENT_FEEDBACK_LOOP_EXT;
+    }
+    if aspect_mask.intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL) {
+        flags |= vk::PipelineCreateFlags::DEPTH_STENCIL_ATTACHMENT_FEEDBACK_LOOP_EXT;
+    }
+    flags
+}
+
+/// Subpass self-dependency making attachment writes of the `aspect_mask` aspects visible to
+/// fragment shader reads of the same attachment. Record the matching pipeline barrier (with
+/// `vk::DependencyFlags::BY_REGION | FEEDBACK_LOOP_EXT`) between draws which write and then read
+/// the attachment.
+pub fn feedback_loop_self_dependency(
+    subpass: u32,
+    aspect_mask: vk::ImageAspectFlags,
+) -> vk::SubpassDependency {
+    let mut src_stage_mask = vk::PipelineStageFlags::empty();
+    let mut src_access_mask = vk::AccessFlags::empty();
+    if aspect_mask.contains(vk::ImageAspectFlags::COLOR) {
+        src_stage_mask |= vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+        src_access_mask |= vk::AccessFlags::COLOR_ATTACHMENT_WRITE;
+    }
+    if aspect_mask.intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL) {
+        src_stage_mask |= vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+            | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+        src_access_mask |= vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE;
+    }
+
+    vk::SubpassDependency {
+        src_subpass: subpass,
+        dst_subpass: subpass,
+        src_stage_mask,
+        dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+        src_access_mask,
+        dst_access_mask: vk::AccessFlags::SHADER_READ | vk::AccessFlags::INPUT_ATTACHMENT_READ,
+        dependency_flags: vk::DependencyFlags::BY_REGION | vk::DependencyFlags::FEEDBACK_LOOP_EXT,
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum AttachmentFeedbackLoopError {
+    /// `VK_EXT_attachment_feedback_loop_layout` or the `attachmentFeedbackLoopLayout` feature
+    /// isn't supported.
+    NotSupported,
+    DynamicStateNotSupported,
+}
+
+impl fmt::Display for AttachmentFeedbackLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSupported => write!(
+                f,
+                "VK_EXT_attachment_feedback_loop_layout or the attachmentFeedbackLoopLayout \
+                feature isn't supported"
+            ),
+            Self::DynamicStateNotSupported => write!(
+                f,
+                "attachment feedback loop dynamic state requested but \
+                VK_EXT_attachment_feedback_loop_dynamic_state isn't supported"
+            ),
+        }
+    }
+}
+
+impl error::Error for AttachmentFeedbackLoopError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn attachment_feedback_loop_flags_and_support() {
+    let color_flags = feedback_loop_pipeline_flags(vk::ImageAspectFlags::COLOR);
+    assert_eq!(
+        color_flags,
+        vk::PipelineCreateFlags::COLOR_ATTACHMENT_FEEDBACK_LOOP_EXT
+    );
+    let depth_flags = feedback_loop_pipeline_flags(vk::ImageAspectFlags::DEPTH);
+    assert_eq!(
+        depth_flags,
+        vk::PipelineCreateFlags::DEPTH_STENCIL_ATTACHMENT_FEEDBACK_LOOP_EXT
+    );
+
+    let dependency = feedback_loop_self_dependency(1, vk::ImageAspectFlags::COLOR);
+    assert_eq!((dependency.src_subpass, dependency.dst_subpass), (1, 1));
+    assert!(dependency
+        .dependency_flags
+        .contains(vk::DependencyFlags::FEEDBACK_LOOP_EXT));
+    assert_eq!(
+        dependency.src_access_mask,
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    );
+
+    let support = AttachmentFeedbackLoopSupport {
+        layout_features: vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT::default()
+            .attachment_feedback_loop_layout(true),
+        dynamic_state_features: None,
+    };
+    assert!(support.validate(false).is_ok());
+    assert!(support.validate(true).is_err());
+}
@@ -0,0 +1,179 @@
+use crate::{
+    BufferAccess, Device, DeviceOwned, ImageAccess, ImageDimensions, ImageViewAccess,
+    ImageViewProperties,
+};
+use ash::vk::{self, Handle};
+use std::sync::Arc;
+
+// ~~ Borrowed Image ~~
+
+/// A `VkImage` owned by external code (e.g. another library or existing ash code) that can be
+/// used wherever bort expects an [`ImageAccess`] e.g. barriers and copies. Nothing is destroyed on
+/// drop. To take ownership instead see [`Image::from_raw`](crate::Image::from_raw).
+pub struct BorrowedImage {
+    handle: vk::Image,
+    dimensions: ImageDimensions,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl BorrowedImage {
+    /// # Safety
+    /// `handle` must be a valid image of `device` with `dimensions` and outlive `self`.
+    pub unsafe fn new(device: Arc<Device>, handle: vk::Image, dimensions: ImageDimensions) -> Self {
+        Self {
+            handle,
+            dimensions,
+            device,
+        }
+    }
+}
+
+impl ImageAccess for BorrowedImage {
+    #[inline]
+    fn handle(&self) -> vk::Image {
+        self.handle
+    }
+
+    #[inline]
+    fn dimensions(&self) -> ImageDimensions {
+        self.dimensions
+    }
+}
+
+impl DeviceOwned for BorrowedImage {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+// ~~ Borrowed Image View ~~
+
+/// A `VkImageView` owned by external code that can be used wherever bort expects an
+/// [`ImageViewAccess`] e.g. descriptor writes and framebuffers. Nothing is destroyed on drop.
+pub struct BorrowedImageView {
+    handle: vk::ImageView,
+    properties: ImageViewProperties,
+
+    // dependencies
+    image: Arc<dyn ImageAccess>,
+}
+
+impl BorrowedImageView {
+    /// `image` can be a [`BorrowedImage`] if the image is also owned by external code.
+    ///
+    /// # Safety
+    /// `handle` must be a valid view of `image` created with `properties` and outlive `self`.
+    pub unsafe fn new(
+        image: Arc<dyn ImageAccess>,
+        handle: vk::ImageView,
+        properties: ImageViewProperties,
+    ) -> Self {
+        Self {
+            handle,
+            properties,
+            image,
+        }
+    }
+
+    /// Descriptor info for e.g. a `SAMPLED_IMAGE` or `STORAGE_IMAGE` write.
+    pub fn descriptor_image_info(&self, image_layout: vk::ImageLayout) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: self.handle,
+            image_layout,
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn image(&self) -> &Arc<dyn ImageAccess> {
+        &self.image
+    }
+}
+
+impl ImageViewAccess for BorrowedImageView {
+    #[inline]
+    fn handle(&self) -> vk::ImageView {
+        self.handle
+    }
+
+    #[inline]
+    fn image_access(&self) -> Arc<dyn ImageAccess> {
+        self.image.clone()
+    }
+
+    #[inline]
+    fn properties(&self) -> &ImageViewProperties {
+        &self.properties
+    }
+}
+
+impl DeviceOwned for BorrowedImageView {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.image.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+// ~~ Borrowed Buffer ~~
+
+/// A `VkBuffer` owned by external code that can be used wherever bort expects a
+/// [`BufferAccess`] e.g. descriptor writes and barriers. Nothing is destroyed on drop. To take
+/// ownership instead see [`Buffer::from_raw`](crate::Buffer::from_raw).
+pub struct BorrowedBuffer {
+    handle: vk::Buffer,
+    size: vk::DeviceSize,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl BorrowedBuffer {
+    /// # Safety
+    /// `handle` must be a valid buffer of `device` at least `size` bytes large and outlive `self`.
+    pub unsafe fn new(device: Arc<Device>, handle: vk::Buffer, size: vk::DeviceSize) -> Self {
+        Self {
+            handle,
+            size,
+            device,
+        }
+    }
+}
+
+impl BufferAccess for BorrowedBuffer {
+    #[inline]
+    fn handle(&self) -> vk::Buffer {
+        self.handle
+    }
+
+    #[inline]
+    fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+}
+
+impl DeviceOwned for BorrowedBuffer {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
@@ -1,9 +1,15 @@
-use crate::{AllocationAccess, AllocatorAccess, Device, DeviceOwned, MemoryAllocation};
+use crate::{
+    allocation_info_cpu_accessible_mapped, allocation_info_device_local, can_update_buffer,
+    device_memory_requirements_available, new_staging_buffer, record_submit_and_wait,
+    transfer_write_memory_barrier, AllocationAccess, AllocationError, AllocatorAccess, ApiVersion,
+    BufferAccess, CommandPool, CountedObjectType, Device, DeviceOwned, HandleOwnership,
+    MemoryAllocation, Queue, ResourceInitError,
+};
 use ash::{
-    prelude::VkResult,
+    khr,
     vk::{self, Handle},
 };
-use bort_vma::AllocationCreateInfo;
+use bort_vma::{ffi, AllocationCreateInfo};
 use std::sync::Arc;
 
 /// Contains a [VkBuffer](https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkBuffer.html)
@@ -12,6 +18,8 @@ pub struct Buffer {
     handle: vk::Buffer,
     properties: BufferProperties,
     memory_allocation: MemoryAllocation,
+    counted: bool,
+    ownership: HandleOwnership,
 }
 
 impl Buffer {
@@ -19,7 +27,7 @@ impl Buffer {
         alloc_access: Arc<dyn AllocatorAccess>,
         properties: BufferProperties,
         allocation_info: AllocationCreateInfo,
-    ) -> VkResult<Self> {
+    ) -> Result<Self, AllocationError> {
         let create_info = properties.create_info();
 
         let (handle, memory_allocation_handle) = unsafe {
@@ -28,6 +36,212 @@ impl Buffer {
                 .vma_create_buffer(&create_info, &allocation_info)
         }?;
 
+        let counted = alloc_access
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Buffer);
+        let memory_allocation =
+            MemoryAllocation::from_vma_allocation(memory_allocation_handle, alloc_access);
+
+        Ok(Self {
+            handle,
+            properties,
+            memory_allocation,
+            counted,
+            ownership: HandleOwnership::Owned,
+        })
+    }
+
+    /// Device local vertex buffer with `VERTEX_BUFFER | TRANSFER_DST` usage. Fill it with a
+    /// transfer command or create it with [`Self::new_with_data`] instead.
+    pub fn new_vertex(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        size: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let properties = BufferProperties::new_default(
+            size,
+            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        Self::new(alloc_access, properties, allocation_info_device_local())
+    }
+
+    /// Device local index buffer with `INDEX_BUFFER | TRANSFER_DST` usage.
+    pub fn new_index(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        size: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let properties = BufferProperties::new_default(
+            size,
+            vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        Self::new(alloc_access, properties, allocation_info_device_local())
+    }
+
+    /// Host visible, persistently mapped uniform buffer with `UNIFORM_BUFFER` usage for data
+    /// written by the cpu every frame.
+    pub fn new_uniform(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        size: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let properties = BufferProperties::new_default(size, vk::BufferUsageFlags::UNIFORM_BUFFER);
+        Self::new(
+            alloc_access,
+            properties,
+            allocation_info_cpu_accessible_mapped(),
+        )
+    }
+
+    /// Device local storage buffer with `STORAGE_BUFFER | TRANSFER_SRC | TRANSFER_DST` usage so it
+    /// can be uploaded to and read back.
+    pub fn new_storage(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        size: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let properties = BufferProperties::new_default(
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        Self::new(alloc_access, properties, allocation_info_device_local())
+    }
+
+    /// Host visible, persistently mapped staging buffer with `TRANSFER_SRC` usage.
+    pub fn new_staging(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        size: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let properties = BufferProperties::new_default(size, vk::BufferUsageFlags::TRANSFER_SRC);
+        Self::new(
+            alloc_access,
+            properties,
+            allocation_info_cpu_accessible_mapped(),
+        )
+    }
+
+    /// Creates a buffer and fills it with zeros using `vkCmdFillBuffer`. Blocks until the fill
+    /// command has completed on `queue`.
+    ///
+    /// `vk::BufferUsageFlags::TRANSFER_DST` is added to the usage flags in `properties`.
+    pub fn new_zeroed(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        mut properties: BufferProperties,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+    ) -> Result<Self, ResourceInitError> {
+        properties.usage |= vk::BufferUsageFlags::TRANSFER_DST;
+        let buffer = Self::new(alloc_access, properties, allocation_info)
+            .map_err(ResourceInitError::Creation)?;
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            command_buffer.fill_buffer(&buffer, 0, vk::WHOLE_SIZE, 0);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[transfer_write_memory_barrier()],
+                &[],
+                &[],
+            );
+        })?;
+
+        Ok(buffer)
+    }
+
+    /// Creates a buffer and writes `data` to the start of it. If the allocated memory is host
+    /// visible the data is written directly, otherwise it is uploaded with `vkCmdUpdateBuffer` (if
+    /// small enough, see [`can_update_buffer`]) or via a temporary staging buffer and this blocks
+    /// until the upload has completed on `queue`.
+    ///
+    /// `vk::BufferUsageFlags::TRANSFER_DST` is added to the usage flags in `properties`.
+    pub fn new_with_data(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        mut properties: BufferProperties,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        data: &[u8],
+    ) -> Result<Self, ResourceInitError> {
+        if data.len() as vk::DeviceSize > properties.size {
+            return Err(ResourceInitError::DataSizeTooBig {
+                data_size: data.len(),
+                resource_size: properties.size,
+            });
+        }
+
+        properties.usage |= vk::BufferUsageFlags::TRANSFER_DST;
+        let mut buffer = Self::new(alloc_access.clone(), properties, allocation_info)
+            .map_err(ResourceInitError::Creation)?;
+
+        if data.is_empty() {
+            return Ok(buffer);
+        }
+
+        let host_visible = buffer
+            .memory_allocation
+            .memory_property_flags()
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+        if host_visible {
+            buffer
+                .write_bytes(data, 0)
+                .map_err(ResourceInitError::HostWrite)?;
+            return Ok(buffer);
+        }
+
+        // small uploads are recorded inline to avoid allocating a staging buffer
+        let staging_buffer = if can_update_buffer(0, data.len() as vk::DeviceSize) {
+            None
+        } else {
+            Some(new_staging_buffer(alloc_access, data)?)
+        };
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            match &staging_buffer {
+                Some(staging_buffer) => {
+                    let copy_region = vk::BufferCopy {
+                        src_offset: 0,
+                        dst_offset: 0,
+                        size: data.len() as vk::DeviceSize,
+                    };
+                    command_buffer.copy_buffer(staging_buffer, &buffer, &[copy_region]);
+                }
+                None => command_buffer.update_buffer(&buffer, 0, data),
+            }
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[transfer_write_memory_barrier()],
+                &[],
+                &[],
+            );
+        })?;
+
+        Ok(buffer)
+    }
+
+    /// Same as [`Self::new`] but the buffer is placed at a multiple of `min_alignment` within its
+    /// memory block, in addition to the alignment required by the buffer memory requirements e.g.
+    /// for shader binding tables which require `shaderGroupBaseAlignment`.
+    pub fn new_with_alignment(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        properties: BufferProperties,
+        allocation_info: AllocationCreateInfo,
+        min_alignment: vk::DeviceSize,
+    ) -> Result<Self, AllocationError> {
+        let create_info = properties.create_info();
+
+        let (handle, memory_allocation_handle) = unsafe {
+            alloc_access
+                .memory_allocator()
+                .vma_create_buffer_with_alignment(&create_info, &allocation_info, min_alignment)
+        }?;
+
+        let counted = alloc_access
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Buffer);
         let memory_allocation =
             MemoryAllocation::from_vma_allocation(memory_allocation_handle, alloc_access);
 
@@ -35,16 +249,37 @@ impl Buffer {
             handle,
             properties,
             memory_allocation,
+            counted,
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Same as [`Self::new`] but the memory is attributed to `tag` (e.g. "meshes") in
+    /// [`MemoryAllocator::tagged_memory_report`](crate::MemoryAllocator::tagged_memory_report).
+    /// The tag is stored in the allocation user data.
+    pub fn new_tagged(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        properties: BufferProperties,
+        allocation_info: AllocationCreateInfo,
+        tag: &'static str,
+    ) -> Result<Self, AllocationError> {
+        let buffer = Self::new(alloc_access, properties, allocation_info)?;
+        unsafe {
+            buffer
+                .allocator_access()
+                .memory_allocator()
+                .set_allocation_tag(buffer.memory_allocation.handle(), tag);
+        }
+        Ok(buffer)
+    }
+
     /// # Safety
     /// Make sure your `p_next` chain contains valid pointers.
     pub unsafe fn new_from_create_info(
         alloc_access: Arc<dyn AllocatorAccess>,
         buffer_create_info: vk::BufferCreateInfo,
         allocation_info: AllocationCreateInfo,
-    ) -> VkResult<Self> {
+    ) -> Result<Self, AllocationError> {
         let properties = BufferProperties::from_create_info(&buffer_create_info);
 
         let (handle, memory_allocation_handle) = unsafe {
@@ -53,6 +288,10 @@ impl Buffer {
                 .vma_create_buffer(&buffer_create_info, &allocation_info)
         }?;
 
+        let counted = alloc_access
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Buffer);
         let memory_allocation =
             MemoryAllocation::from_vma_allocation(memory_allocation_handle, alloc_access);
 
@@ -60,9 +299,68 @@ impl Buffer {
             handle,
             properties,
             memory_allocation,
+            counted,
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Wraps a `VkBuffer` created elsewhere e.g. by existing ash code or other middleware.
+    ///
+    /// `allocation` is the vma allocation bound to `handle` (made with the allocator of
+    /// `alloc_access`), or `None` if the memory is managed elsewhere. With
+    /// [`HandleOwnership::Owned`] the buffer (and `allocation`) are destroyed on drop.
+    ///
+    /// # Safety
+    /// - `handle` must be a valid `VkBuffer` created with the device of `alloc_access` and described by
+    ///   `properties`.
+    /// - if `allocation` is `None`, the memory access functions of [`AllocationAccess`] and
+    ///   [`Self::memory_allocation`] must not be used.
+    /// - with [`HandleOwnership::Borrowed`] the buffer must outlive `self`.
+    pub unsafe fn from_raw(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        handle: vk::Buffer,
+        properties: BufferProperties,
+        allocation: Option<ffi::VmaAllocation>,
+        ownership: HandleOwnership,
+    ) -> Self {
+        let counted = ownership.is_owned()
+            && alloc_access
+                .device()
+                .object_counters()
+                .record_created(CountedObjectType::Buffer);
+        let memory_allocation = match allocation {
+            Some(allocation_handle) => {
+                MemoryAllocation::from_vma_allocation(allocation_handle, alloc_access)
+            }
+            None => MemoryAllocation::null(alloc_access),
+        };
+
+        Self {
+            handle,
+            properties,
+            memory_allocation,
+            counted,
+            ownership,
+        }
+    }
+
+    /// Requires `SHADER_DEVICE_ADDRESS` usage, the `bufferDeviceAddress` feature and an allocator
+    /// created with `BUFFER_DEVICE_ADDRESS`.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetBufferDeviceAddress.html>
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        debug_assert!(self
+            .properties
+            .usage
+            .contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS));
+        let address_info = vk::BufferDeviceAddressInfo::default().buffer(self.handle);
+        unsafe {
+            self.device()
+                .inner()
+                .get_buffer_device_address(&address_info)
+        }
+    }
+
     // Getters
 
     #[inline]
@@ -84,6 +382,23 @@ impl Buffer {
     pub fn memory_allocation(&self) -> &MemoryAllocation {
         &self.memory_allocation
     }
+
+    #[inline]
+    pub fn ownership(&self) -> HandleOwnership {
+        self.ownership
+    }
+}
+
+impl BufferAccess for Buffer {
+    #[inline]
+    fn handle(&self) -> vk::Buffer {
+        self.handle
+    }
+
+    #[inline]
+    fn size(&self) -> vk::DeviceSize {
+        self.properties.size
+    }
 }
 
 impl AllocationAccess for Buffer {
@@ -106,12 +421,19 @@ impl DeviceOwned for Buffer {
 
 impl Drop for Buffer {
     fn drop(&mut self) {
+        if !self.ownership.is_owned() {
+            return;
+        }
         unsafe {
             self.allocator_access()
                 .clone()
                 .memory_allocator()
                 .vma_destroy_buffer(self.handle, self.memory_allocation.handle());
         }
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::Buffer, self.counted);
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
@@ -150,6 +472,15 @@ impl BufferProperties {
         }
     }
 
+    /// Properties with the `PROTECTED` flag. Allocate with
+    /// [`allocation_info_protected`](crate::allocation_info_protected).
+    pub fn new_protected(size: vk::DeviceSize, usage: vk::BufferUsageFlags) -> Self {
+        Self {
+            flags: vk::BufferCreateFlags::PROTECTED,
+            ..Self::new_default(size, usage)
+        }
+    }
+
     pub fn write_create_info<'a>(
         &'a self,
         create_info: vk::BufferCreateInfo<'a>,
@@ -162,10 +493,44 @@ impl BufferProperties {
             .queue_family_indices(&self.queue_family_indices)
     }
 
-    pub fn create_info(&self) -> vk::BufferCreateInfo {
+    pub fn create_info(&self) -> vk::BufferCreateInfo<'_> {
         self.write_create_info(vk::BufferCreateInfo::default())
     }
 
+    /// Memory requirements of a buffer created with these properties, queried without creating
+    /// one, e.g. to check a streaming upload against
+    /// [`MemoryAllocator::fits_in_budget`](crate::MemoryAllocator::fits_in_budget) first.
+    /// Returns `None` unless vulkan 1.3 or `VK_KHR_maintenance4` is available (see
+    /// [`device_memory_requirements_available`]).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetDeviceBufferMemoryRequirements.html>
+    pub fn memory_requirements(&self, device: &Device) -> Option<vk::MemoryRequirements> {
+        if !device_memory_requirements_available(device) {
+            return None;
+        }
+        let create_info = self.create_info();
+        let requirements_info =
+            vk::DeviceBufferMemoryRequirements::default().create_info(&create_info);
+        let mut memory_requirements = vk::MemoryRequirements2::default();
+        if device.effective_api_version() >= ApiVersion::V1_3 {
+            unsafe {
+                device.inner().get_device_buffer_memory_requirements(
+                    &requirements_info,
+                    &mut memory_requirements,
+                )
+            }
+        } else {
+            let maintenance_4_fns = device.extension_loader::<khr::maintenance4::Device>();
+            unsafe {
+                maintenance_4_fns.get_device_buffer_memory_requirements(
+                    &requirements_info,
+                    &mut memory_requirements,
+                )
+            }
+        }
+        Some(memory_requirements.memory_requirements)
+    }
+
     pub fn from_create_info(value: &vk::BufferCreateInfo) -> Self {
         let mut queue_family_indices = Vec::<u32>::new();
         for i in 0..value.queue_family_index_count {
@@ -182,3 +547,9 @@ impl BufferProperties {
         }
     }
 }
+
+impl From<&vk::BufferCreateInfo<'_>> for BufferProperties {
+    fn from(value: &vk::BufferCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::DeviceOwned;
+use ash::vk;
+
+// ~~ Buffer Access ~~
+
+/// Unifies different types of buffers e.g. [`Buffer`](crate::Buffer) and
+/// [`BorrowedBuffer`](crate::BorrowedBuffer).
+pub trait BufferAccess: DeviceOwned + Send + Sync {
+    fn handle(&self) -> vk::Buffer;
+    fn size(&self) -> vk::DeviceSize;
+
+    /// Descriptor info covering the whole buffer.
+    fn whole_descriptor_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.handle(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// Memory barrier covering the whole buffer without a queue family ownership transfer.
+    fn whole_memory_barrier(
+        &self,
+        src_access_mask: vk::AccessFlags,
+        dst_access_mask: vk::AccessFlags,
+    ) -> vk::BufferMemoryBarrier<'static> {
+        vk::BufferMemoryBarrier::default()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.handle())
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+    }
+}
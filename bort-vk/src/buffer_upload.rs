@@ -0,0 +1,177 @@
+use crate::{
+    new_staging_buffer, AllocatorAccess, Buffer, CommandBuffer, DeletionQueue, ResourceInitError,
+};
+use ash::vk;
+use std::sync::Arc;
+
+/// Max data size for `vkCmdUpdateBuffer`.
+pub const UPDATE_BUFFER_MAX_SIZE: vk::DeviceSize = 65536;
+
+/// Whether a write of `size` bytes at `dst_offset` can be done with `vkCmdUpdateBuffer` i.e.
+/// both are multiples of 4 and `size` is non-zero and at most [`UPDATE_BUFFER_MAX_SIZE`].
+pub fn can_update_buffer(dst_offset: vk::DeviceSize, size: vk::DeviceSize) -> bool {
+    size > 0
+        && size <= UPDATE_BUFFER_MAX_SIZE
+        && dst_offset.is_multiple_of(4)
+        && size.is_multiple_of(4)
+}
+
+/// How a [`BufferUploader`] wrote data to a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUploadPath {
+    /// The data was recorded inline in the command buffer with `vkCmdUpdateBuffer`.
+    UpdateBuffer,
+    /// The data was written to a temporary staging buffer and copied with `vkCmdCopyBuffer`.
+    Staging,
+}
+
+impl BufferUploadPath {
+    pub fn for_write(dst_offset: vk::DeviceSize, size: vk::DeviceSize) -> Self {
+        if can_update_buffer(dst_offset, size) {
+            Self::UpdateBuffer
+        } else {
+            Self::Staging
+        }
+    }
+}
+
+/// Counts of the upload paths taken by a [`BufferUploader`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferUploadMetrics {
+    pub update_buffer_count: u32,
+    pub update_buffer_bytes: vk::DeviceSize,
+    pub staging_count: u32,
+    pub staging_bytes: vk::DeviceSize,
+}
+
+impl BufferUploadMetrics {
+    fn record(&mut self, path: BufferUploadPath, size: vk::DeviceSize) {
+        match path {
+            BufferUploadPath::UpdateBuffer => {
+                self.update_buffer_count += 1;
+                self.update_buffer_bytes += size;
+            }
+            BufferUploadPath::Staging => {
+                self.staging_count += 1;
+                self.staging_bytes += size;
+            }
+        }
+    }
+}
+
+/// Records writes to device local buffers. Small, 4 byte aligned writes (e.g. per-frame uniform
+/// updates) are recorded with `vkCmdUpdateBuffer` which avoids allocating a staging buffer.
+/// Larger writes go through a staging buffer which is pushed to a [`DeletionQueue`] to be freed
+/// once the frame has completed.
+///
+/// No barriers are recorded. The writes are transfer operations (`TRANSFER` stage,
+/// `TRANSFER_WRITE` access) which must be synchronized with subsequent reads by the caller.
+pub struct BufferUploader {
+    alloc_access: Arc<dyn AllocatorAccess>,
+    metrics: BufferUploadMetrics,
+}
+
+impl BufferUploader {
+    pub fn new(alloc_access: Arc<dyn AllocatorAccess>) -> Self {
+        Self {
+            alloc_access,
+            metrics: BufferUploadMetrics::default(),
+        }
+    }
+
+    /// Records a write of `data` to `dst_buffer` at `dst_offset`. `dst_buffer` needs
+    /// `TRANSFER_DST` usage. Returns the path taken.
+    pub fn record_upload(
+        &mut self,
+        command_buffer: &CommandBuffer,
+        dst_buffer: &Buffer,
+        dst_offset: vk::DeviceSize,
+        data: &[u8],
+        deletion_queue: &mut DeletionQueue,
+    ) -> Result<BufferUploadPath, ResourceInitError> {
+        let size = data.len() as vk::DeviceSize;
+        if dst_offset + size > dst_buffer.properties().size {
+            return Err(ResourceInitError::DataSizeTooBig {
+                data_size: data.len(),
+                resource_size: dst_buffer.properties().size.saturating_sub(dst_offset),
+            });
+        }
+        debug_assert!(dst_buffer
+            .properties()
+            .usage
+            .contains(vk::BufferUsageFlags::TRANSFER_DST));
+
+        let path = BufferUploadPath::for_write(dst_offset, size);
+        match path {
+            BufferUploadPath::UpdateBuffer => {
+                command_buffer.update_buffer(dst_buffer, dst_offset, data);
+            }
+            BufferUploadPath::Staging => {
+                if data.is_empty() {
+                    return Ok(path);
+                }
+                let staging_buffer = new_staging_buffer(self.alloc_access.clone(), data)?;
+                let copy_region = vk::BufferCopy {
+                    src_offset: 0,
+                    dst_offset,
+                    size,
+                };
+                command_buffer.copy_buffer(&staging_buffer, dst_buffer, &[copy_region]);
+                deletion_queue.push(staging_buffer);
+            }
+        }
+
+        self.metrics.record(path, size);
+        Ok(path)
+    }
+
+    /// Resets the counts returned by [`Self::metrics`] e.g. at the start of each frame.
+    pub fn reset_metrics(&mut self) {
+        self.metrics = BufferUploadMetrics::default();
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn metrics(&self) -> BufferUploadMetrics {
+        self.metrics
+    }
+
+    #[inline]
+    pub fn allocator_access(&self) -> &Arc<dyn AllocatorAccess> {
+        &self.alloc_access
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn buffer_upload_path_selection() {
+    assert_eq!(
+        BufferUploadPath::for_write(0, 256),
+        BufferUploadPath::UpdateBuffer
+    );
+    assert_eq!(
+        BufferUploadPath::for_write(64, UPDATE_BUFFER_MAX_SIZE),
+        BufferUploadPath::UpdateBuffer
+    );
+    assert_eq!(
+        BufferUploadPath::for_write(0, UPDATE_BUFFER_MAX_SIZE + 4),
+        BufferUploadPath::Staging
+    );
+    assert_eq!(
+        BufferUploadPath::for_write(2, 16),
+        BufferUploadPath::Staging
+    );
+    assert_eq!(
+        BufferUploadPath::for_write(0, 18),
+        BufferUploadPath::Staging
+    );
+    assert_eq!(BufferUploadPath::for_write(0, 0), BufferUploadPath::Staging);
+
+    let mut metrics = BufferUploadMetrics::default();
+    metrics.record(BufferUploadPath::UpdateBuffer, 64);
+    metrics.record(BufferUploadPath::Staging, 1 << 20);
+    assert_eq!(metrics.update_buffer_count, 1);
+    assert_eq!(metrics.staging_bytes, 1 << 20);
+}
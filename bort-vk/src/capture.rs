@@ -0,0 +1,161 @@
+use crate::{Device, Queue};
+use ash::{ext, vk};
+#[cfg(feature = "renderdoc")]
+use std::{ffi::c_void, sync::Mutex};
+use std::{ffi::CStr, sync::Arc};
+
+const CAPTURE_LABEL_NAME: &CStr = c"bort frame capture";
+const TRIGGER_LABEL_NAME: &CStr = c"bort capture trigger";
+
+/// Programmatic frame capture triggers e.g. so that automated tests or a hotkey can grab a
+/// capture at an interesting moment.
+///
+/// With the `renderdoc` feature enabled and the application launched from (or injected by)
+/// RenderDoc, captures are made with the RenderDoc in-application api. Otherwise, if
+/// `VK_EXT_debug_utils` is enabled on the instance, queue debug labels are inserted instead so
+/// that the region can be found in tools like Nsight Graphics which capture via their own
+/// triggers. Otherwise the calls do nothing.
+pub struct FrameCapture {
+    device: Arc<Device>,
+    #[cfg(feature = "renderdoc")]
+    renderdoc: Option<Mutex<renderdoc::RenderDoc<renderdoc::V141>>>,
+    debug_utils_enabled: bool,
+}
+
+impl FrameCapture {
+    pub fn new(device: Arc<Device>) -> Self {
+        let debug_utils_enabled = device
+            .instance()
+            .enabled_extensions()
+            .iter()
+            .any(|name| name.as_c_str() == ext::debug_utils::NAME);
+
+        Self {
+            device,
+            #[cfg(feature = "renderdoc")]
+            renderdoc: load_renderdoc(),
+            debug_utils_enabled,
+        }
+    }
+
+    /// Whether captures go through the RenderDoc in-application api.
+    pub fn is_renderdoc_attached(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        return self.renderdoc.is_some();
+        #[cfg(not(feature = "renderdoc"))]
+        return false;
+    }
+
+    /// Starts capturing all work submitted to the device until [`Self::end_frame_capture`].
+    pub fn start_frame_capture(&self, queue: &Queue) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            let device_pointer = self.renderdoc_device_pointer();
+            lock_renderdoc(renderdoc).start_frame_capture(device_pointer, std::ptr::null());
+            return;
+        }
+
+        if self.debug_utils_enabled {
+            let label = vk::DebugUtilsLabelEXT::default().label_name(CAPTURE_LABEL_NAME);
+            // queue label commands require external synchronization of the queue
+            let _external_sync = queue.lock_external_sync();
+            unsafe {
+                self.debug_utils_loader()
+                    .queue_begin_debug_utils_label(queue.handle(), &label)
+            };
+        }
+    }
+
+    pub fn end_frame_capture(&self, queue: &Queue) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            let device_pointer = self.renderdoc_device_pointer();
+            lock_renderdoc(renderdoc).end_frame_capture(device_pointer, std::ptr::null());
+            return;
+        }
+
+        if self.debug_utils_enabled {
+            // queue label commands require external synchronization of the queue
+            let _external_sync = queue.lock_external_sync();
+            unsafe {
+                self.debug_utils_loader()
+                    .queue_end_debug_utils_label(queue.handle())
+            };
+        }
+    }
+
+    /// Captures the next presented frame.
+    pub fn trigger_capture(&self, queue: &Queue) {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            lock_renderdoc(renderdoc).trigger_capture();
+            return;
+        }
+
+        if self.debug_utils_enabled {
+            let label = vk::DebugUtilsLabelEXT::default().label_name(TRIGGER_LABEL_NAME);
+            // queue label commands require external synchronization of the queue
+            let _external_sync = queue.lock_external_sync();
+            unsafe {
+                self.debug_utils_loader()
+                    .queue_insert_debug_utils_label(queue.handle(), &label)
+            };
+        }
+    }
+
+    /// Whether a RenderDoc capture is in progress. Always `false` without RenderDoc.
+    pub fn is_frame_capturing(&self) -> bool {
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &self.renderdoc {
+            return lock_renderdoc(renderdoc).is_frame_capturing();
+        }
+        false
+    }
+
+    fn debug_utils_loader(&self) -> Arc<ext::debug_utils::Device> {
+        self.device.extension_loader::<ext::debug_utils::Device>()
+    }
+
+    /// Equivalent of `RENDERDOC_DEVICEPOINTER_FROM_VKINSTANCE`: the dispatch table pointer of the
+    /// instance.
+    #[cfg(feature = "renderdoc")]
+    fn renderdoc_device_pointer(&self) -> *const c_void {
+        use ash::vk::Handle;
+        let instance_handle =
+            self.device.instance().inner().handle().as_raw() as *const *const c_void;
+        unsafe { *instance_handle }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+// Helper Functions
+
+/// Only succeeds if RenderDoc is already loaded into the process.
+#[cfg(feature = "renderdoc")]
+fn load_renderdoc() -> Option<Mutex<renderdoc::RenderDoc<renderdoc::V141>>> {
+    match renderdoc::RenderDoc::new() {
+        Ok(renderdoc) => {
+            log::info!("renderdoc in-application api loaded");
+            Some(Mutex::new(renderdoc))
+        }
+        Err(e) => {
+            log::debug!("renderdoc not attached: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(feature = "renderdoc")]
+fn lock_renderdoc(
+    renderdoc: &Mutex<renderdoc::RenderDoc<renderdoc::V141>>,
+) -> std::sync::MutexGuard<'_, renderdoc::RenderDoc<renderdoc::V141>> {
+    renderdoc
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -1,16 +1,29 @@
 use crate::{
-    Buffer, CommandPool, DescriptorSet, Device, DeviceOwned, ImageAccess, PipelineAccess,
-    PipelineLayout,
+    validate_blit_format_features, validate_buffer_image_copy, validate_image_blit,
+    AccelerationStructure, ApiVersion, Buffer, BufferAccess, CommandPool, CopyValidationError,
+    CountedObjectType, DescriptorSet, DescriptorSetLayout, DescriptorTemplateData,
+    DescriptorUpdateTemplate, Device, DeviceOwned, Image, ImageAccess, ImageProperties,
+    PipelineAccess, PipelineLayout, QueryPool, QueryPoolError, RenderPass,
+    ShaderBindingTableRegions, Subpass,
 };
 use ash::{
+    amd, ext, khr, nv,
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::{error::Error, sync::Arc};
+use std::{
+    error::Error,
+    ffi::c_void,
+    ops::Range,
+    sync::{Arc, Mutex, PoisonError},
+};
 
 pub struct CommandBuffer {
     handle: vk::CommandBuffer,
     level: vk::CommandBufferLevel,
+    /// Tracks the render pass instance being recorded for command validation.
+    render_pass_scope: Mutex<Option<RenderPassScope>>,
+    counted: bool,
 
     // dependencies
     command_pool: Arc<CommandPool>,
@@ -29,9 +42,15 @@ impl CommandBuffer {
         level: vk::CommandBufferLevel,
         command_pool: Arc<CommandPool>,
     ) -> Self {
+        let counted = command_pool
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::CommandBuffer);
         Self {
             handle,
             level,
+            render_pass_scope: Mutex::new(None),
+            counted,
             command_pool,
         }
     }
@@ -41,6 +60,9 @@ impl CommandBuffer {
     ///
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetCommandBuffer.html>
     pub fn reset(&self, reset_flags: vk::CommandBufferResetFlags) -> VkResult<()> {
+        self.command_pool
+            .debug_assert_owner_thread("reset a command buffer");
+        self.set_render_pass_scope(None);
         unsafe {
             self.device()
                 .inner()
@@ -58,6 +80,18 @@ impl CommandBuffer {
         self.level
     }
 
+    /// `minImageTransferGranularity` of the queue family this command buffer's pool was created
+    /// for.
+    pub fn min_image_transfer_granularity(&self) -> vk::Extent3D {
+        let queue_family_index = self.command_pool.properties().queue_family_index;
+        self.device()
+            .physical_device()
+            .queue_family_properties()
+            .get(queue_family_index as usize)
+            .map(|properties| properties.min_image_transfer_granularity)
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn command_pool(&self) -> &Arc<CommandPool> {
         &self.command_pool
@@ -67,6 +101,23 @@ impl CommandBuffer {
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkBeginCommandBuffer.html>
     pub fn begin(&self, begin_info: &vk::CommandBufferBeginInfo) -> VkResult<()> {
+        self.command_pool
+            .debug_assert_owner_thread("begin recording a command buffer");
+        let continues_render_pass = begin_info
+            .flags
+            .contains(vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE);
+        let render_pass_scope = if continues_render_pass && !begin_info.p_inheritance_info.is_null()
+        {
+            let inheritance_info = unsafe { *begin_info.p_inheritance_info };
+            Some(RenderPassScope {
+                subpass_index: inheritance_info.subpass as usize,
+                render_pass: None,
+            })
+        } else {
+            None
+        };
+        self.set_render_pass_scope(render_pass_scope);
+
         unsafe {
             self.device()
                 .inner()
@@ -74,8 +125,38 @@ impl CommandBuffer {
         }
     }
 
+    /// Begins recording a secondary command buffer which will be executed inside a dynamic
+    /// rendering instance (see [`Self::begin_rendering`]) begun with
+    /// `vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS`. `RENDER_PASS_CONTINUE` is added to
+    /// `usage_flags`. `inheritance` must match the attachments of that rendering instance. This
+    /// allows recording draws on multiple threads without a render pass object.
+    ///
+    /// Requires the `dynamicRendering` feature.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkCommandBufferInheritanceRenderingInfo.html>
+    pub fn begin_secondary_rendering(
+        &self,
+        usage_flags: vk::CommandBufferUsageFlags,
+        inheritance: &RenderingInheritance,
+    ) -> VkResult<()> {
+        debug_assert_eq!(
+            self.level,
+            vk::CommandBufferLevel::SECONDARY,
+            "only secondary command buffers can inherit dynamic rendering state"
+        );
+        let mut inheritance_rendering_info = inheritance.inheritance_rendering_info();
+        let inheritance_info =
+            vk::CommandBufferInheritanceInfo::default().push_next(&mut inheritance_rendering_info);
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(usage_flags | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
+            .inheritance_info(&inheritance_info);
+        self.begin(&begin_info)
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkEndCommandBuffer.html>
     pub fn end(&self) -> VkResult<()> {
+        self.command_pool
+            .debug_assert_owner_thread("end recording a command buffer");
         unsafe { self.device().inner().end_command_buffer(self.handle) }
     }
 
@@ -85,15 +166,39 @@ impl CommandBuffer {
         begin_info: &vk::RenderPassBeginInfo,
         subpass_contents: vk::SubpassContents,
     ) {
-        unsafe {
-            self.device()
-                .inner()
-                .cmd_begin_render_pass(self.handle, begin_info, subpass_contents)
-        }
+        self.set_render_pass_scope(Some(RenderPassScope {
+            subpass_index: 0,
+            render_pass: None,
+        }));
+        self.cmd_begin_render_pass(begin_info, subpass_contents);
+    }
+
+    /// Same as [`Self::begin_render_pass`] but also keeps a reference to `render_pass` so
+    /// commands like [`Self::clear_attachments`] can validate attachment indices against its
+    /// subpasses.
+    ///
+    /// _Note: this fn doesn't check that the render pass handle in `begin_info` is equal to
+    /// that of `render_pass`._
+    pub fn begin_render_pass_with_subpasses(
+        &self,
+        render_pass: &Arc<RenderPass>,
+        begin_info: &vk::RenderPassBeginInfo,
+        subpass_contents: vk::SubpassContents,
+    ) {
+        self.set_render_pass_scope(Some(RenderPassScope {
+            subpass_index: 0,
+            render_pass: Some(render_pass.clone()),
+        }));
+        self.cmd_begin_render_pass(begin_info, subpass_contents);
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdNextSubpass.html>
     pub fn next_subpass(&self, subpass_contents: vk::SubpassContents) {
+        if let Ok(mut scope) = self.render_pass_scope.lock() {
+            if let Some(scope) = scope.as_mut() {
+                scope.subpass_index += 1;
+            }
+        }
         unsafe {
             self.device()
                 .inner()
@@ -103,11 +208,52 @@ impl CommandBuffer {
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdEndRenderPass.html>
     pub fn end_render_pass(&self) {
+        self.set_render_pass_scope(None);
         unsafe { self.device().inner().cmd_end_render_pass(self.handle) }
     }
 
+    /// Uses the core function on Vulkan 1.3 devices and `VK_KHR_dynamic_rendering` otherwise.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBeginRendering.html>
+    pub fn begin_rendering(&self, rendering_info: &vk::RenderingInfo) {
+        self.set_render_pass_scope(Some(RenderPassScope {
+            subpass_index: 0,
+            render_pass: None,
+        }));
+        if self.device().effective_api_version() >= ApiVersion::V1_3 {
+            unsafe {
+                self.device()
+                    .inner()
+                    .cmd_begin_rendering(self.handle, rendering_info)
+            }
+        } else {
+            let dynamic_rendering_fns = self
+                .device()
+                .extension_loader::<khr::dynamic_rendering::Device>();
+            unsafe { dynamic_rendering_fns.cmd_begin_rendering(self.handle, rendering_info) }
+        }
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdEndRendering.html>
+    pub fn end_rendering(&self) {
+        self.set_render_pass_scope(None);
+        if self.device().effective_api_version() >= ApiVersion::V1_3 {
+            unsafe { self.device().inner().cmd_end_rendering(self.handle) }
+        } else {
+            let dynamic_rendering_fns = self
+                .device()
+                .extension_loader::<khr::dynamic_rendering::Device>();
+            unsafe { dynamic_rendering_fns.cmd_end_rendering(self.handle) }
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBindPipeline.html>
     pub fn bind_pipeline(&self, pipeline: &dyn PipelineAccess) {
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Pipeline,
+            [pipeline.handle().as_raw()],
+            "bind_pipeline",
+        );
         unsafe {
             self.device().inner().cmd_bind_pipeline(
                 self.handle,
@@ -130,6 +276,11 @@ impl CommandBuffer {
             .into_iter()
             .map(|descriptor_set| descriptor_set.handle())
             .collect();
+        self.debug_assert_not_poisoned(
+            CountedObjectType::DescriptorSet,
+            descriptor_set_handles.iter().map(|handle| handle.as_raw()),
+            "bind_descriptor_sets",
+        );
         unsafe {
             self.device().inner().cmd_bind_descriptor_sets(
                 self.handle,
@@ -142,6 +293,79 @@ impl CommandBuffer {
         }
     }
 
+    /// Binds `descriptor_sets` for `pipeline`, first checking that the set indices are in range of
+    /// the pipeline layout and that each set was allocated with a layout identically defined to
+    /// the one the pipeline expects at that index (see
+    /// [`DescriptorSetLayoutProperties::is_identically_defined`]).
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBindDescriptorSets.html>
+    pub fn bind_descriptor_sets_for_pipeline(
+        &self,
+        pipeline: &dyn PipelineAccess,
+        first_set: u32,
+        descriptor_sets: &[&DescriptorSet],
+        dynamic_offsets: &[u32],
+    ) -> Result<(), CommandError> {
+        let descriptor_set_layouts: Vec<&DescriptorSetLayout> = descriptor_sets
+            .iter()
+            .map(|descriptor_set| descriptor_set.layout().as_ref())
+            .collect();
+        let pipeline_set_layouts: Vec<&DescriptorSetLayout> = pipeline
+            .descriptor_set_layouts()
+            .iter()
+            .map(|layout| layout.as_ref())
+            .collect();
+        check_descriptor_set_layouts(
+            &pipeline_set_layouts,
+            first_set,
+            &descriptor_set_layouts,
+            |pipeline_set_layout, descriptor_set_layout| {
+                pipeline_set_layout.handle() == descriptor_set_layout.handle()
+                    || pipeline_set_layout
+                        .properties()
+                        .is_identically_defined(descriptor_set_layout.properties())
+            },
+        )?;
+
+        self.bind_descriptor_sets(
+            pipeline.bind_point(),
+            pipeline.pipeline_layout(),
+            first_set,
+            descriptor_sets.iter().copied(),
+            dynamic_offsets,
+        );
+        Ok(())
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdPushDescriptorSetWithTemplateKHR.html>
+    ///
+    /// Requires `VK_KHR_push_descriptor`. `descriptor_update_template` must have been created with
+    /// [`DescriptorUpdateTemplate::new_push_descriptors`].
+    pub fn push_descriptor_set_with_template(
+        &self,
+        descriptor_update_template: &DescriptorUpdateTemplate,
+        pipeline_layout: &PipelineLayout,
+        set: u32,
+        data: &DescriptorTemplateData,
+    ) {
+        debug_assert_eq!(
+            descriptor_update_template.template_type(),
+            vk::DescriptorUpdateTemplateType::PUSH_DESCRIPTORS_KHR
+        );
+        let push_descriptor_fns = self
+            .device()
+            .extension_loader::<khr::push_descriptor::Device>();
+        unsafe {
+            push_descriptor_fns.cmd_push_descriptor_set_with_template(
+                self.handle,
+                descriptor_update_template.handle(),
+                pipeline_layout.handle(),
+                set,
+                data.as_ptr(),
+            )
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBindVertexBuffers.html>
     pub fn bind_vertex_buffers<'a>(
         &self,
@@ -151,6 +375,11 @@ impl CommandBuffer {
     ) {
         let buffer_handles: Vec<vk::Buffer> =
             buffers.into_iter().map(|buffer| buffer.handle()).collect();
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            buffer_handles.iter().map(|handle| handle.as_raw()),
+            "bind_vertex_buffers",
+        );
         unsafe {
             self.device().inner().cmd_bind_vertex_buffers(
                 self.handle,
@@ -164,10 +393,15 @@ impl CommandBuffer {
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBindIndexBuffer.html>
     pub fn bind_index_buffer(
         &self,
-        buffer: &Buffer,
+        buffer: &dyn BufferAccess,
         offset: vk::DeviceSize,
         index_type: vk::IndexType,
     ) {
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [buffer.handle().as_raw()],
+            "bind_index_buffer",
+        );
         unsafe {
             self.device().inner().cmd_bind_index_buffer(
                 self.handle,
@@ -178,6 +412,150 @@ impl CommandBuffer {
         }
     }
 
+    /// Same as [`Self::bind_index_buffer`] but with an explicit `size` (or `vk::WHOLE_SIZE`) so
+    /// index reads past `offset + size` are out of bounds. Passing `None` binds a null index
+    /// buffer (every index read returns zero) which requires the `VK_KHR_maintenance6`
+    /// `maintenance6` feature and `offset` to be zero.
+    ///
+    /// Requires `VK_KHR_maintenance5` to be enabled.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdBindIndexBuffer2KHR.html>
+    pub fn bind_index_buffer_2(
+        &self,
+        buffer: Option<&dyn BufferAccess>,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        index_type: vk::IndexType,
+    ) {
+        debug_assert!(
+            self.device()
+                .enabled_features()
+                .maintenance_5
+                .is_some_and(|maintenance_5| maintenance_5.maintenance5 == vk::TRUE),
+            "bind_index_buffer_2 requires the VK_KHR_maintenance5 maintenance5 feature"
+        );
+        if buffer.is_none() {
+            debug_assert!(
+                self.device()
+                    .enabled_features()
+                    .maintenance_6
+                    .is_some_and(|maintenance_6| maintenance_6.maintenance6 == vk::TRUE),
+                "binding a null index buffer requires the VK_KHR_maintenance6 maintenance6 feature"
+            );
+            debug_assert_eq!(offset, 0, "a null index buffer must be bound at offset 0");
+        }
+        let buffer_handle = buffer.map(|buffer| buffer.handle()).unwrap_or_default();
+        let maintenance_5_fns = self
+            .device()
+            .extension_loader::<khr::maintenance5::Device>();
+        unsafe {
+            maintenance_5_fns.cmd_bind_index_buffer2(
+                self.handle,
+                buffer_handle,
+                offset,
+                size,
+                index_type,
+            )
+        }
+    }
+
+    /// Writes the compacted or serialization size (depending on the query type of `query_pool`)
+    /// of each acceleration structure to consecutive queries starting at `first_query`. The
+    /// queries must have been reset and the acceleration structures built.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdWriteAccelerationStructuresPropertiesKHR.html>
+    pub fn write_acceleration_structures_properties(
+        &self,
+        acceleration_structures: &[&AccelerationStructure],
+        query_pool: &QueryPool,
+        first_query: u32,
+    ) {
+        let handles: Vec<vk::AccelerationStructureKHR> = acceleration_structures
+            .iter()
+            .map(|acceleration_structure| acceleration_structure.handle())
+            .collect();
+        let acceleration_structure_fns = self
+            .device()
+            .extension_loader::<khr::acceleration_structure::Device>();
+        unsafe {
+            acceleration_structure_fns.cmd_write_acceleration_structures_properties(
+                self.handle,
+                &handles,
+                query_pool.properties().query_type,
+                query_pool.handle(),
+                first_query,
+            )
+        }
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdCopyAccelerationStructureKHR.html>
+    pub fn copy_acceleration_structure(
+        &self,
+        src: &AccelerationStructure,
+        dst: &AccelerationStructure,
+        mode: vk::CopyAccelerationStructureModeKHR,
+    ) {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "copy_acceleration_structure",
+        );
+        let copy_info = vk::CopyAccelerationStructureInfoKHR::default()
+            .src(src.handle())
+            .dst(dst.handle())
+            .mode(mode);
+        let acceleration_structure_fns = self
+            .device()
+            .extension_loader::<khr::acceleration_structure::Device>();
+        unsafe {
+            acceleration_structure_fns.cmd_copy_acceleration_structure(self.handle, &copy_info)
+        }
+    }
+
+    /// Records a reset of the queries in `query_range`. Returns an error without recording anything
+    /// if the range is reversed or out of bounds. See also [`QueryPool::reset_host`] which doesn't
+    /// need a command buffer.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdResetQueryPool.html>
+    pub fn reset_query_pool(
+        &self,
+        query_pool: &QueryPool,
+        query_range: Range<u32>,
+    ) -> Result<(), QueryPoolError> {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "reset_query_pool",
+        );
+        query_pool.check_range(&query_range)?;
+        unsafe {
+            self.device().inner().cmd_reset_query_pool(
+                self.handle,
+                query_pool.handle(),
+                query_range.start,
+                query_range.end - query_range.start,
+            )
+        }
+        Ok(())
+    }
+
+    /// Requires the queue family to have non-zero `timestamp_valid_bits`.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdWriteTimestamp.html>
+    pub fn write_timestamp(
+        &self,
+        pipeline_stage: vk::PipelineStageFlags,
+        query_pool: &QueryPool,
+        query: u32,
+    ) {
+        unsafe {
+            self.device().inner().cmd_write_timestamp(
+                self.handle,
+                pipeline_stage,
+                query_pool.handle(),
+                query,
+            )
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdSetViewport.html>
     pub fn set_viewport(&self, first_viewport: u32, viewports: &[vk::Viewport]) {
         unsafe {
@@ -196,6 +574,88 @@ impl CommandBuffer {
         }
     }
 
+    /// Sets which aspects of the bound attachments are in a feedback loop for pipelines created
+    /// with `vk::DynamicState::ATTACHMENT_FEEDBACK_LOOP_ENABLE_EXT`. An empty `aspect_mask`
+    /// disables the feedback loop. Requires the `VK_EXT_attachment_feedback_loop_dynamic_state`
+    /// `attachmentFeedbackLoopDynamicState` feature.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetAttachmentFeedbackLoopEnableEXT.html>
+    pub fn set_attachment_feedback_loop_enable(&self, aspect_mask: vk::ImageAspectFlags) {
+        debug_assert!(
+            self.device()
+                .enabled_features()
+                .attachment_feedback_loop_dynamic_state
+                .is_some_and(|features| features.attachment_feedback_loop_dynamic_state
+                    == vk::TRUE),
+            "set_attachment_feedback_loop_enable requires the \
+            VK_EXT_attachment_feedback_loop_dynamic_state attachmentFeedbackLoopDynamicState feature"
+        );
+        let feedback_loop_fns = self
+            .device()
+            .extension_loader::<ext::attachment_feedback_loop_dynamic_state::Device>();
+        unsafe {
+            (feedback_loop_fns
+                .fp()
+                .cmd_set_attachment_feedback_loop_enable_ext)(self.handle, aspect_mask)
+        }
+    }
+
+    /// Writes `marker` to `buffer` at `offset` once all previous commands have reached
+    /// `pipeline_stage` (only `TOP_OF_PIPE` and `BOTTOM_OF_PIPE` are guaranteed to be precise).
+    /// The write isn't ordered by barriers so it still lands when later commands hang, which
+    /// makes it useful for finding where the device was lost. `buffer` needs `TRANSFER_DST`
+    /// usage. Requires `VK_AMD_buffer_marker` to be enabled. See [`HangTracer`](crate::HangTracer).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdWriteBufferMarkerAMD.html>
+    pub fn write_buffer_marker(
+        &self,
+        pipeline_stage: vk::PipelineStageFlags,
+        buffer: &dyn BufferAccess,
+        offset: vk::DeviceSize,
+        marker: u32,
+    ) {
+        debug_assert!(
+            self.device()
+                .enabled_extensions()
+                .iter()
+                .any(|extension| extension.as_c_str() == amd::buffer_marker::NAME),
+            "write_buffer_marker requires VK_AMD_buffer_marker"
+        );
+        let buffer_marker_fns = self
+            .device()
+            .extension_loader::<amd::buffer_marker::Device>();
+        unsafe {
+            buffer_marker_fns.cmd_write_buffer_marker(
+                self.handle,
+                pipeline_stage,
+                buffer.handle(),
+                offset,
+                marker,
+            )
+        }
+    }
+
+    /// Inserts a diagnostic checkpoint which can be retrieved with
+    /// [`Queue::checkpoint_data`](crate::Queue::checkpoint_data) after a device loss, along with
+    /// the last pipeline stage it reached. `marker` is an opaque value which the driver never
+    /// dereferences. Requires `VK_NV_device_diagnostic_checkpoints` to be enabled. See
+    /// [`HangTracer`](crate::HangTracer).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdSetCheckpointNV.html>
+    pub fn set_checkpoint(&self, marker: usize) {
+        debug_assert!(
+            self.device()
+                .enabled_extensions()
+                .iter()
+                .any(|extension| extension.as_c_str() == nv::device_diagnostic_checkpoints::NAME),
+            "set_checkpoint requires VK_NV_device_diagnostic_checkpoints"
+        );
+        let checkpoint_fns = self
+            .device()
+            .extension_loader::<nv::device_diagnostic_checkpoints::Device>();
+        unsafe { checkpoint_fns.cmd_set_checkpoint(self.handle, marker as *const c_void) }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdDraw.html>
     pub fn draw(
         &self,
@@ -204,6 +664,7 @@ impl CommandBuffer {
         first_vertex: u32,
         first_instance: u32,
     ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Inside, "draw");
         unsafe {
             self.device().inner().cmd_draw(
                 self.handle,
@@ -224,6 +685,7 @@ impl CommandBuffer {
         vertex_offset: i32,
         first_instance: u32,
     ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Inside, "draw_indexed");
         unsafe {
             self.device().inner().cmd_draw_indexed(
                 self.handle,
@@ -239,11 +701,25 @@ impl CommandBuffer {
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndexedIndirect.html>
     pub fn draw_indexed_indirect(
         &self,
-        buffer: &Buffer,
+        buffer: &dyn BufferAccess,
         offset: vk::DeviceSize,
         draw_count: u32,
         stride: u32,
     ) {
+        debug_assert!(
+            draw_count <= 1
+                || self
+                    .device()
+                    .enabled_features()
+                    .features_1_0
+                    .multi_draw_indirect
+                    == vk::TRUE,
+            "a draw count greater than 1 requires the multiDrawIndirect feature"
+        );
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Inside,
+            "draw_indexed_indirect",
+        );
         unsafe {
             self.device().inner().cmd_draw_indexed_indirect(
                 self.handle,
@@ -255,6 +731,85 @@ impl CommandBuffer {
         }
     }
 
+    /// Requires vulkan 1.2 and the `drawIndirectCount` feature.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdDrawIndexedIndirectCount.html>
+    pub fn draw_indexed_indirect_count(
+        &self,
+        buffer: &dyn BufferAccess,
+        offset: vk::DeviceSize,
+        count_buffer: &dyn BufferAccess,
+        count_buffer_offset: vk::DeviceSize,
+        max_draw_count: u32,
+        stride: u32,
+    ) {
+        debug_assert!(
+            self.device()
+                .enabled_features()
+                .features_1_2
+                .draw_indirect_count
+                == vk::TRUE,
+            "draw_indexed_indirect_count requires the drawIndirectCount feature"
+        );
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Inside,
+            "draw_indexed_indirect_count",
+        );
+        unsafe {
+            self.device().inner().cmd_draw_indexed_indirect_count(
+                self.handle,
+                buffer.handle(),
+                offset,
+                count_buffer.handle(),
+                count_buffer_offset,
+                max_draw_count,
+                stride,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdDispatch.html>
+    pub fn dispatch(&self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "dispatch");
+        unsafe {
+            self.device().inner().cmd_dispatch(
+                self.handle,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            )
+        }
+    }
+
+    /// Requires the `VK_KHR_ray_tracing_pipeline` extension. See
+    /// [`SbtBuilder`](crate::SbtBuilder) for creating the shader binding table.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCmdTraceRaysKHR.html>
+    pub fn trace_rays(
+        &self,
+        regions: &ShaderBindingTableRegions,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "trace_rays");
+        let ray_tracing_fns = self
+            .device()
+            .extension_loader::<khr::ray_tracing_pipeline::Device>();
+        unsafe {
+            ray_tracing_fns.cmd_trace_rays(
+                self.handle,
+                &regions.raygen,
+                &regions.miss,
+                &regions.hit,
+                &regions.callable,
+                width,
+                height,
+                depth,
+            )
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdExecuteCommands.html>
     pub fn execute_commands(
         &self,
@@ -272,6 +827,13 @@ impl CommandBuffer {
             .map(|command_buffer| command_buffer.handle())
             .collect();
 
+        self.debug_assert_not_poisoned(
+            CountedObjectType::CommandBuffer,
+            secondary_command_buffer_handles
+                .iter()
+                .map(|handle| handle.as_raw()),
+            "execute_commands",
+        );
         unsafe {
             self.device()
                 .inner()
@@ -284,10 +846,16 @@ impl CommandBuffer {
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdCopyBuffer.html>
     pub fn copy_buffer(
         &self,
-        src_buffer: &Buffer,
-        dst_buffer: &Buffer,
+        src_buffer: &dyn BufferAccess,
+        dst_buffer: &dyn BufferAccess,
         regions: &[vk::BufferCopy],
     ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "copy_buffer");
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [src_buffer.handle().as_raw(), dst_buffer.handle().as_raw()],
+            "copy_buffer",
+        );
         unsafe {
             self.device().inner().cmd_copy_buffer(
                 self.handle,
@@ -301,11 +869,25 @@ impl CommandBuffer {
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdCopyBufferToImage.html>
     pub fn copy_buffer_to_image(
         &self,
-        src_buffer: &Buffer,
+        src_buffer: &dyn BufferAccess,
         dst_image: &dyn ImageAccess,
         dst_image_layout: vk::ImageLayout,
         regions: &[vk::BufferImageCopy],
     ) {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "copy_buffer_to_image",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [src_buffer.handle().as_raw()],
+            "copy_buffer_to_image",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [dst_image.handle().as_raw()],
+            "copy_buffer_to_image",
+        );
         unsafe {
             self.device().inner().cmd_copy_buffer_to_image(
                 self.handle,
@@ -317,6 +899,341 @@ impl CommandBuffer {
         }
     }
 
+    /// Same as [`Self::copy_buffer_to_image`] but first checks each region against the image
+    /// properties and the `minImageTransferGranularity` of the command pool's queue family. See
+    /// [`validate_buffer_image_copy`].
+    pub fn copy_buffer_to_image_checked(
+        &self,
+        src_buffer: &dyn BufferAccess,
+        dst_image: &Image,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::BufferImageCopy],
+    ) -> Result<(), CopyValidationError> {
+        let transfer_granularity = self.min_image_transfer_granularity();
+        for region in regions {
+            validate_buffer_image_copy(dst_image.properties(), transfer_granularity, region)?;
+        }
+        self.copy_buffer_to_image(src_buffer, dst_image, dst_image_layout, regions);
+        Ok(())
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdCopyImageToBuffer.html>
+    pub fn copy_image_to_buffer(
+        &self,
+        src_image: &dyn ImageAccess,
+        src_image_layout: vk::ImageLayout,
+        dst_buffer: &dyn BufferAccess,
+        regions: &[vk::BufferImageCopy],
+    ) {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "copy_image_to_buffer",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [src_image.handle().as_raw()],
+            "copy_image_to_buffer",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [dst_buffer.handle().as_raw()],
+            "copy_image_to_buffer",
+        );
+        unsafe {
+            self.device().inner().cmd_copy_image_to_buffer(
+                self.handle,
+                src_image.handle(),
+                src_image_layout,
+                dst_buffer.handle(),
+                regions,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdCopyImage.html>
+    pub fn copy_image(
+        &self,
+        src_image: &dyn ImageAccess,
+        src_image_layout: vk::ImageLayout,
+        dst_image: &dyn ImageAccess,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::ImageCopy],
+    ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "copy_image");
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [src_image.handle().as_raw(), dst_image.handle().as_raw()],
+            "copy_image",
+        );
+        unsafe {
+            self.device().inner().cmd_copy_image(
+                self.handle,
+                src_image.handle(),
+                src_image_layout,
+                dst_image.handle(),
+                dst_image_layout,
+                regions,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdBlitImage.html>
+    pub fn blit_image(
+        &self,
+        src_image: &dyn ImageAccess,
+        src_image_layout: vk::ImageLayout,
+        dst_image: &dyn ImageAccess,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::ImageBlit],
+        filter: vk::Filter,
+    ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "blit_image");
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [src_image.handle().as_raw(), dst_image.handle().as_raw()],
+            "blit_image",
+        );
+        unsafe {
+            self.device().inner().cmd_blit_image(
+                self.handle,
+                src_image.handle(),
+                src_image_layout,
+                dst_image.handle(),
+                dst_image_layout,
+                regions,
+                filter,
+            )
+        }
+    }
+
+    /// Same as [`Self::blit_image`] but first checks each region's aspect masks, subresources and
+    /// bounds, and that the formats, sample counts and `filter` can be used for blits. See
+    /// [`validate_image_blit`] and [`validate_blit_format_features`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_image_checked(
+        &self,
+        src_image: &Image,
+        src_image_layout: vk::ImageLayout,
+        dst_image: &Image,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::ImageBlit],
+        filter: vk::Filter,
+    ) -> Result<(), CopyValidationError> {
+        validate_blit_format_features(
+            src_image.properties().format,
+            self.format_features(src_image.properties()),
+            dst_image.properties().format,
+            self.format_features(dst_image.properties()),
+            filter,
+        )?;
+        for region in regions {
+            validate_image_blit(
+                src_image.properties(),
+                dst_image.properties(),
+                region,
+                filter,
+            )?;
+        }
+        self.blit_image(
+            src_image,
+            src_image_layout,
+            dst_image,
+            dst_image_layout,
+            regions,
+            filter,
+        );
+        Ok(())
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdFillBuffer.html>
+    pub fn fill_buffer(
+        &self,
+        dst_buffer: &dyn BufferAccess,
+        dst_offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        data: u32,
+    ) {
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "fill_buffer");
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [dst_buffer.handle().as_raw()],
+            "fill_buffer",
+        );
+        unsafe {
+            self.device().inner().cmd_fill_buffer(
+                self.handle,
+                dst_buffer.handle(),
+                dst_offset,
+                size,
+                data,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdUpdateBuffer.html>
+    ///
+    /// `dst_offset` and `data.len()` must be multiples of 4 and `data.len()` must be at most
+    /// 65536. See [`can_update_buffer`](crate::can_update_buffer).
+    pub fn update_buffer(
+        &self,
+        dst_buffer: &dyn BufferAccess,
+        dst_offset: vk::DeviceSize,
+        data: &[u8],
+    ) {
+        debug_assert!(crate::can_update_buffer(
+            dst_offset,
+            data.len() as vk::DeviceSize
+        ));
+        self.debug_assert_render_pass_scope(RenderPassScopeRequirement::Outside, "update_buffer");
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            [dst_buffer.handle().as_raw()],
+            "update_buffer",
+        );
+        unsafe {
+            self.device().inner().cmd_update_buffer(
+                self.handle,
+                dst_buffer.handle(),
+                dst_offset,
+                data,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdClearColorImage.html>
+    pub fn clear_color_image(
+        &self,
+        image: &dyn ImageAccess,
+        image_layout: vk::ImageLayout,
+        clear_color_value: &vk::ClearColorValue,
+        ranges: &[vk::ImageSubresourceRange],
+    ) {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "clear_color_image",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [image.handle().as_raw()],
+            "clear_color_image",
+        );
+        unsafe {
+            self.device().inner().cmd_clear_color_image(
+                self.handle,
+                image.handle(),
+                image_layout,
+                clear_color_value,
+                ranges,
+            )
+        }
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdClearDepthStencilImage.html>
+    pub fn clear_depth_stencil_image(
+        &self,
+        image: &dyn ImageAccess,
+        image_layout: vk::ImageLayout,
+        clear_depth_stencil_value: &vk::ClearDepthStencilValue,
+        ranges: &[vk::ImageSubresourceRange],
+    ) {
+        self.debug_assert_render_pass_scope(
+            RenderPassScopeRequirement::Outside,
+            "clear_depth_stencil_image",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            [image.handle().as_raw()],
+            "clear_depth_stencil_image",
+        );
+        unsafe {
+            self.device().inner().cmd_clear_depth_stencil_image(
+                self.handle,
+                image.handle(),
+                image_layout,
+                clear_depth_stencil_value,
+                ranges,
+            )
+        }
+    }
+
+    /// Clears regions of attachments of the current subpass. Returns an error if called outside
+    /// of a render pass instance, if a rect is empty, or (when the render pass was begun with
+    /// [`Self::begin_render_pass_with_subpasses`]) if an attachment isn't used by the current
+    /// subpass. See [`RenderPass::clear_attachment`](crate::RenderPass::clear_attachment) to
+    /// build `attachments` from render pass attachment indices.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdClearAttachments.html>
+    pub fn clear_attachments(
+        &self,
+        attachments: &[vk::ClearAttachment],
+        rects: &[vk::ClearRect],
+    ) -> Result<(), CommandError> {
+        {
+            let scope = self
+                .render_pass_scope
+                .lock()
+                .map_err(|_| CommandError::RenderPassScopePoisoned)?;
+            let scope = scope.as_ref().ok_or(CommandError::NotInRenderPass)?;
+            if let Some(subpass) = scope.subpass() {
+                for attachment in attachments {
+                    check_clear_attachment(attachment, subpass, scope.subpass_index)?;
+                }
+            }
+        }
+
+        let empty_rect = rects.iter().any(|rect| {
+            rect.layer_count == 0 || rect.rect.extent.width == 0 || rect.rect.extent.height == 0
+        });
+        if empty_rect {
+            return Err(CommandError::EmptyClearRect);
+        }
+
+        unsafe {
+            self.device()
+                .inner()
+                .cmd_clear_attachments(self.handle, attachments, rects)
+        }
+
+        Ok(())
+    }
+
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdPipelineBarrier.html>
+    pub fn pipeline_barrier(
+        &self,
+        src_stage_mask: vk::PipelineStageFlags,
+        dst_stage_mask: vk::PipelineStageFlags,
+        dependency_flags: vk::DependencyFlags,
+        memory_barriers: &[vk::MemoryBarrier],
+        buffer_memory_barriers: &[vk::BufferMemoryBarrier],
+        image_memory_barriers: &[vk::ImageMemoryBarrier],
+    ) {
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Buffer,
+            buffer_memory_barriers
+                .iter()
+                .map(|barrier| barrier.buffer.as_raw()),
+            "pipeline_barrier",
+        );
+        self.debug_assert_not_poisoned(
+            CountedObjectType::Image,
+            image_memory_barriers
+                .iter()
+                .map(|barrier| barrier.image.as_raw()),
+            "pipeline_barrier",
+        );
+        unsafe {
+            self.device().inner().cmd_pipeline_barrier(
+                self.handle,
+                src_stage_mask,
+                dst_stage_mask,
+                dependency_flags,
+                memory_barriers,
+                buffer_memory_barriers,
+                image_memory_barriers,
+            )
+        }
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdPushConstants.html>
     pub fn push_constants(
         &self,
@@ -337,6 +1254,111 @@ impl CommandBuffer {
     }
 }
 
+impl CommandBuffer {
+    /// Updates push constants for `pipeline`, first checking that every byte written is covered by
+    /// a push constant range for each stage in `stage_flags`, and that `stage_flags` includes the
+    /// stages of every range overlapping the write.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkCmdPushConstants.html>
+    pub fn push_constants_for_pipeline(
+        &self,
+        pipeline: &dyn PipelineAccess,
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        constants: &[u8],
+    ) -> Result<(), CommandError> {
+        check_push_constants(
+            pipeline.push_constant_ranges(),
+            stage_flags,
+            offset,
+            constants.len() as u32,
+        )?;
+        self.push_constants(pipeline.pipeline_layout(), stage_flags, offset, constants);
+        Ok(())
+    }
+
+    /// Panics in debug builds if a command which must be recorded outside of a render pass
+    /// instance (transfers, clears, dispatches) is recorded inside one, or a draw is recorded
+    /// outside one. Some drivers silently tolerate these spec violations. Relies on the render
+    /// pass scope tracked by [`Self::begin_render_pass`], [`Self::begin_rendering`] and
+    /// [`Self::begin`] (for secondary command buffers continuing a render pass).
+    #[inline]
+    fn debug_assert_render_pass_scope(
+        &self,
+        requirement: RenderPassScopeRequirement,
+        command: &str,
+    ) {
+        if cfg!(debug_assertions) {
+            let in_render_pass = self
+                .render_pass_scope
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .is_some();
+            if let Err(e) = check_render_pass_scope(requirement, in_render_pass) {
+                panic!("{}: {}", command, e);
+            }
+        }
+    }
+
+    /// Panics in debug builds if the safety audit is enabled (see
+    /// [`Device::set_safety_audit`]) and any of `handles_raw` was read from a dropped bort
+    /// wrapper.
+    #[inline]
+    fn debug_assert_not_poisoned(
+        &self,
+        object_type: CountedObjectType,
+        handles_raw: impl IntoIterator<Item = u64>,
+        command: &str,
+    ) {
+        if cfg!(debug_assertions) {
+            let handle_audit = self.device().handle_audit();
+            if !handle_audit.is_enabled() {
+                return;
+            }
+            for handle_raw in handles_raw {
+                handle_audit.assert_not_poisoned(object_type, handle_raw, command);
+            }
+        }
+    }
+
+    /// Format features of an image with `image_properties` for its tiling.
+    fn format_features(&self, image_properties: &ImageProperties) -> vk::FormatFeatureFlags {
+        let physical_device = self.device().physical_device();
+        let format_properties = unsafe {
+            physical_device
+                .instance()
+                .inner()
+                .get_physical_device_format_properties(
+                    physical_device.handle(),
+                    image_properties.format,
+                )
+        };
+        if image_properties.tiling == vk::ImageTiling::LINEAR {
+            format_properties.linear_tiling_features
+        } else {
+            format_properties.optimal_tiling_features
+        }
+    }
+
+    fn cmd_begin_render_pass(
+        &self,
+        begin_info: &vk::RenderPassBeginInfo,
+        subpass_contents: vk::SubpassContents,
+    ) {
+        unsafe {
+            self.device()
+                .inner()
+                .cmd_begin_render_pass(self.handle, begin_info, subpass_contents)
+        }
+    }
+
+    fn set_render_pass_scope(&self, render_pass_scope: Option<RenderPassScope>) {
+        if let Ok(mut scope) = self.render_pass_scope.lock() {
+            *scope = render_pass_scope;
+        }
+    }
+}
+
 impl Drop for CommandBuffer {
     fn drop(&mut self) {
         unsafe {
@@ -344,6 +1366,10 @@ impl Drop for CommandBuffer {
                 .inner()
                 .free_command_buffers(self.command_pool.handle(), &[self.handle])
         }
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::CommandBuffer, self.counted);
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
@@ -359,11 +1385,245 @@ impl DeviceOwned for CommandBuffer {
     }
 }
 
+// Dynamic Rendering Inheritance
+
+/// The attachment formats, sample count and view mask of the dynamic rendering instance a
+/// secondary command buffer will be executed in. See [`CommandBuffer::begin_secondary_rendering`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderingInheritance {
+    /// Must match the flags passed to `vkCmdBeginRendering` excluding
+    /// `CONTENTS_SECONDARY_COMMAND_BUFFERS`.
+    pub flags: vk::RenderingFlags,
+    pub view_mask: u32,
+    pub color_attachment_formats: Vec<vk::Format>,
+    /// `vk::Format::UNDEFINED` if there's no depth attachment.
+    pub depth_attachment_format: vk::Format,
+    /// `vk::Format::UNDEFINED` if there's no stencil attachment.
+    pub stencil_attachment_format: vk::Format,
+    pub rasterization_samples: vk::SampleCountFlags,
+}
+
+impl RenderingInheritance {
+    pub fn new(
+        color_attachment_formats: Vec<vk::Format>,
+        depth_attachment_format: vk::Format,
+        rasterization_samples: vk::SampleCountFlags,
+    ) -> Self {
+        Self {
+            color_attachment_formats,
+            depth_attachment_format,
+            rasterization_samples,
+            ..Default::default()
+        }
+    }
+
+    pub fn inheritance_rendering_info(&self) -> vk::CommandBufferInheritanceRenderingInfo<'_> {
+        vk::CommandBufferInheritanceRenderingInfo::default()
+            .flags(self.flags)
+            .view_mask(self.view_mask)
+            .color_attachment_formats(&self.color_attachment_formats)
+            .depth_attachment_format(self.depth_attachment_format)
+            .stencil_attachment_format(self.stencil_attachment_format)
+            .rasterization_samples(self.rasterization_samples)
+    }
+}
+
+impl Default for RenderingInheritance {
+    fn default() -> Self {
+        Self {
+            flags: vk::RenderingFlags::empty(),
+            view_mask: 0,
+            color_attachment_formats: Vec::new(),
+            depth_attachment_format: vk::Format::UNDEFINED,
+            stencil_attachment_format: vk::Format::UNDEFINED,
+            rasterization_samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+}
+
+// Render Pass Scope
+
+struct RenderPassScope {
+    subpass_index: usize,
+    /// `None` if the render pass wasn't provided when beginning the render pass instance.
+    render_pass: Option<Arc<RenderPass>>,
+}
+
+impl RenderPassScope {
+    fn subpass(&self) -> Option<&Subpass> {
+        self.render_pass
+            .as_ref()?
+            .properties()
+            .subpasses
+            .get(self.subpass_index)
+    }
+}
+
+/// Whether a command must be recorded inside or outside of a render pass instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RenderPassScopeRequirement {
+    Inside,
+    Outside,
+}
+
+fn check_render_pass_scope(
+    requirement: RenderPassScopeRequirement,
+    in_render_pass: bool,
+) -> Result<(), CommandError> {
+    match (requirement, in_render_pass) {
+        (RenderPassScopeRequirement::Inside, false) => Err(CommandError::NotInRenderPass),
+        (RenderPassScopeRequirement::Outside, true) => Err(CommandError::InRenderPass),
+        _ => Ok(()),
+    }
+}
+
+fn check_clear_attachment(
+    attachment: &vk::ClearAttachment,
+    subpass: &Subpass,
+    subpass_index: usize,
+) -> Result<(), CommandError> {
+    if attachment.aspect_mask.contains(vk::ImageAspectFlags::COLOR) {
+        let color_attachment_used = subpass
+            .color_attachments
+            .get(attachment.color_attachment as usize)
+            .is_some_and(|reference| reference.attachment != vk::ATTACHMENT_UNUSED);
+        if !color_attachment_used {
+            return Err(CommandError::ClearColorAttachmentNotInSubpass {
+                color_attachment: attachment.color_attachment,
+                subpass_index,
+            });
+        }
+    }
+
+    let clears_depth_stencil = attachment
+        .aspect_mask
+        .intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL);
+    if clears_depth_stencil {
+        let depth_attachment_used = subpass
+            .depth_attachment
+            .is_some_and(|reference| reference.attachment != vk::ATTACHMENT_UNUSED);
+        if !depth_attachment_used {
+            return Err(CommandError::ClearDepthStencilAttachmentNotInSubpass { subpass_index });
+        }
+    }
+
+    Ok(())
+}
+
+// Pipeline Layout Compatibility
+
+/// `compatible` decides whether a descriptor set layout can be bound where the pipeline layout
+/// expects another.
+fn check_descriptor_set_layouts<L>(
+    pipeline_set_layouts: &[L],
+    first_set: u32,
+    descriptor_set_layouts: &[L],
+    compatible: impl Fn(&L, &L) -> bool,
+) -> Result<(), CommandError> {
+    let set_count = pipeline_set_layouts.len() as u32;
+    let end_set = first_set as usize + descriptor_set_layouts.len();
+    if end_set > pipeline_set_layouts.len() {
+        return Err(CommandError::DescriptorSetIndexOutOfRange {
+            set_index: end_set as u32 - 1,
+            set_count,
+        });
+    }
+
+    for (set_index, (descriptor_set_layout, pipeline_set_layout)) in descriptor_set_layouts
+        .iter()
+        .zip(&pipeline_set_layouts[first_set as usize..])
+        .enumerate()
+    {
+        if !compatible(pipeline_set_layout, descriptor_set_layout) {
+            return Err(CommandError::DescriptorSetLayoutMismatch {
+                set_index: first_set + set_index as u32,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_push_constants(
+    push_constant_ranges: &[vk::PushConstantRange],
+    stage_flags: vk::ShaderStageFlags,
+    offset: u32,
+    size: u32,
+) -> Result<(), CommandError> {
+    let mismatch = CommandError::PushConstantRangeMismatch {
+        stage_flags,
+        offset,
+        size,
+    };
+    if size == 0 || !offset.is_multiple_of(4) || !size.is_multiple_of(4) {
+        return Err(mismatch);
+    }
+
+    let Some(end) = offset.checked_add(size) else {
+        return Err(mismatch);
+    };
+    for range in push_constant_ranges {
+        let overlaps = range.offset < end && offset < range.offset.saturating_add(range.size);
+        if overlaps && !stage_flags.contains(range.stage_flags) {
+            return Err(mismatch);
+        }
+    }
+
+    // every stage must have every byte in `offset..end` covered by one of its ranges
+    for stage_bit in 0..u32::BITS {
+        let stage = vk::ShaderStageFlags::from_raw(1 << stage_bit);
+        if !stage_flags.contains(stage) {
+            continue;
+        }
+        let mut covered_until = offset;
+        while covered_until < end {
+            let extends_coverage = push_constant_ranges.iter().find(|range| {
+                range.stage_flags.contains(stage)
+                    && range.offset <= covered_until
+                    && covered_until < range.offset.saturating_add(range.size)
+            });
+            match extends_coverage {
+                Some(range) => covered_until = range.offset.saturating_add(range.size),
+                None => return Err(mismatch),
+            }
+        }
+    }
+
+    Ok(())
+}
+
 // ~~ Errors ~~
 
 #[derive(Clone, Copy, Debug)]
 pub enum CommandError {
     CantExecutePrimaryCommandBuffer,
+    NotInRenderPass,
+    /// Transfer, clear and dispatch commands can't be recorded inside a render pass instance.
+    InRenderPass,
+    ClearColorAttachmentNotInSubpass {
+        color_attachment: u32,
+        subpass_index: usize,
+    },
+    ClearDepthStencilAttachmentNotInSubpass {
+        subpass_index: usize,
+    },
+    EmptyClearRect,
+    RenderPassScopePoisoned,
+    DescriptorSetIndexOutOfRange {
+        set_index: u32,
+        set_count: u32,
+    },
+    /// The descriptor set wasn't allocated with the layout the pipeline layout has at this index.
+    DescriptorSetLayoutMismatch {
+        set_index: u32,
+    },
+    /// The push constant update isn't covered by (or doesn't include all the stages of) the
+    /// pipeline layout's push constant ranges.
+    PushConstantRangeMismatch {
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    },
 }
 
 impl std::fmt::Display for CommandError {
@@ -373,8 +1633,193 @@ impl std::fmt::Display for CommandError {
                 f,
                 "attempted to call vkCmdExecuteCommands on a primary command buffer"
             ),
+            Self::NotInRenderPass => write!(
+                f,
+                "attempted to record a render pass command outside of a render pass instance"
+            ),
+            Self::InRenderPass => write!(
+                f,
+                "attempted to record a command which isn't allowed inside a render pass instance"
+            ),
+            Self::ClearColorAttachmentNotInSubpass {
+                color_attachment,
+                subpass_index,
+            } => write!(
+                f,
+                "color attachment {} isn't used by subpass {}",
+                color_attachment, subpass_index
+            ),
+            Self::ClearDepthStencilAttachmentNotInSubpass { subpass_index } => write!(
+                f,
+                "subpass {} doesn't have a depth/stencil attachment",
+                subpass_index
+            ),
+            Self::EmptyClearRect => {
+                write!(f, "clear rects must have a non-zero area and layer count")
+            }
+            Self::RenderPassScopePoisoned => write!(
+                f,
+                "render pass scope mutex was poisoned by a panic on another thread"
+            ),
+            Self::DescriptorSetIndexOutOfRange {
+                set_index,
+                set_count,
+            } => write!(
+                f,
+                "descriptor set index {} is out of range for a pipeline layout with {} set layouts",
+                set_index, set_count
+            ),
+            Self::DescriptorSetLayoutMismatch { set_index } => write!(
+                f,
+                "descriptor set bound at index {} doesn't match the pipeline layout's set layout",
+                set_index
+            ),
+            Self::PushConstantRangeMismatch {
+                stage_flags,
+                offset,
+                size,
+            } => write!(
+                f,
+                "push constant update (stages {:?}, offset {}, size {}) doesn't match the pipeline layout's push constant ranges",
+                stage_flags, offset, size
+            ),
         }
     }
 }
 
 impl Error for CommandError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn check_clear_attachment_rejects_unused_attachments() {
+    let color_reference = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    let subpass = Subpass::new(&[color_reference], None, &[]);
+
+    let color_clear = vk::ClearAttachment {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        color_attachment: 0,
+        clear_value: vk::ClearValue::default(),
+    };
+    assert!(check_clear_attachment(&color_clear, &subpass, 0).is_ok());
+
+    let out_of_range_clear = vk::ClearAttachment {
+        color_attachment: 1,
+        ..color_clear
+    };
+    assert!(check_clear_attachment(&out_of_range_clear, &subpass, 0).is_err());
+
+    let depth_clear = vk::ClearAttachment {
+        aspect_mask: vk::ImageAspectFlags::DEPTH,
+        ..color_clear
+    };
+    assert!(check_clear_attachment(&depth_clear, &subpass, 0).is_err());
+}
+
+#[test]
+fn check_render_pass_scope_matches_command_requirement() {
+    use RenderPassScopeRequirement::{Inside, Outside};
+    assert!(check_render_pass_scope(Inside, true).is_ok());
+    assert!(check_render_pass_scope(Outside, false).is_ok());
+    assert!(matches!(
+        check_render_pass_scope(Inside, false),
+        Err(CommandError::NotInRenderPass)
+    ));
+    assert!(matches!(
+        check_render_pass_scope(Outside, true),
+        Err(CommandError::InRenderPass)
+    ));
+}
+
+#[test]
+fn pipeline_layout_compatibility_checks() {
+    use crate::{DescriptorSetLayoutBinding, DescriptorSetLayoutProperties};
+
+    let set_layouts = [
+        vk::DescriptorSetLayout::from_raw(1),
+        vk::DescriptorSetLayout::from_raw(2),
+    ];
+    let same_handle = |a: &vk::DescriptorSetLayout, b: &vk::DescriptorSetLayout| a == b;
+    assert!(check_descriptor_set_layouts(&set_layouts, 1, &set_layouts[1..], same_handle).is_ok());
+    assert!(check_descriptor_set_layouts(&set_layouts, 1, &set_layouts, same_handle).is_err());
+    assert!(check_descriptor_set_layouts(&set_layouts, 0, &set_layouts[1..], same_handle).is_err());
+
+    // separately created layouts with the same bindings are compatible
+    let uniform_binding = |binding| DescriptorSetLayoutBinding {
+        binding,
+        descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+        descriptor_count: 1,
+        stage_flags: vk::ShaderStageFlags::VERTEX,
+        ..Default::default()
+    };
+    let layout =
+        DescriptorSetLayoutProperties::new_default(vec![uniform_binding(0), uniform_binding(1)]);
+    let reordered =
+        DescriptorSetLayoutProperties::new_default(vec![uniform_binding(1), uniform_binding(0)]);
+    let different =
+        DescriptorSetLayoutProperties::new_default(vec![uniform_binding(0), uniform_binding(2)]);
+    let identically_defined = |a: &&DescriptorSetLayoutProperties,
+                               b: &&DescriptorSetLayoutProperties| {
+        a.is_identically_defined(b)
+    };
+    assert!(
+        check_descriptor_set_layouts(&[&layout], 0, &[&reordered], identically_defined).is_ok()
+    );
+    assert!(
+        check_descriptor_set_layouts(&[&layout], 0, &[&different], identically_defined).is_err()
+    );
+
+    let push_constant_ranges = [
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: 16,
+        },
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            offset: 16,
+            size: 16,
+        },
+    ];
+    let vertex_fragment = vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT;
+    assert!(
+        check_push_constants(&push_constant_ranges, vk::ShaderStageFlags::VERTEX, 0, 16).is_ok()
+    );
+    assert!(check_push_constants(&push_constant_ranges, vertex_fragment, 16, 16).is_ok());
+    // the second range also includes the fragment stage
+    assert!(
+        check_push_constants(&push_constant_ranges, vk::ShaderStageFlags::VERTEX, 0, 32).is_err()
+    );
+    // the fragment stage doesn't cover bytes 0..16
+    assert!(check_push_constants(&push_constant_ranges, vertex_fragment, 0, 32).is_err());
+    assert!(check_push_constants(&push_constant_ranges, vertex_fragment, 16, 32).is_err());
+    assert!(
+        check_push_constants(&push_constant_ranges, vertex_fragment, 16, u32::MAX - 15).is_err()
+    );
+}
+
+#[test]
+fn rendering_inheritance_info() {
+    let inheritance = RenderingInheritance::new(
+        vec![vk::Format::R8G8B8A8_UNORM, vk::Format::R16G16B16A16_SFLOAT],
+        vk::Format::D32_SFLOAT,
+        vk::SampleCountFlags::TYPE_4,
+    );
+    let inheritance_rendering_info = inheritance.inheritance_rendering_info();
+    assert_eq!(inheritance_rendering_info.color_attachment_count, 2);
+    assert_eq!(
+        inheritance_rendering_info.depth_attachment_format,
+        vk::Format::D32_SFLOAT
+    );
+    assert_eq!(
+        inheritance_rendering_info.stencil_attachment_format,
+        vk::Format::UNDEFINED
+    );
+    assert_eq!(
+        RenderingInheritance::default().rasterization_samples,
+        vk::SampleCountFlags::TYPE_1
+    );
+}
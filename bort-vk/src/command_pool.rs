@@ -1,13 +1,27 @@
-use crate::{CommandBuffer, Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use crate::{ApiVersion, CommandBuffer, Device, DeviceOwned, Queue, ALLOCATION_CALLBACK_NONE};
 use ash::{
+    khr,
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
 
+/// Command pools (and the command buffers allocated from them) must be externally synchronized.
+/// In debug builds, allocating, beginning, ending and resetting command buffers and resetting or
+/// trimming the pool asserts that it happens on the thread which created the pool. To use a pool
+/// from another thread (e.g. one pool per worker thread created on the main thread), see
+/// [`Self::set_owner_thread`].
 pub struct CommandPool {
     handle: vk::CommandPool,
     properties: CommandPoolProperties,
+    /// The thread allowed to use the pool. `None` disables the debug checks.
+    owner_thread: Mutex<Option<ThreadId>>,
+    /// Reused between allocations so `vkAllocateCommandBuffers` doesn't need a new Vec each time.
+    handle_scratch: Mutex<Vec<vk::CommandBuffer>>,
 
     // dependencies
     device: Arc<Device>,
@@ -26,6 +40,8 @@ impl CommandPool {
         Ok(Self {
             handle,
             properties,
+            owner_thread: Mutex::new(Some(thread::current().id())),
+            handle_scratch: Mutex::new(Vec::new()),
             device,
         })
     }
@@ -47,6 +63,8 @@ impl CommandPool {
         Ok(Self {
             handle,
             properties,
+            owner_thread: Mutex::new(Some(thread::current().id())),
+            handle_scratch: Mutex::new(Vec::new()),
             device,
         })
     }
@@ -64,6 +82,25 @@ impl CommandPool {
         unsafe { self.allocate_command_buffers_from_allocate_info(allocate_info) }
     }
 
+    /// Like [`Self::allocate_command_buffers`] but appends the new command buffers to
+    /// `command_buffers` so a Vec can be reused e.g. when allocating every frame. Once the Vec
+    /// and the pool have grown to fit, this doesn't allocate any host memory.
+    pub fn allocate_command_buffers_into(
+        self: &Arc<Self>,
+        level: vk::CommandBufferLevel,
+        command_buffer_count: u32,
+        command_buffers: &mut Vec<CommandBuffer>,
+    ) -> VkResult<()> {
+        let allocate_info = vk::CommandBufferAllocateInfo::default()
+            .level(level)
+            .command_buffer_count(command_buffer_count)
+            .command_pool(self.handle);
+
+        unsafe {
+            self.allocate_command_buffers_from_allocate_info_into(allocate_info, command_buffers)
+        }
+    }
+
     #[inline]
     pub fn allocate_command_buffer(
         self: &Arc<Self>,
@@ -79,22 +116,60 @@ impl CommandPool {
         self: &Arc<Self>,
         allocate_info: vk::CommandBufferAllocateInfo,
     ) -> VkResult<Vec<CommandBuffer>> {
+        let mut command_buffers = Vec::with_capacity(allocate_info.command_buffer_count as usize);
+        unsafe {
+            self.allocate_command_buffers_from_allocate_info_into(
+                allocate_info,
+                &mut command_buffers,
+            )
+        }?;
+        Ok(command_buffers)
+    }
+
+    /// Appends the allocated command buffers to `command_buffers`. See
+    /// [`Self::allocate_command_buffers_into`].
+    ///
+    /// # Safety
+    /// Make sure your `p_next` chain contains valid pointers.
+    pub unsafe fn allocate_command_buffers_from_allocate_info_into(
+        self: &Arc<Self>,
+        allocate_info: vk::CommandBufferAllocateInfo,
+        command_buffers: &mut Vec<CommandBuffer>,
+    ) -> VkResult<()> {
+        self.debug_assert_owner_thread("allocate command buffers");
         let level = allocate_info.level;
+        let command_buffer_count = allocate_info.command_buffer_count as usize;
 
-        let command_buffer_handles = unsafe {
-            self.device()
-                .inner()
-                .allocate_command_buffers(&allocate_info)
-        }?;
+        let mut handle_scratch = self
+            .handle_scratch
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        handle_scratch.clear();
+        handle_scratch.reserve(command_buffer_count);
 
-        let command_buffers: Vec<CommandBuffer> = command_buffer_handles
-            .into_iter()
-            .map(|handle| unsafe { CommandBuffer::from_handle(handle, level, self.clone()) })
-            .collect();
-        Ok(command_buffers)
+        // call the function pointer directly to write the handles into the reused scratch Vec
+        let device = self.device.inner();
+        unsafe {
+            (device.fp_v1_0().allocate_command_buffers)(
+                device.handle(),
+                &allocate_info,
+                handle_scratch.as_mut_ptr(),
+            )
+        }
+        .result()?;
+        unsafe { handle_scratch.set_len(command_buffer_count) };
+
+        command_buffers.reserve(command_buffer_count);
+        command_buffers.extend(
+            handle_scratch
+                .drain(..)
+                .map(|handle| unsafe { CommandBuffer::from_handle(handle, level, self.clone()) }),
+        );
+        Ok(())
     }
 
     pub fn reset(&self, reset_flags: vk::CommandPoolResetFlags) -> VkResult<()> {
+        self.debug_assert_owner_thread("reset the command pool");
         unsafe {
             self.device
                 .inner()
@@ -102,6 +177,79 @@ impl CommandPool {
         }
     }
 
+    /// Returns unused memory held by the pool to the system e.g. after a burst of command buffer
+    /// allocations. Requires vulkan 1.1 or `VK_KHR_maintenance1` (see [`Self::supports_trim`]).
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkTrimCommandPool.html>
+    pub fn trim(&self) {
+        debug_assert!(
+            self.supports_trim(),
+            "vkTrimCommandPool requires vulkan 1.1 or VK_KHR_maintenance1"
+        );
+        self.debug_assert_owner_thread("trim the command pool");
+        if self.device.effective_api_version() >= ApiVersion::V1_1 {
+            unsafe {
+                self.device
+                    .inner()
+                    .trim_command_pool(self.handle, vk::CommandPoolTrimFlags::empty())
+            }
+        } else {
+            let maintenance1_fns = self.device.extension_loader::<khr::maintenance1::Device>();
+            unsafe {
+                maintenance1_fns.trim_command_pool(self.handle, vk::CommandPoolTrimFlags::empty())
+            }
+        }
+    }
+
+    /// Whether [`Self::trim`] can be called: the device uses vulkan 1.1 or has
+    /// `VK_KHR_maintenance1` enabled.
+    pub fn supports_trim(&self) -> bool {
+        self.device.effective_api_version() >= ApiVersion::V1_1
+            || self
+                .device
+                .enabled_extensions()
+                .iter()
+                .any(|extension| extension.as_c_str() == khr::maintenance1::NAME)
+    }
+
+    /// Hands the pool over to `owner_thread` for the debug thread affinity checks e.g. when the
+    /// pool is moved to a worker thread. Pass `None` to disable the checks for this pool.
+    ///
+    /// # Safety
+    /// The pool and its command buffers must still be externally synchronized: only one thread
+    /// may use them at a time.
+    pub unsafe fn set_owner_thread(&self, owner_thread: Option<ThreadId>) {
+        *self
+            .owner_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = owner_thread;
+    }
+
+    /// The thread allowed to use the pool in debug builds. Initially the creating thread.
+    pub fn owner_thread(&self) -> Option<ThreadId> {
+        *self
+            .owner_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Panics in debug builds if called from a thread other than the owner thread.
+    #[inline]
+    pub(crate) fn debug_assert_owner_thread(&self, operation: &str) {
+        if cfg!(debug_assertions) {
+            check_owner_thread(self.owner_thread(), thread::current().id(), operation);
+        }
+    }
+
+    /// Panics in debug builds if command buffers from this pool are about to be submitted to a
+    /// queue of a different family.
+    #[inline]
+    pub(crate) fn debug_assert_queue_family(&self, queue: &Queue) {
+        if cfg!(debug_assertions) {
+            check_queue_family(self.properties.queue_family_index, queue.family_index());
+        }
+    }
+
     // Getters
 
     pub fn handle(&self) -> vk::CommandPool {
@@ -135,6 +283,134 @@ impl Drop for CommandPool {
     }
 }
 
+// Helper Functions
+
+fn check_owner_thread(owner_thread: Option<ThreadId>, current_thread: ThreadId, operation: &str) {
+    if let Some(owner_thread) = owner_thread {
+        assert_eq!(
+            owner_thread, current_thread,
+            "attempted to {} from a thread other than the command pool's owner thread. command pools must be externally synchronized, see CommandPool::set_owner_thread",
+            operation
+        );
+    }
+}
+
+fn check_queue_family(pool_family_index: u32, queue_family_index: u32) {
+    assert_eq!(
+        pool_family_index, queue_family_index,
+        "command buffers allocated from a pool of queue family {} can't be submitted to a queue of family {}. see CommandPools::for_queue",
+        pool_family_index, queue_family_index
+    );
+}
+
+// Command Pools
+
+/// Command pools keyed by recording thread and queue family index, created on first use with the
+/// same [`vk::CommandPoolCreateFlags`]. [`Self::for_queue`] hands out the pool matching a
+/// [`Queue`] so command buffers aren't accidentally recorded with a pool of one family and
+/// submitted to a queue of another.
+///
+/// A `CommandPools` can be shared between threads: each thread gets its own pools, owned by that
+/// thread (see [`CommandPool::set_owner_thread`]), so the pools are never used by two threads at
+/// once. Command buffers must still be recorded on the thread whose pool they came from.
+pub struct CommandPools {
+    flags: vk::CommandPoolCreateFlags,
+    pools: Mutex<HashMap<(ThreadId, u32), Arc<CommandPool>>>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl CommandPools {
+    pub fn new(device: Arc<Device>, flags: vk::CommandPoolCreateFlags) -> Self {
+        Self {
+            flags,
+            pools: Mutex::new(HashMap::new()),
+            device,
+        }
+    }
+
+    /// The calling thread's pool for command buffers to be submitted to `queue`.
+    pub fn for_queue(&self, queue: &Queue) -> VkResult<Arc<CommandPool>> {
+        debug_assert!(
+            Arc::ptr_eq(queue.device(), &self.device),
+            "queue belongs to a different device than the command pools"
+        );
+        self.for_family(queue.family_index())
+    }
+
+    /// The calling thread's pool for `queue_family_index`, created if it doesn't exist yet.
+    pub fn for_family(&self, queue_family_index: u32) -> VkResult<Arc<CommandPool>> {
+        debug_assert!(
+            (queue_family_index as usize)
+                < self
+                    .device
+                    .physical_device()
+                    .queue_family_properties()
+                    .len(),
+            "queue family index {} out of range",
+            queue_family_index
+        );
+
+        let key = (thread::current().id(), queue_family_index);
+        let mut pools = self.lock_pools();
+        if let Some(command_pool) = pools.get(&key) {
+            return Ok(command_pool.clone());
+        }
+
+        let properties = CommandPoolProperties {
+            flags: self.flags,
+            queue_family_index,
+        };
+        let command_pool = Arc::new(CommandPool::new(self.device.clone(), properties)?);
+        pools.insert(key, command_pool.clone());
+        Ok(command_pool)
+    }
+
+    /// The calling thread's pool for `queue_family_index` if one has been created.
+    pub fn get(&self, queue_family_index: u32) -> Option<Arc<CommandPool>> {
+        let key = (thread::current().id(), queue_family_index);
+        self.lock_pools().get(&key).cloned()
+    }
+
+    /// Resets every pool created by the calling thread. Command buffers allocated from them must
+    /// not be pending.
+    pub fn reset_all(&self, reset_flags: vk::CommandPoolResetFlags) -> VkResult<()> {
+        let current_thread = thread::current().id();
+        for ((owner_thread, _), command_pool) in self.lock_pools().iter() {
+            if *owner_thread == current_thread {
+                command_pool.reset(reset_flags)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lock_pools(&self) -> MutexGuard<'_, HashMap<(ThreadId, u32), Arc<CommandPool>>> {
+        self.pools.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn flags(&self) -> vk::CommandPoolCreateFlags {
+        self.flags
+    }
+
+    /// Number of pools created so far by all threads.
+    pub fn len(&self) -> usize {
+        self.lock_pools().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock_pools().is_empty()
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
 // Properties
 
 /// Note: default value for `queue_family_index` is nothing!
@@ -152,6 +428,15 @@ impl CommandPoolProperties {
         }
     }
 
+    /// Command buffers allocated from a `PROTECTED` pool can only be submitted as protected
+    /// submissions to a protected capable queue. See [`Queue::submit_protected`](crate::Queue::submit_protected).
+    pub fn new_protected(queue_family_index: u32) -> Self {
+        Self {
+            flags: vk::CommandPoolCreateFlags::PROTECTED,
+            queue_family_index,
+        }
+    }
+
     pub fn write_create_info<'a>(
         &self,
         create_info: vk::CommandPoolCreateInfo<'a>,
@@ -161,7 +446,7 @@ impl CommandPoolProperties {
             .queue_family_index(self.queue_family_index)
     }
 
-    pub fn create_info(&self) -> vk::CommandPoolCreateInfo {
+    pub fn create_info(&self) -> vk::CommandPoolCreateInfo<'_> {
         self.write_create_info(vk::CommandPoolCreateInfo::default())
     }
 
@@ -174,3 +459,31 @@ impl CommandPoolProperties {
         }
     }
 }
+
+impl From<&vk::CommandPoolCreateInfo<'_>> for CommandPoolProperties {
+    fn from(value: &vk::CommandPoolCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn command_pool_owner_thread_check() {
+    let current_thread = thread::current().id();
+    check_owner_thread(Some(current_thread), current_thread, "record");
+    check_owner_thread(None, current_thread, "record");
+
+    let other_thread = thread::spawn(|| thread::current().id()).join().unwrap();
+    let res = std::panic::catch_unwind(|| {
+        check_owner_thread(Some(other_thread), current_thread, "record");
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn command_pool_queue_family_check() {
+    check_queue_family(1, 1);
+    let res = std::panic::catch_unwind(|| check_queue_family(0, 2));
+    assert!(res.is_err());
+}
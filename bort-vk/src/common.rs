@@ -16,3 +16,131 @@ pub fn is_format_srgb(format: vk::Format) -> bool {
 pub fn is_format_linear(format: vk::Format) -> bool {
     !is_format_srgb(format)
 }
+
+/// The sRGB format for a UNORM format and vice versa e.g. `B8G8R8A8_SRGB` for `B8G8R8A8_UNORM`.
+/// Views of a `MUTABLE_FORMAT` image (or swapchain) can use either format of the pair. Returns
+/// `None` for formats without a counterpart.
+pub fn srgb_unorm_pair(format: vk::Format) -> Option<vk::Format> {
+    let pairs = [
+        (vk::Format::R8_SRGB, vk::Format::R8_UNORM),
+        (vk::Format::R8G8_SRGB, vk::Format::R8G8_UNORM),
+        (vk::Format::R8G8B8_SRGB, vk::Format::R8G8B8_UNORM),
+        (vk::Format::B8G8R8_SRGB, vk::Format::B8G8R8_UNORM),
+        (vk::Format::R8G8B8A8_SRGB, vk::Format::R8G8B8A8_UNORM),
+        (vk::Format::B8G8R8A8_SRGB, vk::Format::B8G8R8A8_UNORM),
+        (
+            vk::Format::A8B8G8R8_SRGB_PACK32,
+            vk::Format::A8B8G8R8_UNORM_PACK32,
+        ),
+    ];
+    pairs.into_iter().find_map(|(srgb, unorm)| {
+        if format == srgb {
+            Some(unorm)
+        } else if format == unorm {
+            Some(srgb)
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether `format` has 10-bit color channels (with a 2-bit alpha channel) e.g. for banding-free
+/// swapchain output.
+pub fn is_format_10bit(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::A2R10G10B10_UNORM_PACK32
+            | vk::Format::A2R10G10B10_SNORM_PACK32
+            | vk::Format::A2R10G10B10_USCALED_PACK32
+            | vk::Format::A2R10G10B10_SSCALED_PACK32
+            | vk::Format::A2R10G10B10_UINT_PACK32
+            | vk::Format::A2R10G10B10_SINT_PACK32
+            | vk::Format::A2B10G10R10_UNORM_PACK32
+            | vk::Format::A2B10G10R10_SNORM_PACK32
+            | vk::Format::A2B10G10R10_USCALED_PACK32
+            | vk::Format::A2B10G10R10_SSCALED_PACK32
+            | vk::Format::A2B10G10R10_UINT_PACK32
+            | vk::Format::A2B10G10R10_SINT_PACK32
+    )
+}
+
+/// How the color components of a format are read in shaders and which `vk::ClearColorValue`
+/// member clears it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatNumericType {
+    /// Float, unorm, snorm, scaled and srgb formats. Read as floats in shaders.
+    Float,
+    UInt,
+    SInt,
+}
+
+/// The numeric type of the color components of `format`. Depth/stencil and unknown formats are
+/// treated as [`FormatNumericType::Float`].
+pub fn format_numeric_type(format: vk::Format) -> FormatNumericType {
+    match format {
+        vk::Format::R8_UINT
+        | vk::Format::R8G8_UINT
+        | vk::Format::R8G8B8_UINT
+        | vk::Format::B8G8R8_UINT
+        | vk::Format::R8G8B8A8_UINT
+        | vk::Format::B8G8R8A8_UINT
+        | vk::Format::A8B8G8R8_UINT_PACK32
+        | vk::Format::A2R10G10B10_UINT_PACK32
+        | vk::Format::A2B10G10R10_UINT_PACK32
+        | vk::Format::R16_UINT
+        | vk::Format::R16G16_UINT
+        | vk::Format::R16G16B16_UINT
+        | vk::Format::R16G16B16A16_UINT
+        | vk::Format::R32_UINT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32B32_UINT
+        | vk::Format::R32G32B32A32_UINT
+        | vk::Format::R64_UINT
+        | vk::Format::R64G64_UINT
+        | vk::Format::R64G64B64_UINT
+        | vk::Format::R64G64B64A64_UINT => FormatNumericType::UInt,
+
+        vk::Format::R8_SINT
+        | vk::Format::R8G8_SINT
+        | vk::Format::R8G8B8_SINT
+        | vk::Format::B8G8R8_SINT
+        | vk::Format::R8G8B8A8_SINT
+        | vk::Format::B8G8R8A8_SINT
+        | vk::Format::A8B8G8R8_SINT_PACK32
+        | vk::Format::A2R10G10B10_SINT_PACK32
+        | vk::Format::A2B10G10R10_SINT_PACK32
+        | vk::Format::R16_SINT
+        | vk::Format::R16G16_SINT
+        | vk::Format::R16G16B16_SINT
+        | vk::Format::R16G16B16A16_SINT
+        | vk::Format::R32_SINT
+        | vk::Format::R32G32_SINT
+        | vk::Format::R32G32B32_SINT
+        | vk::Format::R32G32B32A32_SINT
+        | vk::Format::R64_SINT
+        | vk::Format::R64G64_SINT
+        | vk::Format::R64G64B64_SINT
+        | vk::Format::R64G64B64A64_SINT => FormatNumericType::SInt,
+
+        _ => FormatNumericType::Float,
+    }
+}
+
+/// Whether a wrapper adopting an existing Vulkan handle (e.g. [`Device::from_handle`](crate::Device::from_handle))
+/// destroys it when dropped. Lets bort be introduced incrementally into code bases (or alongside
+/// middleware) that create their own objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HandleOwnership {
+    /// The wrapper destroys the handle (and frees its memory allocation if any) on drop.
+    #[default]
+    Owned,
+    /// The handle is destroyed by whoever created it and must outlive the wrapper.
+    Borrowed,
+}
+
+impl HandleOwnership {
+    #[inline]
+    pub fn is_owned(&self) -> bool {
+        *self == Self::Owned
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{
+    aspect_mask_from_format, default_subresource_layers, mip_level_extent, ImageDimensions,
+};
+use ash::vk;
+
+/// Block compressed format families. Each needs the corresponding
+/// `vk::PhysicalDeviceFeatures::texture_compression_*` feature, see e.g.
+/// [`PhysicalDevice::supports_bc`](crate::PhysicalDevice::supports_bc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFamily {
+    /// BC1-7 (a.k.a. DXT/S3TC, RGTC and BPTC). Common on desktop.
+    Bc,
+    /// ETC2 and EAC. Common on mobile.
+    Etc2,
+    /// ASTC with unorm/srgb formats. Common on mobile.
+    AstcLdr,
+    /// ASTC with sfloat formats. Requires `VK_EXT_texture_compression_astc_hdr` (core in 1.3).
+    AstcHdr,
+}
+
+/// Size info of the blocks of a block compressed format. Compressed images are addressed in whole
+/// blocks so texel extents and `vk::BufferImageCopy` row lengths need to be rounded up to the
+/// block extent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedBlockInfo {
+    pub family: CompressionFamily,
+    /// Texel width and height of each block.
+    pub block_extent: vk::Extent2D,
+    /// Size in bytes of each block.
+    pub block_size: u32,
+}
+
+impl CompressedBlockInfo {
+    /// Returns `None` if `format` isn't a BC, ETC2/EAC or ASTC format.
+    pub fn from_format(format: vk::Format) -> Option<Self> {
+        let bc = |block_size| Self::new(CompressionFamily::Bc, [4, 4], block_size);
+        let etc2 = |block_size| Self::new(CompressionFamily::Etc2, [4, 4], block_size);
+
+        let block_info = match format {
+            vk::Format::BC1_RGB_UNORM_BLOCK
+            | vk::Format::BC1_RGB_SRGB_BLOCK
+            | vk::Format::BC1_RGBA_UNORM_BLOCK
+            | vk::Format::BC1_RGBA_SRGB_BLOCK
+            | vk::Format::BC4_UNORM_BLOCK
+            | vk::Format::BC4_SNORM_BLOCK => bc(8),
+            vk::Format::BC2_UNORM_BLOCK
+            | vk::Format::BC2_SRGB_BLOCK
+            | vk::Format::BC3_UNORM_BLOCK
+            | vk::Format::BC3_SRGB_BLOCK
+            | vk::Format::BC5_UNORM_BLOCK
+            | vk::Format::BC5_SNORM_BLOCK
+            | vk::Format::BC6H_UFLOAT_BLOCK
+            | vk::Format::BC6H_SFLOAT_BLOCK
+            | vk::Format::BC7_UNORM_BLOCK
+            | vk::Format::BC7_SRGB_BLOCK => bc(16),
+
+            vk::Format::ETC2_R8G8B8_UNORM_BLOCK
+            | vk::Format::ETC2_R8G8B8_SRGB_BLOCK
+            | vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK
+            | vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK
+            | vk::Format::EAC_R11_UNORM_BLOCK
+            | vk::Format::EAC_R11_SNORM_BLOCK => etc2(8),
+            vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK
+            | vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK
+            | vk::Format::EAC_R11G11_UNORM_BLOCK
+            | vk::Format::EAC_R11G11_SNORM_BLOCK => etc2(16),
+
+            _ => return astc_block_info(format),
+        };
+        Some(block_info)
+    }
+
+    fn new(family: CompressionFamily, [width, height]: [u32; 2], block_size: u32) -> Self {
+        Self {
+            family,
+            block_extent: vk::Extent2D { width, height },
+            block_size,
+        }
+    }
+
+    /// The number of blocks covering `extent` texels in each dimension. Depth isn't blocked.
+    pub fn block_count_3d(&self, extent: vk::Extent3D) -> [u32; 3] {
+        [
+            extent.width.div_ceil(self.block_extent.width),
+            extent.height.div_ceil(self.block_extent.height),
+            extent.depth,
+        ]
+    }
+
+    /// Size in bytes of tightly packed block data covering `extent` texels.
+    pub fn data_size(&self, extent: vk::Extent3D) -> vk::DeviceSize {
+        let [blocks_wide, blocks_high, depth] = self.block_count_3d(extent);
+        blocks_wide as vk::DeviceSize
+            * blocks_high as vk::DeviceSize
+            * depth as vk::DeviceSize
+            * self.block_size as vk::DeviceSize
+    }
+
+    /// `texel_width` rounded up to a multiple of the block width. Use for
+    /// `vk::BufferImageCopy::buffer_row_length` which must be a multiple of the block width for
+    /// compressed formats.
+    #[inline]
+    pub fn aligned_row_length(&self, texel_width: u32) -> u32 {
+        texel_width.next_multiple_of(self.block_extent.width)
+    }
+
+    /// `texel_height` rounded up to a multiple of the block height. Use for
+    /// `vk::BufferImageCopy::buffer_image_height`.
+    #[inline]
+    pub fn aligned_image_height(&self, texel_height: u32) -> u32 {
+        texel_height.next_multiple_of(self.block_extent.height)
+    }
+
+    /// Uncompressed format with the same texel size as a block: `R32G32_UINT` for 8 byte blocks
+    /// and `R32G32B32A32_UINT` for 16 byte blocks. Each texel of a view with this format of an
+    /// image created with `BLOCK_TEXEL_VIEW_COMPATIBLE` is one block, so compute shaders can write
+    /// encoded blocks through it. See
+    /// [`ImageProperties::new_block_texel_view_compatible`](crate::ImageProperties::new_block_texel_view_compatible).
+    pub fn block_texel_view_format(&self) -> vk::Format {
+        if self.block_size == 8 {
+            vk::Format::R32G32_UINT
+        } else {
+            vk::Format::R32G32B32A32_UINT
+        }
+    }
+}
+
+pub fn is_format_compressed(format: vk::Format) -> bool {
+    CompressedBlockInfo::from_format(format).is_some()
+}
+
+/// Size in bytes of `mip_level` (all array layers) of a compressed image with `format` and
+/// `dimensions`. Returns `None` if `format` isn't compressed.
+pub fn compressed_mip_level_size(
+    format: vk::Format,
+    dimensions: ImageDimensions,
+    mip_level: u32,
+) -> Option<vk::DeviceSize> {
+    let block_info = CompressedBlockInfo::from_format(format)?;
+    let level_extent = mip_level_extent(dimensions, mip_level);
+    Some(block_info.data_size(level_extent) * dimensions.array_layers() as vk::DeviceSize)
+}
+
+/// Extent in texels (i.e. blocks) of a block texel view of `mip_level` of a compressed image
+/// with `format` and `dimensions` e.g. for sizing the dispatch of a compression shader. Mip levels
+/// smaller than a block still have one block. Returns `None` if `format` isn't compressed.
+pub fn block_texel_view_extent(
+    format: vk::Format,
+    dimensions: ImageDimensions,
+    mip_level: u32,
+) -> Option<vk::Extent3D> {
+    let block_info = CompressedBlockInfo::from_format(format)?;
+    let [width, height, depth] = block_info.block_count_3d(mip_level_extent(dimensions, mip_level));
+    Some(vk::Extent3D {
+        width,
+        height,
+        depth,
+    })
+}
+
+/// Copy regions for uploading `mip_levels` mip levels (all array layers) of a compressed image
+/// from a buffer containing each level tightly packed one after the other, starting with level 0.
+/// Also returns the total data size. Returns `None` if `format` isn't compressed.
+///
+/// Row lengths and image heights are rounded up to whole blocks so mip levels smaller than a
+/// block (e.g. 2x2 for BC) are handled correctly.
+pub fn compressed_mip_chain_copy_regions(
+    format: vk::Format,
+    dimensions: ImageDimensions,
+    mip_levels: u32,
+) -> Option<(Vec<vk::BufferImageCopy>, vk::DeviceSize)> {
+    let block_info = CompressedBlockInfo::from_format(format)?;
+    let aspect_mask = aspect_mask_from_format(format);
+
+    let mut buffer_offset: vk::DeviceSize = 0;
+    let copy_regions = (0..mip_levels)
+        .map(|mip_level| {
+            let level_extent = mip_level_extent(dimensions, mip_level);
+            let copy_region = vk::BufferImageCopy {
+                buffer_offset,
+                buffer_row_length: block_info.aligned_row_length(level_extent.width),
+                buffer_image_height: block_info.aligned_image_height(level_extent.height),
+                image_subresource: vk::ImageSubresourceLayers {
+                    mip_level,
+                    layer_count: dimensions.array_layers(),
+                    ..default_subresource_layers(aspect_mask)
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: level_extent,
+            };
+            buffer_offset +=
+                block_info.data_size(level_extent) * dimensions.array_layers() as vk::DeviceSize;
+            copy_region
+        })
+        .collect();
+
+    Some((copy_regions, buffer_offset))
+}
+
+// Helper Functions
+
+/// ASTC block extents in `vk::Format` order. Each has a unorm and srgb format (in that order)
+/// and a sfloat format.
+const ASTC_BLOCK_EXTENTS: [[u32; 2]; 14] = [
+    [4, 4],
+    [5, 4],
+    [5, 5],
+    [6, 5],
+    [6, 6],
+    [8, 5],
+    [8, 6],
+    [8, 8],
+    [10, 5],
+    [10, 6],
+    [10, 8],
+    [10, 10],
+    [12, 10],
+    [12, 12],
+];
+
+fn astc_block_info(format: vk::Format) -> Option<CompressedBlockInfo> {
+    const ASTC_BLOCK_SIZE: u32 = 16;
+    let raw = format.as_raw();
+
+    let ldr_first = vk::Format::ASTC_4X4_UNORM_BLOCK.as_raw();
+    let ldr_last = vk::Format::ASTC_12X12_SRGB_BLOCK.as_raw();
+    if (ldr_first..=ldr_last).contains(&raw) {
+        let block_extent = ASTC_BLOCK_EXTENTS[(raw - ldr_first) as usize / 2];
+        return Some(CompressedBlockInfo::new(
+            CompressionFamily::AstcLdr,
+            block_extent,
+            ASTC_BLOCK_SIZE,
+        ));
+    }
+
+    let hdr_first = vk::Format::ASTC_4X4_SFLOAT_BLOCK.as_raw();
+    let hdr_last = vk::Format::ASTC_12X12_SFLOAT_BLOCK.as_raw();
+    if (hdr_first..=hdr_last).contains(&raw) {
+        let block_extent = ASTC_BLOCK_EXTENTS[(raw - hdr_first) as usize];
+        return Some(CompressedBlockInfo::new(
+            CompressionFamily::AstcHdr,
+            block_extent,
+            ASTC_BLOCK_SIZE,
+        ));
+    }
+
+    None
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn compressed_block_info_from_format() {
+    let bc1 = CompressedBlockInfo::from_format(vk::Format::BC1_RGBA_SRGB_BLOCK).unwrap();
+    assert_eq!(bc1.family, CompressionFamily::Bc);
+    assert_eq!(bc1.block_size, 8);
+
+    let astc_10x8 = CompressedBlockInfo::from_format(vk::Format::ASTC_10X8_SRGB_BLOCK).unwrap();
+    assert_eq!(astc_10x8.family, CompressionFamily::AstcLdr);
+    assert_eq!(
+        astc_10x8.block_extent,
+        vk::Extent2D {
+            width: 10,
+            height: 8
+        }
+    );
+
+    let astc_hdr = CompressedBlockInfo::from_format(vk::Format::ASTC_6X5_SFLOAT_BLOCK).unwrap();
+    assert_eq!(astc_hdr.family, CompressionFamily::AstcHdr);
+    assert_eq!(
+        astc_hdr.block_extent,
+        vk::Extent2D {
+            width: 6,
+            height: 5
+        }
+    );
+
+    assert!(is_format_compressed(vk::Format::EAC_R11G11_SNORM_BLOCK));
+    assert!(!is_format_compressed(vk::Format::R8G8B8A8_UNORM));
+
+    assert_eq!(bc1.block_texel_view_format(), vk::Format::R32G32_UINT);
+    assert_eq!(
+        astc_10x8.block_texel_view_format(),
+        vk::Format::R32G32B32A32_UINT
+    );
+}
+
+#[test]
+fn block_texel_view_dimensions() {
+    // 100x60 BC7: level 0 is 25x15 blocks, level 1 (50x30) is 13x8 blocks, level 6 (1x1) is 1
+    let dimensions = ImageDimensions::new_2d(100, 60);
+    let level_0 = block_texel_view_extent(vk::Format::BC7_UNORM_BLOCK, dimensions, 0).unwrap();
+    assert_eq!((level_0.width, level_0.height, level_0.depth), (25, 15, 1));
+    let level_1 = block_texel_view_extent(vk::Format::BC7_UNORM_BLOCK, dimensions, 1).unwrap();
+    assert_eq!((level_1.width, level_1.height), (13, 8));
+    let level_6 = block_texel_view_extent(vk::Format::BC7_UNORM_BLOCK, dimensions, 6).unwrap();
+    assert_eq!((level_6.width, level_6.height), (1, 1));
+    assert!(block_texel_view_extent(vk::Format::R8G8B8A8_UNORM, dimensions, 0).is_none());
+
+    let properties = crate::ImageProperties::new_block_texel_view_compatible(
+        vk::Format::BC7_SRGB_BLOCK,
+        dimensions,
+        vk::ImageUsageFlags::SAMPLED,
+    )
+    .unwrap();
+    assert!(properties.flags.contains(
+        vk::ImageCreateFlags::BLOCK_TEXEL_VIEW_COMPATIBLE | vk::ImageCreateFlags::EXTENDED_USAGE
+    ));
+    assert!(properties.usage.contains(vk::ImageUsageFlags::STORAGE));
+
+    let view = crate::ImageViewProperties::new_block_texel_view(&properties, 1).unwrap();
+    assert_eq!(view.format, vk::Format::R32G32B32A32_UINT);
+    assert!(properties.supports_view_format(view.format));
+    assert_eq!(view.subresource_range.base_mip_level, 1);
+    assert_eq!(view.subresource_range.level_count, 1);
+}
+
+#[test]
+fn compressed_mip_chain_copy_regions_round_up_to_blocks() {
+    // 10x6 BC7: level 0 is 3x2 blocks, level 1 (5x3) is 2x1 blocks, level 2 (2x1) is 1 block
+    let dimensions = ImageDimensions::new_2d(10, 6);
+    let (copy_regions, total_size) =
+        compressed_mip_chain_copy_regions(vk::Format::BC7_UNORM_BLOCK, dimensions, 3).unwrap();
+
+    assert_eq!(copy_regions[0].buffer_row_length, 12);
+    assert_eq!(copy_regions[0].buffer_image_height, 8);
+    assert_eq!(copy_regions[1].buffer_offset, 6 * 16);
+    assert_eq!(copy_regions[1].buffer_row_length, 8);
+    assert_eq!(copy_regions[2].buffer_offset, (6 + 2) * 16);
+    assert_eq!(
+        copy_regions[2].image_extent,
+        vk::Extent3D {
+            width: 2,
+            height: 1,
+            depth: 1
+        }
+    );
+    assert_eq!(total_size, (6 + 2 + 1) * 16);
+    assert_eq!(
+        compressed_mip_level_size(vk::Format::BC7_UNORM_BLOCK, dimensions, 1),
+        Some(2 * 16)
+    );
+}
@@ -0,0 +1,549 @@
+use crate::{
+    allocation_info_device_local, AllocationError, AllocatorAccess, Buffer, BufferProperties,
+    CommandBuffer, ComputePipeline, ComputePipelineProperties, DescriptorPool,
+    DescriptorPoolProperties, DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device, DeviceOwned, PipelineAccess,
+    PipelineLayout, PipelineLayoutProperties, ShaderError, ShaderModule, ShaderStage,
+};
+use ash::vk;
+use std::{error, fmt, io::Cursor, mem, sync::Arc};
+
+/// Spir-V compiled from `shaders/bin_lights.comp`.
+const BIN_LIGHTS_SPIRV: &[u8] = include_bytes!("./shaders/bin_lights.comp.spv");
+/// Must match the `local_size_x/y/z` of `shaders/bin_lights.comp`.
+const BIN_LIGHTS_WORKGROUP_SIZE: u32 = 4;
+/// Size of the push constants of `shaders/bin_lights.comp`.
+const BIN_LIGHTS_PUSH_CONSTANTS_SIZE: usize = 9 * 4;
+const LIGHT_BOUNDS_STRIDE: vk::DeviceSize = mem::size_of::<[f32; 4]>() as vk::DeviceSize;
+const BIN_LIGHTS_BINDING_COUNT: u32 = 3;
+
+// ~~ Light Binning ~~
+
+/// Dimensions of the cluster grid a [`LightBinningPass`] bins lights into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightBinningProperties {
+    /// Number of clusters in screen x, screen y and depth.
+    pub grid_size: [u32; 3],
+    /// Lights touching a cluster beyond this are dropped.
+    pub max_lights_per_cluster: u32,
+}
+
+impl LightBinningProperties {
+    /// Grid of `tile_size` x `tile_size` pixel tiles covering `screen_extent` with `depth_slices`
+    /// depth slices.
+    pub fn new_for_screen(
+        screen_extent: vk::Extent2D,
+        tile_size: u32,
+        depth_slices: u32,
+        max_lights_per_cluster: u32,
+    ) -> Self {
+        let tile_size = tile_size.max(1);
+        Self {
+            grid_size: [
+                screen_extent.width.div_ceil(tile_size).max(1),
+                screen_extent.height.div_ceil(tile_size).max(1),
+                depth_slices.max(1),
+            ],
+            max_lights_per_cluster,
+        }
+    }
+
+    #[inline]
+    pub fn cluster_count(&self) -> u32 {
+        self.grid_size[0] * self.grid_size[1] * self.grid_size[2]
+    }
+
+    /// Index of a cluster in the light grid buffer. Shaders reading the grid should use the same
+    /// formula.
+    #[inline]
+    pub fn cluster_index(&self, cluster: [u32; 3]) -> u32 {
+        cluster[0] + self.grid_size[0] * (cluster[1] + self.grid_size[1] * cluster[2])
+    }
+
+    /// Workgroup counts for the binning dispatch.
+    pub fn dispatch_size(&self) -> [u32; 3] {
+        self.grid_size
+            .map(|dimension| dimension.div_ceil(BIN_LIGHTS_WORKGROUP_SIZE))
+    }
+}
+
+/// Perspective view frustum the cluster grid is fitted to. View space is x right, y down, z
+/// forward (matching vulkan clip space).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterFrustum {
+    /// Tangent of half the horizontal and vertical field of view.
+    pub tan_half_fov: [f32; 2],
+    pub near: f32,
+    pub far: f32,
+}
+
+impl ClusterFrustum {
+    /// `fov_y` is the vertical field of view in radians and `aspect_ratio` is width / height.
+    pub fn new_perspective(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let tan_half_fov_y = (fov_y * 0.5).tan();
+        Self {
+            tan_half_fov: [tan_half_fov_y * aspect_ratio, tan_half_fov_y],
+            near,
+            far,
+        }
+    }
+
+    /// The depth slice containing view space `depth`, matching the exponential slicing of the
+    /// binning shader. Use the same formula in shading shaders to look up the cluster of a
+    /// fragment. Returns `None` if `depth` is outside of `near..far`.
+    pub fn depth_slice(&self, depth: f32, depth_slices: u32) -> Option<u32> {
+        if depth < self.near || depth >= self.far {
+            return None;
+        }
+        let slice = (depth / self.near).ln() / (self.far / self.near).ln() * depth_slices as f32;
+        Some((slice as u32).min(depth_slices - 1))
+    }
+
+    /// View space bounds (min, max) of `cluster`, matching the binning shader.
+    pub fn cluster_bounds(
+        &self,
+        properties: &LightBinningProperties,
+        cluster: [u32; 3],
+    ) -> ([f32; 3], [f32; 3]) {
+        let [grid_x, grid_y, grid_z] = properties.grid_size;
+        let depth_ratio = self.far / self.near;
+        let depth_near = self.near * depth_ratio.powf(cluster[2] as f32 / grid_z as f32);
+        let depth_far = self.near * depth_ratio.powf((cluster[2] + 1) as f32 / grid_z as f32);
+
+        let edge = |index: u32, grid: u32, tan_half_fov: f32| {
+            (index as f32 / grid as f32 * 2. - 1.) * tan_half_fov
+        };
+        let x0 = edge(cluster[0], grid_x, self.tan_half_fov[0]);
+        let x1 = edge(cluster[0] + 1, grid_x, self.tan_half_fov[0]);
+        let y0 = edge(cluster[1], grid_y, self.tan_half_fov[1]);
+        let y1 = edge(cluster[1] + 1, grid_y, self.tan_half_fov[1]);
+
+        (
+            [
+                (x0 * depth_near).min(x0 * depth_far),
+                (y0 * depth_near).min(y0 * depth_far),
+                depth_near,
+            ],
+            [
+                (x1 * depth_near).max(x1 * depth_far),
+                (y1 * depth_near).max(y1 * depth_far),
+                depth_far,
+            ],
+        )
+    }
+}
+
+/// A compute pass binning lights into a 3D grid of view space clusters for forward+ (clustered)
+/// shading. For each cluster, the indices of the lights whose bounding spheres touch it are
+/// written to a light index buffer and the count to a light grid buffer:
+/// - light grid: a `u32` light count per cluster, see [`LightBinningProperties::cluster_index`].
+/// - light indices: `max_lights_per_cluster` `u32` light indices per cluster, starting at
+///   `cluster_index * max_lights_per_cluster`.
+///
+/// Usage:
+/// 1. create [`LightBinningBindings`] with [`Self::create_bindings`] for a buffer of light
+///    bounding spheres.
+/// 2. call [`Self::record_binning`] outside of a render pass each frame after updating the light
+///    bounds.
+/// 3. bind [`LightBinningBindings::light_grid`] and [`LightBinningBindings::light_indices`] as
+///    storage buffers in shading passes.
+pub struct LightBinningPass {
+    pipeline: ComputePipeline,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    descriptor_pool: Arc<DescriptorPool>,
+}
+
+impl LightBinningPass {
+    /// `max_bindings` is the number of [`LightBinningBindings`] which can be alive at once.
+    pub fn new(device: Arc<Device>, max_bindings: u32) -> Result<Self, ComputePassError> {
+        let bindings = (0..BIN_LIGHTS_BINDING_COUNT)
+            .map(|binding| DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            })
+            .collect();
+        let descriptor_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(bindings),
+            )
+            .map_err(ComputePassError::DescriptorSetLayout)?,
+        );
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: BIN_LIGHTS_PUSH_CONSTANTS_SIZE as u32,
+        };
+        let pipeline_layout = Arc::new(
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutProperties::new(
+                    vec![descriptor_set_layout.clone()],
+                    vec![push_constant_range],
+                ),
+            )
+            .map_err(ComputePassError::Vulkan)?,
+        );
+
+        let shader_module = Arc::new(
+            ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(BIN_LIGHTS_SPIRV))
+                .map_err(ComputePassError::Shader)?,
+        );
+        let shader_stage = ShaderStage::compute(shader_module).map_err(ComputePassError::Shader)?;
+        let pipeline = ComputePipeline::new(
+            pipeline_layout,
+            ComputePipelineProperties::default(),
+            &shader_stage,
+            None,
+        )
+        .map_err(ComputePassError::Vulkan)?;
+
+        let max_sets = max_bindings.max(1);
+        let descriptor_pool_properties = DescriptorPoolProperties {
+            flags: vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+            max_sets,
+            pool_sizes: vec![vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: max_sets * BIN_LIGHTS_BINDING_COUNT,
+            }],
+        };
+        let descriptor_pool = Arc::new(
+            DescriptorPool::new(device, descriptor_pool_properties)
+                .map_err(ComputePassError::Vulkan)?,
+        );
+
+        Ok(Self {
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+        })
+    }
+
+    /// Creates the (device local) light grid and light index buffers for a cluster grid with
+    /// `properties`, and a descriptor set binding them with `light_bounds`: a `[f32; 4]` view
+    /// space bounding sphere (xyz = center, w = radius) per light. `light_bounds` requires
+    /// `STORAGE_BUFFER` usage.
+    ///
+    /// `additional_usage` is added to the usage of the output buffers (which have
+    /// `STORAGE_BUFFER` usage).
+    pub fn create_bindings(
+        &self,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        light_bounds: Arc<Buffer>,
+        properties: LightBinningProperties,
+        additional_usage: vk::BufferUsageFlags,
+    ) -> Result<LightBinningBindings, ComputePassError> {
+        let cluster_count = properties.cluster_count() as vk::DeviceSize;
+        let index_size = mem::size_of::<u32>() as vk::DeviceSize;
+        let usage = vk::BufferUsageFlags::STORAGE_BUFFER | additional_usage;
+
+        let light_grid = Buffer::new(
+            alloc_access.clone(),
+            BufferProperties::new_default(cluster_count * index_size, usage),
+            allocation_info_device_local(),
+        )
+        .map_err(ComputePassError::BufferCreation)?;
+        let light_indices_size =
+            cluster_count * properties.max_lights_per_cluster.max(1) as vk::DeviceSize * index_size;
+        let light_indices = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(light_indices_size, usage),
+            allocation_info_device_local(),
+        )
+        .map_err(ComputePassError::BufferCreation)?;
+
+        let descriptor_set = self
+            .descriptor_pool
+            .allocate_descriptor_set(self.descriptor_set_layout.clone())
+            .map_err(ComputePassError::Vulkan)?;
+
+        let buffer_infos = [light_bounds.as_ref(), &light_grid, &light_indices].map(|buffer| {
+            [vk::DescriptorBufferInfo {
+                buffer: buffer.handle(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }]
+        });
+        let descriptor_writes = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer_info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set.handle())
+                    .dst_binding(binding as u32)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(buffer_info)
+            });
+        self.device().update_descriptor_sets(descriptor_writes, []);
+
+        Ok(LightBinningBindings {
+            descriptor_set,
+            light_bounds,
+            light_grid,
+            light_indices,
+            properties,
+        })
+    }
+
+    /// Records the binning dispatch for the first `light_count` lights and the barriers around
+    /// it. Must be recorded outside of a render pass.
+    ///
+    /// Waits for previous fragment/compute shader reads of the outputs on the same queue and
+    /// makes the results visible to later fragment and compute shaders.
+    pub fn record_binning(
+        &self,
+        command_buffer: &CommandBuffer,
+        bindings: &LightBinningBindings,
+        frustum: &ClusterFrustum,
+        light_count: u32,
+    ) {
+        debug_assert!(
+            light_count as vk::DeviceSize * LIGHT_BOUNDS_STRIDE
+                <= bindings.light_bounds.properties().size,
+            "light count {} exceeds the light bounds buffer size",
+            light_count
+        );
+        let shader_stages =
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER;
+
+        // previous reads of the outputs must finish before they're overwritten
+        command_buffer.pipeline_barrier(
+            shader_stages,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[],
+        );
+
+        self.pipeline.record_dispatch(
+            command_buffer,
+            &bindings.descriptor_set,
+            &bin_lights_push_constant_bytes(&bindings.properties, frustum, light_count),
+            bindings.properties.dispatch_size(),
+        );
+
+        let binning_barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ);
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            shader_stages,
+            vk::DependencyFlags::empty(),
+            &[binning_barrier],
+            &[],
+            &[],
+        );
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+
+    #[inline]
+    pub fn descriptor_set_layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.descriptor_set_layout
+    }
+}
+
+impl DeviceOwned for LightBinningPass {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pipeline.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.pipeline.handle_raw()
+    }
+}
+
+/// The light bounds a [`LightBinningPass`] reads and the light grid and index list it writes for
+/// shading passes to read. Typically re-binned every frame, so keep the bindings alive while any
+/// command buffer that bins into them or shades from them is still pending.
+pub struct LightBinningBindings {
+    descriptor_set: DescriptorSet,
+    light_bounds: Arc<Buffer>,
+    light_grid: Buffer,
+    light_indices: Buffer,
+    properties: LightBinningProperties,
+}
+
+impl LightBinningBindings {
+    // Getters
+
+    #[inline]
+    pub fn descriptor_set(&self) -> &DescriptorSet {
+        &self.descriptor_set
+    }
+
+    #[inline]
+    pub fn light_bounds(&self) -> &Arc<Buffer> {
+        &self.light_bounds
+    }
+
+    /// A `u32` light count per cluster.
+    #[inline]
+    pub fn light_grid(&self) -> &Buffer {
+        &self.light_grid
+    }
+
+    /// `max_lights_per_cluster` `u32` light indices per cluster.
+    #[inline]
+    pub fn light_indices(&self) -> &Buffer {
+        &self.light_indices
+    }
+
+    #[inline]
+    pub fn properties(&self) -> LightBinningProperties {
+        self.properties
+    }
+}
+
+impl ComputePipeline {
+    /// Binds this pipeline and `descriptor_set` as set 0, pushes `push_constant_bytes` to the
+    /// compute stage at offset 0 and dispatches `group_count` workgroups. The recording shared by
+    /// the built-in compute passes, which each use one set and one push constant range.
+    pub(crate) fn record_dispatch(
+        &self,
+        command_buffer: &CommandBuffer,
+        descriptor_set: &DescriptorSet,
+        push_constant_bytes: &[u8],
+        group_count: [u32; 3],
+    ) {
+        let pipeline_layout = self.pipeline_layout();
+        command_buffer.bind_pipeline(self);
+        command_buffer.bind_descriptor_sets(
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline_layout,
+            0,
+            [descriptor_set],
+            &[],
+        );
+        command_buffer.push_constants(
+            pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            push_constant_bytes,
+        );
+        let [group_count_x, group_count_y, group_count_z] = group_count;
+        command_buffer.dispatch(group_count_x, group_count_y, group_count_z);
+    }
+}
+
+// Helper Functions
+
+fn bin_lights_push_constant_bytes(
+    properties: &LightBinningProperties,
+    frustum: &ClusterFrustum,
+    light_count: u32,
+) -> Vec<u8> {
+    let [grid_x, grid_y, grid_z] = properties.grid_size;
+    let mut bytes = Vec::with_capacity(BIN_LIGHTS_PUSH_CONSTANTS_SIZE);
+    for value in [grid_x, grid_y, grid_z, light_count] {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+    for value in [
+        frustum.tan_half_fov[0],
+        frustum.tan_half_fov[1],
+        frustum.near,
+        frustum.far,
+    ] {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+    bytes.extend_from_slice(&properties.max_lights_per_cluster.to_ne_bytes());
+    bytes
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum ComputePassError {
+    Shader(ShaderError),
+    DescriptorSetLayout(DescriptorSetLayoutError),
+    Vulkan(vk::Result),
+    BufferCreation(AllocationError),
+}
+
+impl fmt::Display for ComputePassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shader(e) => write!(f, "failed to create compute pass shader: {}", e),
+            Self::Vulkan(e) => write!(f, "compute pass vulkan call failed: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create compute pass buffer: {}", e),
+            Self::DescriptorSetLayout(e) => write!(
+                f,
+                "compute pass descriptor set layout creation failed: {}",
+                e
+            ),
+        }
+    }
+}
+
+impl error::Error for ComputePassError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Shader(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::DescriptorSetLayout(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn light_binning_grid_and_cluster_bounds() {
+    let properties = LightBinningProperties::new_for_screen(
+        vk::Extent2D {
+            width: 1920,
+            height: 1080,
+        },
+        64,
+        24,
+        128,
+    );
+    assert_eq!(properties.grid_size, [30, 17, 24]);
+    assert_eq!(properties.dispatch_size(), [8, 5, 6]);
+    assert_eq!(properties.cluster_index([1, 1, 1]), 1 + 30 * (1 + 17));
+
+    let frustum = ClusterFrustum {
+        tan_half_fov: [1., 1.],
+        near: 1.,
+        far: 16.,
+    };
+    let grid = LightBinningProperties {
+        grid_size: [2, 2, 4],
+        max_lights_per_cluster: 8,
+    };
+    // depth slices are 1..2, 2..4, 4..8, 8..16
+    let (bounds_min, bounds_max) = frustum.cluster_bounds(&grid, [1, 0, 2]);
+    assert!((bounds_min[2] - 4.).abs() < 1e-5);
+    assert!((bounds_max[2] - 8.).abs() < 1e-5);
+    assert!((bounds_min[0] - 0.).abs() < 1e-5);
+    assert!((bounds_max[0] - 8.).abs() < 1e-5);
+    assert!((bounds_min[1] + 8.).abs() < 1e-5);
+    assert_eq!(frustum.depth_slice(5., 4), Some(2));
+    assert_eq!(frustum.depth_slice(0.5, 4), None);
+
+    assert_eq!(
+        bin_lights_push_constant_bytes(&grid, &frustum, 3).len(),
+        BIN_LIGHTS_PUSH_CONSTANTS_SIZE
+    );
+}
+
+#[test]
+fn bin_lights_spirv_has_compute_entry_point() {
+    let spirv = ash::util::read_spv(&mut Cursor::new(BIN_LIGHTS_SPIRV)).unwrap();
+    let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+    assert_eq!(entry_points.len(), 1);
+    assert_eq!(entry_points[0].stage, vk::ShaderStageFlags::COMPUTE);
+}
@@ -0,0 +1,159 @@
+use ash::vk;
+use std::mem;
+
+/// A matrix multiply-accumulate configuration (`A * B + C`) supported by
+/// `VK_KHR_cooperative_matrix`. `A` is `m_size` x `k_size`, `B` is `k_size` x `n_size` and `C` and
+/// the result are `m_size` x `n_size`. See
+/// [`PhysicalDevice::cooperative_matrix_configurations`](crate::PhysicalDevice::cooperative_matrix_configurations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CooperativeMatrixConfiguration {
+    pub m_size: u32,
+    pub n_size: u32,
+    pub k_size: u32,
+    pub a_type: vk::ComponentTypeKHR,
+    pub b_type: vk::ComponentTypeKHR,
+    pub c_type: vk::ComponentTypeKHR,
+    pub result_type: vk::ComponentTypeKHR,
+    pub saturating_accumulation: bool,
+    pub scope: vk::ScopeKHR,
+}
+
+impl CooperativeMatrixConfiguration {
+    /// Whether the operand and result component types match e.g. `(FLOAT16, FLOAT16, FLOAT32,
+    /// FLOAT32)` for half precision inputs with single precision accumulation.
+    pub fn has_types(
+        &self,
+        a_type: vk::ComponentTypeKHR,
+        b_type: vk::ComponentTypeKHR,
+        c_type: vk::ComponentTypeKHR,
+        result_type: vk::ComponentTypeKHR,
+    ) -> bool {
+        self.a_type == a_type
+            && self.b_type == b_type
+            && self.c_type == c_type
+            && self.result_type == result_type
+    }
+
+    /// The tile size of this configuration to pass to shaders as specialization constants.
+    #[inline]
+    pub fn tile_size(&self) -> CooperativeMatrixTileSize {
+        CooperativeMatrixTileSize {
+            m: self.m_size,
+            n: self.n_size,
+            k: self.k_size,
+        }
+    }
+}
+
+impl From<&vk::CooperativeMatrixPropertiesKHR<'_>> for CooperativeMatrixConfiguration {
+    fn from(properties: &vk::CooperativeMatrixPropertiesKHR<'_>) -> Self {
+        Self {
+            m_size: properties.m_size,
+            n_size: properties.n_size,
+            k_size: properties.k_size,
+            a_type: properties.a_type,
+            b_type: properties.b_type,
+            c_type: properties.c_type,
+            result_type: properties.result_type,
+            saturating_accumulation: properties.saturating_accumulation == vk::TRUE,
+            scope: properties.scope,
+        }
+    }
+}
+
+/// The subgroup scoped configuration with the given component types and the largest tile
+/// (`m_size * n_size * k_size`). `None` if no configuration matches.
+pub fn select_cooperative_matrix_configuration(
+    configurations: &[CooperativeMatrixConfiguration],
+    a_type: vk::ComponentTypeKHR,
+    b_type: vk::ComponentTypeKHR,
+    c_type: vk::ComponentTypeKHR,
+    result_type: vk::ComponentTypeKHR,
+) -> Option<CooperativeMatrixConfiguration> {
+    configurations
+        .iter()
+        .filter(|configuration| {
+            configuration.scope == vk::ScopeKHR::SUBGROUP
+                && configuration.has_types(a_type, b_type, c_type, result_type)
+        })
+        .max_by_key(|configuration| {
+            configuration.m_size as u64 * configuration.n_size as u64 * configuration.k_size as u64
+        })
+        .copied()
+}
+
+/// M, N and K tile dimensions written as consecutive `uint` specialization constants so shaders
+/// can declare e.g. `layout(constant_id = 0) const uint TILE_M = 16;` and be specialized for the
+/// configuration picked at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CooperativeMatrixTileSize {
+    pub m: u32,
+    pub n: u32,
+    pub k: u32,
+}
+
+impl CooperativeMatrixTileSize {
+    /// Map entries for constant ids `first_constant_id`, `first_constant_id + 1` and
+    /// `first_constant_id + 2` (M, N, K) and the data they point into. The result matches
+    /// [`RetainedShaderStage::specialization`](crate::RetainedShaderStage::specialization); use
+    /// `vk::SpecializationInfo::default().map_entries(&entries).data(&data)` for a
+    /// [`ShaderStage`](crate::ShaderStage).
+    pub fn specialization(
+        &self,
+        first_constant_id: u32,
+    ) -> (Vec<vk::SpecializationMapEntry>, Vec<u8>) {
+        let size = mem::size_of::<u32>();
+        let map_entries = (0..3)
+            .map(|index| vk::SpecializationMapEntry {
+                constant_id: first_constant_id + index,
+                offset: index * size as u32,
+                size,
+            })
+            .collect();
+        let data = [self.m, self.n, self.k]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        (map_entries, data)
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn cooperative_matrix_selection_and_specialization() {
+    let configuration = |m_size, scope, a_type| CooperativeMatrixConfiguration {
+        m_size,
+        n_size: 16,
+        k_size: 16,
+        a_type,
+        b_type: vk::ComponentTypeKHR::FLOAT16,
+        c_type: vk::ComponentTypeKHR::FLOAT32,
+        result_type: vk::ComponentTypeKHR::FLOAT32,
+        saturating_accumulation: false,
+        scope,
+    };
+    let configurations = [
+        configuration(8, vk::ScopeKHR::SUBGROUP, vk::ComponentTypeKHR::FLOAT16),
+        configuration(16, vk::ScopeKHR::SUBGROUP, vk::ComponentTypeKHR::FLOAT16),
+        configuration(32, vk::ScopeKHR::WORKGROUP, vk::ComponentTypeKHR::FLOAT16),
+        configuration(64, vk::ScopeKHR::SUBGROUP, vk::ComponentTypeKHR::SINT8),
+    ];
+    let selected = select_cooperative_matrix_configuration(
+        &configurations,
+        vk::ComponentTypeKHR::FLOAT16,
+        vk::ComponentTypeKHR::FLOAT16,
+        vk::ComponentTypeKHR::FLOAT32,
+        vk::ComponentTypeKHR::FLOAT32,
+    )
+    .unwrap();
+    assert_eq!(selected.m_size, 16);
+
+    let (map_entries, data) = selected.tile_size().specialization(4);
+    assert_eq!(map_entries.len(), 3);
+    assert_eq!(map_entries[2].constant_id, 6);
+    assert_eq!(map_entries[2].offset, 8);
+    assert_eq!(map_entries[2].size, 4);
+    assert_eq!(data.len(), 12);
+    assert_eq!(data[0..4], 16_u32.to_ne_bytes());
+}
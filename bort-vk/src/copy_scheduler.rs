@@ -0,0 +1,369 @@
+use crate::{Buffer, CommandBuffer};
+use ash::vk;
+use std::{collections::BTreeMap, error, fmt, sync::Arc};
+
+/// Collects buffer copies issued during a frame and records them as as few `vkCmdCopyBuffer`
+/// calls as possible: one call per source/destination buffer pair with regions sorted by
+/// destination offset and contiguous regions merged. A single memory barrier is recorded after
+/// all the copies.
+///
+/// If a copy is scheduled to exactly the same destination range as an existing one (from any
+/// source buffer), the latest replaces the previous (last write wins). Partially overlapping
+/// destination ranges are rejected, including those scheduled from different source buffers,
+/// because the order of overlapping writes within or between the copy commands is undefined.
+pub struct CopyScheduler {
+    batches: Vec<CopyBatch>,
+    dst_stage_mask: vk::PipelineStageFlags,
+    dst_access_mask: vk::AccessFlags,
+}
+
+struct CopyBatch {
+    src_buffer: Arc<Buffer>,
+    dst_buffer: Arc<Buffer>,
+    /// dst offset -> (src offset, size)
+    regions: BTreeMap<vk::DeviceSize, (vk::DeviceSize, vk::DeviceSize)>,
+}
+
+impl CopyScheduler {
+    /// `dst_stage_mask` and `dst_access_mask` describe the commands that will consume the copied
+    /// data and are used for the barrier recorded after the copies.
+    pub fn new(dst_stage_mask: vk::PipelineStageFlags, dst_access_mask: vk::AccessFlags) -> Self {
+        Self {
+            batches: Vec::new(),
+            dst_stage_mask,
+            dst_access_mask,
+        }
+    }
+
+    /// Copied data is made available to all subsequent commands.
+    pub fn new_default() -> Self {
+        Self::new(
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+        )
+    }
+
+    /// Schedules a copy of `region` from `src_buffer` to `dst_buffer`. Nothing is recorded until
+    /// [`Self::record`] is called.
+    pub fn schedule_copy(
+        &mut self,
+        src_buffer: &Arc<Buffer>,
+        dst_buffer: &Arc<Buffer>,
+        region: vk::BufferCopy,
+    ) -> Result<(), CopySchedulerError> {
+        if src_buffer.handle() == dst_buffer.handle() {
+            return Err(CopySchedulerError::SameSrcAndDstBuffer);
+        }
+        if region.size == 0 {
+            return Ok(());
+        }
+        check_region_bounds(region.src_offset, region.size, src_buffer)?;
+        check_region_bounds(region.dst_offset, region.size, dst_buffer)?;
+
+        // check against every batch writing to `dst_buffer` because separate copy commands to
+        // the same memory aren't ordered either
+        let dst_handle = dst_buffer.handle();
+        let mut replaced_batch_index = None;
+        for (batch_index, batch) in self.batches.iter().enumerate() {
+            if batch.dst_buffer.handle() != dst_handle {
+                continue;
+            }
+            match dst_overlap(&batch.regions, region.dst_offset, region.size) {
+                DstOverlap::None => {}
+                DstOverlap::Exact => replaced_batch_index = Some(batch_index),
+                DstOverlap::Partial => {
+                    return Err(CopySchedulerError::OverlappingDstRegion {
+                        dst_offset: region.dst_offset,
+                        size: region.size,
+                    })
+                }
+            }
+        }
+
+        // exact destination range match: last write wins
+        if let Some(batch_index) = replaced_batch_index {
+            self.batches[batch_index].regions.remove(&region.dst_offset);
+            self.batches.retain(|batch| !batch.regions.is_empty());
+        }
+
+        let batch = self.batch_mut(src_buffer, dst_buffer);
+        batch
+            .regions
+            .insert(region.dst_offset, (region.src_offset, region.size));
+        Ok(())
+    }
+
+    /// Records all scheduled copies followed by a single memory barrier from the transfer writes
+    /// to `dst_stage_mask`/`dst_access_mask`. Clears the scheduled copies so this can be reused
+    /// for the next frame. Records nothing if no copies were scheduled.
+    ///
+    /// Returns the number of `vkCmdCopyBuffer` regions recorded after coalescing.
+    pub fn record(&mut self, command_buffer: &CommandBuffer) -> usize {
+        let mut recorded_region_count = 0_usize;
+
+        for batch in self.batches.drain(..) {
+            let sorted_regions: Vec<vk::BufferCopy> = batch
+                .regions
+                .iter()
+                .map(|(&dst_offset, &(src_offset, size))| vk::BufferCopy {
+                    src_offset,
+                    dst_offset,
+                    size,
+                })
+                .collect();
+            let coalesced_regions = coalesce_buffer_copies(&sorted_regions);
+            recorded_region_count += coalesced_regions.len();
+
+            command_buffer.copy_buffer(&*batch.src_buffer, &*batch.dst_buffer, &coalesced_regions);
+        }
+
+        if recorded_region_count > 0 {
+            let memory_barrier = vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(self.dst_access_mask);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                self.dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        }
+
+        recorded_region_count
+    }
+
+    /// Discards all scheduled copies without recording them.
+    pub fn clear(&mut self) {
+        self.batches.clear();
+    }
+
+    fn batch_mut(&mut self, src_buffer: &Arc<Buffer>, dst_buffer: &Arc<Buffer>) -> &mut CopyBatch {
+        let batch_index = self.batches.iter().position(|batch| {
+            batch.src_buffer.handle() == src_buffer.handle()
+                && batch.dst_buffer.handle() == dst_buffer.handle()
+        });
+
+        let batch_index = batch_index.unwrap_or_else(|| {
+            self.batches.push(CopyBatch {
+                src_buffer: src_buffer.clone(),
+                dst_buffer: dst_buffer.clone(),
+                regions: BTreeMap::new(),
+            });
+            self.batches.len() - 1
+        });
+
+        &mut self.batches[batch_index]
+    }
+
+    // Getters
+
+    /// Number of distinct regions currently scheduled (before coalescing).
+    pub fn scheduled_region_count(&self) -> usize {
+        self.batches.iter().map(|batch| batch.regions.len()).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    #[inline]
+    pub fn dst_stage_mask(&self) -> vk::PipelineStageFlags {
+        self.dst_stage_mask
+    }
+
+    #[inline]
+    pub fn dst_access_mask(&self) -> vk::AccessFlags {
+        self.dst_access_mask
+    }
+}
+
+impl Default for CopyScheduler {
+    fn default() -> Self {
+        Self::new_default()
+    }
+}
+
+// Helper Functions
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DstOverlap {
+    None,
+    /// A region with the same destination offset and size.
+    Exact,
+    Partial,
+}
+
+/// How the destination range `dst_offset..dst_offset + size` overlaps the (non-overlapping)
+/// destination ranges of `regions`.
+fn dst_overlap(
+    regions: &BTreeMap<vk::DeviceSize, (vk::DeviceSize, vk::DeviceSize)>,
+    dst_offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+) -> DstOverlap {
+    if regions
+        .get(&dst_offset)
+        .map(|&(_, existing_size)| existing_size)
+        == Some(size)
+    {
+        return DstOverlap::Exact;
+    }
+
+    let dst_end = dst_offset + size;
+    let overlaps_previous = regions
+        .range(..=dst_offset)
+        .next_back()
+        .map(|(&existing_offset, &(_, existing_size))| existing_offset + existing_size > dst_offset)
+        .unwrap_or(false);
+    let overlaps_next = regions
+        .range(dst_offset..)
+        .next()
+        .map(|(&existing_offset, _)| existing_offset < dst_end)
+        .unwrap_or(false);
+    if overlaps_previous || overlaps_next {
+        DstOverlap::Partial
+    } else {
+        DstOverlap::None
+    }
+}
+
+/// Sorts `regions` by destination offset and merges regions that are contiguous in both the
+/// source and destination buffers.
+pub fn coalesce_buffer_copies(regions: &[vk::BufferCopy]) -> Vec<vk::BufferCopy> {
+    let mut sorted_regions = regions.to_vec();
+    sorted_regions.sort_by_key(|region| region.dst_offset);
+
+    let mut coalesced_regions = Vec::<vk::BufferCopy>::with_capacity(sorted_regions.len());
+    for region in sorted_regions {
+        if let Some(last) = coalesced_regions.last_mut() {
+            let contiguous = last.src_offset + last.size == region.src_offset
+                && last.dst_offset + last.size == region.dst_offset;
+            if contiguous {
+                last.size += region.size;
+                continue;
+            }
+        }
+        coalesced_regions.push(region);
+    }
+
+    coalesced_regions
+}
+
+fn check_region_bounds(
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    buffer: &Buffer,
+) -> Result<(), CopySchedulerError> {
+    let buffer_size = buffer.properties().size;
+    let in_bounds = offset
+        .checked_add(size)
+        .map(|end| end <= buffer_size)
+        .unwrap_or(false);
+    if !in_bounds {
+        return Err(CopySchedulerError::RegionOutOfBounds {
+            offset,
+            size,
+            buffer_size,
+        });
+    }
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum CopySchedulerError {
+    SameSrcAndDstBuffer,
+    RegionOutOfBounds {
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        buffer_size: vk::DeviceSize,
+    },
+    OverlappingDstRegion {
+        dst_offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    },
+}
+
+impl fmt::Display for CopySchedulerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SameSrcAndDstBuffer => write!(
+                f,
+                "copies within the same buffer aren't supported by the copy scheduler"
+            ),
+            Self::RegionOutOfBounds {
+                offset,
+                size,
+                buffer_size,
+            } => write!(
+                f,
+                "copy region (offset = {}, size = {}) is outside of the buffer size {}",
+                offset, size, buffer_size
+            ),
+            Self::OverlappingDstRegion { dst_offset, size } => write!(
+                f,
+                "copy destination region (offset = {}, size = {}) partially overlaps a previously scheduled copy",
+                dst_offset, size
+            ),
+        }
+    }
+}
+
+impl error::Error for CopySchedulerError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn coalesce_buffer_copies_merges_contiguous() {
+    let regions = [
+        vk::BufferCopy {
+            src_offset: 16,
+            dst_offset: 116,
+            size: 16,
+        },
+        vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 100,
+            size: 16,
+        },
+        vk::BufferCopy {
+            src_offset: 64,
+            dst_offset: 132,
+            size: 8,
+        },
+    ];
+    let coalesced = coalesce_buffer_copies(&regions);
+    assert_eq!(coalesced.len(), 2);
+    assert_eq!(
+        (
+            coalesced[0].src_offset,
+            coalesced[0].dst_offset,
+            coalesced[0].size
+        ),
+        (0, 100, 32)
+    );
+    assert_eq!(
+        (
+            coalesced[1].src_offset,
+            coalesced[1].dst_offset,
+            coalesced[1].size
+        ),
+        (64, 132, 8)
+    );
+}
+
+#[test]
+fn dst_overlap_kinds() {
+    let mut regions = BTreeMap::new();
+    regions.insert(100, (0, 16));
+    regions.insert(132, (64, 8));
+    assert_eq!(dst_overlap(&regions, 100, 16), DstOverlap::Exact);
+    assert_eq!(dst_overlap(&regions, 116, 16), DstOverlap::None);
+    assert_eq!(dst_overlap(&regions, 108, 16), DstOverlap::Partial);
+    assert_eq!(dst_overlap(&regions, 100, 8), DstOverlap::Partial);
+    assert_eq!(dst_overlap(&regions, 90, 50), DstOverlap::Partial);
+    assert_eq!(dst_overlap(&regions, 140, 4), DstOverlap::None);
+}
@@ -0,0 +1,694 @@
+//! Host side checks for buffer/image copies and blits. Mistakes like copying at an offset which
+//! isn't a multiple of the queue family's `minImageTransferGranularity`, or with the wrong aspect
+//! mask, tend to produce driver dependent corruption rather than a clear error.
+//!
+//! Used by [`CommandBuffer::copy_buffer_to_image_checked`](crate::CommandBuffer::copy_buffer_to_image_checked)
+//! and [`CommandBuffer::blit_image_checked`](crate::CommandBuffer::blit_image_checked).
+
+use crate::{
+    aspect_mask_from_format, format_texel_size, mip_level_extent, CompressedBlockInfo,
+    ImageProperties,
+};
+use ash::vk;
+use std::{error, fmt};
+
+/// Checks a `vk::BufferImageCopy` region against `image_properties` and the
+/// `minImageTransferGranularity` of the queue family the copy is recorded for.
+pub fn validate_buffer_image_copy(
+    image_properties: &ImageProperties,
+    transfer_granularity: vk::Extent3D,
+    region: &vk::BufferImageCopy,
+) -> Result<(), CopyValidationError> {
+    let subresource = region.image_subresource;
+    check_single_aspect(image_properties.format, subresource.aspect_mask)?;
+    check_subresource(image_properties, &subresource)?;
+    check_linear_tiling(image_properties, &subresource)?;
+
+    let level_extent = mip_level_extent(image_properties.dimensions, subresource.mip_level);
+    check_region_bounds(region.image_offset, region.image_extent, level_extent)?;
+
+    if (region.buffer_row_length != 0 && region.buffer_row_length < region.image_extent.width)
+        || (region.buffer_image_height != 0
+            && region.buffer_image_height < region.image_extent.height)
+    {
+        return Err(CopyValidationError::BufferRowLengthTooSmall {
+            buffer_row_length: region.buffer_row_length,
+            buffer_image_height: region.buffer_image_height,
+            image_extent: region.image_extent,
+        });
+    }
+    check_buffer_row_pitch(
+        image_properties.format,
+        subresource.aspect_mask,
+        region.buffer_row_length,
+        region.buffer_image_height,
+    )?;
+
+    let offset_alignment =
+        buffer_offset_alignment(image_properties.format, subresource.aspect_mask);
+    if !region.buffer_offset.is_multiple_of(offset_alignment) {
+        return Err(CopyValidationError::MisalignedBufferOffset {
+            buffer_offset: region.buffer_offset,
+            required_alignment: offset_alignment,
+        });
+    }
+
+    check_transfer_granularity(
+        image_properties.format,
+        transfer_granularity,
+        region.image_offset,
+        region.image_extent,
+        level_extent,
+    )
+}
+
+/// Checks a `vk::ImageBlit` region between images with `src_properties` and `dst_properties`
+/// using `filter`.
+pub fn validate_image_blit(
+    src_properties: &ImageProperties,
+    dst_properties: &ImageProperties,
+    region: &vk::ImageBlit,
+    filter: vk::Filter,
+) -> Result<(), CopyValidationError> {
+    for properties in [src_properties, dst_properties] {
+        if CompressedBlockInfo::from_format(properties.format).is_some() {
+            return Err(CopyValidationError::BlitCompressedFormat(properties.format));
+        }
+        if properties.samples != vk::SampleCountFlags::TYPE_1 {
+            return Err(CopyValidationError::BlitMultisampled(properties.samples));
+        }
+    }
+
+    let src_subresource = region.src_subresource;
+    let dst_subresource = region.dst_subresource;
+    if src_subresource.aspect_mask != dst_subresource.aspect_mask {
+        return Err(CopyValidationError::AspectMaskMismatch {
+            src_aspect_mask: src_subresource.aspect_mask,
+            dst_aspect_mask: dst_subresource.aspect_mask,
+        });
+    }
+    check_aspect_in_format(src_properties.format, src_subresource.aspect_mask)?;
+    check_aspect_in_format(dst_properties.format, dst_subresource.aspect_mask)?;
+
+    let depth_stencil = vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+    if src_subresource.aspect_mask.intersects(depth_stencil) && filter != vk::Filter::NEAREST {
+        return Err(CopyValidationError::DepthStencilBlitFilter(filter));
+    }
+
+    check_subresource(src_properties, &src_subresource)?;
+    check_subresource(dst_properties, &dst_subresource)?;
+    check_linear_tiling(src_properties, &src_subresource)?;
+    check_linear_tiling(dst_properties, &dst_subresource)?;
+
+    let src_extent = mip_level_extent(src_properties.dimensions, src_subresource.mip_level);
+    let dst_extent = mip_level_extent(dst_properties.dimensions, dst_subresource.mip_level);
+    check_blit_bounds(region.src_offsets, src_extent)?;
+    check_blit_bounds(region.dst_offsets, dst_extent)?;
+
+    Ok(())
+}
+
+/// Checks that `src_format_features` and `dst_format_features` (the format features of the
+/// source and destination images for their tiling) support blitting with `filter`.
+pub fn validate_blit_format_features(
+    src_format: vk::Format,
+    src_format_features: vk::FormatFeatureFlags,
+    dst_format: vk::Format,
+    dst_format_features: vk::FormatFeatureFlags,
+    filter: vk::Filter,
+) -> Result<(), CopyValidationError> {
+    let mut src_required_features = vk::FormatFeatureFlags::BLIT_SRC;
+    match filter {
+        vk::Filter::LINEAR => {
+            src_required_features |= vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+        }
+        vk::Filter::CUBIC_EXT => {
+            src_required_features |= vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_CUBIC_EXT
+        }
+        _ => (),
+    }
+    let checks = [
+        (src_format, src_format_features, src_required_features),
+        (
+            dst_format,
+            dst_format_features,
+            vk::FormatFeatureFlags::BLIT_DST,
+        ),
+    ];
+    for (format, format_features, required_features) in checks {
+        if !format_features.contains(required_features) {
+            return Err(CopyValidationError::BlitFormatFeatures {
+                format,
+                required_features,
+                format_features,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks `image_offset` and `image_extent` against `transfer_granularity` (as described by
+/// `vk::QueueFamilyProperties::min_image_transfer_granularity`) for a subresource of
+/// `level_extent`. Granularity is in texel blocks for compressed formats.
+pub fn check_transfer_granularity(
+    format: vk::Format,
+    transfer_granularity: vk::Extent3D,
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+    level_extent: vk::Extent3D,
+) -> Result<(), CopyValidationError> {
+    let granularity_error = CopyValidationError::TransferGranularity {
+        image_offset,
+        image_extent,
+        transfer_granularity,
+    };
+
+    let is_zero_granularity = transfer_granularity.width == 0
+        && transfer_granularity.height == 0
+        && transfer_granularity.depth == 0;
+    if is_zero_granularity {
+        // only whole subresources can be transferred
+        let whole_subresource = image_offset == vk::Offset3D::default()
+            && image_extent.width == level_extent.width
+            && image_extent.height == level_extent.height
+            && image_extent.depth == level_extent.depth;
+        return if whole_subresource {
+            Ok(())
+        } else {
+            Err(granularity_error)
+        };
+    }
+
+    let block_extent = CompressedBlockInfo::from_format(format)
+        .map(|block_info| block_info.block_extent)
+        .unwrap_or(vk::Extent2D {
+            width: 1,
+            height: 1,
+        });
+    let axes = [
+        (
+            image_offset.x,
+            image_extent.width,
+            level_extent.width,
+            transfer_granularity.width * block_extent.width,
+        ),
+        (
+            image_offset.y,
+            image_extent.height,
+            level_extent.height,
+            transfer_granularity.height * block_extent.height,
+        ),
+        (
+            image_offset.z,
+            image_extent.depth,
+            level_extent.depth,
+            transfer_granularity.depth,
+        ),
+    ];
+    for (offset, extent, level_size, granularity) in axes {
+        let offset = offset as u32;
+        let offset_aligned = granularity == 0 || offset.is_multiple_of(granularity);
+        let extent_aligned =
+            granularity == 0 || extent.is_multiple_of(granularity) || offset + extent == level_size;
+        if !offset_aligned || !extent_aligned {
+            return Err(granularity_error);
+        }
+    }
+
+    Ok(())
+}
+
+// Helper Functions
+
+fn check_aspect_in_format(
+    format: vk::Format,
+    aspect_mask: vk::ImageAspectFlags,
+) -> Result<(), CopyValidationError> {
+    if aspect_mask.is_empty() || !aspect_mask_from_format(format).contains(aspect_mask) {
+        return Err(CopyValidationError::InvalidAspectMask {
+            aspect_mask,
+            format,
+        });
+    }
+    Ok(())
+}
+
+/// Buffer/image copies must specify exactly one aspect.
+fn check_single_aspect(
+    format: vk::Format,
+    aspect_mask: vk::ImageAspectFlags,
+) -> Result<(), CopyValidationError> {
+    check_aspect_in_format(format, aspect_mask)?;
+    if aspect_mask.as_raw().count_ones() != 1 {
+        return Err(CopyValidationError::InvalidAspectMask {
+            aspect_mask,
+            format,
+        });
+    }
+    Ok(())
+}
+
+fn check_subresource(
+    image_properties: &ImageProperties,
+    subresource: &vk::ImageSubresourceLayers,
+) -> Result<(), CopyValidationError> {
+    let array_layers = image_properties.dimensions.array_layers();
+    let layers_in_range = if subresource.layer_count == vk::REMAINING_ARRAY_LAYERS {
+        subresource.base_array_layer < array_layers
+    } else {
+        subresource
+            .base_array_layer
+            .checked_add(subresource.layer_count)
+            .is_some_and(|layer_end| layer_end <= array_layers)
+    };
+    if subresource.mip_level >= image_properties.mip_levels || !layers_in_range {
+        return Err(CopyValidationError::SubresourceOutOfRange {
+            subresource: *subresource,
+            mip_levels: image_properties.mip_levels,
+            array_layers,
+        });
+    }
+    Ok(())
+}
+
+/// Linear tiling is only guaranteed to be supported for single mip, single layer color images.
+fn check_linear_tiling(
+    image_properties: &ImageProperties,
+    subresource: &vk::ImageSubresourceLayers,
+) -> Result<(), CopyValidationError> {
+    if image_properties.tiling != vk::ImageTiling::LINEAR {
+        return Ok(());
+    }
+    let supported = subresource.aspect_mask == vk::ImageAspectFlags::COLOR
+        && subresource.mip_level == 0
+        && subresource.base_array_layer == 0
+        && subresource.layer_count == 1;
+    if !supported {
+        return Err(CopyValidationError::LinearTilingSubresource(*subresource));
+    }
+    Ok(())
+}
+
+fn check_region_bounds(
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+    level_extent: vk::Extent3D,
+) -> Result<(), CopyValidationError> {
+    let in_bounds = |offset: i32, extent: u32, level_size: u32| {
+        offset >= 0 && offset as u64 + extent as u64 <= level_size as u64
+    };
+    let all_in_bounds = in_bounds(image_offset.x, image_extent.width, level_extent.width)
+        && in_bounds(image_offset.y, image_extent.height, level_extent.height)
+        && in_bounds(image_offset.z, image_extent.depth, level_extent.depth);
+    if !all_in_bounds {
+        return Err(CopyValidationError::RegionOutOfBounds {
+            image_offset,
+            image_extent,
+            level_extent,
+        });
+    }
+    Ok(())
+}
+
+fn check_blit_bounds(
+    offsets: [vk::Offset3D; 2],
+    level_extent: vk::Extent3D,
+) -> Result<(), CopyValidationError> {
+    let in_bounds = |offset: i32, level_size: u32| offset >= 0 && offset as u32 <= level_size;
+    let all_in_bounds = offsets.iter().all(|offset| {
+        in_bounds(offset.x, level_extent.width)
+            && in_bounds(offset.y, level_extent.height)
+            && in_bounds(offset.z, level_extent.depth)
+    });
+    if !all_in_bounds {
+        return Err(CopyValidationError::BlitOutOfBounds {
+            offsets,
+            level_extent,
+        });
+    }
+    Ok(())
+}
+
+/// Compressed formats need `buffer_row_length` and `buffer_image_height` to be multiples of the
+/// block extent, and the row pitch in bytes must fit in an `i32`.
+fn check_buffer_row_pitch(
+    format: vk::Format,
+    aspect_mask: vk::ImageAspectFlags,
+    buffer_row_length: u32,
+    buffer_image_height: u32,
+) -> Result<(), CopyValidationError> {
+    let block_info = CompressedBlockInfo::from_format(format);
+    if let Some(block_info) = block_info {
+        let block_extent = block_info.block_extent;
+        if !buffer_row_length.is_multiple_of(block_extent.width)
+            || !buffer_image_height.is_multiple_of(block_extent.height)
+        {
+            return Err(CopyValidationError::BufferRowLengthNotBlockAligned {
+                buffer_row_length,
+                buffer_image_height,
+                block_extent,
+            });
+        }
+    }
+
+    let row_pitch = match block_info {
+        Some(block_info) => {
+            (buffer_row_length / block_info.block_extent.width) as u64
+                * block_info.block_size as u64
+        }
+        None => buffer_row_length as u64 * buffer_offset_alignment(format, aspect_mask),
+    };
+    if row_pitch > i32::MAX as u64 {
+        return Err(CopyValidationError::BufferRowPitchTooLarge { row_pitch });
+    }
+    Ok(())
+}
+
+/// Depth/stencil copies need 4 byte aligned buffer offsets, other formats need texel block size
+/// aligned offsets. Formats with an unknown texel size (e.g. multi-planar) aren't checked.
+#[inline]
+fn buffer_offset_alignment(
+    format: vk::Format,
+    aspect_mask: vk::ImageAspectFlags,
+) -> vk::DeviceSize {
+    if aspect_mask.intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL) {
+        return 4;
+    }
+    let texel_block_size = match CompressedBlockInfo::from_format(format) {
+        Some(block_info) => Some(block_info.block_size),
+        None => format_texel_size(format),
+    };
+    texel_block_size.unwrap_or(1) as vk::DeviceSize
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum CopyValidationError {
+    /// The aspect mask is empty, isn't part of the format, or (for buffer/image copies) has more
+    /// than one aspect.
+    InvalidAspectMask {
+        aspect_mask: vk::ImageAspectFlags,
+        format: vk::Format,
+    },
+    AspectMaskMismatch {
+        src_aspect_mask: vk::ImageAspectFlags,
+        dst_aspect_mask: vk::ImageAspectFlags,
+    },
+    SubresourceOutOfRange {
+        subresource: vk::ImageSubresourceLayers,
+        mip_levels: u32,
+        array_layers: u32,
+    },
+    /// Linear tiled images are only guaranteed to support a single mip level and array layer of
+    /// color formats.
+    LinearTilingSubresource(vk::ImageSubresourceLayers),
+    RegionOutOfBounds {
+        image_offset: vk::Offset3D,
+        image_extent: vk::Extent3D,
+        level_extent: vk::Extent3D,
+    },
+    BlitOutOfBounds {
+        offsets: [vk::Offset3D; 2],
+        level_extent: vk::Extent3D,
+    },
+    BufferRowLengthTooSmall {
+        buffer_row_length: u32,
+        buffer_image_height: u32,
+        image_extent: vk::Extent3D,
+    },
+    /// Compressed formats need `buffer_row_length` and `buffer_image_height` to be multiples of
+    /// the block extent.
+    BufferRowLengthNotBlockAligned {
+        buffer_row_length: u32,
+        buffer_image_height: u32,
+        block_extent: vk::Extent2D,
+    },
+    /// The size in bytes of a buffer row must be at most `i32::MAX`.
+    BufferRowPitchTooLarge {
+        row_pitch: u64,
+    },
+    MisalignedBufferOffset {
+        buffer_offset: vk::DeviceSize,
+        required_alignment: vk::DeviceSize,
+    },
+    /// The region doesn't respect the `minImageTransferGranularity` of the queue family.
+    TransferGranularity {
+        image_offset: vk::Offset3D,
+        image_extent: vk::Extent3D,
+        transfer_granularity: vk::Extent3D,
+    },
+    BlitCompressedFormat(vk::Format),
+    BlitFormatFeatures {
+        format: vk::Format,
+        required_features: vk::FormatFeatureFlags,
+        format_features: vk::FormatFeatureFlags,
+    },
+    BlitMultisampled(vk::SampleCountFlags),
+    /// Depth/stencil blits must use `vk::Filter::NEAREST`.
+    DepthStencilBlitFilter(vk::Filter),
+}
+
+impl fmt::Display for CopyValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAspectMask {
+                aspect_mask,
+                format,
+            } => write!(
+                f,
+                "aspect mask {:?} is invalid for format {:?} (buffer/image copies need exactly one aspect)",
+                aspect_mask, format
+            ),
+            Self::AspectMaskMismatch {
+                src_aspect_mask,
+                dst_aspect_mask,
+            } => write!(
+                f,
+                "source aspect mask {:?} doesn't match destination aspect mask {:?}",
+                src_aspect_mask, dst_aspect_mask
+            ),
+            Self::SubresourceOutOfRange {
+                subresource,
+                mip_levels,
+                array_layers,
+            } => write!(
+                f,
+                "subresource {:?} is out of range for an image with {} mip levels and {} array layers",
+                subresource, mip_levels, array_layers
+            ),
+            Self::LinearTilingSubresource(subresource) => write!(
+                f,
+                "subresource {:?} of a linear tiled image may be unsupported. linear images are only guaranteed to support a single mip level and array layer of color formats",
+                subresource
+            ),
+            Self::RegionOutOfBounds {
+                image_offset,
+                image_extent,
+                level_extent,
+            } => write!(
+                f,
+                "copy region (offset {:?}, extent {:?}) is out of bounds for mip level extent {:?}",
+                image_offset, image_extent, level_extent
+            ),
+            Self::BlitOutOfBounds {
+                offsets,
+                level_extent,
+            } => write!(
+                f,
+                "blit offsets {:?} are out of bounds for mip level extent {:?}",
+                offsets, level_extent
+            ),
+            Self::BufferRowLengthTooSmall {
+                buffer_row_length,
+                buffer_image_height,
+                image_extent,
+            } => write!(
+                f,
+                "buffer row length {} / image height {} must be 0 or at least the image extent {:?}",
+                buffer_row_length, buffer_image_height, image_extent
+            ),
+            Self::BufferRowLengthNotBlockAligned {
+                buffer_row_length,
+                buffer_image_height,
+                block_extent,
+            } => write!(
+                f,
+                "buffer row length {} / image height {} must be multiples of the compressed block extent {:?}",
+                buffer_row_length, buffer_image_height, block_extent
+            ),
+            Self::BufferRowPitchTooLarge { row_pitch } => write!(
+                f,
+                "buffer row pitch of {} bytes is larger than 2^31 - 1",
+                row_pitch
+            ),
+            Self::MisalignedBufferOffset {
+                buffer_offset,
+                required_alignment,
+            } => write!(
+                f,
+                "buffer offset {} must be a multiple of {}",
+                buffer_offset, required_alignment
+            ),
+            Self::TransferGranularity {
+                image_offset,
+                image_extent,
+                transfer_granularity,
+            } => write!(
+                f,
+                "copy region (offset {:?}, extent {:?}) doesn't respect the queue family's minImageTransferGranularity {:?}",
+                image_offset, image_extent, transfer_granularity
+            ),
+            Self::BlitCompressedFormat(format) => {
+                write!(f, "compressed format {:?} can't be blitted", format)
+            }
+            Self::BlitFormatFeatures {
+                format,
+                required_features,
+                format_features,
+            } => write!(
+                f,
+                "format {:?} with features {:?} doesn't support {:?} required for this blit",
+                format, format_features, required_features
+            ),
+            Self::BlitMultisampled(samples) => write!(
+                f,
+                "multisampled images ({:?}) can't be blitted. resolve them instead",
+                samples
+            ),
+            Self::DepthStencilBlitFilter(filter) => write!(
+                f,
+                "depth/stencil blits must use a nearest filter, not {:?}",
+                filter
+            ),
+        }
+    }
+}
+
+impl error::Error for CopyValidationError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn buffer_image_copy_validation() {
+    use crate::{default_subresource_layers, ImageDimensions};
+
+    let image_properties = ImageProperties::new_default(
+        vk::Format::D32_SFLOAT_S8_UINT,
+        ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::TRANSFER_DST,
+    );
+    let region = vk::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: default_subresource_layers(vk::ImageAspectFlags::DEPTH),
+        image_offset: vk::Offset3D::default(),
+        image_extent: vk::Extent3D {
+            width: 64,
+            height: 64,
+            depth: 1,
+        },
+    };
+    let granularity = vk::Extent3D {
+        width: 16,
+        height: 16,
+        depth: 1,
+    };
+    assert!(validate_buffer_image_copy(&image_properties, granularity, &region).is_ok());
+
+    let both_aspects = vk::BufferImageCopy {
+        image_subresource: default_subresource_layers(
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+        ),
+        ..region
+    };
+    assert!(matches!(
+        validate_buffer_image_copy(&image_properties, granularity, &both_aspects),
+        Err(CopyValidationError::InvalidAspectMask { .. })
+    ));
+
+    let misaligned = vk::BufferImageCopy {
+        image_offset: vk::Offset3D { x: 8, y: 0, z: 0 },
+        image_extent: vk::Extent3D {
+            width: 16,
+            height: 16,
+            depth: 1,
+        },
+        ..region
+    };
+    assert!(matches!(
+        validate_buffer_image_copy(&image_properties, granularity, &misaligned),
+        Err(CopyValidationError::TransferGranularity { .. })
+    ));
+
+    // zero granularity only allows whole subresources
+    let zero_granularity = vk::Extent3D::default();
+    assert!(validate_buffer_image_copy(&image_properties, zero_granularity, &region).is_ok());
+    let partial = vk::BufferImageCopy {
+        image_extent: vk::Extent3D {
+            width: 32,
+            height: 64,
+            depth: 1,
+        },
+        ..region
+    };
+    assert!(validate_buffer_image_copy(&image_properties, zero_granularity, &partial).is_err());
+
+    let remaining_layers = vk::BufferImageCopy {
+        image_subresource: vk::ImageSubresourceLayers {
+            base_array_layer: 1,
+            layer_count: vk::REMAINING_ARRAY_LAYERS,
+            ..region.image_subresource
+        },
+        ..region
+    };
+    assert!(matches!(
+        validate_buffer_image_copy(&image_properties, granularity, &remaining_layers),
+        Err(CopyValidationError::SubresourceOutOfRange { .. })
+    ));
+
+    let color_properties = ImageProperties::new_default(
+        vk::Format::R8G8B8A8_UNORM,
+        ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::TRANSFER_DST,
+    );
+    let color_region = vk::BufferImageCopy {
+        buffer_offset: 2,
+        image_subresource: default_subresource_layers(vk::ImageAspectFlags::COLOR),
+        ..region
+    };
+    assert!(matches!(
+        validate_buffer_image_copy(&color_properties, granularity, &color_region),
+        Err(CopyValidationError::MisalignedBufferOffset {
+            required_alignment: 4,
+            ..
+        })
+    ));
+
+    let bc1_properties = ImageProperties::new_default(
+        vk::Format::BC1_RGBA_UNORM_BLOCK,
+        ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::TRANSFER_DST,
+    );
+    let unaligned_row_length = vk::BufferImageCopy {
+        buffer_row_length: 66,
+        image_subresource: default_subresource_layers(vk::ImageAspectFlags::COLOR),
+        ..region
+    };
+    assert!(matches!(
+        validate_buffer_image_copy(&bc1_properties, granularity, &unaligned_row_length),
+        Err(CopyValidationError::BufferRowLengthNotBlockAligned { .. })
+    ));
+
+    assert!(matches!(
+        validate_blit_format_features(
+            vk::Format::R8G8B8A8_UNORM,
+            vk::FormatFeatureFlags::BLIT_SRC,
+            vk::Format::R8G8B8A8_UNORM,
+            vk::FormatFeatureFlags::BLIT_DST,
+            vk::Filter::LINEAR,
+        ),
+        Err(CopyValidationError::BlitFormatFeatures { .. })
+    ));
+}
@@ -0,0 +1,111 @@
+use ash::{prelude::VkResult, vk};
+use std::time::{Duration, Instant};
+
+/// When a blocking call should give up waiting. Durations are converted to a deadline relative to
+/// now so that a single deadline can bound a sequence of waits e.g. for a watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Deadline {
+    At(Instant),
+    Never,
+}
+
+impl Deadline {
+    /// `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        match Instant::now().checked_add(duration) {
+            Some(instant) => Self::At(instant),
+            None => Self::Never,
+        }
+    }
+
+    /// Don't block at all: only check the current status.
+    pub fn immediate() -> Self {
+        Self::At(Instant::now())
+    }
+
+    /// Time left until the deadline. `None` for [`Self::Never`].
+    pub fn remaining(&self) -> Option<Duration> {
+        match self {
+            Self::At(instant) => Some(instant.saturating_duration_since(Instant::now())),
+            Self::Never => None,
+        }
+    }
+
+    pub fn has_passed(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// The timeout to pass to vulkan wait functions. `u64::MAX` (wait forever) for
+    /// [`Self::Never`].
+    pub fn timeout_nanoseconds(&self) -> u64 {
+        match self.remaining() {
+            Some(remaining) => remaining.as_nanos().min(u64::MAX as u128 - 1) as u64,
+            None => u64::MAX,
+        }
+    }
+}
+
+impl From<Duration> for Deadline {
+    fn from(duration: Duration) -> Self {
+        Self::after(duration)
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self::At(instant)
+    }
+}
+
+/// Result of a wait bounded by a [`Deadline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    Complete,
+    TimedOut,
+}
+
+impl WaitStatus {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        *self == Self::Complete
+    }
+
+    #[inline]
+    pub fn is_timed_out(&self) -> bool {
+        *self == Self::TimedOut
+    }
+
+    /// Maps `TIMEOUT` (and `NOT_READY` returned for zero timeouts) to [`Self::TimedOut`].
+    pub(crate) fn from_vk_result(res: VkResult<()>) -> VkResult<Self> {
+        match res {
+            Ok(()) => Ok(Self::Complete),
+            Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY) => Ok(Self::TimedOut),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn deadline_timeouts() {
+    assert_eq!(Deadline::Never.timeout_nanoseconds(), u64::MAX);
+    assert!(!Deadline::Never.has_passed());
+    assert!(Deadline::immediate().has_passed());
+    assert_eq!(Deadline::immediate().timeout_nanoseconds(), 0);
+
+    let deadline = Deadline::from(Duration::from_secs(60));
+    assert!(deadline.timeout_nanoseconds() > 59_000_000_000);
+    assert!(deadline.timeout_nanoseconds() <= 60_000_000_000);
+    assert!(deadline < Deadline::Never);
+
+    assert_eq!(WaitStatus::from_vk_result(Ok(())), Ok(WaitStatus::Complete));
+    assert_eq!(
+        WaitStatus::from_vk_result(Err(vk::Result::TIMEOUT)),
+        Ok(WaitStatus::TimedOut)
+    );
+    assert_eq!(
+        WaitStatus::from_vk_result(Err(vk::Result::ERROR_DEVICE_LOST)),
+        Err(vk::Result::ERROR_DEVICE_LOST)
+    );
+}
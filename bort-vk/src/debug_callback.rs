@@ -1,6 +1,9 @@
 use crate::{Instance, ALLOCATION_CALLBACK_NONE};
 use ash::{ext::debug_utils, prelude::VkResult, vk};
-use std::sync::Arc;
+use log::{error, info, trace, warn};
+use std::{borrow::Cow, ffi::CStr, sync::Arc};
+
+pub const VALIDATION_LAYER_NAME: &CStr = c"VK_LAYER_KHRONOS_validation";
 
 pub struct DebugCallback {
     handle: vk::DebugUtilsMessengerEXT,
@@ -78,6 +81,40 @@ impl Drop for DebugCallback {
     }
 }
 
+/// Logs vulkan debug messages with the `log` crate at a level matching the message severity.
+///
+/// # Safety
+/// Called by the vulkan implementation with valid callback data.
+pub unsafe extern "system" fn log_vulkan_debug_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut std::os::raw::c_void,
+) -> vk::Bool32 {
+    let callback_data = *p_callback_data;
+
+    let message = if callback_data.p_message.is_null() {
+        Cow::from("")
+    } else {
+        CStr::from_ptr(callback_data.p_message).to_string_lossy()
+    };
+
+    match message_severity {
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
+            error!("Vulkan [{:?}]:\n{}", message_type, message);
+        }
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => {
+            warn!("Vulkan [{:?}]: {}", message_type, message);
+        }
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => {
+            info!("Vulkan [{:?}]: {}", message_type, message);
+        }
+        _ => trace!("Vulkan [{:?}]: {}", message_type, message),
+    }
+
+    vk::FALSE
+}
+
 // Properties
 
 #[derive(Clone, Copy, Debug)]
@@ -104,7 +141,7 @@ impl DebugCallbackProperties {
     pub fn write_create_info<'a>(
         &'a self,
         create_info: vk::DebugUtilsMessengerCreateInfoEXT<'a>,
-    ) -> vk::DebugUtilsMessengerCreateInfoEXT {
+    ) -> vk::DebugUtilsMessengerCreateInfoEXT<'a> {
         create_info
             .message_severity(self.message_severity)
             .message_type(self.message_type)
@@ -113,7 +150,7 @@ impl DebugCallbackProperties {
     pub fn create_info(
         &self,
         debug_callback: vk::PFN_vkDebugUtilsMessengerCallbackEXT,
-    ) -> vk::DebugUtilsMessengerCreateInfoEXT {
+    ) -> vk::DebugUtilsMessengerCreateInfoEXT<'_> {
         let create_info =
             vk::DebugUtilsMessengerCreateInfoEXT::default().pfn_user_callback(debug_callback);
         self.write_create_info(create_info)
@@ -126,3 +163,60 @@ impl DebugCallbackProperties {
         }
     }
 }
+
+impl From<&vk::DebugUtilsMessengerCreateInfoEXT<'_>> for DebugCallbackProperties {
+    fn from(value: &vk::DebugUtilsMessengerCreateInfoEXT<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+// Instance Debug Messenger
+
+/// A debug messenger chained to `vk::InstanceCreateInfo` which reports messages emitted during
+/// `vkCreateInstance` and `vkDestroyInstance`, when a [`DebugCallback`] can't exist. Pass to
+/// [`Instance::new_with_debug_messenger`](crate::Instance::new_with_debug_messenger) or
+/// [`DebugPrintfConfig::debug_messenger`](crate::DebugPrintfConfig::debug_messenger). A
+/// `DebugCallback` is still needed for messages in between.
+#[derive(Clone, Copy, Debug)]
+pub struct InstanceDebugMessenger {
+    pub debug_callback: vk::PFN_vkDebugUtilsMessengerCallbackEXT,
+    pub properties: DebugCallbackProperties,
+}
+
+impl Default for InstanceDebugMessenger {
+    fn default() -> Self {
+        Self {
+            debug_callback: Some(log_vulkan_debug_callback),
+            properties: DebugCallbackProperties::default(),
+        }
+    }
+}
+
+impl InstanceDebugMessenger {
+    pub fn create_info(&self) -> vk::DebugUtilsMessengerCreateInfoEXT<'static> {
+        vk::DebugUtilsMessengerCreateInfoEXT::default()
+            .pfn_user_callback(self.debug_callback)
+            .message_severity(self.properties.message_severity)
+            .message_type(self.properties.message_type)
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn instance_debug_messenger_create_info() {
+    let debug_messenger = InstanceDebugMessenger::default();
+    let create_info = debug_messenger.create_info();
+    assert!(create_info.pfn_user_callback.is_some());
+    assert!(create_info.p_next.is_null());
+
+    let properties = DebugCallbackProperties::from_create_info(&create_info);
+    assert_eq!(
+        properties.message_severity,
+        debug_messenger.properties.message_severity
+    );
+    assert_eq!(
+        properties.message_type,
+        debug_messenger.properties.message_type
+    );
+}
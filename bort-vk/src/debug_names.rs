@@ -0,0 +1,59 @@
+use ash::vk::{self, Handle};
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+};
+
+/// Debug names given to objects with [`Device::set_debug_name`](crate::Device::set_debug_name).
+/// Kept on the host so that the `tracing` spans can name the objects they're about, independent
+/// of whether `VK_EXT_debug_utils` is enabled.
+#[derive(Debug, Default)]
+pub struct DebugNames {
+    names: RwLock<HashMap<(vk::ObjectType, u64), String>>,
+}
+
+impl DebugNames {
+    pub fn set<H: Handle>(&self, handle: H, name: &str) {
+        self.write_names()
+            .insert((H::TYPE, handle.as_raw()), name.to_owned());
+    }
+
+    pub fn get<H: Handle>(&self, handle: H) -> Option<String> {
+        self.names
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(H::TYPE, handle.as_raw()))
+            .cloned()
+    }
+
+    /// Forgets the name of `handle` so that a later object reusing the handle value isn't
+    /// reported with it. Called when the object is destroyed.
+    pub fn remove<H: Handle>(&self, handle: H) -> Option<String> {
+        self.write_names().remove(&(H::TYPE, handle.as_raw()))
+    }
+
+    fn write_names(
+        &self,
+    ) -> std::sync::RwLockWriteGuard<'_, HashMap<(vk::ObjectType, u64), String>> {
+        self.names.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn debug_names_are_per_object_type() {
+    let names = DebugNames::default();
+    names.set(vk::Buffer::from_raw(1), "vertices");
+    assert_eq!(
+        names.get(vk::Buffer::from_raw(1)).as_deref(),
+        Some("vertices")
+    );
+    assert_eq!(names.get(vk::Image::from_raw(1)), None);
+
+    assert_eq!(
+        names.remove(vk::Buffer::from_raw(1)).as_deref(),
+        Some("vertices")
+    );
+    assert_eq!(names.get(vk::Buffer::from_raw(1)), None);
+}
@@ -0,0 +1,168 @@
+use crate::{
+    log_vulkan_debug_callback, ApiVersion, InstanceDebugMessenger, PhysicalDeviceFeatures,
+};
+use ash::vk;
+use log::info;
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+};
+
+/// `log` target that shader `debugPrintfEXT` output is sent to by [`debug_printf_callback`].
+/// Filter on it to e.g. send shader output to its own log file.
+pub const DEBUG_PRINTF_LOG_TARGET: &str = "bort_vk::debug_printf";
+
+/// Validation layer message ids identifying debug printf output. Older layers use the
+/// `UNASSIGNED` prefix.
+pub const DEBUG_PRINTF_MESSAGE_ID_NAMES: [&str; 2] =
+    ["WARNING-DEBUG-PRINTF", "UNASSIGNED-DEBUG-PRINTF"];
+
+/// Options for [`Instance::new_with_debug_printf`](crate::Instance::new_with_debug_printf).
+/// The layer settings are only applied if the validation layer supports
+/// `VK_EXT_layer_settings`, otherwise the layer defaults (or `vk_layer_settings.txt`) are used.
+#[derive(Clone, Copy, Debug)]
+pub struct DebugPrintfConfig {
+    /// Size in bytes of the buffer shaders write printf output to each submission. Output beyond
+    /// this is dropped.
+    pub buffer_size: u32,
+    /// Include the shader stage, draw/dispatch index and other info with each message.
+    pub verbose: bool,
+    /// Keep the rest of GPU-assisted and core validation enabled. Debug printf and GPU-assisted
+    /// validation can't be used at the same time so this only affects core validation.
+    pub keep_core_validation: bool,
+    /// Also report messages emitted during instance creation and destruction, see
+    /// [`InstanceDebugMessenger`].
+    pub debug_messenger: Option<InstanceDebugMessenger>,
+}
+
+impl Default for DebugPrintfConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 1024,
+            verbose: false,
+            keep_core_validation: true,
+            debug_messenger: None,
+        }
+    }
+}
+
+impl DebugPrintfConfig {
+    pub(crate) const ENABLED_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 1] =
+        [vk::ValidationFeatureEnableEXT::DEBUG_PRINTF];
+    pub(crate) const CORE_VALIDATION_DISABLED: [vk::ValidationFeatureDisableEXT; 1] =
+        [vk::ValidationFeatureDisableEXT::CORE_CHECKS];
+
+    /// Validation features to disable (the `disabled` slice of `vk::ValidationFeaturesEXT`).
+    pub fn disabled_validation_features(&self) -> &'static [vk::ValidationFeatureDisableEXT] {
+        if self.keep_core_validation {
+            &[]
+        } else {
+            &Self::CORE_VALIDATION_DISABLED
+        }
+    }
+}
+
+/// Routes shader `debugPrintfEXT` output to the [`DEBUG_PRINTF_LOG_TARGET`] log target at info
+/// level and forwards everything else to [`log_vulkan_debug_callback`]. Pass to
+/// [`DebugCallback::new`](crate::DebugCallback::new) with a message severity that includes
+/// `INFO`.
+///
+/// # Safety
+/// Called by the vulkan implementation with valid callback data.
+pub unsafe extern "system" fn debug_printf_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut std::os::raw::c_void,
+) -> vk::Bool32 {
+    let callback_data = *p_callback_data;
+
+    let message_id_name = if callback_data.p_message_id_name.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(callback_data.p_message_id_name))
+    };
+    if !is_debug_printf_message(message_id_name) {
+        return log_vulkan_debug_callback(
+            message_severity,
+            message_type,
+            p_callback_data,
+            user_data,
+        );
+    }
+
+    let message = if callback_data.p_message.is_null() {
+        Cow::from("")
+    } else {
+        CStr::from_ptr(callback_data.p_message).to_string_lossy()
+    };
+    info!(target: DEBUG_PRINTF_LOG_TARGET, "{}", debug_printf_message_text(&message));
+
+    vk::FALSE
+}
+
+/// Whether a debug callback message with id name `message_id_name` is shader printf output.
+pub fn is_debug_printf_message(message_id_name: Option<&CStr>) -> bool {
+    let Some(message_id_name) = message_id_name.and_then(|name| name.to_str().ok()) else {
+        return false;
+    };
+    DEBUG_PRINTF_MESSAGE_ID_NAMES
+        .iter()
+        .any(|&printf_id_name| message_id_name.contains(printf_id_name))
+}
+
+/// Strips the `Validation Information: [ id ] | MessageID = ... |` header the validation layer
+/// prepends to printf output.
+pub fn debug_printf_message_text(message: &str) -> &str {
+    const HEADER_SEPARATOR: &str = " | ";
+    let Some(message_id_index) = message.find("MessageID = ") else {
+        return message;
+    };
+    match message[message_id_index..].find(HEADER_SEPARATOR) {
+        Some(separator_offset) => {
+            &message[message_id_index + separator_offset + HEADER_SEPARATOR.len()..]
+        }
+        None => message,
+    }
+}
+
+/// Device extensions shaders using `debugPrintfEXT` need. `VK_KHR_shader_non_semantic_info` is
+/// core in Vulkan 1.3.
+pub fn debug_printf_device_extensions(device_api_version: ApiVersion) -> Vec<CString> {
+    if device_api_version >= ApiVersion::V1_3 {
+        Vec::new()
+    } else {
+        vec![vk::KHR_SHADER_NON_SEMANTIC_INFO_NAME.to_owned()]
+    }
+}
+
+/// Enables the features the validation layer needs to instrument vertex, tessellation, geometry
+/// and fragment shaders for printf.
+pub fn enable_debug_printf_features(features: &mut PhysicalDeviceFeatures) {
+    features.features_1_0.vertex_pipeline_stores_and_atomics = vk::TRUE;
+    features.features_1_0.fragment_stores_and_atomics = vk::TRUE;
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn debug_printf_message_filtering() {
+    assert!(is_debug_printf_message(Some(c"WARNING-DEBUG-PRINTF")));
+    assert!(is_debug_printf_message(Some(c"UNASSIGNED-DEBUG-PRINTF")));
+    assert!(!is_debug_printf_message(Some(c"VUID-vkCmdDraw-None-02699")));
+    assert!(!is_debug_printf_message(None));
+
+    assert_eq!(
+        debug_printf_message_text(
+            "Validation Information: [ WARNING-DEBUG-PRINTF ] | MessageID = 0x76589099 | x = 4"
+        ),
+        "x = 4"
+    );
+    assert_eq!(
+        debug_printf_message_text(
+            "Validation Information: [ WARNING-DEBUG-PRINTF ] | MessageID = 0x76589099 | a | b"
+        ),
+        "a | b"
+    );
+    assert_eq!(debug_printf_message_text("x = 4"), "x = 4");
+}
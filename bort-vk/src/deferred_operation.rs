@@ -0,0 +1,286 @@
+use crate::{Device, DeviceOwned, PipelineCache, ALLOCATION_CALLBACK_NONE};
+use ash::{
+    khr,
+    prelude::VkResult,
+    vk::{self, Handle},
+};
+use std::{sync::Arc, thread};
+
+/// A `VK_KHR_deferred_host_operations` operation. Pass it to a command which supports deferral
+/// (e.g. [`Self::build_acceleration_structures`] or [`Self::create_ray_tracing_pipelines`]) and
+/// then have one or more threads call [`Self::join`] to do the work, or use
+/// [`Self::join_on_threads`] to spread the work over scoped threads.
+///
+/// Requires the `VK_KHR_deferred_host_operations` device extension.
+pub struct DeferredOperation {
+    handle: vk::DeferredOperationKHR,
+    loader: Arc<khr::deferred_host_operations::Device>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl DeferredOperation {
+    pub fn new(device: Arc<Device>) -> VkResult<Self> {
+        let loader = device.extension_loader::<khr::deferred_host_operations::Device>();
+        let handle = unsafe { loader.create_deferred_operation(ALLOCATION_CALLBACK_NONE) }?;
+
+        Ok(Self {
+            handle,
+            loader,
+            device,
+        })
+    }
+
+    /// Has the calling thread contribute to the operation. Can be called from multiple threads
+    /// at once.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkDeferredOperationJoinKHR.html>
+    pub fn join(&self) -> VkResult<DeferredJoinStatus> {
+        match unsafe { self.loader.deferred_operation_join(self.handle) } {
+            Ok(()) => Ok(DeferredJoinStatus::Complete),
+            Err(vk::Result::THREAD_DONE_KHR) => Ok(DeferredJoinStatus::ThreadDone),
+            Err(vk::Result::THREAD_IDLE_KHR) => Ok(DeferredJoinStatus::ThreadIdle),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Joins the operation from up to `max_thread_count` threads (capped at
+    /// [`Self::max_concurrency`]) including the calling thread, and returns [`Self::result`] once
+    /// the operation is complete.
+    pub fn join_on_threads(&self, max_thread_count: u32) -> VkResult<()> {
+        let thread_count = max_thread_count.min(self.max_concurrency()).max(1);
+
+        let join_until_done = || -> VkResult<()> {
+            loop {
+                match self.join()? {
+                    DeferredJoinStatus::Complete | DeferredJoinStatus::ThreadDone => return Ok(()),
+                    DeferredJoinStatus::ThreadIdle => thread::yield_now(),
+                }
+            }
+        };
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (1..thread_count)
+                .map(|_| scope.spawn(join_until_done))
+                .collect();
+            let mut join_res = join_until_done();
+            for handle in handles {
+                let thread_res = handle
+                    .join()
+                    .expect("deferred operation join thread panicked");
+                join_res = join_res.and(thread_res);
+            }
+            join_res
+        })?;
+
+        // the operation may still be running on another thread when `ThreadDone` is returned
+        while self.is_pending() {
+            thread::yield_now();
+        }
+        self.result()
+    }
+
+    /// Result of the deferred command. Returns `Err(vk::Result::NOT_READY)` while the operation
+    /// is still pending.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetDeferredOperationResultKHR.html>
+    pub fn result(&self) -> VkResult<()> {
+        unsafe { self.loader.get_deferred_operation_result(self.handle) }
+    }
+
+    #[inline]
+    pub fn is_pending(&self) -> bool {
+        self.result() == Err(vk::Result::NOT_READY)
+    }
+
+    /// The number of threads that can usefully call [`Self::join`]. Returns `u32::MAX` if there
+    /// is no limit.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetDeferredOperationMaxConcurrencyKHR.html>
+    pub fn max_concurrency(&self) -> u32 {
+        unsafe {
+            self.loader
+                .get_deferred_operation_max_concurrency(self.handle)
+        }
+    }
+
+    /// Host acceleration structure build deferred to this operation. Requires the
+    /// `VK_KHR_acceleration_structure` extension and the `accelerationStructureHostCommands`
+    /// feature.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkBuildAccelerationStructuresKHR.html>
+    ///
+    /// # Safety
+    /// - everything referenced by `infos` must stay valid until the operation is complete.
+    /// - `self` mustn't have another deferred command which hasn't completed yet (see
+    ///   [`Self::is_pending`]).
+    pub unsafe fn build_acceleration_structures(
+        &self,
+        infos: &[vk::AccelerationStructureBuildGeometryInfoKHR],
+        build_range_infos: &[&[vk::AccelerationStructureBuildRangeInfoKHR]],
+    ) -> VkResult<DeferralStatus> {
+        let loader = self
+            .device
+            .extension_loader::<khr::acceleration_structure::Device>();
+        debug_assert!(!self.is_pending(), "deferred operation is already in use");
+        let build_res =
+            unsafe { loader.build_acceleration_structures(self.handle, infos, build_range_infos) };
+        DeferralStatus::from_command_result(build_res)
+    }
+
+    /// Host acceleration structure copy deferred to this operation. Requires the
+    /// `VK_KHR_acceleration_structure` extension and the `accelerationStructureHostCommands`
+    /// feature.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCopyAccelerationStructureKHR.html>
+    ///
+    /// # Safety
+    /// - the source and destination acceleration structures must stay valid until the operation
+    ///   is complete.
+    /// - `self` mustn't have another deferred command which hasn't completed yet (see
+    ///   [`Self::is_pending`]).
+    pub unsafe fn copy_acceleration_structure(
+        &self,
+        info: &vk::CopyAccelerationStructureInfoKHR,
+    ) -> VkResult<DeferralStatus> {
+        let loader = self
+            .device
+            .extension_loader::<khr::acceleration_structure::Device>();
+        debug_assert!(!self.is_pending(), "deferred operation is already in use");
+        let copy_res = unsafe { loader.copy_acceleration_structure(self.handle, info) };
+        DeferralStatus::from_command_result(copy_res)
+    }
+
+    /// Ray tracing pipeline creation deferred to this operation. Requires the
+    /// `VK_KHR_ray_tracing_pipeline` extension. The returned pipeline handles are only valid
+    /// once the operation completes successfully and must be destroyed by the caller.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkCreateRayTracingPipelinesKHR.html>
+    ///
+    /// # Safety
+    /// - everything referenced by `create_infos` (including the `p_next` chains) must stay valid
+    ///   until the operation is complete.
+    /// - an externally synchronized `pipeline_cache` is only locked for this call so it mustn't be
+    ///   used elsewhere until the operation is complete either.
+    /// - `self` mustn't have another deferred command which hasn't completed yet (see
+    ///   [`Self::is_pending`]).
+    pub unsafe fn create_ray_tracing_pipelines(
+        &self,
+        create_infos: &[vk::RayTracingPipelineCreateInfoKHR],
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> VkResult<(Vec<vk::Pipeline>, DeferralStatus)> {
+        let loader = self
+            .device
+            .extension_loader::<khr::ray_tracing_pipeline::Device>();
+        debug_assert!(!self.is_pending(), "deferred operation is already in use");
+        let cache_handle = pipeline_cache
+            .map(|pipeline_cache| pipeline_cache.handle())
+            .unwrap_or_default();
+        let _cache_sync = pipeline_cache.and_then(PipelineCache::lock_external_sync);
+
+        let create_res = unsafe {
+            loader.create_ray_tracing_pipelines(
+                self.handle,
+                cache_handle,
+                create_infos,
+                ALLOCATION_CALLBACK_NONE,
+            )
+        };
+        match create_res {
+            Ok(pipelines) => Ok((pipelines, DeferralStatus::Complete)),
+            Err((pipelines, result)) => {
+                let status = DeferralStatus::from_command_result(Err(result))?;
+                Ok((pipelines, status))
+            }
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> vk::DeferredOperationKHR {
+        self.handle
+    }
+}
+
+impl DeviceOwned for DeferredOperation {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+impl Drop for DeferredOperation {
+    fn drop(&mut self) {
+        // destroying a pending operation is invalid
+        while self.is_pending() {
+            let _ = self.join();
+        }
+        unsafe {
+            self.loader
+                .destroy_deferred_operation(self.handle, ALLOCATION_CALLBACK_NONE)
+        }
+    }
+}
+
+/// Outcome of a command deferred with a [`DeferredOperation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferralStatus {
+    /// The command was deferred. Call [`DeferredOperation::join`] to complete it.
+    Deferred,
+    /// The command completed immediately. Check [`DeferredOperation::result`] for the result.
+    NotDeferred,
+    /// The command completed immediately and successfully.
+    Complete,
+}
+
+impl DeferralStatus {
+    fn from_command_result(command_res: VkResult<()>) -> VkResult<Self> {
+        match command_res {
+            Ok(()) => Ok(Self::Complete),
+            Err(vk::Result::OPERATION_DEFERRED_KHR) => Ok(Self::Deferred),
+            Err(vk::Result::OPERATION_NOT_DEFERRED_KHR) => Ok(Self::NotDeferred),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Result of [`DeferredOperation::join`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferredJoinStatus {
+    /// The operation is complete. Check [`DeferredOperation::result`] for the result.
+    Complete,
+    /// The operation isn't complete but there is no more work for this thread.
+    ThreadDone,
+    /// The operation isn't complete and there is currently no work for this thread but there
+    /// may be more later.
+    ThreadIdle,
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn deferral_status_from_command_result() {
+    assert_eq!(
+        DeferralStatus::from_command_result(Ok(())),
+        Ok(DeferralStatus::Complete)
+    );
+    assert_eq!(
+        DeferralStatus::from_command_result(Err(vk::Result::OPERATION_DEFERRED_KHR)),
+        Ok(DeferralStatus::Deferred)
+    );
+    assert_eq!(
+        DeferralStatus::from_command_result(Err(vk::Result::OPERATION_NOT_DEFERRED_KHR)),
+        Ok(DeferralStatus::NotDeferred)
+    );
+    assert_eq!(
+        DeferralStatus::from_command_result(Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY)),
+        Err(vk::Result::ERROR_OUT_OF_HOST_MEMORY)
+    );
+}
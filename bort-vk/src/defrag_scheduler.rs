@@ -0,0 +1,249 @@
+use crate::{DefragmentationPass, MemoryAllocator, MemoryPool};
+use ash::{prelude::VkResult, vk};
+use bort_vma::{ffi, DetailedStatistics};
+use std::sync::Arc;
+
+/// Fragmentation above which [`DefragScheduler`] defragments by default.
+pub const DEFAULT_FRAGMENTATION_THRESHOLD: f32 = 0.25;
+
+/// Incrementally defragments the default memory pools and any registered [`MemoryPool`]s once
+/// their fragmentation (see [`fragmentation_from_statistics`]) exceeds a threshold.
+///
+/// Nothing happens in the background: call the unsafe [`Self::run_budgeted`] during idle windows (e.g. once
+/// per frame, or on loading screens) to run at most one bounded defragmentation pass per pool.
+/// Long running sessions which stream resources in and out benefit the most.
+pub struct DefragScheduler {
+    memory_allocator: Arc<MemoryAllocator>,
+    memory_pools: Vec<Arc<MemoryPool>>,
+    fragmentation_threshold: f32,
+    include_default_pools: bool,
+}
+
+impl DefragScheduler {
+    pub fn new(memory_allocator: Arc<MemoryAllocator>) -> Self {
+        Self {
+            memory_allocator,
+            memory_pools: Vec::new(),
+            fragmentation_threshold: DEFAULT_FRAGMENTATION_THRESHOLD,
+            include_default_pools: true,
+        }
+    }
+
+    /// Also monitor and defragment `memory_pool`.
+    pub fn add_memory_pool(&mut self, memory_pool: Arc<MemoryPool>) {
+        self.memory_pools.push(memory_pool);
+    }
+
+    pub fn remove_memory_pool(&mut self, memory_pool: &Arc<MemoryPool>) {
+        self.memory_pools
+            .retain(|existing| !Arc::ptr_eq(existing, memory_pool));
+    }
+
+    /// Pools are only defragmented once their fragmentation exceeds `fragmentation_threshold`
+    /// (between 0 and 1).
+    pub fn set_fragmentation_threshold(&mut self, fragmentation_threshold: f32) {
+        self.fragmentation_threshold = fragmentation_threshold.clamp(0., 1.);
+    }
+
+    /// Whether to monitor and defragment the allocator's default pools. Enabled by default.
+    pub fn set_include_default_pools(&mut self, include_default_pools: bool) {
+        self.include_default_pools = include_default_pools;
+    }
+
+    /// Fragmentation of the default pools, followed by each registered memory pool in the order
+    /// they were added. The default pools are measured over the whole allocator.
+    pub fn fragmentation(&self) -> VkResult<Vec<f32>> {
+        let mut fragmentation = Vec::with_capacity(self.memory_pools.len() + 1);
+        if self.include_default_pools {
+            let total_statistics = self.memory_allocator.calculate_statistics()?;
+            fragmentation.push(fragmentation_from_statistics(&total_statistics.total));
+        }
+        for memory_pool in &self.memory_pools {
+            let statistics = memory_pool.calculate_statistics()?;
+            fragmentation.push(fragmentation_from_statistics(&statistics));
+        }
+        Ok(fragmentation)
+    }
+
+    /// Runs at most one defragmentation pass for each pool whose fragmentation exceeds the
+    /// threshold, moving at most `max_bytes` and `max_moves` allocations in total (0 means no
+    /// limit). Pools are skipped once the budget is used up.
+    ///
+    /// `mover` is called for each pass and must handle its moves as described by
+    /// [`DefragmentationPass`].
+    ///
+    /// # Safety
+    /// Moves left with the default [`DefragmentationMoveOperation::Copy`](crate::DefragmentationMoveOperation::Copy) operation rebind their
+    /// allocation to the destination memory when the pass ends. For each of those, `mover` must
+    /// have recreated the resource at the destination and waited for its data to be copied
+    /// before returning. Any other move must be marked with [`DefragmentationMove::ignore`](crate::DefragmentationMove::ignore) or
+    /// [`DefragmentationMove::destroy`](crate::DefragmentationMove::destroy) (after destroying the resource). The moved resources
+    /// mustn't be in use by the device during the call.
+    pub unsafe fn run_budgeted(
+        &self,
+        max_bytes: vk::DeviceSize,
+        max_moves: u32,
+        mut mover: impl FnMut(&mut DefragmentationPass),
+    ) -> VkResult<DefragRunStats> {
+        let mut run_stats = DefragRunStats::default();
+        let mut budget = DefragBudget::new(max_bytes, max_moves);
+
+        let mut targets: Vec<ffi::VmaPool> = Vec::new();
+        let fragmentation = self.fragmentation()?;
+        let pool_handles = self
+            .include_default_pools
+            .then_some(std::ptr::null_mut())
+            .into_iter()
+            .chain(self.memory_pools.iter().map(|pool| pool.handle()));
+        for (pool_handle, pool_fragmentation) in pool_handles.zip(fragmentation) {
+            if pool_fragmentation > self.fragmentation_threshold {
+                targets.push(pool_handle);
+            }
+        }
+
+        for pool_handle in targets {
+            if budget.is_exhausted() {
+                break;
+            }
+
+            let info = ffi::VmaDefragmentationInfo {
+                flags: ffi::VmaDefragmentationFlagBits::VMA_DEFRAGMENTATION_FLAG_ALGORITHM_FAST_BIT
+                    as vk::Flags,
+                pool: pool_handle,
+                maxBytesPerPass: budget.remaining_bytes(),
+                maxAllocationsPerPass: budget.remaining_moves(),
+            };
+            let context = self.memory_allocator.begin_defragmentation(&info)?;
+            if let Some(mut pass) = context.passes().next() {
+                mover(&mut pass);
+                pass.end();
+            }
+            let stats = context.end();
+
+            budget.consume(stats.bytesMoved, stats.allocationsMoved);
+            run_stats.pools_defragmented += 1;
+            run_stats.bytes_moved += stats.bytesMoved;
+            run_stats.bytes_freed += stats.bytesFreed;
+            run_stats.allocations_moved += stats.allocationsMoved;
+            run_stats.device_memory_blocks_freed += stats.deviceMemoryBlocksFreed;
+        }
+
+        Ok(run_stats)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn memory_allocator(&self) -> &Arc<MemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    #[inline]
+    pub fn memory_pools(&self) -> &[Arc<MemoryPool>] {
+        &self.memory_pools
+    }
+
+    #[inline]
+    pub fn fragmentation_threshold(&self) -> f32 {
+        self.fragmentation_threshold
+    }
+}
+
+/// Totals of a [`DefragScheduler::run_budgeted`] call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefragRunStats {
+    pub pools_defragmented: u32,
+    pub bytes_moved: vk::DeviceSize,
+    pub bytes_freed: vk::DeviceSize,
+    pub allocations_moved: u32,
+    pub device_memory_blocks_freed: u32,
+}
+
+// Helper Functions
+
+/// How fragmented the free memory of a pool is, from 0 (all free memory is one contiguous range)
+/// to approaching 1 (free memory is split into many small ranges). Calculated as 1 minus the
+/// largest free range over the total free memory.
+pub fn fragmentation_from_statistics(statistics: &DetailedStatistics) -> f32 {
+    let free_bytes = statistics
+        .statistics
+        .block_bytes
+        .saturating_sub(statistics.statistics.allocation_bytes);
+    if free_bytes == 0 || statistics.unused_range_count <= 1 {
+        return 0.;
+    }
+    let largest_free_range = statistics.unused_range_size_max.min(free_bytes);
+    1. - largest_free_range as f32 / free_bytes as f32
+}
+
+/// Remaining bytes/moves of a [`DefragScheduler::run_budgeted`] call. 0 means no limit.
+struct DefragBudget {
+    max_bytes: vk::DeviceSize,
+    max_moves: u32,
+    bytes_used: vk::DeviceSize,
+    moves_used: u32,
+}
+
+impl DefragBudget {
+    fn new(max_bytes: vk::DeviceSize, max_moves: u32) -> Self {
+        Self {
+            max_bytes,
+            max_moves,
+            bytes_used: 0,
+            moves_used: 0,
+        }
+    }
+
+    fn consume(&mut self, bytes: vk::DeviceSize, moves: u32) {
+        self.bytes_used += bytes;
+        self.moves_used += moves;
+    }
+
+    fn is_exhausted(&self) -> bool {
+        (self.max_bytes != 0 && self.bytes_used >= self.max_bytes)
+            || (self.max_moves != 0 && self.moves_used >= self.max_moves)
+    }
+
+    fn remaining_bytes(&self) -> vk::DeviceSize {
+        if self.max_bytes == 0 {
+            return 0;
+        }
+        self.max_bytes.saturating_sub(self.bytes_used)
+    }
+
+    fn remaining_moves(&self) -> u32 {
+        if self.max_moves == 0 {
+            return 0;
+        }
+        self.max_moves.saturating_sub(self.moves_used)
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn defrag_fragmentation_and_budget() {
+    let mut statistics = DetailedStatistics {
+        statistics: bort_vma::Statistics {
+            block_bytes: 1024,
+            allocation_bytes: 512,
+            ..Default::default()
+        },
+        unused_range_count: 1,
+        unused_range_size_max: 512,
+        ..Default::default()
+    };
+    assert_eq!(fragmentation_from_statistics(&statistics), 0.);
+
+    statistics.unused_range_count = 4;
+    statistics.unused_range_size_max = 128;
+    assert!((fragmentation_from_statistics(&statistics) - 0.75).abs() < 1e-6);
+
+    let mut budget = DefragBudget::new(1000, 0);
+    budget.consume(400, 3);
+    assert_eq!(budget.remaining_bytes(), 600);
+    assert_eq!(budget.remaining_moves(), 0);
+    assert!(!budget.is_exhausted());
+    budget.consume(600, 1);
+    assert!(budget.is_exhausted());
+}
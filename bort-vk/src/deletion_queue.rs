@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+/// Defers dropping resources until the frames that may still be using them have completed.
+///
+/// Resources pushed during a frame are dropped once [`Self::next_frame`] has been called
+/// `frames_in_flight` times after that i.e. when the frame slot they were used in comes around
+/// again. Call [`Self::next_frame`] once each time a frame slot is reused, after waiting on its
+/// fence. Frames which are skipped before submitting anything (e.g. to recreate the swapchain)
+/// shouldn't call it as the slot's previous submission may still be in flight for the other slots.
+pub struct DeletionQueue {
+    frames_in_flight: u64,
+    current_frame: u64,
+    /// Resources paired with the frame they were pushed in, oldest first.
+    pending: VecDeque<PendingDeletion>,
+}
+
+struct PendingDeletion {
+    pushed_frame: u64,
+    resource: Box<dyn Send>,
+    /// Extra condition checked once the frames in flight have passed e.g. that the presents of a
+    /// retired swapchain have completed.
+    is_ready: Option<Box<dyn FnMut() -> bool + Send>>,
+}
+
+impl DeletionQueue {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            frames_in_flight: frames_in_flight.max(1) as u64,
+            current_frame: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Queues `resource` to be dropped once the current frame has completed.
+    pub fn push<T: Send + 'static>(&mut self, resource: T) {
+        self.push_boxed(Box::new(resource));
+    }
+
+    /// Same as [`Self::push`] for an already boxed resource.
+    pub fn push_boxed(&mut self, resource: Box<dyn Send>) {
+        self.pending.push_back(PendingDeletion {
+            pushed_frame: self.current_frame,
+            resource,
+            is_ready: None,
+        });
+    }
+
+    /// Same as [`Self::push`] but once the current frame has completed, `resource` is only
+    /// dropped when `is_ready` returns true (checked on each [`Self::next_frame`]) e.g. once the
+    /// presentation engine has finished with a retired swapchain.
+    pub fn push_when<T: Send + 'static>(
+        &mut self,
+        resource: T,
+        is_ready: impl FnMut() -> bool + Send + 'static,
+    ) {
+        self.pending.push_back(PendingDeletion {
+            pushed_frame: self.current_frame,
+            resource: Box::new(resource),
+            is_ready: Some(Box::new(is_ready)),
+        });
+    }
+
+    /// Advances to the next frame and drops resources that are no longer in use. Returns the
+    /// number of dropped resources.
+    pub fn next_frame(&mut self) -> usize {
+        self.current_frame += 1;
+
+        let pending_count = self.pending.len();
+        let current_frame = self.current_frame;
+        let frames_in_flight = self.frames_in_flight;
+        self.pending.retain_mut(|pending_deletion| {
+            let frames_completed =
+                pending_deletion.pushed_frame + frames_in_flight <= current_frame;
+            let is_ready = frames_completed
+                && pending_deletion
+                    .is_ready
+                    .as_mut()
+                    .is_none_or(|is_ready| is_ready());
+            !is_ready
+        });
+        pending_count - self.pending.len()
+    }
+
+    /// Drops all queued resources. Only call once the device is idle (or at least not using any
+    /// of the queued resources).
+    pub fn flush(&mut self) -> usize {
+        let dropped_count = self.pending.len();
+        self.pending.clear();
+        dropped_count
+    }
+
+    /// Removes all queued resources without dropping them e.g. to hand them to a
+    /// [`ShutdownGuard`](crate::ShutdownGuard).
+    pub fn take_all(&mut self) -> Vec<Box<dyn Send>> {
+        self.pending
+            .drain(..)
+            .map(|pending_deletion| pending_deletion.resource)
+            .collect()
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames_in_flight as usize
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn deletion_queue_drops_after_frames_in_flight() {
+    use std::sync::Arc;
+
+    let resource = Arc::new(());
+    let mut deletion_queue = DeletionQueue::new(2);
+
+    deletion_queue.push(resource.clone());
+    assert_eq!(Arc::strong_count(&resource), 2);
+
+    assert_eq!(deletion_queue.next_frame(), 0);
+    deletion_queue.push(resource.clone());
+    assert_eq!(Arc::strong_count(&resource), 3);
+
+    assert_eq!(deletion_queue.next_frame(), 1);
+    assert_eq!(Arc::strong_count(&resource), 2);
+
+    assert_eq!(deletion_queue.next_frame(), 1);
+    assert_eq!(Arc::strong_count(&resource), 1);
+    assert!(deletion_queue.is_empty());
+
+    deletion_queue.push(resource.clone());
+    assert_eq!(deletion_queue.flush(), 1);
+    assert_eq!(Arc::strong_count(&resource), 1);
+
+    deletion_queue.push(resource.clone());
+    let taken = deletion_queue.take_all();
+    assert!(deletion_queue.is_empty());
+    assert_eq!(Arc::strong_count(&resource), 2);
+    drop(taken);
+    assert_eq!(Arc::strong_count(&resource), 1);
+
+    let ready = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let ready_check = ready.clone();
+    deletion_queue.push_when(resource.clone(), move || {
+        ready_check.load(std::sync::atomic::Ordering::Relaxed)
+    });
+    deletion_queue.next_frame();
+    deletion_queue.next_frame();
+    assert_eq!(Arc::strong_count(&resource), 2);
+    ready.store(true, std::sync::atomic::Ordering::Relaxed);
+    assert_eq!(deletion_queue.next_frame(), 1);
+    assert_eq!(Arc::strong_count(&resource), 1);
+}
@@ -0,0 +1,177 @@
+use crate::{ApiVersion, Device};
+use ash::vk;
+use std::{error, fmt};
+
+/// Whether an attachment aspect is written or only read (e.g. depth tested against but not
+/// written, or sampled in a shader while still bound as an attachment).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AspectAccess {
+    ReadWrite,
+    ReadOnly,
+}
+
+/// Layout of a combined depth/stencil image where the depth and stencil aspects are accessed as
+/// `depth` and `stencil`. The mixed read-only/read-write layouts (e.g.
+/// `DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL`) require vulkan 1.1 or `VK_KHR_maintenance2`.
+///
+/// Use this for images with both aspects. For depth-only or stencil-only formats, or to
+/// transition the aspects individually, see [`separate_depth_layout`] and
+/// [`separate_stencil_layout`].
+pub fn depth_stencil_attachment_layout(
+    depth: AspectAccess,
+    stencil: AspectAccess,
+) -> vk::ImageLayout {
+    match (depth, stencil) {
+        (AspectAccess::ReadWrite, AspectAccess::ReadWrite) => {
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+        }
+        (AspectAccess::ReadOnly, AspectAccess::ReadWrite) => {
+            vk::ImageLayout::DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL
+        }
+        (AspectAccess::ReadWrite, AspectAccess::ReadOnly) => {
+            vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL
+        }
+        (AspectAccess::ReadOnly, AspectAccess::ReadOnly) => {
+            vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL
+        }
+    }
+}
+
+/// Layout of only the depth aspect e.g. `DEPTH_ATTACHMENT_OPTIMAL`. Requires the vulkan 1.2
+/// `separateDepthStencilLayouts` feature.
+pub fn separate_depth_layout(access: AspectAccess) -> vk::ImageLayout {
+    match access {
+        AspectAccess::ReadWrite => vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+        AspectAccess::ReadOnly => vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+    }
+}
+
+/// Layout of only the stencil aspect e.g. `STENCIL_ATTACHMENT_OPTIMAL`. Requires the vulkan 1.2
+/// `separateDepthStencilLayouts` feature. With a combined depth/stencil format, chain a
+/// `vk::AttachmentReferenceStencilLayout` / `vk::AttachmentDescriptionStencilLayout` to give the
+/// stencil aspect its own layout in a render pass.
+pub fn separate_stencil_layout(access: AspectAccess) -> vk::ImageLayout {
+    match access {
+        AspectAccess::ReadWrite => vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL,
+        AspectAccess::ReadOnly => vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL,
+    }
+}
+
+/// What `layout` requires beyond vulkan 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthStencilLayoutRequirement {
+    None,
+    /// Vulkan 1.1 (or `VK_KHR_maintenance2`).
+    Vulkan11,
+    /// Vulkan 1.2 with the `separateDepthStencilLayouts` feature enabled.
+    SeparateDepthStencilLayouts,
+}
+
+impl DepthStencilLayoutRequirement {
+    pub fn for_layout(layout: vk::ImageLayout) -> Self {
+        match layout {
+            vk::ImageLayout::DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL
+            | vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL => Self::Vulkan11,
+            vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL
+            | vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL
+            | vk::ImageLayout::STENCIL_ATTACHMENT_OPTIMAL
+            | vk::ImageLayout::STENCIL_READ_ONLY_OPTIMAL => Self::SeparateDepthStencilLayouts,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Checks that `device` supports `layout`. These combinations are easy to get wrong and the
+/// validation layer errors don't always point at the cause.
+pub fn validate_depth_stencil_layout(
+    device: &Device,
+    layout: vk::ImageLayout,
+) -> Result<(), DepthStencilLayoutError> {
+    let separate_layouts_enabled = device
+        .enabled_features()
+        .features_1_2
+        .separate_depth_stencil_layouts
+        == vk::TRUE;
+    check_layout_support(
+        layout,
+        device.effective_api_version(),
+        separate_layouts_enabled,
+    )
+}
+
+// Helper Functions
+
+fn check_layout_support(
+    layout: vk::ImageLayout,
+    api_version: ApiVersion,
+    separate_layouts_enabled: bool,
+) -> Result<(), DepthStencilLayoutError> {
+    match DepthStencilLayoutRequirement::for_layout(layout) {
+        DepthStencilLayoutRequirement::None => Ok(()),
+        DepthStencilLayoutRequirement::Vulkan11 => {
+            if api_version >= ApiVersion::V1_1 {
+                Ok(())
+            } else {
+                Err(DepthStencilLayoutError::RequiresVulkan11(layout))
+            }
+        }
+        DepthStencilLayoutRequirement::SeparateDepthStencilLayouts => {
+            if api_version >= ApiVersion::V1_2 && separate_layouts_enabled {
+                Ok(())
+            } else {
+                Err(DepthStencilLayoutError::SeparateDepthStencilLayoutsNotEnabled(layout))
+            }
+        }
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum DepthStencilLayoutError {
+    RequiresVulkan11(vk::ImageLayout),
+    SeparateDepthStencilLayoutsNotEnabled(vk::ImageLayout),
+}
+
+impl fmt::Display for DepthStencilLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequiresVulkan11(layout) => write!(
+                f,
+                "image layout {:?} requires vulkan 1.1 or VK_KHR_maintenance2",
+                layout
+            ),
+            Self::SeparateDepthStencilLayoutsNotEnabled(layout) => write!(
+                f,
+                "image layout {:?} requires the vulkan 1.2 separateDepthStencilLayouts feature",
+                layout
+            ),
+        }
+    }
+}
+
+impl error::Error for DepthStencilLayoutError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn depth_stencil_layout_selection_and_support() {
+    let depth_read_only_layout =
+        depth_stencil_attachment_layout(AspectAccess::ReadOnly, AspectAccess::ReadWrite);
+    assert_eq!(
+        depth_read_only_layout,
+        vk::ImageLayout::DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL
+    );
+    assert!(check_layout_support(depth_read_only_layout, ApiVersion::V1_1, false).is_ok());
+    assert!(check_layout_support(depth_read_only_layout, ApiVersion::V1_0, false).is_err());
+
+    let stencil_layout = separate_stencil_layout(AspectAccess::ReadWrite);
+    assert!(check_layout_support(stencil_layout, ApiVersion::V1_3, false).is_err());
+    assert!(check_layout_support(stencil_layout, ApiVersion::V1_2, true).is_ok());
+    assert!(check_layout_support(
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ApiVersion::V1_0,
+        false
+    )
+    .is_ok());
+}
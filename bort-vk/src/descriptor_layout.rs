@@ -3,7 +3,7 @@ use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 pub struct DescriptorSetLayout {
     handle: vk::DescriptorSetLayout,
@@ -14,19 +14,40 @@ pub struct DescriptorSetLayout {
 }
 
 impl DescriptorSetLayout {
-    pub fn new(device: Arc<Device>, properties: DescriptorSetLayoutProperties) -> VkResult<Self> {
+    /// If any of the bindings have non-empty `binding_flags`, a
+    /// `vk::DescriptorSetLayoutBindingFlagsCreateInfo` is chained to the create info.
+    ///
+    /// Returns an error if a binding with immutable samplers doesn't have exactly one sampler per
+    /// descriptor or isn't a `SAMPLER`/`COMBINED_IMAGE_SAMPLER` binding.
+    pub fn new(
+        device: Arc<Device>,
+        properties: DescriptorSetLayoutProperties,
+    ) -> Result<Self, DescriptorSetLayoutError> {
+        for binding in &properties.bindings {
+            binding.validate_immutable_samplers()?;
+        }
+
         let mut vk_layout_bindings_storage: Vec<vk::DescriptorSetLayoutBinding> = Vec::new();
         let mut vk_immutable_samplers_storage: Vec<Vec<vk::Sampler>> = Vec::new();
-        let create_info = properties.create_info(
+        let mut create_info = properties.create_info(
             &mut vk_layout_bindings_storage,
             &mut vk_immutable_samplers_storage,
         );
 
+        let vk_binding_flags = properties.vk_binding_flags();
+        let mut binding_flags_create_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default();
+        if let Some(vk_binding_flags) = vk_binding_flags.as_ref() {
+            binding_flags_create_info = binding_flags_create_info.binding_flags(vk_binding_flags);
+            create_info = create_info.push_next(&mut binding_flags_create_info);
+        }
+
         let handle = unsafe {
             device
                 .inner()
                 .create_descriptor_set_layout(&create_info, ALLOCATION_CALLBACK_NONE)
-        }?;
+        }
+        .map_err(DescriptorSetLayoutError::Vulkan)?;
         Ok(Self {
             handle,
             properties,
@@ -64,6 +85,17 @@ impl DescriptorSetLayout {
     pub fn properties(&self) -> &DescriptorSetLayoutProperties {
         &self.properties
     }
+
+    /// The immutable samplers of `binding` (kept alive for the lifetime of this layout). Empty if
+    /// the binding doesn't exist or doesn't use immutable samplers.
+    pub fn immutable_samplers(&self, binding: u32) -> &[Arc<Sampler>] {
+        self.properties
+            .bindings
+            .iter()
+            .find(|layout_binding| layout_binding.binding == binding)
+            .map(|layout_binding| layout_binding.immutable_samplers.as_slice())
+            .unwrap_or(&[])
+    }
 }
 
 impl DeviceOwned for DescriptorSetLayout {
@@ -105,6 +137,18 @@ impl DescriptorSetLayoutProperties {
         }
     }
 
+    /// Sets the `UPDATE_AFTER_BIND_POOL` flag which is required if any of the bindings have the
+    /// `UPDATE_AFTER_BIND` binding flag e.g. those created with
+    /// [`DescriptorSetLayoutBinding::new_bindless`]. Sets allocated with this layout must come
+    /// from a pool created with
+    /// [`DescriptorPoolProperties::new_update_after_bind`](crate::DescriptorPoolProperties::new_update_after_bind).
+    pub fn new_update_after_bind(bindings: Vec<DescriptorSetLayoutBinding>) -> Self {
+        Self {
+            flags: vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL,
+            bindings,
+        }
+    }
+
     /// Clears and populates `vk_layout_bindings_storage` and `vk_immutable_samplers_storage`
     /// with data pointed to by the returned create info. `vk_layout_bindings_storage` and
     /// `vk_immutable_samplers_storage` must outlive the returned create info.
@@ -112,7 +156,7 @@ impl DescriptorSetLayoutProperties {
         &'a self,
         vk_layout_bindings_storage: &'a mut Vec<vk::DescriptorSetLayoutBinding<'a>>,
         vk_immutable_samplers_storage: &'a mut Vec<Vec<vk::Sampler>>,
-    ) -> vk::DescriptorSetLayoutCreateInfo {
+    ) -> vk::DescriptorSetLayoutCreateInfo<'a> {
         *vk_layout_bindings_storage = self.vk_layout_bindings(vk_immutable_samplers_storage);
         vk::DescriptorSetLayoutCreateInfo::default()
             .flags(self.flags)
@@ -143,6 +187,102 @@ impl DescriptorSetLayoutProperties {
         vk_layout_bindings
     }
 
+    /// The binding flags of each binding for `vk::DescriptorSetLayoutBindingFlagsCreateInfo`.
+    /// Returns `None` if none of the bindings have any binding flags set.
+    pub fn vk_binding_flags(&self) -> Option<Vec<vk::DescriptorBindingFlags>> {
+        let any_binding_flags = self
+            .bindings
+            .iter()
+            .any(|binding| !binding.binding_flags.is_empty());
+        if !any_binding_flags {
+            return None;
+        }
+
+        let vk_binding_flags = self
+            .bindings
+            .iter()
+            .map(|binding| binding.binding_flags)
+            .collect();
+        Some(vk_binding_flags)
+    }
+
+    /// Unions the bindings of `stage_layouts` (e.g. built from per-stage reflection data) into a
+    /// single layout. Bindings with the same binding number have their stage flags combined and
+    /// must otherwise agree on descriptor type, count, binding flags and immutable samplers.
+    /// The result is sorted by binding number.
+    pub fn merge(
+        stage_layouts: &[DescriptorSetLayoutProperties],
+    ) -> Result<Self, DescriptorSetLayoutMergeError> {
+        let mut flags = vk::DescriptorSetLayoutCreateFlags::empty();
+        let mut bindings = Vec::<DescriptorSetLayoutBinding>::new();
+
+        for stage_layout in stage_layouts {
+            flags |= stage_layout.flags;
+
+            for binding in &stage_layout.bindings {
+                let Some(merged) = bindings
+                    .iter_mut()
+                    .find(|merged| merged.binding == binding.binding)
+                else {
+                    bindings.push(binding.clone());
+                    continue;
+                };
+
+                if merged.descriptor_type != binding.descriptor_type {
+                    return Err(DescriptorSetLayoutMergeError::DescriptorTypeMismatch {
+                        binding: binding.binding,
+                        first: merged.descriptor_type,
+                        second: binding.descriptor_type,
+                    });
+                }
+                if merged.descriptor_count != binding.descriptor_count {
+                    return Err(DescriptorSetLayoutMergeError::DescriptorCountMismatch {
+                        binding: binding.binding,
+                        first: merged.descriptor_count,
+                        second: binding.descriptor_count,
+                    });
+                }
+                if merged.binding_flags != binding.binding_flags {
+                    return Err(DescriptorSetLayoutMergeError::BindingFlagsMismatch {
+                        binding: binding.binding,
+                        first: merged.binding_flags,
+                        second: binding.binding_flags,
+                    });
+                }
+                if merged.vk_immutable_samplers() != binding.vk_immutable_samplers() {
+                    return Err(DescriptorSetLayoutMergeError::ImmutableSamplersMismatch {
+                        binding: binding.binding,
+                    });
+                }
+
+                merged.stage_flags |= binding.stage_flags;
+            }
+        }
+
+        bindings.sort_by_key(|binding| binding.binding);
+        Ok(Self { flags, bindings })
+    }
+
+    /// Whether layouts created from `self` and `other` are "identically defined" and so
+    /// compatible for pipeline layout purposes even if they're different handles: same flags and
+    /// the same bindings (in any order) with the same type, count, stages, binding flags and
+    /// immutable samplers.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/html/chap14.html#descriptorsets-compatibility>
+    pub fn is_identically_defined(&self, other: &Self) -> bool {
+        if self.flags != other.flags || self.bindings.len() != other.bindings.len() {
+            return false;
+        }
+        self.bindings.iter().all(|binding| {
+            other
+                .bindings
+                .iter()
+                .find(|other_binding| other_binding.binding == binding.binding)
+                .is_some_and(|other_binding| binding.is_identically_defined(other_binding))
+        })
+    }
+
+    /// Note: `binding_flags` are left empty because they're in the `p_next` chain.
     pub fn from_create_info(value: &vk::DescriptorSetLayoutCreateInfo) -> Self {
         let mut bindings = Vec::<DescriptorSetLayoutBinding>::new();
         for i in 0..value.binding_count {
@@ -157,6 +297,12 @@ impl DescriptorSetLayoutProperties {
     }
 }
 
+impl From<&vk::DescriptorSetLayoutCreateInfo<'_>> for DescriptorSetLayoutProperties {
+    fn from(value: &vk::DescriptorSetLayoutCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 // Descriptor set layout binding
 
 /// Note: default values are nothing!
@@ -167,9 +313,101 @@ pub struct DescriptorSetLayoutBinding {
     pub descriptor_count: u32,
     pub stage_flags: vk::ShaderStageFlags,
     pub immutable_samplers: Vec<Arc<Sampler>>,
+    /// Descriptor indexing flags e.g. `PARTIALLY_BOUND`. Requires the relevant
+    /// `VK_EXT_descriptor_indexing` (or vulkan 1.2) features to be enabled if not empty.
+    pub binding_flags: vk::DescriptorBindingFlags,
 }
 
 impl DescriptorSetLayoutBinding {
+    /// A large descriptor array binding for bindless resources with the `PARTIALLY_BOUND` and
+    /// `UPDATE_AFTER_BIND` binding flags. Unused elements don't need to be written and elements
+    /// that aren't being used by pending command buffers can be updated after the set is bound.
+    /// See [`PhysicalDeviceFeatures::with_descriptor_indexing`](crate::PhysicalDeviceFeatures::with_descriptor_indexing).
+    ///
+    /// The layout must be created with the `UPDATE_AFTER_BIND_POOL` flag (see
+    /// [`DescriptorSetLayoutProperties::new_update_after_bind`]). Unused elements can also be
+    /// written with null handles if the `VK_EXT_robustness2` `nullDescriptor` feature is enabled.
+    pub fn new_bindless(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        descriptor_count: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> Self {
+        Self {
+            binding,
+            descriptor_type,
+            descriptor_count,
+            stage_flags,
+            immutable_samplers: Vec::new(),
+            binding_flags: vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+        }
+    }
+
+    /// A `SAMPLER` or `COMBINED_IMAGE_SAMPLER` binding with one immutable sampler per array
+    /// element. `descriptor_count` is the number of `samplers`. The samplers are baked into the
+    /// layout and kept alive by it, so `SAMPLER` bindings never need to be written and writes to
+    /// `COMBINED_IMAGE_SAMPLER` bindings only need to provide image views. Other descriptor types
+    /// are rejected by [`DescriptorSetLayout::new`].
+    pub fn new_immutable_samplers(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        stage_flags: vk::ShaderStageFlags,
+        samplers: Vec<Arc<Sampler>>,
+    ) -> Self {
+        Self {
+            binding,
+            descriptor_type,
+            descriptor_count: samplers.len() as u32,
+            stage_flags,
+            immutable_samplers: samplers,
+            binding_flags: vk::DescriptorBindingFlags::empty(),
+        }
+    }
+
+    /// Whether this binding has immutable samplers baked into the layout.
+    #[inline]
+    /// Same binding number, descriptor type, count, stage flags, binding flags and immutable
+    /// sampler handles.
+    pub fn is_identically_defined(&self, other: &Self) -> bool {
+        self.binding == other.binding
+            && self.descriptor_type == other.descriptor_type
+            && self.descriptor_count == other.descriptor_count
+            && self.stage_flags == other.stage_flags
+            && self.binding_flags == other.binding_flags
+            && self.vk_immutable_samplers() == other.vk_immutable_samplers()
+    }
+
+    pub fn has_immutable_samplers(&self) -> bool {
+        !self.immutable_samplers.is_empty()
+    }
+
+    /// Checks that non-empty `immutable_samplers` has exactly `descriptor_count` samplers (as
+    /// `p_immutable_samplers` is read for every array element) and that the descriptor type
+    /// can use immutable samplers.
+    pub fn validate_immutable_samplers(&self) -> Result<(), DescriptorSetLayoutError> {
+        if self.immutable_samplers.is_empty() {
+            return Ok(());
+        }
+        if !matches!(
+            self.descriptor_type,
+            vk::DescriptorType::SAMPLER | vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+        ) {
+            return Err(DescriptorSetLayoutError::ImmutableSamplerDescriptorType {
+                binding: self.binding,
+                descriptor_type: self.descriptor_type,
+            });
+        }
+        if self.immutable_samplers.len() != self.descriptor_count as usize {
+            return Err(DescriptorSetLayoutError::ImmutableSamplerCountMismatch {
+                binding: self.binding,
+                sampler_count: self.immutable_samplers.len(),
+                descriptor_count: self.descriptor_count,
+            });
+        }
+        Ok(())
+    }
+
     /// Note: leaves `immutable_samplers` empty because the create info only provides the handles.
     pub fn from_vk_binding(value: &vk::DescriptorSetLayoutBinding) -> Self {
         Self {
@@ -178,18 +416,29 @@ impl DescriptorSetLayoutBinding {
             descriptor_count: value.descriptor_count,
             stage_flags: value.stage_flags,
             immutable_samplers: Vec::new(), // because the create info only provides handles
+            binding_flags: vk::DescriptorBindingFlags::empty(), // because these are in the p_next chain
         }
     }
 
     /// Clears `vk_immutable_samplers` and stores in it a vector of sampler handles for each
     /// binding. The returned create_info struct contains references to these vectors with a
     /// lifetime of `'a`.
+    ///
+    /// If `immutable_samplers` isn't empty it must contain exactly `descriptor_count` samplers
+    /// because `p_immutable_samplers` is read for every array element (see
+    /// [`Self::validate_immutable_samplers`]).
     pub fn vk_binding<'a>(
         &'a self,
         vk_immutable_samplers_storage: &'a mut Vec<vk::Sampler>,
     ) -> vk::DescriptorSetLayoutBinding<'a> {
         let mut vk_binding = vk::DescriptorSetLayoutBinding::default();
         if !self.immutable_samplers.is_empty() {
+            debug_assert_eq!(
+                self.immutable_samplers.len(),
+                self.descriptor_count as usize,
+                "binding {} must have one immutable sampler per descriptor",
+                self.binding
+            );
             *vk_immutable_samplers_storage = self.vk_immutable_samplers();
             vk_binding = vk_binding.immutable_samplers(vk_immutable_samplers_storage);
         }
@@ -207,3 +456,166 @@ impl DescriptorSetLayoutBinding {
             .collect()
     }
 }
+
+// Errors
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorSetLayoutMergeError {
+    DescriptorTypeMismatch {
+        binding: u32,
+        first: vk::DescriptorType,
+        second: vk::DescriptorType,
+    },
+    DescriptorCountMismatch {
+        binding: u32,
+        first: u32,
+        second: u32,
+    },
+    BindingFlagsMismatch {
+        binding: u32,
+        first: vk::DescriptorBindingFlags,
+        second: vk::DescriptorBindingFlags,
+    },
+    ImmutableSamplersMismatch {
+        binding: u32,
+    },
+}
+
+impl fmt::Display for DescriptorSetLayoutMergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DescriptorTypeMismatch {
+                binding,
+                first,
+                second,
+            } => write!(
+                f,
+                "binding {} is declared as both {:?} and {:?} in different shader stages",
+                binding, first, second
+            ),
+            Self::DescriptorCountMismatch {
+                binding,
+                first,
+                second,
+            } => write!(
+                f,
+                "binding {} is declared with both {} and {} descriptors in different shader stages",
+                binding, first, second
+            ),
+            Self::BindingFlagsMismatch {
+                binding,
+                first,
+                second,
+            } => write!(
+                f,
+                "binding {} has binding flags {:?} and {:?} in different shader stages",
+                binding, first, second
+            ),
+            Self::ImmutableSamplersMismatch { binding } => write!(
+                f,
+                "binding {} has different immutable samplers in different shader stages",
+                binding
+            ),
+        }
+    }
+}
+
+impl error::Error for DescriptorSetLayoutMergeError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorSetLayoutError {
+    ImmutableSamplerCountMismatch {
+        binding: u32,
+        sampler_count: usize,
+        descriptor_count: u32,
+    },
+    /// Immutable samplers can only be used by `SAMPLER` and `COMBINED_IMAGE_SAMPLER` bindings.
+    ImmutableSamplerDescriptorType {
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+    },
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for DescriptorSetLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImmutableSamplerCountMismatch {
+                binding,
+                sampler_count,
+                descriptor_count,
+            } => write!(
+                f,
+                "binding {} has {} immutable samplers but {} descriptors",
+                binding, sampler_count, descriptor_count
+            ),
+            Self::ImmutableSamplerDescriptorType {
+                binding,
+                descriptor_type,
+            } => write!(
+                f,
+                "binding {} has immutable samplers but descriptor type {:?}",
+                binding, descriptor_type
+            ),
+            Self::Vulkan(e) => write!(f, "failed to create descriptor set layout: {}", e),
+        }
+    }
+}
+
+impl error::Error for DescriptorSetLayoutError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn merge_combines_stage_flags() {
+    let binding = |binding, descriptor_type, stage_flags| DescriptorSetLayoutBinding {
+        binding,
+        descriptor_type,
+        descriptor_count: 1,
+        stage_flags,
+        ..Default::default()
+    };
+    let vertex = DescriptorSetLayoutProperties::new_default(vec![
+        binding(
+            1,
+            vk::DescriptorType::STORAGE_BUFFER,
+            vk::ShaderStageFlags::VERTEX,
+        ),
+        binding(
+            0,
+            vk::DescriptorType::UNIFORM_BUFFER,
+            vk::ShaderStageFlags::VERTEX,
+        ),
+    ]);
+    let fragment = DescriptorSetLayoutProperties::new_default(vec![binding(
+        0,
+        vk::DescriptorType::UNIFORM_BUFFER,
+        vk::ShaderStageFlags::FRAGMENT,
+    )]);
+
+    let merged = DescriptorSetLayoutProperties::merge(&[vertex.clone(), fragment]).unwrap();
+    assert_eq!(merged.bindings.len(), 2);
+    assert_eq!(merged.bindings[0].binding, 0);
+    assert_eq!(
+        merged.bindings[0].stage_flags,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+    );
+    assert_eq!(merged.bindings[1].stage_flags, vk::ShaderStageFlags::VERTEX);
+
+    let conflicting = DescriptorSetLayoutProperties::new_default(vec![binding(
+        1,
+        vk::DescriptorType::SAMPLED_IMAGE,
+        vk::ShaderStageFlags::FRAGMENT,
+    )]);
+    assert!(matches!(
+        DescriptorSetLayoutProperties::merge(&[vertex, conflicting]),
+        Err(DescriptorSetLayoutMergeError::DescriptorTypeMismatch { binding: 1, .. })
+    ));
+}
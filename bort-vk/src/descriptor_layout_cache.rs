@@ -0,0 +1,125 @@
+use crate::{DescriptorSetLayout, DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device};
+use ash::vk;
+use std::{collections::HashMap, sync::Arc};
+
+/// Identifies a descriptor set layout by everything that goes into its create info.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DescriptorSetLayoutKey {
+    pub flags: vk::DescriptorSetLayoutCreateFlags,
+    pub bindings: Vec<DescriptorSetLayoutBindingKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DescriptorSetLayoutBindingKey {
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub descriptor_count: u32,
+    pub stage_flags: vk::ShaderStageFlags,
+    pub immutable_samplers: Vec<vk::Sampler>,
+    pub binding_flags: vk::DescriptorBindingFlags,
+}
+
+impl DescriptorSetLayoutKey {
+    pub fn new(properties: &DescriptorSetLayoutProperties) -> Self {
+        let bindings = properties
+            .bindings
+            .iter()
+            .map(|binding| DescriptorSetLayoutBindingKey {
+                binding: binding.binding,
+                descriptor_type: binding.descriptor_type,
+                descriptor_count: binding.descriptor_count,
+                stage_flags: binding.stage_flags,
+                immutable_samplers: binding.vk_immutable_samplers(),
+                binding_flags: binding.binding_flags,
+            })
+            .collect();
+        Self {
+            flags: properties.flags,
+            bindings,
+        }
+    }
+}
+
+/// Creates descriptor set layouts on demand and shares them between callers requesting
+/// identical layouts. Cached layouts keep their immutable samplers alive.
+pub struct DescriptorSetLayoutCache {
+    entries: HashMap<DescriptorSetLayoutKey, Arc<DescriptorSetLayout>>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl DescriptorSetLayoutCache {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            device,
+        }
+    }
+
+    /// Returns the cached layout matching `properties` or creates (and caches) a new one.
+    pub fn get_or_create(
+        &mut self,
+        properties: DescriptorSetLayoutProperties,
+    ) -> Result<Arc<DescriptorSetLayout>, DescriptorSetLayoutError> {
+        let key = DescriptorSetLayoutKey::new(&properties);
+        if let Some(layout) = self.entries.get(&key) {
+            return Ok(layout.clone());
+        }
+
+        let layout = Arc::new(DescriptorSetLayout::new(self.device.clone(), properties)?);
+        self.entries.insert(key, layout.clone());
+        Ok(layout)
+    }
+
+    /// Removes entries that aren't referenced outside of the cache. Returns the number of
+    /// evicted entries.
+    ///
+    /// The evicted entries are destroyed immediately so command buffers recorded with them must
+    /// have completed. See [`Self::take_unused`] to defer that.
+    pub fn evict_unused(&mut self) -> usize {
+        self.take_unused().len()
+    }
+
+    /// Removes and returns entries that aren't referenced outside of the cache e.g. to push them
+    /// to a [`DeletionQueue`](crate::DeletionQueue).
+    pub fn take_unused(&mut self) -> Vec<Arc<DescriptorSetLayout>> {
+        let unused_keys: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, layout)| Arc::strong_count(layout) == 1)
+            .map(|(key, _)| key.clone())
+            .collect();
+        unused_keys
+            .iter()
+            .filter_map(|key| self.entries.remove(key))
+            .collect()
+    }
+
+    /// Removes and returns all entries.
+    pub fn take_all(&mut self) -> Vec<Arc<DescriptorSetLayout>> {
+        self.entries.drain().map(|(_, layout)| layout).collect()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
@@ -1,9 +1,12 @@
-use crate::{DescriptorSet, DescriptorSetLayout, Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use crate::{
+    DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutProperties, Device, DeviceOwned,
+    ALLOCATION_CALLBACK_NONE,
+};
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 pub struct DescriptorPool {
     handle: vk::DescriptorPool,
@@ -63,13 +66,82 @@ impl DescriptorPool {
         let descriptor_set_handle =
             unsafe { self.device().inner().allocate_descriptor_sets(&create_info) }?[0];
 
-        Ok(unsafe { DescriptorSet::from_handle(descriptor_set_handle, layout, self.clone()) })
+        Ok(
+            unsafe {
+                DescriptorSet::from_handle(descriptor_set_handle, layout, None, self.clone())
+            },
+        )
+    }
+
+    /// Allocates a set whose last binding has the `VARIABLE_DESCRIPTOR_COUNT` binding flag with
+    /// `variable_descriptor_count` descriptors in that binding (instead of the count declared in
+    /// the layout which acts as an upper bound).
+    ///
+    /// Returns an error if the binding with the highest binding number in `layout` doesn't have
+    /// the `VARIABLE_DESCRIPTOR_COUNT` flag or declares fewer than `variable_descriptor_count`
+    /// descriptors. Note that layouts created with
+    /// [`DescriptorSetLayout::new_from_create_info`] don't know their binding flags.
+    pub fn allocate_descriptor_set_variable_count(
+        self: &Arc<Self>,
+        layout: Arc<DescriptorSetLayout>,
+        variable_descriptor_count: u32,
+    ) -> Result<DescriptorSet, DescriptorPoolError> {
+        check_variable_descriptor_count(layout.properties(), variable_descriptor_count)?;
+
+        let layout_handles = [layout.handle()];
+        let descriptor_counts = [variable_descriptor_count];
+        let mut variable_count_info =
+            vk::DescriptorSetVariableDescriptorCountAllocateInfo::default()
+                .descriptor_counts(&descriptor_counts);
+        let create_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(self.handle)
+            .set_layouts(&layout_handles)
+            .push_next(&mut variable_count_info);
+
+        let descriptor_set_handle =
+            unsafe { self.device().inner().allocate_descriptor_sets(&create_info) }
+                .map_err(DescriptorPoolError::Vulkan)?[0];
+
+        Ok(unsafe {
+            DescriptorSet::from_handle(
+                descriptor_set_handle,
+                layout,
+                Some(variable_descriptor_count),
+                self.clone(),
+            )
+        })
     }
 
     pub fn allocate_descriptor_sets(
         self: &Arc<Self>,
         layouts: Vec<Arc<DescriptorSetLayout>>,
     ) -> VkResult<Vec<DescriptorSet>> {
+        let layout_refs: Vec<&Arc<DescriptorSetLayout>> = layouts.iter().collect();
+        self.allocate_many(&layout_refs)
+    }
+
+    /// Allocates a set for each of `layouts` with a single `vkAllocateDescriptorSets` call. The
+    /// returned Vec is reserved up front to hold exactly `layouts.len()` sets.
+    pub fn allocate_many(
+        self: &Arc<Self>,
+        layouts: &[&Arc<DescriptorSetLayout>],
+    ) -> VkResult<Vec<DescriptorSet>> {
+        let mut descriptor_sets = Vec::with_capacity(layouts.len());
+        self.allocate_many_into(layouts, &mut descriptor_sets)?;
+        Ok(descriptor_sets)
+    }
+
+    /// Like [`Self::allocate_many`] but appends the new sets to `descriptor_sets` so a Vec can be
+    /// reused between allocations e.g. in per-frame code.
+    pub fn allocate_many_into(
+        self: &Arc<Self>,
+        layouts: &[&Arc<DescriptorSetLayout>],
+        descriptor_sets: &mut Vec<DescriptorSet>,
+    ) -> VkResult<()> {
+        if layouts.is_empty() {
+            return Ok(());
+        }
+
         let layout_handles: Vec<vk::DescriptorSetLayout> =
             layouts.iter().map(|l| l.handle()).collect();
         let create_info = vk::DescriptorSetAllocateInfo::default()
@@ -79,19 +151,18 @@ impl DescriptorPool {
         let descriptor_set_handles =
             unsafe { self.device().inner().allocate_descriptor_sets(&create_info) }?;
 
-        let mut descriptor_sets = Vec::<DescriptorSet>::new();
-        for i in 0..layouts.len() {
-            let descriptor_set = unsafe {
+        descriptor_sets.reserve(layouts.len());
+        descriptor_sets.extend(descriptor_set_handles.into_iter().zip(layouts).map(
+            |(descriptor_set_handle, &layout)| unsafe {
                 DescriptorSet::from_handle(
-                    descriptor_set_handles[i],
-                    layouts[i].clone(),
+                    descriptor_set_handle,
+                    layout.clone(),
+                    None,
                     self.clone(),
                 )
-            };
-            descriptor_sets.push(descriptor_set);
-        }
-
-        Ok(descriptor_sets)
+            },
+        ));
+        Ok(())
     }
 
     // Getters
@@ -146,6 +217,16 @@ impl DescriptorPoolProperties {
         }
     }
 
+    /// Sets the `UPDATE_AFTER_BIND` flag so sets with `UPDATE_AFTER_BIND_POOL` layouts can be
+    /// allocated from this pool.
+    pub fn new_update_after_bind(max_sets: u32, pool_sizes: Vec<vk::DescriptorPoolSize>) -> Self {
+        Self {
+            flags: vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND,
+            max_sets,
+            pool_sizes,
+        }
+    }
+
     pub fn write_create_info<'a>(
         &'a self,
         create_info: vk::DescriptorPoolCreateInfo<'a>,
@@ -156,7 +237,7 @@ impl DescriptorPoolProperties {
             .pool_sizes(&self.pool_sizes)
     }
 
-    pub fn create_info(&self) -> vk::DescriptorPoolCreateInfo {
+    pub fn create_info(&self) -> vk::DescriptorPoolCreateInfo<'_> {
         self.write_create_info(vk::DescriptorPoolCreateInfo::default())
     }
 
@@ -174,3 +255,117 @@ impl DescriptorPoolProperties {
         }
     }
 }
+
+impl From<&vk::DescriptorPoolCreateInfo<'_>> for DescriptorPoolProperties {
+    fn from(value: &vk::DescriptorPoolCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+// Helper Functions
+
+/// Checks that the binding with the highest binding number in `layout_properties` (the only one
+/// allowed to have a variable count) has the `VARIABLE_DESCRIPTOR_COUNT` flag and at least
+/// `variable_descriptor_count` descriptors.
+fn check_variable_descriptor_count(
+    layout_properties: &DescriptorSetLayoutProperties,
+    variable_descriptor_count: u32,
+) -> Result<(), DescriptorPoolError> {
+    let last_binding = layout_properties
+        .bindings
+        .iter()
+        .max_by_key(|binding| binding.binding)
+        .filter(|binding| {
+            binding
+                .binding_flags
+                .contains(vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT)
+        })
+        .ok_or(DescriptorPoolError::NoVariableCountBinding)?;
+
+    if variable_descriptor_count > last_binding.descriptor_count {
+        return Err(DescriptorPoolError::VariableDescriptorCountTooLarge {
+            binding: last_binding.binding,
+            variable_descriptor_count,
+            max_descriptor_count: last_binding.descriptor_count,
+        });
+    }
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorPoolError {
+    /// The last binding of the layout doesn't have the `VARIABLE_DESCRIPTOR_COUNT` binding flag.
+    NoVariableCountBinding,
+    VariableDescriptorCountTooLarge {
+        binding: u32,
+        variable_descriptor_count: u32,
+        max_descriptor_count: u32,
+    },
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for DescriptorPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoVariableCountBinding => write!(
+                f,
+                "the last binding of the descriptor set layout doesn't have the VARIABLE_DESCRIPTOR_COUNT flag"
+            ),
+            Self::VariableDescriptorCountTooLarge {
+                binding,
+                variable_descriptor_count,
+                max_descriptor_count,
+            } => write!(
+                f,
+                "variable descriptor count {} exceeds the {} descriptors declared by binding {}",
+                variable_descriptor_count, max_descriptor_count, binding
+            ),
+            Self::Vulkan(e) => write!(f, "failed to allocate descriptor set: {}", e),
+        }
+    }
+}
+
+impl error::Error for DescriptorPoolError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn variable_descriptor_count_checks() {
+    use crate::DescriptorSetLayoutBinding;
+
+    let mut layout_properties = DescriptorSetLayoutProperties::new_default(vec![
+        DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 1,
+            ..Default::default()
+        },
+        DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+            descriptor_count: 64,
+            ..Default::default()
+        },
+    ]);
+    assert!(matches!(
+        check_variable_descriptor_count(&layout_properties, 8),
+        Err(DescriptorPoolError::NoVariableCountBinding)
+    ));
+
+    layout_properties.bindings[1].binding_flags =
+        vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT;
+    assert!(check_variable_descriptor_count(&layout_properties, 64).is_ok());
+    assert!(matches!(
+        check_variable_descriptor_count(&layout_properties, 65),
+        Err(DescriptorPoolError::VariableDescriptorCountTooLarge { binding: 1, .. })
+    ));
+}
@@ -1,14 +1,20 @@
-use crate::{DescriptorPool, DescriptorSetLayout, Device, DeviceOwned};
+use crate::{
+    BufferAccess, CountedObjectType, DescriptorPool, DescriptorSetLayout,
+    DescriptorSetLayoutProperties, DescriptorTemplateData, DescriptorTemplateError,
+    DescriptorTemplateLayout, DescriptorUpdateTemplate, Device, DeviceOwned,
+};
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 // Note: no destructor needed. Just drop pool.
 pub struct DescriptorSet {
     handle: vk::DescriptorSet,
     layout: Arc<DescriptorSetLayout>,
+    variable_descriptor_count: Option<u32>,
+    counted: bool,
 
     // dependencies
     descriptor_pool: Arc<DescriptorPool>,
@@ -26,15 +32,191 @@ impl DescriptorSet {
     pub(crate) unsafe fn from_handle(
         handle: vk::DescriptorSet,
         layout: Arc<DescriptorSetLayout>,
+        variable_descriptor_count: Option<u32>,
         descriptor_pool: Arc<DescriptorPool>,
     ) -> Self {
+        let counted = descriptor_pool
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::DescriptorSet);
         Self {
             handle,
             layout,
+            variable_descriptor_count,
+            counted,
             descriptor_pool,
         }
     }
 
+    /// Writes the descriptors in `data` to this set with a single
+    /// `vkUpdateDescriptorSetWithTemplate` call. `descriptor_update_template` must have been
+    /// created with [`DescriptorUpdateTemplate::new`] for this set's layout.
+    ///
+    /// Checks the template type, that `data` matches the template (see
+    /// [`DescriptorUpdateTemplate::check_data`]) and that the template entries fit this set's
+    /// layout (see [`Self::validate_template_layout`]) before the update.
+    pub fn update_with_template(
+        &self,
+        descriptor_update_template: &DescriptorUpdateTemplate,
+        data: &DescriptorTemplateData,
+    ) -> Result<(), DescriptorTemplateUpdateError> {
+        let template_type = descriptor_update_template.template_type();
+        if template_type != vk::DescriptorUpdateTemplateType::DESCRIPTOR_SET {
+            return Err(DescriptorTemplateUpdateError::TemplateType(template_type));
+        }
+        descriptor_update_template
+            .check_data(data)
+            .map_err(DescriptorTemplateUpdateError::Data)?;
+        self.validate_template_layout(descriptor_update_template.data_layout())
+            .map_err(DescriptorTemplateUpdateError::Write)?;
+        unsafe {
+            self.device().inner().update_descriptor_set_with_template(
+                self.handle,
+                descriptor_update_template.handle(),
+                data.as_ptr(),
+            )
+        }
+        Ok(())
+    }
+
+    /// Writes `image_infos` to consecutive array elements of `binding` starting at
+    /// `dst_array_element`. The range is checked against the descriptor count of the binding
+    /// (or the allocated variable count) rather than spilling over into the next binding.
+    pub fn write_images(
+        &self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        image_infos: &[vk::DescriptorImageInfo],
+    ) -> Result<(), DescriptorWriteError> {
+        self.validate_write(
+            binding,
+            dst_array_element,
+            descriptor_type,
+            image_infos.len() as u32,
+        )?;
+        let descriptor_write = vk::WriteDescriptorSet::default()
+            .dst_set(self.handle)
+            .dst_binding(binding)
+            .dst_array_element(dst_array_element)
+            .descriptor_type(descriptor_type)
+            .image_info(image_infos);
+        self.device().update_descriptor_sets([descriptor_write], []);
+        Ok(())
+    }
+
+    /// Writes `buffer_infos` to consecutive array elements of `binding` starting at
+    /// `dst_array_element`. See [`Self::write_images`].
+    pub fn write_buffers(
+        &self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        buffer_infos: &[vk::DescriptorBufferInfo],
+    ) -> Result<(), DescriptorWriteError> {
+        self.validate_write(
+            binding,
+            dst_array_element,
+            descriptor_type,
+            buffer_infos.len() as u32,
+        )?;
+        let descriptor_write = vk::WriteDescriptorSet::default()
+            .dst_set(self.handle)
+            .dst_binding(binding)
+            .dst_array_element(dst_array_element)
+            .descriptor_type(descriptor_type)
+            .buffer_info(buffer_infos);
+        self.device().update_descriptor_sets([descriptor_write], []);
+        Ok(())
+    }
+
+    /// Same as [`Self::write_buffers`] but writes the whole range of each buffer. Works with
+    /// buffers owned by external code via [`BorrowedBuffer`](crate::BorrowedBuffer).
+    pub fn write_whole_buffers(
+        &self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        buffers: &[&dyn BufferAccess],
+    ) -> Result<(), DescriptorWriteError> {
+        let buffer_infos: Vec<vk::DescriptorBufferInfo> = buffers
+            .iter()
+            .map(|buffer| buffer.whole_descriptor_info())
+            .collect();
+        self.write_buffers(binding, dst_array_element, descriptor_type, &buffer_infos)
+    }
+
+    /// Writes `buffer_views` to consecutive array elements of `binding` starting at
+    /// `dst_array_element`. See [`Self::write_images`].
+    pub fn write_texel_buffers(
+        &self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        buffer_views: &[vk::BufferView],
+    ) -> Result<(), DescriptorWriteError> {
+        self.validate_write(
+            binding,
+            dst_array_element,
+            descriptor_type,
+            buffer_views.len() as u32,
+        )?;
+        let descriptor_write = vk::WriteDescriptorSet::default()
+            .dst_set(self.handle)
+            .dst_binding(binding)
+            .dst_array_element(dst_array_element)
+            .descriptor_type(descriptor_type)
+            .texel_buffer_view(buffer_views);
+        self.device().update_descriptor_sets([descriptor_write], []);
+        Ok(())
+    }
+
+    /// Checks that writing `descriptor_count` descriptors of `descriptor_type` to `binding`
+    /// starting at `dst_array_element` stays within the binding.
+    pub fn validate_write(
+        &self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        descriptor_count: u32,
+    ) -> Result<(), DescriptorWriteError> {
+        validate_descriptor_array_write(
+            self.layout.properties(),
+            self.variable_descriptor_count,
+            binding,
+            dst_array_element,
+            descriptor_type,
+            descriptor_count,
+        )
+    }
+
+    /// Checks each entry of `data_layout` with [`Self::validate_write`].
+    pub fn validate_template_layout(
+        &self,
+        data_layout: &DescriptorTemplateLayout,
+    ) -> Result<(), DescriptorWriteError> {
+        for entry in data_layout.entries() {
+            self.validate_write(
+                entry.dst_binding,
+                entry.dst_array_element,
+                entry.descriptor_type,
+                entry.descriptor_count,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Number of descriptors in `binding` for this set. For bindings with the
+    /// `VARIABLE_DESCRIPTOR_COUNT` flag this is the count the set was allocated with. `None` if
+    /// the layout has no such binding.
+    pub fn binding_descriptor_count(&self, binding: u32) -> Option<u32> {
+        binding_descriptor_count(
+            self.layout.properties(),
+            self.variable_descriptor_count,
+            binding,
+        )
+    }
+
     // Getters
 
     pub fn handle(&self) -> vk::DescriptorSet {
@@ -45,6 +227,13 @@ impl DescriptorSet {
         &self.layout
     }
 
+    /// The descriptor count of the variable count binding if allocated with
+    /// [`DescriptorPool::allocate_descriptor_set_variable_count`].
+    #[inline]
+    pub fn variable_descriptor_count(&self) -> Option<u32> {
+        self.variable_descriptor_count
+    }
+
     #[inline]
     pub fn descriptor_pool(&self) -> &Arc<DescriptorPool> {
         &self.descriptor_pool
@@ -53,6 +242,10 @@ impl DescriptorSet {
 
 impl Drop for DescriptorSet {
     fn drop(&mut self) {
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::DescriptorSet, self.counted);
+
         let reset_flag_set = self
             .descriptor_pool
             .properties()
@@ -68,6 +261,7 @@ impl Drop for DescriptorSet {
                 .inner()
                 .free_descriptor_sets(self.descriptor_pool.handle(), &[self.handle]);
         }
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
@@ -82,3 +276,225 @@ impl DeviceOwned for DescriptorSet {
         self.handle.as_raw()
     }
 }
+
+// Array Write Validation
+
+/// Checks that writing `descriptor_count` descriptors of `descriptor_type` to `binding` starting
+/// at `dst_array_element` stays within the binding. Vulkan would otherwise silently continue the
+/// write into the following binding(s), which is almost never intended.
+///
+/// `variable_descriptor_count` is the count a set was allocated with if the binding has the
+/// `VARIABLE_DESCRIPTOR_COUNT` flag.
+pub fn validate_descriptor_array_write(
+    layout_properties: &DescriptorSetLayoutProperties,
+    variable_descriptor_count: Option<u32>,
+    binding: u32,
+    dst_array_element: u32,
+    descriptor_type: vk::DescriptorType,
+    descriptor_count: u32,
+) -> Result<(), DescriptorWriteError> {
+    let layout_binding = layout_properties
+        .bindings
+        .iter()
+        .find(|layout_binding| layout_binding.binding == binding)
+        .ok_or(DescriptorWriteError::BindingNotInLayout(binding))?;
+
+    if layout_binding.descriptor_type != descriptor_type {
+        return Err(DescriptorWriteError::DescriptorTypeMismatch {
+            binding,
+            layout_type: layout_binding.descriptor_type,
+            write_type: descriptor_type,
+        });
+    }
+    if descriptor_count == 0 {
+        return Err(DescriptorWriteError::EmptyWrite { binding });
+    }
+
+    let binding_descriptor_count =
+        binding_descriptor_count(layout_properties, variable_descriptor_count, binding)
+            .unwrap_or(layout_binding.descriptor_count);
+    let write_end = dst_array_element as u64 + descriptor_count as u64;
+    if write_end > binding_descriptor_count as u64 {
+        return Err(DescriptorWriteError::ArrayRangeOutOfBounds {
+            binding,
+            dst_array_element,
+            descriptor_count,
+            binding_descriptor_count,
+        });
+    }
+
+    Ok(())
+}
+
+fn binding_descriptor_count(
+    layout_properties: &DescriptorSetLayoutProperties,
+    variable_descriptor_count: Option<u32>,
+    binding: u32,
+) -> Option<u32> {
+    let layout_binding = layout_properties
+        .bindings
+        .iter()
+        .find(|layout_binding| layout_binding.binding == binding)?;
+    let is_variable_count = layout_binding
+        .binding_flags
+        .contains(vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT);
+    match variable_descriptor_count {
+        Some(variable_descriptor_count) if is_variable_count => Some(variable_descriptor_count),
+        _ => Some(layout_binding.descriptor_count),
+    }
+}
+
+// Errors
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorWriteError {
+    BindingNotInLayout(u32),
+    DescriptorTypeMismatch {
+        binding: u32,
+        layout_type: vk::DescriptorType,
+        write_type: vk::DescriptorType,
+    },
+    EmptyWrite {
+        binding: u32,
+    },
+    /// `binding_descriptor_count` is the allocated count for variable count bindings.
+    ArrayRangeOutOfBounds {
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_count: u32,
+        binding_descriptor_count: u32,
+    },
+}
+
+impl fmt::Display for DescriptorWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BindingNotInLayout(binding) => write!(
+                f,
+                "descriptor set layout has no binding {}",
+                binding
+            ),
+            Self::DescriptorTypeMismatch {
+                binding,
+                layout_type,
+                write_type,
+            } => write!(
+                f,
+                "binding {} is declared as {:?} but is being written with {:?}",
+                binding, layout_type, write_type
+            ),
+            Self::EmptyWrite { binding } => {
+                write!(f, "descriptor write to binding {} has no descriptors", binding)
+            }
+            Self::ArrayRangeOutOfBounds {
+                binding,
+                dst_array_element,
+                descriptor_count,
+                binding_descriptor_count,
+            } => write!(
+                f,
+                "writing elements {}..{} of binding {} is out of bounds for a binding with {} descriptors",
+                dst_array_element,
+                *dst_array_element as u64 + *descriptor_count as u64,
+                binding,
+                binding_descriptor_count
+            ),
+        }
+    }
+}
+
+impl error::Error for DescriptorWriteError {}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorTemplateUpdateError {
+    /// The template wasn't created with [`DescriptorUpdateTemplate::new`].
+    TemplateType(vk::DescriptorUpdateTemplateType),
+    Data(DescriptorTemplateError),
+    Write(DescriptorWriteError),
+}
+
+impl fmt::Display for DescriptorTemplateUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TemplateType(template_type) => write!(
+                f,
+                "descriptor sets can't be updated with a {:?} template",
+                template_type
+            ),
+            Self::Data(e) => write!(f, "invalid descriptor template data: {}", e),
+            Self::Write(e) => write!(f, "descriptor template doesn't fit the set layout: {}", e),
+        }
+    }
+}
+
+impl error::Error for DescriptorTemplateUpdateError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::TemplateType(_) => None,
+            Self::Data(e) => Some(e),
+            Self::Write(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn validate_descriptor_array_write_checks_element_range() {
+    use crate::DescriptorSetLayoutBinding;
+
+    let layout_properties = DescriptorSetLayoutProperties::new_default(vec![
+        DescriptorSetLayoutBinding {
+            binding: 0,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 4,
+            stage_flags: vk::ShaderStageFlags::ALL,
+            ..Default::default()
+        },
+        DescriptorSetLayoutBinding {
+            binding: 1,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1024,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            binding_flags: vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT,
+            ..Default::default()
+        },
+    ]);
+    let validate = |variable_count, binding, dst_array_element, descriptor_type, count| {
+        validate_descriptor_array_write(
+            &layout_properties,
+            variable_count,
+            binding,
+            dst_array_element,
+            descriptor_type,
+            count,
+        )
+    };
+    let uniform_buffer = vk::DescriptorType::UNIFORM_BUFFER;
+    let image_sampler = vk::DescriptorType::COMBINED_IMAGE_SAMPLER;
+
+    assert!(validate(None, 0, 1, uniform_buffer, 3).is_ok());
+    assert!(matches!(
+        validate(None, 0, 2, uniform_buffer, 3),
+        Err(DescriptorWriteError::ArrayRangeOutOfBounds {
+            binding_descriptor_count: 4,
+            ..
+        })
+    ));
+    assert!(matches!(
+        validate(None, 0, 0, image_sampler, 1),
+        Err(DescriptorWriteError::DescriptorTypeMismatch { .. })
+    ));
+    assert!(matches!(
+        validate(None, 2, 0, uniform_buffer, 1),
+        Err(DescriptorWriteError::BindingNotInLayout(2))
+    ));
+    assert!(validate(None, 1, 1000, image_sampler, 24).is_ok());
+    assert!(matches!(
+        validate(Some(16), 1, 10, image_sampler, 8),
+        Err(DescriptorWriteError::ArrayRangeOutOfBounds {
+            binding_descriptor_count: 16,
+            ..
+        })
+    ));
+}
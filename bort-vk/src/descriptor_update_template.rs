@@ -0,0 +1,521 @@
+use crate::{
+    ApiVersion, DescriptorSetLayout, Device, DeviceOwned, PipelineLayout, ALLOCATION_CALLBACK_NONE,
+};
+use ash::{
+    prelude::VkResult,
+    vk::{self, Handle},
+};
+use std::{error, ffi::c_void, fmt, mem, sync::Arc};
+
+/// A `vk::DescriptorUpdateTemplate` (vulkan 1.1) describing where the descriptor infos for each
+/// binding are stored in a [`DescriptorTemplateData`] blob. Updating a descriptor set from a
+/// template is a single call with much less CPU overhead than building `vk::WriteDescriptorSet`s,
+/// which adds up for per-draw updates.
+///
+/// Use with [`DescriptorSet::update_with_template`](crate::DescriptorSet::update_with_template)
+/// or [`CommandBuffer::push_descriptor_set_with_template`](crate::CommandBuffer::push_descriptor_set_with_template).
+pub struct DescriptorUpdateTemplate {
+    handle: vk::DescriptorUpdateTemplate,
+    data_layout: DescriptorTemplateLayout,
+    template_type: vk::DescriptorUpdateTemplateType,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl DescriptorUpdateTemplate {
+    /// A template for updating descriptor sets allocated with `descriptor_set_layout`.
+    pub fn new(
+        descriptor_set_layout: &DescriptorSetLayout,
+        data_layout: DescriptorTemplateLayout,
+    ) -> VkResult<Self> {
+        let create_info = vk::DescriptorUpdateTemplateCreateInfo::default()
+            .descriptor_update_entries(&data_layout.entries)
+            .template_type(vk::DescriptorUpdateTemplateType::DESCRIPTOR_SET)
+            .descriptor_set_layout(descriptor_set_layout.handle());
+
+        let device = descriptor_set_layout.device().clone();
+        let handle = create_template(&device, &create_info)?;
+        Ok(Self {
+            handle,
+            data_layout,
+            template_type: vk::DescriptorUpdateTemplateType::DESCRIPTOR_SET,
+            device,
+        })
+    }
+
+    /// A template for pushing descriptors to `set` of `pipeline_layout`. Requires
+    /// `VK_KHR_push_descriptor` and the set layout to have been created with the
+    /// `PUSH_DESCRIPTOR_KHR` flag.
+    pub fn new_push_descriptors(
+        pipeline_layout: &PipelineLayout,
+        pipeline_bind_point: vk::PipelineBindPoint,
+        set: u32,
+        data_layout: DescriptorTemplateLayout,
+    ) -> VkResult<Self> {
+        let create_info = vk::DescriptorUpdateTemplateCreateInfo::default()
+            .descriptor_update_entries(&data_layout.entries)
+            .template_type(vk::DescriptorUpdateTemplateType::PUSH_DESCRIPTORS_KHR)
+            .pipeline_bind_point(pipeline_bind_point)
+            .pipeline_layout(pipeline_layout.handle())
+            .set(set);
+
+        let device = pipeline_layout.device().clone();
+        let handle = create_template(&device, &create_info)?;
+        Ok(Self {
+            handle,
+            data_layout,
+            template_type: vk::DescriptorUpdateTemplateType::PUSH_DESCRIPTORS_KHR,
+            device,
+        })
+    }
+
+    /// Checks that `data` was laid out with the same [`DescriptorTemplateLayout`] as this
+    /// template and is large enough for it.
+    pub fn check_data(&self, data: &DescriptorTemplateData) -> Result<(), DescriptorTemplateError> {
+        if data.data_size() < self.data_layout.data_size {
+            return Err(DescriptorTemplateError::DataTooSmall {
+                data_size: data.data_size(),
+                required_size: self.data_layout.data_size,
+            });
+        }
+        if !data.data_layout().matches(&self.data_layout) {
+            return Err(DescriptorTemplateError::DataLayoutMismatch);
+        }
+        Ok(())
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> vk::DescriptorUpdateTemplate {
+        self.handle
+    }
+
+    #[inline]
+    pub fn data_layout(&self) -> &DescriptorTemplateLayout {
+        &self.data_layout
+    }
+
+    #[inline]
+    pub fn template_type(&self) -> vk::DescriptorUpdateTemplateType {
+        self.template_type
+    }
+}
+
+impl DeviceOwned for DescriptorUpdateTemplate {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+impl Drop for DescriptorUpdateTemplate {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .inner()
+                .destroy_descriptor_update_template(self.handle, ALLOCATION_CALLBACK_NONE);
+        }
+    }
+}
+
+// Helper Functions
+
+fn create_template(
+    device: &Device,
+    create_info: &vk::DescriptorUpdateTemplateCreateInfo,
+) -> VkResult<vk::DescriptorUpdateTemplate> {
+    debug_assert!(
+        device.effective_api_version() >= ApiVersion::V1_1,
+        "descriptor update templates require vulkan 1.1"
+    );
+    unsafe {
+        device
+            .inner()
+            .create_descriptor_update_template(create_info, ALLOCATION_CALLBACK_NONE)
+    }
+}
+
+// Data Layout
+
+/// The kind of info struct a descriptor type is written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorInfoKind {
+    /// `vk::DescriptorImageInfo`
+    Image,
+    /// `vk::DescriptorBufferInfo`
+    Buffer,
+    /// `vk::BufferView`
+    TexelBuffer,
+}
+
+impl DescriptorInfoKind {
+    /// Returns `None` for descriptor types which aren't written with info structs (e.g. inline
+    /// uniform blocks and acceleration structures).
+    pub fn from_descriptor_type(descriptor_type: vk::DescriptorType) -> Option<Self> {
+        match descriptor_type {
+            vk::DescriptorType::SAMPLER
+            | vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+            | vk::DescriptorType::SAMPLED_IMAGE
+            | vk::DescriptorType::STORAGE_IMAGE
+            | vk::DescriptorType::INPUT_ATTACHMENT => Some(Self::Image),
+            vk::DescriptorType::UNIFORM_BUFFER
+            | vk::DescriptorType::STORAGE_BUFFER
+            | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+            | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC => Some(Self::Buffer),
+            vk::DescriptorType::UNIFORM_TEXEL_BUFFER | vk::DescriptorType::STORAGE_TEXEL_BUFFER => {
+                Some(Self::TexelBuffer)
+            }
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of the info struct.
+    pub fn info_size(&self) -> usize {
+        match self {
+            Self::Image => mem::size_of::<vk::DescriptorImageInfo>(),
+            Self::Buffer => mem::size_of::<vk::DescriptorBufferInfo>(),
+            Self::TexelBuffer => mem::size_of::<vk::BufferView>(),
+        }
+    }
+}
+
+/// Builds the entries of a [`DescriptorUpdateTemplate`], laying out the info structs for each
+/// entry one after the other in a [`DescriptorTemplateData`] blob.
+///
+/// ```
+/// # use bort_vk::DescriptorTemplateLayout;
+/// # use ash::vk;
+/// let data_layout = DescriptorTemplateLayout::new()
+///     .with_entry(0, vk::DescriptorType::UNIFORM_BUFFER, 1)
+///     .unwrap()
+///     .with_entry(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4)
+///     .unwrap();
+/// assert_eq!(data_layout.entries().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DescriptorTemplateLayout {
+    entries: Vec<vk::DescriptorUpdateTemplateEntry>,
+    kinds: Vec<DescriptorInfoKind>,
+    data_size: usize,
+}
+
+impl DescriptorTemplateLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry updating `descriptor_count` descriptors of `binding` starting at array
+    /// element 0.
+    pub fn with_entry(
+        self,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        descriptor_count: u32,
+    ) -> Result<Self, DescriptorTemplateError> {
+        self.with_array_entry(binding, 0, descriptor_type, descriptor_count)
+    }
+
+    /// Appends an entry updating `descriptor_count` descriptors of `binding` starting at
+    /// `dst_array_element`.
+    pub fn with_array_entry(
+        mut self,
+        binding: u32,
+        dst_array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        descriptor_count: u32,
+    ) -> Result<Self, DescriptorTemplateError> {
+        let kind = DescriptorInfoKind::from_descriptor_type(descriptor_type).ok_or(
+            DescriptorTemplateError::UnsupportedDescriptorType(descriptor_type),
+        )?;
+
+        let stride = kind.info_size();
+        // all info structs are 8 byte aligned
+        let offset = self.data_size.next_multiple_of(DATA_ALIGNMENT);
+        self.entries.push(vk::DescriptorUpdateTemplateEntry {
+            dst_binding: binding,
+            dst_array_element,
+            descriptor_count,
+            descriptor_type,
+            offset,
+            stride,
+        });
+        self.kinds.push(kind);
+        self.data_size = offset + stride * descriptor_count as usize;
+        Ok(self)
+    }
+
+    /// Whether `other` has the same entries at the same offsets.
+    pub fn matches(&self, other: &DescriptorTemplateLayout) -> bool {
+        let entries_match = self.entries.len() == other.entries.len()
+            && self.entries.iter().zip(&other.entries).all(|(a, b)| {
+                a.dst_binding == b.dst_binding
+                    && a.dst_array_element == b.dst_array_element
+                    && a.descriptor_count == b.descriptor_count
+                    && a.descriptor_type == b.descriptor_type
+                    && a.offset == b.offset
+                    && a.stride == b.stride
+            });
+        entries_match && self.kinds == other.kinds && self.data_size == other.data_size
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn entries(&self) -> &[vk::DescriptorUpdateTemplateEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn kinds(&self) -> &[DescriptorInfoKind] {
+        &self.kinds
+    }
+
+    /// Size in bytes of the data blob.
+    #[inline]
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+}
+
+const DATA_ALIGNMENT: usize = mem::align_of::<u64>();
+
+/// Descriptor infos laid out according to a [`DescriptorTemplateLayout`]. Reuse it between
+/// updates and only overwrite the descriptors that changed.
+#[derive(Debug, Clone)]
+pub struct DescriptorTemplateData {
+    /// `u64` storage so the info structs are suitably aligned.
+    data: Vec<u64>,
+    data_layout: DescriptorTemplateLayout,
+}
+
+impl DescriptorTemplateData {
+    /// All descriptor infos are zeroed (null handles).
+    pub fn new(data_layout: &DescriptorTemplateLayout) -> Self {
+        let word_count = data_layout.data_size.div_ceil(DATA_ALIGNMENT);
+        Self {
+            data: vec![0; word_count],
+            data_layout: data_layout.clone(),
+        }
+    }
+
+    pub fn set_image(
+        &mut self,
+        entry_index: usize,
+        array_index: u32,
+        image_info: vk::DescriptorImageInfo,
+    ) -> Result<(), DescriptorTemplateError> {
+        self.write(
+            entry_index,
+            array_index,
+            DescriptorInfoKind::Image,
+            image_info,
+        )
+    }
+
+    pub fn set_buffer(
+        &mut self,
+        entry_index: usize,
+        array_index: u32,
+        buffer_info: vk::DescriptorBufferInfo,
+    ) -> Result<(), DescriptorTemplateError> {
+        self.write(
+            entry_index,
+            array_index,
+            DescriptorInfoKind::Buffer,
+            buffer_info,
+        )
+    }
+
+    pub fn set_texel_buffer(
+        &mut self,
+        entry_index: usize,
+        array_index: u32,
+        buffer_view: vk::BufferView,
+    ) -> Result<(), DescriptorTemplateError> {
+        self.write(
+            entry_index,
+            array_index,
+            DescriptorInfoKind::TexelBuffer,
+            buffer_view,
+        )
+    }
+
+    fn write<T: Copy>(
+        &mut self,
+        entry_index: usize,
+        array_index: u32,
+        kind: DescriptorInfoKind,
+        info: T,
+    ) -> Result<(), DescriptorTemplateError> {
+        let entry = self
+            .data_layout
+            .entries
+            .get(entry_index)
+            .ok_or(DescriptorTemplateError::EntryIndexOutOfBounds(entry_index))?;
+        let entry_kind = self.data_layout.kinds[entry_index];
+        if entry_kind != kind {
+            return Err(DescriptorTemplateError::InfoKindMismatch {
+                entry_index,
+                expected: entry_kind,
+                provided: kind,
+            });
+        }
+        if array_index >= entry.descriptor_count {
+            return Err(DescriptorTemplateError::ArrayIndexOutOfBounds {
+                entry_index,
+                array_index,
+                descriptor_count: entry.descriptor_count,
+            });
+        }
+
+        let byte_offset = entry.offset + entry.stride * array_index as usize;
+        debug_assert!(byte_offset + mem::size_of::<T>() <= self.data.len() * DATA_ALIGNMENT);
+        unsafe {
+            let dst_ptr = (self.data.as_mut_ptr() as *mut u8).add(byte_offset) as *mut T;
+            dst_ptr.write_unaligned(info);
+        }
+        Ok(())
+    }
+
+    /// Pointer to the data for `vkUpdateDescriptorSetWithTemplate` etc.
+    #[inline]
+    pub fn as_ptr(&self) -> *const c_void {
+        self.data.as_ptr() as *const c_void
+    }
+
+    #[inline]
+    pub fn data_layout(&self) -> &DescriptorTemplateLayout {
+        &self.data_layout
+    }
+
+    /// Size in bytes of the data blob.
+    #[inline]
+    pub fn data_size(&self) -> usize {
+        self.data.len() * DATA_ALIGNMENT
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum DescriptorTemplateError {
+    /// Descriptor types such as inline uniform blocks and acceleration structures aren't
+    /// supported by [`DescriptorTemplateLayout`].
+    UnsupportedDescriptorType(vk::DescriptorType),
+    EntryIndexOutOfBounds(usize),
+    ArrayIndexOutOfBounds {
+        entry_index: usize,
+        array_index: u32,
+        descriptor_count: u32,
+    },
+    InfoKindMismatch {
+        entry_index: usize,
+        expected: DescriptorInfoKind,
+        provided: DescriptorInfoKind,
+    },
+    DataTooSmall {
+        data_size: usize,
+        required_size: usize,
+    },
+    /// The data was created for a different [`DescriptorTemplateLayout`] than the template.
+    DataLayoutMismatch,
+}
+
+impl fmt::Display for DescriptorTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedDescriptorType(descriptor_type) => write!(
+                f,
+                "descriptor type {:?} isn't supported in descriptor templates",
+                descriptor_type
+            ),
+            Self::EntryIndexOutOfBounds(entry_index) => {
+                write!(f, "descriptor template has no entry {}", entry_index)
+            }
+            Self::ArrayIndexOutOfBounds {
+                entry_index,
+                array_index,
+                descriptor_count,
+            } => write!(
+                f,
+                "array index {} is out of bounds for descriptor template entry {} with {} descriptors",
+                array_index, entry_index, descriptor_count
+            ),
+            Self::InfoKindMismatch {
+                entry_index,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "descriptor template entry {} expects {:?} infos but {:?} was provided",
+                entry_index, expected, provided
+            ),
+            Self::DataTooSmall {
+                data_size,
+                required_size,
+            } => write!(
+                f,
+                "descriptor template data is {} bytes but the template needs {}",
+                data_size, required_size
+            ),
+            Self::DataLayoutMismatch => write!(
+                f,
+                "descriptor template data was laid out for a different template"
+            ),
+        }
+    }
+}
+
+impl error::Error for DescriptorTemplateError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn descriptor_template_data_layout_and_writes() {
+    let data_layout = DescriptorTemplateLayout::new()
+        .with_entry(0, vk::DescriptorType::UNIFORM_BUFFER, 1)
+        .unwrap()
+        .with_entry(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 2)
+        .unwrap()
+        .with_entry(2, vk::DescriptorType::UNIFORM_TEXEL_BUFFER, 1)
+        .unwrap();
+
+    let buffer_info_size = mem::size_of::<vk::DescriptorBufferInfo>();
+    let image_info_size = mem::size_of::<vk::DescriptorImageInfo>();
+    assert_eq!(data_layout.entries()[1].offset, buffer_info_size);
+    assert_eq!(data_layout.entries()[1].stride, image_info_size);
+    assert_eq!(
+        data_layout.entries()[2].offset,
+        buffer_info_size + 2 * image_info_size
+    );
+
+    let mut data = DescriptorTemplateData::new(&data_layout);
+    let buffer_info = vk::DescriptorBufferInfo {
+        offset: 64,
+        range: vk::WHOLE_SIZE,
+        ..Default::default()
+    };
+    data.set_buffer(0, 0, buffer_info).unwrap();
+    assert_eq!(data.data[1], 64);
+    assert!(data
+        .set_image(1, 2, vk::DescriptorImageInfo::default())
+        .is_err());
+    assert!(data.set_buffer(1, 0, buffer_info).is_err());
+    assert!(DescriptorTemplateLayout::new()
+        .with_entry(0, vk::DescriptorType::INLINE_UNIFORM_BLOCK, 16)
+        .is_err());
+
+    assert!(data.data_size() >= data_layout.data_size());
+    assert!(data.data_layout().matches(&data_layout));
+    let other_layout = DescriptorTemplateLayout::new()
+        .with_entry(0, vk::DescriptorType::STORAGE_BUFFER, 1)
+        .unwrap();
+    assert!(!other_layout.matches(&data_layout));
+}
@@ -1,10 +1,13 @@
 use crate::{
-    ApiVersion, DebugCallback, Fence, Instance, PhysicalDevice, PhysicalDeviceFeatures, Queue,
-    ALLOCATION_CALLBACK_NONE,
+    extension_loader::ExtensionLoaderCache, instrumentation::trace_span, ApiVersion, Deadline,
+    DebugCallback, DebugNames, DeviceExtensionLoader, Feature, Fence, HandleAudit, HandleOwnership,
+    Instance, ObjectCountReport, ObjectCounters, PhysicalDevice, PhysicalDeviceFeatures, Queue,
+    SubmissionGraph, SubmissionRecorder, WaitStatus, ALLOCATION_CALLBACK_NONE,
 };
 use ash::{
+    ext,
     prelude::VkResult,
-    vk::{self, DeviceQueueCreateInfo, ExtendsDeviceCreateInfo},
+    vk::{self, DeviceQueueCreateInfo, ExtendsDeviceCreateInfo, Handle},
 };
 use std::{
     error,
@@ -24,6 +27,13 @@ pub struct Device {
     debug_callback_ref: Option<Arc<DebugCallback>>,
     enabled_extensions: Vec<CString>,
     enabled_layers: Vec<CString>,
+    enabled_features: PhysicalDeviceFeatures<'static>,
+    extension_loaders: ExtensionLoaderCache,
+    object_counters: ObjectCounters,
+    handle_audit: HandleAudit,
+    submission_recorder: SubmissionRecorder,
+    debug_names: DebugNames,
+    ownership: HandleOwnership,
 
     // dependencies
     physical_device: Arc<PhysicalDevice>,
@@ -31,7 +41,9 @@ pub struct Device {
 
 impl Device {
     /// `features_1_1`, `features_1_2` and `features_1_3` might get ignored depending on the
-    /// `instance` api version.
+    /// effective api version (see [`PhysicalDevice::effective_api_version`]).
+    /// Extension feature structs in `features` (e.g. `robustness_2`) are only chained if their
+    /// extension is in `extension_names` or is core in that version.
     pub fn new<'a>(
         physical_device: Arc<PhysicalDevice>,
         queue_create_infos: impl IntoIterator<Item = vk::DeviceQueueCreateInfo<'a>>,
@@ -58,7 +70,9 @@ impl Device {
     }
 
     /// `features_1_1`, `features_1_2` and `features_1_3` might get ignored depending on the
-    /// `instance` api version.
+    /// effective api version (see [`PhysicalDevice::effective_api_version`]).
+    /// Extension feature structs in `features` (e.g. `robustness_2`) are only chained if their
+    /// extension is in `extension_names` or is core in that version.
     ///
     /// _Note that each member of `p_next_structs` can only be one type (known at compile time)
     /// because the ash fn `push_next` currently requires the template to be `Sized`. Just treat
@@ -76,8 +90,6 @@ impl Device {
         debug_callback_ref: Option<Arc<DebugCallback>>,
         mut p_next_structs: Vec<impl ExtendsDeviceCreateInfo>,
     ) -> Result<Self, DeviceError> {
-        let instance = physical_device.instance();
-
         let extension_name_ptrs: Vec<*const c_char> = extension_names
             .iter()
             .map(|cstring| cstring.as_ptr())
@@ -92,14 +104,48 @@ impl Device {
             .enabled_layer_names(&layer_name_ptrs);
 
         let mut features_2 = vk::PhysicalDeviceFeatures2::default();
-        let max_api_version = instance.max_api_version();
+        let max_api_version = physical_device.effective_api_version();
 
         let PhysicalDeviceFeatures {
             features_1_0,
             mut features_1_1,
             mut features_1_2,
             mut features_1_3,
+            robustness_2,
+            pipeline_robustness,
+            maintenance_5,
+            maintenance_6,
+            cooperative_matrix,
+            attachment_feedback_loop_layout,
+            attachment_feedback_loop_dynamic_state,
+            device_coherent_memory,
+            memory_priority,
         } = features;
+        // only chain extension feature structs whose extension is enabled (or core) e.g. when
+        // `features` comes straight from `Instance::physical_device_features`
+        let extension_enabled = |feature: Feature| match feature.required_extension(max_api_version)
+        {
+            Some(extension_name) => extension_names
+                .iter()
+                .any(|enabled_extension| enabled_extension.as_c_str() == extension_name),
+            None => true,
+        };
+        let mut robustness_2 =
+            robustness_2.filter(|_| extension_enabled(Feature::RobustBufferAccess2));
+        let mut pipeline_robustness =
+            pipeline_robustness.filter(|_| extension_enabled(Feature::PipelineRobustness));
+        let mut maintenance_5 = maintenance_5.filter(|_| extension_enabled(Feature::Maintenance5));
+        let mut maintenance_6 = maintenance_6.filter(|_| extension_enabled(Feature::Maintenance6));
+        let mut cooperative_matrix =
+            cooperative_matrix.filter(|_| extension_enabled(Feature::CooperativeMatrix));
+        let mut attachment_feedback_loop_layout = attachment_feedback_loop_layout
+            .filter(|_| extension_enabled(Feature::AttachmentFeedbackLoopLayout));
+        let mut attachment_feedback_loop_dynamic_state = attachment_feedback_loop_dynamic_state
+            .filter(|_| extension_enabled(Feature::AttachmentFeedbackLoopDynamicState));
+        let mut device_coherent_memory =
+            device_coherent_memory.filter(|_| extension_enabled(Feature::DeviceCoherentMemory));
+        let mut memory_priority =
+            memory_priority.filter(|_| extension_enabled(Feature::MemoryPriority));
 
         if max_api_version <= ApiVersion::V1_0 {
             device_create_info = device_create_info.enabled_features(&features_1_0);
@@ -116,6 +162,38 @@ impl Device {
             if max_api_version >= ApiVersion::V1_3 {
                 device_create_info = device_create_info.push_next(&mut features_1_3);
             }
+
+            if let Some(robustness_2) = robustness_2.as_mut() {
+                device_create_info = device_create_info.push_next(robustness_2);
+            }
+            if let Some(pipeline_robustness) = pipeline_robustness.as_mut() {
+                device_create_info = device_create_info.push_next(pipeline_robustness);
+            }
+            if let Some(maintenance_5) = maintenance_5.as_mut() {
+                device_create_info = device_create_info.push_next(maintenance_5);
+            }
+            if let Some(maintenance_6) = maintenance_6.as_mut() {
+                device_create_info = device_create_info.push_next(maintenance_6);
+            }
+            if let Some(cooperative_matrix) = cooperative_matrix.as_mut() {
+                device_create_info = device_create_info.push_next(cooperative_matrix);
+            }
+            if let Some(attachment_feedback_loop_layout) = attachment_feedback_loop_layout.as_mut()
+            {
+                device_create_info = device_create_info.push_next(attachment_feedback_loop_layout);
+            }
+            if let Some(attachment_feedback_loop_dynamic_state) =
+                attachment_feedback_loop_dynamic_state.as_mut()
+            {
+                device_create_info =
+                    device_create_info.push_next(attachment_feedback_loop_dynamic_state);
+            }
+            if let Some(device_coherent_memory) = device_coherent_memory.as_mut() {
+                device_create_info = device_create_info.push_next(device_coherent_memory);
+            }
+            if let Some(memory_priority) = memory_priority.as_mut() {
+                device_create_info = device_create_info.push_next(memory_priority);
+            }
         }
 
         for p_next_struct in &mut p_next_structs {
@@ -149,15 +227,62 @@ impl Device {
         }
         .map_err(DeviceError::Creation)?;
 
+        let enabled_features =
+            unsafe { PhysicalDeviceFeatures::from_device_create_info(&create_info) };
+
         Ok(Self {
             inner,
             debug_callback_ref,
             physical_device,
             enabled_extensions,
             enabled_layers,
+            enabled_features,
+            extension_loaders: ExtensionLoaderCache::default(),
+            object_counters: ObjectCounters::default(),
+            handle_audit: HandleAudit::default(),
+            submission_recorder: SubmissionRecorder::default(),
+            debug_names: DebugNames::default(),
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Wraps a `VkDevice` created elsewhere e.g. by existing ash code or other middleware. With
+    /// [`HandleOwnership::Owned`] the device is waited on and destroyed on drop.
+    ///
+    /// `enabled_extensions`, `enabled_layers` and `enabled_features` should match what the device
+    /// was created with as they're used to check support for some functionality.
+    ///
+    /// # Safety
+    /// - `handle` must be a valid `VkDevice` created from `physical_device`.
+    /// - with [`HandleOwnership::Borrowed`] the device must outlive `self` and every object
+    ///   created with it through bort.
+    pub unsafe fn from_handle(
+        physical_device: Arc<PhysicalDevice>,
+        handle: vk::Device,
+        enabled_extensions: Vec<CString>,
+        enabled_layers: Vec<CString>,
+        enabled_features: PhysicalDeviceFeatures<'static>,
+        ownership: HandleOwnership,
+    ) -> Self {
+        let inner =
+            unsafe { ash::Device::load(physical_device.instance().inner().fp_v1_0(), handle) };
+
+        Self {
+            inner,
+            debug_callback_ref: None,
+            physical_device,
+            enabled_extensions,
+            enabled_layers,
+            enabled_features,
+            extension_loaders: ExtensionLoaderCache::default(),
+            object_counters: ObjectCounters::default(),
+            handle_audit: HandleAudit::default(),
+            submission_recorder: SubmissionRecorder::default(),
+            debug_names: DebugNames::default(),
+            ownership,
+        }
+    }
+
     /// Store a reference to a debug callback. The means that the debug callback won't be dropped
     /// (and destroyed) until this device is! Handy to make sure that you still get validation
     /// while device resources are being dropped/destroyed.
@@ -167,12 +292,19 @@ impl Device {
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkDeviceWaitIdle.html>
     pub fn wait_idle(&self) -> Result<(), DeviceError> {
+        let _span = trace_span!("device_wait_idle", device = ?self.inner.handle());
         let res = unsafe { self.inner.device_wait_idle() };
         res.map_err(DeviceError::WaitIdle)
     }
 
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkQueueWaitIdle.html>
     pub fn queue_wait_idle(&self, queue: &Queue) -> Result<(), DeviceError> {
+        let _span = trace_span!(
+            "queue_wait_idle",
+            queue = ?queue.handle(),
+            queue_name = self.debug_name(queue.handle()).as_deref()
+        );
+        let _external_sync = queue.lock_external_sync();
         let res = unsafe { self.inner.queue_wait_idle(queue.handle()) };
         res.map_err(DeviceError::WaitIdle)
     }
@@ -208,6 +340,120 @@ impl Device {
         unsafe { self.inner.wait_for_fences(&fence_hanles, wait_all, timeout) }
     }
 
+    /// Same as [`Self::wait_for_fences`] but gives up at `deadline`, returning
+    /// [`WaitStatus::TimedOut`].
+    pub fn wait_for_fences_until<'a>(
+        &self,
+        fences: impl IntoIterator<Item = &'a Fence>,
+        wait_all: bool,
+        deadline: impl Into<Deadline>,
+    ) -> VkResult<WaitStatus> {
+        let timeout = deadline.into().timeout_nanoseconds();
+        WaitStatus::from_vk_result(self.wait_for_fences(fences, wait_all, timeout))
+    }
+
+    /// Same as [`Self::wait_idle`] but gives up at `deadline`, returning [`WaitStatus::TimedOut`].
+    /// `vkDeviceWaitIdle` has no timeout so this waits for each of `queues` to go idle instead
+    /// (see [`Queue::wait_idle_with_timeout`]). Pass every queue with submitted work.
+    pub fn wait_idle_with_timeout<'a>(
+        &self,
+        queues: impl IntoIterator<Item = &'a Queue>,
+        deadline: impl Into<Deadline>,
+    ) -> VkResult<WaitStatus> {
+        let deadline = deadline.into();
+        for queue in queues {
+            if queue.wait_idle_with_timeout(deadline)?.is_timed_out() {
+                return Ok(WaitStatus::TimedOut);
+            }
+        }
+        Ok(WaitStatus::Complete)
+    }
+
+    /// Returns a cached ash extension loader e.g. `ash::khr::synchronization2::Device`, creating
+    /// it on first use. Make sure the extension was enabled when creating the device.
+    pub fn extension_loader<T: DeviceExtensionLoader>(&self) -> Arc<T> {
+        self.extension_loaders
+            .get_or_load(|| T::load(self.instance().inner(), &self.inner))
+    }
+
+    /// Returns true if `feature` was enabled in the device create info along with any extension
+    /// it requires at [`Self::effective_api_version`] (see [`Feature::required_extension`]).
+    pub fn feature_enabled(&self, feature: Feature) -> bool {
+        if !self.enabled_features.contains(feature) {
+            return false;
+        }
+        match feature.required_extension(self.effective_api_version()) {
+            Some(extension_name) => self
+                .enabled_extensions
+                .iter()
+                .any(|enabled_extension| enabled_extension.as_c_str() == extension_name),
+            None => true,
+        }
+    }
+
+    /// Start or stop counting created/destroyed pipelines, descriptor sets, command buffers,
+    /// images and buffers. Disabled by default. See [`ObjectCounters`].
+    pub fn set_object_counting(&self, enabled: bool) {
+        self.object_counters.set_enabled(enabled);
+    }
+
+    /// Start or stop the safety audit mode in debug builds. While enabled, dropping a bort
+    /// pipeline, descriptor set, command buffer, image or buffer overwrites the handle stored in
+    /// the wrapper with a sentinel and recording or submitting commands with that sentinel
+    /// panics. Disabled by default and ignored in release builds. See [`HandleAudit`].
+    pub fn set_safety_audit(&self, enabled: bool) {
+        self.handle_audit.set_enabled(enabled);
+    }
+
+    /// Created, destroyed and peak live counts of each object type since counting was enabled.
+    pub fn object_count_report(&self) -> ObjectCountReport {
+        self.object_counters.report()
+    }
+
+    /// Start or stop recording queue submits, swapchain acquires/presents and host timeline
+    /// signals for [`Self::take_submission_graph`]. Disabled by default. See
+    /// [`SubmissionRecorder`].
+    pub fn set_submission_recording(&self, enabled: bool) {
+        self.submission_recorder.set_enabled(enabled);
+    }
+
+    /// The submissions recorded since the last call e.g. over one frame.
+    pub fn take_submission_graph(&self) -> SubmissionGraph {
+        self.submission_recorder.take_graph()
+    }
+
+    /// Names `handle` in the spans of the `tracing` feature and, if `VK_EXT_debug_utils` is
+    /// enabled on the instance, in validation messages and graphics debuggers. Anything after a
+    /// nul character is only kept for the former.
+    pub fn set_debug_name<H: Handle + Copy>(&self, handle: H, name: &str) -> VkResult<()> {
+        self.debug_names.set(handle, name);
+
+        let debug_utils_enabled = self
+            .instance()
+            .enabled_extensions()
+            .iter()
+            .any(|enabled_extension| enabled_extension.as_c_str() == ext::debug_utils::NAME);
+        if !debug_utils_enabled {
+            return Ok(());
+        }
+
+        let name_until_nul = name.split('\0').next().unwrap_or_default();
+        let object_name =
+            CString::new(name_until_nul).expect("name was split at the first nul character");
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::default()
+            .object_handle(handle)
+            .object_name(&object_name);
+        unsafe {
+            self.extension_loader::<ext::debug_utils::Device>()
+                .set_debug_utils_object_name(&name_info)
+        }
+    }
+
+    /// The name given to `handle` with [`Self::set_debug_name`].
+    pub fn debug_name<H: Handle>(&self, handle: H) -> Option<String> {
+        self.debug_names.get(handle)
+    }
+
     // Getters
 
     /// Access the `ash::Device` struct that `self` contains. Allows you to access vulkan device
@@ -227,6 +473,13 @@ impl Device {
         self.physical_device.instance()
     }
 
+    /// The api version usable with this device: the requested instance version clamped to what
+    /// the loader and physical device support.
+    #[inline]
+    pub fn effective_api_version(&self) -> ApiVersion {
+        self.physical_device.effective_api_version()
+    }
+
     #[inline]
     pub fn debug_callback_ref(&self) -> &Option<Arc<DebugCallback>> {
         &self.debug_callback_ref
@@ -241,11 +494,49 @@ impl Device {
     pub fn enabled_layers(&self) -> &Vec<CString> {
         &self.enabled_layers
     }
+
+    /// The features enabled in the device create info. `p_next` pointers are null.
+    #[inline]
+    pub fn enabled_features(&self) -> &PhysicalDeviceFeatures<'static> {
+        &self.enabled_features
+    }
+
+    #[inline]
+    pub fn ownership(&self) -> HandleOwnership {
+        self.ownership
+    }
+
+    #[inline]
+    pub fn object_counters(&self) -> &ObjectCounters {
+        &self.object_counters
+    }
+
+    #[inline]
+    pub fn handle_audit(&self) -> &HandleAudit {
+        &self.handle_audit
+    }
+
+    #[inline]
+    pub fn submission_recorder(&self) -> &SubmissionRecorder {
+        &self.submission_recorder
+    }
+
+    #[inline]
+    pub fn debug_names(&self) -> &DebugNames {
+        &self.debug_names
+    }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        self.wait_idle().expect("vkDeviceWaitIdle");
+        if !self.ownership.is_owned() {
+            return;
+        }
+        // a lost device still needs to be destroyed e.g. by `DeviceRecovery`
+        match self.wait_idle() {
+            Ok(()) | Err(DeviceError::WaitIdle(vk::Result::ERROR_DEVICE_LOST)) => (),
+            Err(e) => panic!("vkDeviceWaitIdle: {}", e),
+        }
         unsafe {
             self.inner.destroy_device(ALLOCATION_CALLBACK_NONE);
         }
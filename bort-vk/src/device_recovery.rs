@@ -0,0 +1,326 @@
+use crate::{
+    DebugCallback, Device, DeviceError, ErrorRecovery, MemoryAllocator, PhysicalDevice,
+    PhysicalDeviceFeatures, Queue, QueueError, VkResultSource,
+};
+#[cfg(feature = "presentation")]
+use crate::{Surface, Swapchain, SwapchainError, SwapchainProperties};
+use ash::vk;
+use log::info;
+use std::{error, ffi::CString, fmt, sync::Arc};
+
+/// The device level objects managed by [`DeviceRecovery`].
+pub struct DeviceContext {
+    pub device: Arc<Device>,
+    /// Queue 0 of each family passed to [`DeviceRecovery::new`], in the same order.
+    pub queues: Vec<Arc<Queue>>,
+    pub memory_allocator: Arc<MemoryAllocator>,
+    /// `Some` if a swapchain was registered with [`DeviceRecovery::set_swapchain`].
+    #[cfg(feature = "presentation")]
+    pub swapchain: Option<Arc<Swapchain>>,
+}
+
+/// Called by [`DeviceRecovery::recover`] with the recreated device objects so that the
+/// application can recreate its own resources (pipelines, buffers, descriptor sets etc.).
+pub type RecreationCallback =
+    Box<dyn FnMut(&DeviceContext) -> Result<(), Box<dyn error::Error + Send + Sync>>>;
+
+/// Recreates the device and everything created from it after `ERROR_DEVICE_LOST` (e.g. a driver
+/// reset or TDR) so that the application can keep running rather than aborting.
+///
+/// [`Self::recover`] tears down the old [`DeviceContext`] in reverse dependency order (swapchain,
+/// memory allocator, queues, device) then recreates the device with the same extensions, layers
+/// and features, followed by the queues, memory allocator, swapchain and finally the registered
+/// recreation callbacks in registration order. Register callbacks for dependencies first.
+///
+/// All other resources created from the old device must be dropped before calling
+/// [`Self::recover`] otherwise the old device can't be destroyed and
+/// [`DeviceRecoveryError::StillReferenced`] is returned.
+pub struct DeviceRecovery {
+    physical_device: Arc<PhysicalDevice>,
+    queue_family_indices: Vec<u32>,
+    extension_names: Vec<CString>,
+    layer_names: Vec<CString>,
+    features: PhysicalDeviceFeatures<'static>,
+    debug_callback_ref: Option<Arc<DebugCallback>>,
+    #[cfg(feature = "presentation")]
+    swapchain_source: Option<(Arc<Surface>, SwapchainProperties)>,
+    recreation_callbacks: Vec<(String, RecreationCallback)>,
+    max_recoveries: Option<u32>,
+    recovery_count: u32,
+}
+
+impl DeviceRecovery {
+    /// Device creation parameters are copied from `device`. One queue (index 0) is recreated for
+    /// each of `queue_family_indices`.
+    pub fn new(device: &Device, queue_family_indices: Vec<u32>) -> Self {
+        Self {
+            physical_device: device.physical_device().clone(),
+            queue_family_indices,
+            extension_names: device.enabled_extensions().clone(),
+            layer_names: device.enabled_layers().clone(),
+            features: *device.enabled_features(),
+            debug_callback_ref: device.debug_callback_ref().clone(),
+            #[cfg(feature = "presentation")]
+            swapchain_source: None,
+            recreation_callbacks: Vec::new(),
+            max_recoveries: Some(3),
+            recovery_count: 0,
+        }
+    }
+
+    /// Recreate a swapchain with the same surface and properties as `swapchain`. Call this again
+    /// after recreating the swapchain to keep the properties (e.g. extent) up to date.
+    #[cfg(feature = "presentation")]
+    pub fn set_swapchain(&mut self, swapchain: &Swapchain) {
+        self.swapchain_source = Some((swapchain.surface().clone(), swapchain.properties().clone()));
+    }
+
+    /// `callback` is called after the device objects have been recreated. Callbacks are called
+    /// in the order they were registered. `name` is used for error messages.
+    pub fn register_callback(&mut self, name: impl Into<String>, callback: RecreationCallback) {
+        self.recreation_callbacks.push((name.into(), callback));
+    }
+
+    /// Give up after this many calls to [`Self::recover`] so that a device which keeps getting
+    /// lost doesn't loop forever. `None` means no limit. Defaults to 3.
+    pub fn set_max_recoveries(&mut self, max_recoveries: Option<u32>) {
+        self.max_recoveries = max_recoveries;
+    }
+
+    /// Whether `error` means the device was lost and [`Self::recover`] should be called.
+    pub fn should_recover(error: &impl VkResultSource) -> bool {
+        ErrorRecovery::from_error(error) == Some(ErrorRecovery::RecreateDevice)
+    }
+
+    /// Tears down `lost_context` and recreates the device objects and application resources.
+    /// See [`DeviceRecovery`].
+    ///
+    /// Returns [`DeviceRecoveryError::StillReferenced`] without recreating anything if the old
+    /// swapchain, memory allocator or device is still referenced outside of `lost_context`. This
+    /// doesn't count as a recovery attempt.
+    pub fn recover(
+        &mut self,
+        lost_context: DeviceContext,
+    ) -> Result<DeviceContext, DeviceRecoveryError> {
+        if let Some(max_recoveries) = self.max_recoveries {
+            if self.recovery_count >= max_recoveries {
+                return Err(DeviceRecoveryError::TooManyRecoveries {
+                    recovery_count: self.recovery_count,
+                });
+            }
+        }
+
+        teardown(lost_context)?;
+
+        self.recovery_count += 1;
+        info!(
+            "recovering from device loss (attempt {})",
+            self.recovery_count
+        );
+
+        let context = self.recreate_device_objects()?;
+
+        for (name, callback) in &mut self.recreation_callbacks {
+            callback(&context).map_err(|error| DeviceRecoveryError::Callback {
+                name: name.clone(),
+                error,
+            })?;
+        }
+
+        Ok(context)
+    }
+
+    fn recreate_device_objects(&self) -> Result<DeviceContext, DeviceRecoveryError> {
+        let queue_priorities = [1.0];
+        let queue_create_infos = self.queue_family_indices.iter().map(|&family_index| {
+            vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(family_index)
+                .queue_priorities(&queue_priorities)
+        });
+        let device = Arc::new(
+            Device::new(
+                self.physical_device.clone(),
+                queue_create_infos,
+                self.features,
+                self.extension_names.clone(),
+                self.layer_names.clone(),
+                self.debug_callback_ref.clone(),
+            )
+            .map_err(DeviceRecoveryError::Device)?,
+        );
+
+        let queues = self
+            .queue_family_indices
+            .iter()
+            .map(|&family_index| Queue::new(device.clone(), family_index, 0).map(Arc::new))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DeviceRecoveryError::Queue)?;
+
+        let memory_allocator = Arc::new(
+            MemoryAllocator::new(device.clone()).map_err(DeviceRecoveryError::MemoryAllocator)?,
+        );
+
+        #[cfg(feature = "presentation")]
+        let swapchain = match &self.swapchain_source {
+            Some((surface, properties)) => Some(Arc::new(
+                Swapchain::new(device.clone(), surface.clone(), properties.clone())
+                    .map_err(DeviceRecoveryError::Swapchain)?,
+            )),
+            None => None,
+        };
+
+        Ok(DeviceContext {
+            device,
+            queues,
+            memory_allocator,
+            #[cfg(feature = "presentation")]
+            swapchain,
+        })
+    }
+
+    // Getters
+
+    /// Number of times [`Self::recover`] has been called.
+    #[inline]
+    pub fn recovery_count(&self) -> u32 {
+        self.recovery_count
+    }
+
+    #[inline]
+    pub fn physical_device(&self) -> &Arc<PhysicalDevice> {
+        &self.physical_device
+    }
+}
+
+// Helper Functions
+
+/// Drops the device objects in reverse dependency order. Fails if any of them are still referenced
+/// elsewhere (and so wouldn't be destroyed).
+fn teardown(lost_context: DeviceContext) -> Result<(), DeviceRecoveryError> {
+    let DeviceContext {
+        device,
+        queues,
+        memory_allocator,
+        #[cfg(feature = "presentation")]
+        swapchain,
+    } = lost_context;
+
+    #[cfg(feature = "presentation")]
+    if let Some(swapchain) = swapchain {
+        check_unshared("swapchain", Arc::strong_count(&swapchain))?;
+        drop(swapchain);
+    }
+    check_unshared("memory allocator", Arc::strong_count(&memory_allocator))?;
+    drop(memory_allocator);
+    drop(queues);
+    check_unshared("device", Arc::strong_count(&device))
+}
+
+fn check_unshared(object: &'static str, strong_count: usize) -> Result<(), DeviceRecoveryError> {
+    if strong_count > 1 {
+        return Err(DeviceRecoveryError::StillReferenced {
+            object,
+            other_reference_count: strong_count - 1,
+        });
+    }
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum DeviceRecoveryError {
+    TooManyRecoveries {
+        recovery_count: u32,
+    },
+    /// The lost `object` is still referenced elsewhere so it (and the device) can't be destroyed.
+    StillReferenced {
+        object: &'static str,
+        other_reference_count: usize,
+    },
+    Device(DeviceError),
+    Queue(QueueError),
+    MemoryAllocator(vk::Result),
+    #[cfg(feature = "presentation")]
+    Swapchain(SwapchainError),
+    Callback {
+        name: String,
+        error: Box<dyn error::Error + Send + Sync>,
+    },
+}
+
+impl fmt::Display for DeviceRecoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyRecoveries { recovery_count } => write!(
+                f,
+                "giving up on device recovery after {} attempts",
+                recovery_count
+            ),
+            Self::StillReferenced {
+                object,
+                other_reference_count,
+            } => write!(
+                f,
+                "lost {} is still referenced {} other time(s) so it can't be destroyed",
+                object, other_reference_count
+            ),
+            Self::Device(e) => write!(f, "failed to recreate device: {}", e),
+            Self::Queue(e) => write!(f, "failed to recreate queue: {}", e),
+            Self::MemoryAllocator(e) => write!(f, "failed to recreate memory allocator: {}", e),
+            #[cfg(feature = "presentation")]
+            Self::Swapchain(e) => write!(f, "failed to recreate swapchain: {}", e),
+            Self::Callback { name, error } => {
+                write!(f, "recreation callback '{}' failed: {}", name, error)
+            }
+        }
+    }
+}
+
+impl error::Error for DeviceRecoveryError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::TooManyRecoveries { .. } | Self::StillReferenced { .. } => None,
+            Self::Device(e) => Some(e),
+            Self::Queue(e) => Some(e),
+            Self::MemoryAllocator(e) => Some(e),
+            #[cfg(feature = "presentation")]
+            Self::Swapchain(e) => Some(e),
+            Self::Callback { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+impl VkResultSource for DeviceRecoveryError {
+    fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Device(e) => e.vk_result(),
+            #[cfg(feature = "presentation")]
+            Self::Swapchain(e) => e.vk_result(),
+            Self::MemoryAllocator(e) => Some(*e),
+            Self::TooManyRecoveries { .. }
+            | Self::StillReferenced { .. }
+            | Self::Queue(_)
+            | Self::Callback { .. } => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn device_recovery_should_recover() {
+    assert!(DeviceRecovery::should_recover(
+        &vk::Result::ERROR_DEVICE_LOST
+    ));
+    #[cfg(feature = "presentation")]
+    assert!(DeviceRecovery::should_recover(&SwapchainError::Creation(
+        vk::Result::ERROR_DEVICE_LOST
+    )));
+    assert!(!DeviceRecovery::should_recover(
+        &vk::Result::ERROR_OUT_OF_DATE_KHR
+    ));
+    assert!(!DeviceRecovery::should_recover(
+        &DeviceRecoveryError::TooManyRecoveries { recovery_count: 3 }
+    ));
+}
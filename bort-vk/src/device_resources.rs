@@ -0,0 +1,150 @@
+use crate::{
+    DeferDrop, DeletionQueue, DescriptorSetLayout, DescriptorSetLayoutCache,
+    DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device, Fence, FencePool, Gpu,
+    GpuDropPolicy, PipelineCache, Sampler, SamplerCache, SamplerProperties, ShutdownGuard,
+};
+use ash::{prelude::VkResult, vk};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Device-wide shared resources behind one handle: a sampler cache, a descriptor set layout
+/// cache, a pipeline cache, a fence pool and a deletion queue. Create one per [`Device`] and share
+/// it (e.g. in an `Arc`) so higher level helpers can be constructed from a single object.
+///
+/// Each subsystem has its own lock so they can be used from multiple threads. Everything that
+/// may still be in use by the device (deferred drops and evicted cache entries) goes through the
+/// deletion queue, so hand it to [`Device::shutdown`] with [`Self::defer_to_shutdown`] before
+/// dropping this.
+pub struct DeviceResources {
+    sampler_cache: Mutex<SamplerCache>,
+    descriptor_set_layout_cache: Mutex<DescriptorSetLayoutCache>,
+    pipeline_cache: Arc<PipelineCache>,
+    fence_pool: Mutex<FencePool>,
+    deletion_queue: Mutex<DeletionQueue>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl DeviceResources {
+    /// `frames_in_flight` determines how long the deletion queue holds on to resources.
+    pub fn new(device: Arc<Device>, frames_in_flight: usize) -> VkResult<Self> {
+        let pipeline_cache = Arc::new(PipelineCache::new(
+            device.clone(),
+            vk::PipelineCacheCreateInfo::default(),
+        )?);
+
+        Ok(Self {
+            sampler_cache: Mutex::new(SamplerCache::new(device.clone())),
+            descriptor_set_layout_cache: Mutex::new(DescriptorSetLayoutCache::new(device.clone())),
+            pipeline_cache,
+            fence_pool: Mutex::new(FencePool::new(device.clone())),
+            deletion_queue: Mutex::new(DeletionQueue::new(frames_in_flight)),
+            device,
+        })
+    }
+
+    /// See [`SamplerCache::get_or_create`].
+    pub fn sampler(&self, properties: SamplerProperties) -> VkResult<Arc<Sampler>> {
+        self.sampler_cache().get_or_create(properties)
+    }
+
+    /// See [`DescriptorSetLayoutCache::get_or_create`].
+    pub fn descriptor_set_layout(
+        &self,
+        properties: DescriptorSetLayoutProperties,
+    ) -> Result<Arc<DescriptorSetLayout>, DescriptorSetLayoutError> {
+        self.descriptor_set_layout_cache().get_or_create(properties)
+    }
+
+    /// See [`FencePool::acquire`].
+    pub fn acquire_fence(&self) -> VkResult<Fence> {
+        self.fence_pool().acquire()
+    }
+
+    /// See [`FencePool::release`].
+    pub fn release_fence(&self, fence: Fence) -> VkResult<()> {
+        self.fence_pool().release(fence)
+    }
+
+    /// See [`DeletionQueue::push`].
+    pub fn defer_drop<T: Send + 'static>(&self, resource: T) {
+        self.deletion_queue().push(resource);
+    }
+
+    /// Wraps `resource` in a [`Gpu`] pointer which retires it to this deletion queue when dropped.
+    pub fn gpu<T: GpuDropPolicy + Send + 'static>(self: &Arc<Self>, resource: T) -> Gpu<T> {
+        Gpu::new(self.clone(), resource)
+    }
+
+    /// Advances the deletion queue (see [`DeletionQueue::next_frame`] for when to call this) and
+    /// evicts cached samplers and layouts that are no longer referenced elsewhere. Evicted entries
+    /// may still be used by frames in flight so they're pushed to the deletion queue rather than
+    /// destroyed.
+    pub fn next_frame(&self) {
+        let unused_samplers = self.sampler_cache().take_unused();
+        let unused_layouts = self.descriptor_set_layout_cache().take_unused();
+
+        let mut deletion_queue = self.deletion_queue();
+        deletion_queue.next_frame();
+        if !unused_samplers.is_empty() {
+            deletion_queue.push(unused_samplers);
+        }
+        if !unused_layouts.is_empty() {
+            deletion_queue.push(unused_layouts);
+        }
+    }
+
+    /// Moves the deletion queue contents and the cached samplers and layouts to `guard` so
+    /// [`Device::shutdown`] drops them once the device is idle.
+    pub fn defer_to_shutdown(&self, guard: &mut ShutdownGuard) {
+        for resource in self.deletion_queue().take_all() {
+            guard.defer_drop(resource);
+        }
+        guard.defer_drop(self.sampler_cache().take_all());
+        guard.defer_drop(self.descriptor_set_layout_cache().take_all());
+    }
+
+    // Getters
+
+    /// Panics if the lock is poisoned.
+    pub fn sampler_cache(&self) -> MutexGuard<'_, SamplerCache> {
+        self.sampler_cache
+            .lock()
+            .expect("sampler cache lock poisoned")
+    }
+
+    /// Panics if the lock is poisoned.
+    pub fn descriptor_set_layout_cache(&self) -> MutexGuard<'_, DescriptorSetLayoutCache> {
+        self.descriptor_set_layout_cache
+            .lock()
+            .expect("descriptor set layout cache lock poisoned")
+    }
+
+    #[inline]
+    pub fn pipeline_cache(&self) -> &Arc<PipelineCache> {
+        &self.pipeline_cache
+    }
+
+    /// Panics if the lock is poisoned.
+    pub fn fence_pool(&self) -> MutexGuard<'_, FencePool> {
+        self.fence_pool.lock().expect("fence pool lock poisoned")
+    }
+
+    /// Panics if the lock is poisoned.
+    pub fn deletion_queue(&self) -> MutexGuard<'_, DeletionQueue> {
+        self.deletion_queue
+            .lock()
+            .expect("deletion queue lock poisoned")
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+impl DeferDrop for DeviceResources {
+    fn defer_drop_boxed(&self, resource: Box<dyn Send>) {
+        self.deletion_queue().push_boxed(resource);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{
+    allocation_info_cpu_accessible, AllocationAccess, AllocatorAccess, Buffer, BufferProperties,
+    DescriptorSet, DescriptorSetLayoutBinding, FrameIndex, MemoryError,
+};
+use ash::{prelude::VkResult, vk};
+use bort_vma::AllocationCreateInfo;
+use std::{error, fmt, marker::PhantomData, mem, ptr, sync::Arc};
+
+/// A uniform buffer holding an array of `T` where each element is padded to
+/// `minUniformBufferOffsetAlignment` so it can be selected with a dynamic offset when binding a
+/// `UNIFORM_BUFFER_DYNAMIC` descriptor. One descriptor set can then serve per-object data for
+/// many objects by passing [`Self::dynamic_offset`] to
+/// [`CommandBuffer::bind_descriptor_sets`](crate::CommandBuffer::bind_descriptor_sets) for each
+/// draw.
+///
+/// The memory must be host visible.
+pub struct DynamicUniformBuffer<T: Copy> {
+    buffer: Buffer,
+    element_stride: vk::DeviceSize,
+    capacity: u32,
+    _element_type: PhantomData<T>,
+}
+
+impl<T: Copy> DynamicUniformBuffer<T> {
+    /// Creates a buffer with room for `capacity` elements.
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        capacity: u32,
+        allocation_info: AllocationCreateInfo,
+    ) -> VkResult<Self> {
+        let min_offset_alignment = alloc_access
+            .device()
+            .physical_device()
+            .min_uniform_buffer_offset_alignment();
+        let element_stride = element_stride::<T>(min_offset_alignment);
+
+        let buffer_properties = BufferProperties::new_default(
+            element_stride * capacity.max(1) as vk::DeviceSize,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+        );
+        let buffer = Buffer::new(alloc_access, buffer_properties, allocation_info)?;
+
+        Ok(Self {
+            buffer,
+            element_stride,
+            capacity,
+            _element_type: PhantomData,
+        })
+    }
+
+    /// Same as [`Self::new`] with memory that is host visible and preferably host coherent.
+    pub fn new_cpu_accessible(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        capacity: u32,
+    ) -> VkResult<Self> {
+        Self::new(alloc_access, capacity, allocation_info_cpu_accessible())
+    }
+
+    /// Writes `element` to the slot at `index`.
+    pub fn write(&mut self, index: u32, element: T) -> Result<(), DynamicUniformBufferError> {
+        if index >= self.capacity {
+            return Err(DynamicUniformBufferError::IndexOutOfRange {
+                index,
+                capacity: self.capacity,
+            });
+        }
+        self.buffer
+            .write_struct(element, self.element_offset(index))
+            .map_err(DynamicUniformBufferError::Memory)
+    }
+
+    /// Writes `elements` to the slots starting at index 0, mapping the memory once.
+    pub fn write_all(&mut self, elements: &[T]) -> Result<(), DynamicUniformBufferError> {
+        self.write_range(0, elements)
+    }
+
+    /// Writes `elements` to the slots starting at `first_index`, mapping the memory once and only
+    /// flushing the written range.
+    pub fn write_range(
+        &mut self,
+        first_index: u32,
+        elements: &[T],
+    ) -> Result<(), DynamicUniformBufferError> {
+        let available = self.capacity.saturating_sub(first_index) as usize;
+        if elements.len() > available {
+            return Err(DynamicUniformBufferError::TooManyElements {
+                element_count: elements.len(),
+                capacity: available as u32,
+            });
+        }
+        if elements.is_empty() {
+            return Ok(());
+        }
+
+        let first_offset = self.element_offset(first_index);
+        let memory_allocation = self.buffer.memory_allocation_mut();
+        let mapped_memory =
+            unsafe { memory_allocation.map_memory() }.map_err(DynamicUniformBufferError::Memory)?;
+
+        for (index, &element) in elements.iter().enumerate() {
+            let element_offset = first_offset + index * self.element_stride as usize;
+            unsafe {
+                let element_ptr = mapped_memory.add(element_offset) as *mut T;
+                ptr::write_unaligned(element_ptr, element);
+            }
+        }
+
+        let written_size = elements.len() * self.element_stride as usize;
+        let flush_res = memory_allocation.flush_allocation(first_offset, written_size);
+        unsafe { memory_allocation.unmap_memory() };
+        flush_res.map_err(DynamicUniformBufferError::Memory)
+    }
+
+    /// The dynamic offset that selects the element at `index`.
+    #[inline]
+    pub fn dynamic_offset(&self, index: u32) -> u32 {
+        self.element_offset(index) as u32
+    }
+
+    /// Buffer info covering a single element. The dynamic offset is added to `offset` (0) when
+    /// binding.
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer.handle(),
+            offset: 0,
+            range: mem::size_of::<T>() as vk::DeviceSize,
+        }
+    }
+
+    /// Descriptor write for a `UNIFORM_BUFFER_DYNAMIC` binding with `buffer_info` (see
+    /// [`Self::descriptor_buffer_info`]). Submit with
+    /// [`Device::update_descriptor_sets`](crate::Device::update_descriptor_sets).
+    pub fn write_descriptor_set<'a>(
+        descriptor_set: &DescriptorSet,
+        binding: u32,
+        buffer_info: &'a vk::DescriptorBufferInfo,
+    ) -> vk::WriteDescriptorSet<'a> {
+        vk::WriteDescriptorSet::default()
+            .dst_set(descriptor_set.handle())
+            .dst_binding(binding)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .buffer_info(std::slice::from_ref(buffer_info))
+    }
+
+    /// Layout binding for a single `UNIFORM_BUFFER_DYNAMIC` descriptor.
+    pub fn layout_binding(
+        binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> DescriptorSetLayoutBinding {
+        DescriptorSetLayoutBinding {
+            binding,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+            descriptor_count: 1,
+            stage_flags,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    fn element_offset(&self, index: u32) -> usize {
+        (index as vk::DeviceSize * self.element_stride) as usize
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Size of each padded element in bytes.
+    #[inline]
+    pub fn element_stride(&self) -> vk::DeviceSize {
+        self.element_stride
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+/// A [`DynamicUniformBuffer`] split into one region of `capacity_per_frame` elements per frame
+/// in flight. Each frame writes its per-object data (e.g. transforms) to its own region with
+/// [`Self::write_frame`] so the CPU never overwrites data a previous frame is still reading on the
+/// GPU, and a single `UNIFORM_BUFFER_DYNAMIC` descriptor set serves every frame and object via
+/// [`Self::dynamic_offset`].
+///
+/// Index the ring with [`PerFrame::current_index`](crate::PerFrame::current_index) after waiting
+/// for that frame's fence. See the `uniform_ring` example for a complete render loop.
+pub struct DynamicUniformRing<T: Copy> {
+    uniform_buffer: DynamicUniformBuffer<T>,
+    capacity_per_frame: u32,
+    frame_count: usize,
+}
+
+impl<T: Copy> DynamicUniformRing<T> {
+    /// Creates a host visible (and preferably host coherent) buffer with room for
+    /// `capacity_per_frame` elements for each of the `frame_count` frames in flight. Returns
+    /// [`DynamicUniformBufferError::CapacityOverflow`] if the total element count doesn't fit in
+    /// a `u32`.
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        frame_count: usize,
+        capacity_per_frame: u32,
+    ) -> Result<Self, DynamicUniformBufferError> {
+        let capacity = ring_capacity(frame_count, capacity_per_frame).ok_or(
+            DynamicUniformBufferError::CapacityOverflow {
+                capacity_per_frame,
+                frame_count,
+            },
+        )?;
+        let uniform_buffer = DynamicUniformBuffer::new_cpu_accessible(alloc_access, capacity)
+            .map_err(DynamicUniformBufferError::Creation)?;
+        Ok(Self {
+            uniform_buffer,
+            capacity_per_frame,
+            frame_count,
+        })
+    }
+
+    /// Writes `elements` to the region of `frame`, starting at object index 0.
+    pub fn write_frame(
+        &mut self,
+        frame: FrameIndex,
+        elements: &[T],
+    ) -> Result<(), DynamicUniformBufferError> {
+        self.check_frame(frame)?;
+        if elements.len() > self.capacity_per_frame as usize {
+            return Err(DynamicUniformBufferError::TooManyElements {
+                element_count: elements.len(),
+                capacity: self.capacity_per_frame,
+            });
+        }
+        let first_index = ring_slot(frame.value(), self.capacity_per_frame, 0);
+        self.uniform_buffer.write_range(first_index, elements)
+    }
+
+    /// The dynamic offset that selects element `index` written for `frame`.
+    pub fn dynamic_offset(
+        &self,
+        frame: FrameIndex,
+        index: u32,
+    ) -> Result<u32, DynamicUniformBufferError> {
+        self.check_frame(frame)?;
+        if index >= self.capacity_per_frame {
+            return Err(DynamicUniformBufferError::IndexOutOfRange {
+                index,
+                capacity: self.capacity_per_frame,
+            });
+        }
+        let slot = ring_slot(frame.value(), self.capacity_per_frame, index);
+        Ok(self.uniform_buffer.dynamic_offset(slot))
+    }
+
+    /// `frame` must come from a [`PerFrame`](crate::PerFrame) with at most `frame_count` frames.
+    fn check_frame(&self, frame: FrameIndex) -> Result<(), DynamicUniformBufferError> {
+        if frame.value() >= self.frame_count {
+            return Err(DynamicUniformBufferError::FrameOutOfRange {
+                frame_index: frame.value(),
+                frame_count: self.frame_count,
+            });
+        }
+        Ok(())
+    }
+
+    /// See [`DynamicUniformBuffer::descriptor_buffer_info`].
+    #[inline]
+    pub fn descriptor_buffer_info(&self) -> vk::DescriptorBufferInfo {
+        self.uniform_buffer.descriptor_buffer_info()
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn uniform_buffer(&self) -> &DynamicUniformBuffer<T> {
+        &self.uniform_buffer
+    }
+
+    #[inline]
+    pub fn capacity_per_frame(&self) -> u32 {
+        self.capacity_per_frame
+    }
+
+    /// The number of frames in flight.
+    #[inline]
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+}
+
+/// Total element count of a ring. `None` on overflow.
+fn ring_capacity(frame_count: usize, capacity_per_frame: u32) -> Option<u32> {
+    capacity_per_frame.checked_mul(u32::try_from(frame_count).ok()?)
+}
+
+/// Index of element `index` of frame `frame_index` in the underlying buffer.
+fn ring_slot(frame_index: usize, capacity_per_frame: u32, index: u32) -> u32 {
+    frame_index as u32 * capacity_per_frame + index
+}
+
+/// Size of `T` rounded up to a multiple of `min_offset_alignment` (and the alignment of `T`).
+fn element_stride<T>(min_offset_alignment: vk::DeviceSize) -> vk::DeviceSize {
+    let alignment = min_offset_alignment.max(mem::align_of::<T>() as vk::DeviceSize);
+    let size = (mem::size_of::<T>() as vk::DeviceSize).max(1);
+    size.div_ceil(alignment) * alignment
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum DynamicUniformBufferError {
+    IndexOutOfRange {
+        index: u32,
+        capacity: u32,
+    },
+    TooManyElements {
+        element_count: usize,
+        capacity: u32,
+    },
+    /// The frame index is beyond the frames in flight of a [`DynamicUniformRing`].
+    FrameOutOfRange {
+        frame_index: usize,
+        frame_count: usize,
+    },
+    /// `capacity_per_frame * frame_count` of a [`DynamicUniformRing`] overflows a `u32`.
+    CapacityOverflow {
+        capacity_per_frame: u32,
+        frame_count: usize,
+    },
+    Creation(vk::Result),
+    Memory(MemoryError),
+}
+
+impl fmt::Display for DynamicUniformBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexOutOfRange { index, capacity } => write!(
+                f,
+                "element index {} is out of range for a dynamic uniform buffer with capacity {}",
+                index, capacity
+            ),
+            Self::TooManyElements {
+                element_count,
+                capacity,
+            } => write!(
+                f,
+                "{} elements don't fit in a dynamic uniform buffer with capacity {}",
+                element_count, capacity
+            ),
+            Self::FrameOutOfRange {
+                frame_index,
+                frame_count,
+            } => write!(
+                f,
+                "frame index {} is out of range for a dynamic uniform ring with {} frames",
+                frame_index, frame_count
+            ),
+            Self::CapacityOverflow {
+                capacity_per_frame,
+                frame_count,
+            } => write!(
+                f,
+                "dynamic uniform ring capacity of {} elements for each of {} frames overflows",
+                capacity_per_frame, frame_count
+            ),
+            Self::Creation(e) => write!(f, "failed to create dynamic uniform buffer: {}", e),
+            Self::Memory(e) => write!(f, "failed to write dynamic uniform buffer: {}", e),
+        }
+    }
+}
+
+impl error::Error for DynamicUniformBufferError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Creation(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn element_stride_rounds_up_to_alignment() {
+    assert_eq!(element_stride::<[f32; 4]>(256), 256);
+    assert_eq!(element_stride::<[f32; 80]>(256), 512);
+    assert_eq!(element_stride::<[f32; 16]>(64), 64);
+    assert_eq!(element_stride::<u8>(1), 1);
+}
+
+#[test]
+fn ring_slots_of_each_frame_dont_overlap() {
+    let frames = crate::PerFrame::from_fn(3, |frame| frame);
+    let capacity_per_frame = 4;
+    let mut slots: Vec<u32> = frames
+        .iter()
+        .flat_map(|frame| {
+            (0..capacity_per_frame).map(|index| ring_slot(frame.value(), capacity_per_frame, index))
+        })
+        .collect();
+    slots.sort_unstable();
+    assert_eq!(slots, (0..12).collect::<Vec<u32>>());
+}
+
+#[test]
+fn ring_capacity_overflow() {
+    assert_eq!(ring_capacity(3, 4), Some(12));
+    assert_eq!(ring_capacity(2, u32::MAX), None);
+    assert_eq!(ring_capacity(usize::MAX, 1), None);
+}
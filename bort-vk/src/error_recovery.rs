@@ -0,0 +1,259 @@
+#[cfg(feature = "presentation")]
+use crate::SwapchainError;
+use crate::{DeviceError, MemoryError, ResourceInitError};
+use ash::{prelude::VkResult, vk};
+use std::fmt;
+
+/// How an application can recover from a `vk::Result` error code. Saves pattern matching on raw
+/// vulkan codes in every render loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorRecovery {
+    /// The operation didn't complete in time (`TIMEOUT`, `NOT_READY`). Try again.
+    Retry,
+    /// The swapchain no longer matches the surface (`ERROR_OUT_OF_DATE_KHR`, `SUBOPTIMAL_KHR`).
+    RecreateSwapchain,
+    /// The surface is no longer usable (`ERROR_SURFACE_LOST_KHR`). Recreate the surface and
+    /// swapchain.
+    RecreateSurface,
+    /// Device memory or a pool was exhausted (`ERROR_OUT_OF_DEVICE_MEMORY`,
+    /// `ERROR_OUT_OF_POOL_MEMORY`, `ERROR_FRAGMENTED_POOL`, `ERROR_FRAGMENTATION`). Free resources,
+    /// allocate a new pool or defragment and try again.
+    FreeDeviceMemory,
+    /// The logical device was lost (`ERROR_DEVICE_LOST`). The device and everything created from
+    /// it must be recreated.
+    RecreateDevice,
+    /// Host memory was exhausted (`ERROR_OUT_OF_HOST_MEMORY`). There's usually nothing to do but
+    /// shut down.
+    OutOfHostMemory,
+    /// Indicates a bug or an unsupported configuration. Not recoverable at runtime.
+    Unrecoverable,
+}
+
+/// Broad categories of [`ErrorRecovery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Can be recovered from without recreating the device.
+    Retryable,
+    /// The device must be recreated.
+    FatalDevice,
+    /// The application can't continue.
+    FatalHost,
+}
+
+impl ErrorRecovery {
+    pub fn from_vk_result(result: vk::Result) -> Self {
+        match result {
+            vk::Result::TIMEOUT | vk::Result::NOT_READY => Self::Retry,
+            vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::SUBOPTIMAL_KHR => {
+                Self::RecreateSwapchain
+            }
+            vk::Result::ERROR_SURFACE_LOST_KHR => Self::RecreateSurface,
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+            | vk::Result::ERROR_OUT_OF_POOL_MEMORY
+            | vk::Result::ERROR_FRAGMENTED_POOL
+            | vk::Result::ERROR_FRAGMENTATION => Self::FreeDeviceMemory,
+            vk::Result::ERROR_DEVICE_LOST => Self::RecreateDevice,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Self::OutOfHostMemory,
+            _ => Self::Unrecoverable,
+        }
+    }
+
+    /// Returns `None` if `error` wasn't caused by a vulkan call.
+    pub fn from_error(error: &impl VkResultSource) -> Option<Self> {
+        error.vk_result().map(Self::from_vk_result)
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Retry
+            | Self::RecreateSwapchain
+            | Self::RecreateSurface
+            | Self::FreeDeviceMemory => ErrorCategory::Retryable,
+            Self::RecreateDevice => ErrorCategory::FatalDevice,
+            Self::OutOfHostMemory | Self::Unrecoverable => ErrorCategory::FatalHost,
+        }
+    }
+
+    #[inline]
+    pub fn is_retryable(&self) -> bool {
+        self.category() == ErrorCategory::Retryable
+    }
+
+    /// A short description of what the application should do.
+    pub fn suggested_action(&self) -> &'static str {
+        match self {
+            Self::Retry => "try the operation again",
+            Self::RecreateSwapchain => "recreate the swapchain",
+            Self::RecreateSurface => "recreate the surface and swapchain",
+            Self::FreeDeviceMemory => "free device memory or descriptor pools and try again",
+            Self::RecreateDevice => "recreate the device and all resources created from it",
+            Self::OutOfHostMemory => "free host memory or shut down",
+            Self::Unrecoverable => "shut down",
+        }
+    }
+}
+
+impl fmt::Display for ErrorRecovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.suggested_action())
+    }
+}
+
+/// The outcome of rendering and presenting a frame, for the render loop to act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOutcome {
+    Presented,
+    /// The frame may or may not have been presented but the swapchain must be recreated before
+    /// the next one.
+    RecreateSwapchain,
+    /// The surface and swapchain must be recreated.
+    RecreateSurface,
+    /// The device must be recreated.
+    DeviceLost,
+    /// Some other error. See [`ErrorRecovery::from_vk_result`].
+    Error(vk::Result),
+    /// Rendering is suspended (e.g. an Android app is paused or the window is minimized) so no
+    /// frame was rendered. Skip frames until the swapchain is resumed (see
+    /// [`SuspendedSwapchain`](crate::SuspendedSwapchain)) or the window has a non-zero extent
+    /// again. Also see [`AcquireStatus::Suspended`](crate::AcquireStatus::Suspended).
+    Suspended,
+}
+
+impl FrameOutcome {
+    /// Classifies the result of [`Swapchain::queue_present`](crate::Swapchain::queue_present)
+    /// where `Ok(true)` means the swapchain is suboptimal.
+    pub fn from_present_result(result: VkResult<bool>) -> Self {
+        match result {
+            Ok(false) => Self::Presented,
+            Ok(true) => Self::RecreateSwapchain,
+            Err(e) => Self::from_vk_result(e),
+        }
+    }
+
+    pub fn from_vk_result(result: vk::Result) -> Self {
+        if result == vk::Result::SUCCESS {
+            return Self::Presented;
+        }
+        match ErrorRecovery::from_vk_result(result) {
+            ErrorRecovery::RecreateSwapchain => Self::RecreateSwapchain,
+            ErrorRecovery::RecreateSurface => Self::RecreateSurface,
+            ErrorRecovery::RecreateDevice => Self::DeviceLost,
+            _ => Self::Error(result),
+        }
+    }
+
+    #[inline]
+    pub fn is_presented(&self) -> bool {
+        *self == Self::Presented
+    }
+}
+
+/// Errors which may have been caused by a failed vulkan call.
+pub trait VkResultSource {
+    /// The `vk::Result` returned by the failed call, if any.
+    fn vk_result(&self) -> Option<vk::Result>;
+}
+
+impl VkResultSource for vk::Result {
+    fn vk_result(&self) -> Option<vk::Result> {
+        Some(*self)
+    }
+}
+
+impl VkResultSource for DeviceError {
+    fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Creation(e) | Self::WaitIdle(e) => Some(*e),
+            Self::ExtensionStringConversion(_) | Self::LayerStringConversion(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "presentation")]
+impl VkResultSource for SwapchainError {
+    fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::GetPhysicalDeviceSurfaceCapabilities(e)
+            | Self::GetPhysicalDeviceSurfacePresentModes(e)
+            | Self::Creation(e)
+            | Self::GetSwapchainImages(e) => Some(*e),
+            Self::ZeroExtent { .. } => None,
+        }
+    }
+}
+
+impl VkResultSource for MemoryError {
+    fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Mapping(e) | Self::Flushing(e) | Self::Invalidating(e) => Some(*e),
+            _ => None,
+        }
+    }
+}
+
+impl VkResultSource for ResourceInitError {
+    fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            Self::Creation(e) | Self::StagingBufferCreation(e) => Some(e.result),
+            Self::CommandBufferAllocation(e)
+            | Self::CommandBufferRecording(e)
+            | Self::FenceCreation(e)
+            | Self::Submission(e)
+            | Self::FenceWait(e) => Some(*e),
+            Self::HostWrite(e) | Self::StagingBufferWrite(e) => e.vk_result(),
+            Self::DataSizeTooBig { .. }
+            | Self::DataSizeMismatch { .. }
+            | Self::FormatNotCompressed(_)
+            | Self::UnknownTexelSize(_)
+            | Self::CombinedDepthStencilFormat(_) => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn error_recovery_categories() {
+    let out_of_date = ErrorRecovery::from_vk_result(vk::Result::ERROR_OUT_OF_DATE_KHR);
+    assert_eq!(out_of_date, ErrorRecovery::RecreateSwapchain);
+    assert!(out_of_date.is_retryable());
+    assert_eq!(
+        ErrorRecovery::from_vk_result(vk::Result::ERROR_DEVICE_LOST).category(),
+        ErrorCategory::FatalDevice
+    );
+    assert_eq!(
+        ErrorRecovery::from_vk_result(vk::Result::ERROR_OUT_OF_HOST_MEMORY).category(),
+        ErrorCategory::FatalHost
+    );
+    #[cfg(feature = "presentation")]
+    assert_eq!(
+        ErrorRecovery::from_error(&SwapchainError::Creation(vk::Result::ERROR_DEVICE_LOST)),
+        Some(ErrorRecovery::RecreateDevice)
+    );
+    assert_eq!(
+        ErrorRecovery::from_error(&ResourceInitError::FormatNotCompressed(
+            vk::Format::R8_UNORM
+        )),
+        None
+    );
+}
+
+#[test]
+fn frame_outcome_from_present_result() {
+    assert_eq!(
+        FrameOutcome::from_present_result(Ok(false)),
+        FrameOutcome::Presented
+    );
+    assert_eq!(
+        FrameOutcome::from_present_result(Ok(true)),
+        FrameOutcome::RecreateSwapchain
+    );
+    assert_eq!(
+        FrameOutcome::from_present_result(Err(vk::Result::ERROR_OUT_OF_DATE_KHR)),
+        FrameOutcome::RecreateSwapchain
+    );
+    assert_eq!(
+        FrameOutcome::from_present_result(Err(vk::Result::ERROR_DEVICE_LOST)),
+        FrameOutcome::DeviceLost
+    );
+}
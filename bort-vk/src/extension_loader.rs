@@ -0,0 +1,119 @@
+use ash::{amd, ext, khr, nv, Entry};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// An ash device extension loader e.g. `ash::khr::synchronization2::Device`. Implemented for
+/// the common ash loaders; implement it for any others you need.
+pub trait DeviceExtensionLoader: Any + Send + Sync {
+    fn load(instance: &ash::Instance, device: &ash::Device) -> Self;
+}
+
+/// An ash instance extension loader e.g. `ash::khr::surface::Instance`. Implemented for the
+/// common ash loaders; implement it for any others you need.
+pub trait InstanceExtensionLoader: Any + Send + Sync {
+    fn load(entry: &Entry, instance: &ash::Instance) -> Self;
+}
+
+macro_rules! impl_device_extension_loaders {
+    ($($loader:ty),* $(,)?) => {
+        $(
+            impl DeviceExtensionLoader for $loader {
+                #[inline]
+                fn load(instance: &ash::Instance, device: &ash::Device) -> Self {
+                    Self::new(instance, device)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_instance_extension_loaders {
+    ($($loader:ty),* $(,)?) => {
+        $(
+            impl InstanceExtensionLoader for $loader {
+                #[inline]
+                fn load(entry: &Entry, instance: &ash::Instance) -> Self {
+                    Self::new(entry, instance)
+                }
+            }
+        )*
+    };
+}
+
+impl_device_extension_loaders!(
+    khr::acceleration_structure::Device,
+    khr::buffer_device_address::Device,
+    khr::copy_commands2::Device,
+    khr::create_renderpass2::Device,
+    khr::deferred_host_operations::Device,
+    khr::draw_indirect_count::Device,
+    khr::dynamic_rendering::Device,
+    khr::maintenance1::Device,
+    khr::maintenance4::Device,
+    khr::maintenance5::Device,
+    khr::maintenance6::Device,
+    khr::push_descriptor::Device,
+    khr::ray_tracing_pipeline::Device,
+    khr::swapchain::Device,
+    khr::synchronization2::Device,
+    khr::timeline_semaphore::Device,
+    ext::attachment_feedback_loop_dynamic_state::Device,
+    ext::debug_utils::Device,
+    ext::descriptor_buffer::Device,
+    ext::extended_dynamic_state::Device,
+    ext::extended_dynamic_state2::Device,
+    ext::extended_dynamic_state3::Device,
+    ext::host_query_reset::Device,
+    ext::mesh_shader::Device,
+    ext::opacity_micromap::Device,
+    ext::shader_object::Device,
+    ext::swapchain_maintenance1::Device,
+    amd::buffer_marker::Device,
+    nv::device_diagnostic_checkpoints::Device,
+    nv::low_latency2::Device,
+);
+
+impl_instance_extension_loaders!(
+    khr::cooperative_matrix::Instance,
+    khr::get_physical_device_properties2::Instance,
+    khr::get_surface_capabilities2::Instance,
+    khr::surface::Instance,
+    ext::debug_utils::Instance,
+);
+
+/// Lazily created extension loaders keyed by type.
+#[derive(Default)]
+pub(crate) struct ExtensionLoaderCache {
+    loaders: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl ExtensionLoaderCache {
+    /// Returns the cached loader of type `T` or creates and caches one with `load`.
+    pub(crate) fn get_or_load<T: Any + Send + Sync>(&self, load: impl FnOnce() -> T) -> Arc<T> {
+        let mut loaders = self
+            .loaders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let loader = loaders
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Arc::new(load()))
+            .clone();
+        loader
+            .downcast::<T>()
+            .expect("extension loaders are keyed by their type id")
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn extension_loader_cache_loads_once_per_type() {
+    let cache = ExtensionLoaderCache::default();
+    let first = cache.get_or_load(|| 1_u32);
+    let second = cache.get_or_load(|| 2_u32);
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(*cache.get_or_load(|| 3_u64), 3);
+}
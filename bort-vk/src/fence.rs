@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use crate::{Deadline, Device, DeviceOwned, WaitStatus, ALLOCATION_CALLBACK_NONE};
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
@@ -48,6 +48,11 @@ impl Fence {
         }
     }
 
+    /// Same as [`Self::wait`] but gives up at `deadline`, returning [`WaitStatus::TimedOut`].
+    pub fn wait_until(&self, deadline: impl Into<Deadline>) -> VkResult<WaitStatus> {
+        WaitStatus::from_vk_result(self.wait(deadline.into().timeout_nanoseconds()))
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkResetFences.html>
     pub fn reset(&self) -> VkResult<()> {
         unsafe { self.device.inner().reset_fences(&[self.handle]) }
@@ -0,0 +1,49 @@
+use crate::{Device, Fence};
+use ash::prelude::VkResult;
+use std::sync::Arc;
+
+/// Recycles unsignalled fences to avoid creating and destroying one for every submission.
+pub struct FencePool {
+    free_fences: Vec<Fence>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl FencePool {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            free_fences: Vec::new(),
+            device,
+        }
+    }
+
+    /// Returns an unsignalled fence, creating one if the pool is empty.
+    pub fn acquire(&mut self) -> VkResult<Fence> {
+        match self.free_fences.pop() {
+            Some(fence) => Ok(fence),
+            None => Fence::new_unsignalled(self.device.clone()),
+        }
+    }
+
+    /// Resets `fence` and returns it to the pool. Make sure the device is no longer using it
+    /// e.g. it has been waited on.
+    pub fn release(&mut self, fence: Fence) -> VkResult<()> {
+        fence.reset()?;
+        self.free_fences.push(fence);
+        Ok(())
+    }
+
+    // Getters
+
+    /// Number of fences ready to be acquired without creating a new one.
+    #[inline]
+    pub fn free_count(&self) -> usize {
+        self.free_fences.len()
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
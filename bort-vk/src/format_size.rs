@@ -0,0 +1,220 @@
+use crate::{aspect_mask_from_format, CompressedBlockInfo};
+use ash::vk;
+
+/// Size in bytes of a texel of an uncompressed `format` as laid out in a buffer for
+/// `vkCmdCopyBufferToImage`/`vkCmdCopyImageToBuffer`. Depth and stencil only formats return the
+/// size of their buffer representation (e.g. 4 for `X8_D24_UNORM_PACK32`). Returns `None` for
+/// compressed formats (see [`CompressedBlockInfo`]), combined depth/stencil formats (each aspect
+/// is copied separately with a different size) and formats this doesn't know about (e.g.
+/// multi-planar).
+pub fn format_texel_size(format: vk::Format) -> Option<u32> {
+    let texel_size = match format {
+        vk::Format::R4G4_UNORM_PACK8
+        | vk::Format::R8_UNORM
+        | vk::Format::R8_SNORM
+        | vk::Format::R8_USCALED
+        | vk::Format::R8_SSCALED
+        | vk::Format::R8_UINT
+        | vk::Format::R8_SINT
+        | vk::Format::R8_SRGB
+        | vk::Format::S8_UINT => 1,
+
+        vk::Format::R4G4B4A4_UNORM_PACK16
+        | vk::Format::B4G4R4A4_UNORM_PACK16
+        | vk::Format::R5G6B5_UNORM_PACK16
+        | vk::Format::B5G6R5_UNORM_PACK16
+        | vk::Format::R5G5B5A1_UNORM_PACK16
+        | vk::Format::B5G5R5A1_UNORM_PACK16
+        | vk::Format::A1R5G5B5_UNORM_PACK16
+        | vk::Format::R8G8_UNORM
+        | vk::Format::R8G8_SNORM
+        | vk::Format::R8G8_USCALED
+        | vk::Format::R8G8_SSCALED
+        | vk::Format::R8G8_UINT
+        | vk::Format::R8G8_SINT
+        | vk::Format::R8G8_SRGB
+        | vk::Format::R16_UNORM
+        | vk::Format::R16_SNORM
+        | vk::Format::R16_USCALED
+        | vk::Format::R16_SSCALED
+        | vk::Format::R16_UINT
+        | vk::Format::R16_SINT
+        | vk::Format::R16_SFLOAT
+        | vk::Format::D16_UNORM => 2,
+
+        vk::Format::R8G8B8_UNORM
+        | vk::Format::R8G8B8_SNORM
+        | vk::Format::R8G8B8_USCALED
+        | vk::Format::R8G8B8_SSCALED
+        | vk::Format::R8G8B8_UINT
+        | vk::Format::R8G8B8_SINT
+        | vk::Format::R8G8B8_SRGB
+        | vk::Format::B8G8R8_UNORM
+        | vk::Format::B8G8R8_SNORM
+        | vk::Format::B8G8R8_USCALED
+        | vk::Format::B8G8R8_SSCALED
+        | vk::Format::B8G8R8_UINT
+        | vk::Format::B8G8R8_SINT
+        | vk::Format::B8G8R8_SRGB => 3,
+
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SNORM
+        | vk::Format::R8G8B8A8_USCALED
+        | vk::Format::R8G8B8A8_SSCALED
+        | vk::Format::R8G8B8A8_UINT
+        | vk::Format::R8G8B8A8_SINT
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SNORM
+        | vk::Format::B8G8R8A8_USCALED
+        | vk::Format::B8G8R8A8_SSCALED
+        | vk::Format::B8G8R8A8_UINT
+        | vk::Format::B8G8R8A8_SINT
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A8B8G8R8_UNORM_PACK32
+        | vk::Format::A8B8G8R8_SNORM_PACK32
+        | vk::Format::A8B8G8R8_USCALED_PACK32
+        | vk::Format::A8B8G8R8_SSCALED_PACK32
+        | vk::Format::A8B8G8R8_UINT_PACK32
+        | vk::Format::A8B8G8R8_SINT_PACK32
+        | vk::Format::A8B8G8R8_SRGB_PACK32
+        | vk::Format::A2R10G10B10_UNORM_PACK32
+        | vk::Format::A2R10G10B10_SNORM_PACK32
+        | vk::Format::A2R10G10B10_USCALED_PACK32
+        | vk::Format::A2R10G10B10_SSCALED_PACK32
+        | vk::Format::A2R10G10B10_UINT_PACK32
+        | vk::Format::A2R10G10B10_SINT_PACK32
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::A2B10G10R10_SNORM_PACK32
+        | vk::Format::A2B10G10R10_USCALED_PACK32
+        | vk::Format::A2B10G10R10_SSCALED_PACK32
+        | vk::Format::A2B10G10R10_UINT_PACK32
+        | vk::Format::A2B10G10R10_SINT_PACK32
+        | vk::Format::R16G16_UNORM
+        | vk::Format::R16G16_SNORM
+        | vk::Format::R16G16_USCALED
+        | vk::Format::R16G16_SSCALED
+        | vk::Format::R16G16_UINT
+        | vk::Format::R16G16_SINT
+        | vk::Format::R16G16_SFLOAT
+        | vk::Format::R32_UINT
+        | vk::Format::R32_SINT
+        | vk::Format::R32_SFLOAT
+        | vk::Format::B10G11R11_UFLOAT_PACK32
+        | vk::Format::E5B9G9R9_UFLOAT_PACK32
+        | vk::Format::X8_D24_UNORM_PACK32
+        | vk::Format::D32_SFLOAT => 4,
+
+        vk::Format::R16G16B16_UNORM
+        | vk::Format::R16G16B16_SNORM
+        | vk::Format::R16G16B16_USCALED
+        | vk::Format::R16G16B16_SSCALED
+        | vk::Format::R16G16B16_UINT
+        | vk::Format::R16G16B16_SINT
+        | vk::Format::R16G16B16_SFLOAT => 6,
+
+        vk::Format::R16G16B16A16_UNORM
+        | vk::Format::R16G16B16A16_SNORM
+        | vk::Format::R16G16B16A16_USCALED
+        | vk::Format::R16G16B16A16_SSCALED
+        | vk::Format::R16G16B16A16_UINT
+        | vk::Format::R16G16B16A16_SINT
+        | vk::Format::R16G16B16A16_SFLOAT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32_SINT
+        | vk::Format::R32G32_SFLOAT
+        | vk::Format::R64_UINT
+        | vk::Format::R64_SINT
+        | vk::Format::R64_SFLOAT => 8,
+
+        vk::Format::R32G32B32_UINT | vk::Format::R32G32B32_SINT | vk::Format::R32G32B32_SFLOAT => {
+            12
+        }
+
+        vk::Format::R32G32B32A32_UINT
+        | vk::Format::R32G32B32A32_SINT
+        | vk::Format::R32G32B32A32_SFLOAT
+        | vk::Format::R64G64_UINT
+        | vk::Format::R64G64_SINT
+        | vk::Format::R64G64_SFLOAT => 16,
+
+        vk::Format::R64G64B64_UINT | vk::Format::R64G64B64_SINT | vk::Format::R64G64B64_SFLOAT => {
+            24
+        }
+
+        vk::Format::R64G64B64A64_UINT
+        | vk::Format::R64G64B64A64_SINT
+        | vk::Format::R64G64B64A64_SFLOAT => 32,
+
+        _ => return None,
+    };
+    Some(texel_size)
+}
+
+/// Size in bytes of tightly packed buffer data covering `extent` texels of `layer_count` array
+/// layers of an image with `format` i.e. what a single `vk::BufferImageCopy` with zero
+/// `buffer_row_length` and `buffer_image_height` reads or writes. Compressed formats are counted
+/// in whole blocks. Returns `None` if the texel size of `format` isn't known (see
+/// [`format_texel_size`]) or the size overflows.
+pub fn image_copy_data_size(
+    format: vk::Format,
+    extent: vk::Extent3D,
+    layer_count: u32,
+) -> Option<vk::DeviceSize> {
+    let layer_size = match CompressedBlockInfo::from_format(format) {
+        Some(block_info) => {
+            let [blocks_wide, blocks_high, depth] = block_info.block_count_3d(extent);
+            (blocks_wide as vk::DeviceSize)
+                .checked_mul(blocks_high as vk::DeviceSize)?
+                .checked_mul(depth as vk::DeviceSize)?
+                .checked_mul(block_info.block_size as vk::DeviceSize)?
+        }
+        None => (format_texel_size(format)? as vk::DeviceSize)
+            .checked_mul(extent.width as vk::DeviceSize)?
+            .checked_mul(extent.height as vk::DeviceSize)?
+            .checked_mul(extent.depth as vk::DeviceSize)?,
+    };
+    layer_size.checked_mul(layer_count as vk::DeviceSize)
+}
+
+/// The aspect to use in `vk::ImageSubresourceLayers` when copying between a buffer and an image
+/// with `format`. Buffer/image copies must name exactly one aspect so this returns `None` for
+/// combined depth/stencil formats whose depth and stencil aspects have to be copied separately.
+pub fn single_copy_aspect(format: vk::Format) -> Option<vk::ImageAspectFlags> {
+    let aspect_mask = aspect_mask_from_format(format);
+    if aspect_mask.as_raw().count_ones() == 1 {
+        Some(aspect_mask)
+    } else {
+        None
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn image_copy_data_sizes() {
+    let extent = vk::Extent3D {
+        width: 5,
+        height: 3,
+        depth: 1,
+    };
+    assert_eq!(
+        image_copy_data_size(vk::Format::R8G8B8A8_UNORM, extent, 2),
+        Some(5 * 3 * 4 * 2)
+    );
+    // 2x1 blocks of 8 bytes
+    assert_eq!(
+        image_copy_data_size(vk::Format::BC1_RGBA_UNORM_BLOCK, extent, 1),
+        Some(2 * 8)
+    );
+    assert_eq!(
+        image_copy_data_size(vk::Format::D24_UNORM_S8_UINT, extent, 1),
+        None
+    );
+
+    assert_eq!(
+        single_copy_aspect(vk::Format::D32_SFLOAT),
+        Some(vk::ImageAspectFlags::DEPTH)
+    );
+    assert_eq!(single_copy_aspect(vk::Format::D32_SFLOAT_S8_UINT), None);
+}
@@ -0,0 +1,183 @@
+use crate::PhysicalDevice;
+use ash::vk;
+use std::{error, fmt};
+
+/// `VK_EXT_fragment_density_map` capabilities of a physical device. A fragment density map is a
+/// low resolution attachment where each texel sets how densely fragments are shaded over an area
+/// of the framebuffer, allowing e.g. foveated rendering on VR and mobile hardware.
+///
+/// Setup:
+/// - enable `VK_EXT_fragment_density_map` and the `fragmentDensityMap` feature
+/// - create the density map with [`ImageProperties::new_fragment_density_map`](crate::ImageProperties::new_fragment_density_map)
+///   and view it with [`ImageViewProperties::new_fragment_density_map`](crate::ImageViewProperties::new_fragment_density_map)
+/// - add [`fragment_density_map_attachment_description`] to the render pass attachments and
+///   create the render pass with [`RenderPass::new_with_fragment_density_map`](crate::RenderPass::new_with_fragment_density_map)
+#[derive(Debug, Clone, Copy)]
+pub struct FragmentDensityMapSupport {
+    pub features: vk::PhysicalDeviceFragmentDensityMapFeaturesEXT<'static>,
+    pub properties: vk::PhysicalDeviceFragmentDensityMapPropertiesEXT<'static>,
+}
+
+impl FragmentDensityMapSupport {
+    /// `None` if `physical_device` doesn't support `VK_EXT_fragment_density_map`.
+    pub fn query(physical_device: &PhysicalDevice) -> Option<Self> {
+        let instance = physical_device.instance();
+        let features = instance.physical_device_fragment_density_map_features(physical_device)?;
+        let properties =
+            instance.physical_device_fragment_density_map_properties(physical_device)?;
+        Some(Self {
+            features,
+            properties,
+        })
+    }
+
+    #[inline]
+    pub fn is_supported(&self) -> bool {
+        self.features.fragment_density_map == vk::TRUE
+    }
+
+    #[inline]
+    pub fn dynamic_supported(&self) -> bool {
+        self.features.fragment_density_map_dynamic == vk::TRUE
+    }
+
+    /// Extent of a density map covering `framebuffer_extent` where each texel covers
+    /// `texel_size` framebuffer pixels. `texel_size` is clamped to the supported
+    /// `minFragmentDensityTexelSize`..`maxFragmentDensityTexelSize` range.
+    pub fn density_map_extent(
+        &self,
+        framebuffer_extent: vk::Extent2D,
+        texel_size: vk::Extent2D,
+    ) -> vk::Extent2D {
+        let min_texel_size = self.properties.min_fragment_density_texel_size;
+        let max_texel_size = self.properties.max_fragment_density_texel_size;
+        let texel_size = vk::Extent2D {
+            width: texel_size
+                .width
+                .clamp(min_texel_size.width, max_texel_size.width),
+            height: texel_size
+                .height
+                .clamp(min_texel_size.height, max_texel_size.height),
+        };
+        density_map_extent(framebuffer_extent, texel_size)
+    }
+
+    /// Checks that fragment density maps (and dynamic density maps if `dynamic`) are supported.
+    pub fn validate(&self, dynamic: bool) -> Result<(), FragmentDensityMapError> {
+        if !self.is_supported() {
+            return Err(FragmentDensityMapError::NotSupported);
+        }
+        if dynamic && !self.dynamic_supported() {
+            return Err(FragmentDensityMapError::DynamicNotSupported);
+        }
+        Ok(())
+    }
+}
+
+/// Attachment description for a fragment density map which is read at the start of each render
+/// pass instance. Use `load_op` `LOAD` to keep the uploaded densities.
+pub fn fragment_density_map_attachment_description(
+    format: vk::Format,
+    load_op: vk::AttachmentLoadOp,
+) -> vk::AttachmentDescription {
+    vk::AttachmentDescription {
+        format,
+        samples: vk::SampleCountFlags::TYPE_1,
+        load_op,
+        store_op: vk::AttachmentStoreOp::DONT_CARE,
+        stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+        stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+        initial_layout: vk::ImageLayout::FRAGMENT_DENSITY_MAP_OPTIMAL_EXT,
+        final_layout: vk::ImageLayout::FRAGMENT_DENSITY_MAP_OPTIMAL_EXT,
+        ..Default::default()
+    }
+}
+
+/// Reference to the fragment density map at `attachment_index` for
+/// [`RenderPass::new_with_fragment_density_map`](crate::RenderPass::new_with_fragment_density_map).
+pub fn fragment_density_map_attachment_reference(attachment_index: u32) -> vk::AttachmentReference {
+    vk::AttachmentReference {
+        attachment: attachment_index,
+        layout: vk::ImageLayout::FRAGMENT_DENSITY_MAP_OPTIMAL_EXT,
+    }
+}
+
+// Helper Functions
+
+fn density_map_extent(framebuffer_extent: vk::Extent2D, texel_size: vk::Extent2D) -> vk::Extent2D {
+    vk::Extent2D {
+        width: framebuffer_extent.width.div_ceil(texel_size.width.max(1)),
+        height: framebuffer_extent.height.div_ceil(texel_size.height.max(1)),
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum FragmentDensityMapError {
+    /// `VK_EXT_fragment_density_map` or the `fragmentDensityMap` feature isn't supported.
+    NotSupported,
+    DynamicNotSupported,
+}
+
+impl fmt::Display for FragmentDensityMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSupported => write!(
+                f,
+                "VK_EXT_fragment_density_map or the fragmentDensityMap feature isn't supported"
+            ),
+            Self::DynamicNotSupported => write!(
+                f,
+                "dynamic fragment density map requested but fragmentDensityMapDynamic isn't supported"
+            ),
+        }
+    }
+}
+
+impl error::Error for FragmentDensityMapError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn fragment_density_map_extent_and_support() {
+    let support = FragmentDensityMapSupport {
+        features: vk::PhysicalDeviceFragmentDensityMapFeaturesEXT::default()
+            .fragment_density_map(true),
+        properties: vk::PhysicalDeviceFragmentDensityMapPropertiesEXT::default()
+            .min_fragment_density_texel_size(vk::Extent2D {
+                width: 16,
+                height: 16,
+            })
+            .max_fragment_density_texel_size(vk::Extent2D {
+                width: 64,
+                height: 64,
+            }),
+    };
+    let framebuffer_extent = vk::Extent2D {
+        width: 1920,
+        height: 1080,
+    };
+
+    let extent = support.density_map_extent(
+        framebuffer_extent,
+        vk::Extent2D {
+            width: 32,
+            height: 32,
+        },
+    );
+    assert_eq!((extent.width, extent.height), (60, 34));
+
+    // clamped to the max texel size
+    let extent = support.density_map_extent(
+        framebuffer_extent,
+        vk::Extent2D {
+            width: 128,
+            height: 128,
+        },
+    );
+    assert_eq!((extent.width, extent.height), (30, 17));
+
+    assert!(support.validate(false).is_ok());
+    assert!(support.validate(true).is_err());
+}
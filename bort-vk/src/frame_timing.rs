@@ -0,0 +1,365 @@
+use ash::vk;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Records per-frame CPU timestamps (fence wait, acquire, submit, present, fence signalled) and
+/// exposes rolling statistics over the last `window_size` completed frames.
+///
+/// Typical frame usage:
+/// 1. `let frame_id = recorder.begin_frame();`
+/// 2. wait for the in-flight fence, then [`Self::record_fence_wait`] with the time spent waiting
+///    and [`Self::record_fence_signalled`] for the frame that the fence belonged to
+/// 3. [`Self::record_acquire`] after `vkAcquireNextImageKHR` returns
+/// 4. [`Self::record_submit`] after `vkQueueSubmit`
+/// 5. [`Self::record_present`] after `vkQueuePresentKHR`
+///
+/// A frame is complete once its fence has been signalled. If `VK_KHR_present_wait` or
+/// `VK_GOOGLE_display_timing` are available, actual presentation times can be added with
+/// [`Self::record_present_completed`] and [`Self::record_past_presentation_timings`].
+pub struct FrameTimingRecorder {
+    window_size: usize,
+    next_frame_id: u64,
+    in_progress: HashMap<u64, FrameTiming>,
+    completed: VecDeque<FrameTiming>,
+}
+
+/// CPU timestamps for a single frame.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTiming {
+    pub frame_id: u64,
+    pub begin: Instant,
+    /// Time spent waiting for the in-flight fence before recording this frame.
+    pub fence_wait: Duration,
+    pub acquired: Option<Instant>,
+    pub submitted: Option<Instant>,
+    pub presented: Option<Instant>,
+    /// When the CPU observed the frame's fence signalled.
+    pub fence_signalled: Option<Instant>,
+    /// When `vkWaitForPresentKHR` returned for this frame (`VK_KHR_present_wait`).
+    pub present_completed: Option<Instant>,
+    /// `actualPresentTime` in nanoseconds from `VK_GOOGLE_display_timing`.
+    pub actual_present_time_ns: Option<u64>,
+}
+
+impl FrameTiming {
+    fn new(frame_id: u64) -> Self {
+        Self {
+            frame_id,
+            begin: Instant::now(),
+            fence_wait: Duration::ZERO,
+            acquired: None,
+            submitted: None,
+            presented: None,
+            fence_signalled: None,
+            present_completed: None,
+            actual_present_time_ns: None,
+        }
+    }
+
+    /// Time spent blocked in `vkAcquireNextImageKHR` (measured from the end of the fence wait).
+    pub fn acquire_time(&self) -> Option<Duration> {
+        let acquired = self.acquired?;
+        Some(
+            acquired
+                .saturating_duration_since(self.begin)
+                .saturating_sub(self.fence_wait),
+        )
+    }
+
+    /// CPU time from the start of the frame to submission, excluding the fence wait and acquire.
+    pub fn cpu_time(&self) -> Option<Duration> {
+        let submitted = self.submitted?;
+        let acquire_time = self.acquire_time().unwrap_or_default();
+        Some(
+            submitted
+                .saturating_duration_since(self.begin)
+                .saturating_sub(self.fence_wait)
+                .saturating_sub(acquire_time),
+        )
+    }
+
+    /// Time between submission and the CPU observing the fence signalled. An upper bound of the
+    /// GPU time for this frame.
+    pub fn submit_to_signal_time(&self) -> Option<Duration> {
+        Some(
+            self.fence_signalled?
+                .saturating_duration_since(self.submitted?),
+        )
+    }
+}
+
+impl FrameTimingRecorder {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            window_size: window_size.max(1),
+            next_frame_id: 0,
+            in_progress: HashMap::new(),
+            completed: VecDeque::new(),
+        }
+    }
+
+    /// Starts timing a new frame and returns its id. The id can also be used as the `presentID`
+    /// for `VK_GOOGLE_display_timing` and `VK_KHR_present_id`.
+    pub fn begin_frame(&mut self) -> u64 {
+        let frame_id = self.next_frame_id;
+        self.next_frame_id += 1;
+        self.in_progress
+            .insert(frame_id, FrameTiming::new(frame_id));
+        frame_id
+    }
+
+    pub fn record_fence_wait(&mut self, frame_id: u64, wait_duration: Duration) {
+        if let Some(frame) = self.in_progress.get_mut(&frame_id) {
+            frame.fence_wait = wait_duration;
+        }
+    }
+
+    pub fn record_acquire(&mut self, frame_id: u64) {
+        if let Some(frame) = self.in_progress.get_mut(&frame_id) {
+            frame.acquired = Some(Instant::now());
+        }
+    }
+
+    pub fn record_submit(&mut self, frame_id: u64) {
+        if let Some(frame) = self.in_progress.get_mut(&frame_id) {
+            frame.submitted = Some(Instant::now());
+        }
+    }
+
+    pub fn record_present(&mut self, frame_id: u64) {
+        if let Some(frame) = self.in_progress.get_mut(&frame_id) {
+            frame.presented = Some(Instant::now());
+        }
+    }
+
+    /// Completes the frame. Call when the frame's fence has been observed signalled.
+    pub fn record_fence_signalled(&mut self, frame_id: u64) {
+        if let Some(mut frame) = self.in_progress.remove(&frame_id) {
+            frame.fence_signalled = Some(Instant::now());
+            self.push_completed(frame);
+        }
+    }
+
+    /// Discards a frame that won't be submitted e.g. because the swapchain was out of date.
+    pub fn cancel_frame(&mut self, frame_id: u64) {
+        self.in_progress.remove(&frame_id);
+    }
+
+    /// Call when `vkWaitForPresentKHR` returns for the frame.
+    pub fn record_present_completed(&mut self, frame_id: u64) {
+        let now = Instant::now();
+        if let Some(frame) = self.frame_mut(frame_id) {
+            frame.present_completed = Some(now);
+        }
+    }
+
+    /// Adds results from `vkGetPastPresentationTimingGOOGLE`. Assumes the frame ids returned by
+    /// [`Self::begin_frame`] were used as `presentID`s.
+    pub fn record_past_presentation_timings(
+        &mut self,
+        past_presentation_timings: &[vk::PastPresentationTimingGOOGLE],
+    ) {
+        for timing in past_presentation_timings {
+            if let Some(frame) = self.frame_mut(timing.present_id as u64) {
+                frame.actual_present_time_ns = Some(timing.actual_present_time);
+            }
+        }
+    }
+
+    /// Statistics over the completed frames in the rolling window. Returns `None` if fewer than
+    /// two frames have completed.
+    pub fn stats(&self) -> Option<FrameTimingStats> {
+        frame_timing_stats(self.completed.iter().copied())
+    }
+
+    fn push_completed(&mut self, frame: FrameTiming) {
+        // frames can complete out of order if fences are observed out of order
+        let insert_index = self
+            .completed
+            .iter()
+            .rposition(|completed| completed.frame_id < frame.frame_id)
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        self.completed.insert(insert_index, frame);
+        while self.completed.len() > self.window_size {
+            self.completed.pop_front();
+        }
+    }
+
+    fn frame_mut(&mut self, frame_id: u64) -> Option<&mut FrameTiming> {
+        if let Some(frame) = self.in_progress.get_mut(&frame_id) {
+            return Some(frame);
+        }
+        self.completed
+            .iter_mut()
+            .find(|frame| frame.frame_id == frame_id)
+    }
+
+    // Getters
+
+    /// Completed frames in the rolling window, oldest first.
+    #[inline]
+    pub fn completed_frames(&self) -> &VecDeque<FrameTiming> {
+        &self.completed
+    }
+
+    #[inline]
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+}
+
+// Stats
+
+/// Which part of the frame loop is most likely limiting the frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameBound {
+    /// The CPU spends a significant part of the frame waiting for the GPU to finish previous
+    /// frames.
+    Gpu,
+    /// The CPU spends a significant part of the frame blocked on acquiring a swapchain image
+    /// e.g. because of vsync.
+    Present,
+    /// Neither of the above; the CPU work is the limiting factor.
+    Cpu,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTimingStats {
+    pub frame_count: usize,
+    pub p50_frame_time: Duration,
+    pub p95_frame_time: Duration,
+    pub p50_cpu_time: Duration,
+    pub p50_fence_wait: Duration,
+    pub p50_acquire_time: Duration,
+    /// Percentiles of the interval between consecutive display times if
+    /// `VK_GOOGLE_display_timing` results were recorded.
+    pub p50_display_interval: Option<Duration>,
+    pub p95_display_interval: Option<Duration>,
+    pub bound: FrameBound,
+}
+
+/// A wait that takes up more than this fraction of the median frame time is considered limiting.
+const BOUND_WAIT_FRACTION: f64 = 0.2;
+
+/// Calculates statistics for `frames` which are expected to be sorted by frame id.
+pub fn frame_timing_stats(
+    frames: impl IntoIterator<Item = FrameTiming>,
+) -> Option<FrameTimingStats> {
+    let frames: Vec<FrameTiming> = frames.into_iter().collect();
+    if frames.len() < 2 {
+        return None;
+    }
+
+    let mut frame_times: Vec<Duration> = frames
+        .windows(2)
+        .map(|pair| pair[1].begin.saturating_duration_since(pair[0].begin))
+        .collect();
+    let mut cpu_times: Vec<Duration> = frames.iter().filter_map(FrameTiming::cpu_time).collect();
+    let mut fence_waits: Vec<Duration> = frames.iter().map(|frame| frame.fence_wait).collect();
+    let mut acquire_times: Vec<Duration> = frames
+        .iter()
+        .filter_map(FrameTiming::acquire_time)
+        .collect();
+    let mut display_intervals: Vec<Duration> = frames
+        .windows(2)
+        .filter_map(|pair| {
+            let interval_ns = pair[1]
+                .actual_present_time_ns?
+                .checked_sub(pair[0].actual_present_time_ns?)?;
+            Some(Duration::from_nanos(interval_ns))
+        })
+        .collect();
+
+    let p50_frame_time = percentile(&mut frame_times, 50.)?;
+    let p95_frame_time = percentile(&mut frame_times, 95.)?;
+    let p50_fence_wait = percentile(&mut fence_waits, 50.).unwrap_or_default();
+    let p50_acquire_time = percentile(&mut acquire_times, 50.).unwrap_or_default();
+
+    let bound = frame_bound(p50_frame_time, p50_fence_wait, p50_acquire_time);
+
+    Some(FrameTimingStats {
+        frame_count: frames.len(),
+        p50_frame_time,
+        p95_frame_time,
+        p50_cpu_time: percentile(&mut cpu_times, 50.).unwrap_or_default(),
+        p50_fence_wait,
+        p50_acquire_time,
+        p50_display_interval: percentile(&mut display_intervals, 50.),
+        p95_display_interval: percentile(&mut display_intervals, 95.),
+        bound,
+    })
+}
+
+/// GPU vs present vs CPU bound heuristic based on how much of the frame is spent waiting.
+pub fn frame_bound(
+    frame_time: Duration,
+    fence_wait: Duration,
+    acquire_time: Duration,
+) -> FrameBound {
+    let frame_secs = frame_time.as_secs_f64();
+    if frame_secs <= 0. {
+        return FrameBound::Cpu;
+    }
+
+    let fence_wait_fraction = fence_wait.as_secs_f64() / frame_secs;
+    let acquire_fraction = acquire_time.as_secs_f64() / frame_secs;
+
+    if fence_wait_fraction >= BOUND_WAIT_FRACTION && fence_wait_fraction >= acquire_fraction {
+        FrameBound::Gpu
+    } else if acquire_fraction >= BOUND_WAIT_FRACTION {
+        FrameBound::Present
+    } else {
+        FrameBound::Cpu
+    }
+}
+
+/// Nearest-rank percentile. Sorts `values`. Returns `None` if `values` is empty.
+pub fn percentile(values: &mut [Duration], percentile: f64) -> Option<Duration> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let rank = (percentile / 100. * values.len() as f64).ceil() as usize;
+    let index = rank.clamp(1, values.len()) - 1;
+    Some(values[index])
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn percentile_nearest_rank() {
+    let mut values: Vec<Duration> = (1..=20).rev().map(Duration::from_millis).collect();
+    assert_eq!(
+        percentile(&mut values, 50.),
+        Some(Duration::from_millis(10))
+    );
+    assert_eq!(
+        percentile(&mut values, 95.),
+        Some(Duration::from_millis(19))
+    );
+    assert_eq!(percentile(&mut [], 50.), None);
+}
+
+#[test]
+fn frame_bound_heuristic() {
+    let frame_time = Duration::from_millis(16);
+    assert_eq!(
+        frame_bound(frame_time, Duration::from_millis(8), Duration::ZERO),
+        FrameBound::Gpu
+    );
+    assert_eq!(
+        frame_bound(frame_time, Duration::ZERO, Duration::from_millis(10)),
+        FrameBound::Present
+    );
+    assert_eq!(
+        frame_bound(
+            frame_time,
+            Duration::from_millis(1),
+            Duration::from_millis(1)
+        ),
+        FrameBound::Cpu
+    );
+}
@@ -65,13 +65,7 @@ impl Framebuffer {
     }
 
     pub fn whole_rect(&self) -> vk::Rect2D {
-        vk::Rect2D {
-            extent: vk::Extent2D {
-                width: self.properties.dimensions.width(),
-                height: self.properties.dimensions.height(),
-            },
-            offset: vk::Offset2D { x: 0, y: 0 },
-        }
+        self.properties.dimensions.whole_rect()
     }
 
     pub fn whole_viewport(&self) -> vk::Viewport {
@@ -89,6 +83,11 @@ impl Framebuffer {
     pub fn properties(&self) -> &FramebufferProperties {
         &self.properties
     }
+
+    #[inline]
+    pub fn render_pass(&self) -> &Arc<RenderPass> {
+        &self.render_pass
+    }
 }
 
 impl DeviceOwned for Framebuffer {
@@ -138,7 +137,7 @@ impl FramebufferProperties {
         create_info: vk::FramebufferCreateInfo<'a>,
         vk_attchment_image_view_handles: &'a [vk::ImageView],
         render_pass: &RenderPass,
-    ) -> vk::FramebufferCreateInfo {
+    ) -> vk::FramebufferCreateInfo<'a> {
         create_info
             .flags(self.flags)
             .attachments(vk_attchment_image_view_handles)
@@ -165,3 +164,9 @@ impl FramebufferProperties {
         }
     }
 }
+
+impl From<&vk::FramebufferCreateInfo<'_>> for FramebufferProperties {
+    fn from(value: &vk::FramebufferCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    DeferDrop, DeviceOwned, Framebuffer, FramebufferProperties, Gpu, ImageDimensions,
+    ImageViewAccess, RenderPass,
+};
+use ash::{prelude::VkResult, vk};
+use std::{collections::HashMap, sync::Arc};
+
+/// Identifies a framebuffer by everything that goes into its create info.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FramebufferKey {
+    pub render_pass: vk::RenderPass,
+    pub attachments: Vec<vk::ImageView>,
+    pub width: u32,
+    pub height: u32,
+    pub layers: u32,
+}
+
+impl FramebufferKey {
+    pub fn new(
+        render_pass: &RenderPass,
+        attachments: &[Arc<dyn ImageViewAccess>],
+        dimensions: ImageDimensions,
+    ) -> Self {
+        Self {
+            render_pass: render_pass.handle(),
+            attachments: attachments
+                .iter()
+                .map(|image_view| image_view.handle())
+                .collect(),
+            width: dimensions.width(),
+            height: dimensions.height(),
+            layers: dimensions.array_layers(),
+        }
+    }
+}
+
+/// Creates framebuffers on demand and retains them for reuse, keyed by render pass, attachment
+/// views and dimensions. Useful when attachment combinations change from frame to frame e.g. in a
+/// render graph.
+///
+/// Cached framebuffers keep their render pass and attachment views alive, so handles in a key
+/// can't be reused by new objects while the entry exists. Once the caller has dropped all other
+/// references to one of those objects the entry is considered stale and is removed by
+/// [`Self::evict_stale`]. Framebuffers are handed out as [`Gpu`] pointers, so evicted ones are
+/// retired to `deferred_drop` (e.g. a [`DeviceResources`](crate::DeviceResources)) rather than
+/// destroyed while a frame in flight may still be using them.
+pub struct FramebufferCache {
+    entries: HashMap<FramebufferKey, Gpu<Framebuffer>>,
+    deferred_drop: Arc<dyn DeferDrop>,
+}
+
+impl FramebufferCache {
+    pub fn new(deferred_drop: Arc<dyn DeferDrop>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            deferred_drop,
+        }
+    }
+
+    /// Returns the cached framebuffer matching the arguments or creates (and caches) a new one.
+    pub fn get_or_create(
+        &mut self,
+        render_pass: &Arc<RenderPass>,
+        attachments: &[Arc<dyn ImageViewAccess>],
+        dimensions: ImageDimensions,
+    ) -> VkResult<Gpu<Framebuffer>> {
+        let key = FramebufferKey::new(render_pass, attachments, dimensions);
+        if let Some(framebuffer) = self.entries.get(&key) {
+            return Ok(framebuffer.clone());
+        }
+
+        let properties = FramebufferProperties::new_default(attachments.to_vec(), dimensions);
+        let framebuffer = Gpu::new(
+            self.deferred_drop.clone(),
+            Framebuffer::new(render_pass.clone(), properties)?,
+        );
+        self.entries.insert(key, framebuffer.clone());
+        Ok(framebuffer)
+    }
+
+    /// Removes entries whose render pass or any attachment view is only referenced by cached
+    /// framebuffers. Returns the number of evicted entries.
+    pub fn evict_stale(&mut self) -> usize {
+        // number of references to each object held by the cached framebuffers
+        let mut cached_references = HashMap::<u64, usize>::new();
+        for framebuffer in self.entries.values() {
+            *cached_references
+                .entry(framebuffer.render_pass().handle_raw())
+                .or_default() += 1;
+            for image_view in &framebuffer.properties().attachments {
+                *cached_references
+                    .entry(image_view.handle_raw())
+                    .or_default() += 1;
+            }
+        }
+
+        let is_only_cached = |handle_raw: u64, strong_count: usize| {
+            strong_count <= cached_references.get(&handle_raw).copied().unwrap_or(0)
+        };
+
+        let entry_count = self.entries.len();
+        self.entries.retain(|_, framebuffer| {
+            let render_pass = framebuffer.render_pass();
+            if is_only_cached(render_pass.handle_raw(), Arc::strong_count(render_pass)) {
+                return false;
+            }
+            !framebuffer
+                .properties()
+                .attachments
+                .iter()
+                .any(|image_view| {
+                    is_only_cached(image_view.handle_raw(), Arc::strong_count(image_view))
+                })
+        });
+        entry_count - self.entries.len()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    AccelerationStructure, Buffer, ComputePipeline, DeletionQueue, DescriptorSet, Framebuffer,
+    GraphicsPipeline, Image, ImageAccess, ImageView, PipelineCache, Sampler, ShaderModule,
+};
+use std::{
+    fmt,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// What happens to a resource when the last [`Gpu`] pointer to it is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Handed to the pointer's [`DeferDrop`] e.g. the deletion queue of a
+    /// [`DeviceResources`](crate::DeviceResources), so it's only destroyed once the frames that may
+    /// be using it have completed.
+    Deferred,
+    /// Dropped straight away. For objects the device doesn't use after creation e.g. shader
+    /// modules once their pipelines have been created.
+    Immediate,
+}
+
+/// Selects the [`DropPolicy`] of a resource type held in a [`Gpu`]. Implement it (usually with an
+/// empty impl, which defers) for your own types to put them in a [`Gpu`].
+pub trait GpuDropPolicy {
+    const DROP_POLICY: DropPolicy = DropPolicy::Deferred;
+}
+
+impl GpuDropPolicy for AccelerationStructure {}
+impl GpuDropPolicy for Buffer {}
+impl GpuDropPolicy for ComputePipeline {}
+impl GpuDropPolicy for DescriptorSet {}
+impl GpuDropPolicy for Framebuffer {}
+impl GpuDropPolicy for GraphicsPipeline {}
+impl GpuDropPolicy for Image {}
+impl<I: ImageAccess + 'static> GpuDropPolicy for ImageView<I> {}
+impl GpuDropPolicy for Sampler {}
+
+impl GpuDropPolicy for PipelineCache {
+    const DROP_POLICY: DropPolicy = DropPolicy::Immediate;
+}
+impl GpuDropPolicy for ShaderModule {
+    const DROP_POLICY: DropPolicy = DropPolicy::Immediate;
+}
+
+/// Somewhere to send resources that may still be in use by the device, to be dropped later.
+pub trait DeferDrop: Send + Sync {
+    fn defer_drop_boxed(&self, resource: Box<dyn Send>);
+}
+
+/// Panics if the lock is poisoned.
+impl DeferDrop for Mutex<DeletionQueue> {
+    fn defer_drop_boxed(&self, resource: Box<dyn Send>) {
+        self.lock()
+            .expect("deletion queue lock poisoned")
+            .push_boxed(resource);
+    }
+}
+
+/// A shared pointer (wrapping an [`Arc`]) to a resource that the device may still be using when
+/// the last pointer is dropped. Instead of being destroyed then, the resource is handed to a
+/// [`DeferDrop`] (typically [`DeviceResources`](crate::DeviceResources), see
+/// [`DeviceResources::gpu`](crate::DeviceResources::gpu)) depending on its
+/// [`GpuDropPolicy`]. This makes dropping a per-frame resource mid-frame safe without having to
+/// remember to push it to a [`DeletionQueue`].
+pub struct Gpu<T: GpuDropPolicy + Send + 'static> {
+    inner: Arc<GpuInner<T>>,
+}
+
+struct GpuInner<T: GpuDropPolicy + Send + 'static> {
+    /// Only `None` while being dropped.
+    resource: Option<T>,
+    deferred_drop: Arc<dyn DeferDrop>,
+}
+
+impl<T: GpuDropPolicy + Send + 'static> Gpu<T> {
+    pub fn new(deferred_drop: Arc<dyn DeferDrop>, resource: T) -> Self {
+        Self {
+            inner: Arc::new(GpuInner {
+                resource: Some(resource),
+                deferred_drop,
+            }),
+        }
+    }
+
+    /// Whether both pointers point to the same resource.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.inner, &other.inner)
+    }
+
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn deferred_drop(this: &Self) -> &Arc<dyn DeferDrop> {
+        &this.inner.deferred_drop
+    }
+}
+
+impl<T: GpuDropPolicy + Send + 'static> Deref for Gpu<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.inner
+            .resource
+            .as_ref()
+            .expect("gpu resource accessed while being dropped")
+    }
+}
+
+impl<T: GpuDropPolicy + Send + 'static> AsRef<T> for Gpu<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: GpuDropPolicy + Send + 'static> Clone for Gpu<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: GpuDropPolicy + Send + fmt::Debug + 'static> fmt::Debug for Gpu<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Gpu").field(&**self).finish()
+    }
+}
+
+impl<T: GpuDropPolicy + Send + 'static> Drop for GpuInner<T> {
+    fn drop(&mut self) {
+        let Some(resource) = self.resource.take() else {
+            return;
+        };
+        match T::DROP_POLICY {
+            DropPolicy::Deferred => self.deferred_drop.defer_drop_boxed(Box::new(resource)),
+            DropPolicy::Immediate => drop(resource),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn gpu_drop_policies() {
+    struct Deferred(#[allow(dead_code)] Arc<()>);
+    impl GpuDropPolicy for Deferred {}
+    struct Immediate(#[allow(dead_code)] Arc<()>);
+    impl GpuDropPolicy for Immediate {
+        const DROP_POLICY: DropPolicy = DropPolicy::Immediate;
+    }
+
+    let deletion_queue = Arc::new(Mutex::new(DeletionQueue::new(1)));
+    let tracker = Arc::new(());
+
+    let deferred = Gpu::new(deletion_queue.clone(), Deferred(tracker.clone()));
+    let deferred_clone = deferred.clone();
+    assert!(Gpu::ptr_eq(&deferred, &deferred_clone));
+    drop(deferred);
+    drop(deferred_clone);
+    assert_eq!(Arc::strong_count(&tracker), 2);
+    assert_eq!(deletion_queue.lock().unwrap().next_frame(), 1);
+    assert_eq!(Arc::strong_count(&tracker), 1);
+
+    let immediate = Gpu::new(deletion_queue.clone(), Immediate(tracker.clone()));
+    drop(immediate);
+    assert_eq!(Arc::strong_count(&tracker), 1);
+    assert!(deletion_queue.lock().unwrap().is_empty());
+}
@@ -0,0 +1,272 @@
+use crate::{
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, BufferProperties, MemoryError,
+    VirtualAllocation, VirtualBlock, VirtualBlockError,
+};
+use ash::vk;
+use bort_vma::AllocationCreateInfo;
+use std::{error, fmt, marker::PhantomData, mem, sync::Arc};
+
+/// A large storage buffer of `T` elements which is suballocated into [`GpuHeapSlot`]s with a cpu
+/// side free list (a [`VirtualBlock`]), e.g. for pooling meshlet, vertex or instance data of many
+/// meshes in one buffer that gpu driven passes index into. Shaders address a slot with
+/// [`GpuHeapSlot::first_element`] (bind [`Self::descriptor_info`] as a storage buffer) or by
+/// device address (see [`Self::slot_device_address`]).
+///
+/// Freed ranges are reused immediately, so only free a slot once the device has finished reading
+/// it, e.g. a frames-in-flight number of frames after it was last used.
+pub struct GpuHeap<T> {
+    buffer: Buffer,
+    free_list: VirtualBlock,
+    capacity: u32,
+    /// `Some` if the buffer was created with `SHADER_DEVICE_ADDRESS` usage.
+    device_address: Option<vk::DeviceAddress>,
+    element_type: PhantomData<T>,
+}
+
+impl<T: Copy> GpuHeap<T> {
+    /// Creates a buffer with room for `capacity` elements. `STORAGE_BUFFER | TRANSFER_DST` are
+    /// added to `usage`; add `SHADER_DEVICE_ADDRESS` for [`Self::slot_device_address`].
+    /// `allocation_info` would typically be
+    /// [`allocation_info_device_local`](crate::allocation_info_device_local) (upload with
+    /// [`Self::slot_copy_region`]) or a host visible preset (write with [`Self::write`]).
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        capacity: u32,
+        usage: vk::BufferUsageFlags,
+        allocation_info: AllocationCreateInfo,
+    ) -> Result<Self, GpuHeapError> {
+        if capacity == 0 || mem::size_of::<T>() == 0 {
+            return Err(GpuHeapError::ZeroSize);
+        }
+        let usage =
+            usage | vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST;
+        let buffer_size = capacity as vk::DeviceSize * element_size::<T>();
+        let buffer = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(buffer_size, usage),
+            allocation_info,
+        )
+        .map_err(GpuHeapError::BufferCreation)?;
+
+        let device_address = usage
+            .contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS)
+            .then(|| buffer.device_address());
+        let free_list =
+            VirtualBlock::new(capacity as vk::DeviceSize, false).map_err(GpuHeapError::FreeList)?;
+
+        Ok(Self {
+            buffer,
+            free_list,
+            capacity,
+            device_address,
+            element_type: PhantomData,
+        })
+    }
+
+    /// Allocates a contiguous range of `count` elements.
+    pub fn allocate(&mut self, count: u32) -> Result<GpuHeapSlot<T>, GpuHeapError> {
+        if count == 0 {
+            return Err(GpuHeapError::ZeroSize);
+        }
+        let allocation = self
+            .free_list
+            .allocate(count as vk::DeviceSize, 0)
+            .map_err(|_| GpuHeapError::OutOfSpace {
+                requested: count,
+                free: self.free_elements(),
+            })?;
+        Ok(GpuHeapSlot {
+            allocation,
+            element_type: PhantomData,
+        })
+    }
+
+    /// Returns the slot's range to the free list. The device must no longer be using it. Returns
+    /// [`GpuHeapError::FreeList`] if `slot` wasn't allocated from this heap.
+    pub fn free(&mut self, slot: GpuHeapSlot<T>) -> Result<(), GpuHeapError> {
+        self.free_list
+            .free(slot.allocation)
+            .map_err(GpuHeapError::FreeList)
+    }
+
+    /// Writes `data` to the start of `slot`. The heap must be host visible.
+    pub fn write(&mut self, slot: &GpuHeapSlot<T>, data: &[T]) -> Result<(), GpuHeapError> {
+        if data.len() > slot.count() as usize {
+            return Err(GpuHeapError::DataTooLarge {
+                data_count: data.len(),
+                slot_count: slot.count(),
+            });
+        }
+        self.buffer
+            .write_iter(data.iter().copied(), slot.byte_offset() as usize)
+            .map_err(GpuHeapError::Memory)
+    }
+
+    /// Copy region for uploading `slot` from a staging buffer at `src_offset` with
+    /// [`CommandBuffer::copy_buffer`](crate::CommandBuffer::copy_buffer).
+    pub fn slot_copy_region(
+        &self,
+        slot: &GpuHeapSlot<T>,
+        src_offset: vk::DeviceSize,
+    ) -> vk::BufferCopy {
+        vk::BufferCopy {
+            src_offset,
+            dst_offset: slot.byte_offset(),
+            size: slot.byte_size(),
+        }
+    }
+
+    /// Device address of the first element of `slot`. `None` if the heap wasn't created with
+    /// `SHADER_DEVICE_ADDRESS` usage.
+    pub fn slot_device_address(&self, slot: &GpuHeapSlot<T>) -> Option<vk::DeviceAddress> {
+        self.device_address
+            .map(|device_address| device_address + slot.byte_offset())
+    }
+
+    /// The whole heap, for binding as a storage buffer.
+    pub fn descriptor_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer.handle(),
+            offset: 0,
+            range: vk::WHOLE_SIZE,
+        }
+    }
+
+    /// Number of unallocated elements. They may be fragmented across several free ranges.
+    pub fn free_elements(&self) -> u32 {
+        self.capacity - self.free_list.statistics().allocation_bytes as u32
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Total number of elements.
+    #[inline]
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+}
+
+/// A range of elements allocated from a [`GpuHeap`]. Not `Clone` so that it can only be freed
+/// once; dropping it without [`GpuHeap::free`] leaks the range until the heap is dropped.
+#[derive(Debug)]
+pub struct GpuHeapSlot<T> {
+    allocation: VirtualAllocation,
+    element_type: PhantomData<T>,
+}
+
+impl<T> GpuHeapSlot<T> {
+    /// Index of the first element in the heap buffer, e.g. for indexing a storage buffer array
+    /// of `T` in a shader.
+    #[inline]
+    pub fn first_element(&self) -> u32 {
+        self.allocation.offset() as u32
+    }
+
+    /// Number of elements.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.allocation.size() as u32
+    }
+
+    #[inline]
+    pub fn byte_offset(&self) -> vk::DeviceSize {
+        self.allocation.offset() * element_size::<T>()
+    }
+
+    #[inline]
+    pub fn byte_size(&self) -> vk::DeviceSize {
+        self.allocation.size() * element_size::<T>()
+    }
+}
+
+// Helper Functions
+
+fn element_size<T>() -> vk::DeviceSize {
+    mem::size_of::<T>() as vk::DeviceSize
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum GpuHeapError {
+    /// Zero capacity, zero element count or zero sized `T`.
+    ZeroSize,
+    OutOfSpace {
+        requested: u32,
+        free: u32,
+    },
+    DataTooLarge {
+        data_count: usize,
+        slot_count: u32,
+    },
+    Vulkan(vk::Result),
+    BufferCreation(AllocationError),
+    /// Creating the free list failed or a slot from another heap was freed.
+    FreeList(VirtualBlockError),
+    Memory(MemoryError),
+}
+
+impl fmt::Display for GpuHeapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroSize => write!(f, "gpu heap sizes and element types can't be zero sized"),
+            Self::OutOfSpace { requested, free } => write!(
+                f,
+                "no free range of {} elements in gpu heap ({} elements free in total)",
+                requested, free
+            ),
+            Self::DataTooLarge {
+                data_count,
+                slot_count,
+            } => write!(
+                f,
+                "attempted to write {} elements to a gpu heap slot of {} elements",
+                data_count, slot_count
+            ),
+            Self::Vulkan(e) => write!(f, "failed to create gpu heap: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create gpu heap buffer: {}", e),
+            Self::FreeList(e) => write!(f, "gpu heap free list error: {}", e),
+            Self::Memory(e) => write!(f, "failed to write to gpu heap: {}", e),
+        }
+    }
+}
+
+impl error::Error for GpuHeapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::FreeList(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn gpu_heap_slot_offsets() {
+    let mut free_list = VirtualBlock::new(64, false).unwrap();
+    let slot = GpuHeapSlot::<[f32; 4]> {
+        allocation: free_list.allocate(3, 0).unwrap(),
+        element_type: PhantomData,
+    };
+    let next_slot = GpuHeapSlot::<[f32; 4]> {
+        allocation: free_list.allocate(5, 0).unwrap(),
+        element_type: PhantomData,
+    };
+    assert_eq!(slot.count(), 3);
+    assert_eq!(slot.byte_size(), 48);
+    assert_eq!(
+        next_slot.byte_offset(),
+        next_slot.first_element() as vk::DeviceSize * 16
+    );
+    free_list.free(slot.allocation).unwrap();
+    free_list.free(next_slot.allocation).unwrap();
+}
@@ -0,0 +1,505 @@
+use crate::{
+    allocation_info_device_local, AllocatorAccess, Buffer, BufferProperties, CommandBuffer,
+    ComputePassError, ComputePipeline, ComputePipelineProperties, DescriptorPool,
+    DescriptorPoolProperties, DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutProperties, Device, DeviceOwned, PipelineLayout, PipelineLayoutProperties,
+    ShaderModule, ShaderStage,
+};
+use ash::vk;
+use std::{io::Cursor, mem, sync::Arc};
+
+/// Spir-V compiled from `shaders/reduce_sum_u32.comp`.
+const REDUCE_SUM_SPIRV: &[u8] = include_bytes!("./shaders/reduce_sum_u32.comp.spv");
+/// Spir-V compiled from `shaders/scan_step_u32.comp`.
+const SCAN_STEP_SPIRV: &[u8] = include_bytes!("./shaders/scan_step_u32.comp.spv");
+/// Spir-V compiled from `shaders/compact_scatter_u32.comp`.
+const COMPACT_SCATTER_SPIRV: &[u8] = include_bytes!("./shaders/compact_scatter_u32.comp.spv");
+/// Must match the `local_size_x` of the `GpuReduce` shaders.
+const GPU_REDUCE_WORKGROUP_SIZE: u32 = 256;
+/// `count`, `offset` and `flags_mode` push constants shared by the `GpuReduce` shaders.
+const GPU_REDUCE_PUSH_CONSTANTS_SIZE: usize = 3 * 4;
+const GPU_REDUCE_BINDING_COUNT: u32 = 4;
+/// Most descriptor sets used by one operation (stream compaction).
+const MAX_DESCRIPTOR_SETS_PER_OPERATION: u32 = 4;
+const U32_SIZE: vk::DeviceSize = mem::size_of::<u32>() as vk::DeviceSize;
+
+/// Ready-made compute dispatches for parallel reduction, inclusive prefix sum and stream
+/// compaction of `u32` storage buffers. Each call records its dispatches and barriers to a
+/// command buffer and returns a [`GpuReduceOutput`] owning the (device local) output and
+/// correctly sized scratch buffers.
+///
+/// Input buffers require `STORAGE_BUFFER` usage and must be written before the recorded commands
+/// with the appropriate barriers. Results are visible to later compute shaders, transfer reads and
+/// indirect draw/dispatch parameter reads.
+pub struct GpuReduce {
+    reduce_pipeline: ComputePipeline,
+    scan_pipeline: ComputePipeline,
+    scatter_pipeline: ComputePipeline,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    descriptor_pool: Arc<DescriptorPool>,
+    alloc_access: Arc<dyn AllocatorAccess>,
+}
+
+impl GpuReduce {
+    /// `max_operations` is the number of [`GpuReduceOutput`]s which can be alive at once.
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        max_operations: u32,
+    ) -> Result<Self, ComputePassError> {
+        let device = alloc_access.device().clone();
+
+        let bindings = (0..GPU_REDUCE_BINDING_COUNT)
+            .map(|binding| DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            })
+            .collect();
+        let descriptor_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(bindings),
+            )
+            .map_err(ComputePassError::DescriptorSetLayout)?,
+        );
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: GPU_REDUCE_PUSH_CONSTANTS_SIZE as u32,
+        };
+        let pipeline_layout = Arc::new(
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutProperties::new(
+                    vec![descriptor_set_layout.clone()],
+                    vec![push_constant_range],
+                ),
+            )
+            .map_err(ComputePassError::Vulkan)?,
+        );
+
+        let create_pipeline = |spirv: &[u8]| -> Result<ComputePipeline, ComputePassError> {
+            let shader_module = Arc::new(
+                ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(spirv))
+                    .map_err(ComputePassError::Shader)?,
+            );
+            let shader_stage =
+                ShaderStage::compute(shader_module).map_err(ComputePassError::Shader)?;
+            ComputePipeline::new(
+                pipeline_layout.clone(),
+                ComputePipelineProperties::default(),
+                &shader_stage,
+                None,
+            )
+            .map_err(ComputePassError::Vulkan)
+        };
+        let reduce_pipeline = create_pipeline(REDUCE_SUM_SPIRV)?;
+        let scan_pipeline = create_pipeline(SCAN_STEP_SPIRV)?;
+        let scatter_pipeline = create_pipeline(COMPACT_SCATTER_SPIRV)?;
+
+        let max_sets = max_operations.max(1) * MAX_DESCRIPTOR_SETS_PER_OPERATION;
+        let descriptor_pool_properties = DescriptorPoolProperties {
+            flags: vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+            max_sets,
+            pool_sizes: vec![vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: max_sets * GPU_REDUCE_BINDING_COUNT,
+            }],
+        };
+        let descriptor_pool = Arc::new(
+            DescriptorPool::new(device, descriptor_pool_properties)
+                .map_err(ComputePassError::Vulkan)?,
+        );
+
+        Ok(Self {
+            reduce_pipeline,
+            scan_pipeline,
+            scatter_pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+            alloc_access,
+        })
+    }
+
+    /// Records the sum of the first `count` `u32` values of `input` into a single `u32` output
+    /// buffer. Sums wrap on overflow.
+    pub fn sum_u32(
+        &self,
+        command_buffer: &CommandBuffer,
+        input: Arc<Buffer>,
+        count: u32,
+    ) -> Result<GpuReduceOutput, ComputePassError> {
+        debug_assert_input_size(&input, count);
+        let result = self.create_buffer(1)?;
+        let descriptor_set = self.create_descriptor_set(&[&input, &result])?;
+
+        clear_buffers(command_buffer, &[&result]);
+        self.dispatch(
+            command_buffer,
+            &self.reduce_pipeline,
+            &descriptor_set,
+            [count, 0, 0],
+        );
+        output_barrier(command_buffer);
+
+        Ok(GpuReduceOutput {
+            output: result,
+            count: None,
+            scratch_buffers: Vec::new(),
+            descriptor_sets: vec![descriptor_set],
+            input,
+        })
+    }
+
+    /// Records the inclusive prefix sum of the first `count` `u32` values of `input` into an
+    /// output buffer of `count` values: `output[i] = input[0] + ... + input[i]`.
+    pub fn inclusive_scan_u32(
+        &self,
+        command_buffer: &CommandBuffer,
+        input: Arc<Buffer>,
+        count: u32,
+    ) -> Result<GpuReduceOutput, ComputePassError> {
+        debug_assert_input_size(&input, count);
+        let output = self.create_buffer(count)?;
+        let scratch = self.create_buffer(count)?;
+        let descriptor_sets =
+            self.record_scan(command_buffer, &input, &output, &scratch, count, false)?;
+        output_barrier(command_buffer);
+
+        Ok(GpuReduceOutput {
+            output,
+            count: None,
+            scratch_buffers: vec![scratch],
+            descriptor_sets,
+            input,
+        })
+    }
+
+    /// Records an order preserving stream compaction of the first `count` `u32` values of
+    /// `input`: the non-zero values are written to the start of the output buffer and the number
+    /// of them to [`GpuReduceOutput::count`] e.g. to use as an indirect dispatch/draw count.
+    pub fn compact_u32(
+        &self,
+        command_buffer: &CommandBuffer,
+        input: Arc<Buffer>,
+        count: u32,
+    ) -> Result<GpuReduceOutput, ComputePassError> {
+        debug_assert_input_size(&input, count);
+        let compacted = self.create_buffer(count)?;
+        let compacted_count = self.create_buffer(1)?;
+        let scan = self.create_buffer(count)?;
+        let scan_scratch = self.create_buffer(count)?;
+
+        // the count isn't written by the scatter shader if there are no values
+        clear_buffers(command_buffer, &[&compacted_count]);
+        let mut descriptor_sets =
+            self.record_scan(command_buffer, &input, &scan, &scan_scratch, count, true)?;
+        compute_barrier(command_buffer);
+
+        let scatter_set =
+            self.create_descriptor_set(&[&input, &compacted, &scan, &compacted_count])?;
+        self.dispatch(
+            command_buffer,
+            &self.scatter_pipeline,
+            &scatter_set,
+            [count, 0, 0],
+        );
+        output_barrier(command_buffer);
+        descriptor_sets.push(scatter_set);
+
+        Ok(GpuReduceOutput {
+            output: compacted,
+            count: Some(compacted_count),
+            scratch_buffers: vec![scan, scan_scratch],
+            descriptor_sets,
+            input,
+        })
+    }
+
+    /// Records the Hillis-Steele scan steps, ping-ponging between `output` and `scratch` so that
+    /// the last step writes to `output`. With `flags_mode` the first step converts values to 0/1
+    /// flags first, for stream compaction.
+    fn record_scan(
+        &self,
+        command_buffer: &CommandBuffer,
+        input: &Buffer,
+        output: &Buffer,
+        scratch: &Buffer,
+        count: u32,
+        flags_mode: bool,
+    ) -> Result<Vec<DescriptorSet>, ComputePassError> {
+        let step_offsets = scan_step_offsets(count);
+        let step_count = step_offsets.len();
+
+        let first_target = scan_step_target(0, step_count);
+        let first_set =
+            self.create_descriptor_set(&[input, if first_target { output } else { scratch }])?;
+        let mut descriptor_sets = vec![first_set];
+        if step_count > 1 {
+            descriptor_sets.push(self.create_descriptor_set(&[scratch, output])?);
+        }
+        if step_count > 2 {
+            descriptor_sets.push(self.create_descriptor_set(&[output, scratch])?);
+        }
+
+        for (step, &offset) in step_offsets.iter().enumerate() {
+            if step > 0 {
+                compute_barrier(command_buffer);
+            }
+            let descriptor_set = match (step, scan_step_target(step, step_count)) {
+                (0, _) => &descriptor_sets[0],
+                (_, true) => &descriptor_sets[1],
+                (_, false) => &descriptor_sets[2],
+            };
+            self.dispatch(
+                command_buffer,
+                &self.scan_pipeline,
+                descriptor_set,
+                [count, offset, (flags_mode && step == 0) as u32],
+            );
+        }
+
+        Ok(descriptor_sets)
+    }
+
+    fn dispatch(
+        &self,
+        command_buffer: &CommandBuffer,
+        pipeline: &ComputePipeline,
+        descriptor_set: &DescriptorSet,
+        push_constants: [u32; 3],
+    ) {
+        let push_constant_bytes: Vec<u8> = push_constants
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        pipeline.record_dispatch(
+            command_buffer,
+            descriptor_set,
+            &push_constant_bytes,
+            [push_constants[0].div_ceil(GPU_REDUCE_WORKGROUP_SIZE), 1, 1],
+        );
+    }
+
+    /// Device local buffer of `count` (at least 1) `u32` values.
+    fn create_buffer(&self, count: u32) -> Result<Buffer, ComputePassError> {
+        let usage = vk::BufferUsageFlags::STORAGE_BUFFER
+            | vk::BufferUsageFlags::TRANSFER_SRC
+            | vk::BufferUsageFlags::TRANSFER_DST
+            | vk::BufferUsageFlags::INDIRECT_BUFFER;
+        Buffer::new(
+            self.alloc_access.clone(),
+            BufferProperties::new_default(count.max(1) as vk::DeviceSize * U32_SIZE, usage),
+            allocation_info_device_local(),
+        )
+        .map_err(ComputePassError::BufferCreation)
+    }
+
+    /// Descriptor set with `buffers` bound to the first bindings.
+    fn create_descriptor_set(
+        &self,
+        buffers: &[&Buffer],
+    ) -> Result<DescriptorSet, ComputePassError> {
+        let descriptor_set = self
+            .descriptor_pool
+            .allocate_descriptor_set(self.descriptor_set_layout.clone())
+            .map_err(ComputePassError::Vulkan)?;
+
+        let buffer_infos: Vec<[vk::DescriptorBufferInfo; 1]> = buffers
+            .iter()
+            .map(|buffer| {
+                [vk::DescriptorBufferInfo {
+                    buffer: buffer.handle(),
+                    offset: 0,
+                    range: vk::WHOLE_SIZE,
+                }]
+            })
+            .collect();
+        let descriptor_writes = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer_info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set.handle())
+                    .dst_binding(binding as u32)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(buffer_info)
+            });
+        self.device().update_descriptor_sets(descriptor_writes, []);
+
+        Ok(descriptor_set)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn descriptor_set_layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.descriptor_set_layout
+    }
+
+    #[inline]
+    pub fn alloc_access(&self) -> &Arc<dyn AllocatorAccess> {
+        &self.alloc_access
+    }
+}
+
+impl DeviceOwned for GpuReduce {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.reduce_pipeline.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.reduce_pipeline.handle_raw()
+    }
+}
+
+/// Result of a [`GpuReduce`] call. Besides the output (and compaction count) it holds the
+/// intermediate scan buffers and the per-step descriptor sets the dispatches read and write, so
+/// don't drop it until the command buffer has finished executing, e.g. after waiting on its fence.
+/// Read the results back from [`Self::output`] once it has.
+pub struct GpuReduceOutput {
+    output: Buffer,
+    count: Option<Buffer>,
+    scratch_buffers: Vec<Buffer>,
+    descriptor_sets: Vec<DescriptorSet>,
+    input: Arc<Buffer>,
+}
+
+impl GpuReduceOutput {
+    // Getters
+
+    /// The sum (a single `u32`), scanned values or compacted values.
+    #[inline]
+    pub fn output(&self) -> &Buffer {
+        &self.output
+    }
+
+    /// A single `u32` count of the compacted values. `None` for sums and scans.
+    #[inline]
+    pub fn count(&self) -> Option<&Buffer> {
+        self.count.as_ref()
+    }
+
+    #[inline]
+    pub fn scratch_buffers(&self) -> &[Buffer] {
+        &self.scratch_buffers
+    }
+
+    #[inline]
+    pub fn descriptor_sets(&self) -> &[DescriptorSet] {
+        &self.descriptor_sets
+    }
+
+    #[inline]
+    pub fn input(&self) -> &Arc<Buffer> {
+        &self.input
+    }
+}
+
+// Helper Functions
+
+/// Offsets of the Hillis-Steele scan steps for `count` values. There's always at least one step
+/// so that the output is written.
+fn scan_step_offsets(count: u32) -> Vec<u32> {
+    let mut offsets = vec![1u32];
+    while let Some(&last) = offsets.last() {
+        match last.checked_mul(2) {
+            Some(offset) if offset < count => offsets.push(offset),
+            _ => break,
+        }
+    }
+    offsets
+}
+
+/// Whether scan step `step` writes to the output buffer (otherwise the scratch buffer) such that
+/// the last step writes to the output.
+fn scan_step_target(step: usize, step_count: usize) -> bool {
+    (step_count - 1 - step).is_multiple_of(2)
+}
+
+fn debug_assert_input_size(input: &Buffer, count: u32) {
+    debug_assert!(
+        count as vk::DeviceSize * U32_SIZE <= input.properties().size,
+        "count {} exceeds the input buffer size",
+        count
+    );
+}
+
+/// Zeroes `buffers` and makes the clear visible to compute shaders.
+fn clear_buffers(command_buffer: &CommandBuffer, buffers: &[&Buffer]) {
+    for buffer in buffers {
+        command_buffer.fill_buffer(*buffer, 0, vk::WHOLE_SIZE, 0);
+    }
+    let clear_barrier = vk::MemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+    command_buffer.pipeline_barrier(
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::DependencyFlags::empty(),
+        &[clear_barrier],
+        &[],
+        &[],
+    );
+}
+
+fn compute_barrier(command_buffer: &CommandBuffer) {
+    let barrier = vk::MemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+    command_buffer.pipeline_barrier(
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::DependencyFlags::empty(),
+        &[barrier],
+        &[],
+        &[],
+    );
+}
+
+fn output_barrier(command_buffer: &CommandBuffer) {
+    let barrier = vk::MemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(
+            vk::AccessFlags::SHADER_READ
+                | vk::AccessFlags::TRANSFER_READ
+                | vk::AccessFlags::INDIRECT_COMMAND_READ,
+        );
+    command_buffer.pipeline_barrier(
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        vk::PipelineStageFlags::COMPUTE_SHADER
+            | vk::PipelineStageFlags::TRANSFER
+            | vk::PipelineStageFlags::DRAW_INDIRECT,
+        vk::DependencyFlags::empty(),
+        &[barrier],
+        &[],
+        &[],
+    );
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn scan_steps_end_in_output() {
+    assert_eq!(scan_step_offsets(0), vec![1]);
+    assert_eq!(scan_step_offsets(2), vec![1]);
+    assert_eq!(scan_step_offsets(5), vec![1, 2, 4]);
+    assert_eq!(scan_step_offsets(8), vec![1, 2, 4]);
+    assert_eq!(scan_step_offsets(9), vec![1, 2, 4, 8]);
+
+    let targets: Vec<bool> = (0..4).map(|step| scan_step_target(step, 4)).collect();
+    assert_eq!(targets, vec![false, true, false, true]);
+    assert!(scan_step_target(0, 1));
+
+    for spirv in [REDUCE_SUM_SPIRV, SCAN_STEP_SPIRV, COMPACT_SCATTER_SPIRV] {
+        let spirv = ash::util::read_spv(&mut Cursor::new(spirv)).unwrap();
+        let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+        assert_eq!(entry_points.len(), 1);
+        assert_eq!(entry_points[0].stage, vk::ShaderStageFlags::COMPUTE);
+    }
+}
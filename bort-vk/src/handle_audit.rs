@@ -0,0 +1,104 @@
+use crate::CountedObjectType;
+use ash::vk::Handle;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Raw value that dropped wrappers overwrite their handle with while the safety audit is enabled.
+/// Not a valid handle on any implementation we know of (it isn't aligned for a pointer).
+pub const POISONED_HANDLE_RAW: u64 = 0xDEAD_BEEF_DEAD_BEEF;
+
+/// Safety audit mode. In debug builds, when enabled with
+/// [`Device::set_safety_audit`](crate::Device::set_safety_audit), the `Drop` impls of pipelines,
+/// descriptor sets, command buffers, images and buffers overwrite the handle stored in the
+/// wrapper with [`POISONED_HANDLE_RAW`] after destroying it, and command recording and queue
+/// submission panic if they're given that sentinel. This turns use of a wrapper after its `Drop`
+/// has run (e.g. through a dangling reference from unsafe code) into a clear panic instead of
+/// undefined behavior inside the driver.
+///
+/// The sentinel lives in the wrapper so handles of objects created outside bort (e.g. swapchain
+/// images or pipelines created with a deferred operation) are never mistaken for dropped ones and
+/// nothing accumulates over time. Has no effect in release builds.
+#[derive(Debug, Default)]
+pub struct HandleAudit {
+    enabled: AtomicBool,
+}
+
+impl HandleAudit {
+    pub fn set_enabled(&self, enabled: bool) {
+        let enabled = enabled && cfg!(debug_assertions);
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the poisoned sentinel if auditing is enabled, otherwise `handle`. Assign it to the
+    /// wrapper's handle at the end of `Drop` once the handle has been destroyed.
+    #[inline]
+    pub(crate) fn poisoned<H: Handle>(&self, handle: H) -> H {
+        if self.is_enabled() {
+            H::from_raw(POISONED_HANDLE_RAW)
+        } else {
+            handle
+        }
+    }
+
+    /// Panics if auditing is enabled and `handle_raw` is the poisoned sentinel.
+    #[inline]
+    pub(crate) fn assert_not_poisoned(
+        &self,
+        object_type: CountedObjectType,
+        handle_raw: u64,
+        operation: &str,
+    ) {
+        if self.is_enabled() && is_poisoned_handle(handle_raw) {
+            panic_use_after_drop(object_type, operation);
+        }
+    }
+}
+
+/// Whether `handle_raw` is the sentinel written by a dropped bort wrapper.
+#[inline]
+pub fn is_poisoned_handle(handle_raw: u64) -> bool {
+    handle_raw == POISONED_HANDLE_RAW
+}
+
+// Helper Functions
+
+#[cold]
+fn panic_use_after_drop(object_type: CountedObjectType, operation: &str) -> ! {
+    panic!(
+        "safety audit: attempted to {} with a {} whose bort wrapper has been dropped",
+        operation, object_type
+    );
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn handle_audit_poisons_dropped_handles() {
+    use ash::vk;
+
+    let audit = HandleAudit::default();
+    let buffer = audit.poisoned(vk::Buffer::from_raw(1));
+    assert_eq!(buffer.as_raw(), 1);
+
+    audit.set_enabled(true);
+    if !cfg!(debug_assertions) {
+        assert!(!audit.is_enabled());
+        return;
+    }
+    // handles that were never wrapped by bort aren't poisoned
+    audit.assert_not_poisoned(CountedObjectType::Image, 1, "copy");
+
+    let buffer = audit.poisoned(buffer);
+    assert!(is_poisoned_handle(buffer.as_raw()));
+    let res = std::panic::catch_unwind(|| {
+        audit.assert_not_poisoned(CountedObjectType::Buffer, buffer.as_raw(), "copy");
+    });
+    assert!(res.is_err());
+
+    audit.set_enabled(false);
+    audit.assert_not_poisoned(CountedObjectType::Buffer, buffer.as_raw(), "copy");
+}
@@ -0,0 +1,359 @@
+use crate::{
+    allocation_info_cpu_accessible_mapped, allocation_info_device_coherent, AllocationAccess,
+    AllocationError, AllocatorAccess, Buffer, BufferProperties, CommandBuffer, Device,
+    MemoryAllocator, MemoryError, PhysicalDevice, Queue,
+};
+use ash::{amd, nv, vk};
+use log::warn;
+use std::{
+    collections::VecDeque,
+    error,
+    ffi::{CStr, CString},
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// A checkpoint reported by `vkGetQueueCheckpointDataNV`: the last pipeline stage reached by
+/// the command stream at a [`CommandBuffer::set_checkpoint`] marker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub stage: vk::PipelineStageFlags,
+    pub marker: usize,
+}
+
+/// The hang diagnosis extension enabled on a device. `VK_AMD_buffer_marker` is preferred when
+/// both are available because the markers land in memory the application can read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HangMarkerSupport {
+    /// `VK_AMD_buffer_marker`
+    BufferMarker,
+    /// `VK_NV_device_diagnostic_checkpoints`
+    Checkpoints,
+    Unsupported,
+}
+
+impl HangMarkerSupport {
+    /// Checks the extensions enabled on `device`.
+    pub fn from_device(device: &Device) -> Self {
+        let enabled = |name: &CStr| {
+            device
+                .enabled_extensions()
+                .iter()
+                .any(|extension| extension.as_c_str() == name)
+        };
+        if enabled(amd::buffer_marker::NAME) {
+            Self::BufferMarker
+        } else if enabled(nv::device_diagnostic_checkpoints::NAME) {
+            Self::Checkpoints
+        } else {
+            Self::Unsupported
+        }
+    }
+
+    /// The device extension to enable for hang markers on `physical_device`, if it supports any.
+    pub fn device_extension(physical_device: &PhysicalDevice) -> Option<CString> {
+        [
+            amd::buffer_marker::NAME,
+            nv::device_diagnostic_checkpoints::NAME,
+        ]
+        .into_iter()
+        .map(CStr::to_owned)
+        .find(|name| physical_device.supports_extension(name.clone()))
+    }
+}
+
+/// Records labelled markers between passes so that after `ERROR_DEVICE_LOST` the last marker
+/// the device reached (and the last one it finished all prior work for) can be logged.
+///
+/// Uses [`CommandBuffer::write_buffer_marker`] to write the marker to a small host visible
+/// buffer at `TOP_OF_PIPE` and `BOTTOM_OF_PIPE`, or [`CommandBuffer::set_checkpoint`] if only
+/// `VK_NV_device_diagnostic_checkpoints` is enabled. Does nothing if neither is enabled so
+/// markers can be left in release builds. Enable `VK_AMD_device_coherent_memory` too (see
+/// [`allocation_info_device_coherent`]) otherwise buffer markers can still be sitting in a gpu
+/// cache when the device hangs.
+///
+/// Pairs with [`DeviceRecovery`](crate::DeviceRecovery): call [`Self::report`] and log it before
+/// recovering.
+pub struct HangTracer {
+    support: HangMarkerSupport,
+    /// Two `u32` slots: the last marker reached at `TOP_OF_PIPE` then at `BOTTOM_OF_PIPE`. Only
+    /// used with [`HangMarkerSupport::BufferMarker`].
+    marker_buffer: Option<Buffer>,
+    labels: Mutex<MarkerLabels>,
+}
+
+impl HangTracer {
+    /// The most recent `label_capacity` marker labels are kept for [`Self::report`].
+    pub fn new(
+        memory_allocator: Arc<MemoryAllocator>,
+        label_capacity: usize,
+    ) -> Result<Self, HangTracerError> {
+        let support = HangMarkerSupport::from_device(memory_allocator.device());
+
+        let marker_buffer = if support == HangMarkerSupport::BufferMarker {
+            let allocation_info = if memory_allocator.device_coherent_memory_enabled() {
+                allocation_info_device_coherent()
+            } else {
+                warn!(
+                    "VK_AMD_device_coherent_memory isn't enabled so hang markers may not reach memory before a device loss"
+                );
+                allocation_info_cpu_accessible_mapped()
+            };
+            let mut marker_buffer = Buffer::new(
+                memory_allocator,
+                BufferProperties::new_default(
+                    MARKER_BUFFER_SIZE,
+                    vk::BufferUsageFlags::TRANSFER_DST,
+                ),
+                allocation_info,
+            )
+            .map_err(HangTracerError::BufferCreation)?;
+            marker_buffer
+                .write_struct([0_u32; 2], 0)
+                .map_err(HangTracerError::Memory)?;
+            Some(marker_buffer)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            support,
+            marker_buffer,
+            labels: Mutex::new(MarkerLabels::new(label_capacity)),
+        })
+    }
+
+    /// Records a marker labelled `label` e.g. before each pass. Returns the marker value.
+    pub fn mark(&self, command_buffer: &CommandBuffer, label: impl Into<String>) -> u32 {
+        let marker = self
+            .labels
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(label.into());
+
+        match (self.support, &self.marker_buffer) {
+            (HangMarkerSupport::BufferMarker, Some(marker_buffer)) => {
+                command_buffer.write_buffer_marker(
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    marker_buffer,
+                    STARTED_OFFSET,
+                    marker,
+                );
+                command_buffer.write_buffer_marker(
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    marker_buffer,
+                    COMPLETED_OFFSET,
+                    marker,
+                );
+            }
+            (HangMarkerSupport::Checkpoints, _) => command_buffer.set_checkpoint(marker as usize),
+            _ => (),
+        }
+        marker
+    }
+
+    /// Decodes the last markers reached. Call after `ERROR_DEVICE_LOST`; `queue` is only used
+    /// with [`HangMarkerSupport::Checkpoints`] and should be the queue which reported the loss.
+    pub fn report(&mut self, queue: &Queue) -> Result<HangReport, HangTracerError> {
+        let (started, completed) = match (self.support, self.marker_buffer.as_mut()) {
+            (HangMarkerSupport::BufferMarker, Some(marker_buffer)) => {
+                let [started, completed] = marker_buffer
+                    .read_struct::<[u32; 2]>(0)
+                    .map_err(HangTracerError::Memory)?;
+                (started, completed)
+            }
+            (HangMarkerSupport::Checkpoints, _) => decode_checkpoints(&queue.checkpoint_data()),
+            _ => (0, 0),
+        };
+
+        let labels = self.labels.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(HangReport {
+            support: self.support,
+            last_started: labels.hang_marker(started),
+            last_completed: labels.hang_marker(completed),
+        })
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn support(&self) -> HangMarkerSupport {
+        self.support
+    }
+
+    /// `false` if neither `VK_AMD_buffer_marker` nor `VK_NV_device_diagnostic_checkpoints` is
+    /// enabled and [`Self::mark`] does nothing.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.support != HangMarkerSupport::Unsupported
+    }
+}
+
+/// A marker recorded by [`HangTracer::mark`]. `label` is `None` if it has been evicted from the
+/// label history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HangMarker {
+    pub marker: u32,
+    pub label: Option<String>,
+}
+
+impl fmt::Display for HangMarker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "'{}' (#{})", label, self.marker),
+            None => write!(f, "#{}", self.marker),
+        }
+    }
+}
+
+/// Where the device got to before it was lost. The hang is in the work recorded after
+/// `last_completed`, at or before `last_started`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HangReport {
+    pub support: HangMarkerSupport,
+    /// The last marker the device reached (all prior commands had started).
+    pub last_started: Option<HangMarker>,
+    /// The last marker for which all prior commands had completed.
+    pub last_completed: Option<HangMarker>,
+}
+
+impl fmt::Display for HangReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.support == HangMarkerSupport::Unsupported {
+            return write!(f, "no hang markers (extension not enabled)");
+        }
+        match &self.last_started {
+            Some(marker) => write!(f, "last marker reached: {}", marker)?,
+            None => write!(f, "no markers reached")?,
+        }
+        match &self.last_completed {
+            Some(marker) => write!(f, ", last marker completed: {}", marker),
+            None => write!(f, ", no markers completed"),
+        }
+    }
+}
+
+// Helper Functions
+
+const MARKER_BUFFER_SIZE: vk::DeviceSize = 8;
+const STARTED_OFFSET: vk::DeviceSize = 0;
+const COMPLETED_OFFSET: vk::DeviceSize = 4;
+
+/// Labels of the most recent markers. Marker values start at 1 so that 0 means none reached.
+struct MarkerLabels {
+    first_marker: u32,
+    labels: VecDeque<String>,
+    capacity: usize,
+}
+
+impl MarkerLabels {
+    fn new(capacity: usize) -> Self {
+        Self {
+            first_marker: 1,
+            labels: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    fn push(&mut self, label: String) -> u32 {
+        if self.labels.len() == self.capacity {
+            self.labels.pop_front();
+            self.first_marker += 1;
+        }
+        self.labels.push_back(label);
+        self.first_marker + self.labels.len() as u32 - 1
+    }
+
+    fn hang_marker(&self, marker: u32) -> Option<HangMarker> {
+        if marker == 0 {
+            return None;
+        }
+        let label = marker
+            .checked_sub(self.first_marker)
+            .and_then(|index| self.labels.get(index as usize))
+            .cloned();
+        Some(HangMarker { marker, label })
+    }
+}
+
+/// Returns the last (started, completed) markers. A checkpoint at `BOTTOM_OF_PIPE` means all
+/// prior commands completed; any other stage means they at least started.
+fn decode_checkpoints(checkpoints: &[Checkpoint]) -> (u32, u32) {
+    let started = checkpoints
+        .iter()
+        .map(|checkpoint| checkpoint.marker as u32)
+        .max()
+        .unwrap_or(0);
+    let completed = checkpoints
+        .iter()
+        .filter(|checkpoint| {
+            checkpoint
+                .stage
+                .contains(vk::PipelineStageFlags::BOTTOM_OF_PIPE)
+        })
+        .map(|checkpoint| checkpoint.marker as u32)
+        .max()
+        .unwrap_or(0);
+    (started, completed)
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum HangTracerError {
+    BufferCreation(AllocationError),
+    Memory(MemoryError),
+}
+
+impl fmt::Display for HangTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferCreation(e) => write!(f, "failed to create hang marker buffer: {}", e),
+            Self::Memory(e) => write!(f, "failed to access hang marker buffer: {}", e),
+        }
+    }
+}
+
+impl error::Error for HangTracerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::BufferCreation(e) => Some(e),
+            Self::Memory(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn hang_tracer_labels_and_checkpoints() {
+    let mut labels = MarkerLabels::new(2);
+    assert_eq!(labels.push("shadows".to_owned()), 1);
+    assert_eq!(labels.push("gbuffer".to_owned()), 2);
+    assert_eq!(labels.push("lighting".to_owned()), 3);
+    assert_eq!(labels.hang_marker(0), None);
+    assert_eq!(
+        labels.hang_marker(1),
+        Some(HangMarker {
+            marker: 1,
+            label: None
+        })
+    );
+    assert_eq!(
+        labels.hang_marker(3).and_then(|marker| marker.label),
+        Some("lighting".to_owned())
+    );
+
+    let checkpoints = [
+        Checkpoint {
+            stage: vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            marker: 2,
+        },
+        Checkpoint {
+            stage: vk::PipelineStageFlags::FRAGMENT_SHADER,
+            marker: 3,
+        },
+    ];
+    assert_eq!(decode_checkpoints(&checkpoints), (3, 2));
+    assert_eq!(decode_checkpoints(&[]), (0, 0));
+}
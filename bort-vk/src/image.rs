@@ -1,12 +1,18 @@
 use crate::{
-    AllocationAccess, AllocatorAccess, Device, DeviceOwned, ImageAccess, ImageDimensions,
-    MemoryAllocation, MemoryAllocator, PhysicalDevice,
+    allocation_info_device_local, compressed_mip_chain_copy_regions, default_subresource_layers,
+    device_memory_requirements_available, image_copy_data_size, new_staging_buffer,
+    record_submit_and_wait, single_copy_aspect, AllocationAccess, AllocationError, AllocatorAccess,
+    ApiVersion, CommandBuffer, CommandPool, CompressedBlockInfo, CountedObjectType, Device,
+    DeviceOwned, HandleOwnership, ImageAccess, ImageDimensions, ImageMipLevel, ImageSubresource,
+    ImageView, ImageViewProperties, LinearImageError, LinearImageView, MemoryAllocation,
+    MemoryAllocator, PhysicalDevice, Queue, ResourceInitError, CUBE_FACE_COUNT,
 };
 use ash::{
+    khr,
     prelude::VkResult,
     vk::{self, Handle},
 };
-use bort_vma::AllocationCreateInfo;
+use bort_vma::{ffi, AllocationCreateInfo};
 use std::sync::Arc;
 
 // ~~ Image ~~
@@ -15,6 +21,8 @@ pub struct Image {
     handle: vk::Image,
     properties: ImageProperties,
     memory_allocation: MemoryAllocation,
+    counted: bool,
+    ownership: HandleOwnership,
 }
 
 impl Image {
@@ -22,13 +30,23 @@ impl Image {
         alloc_access: Arc<dyn AllocatorAccess>,
         properties: ImageProperties,
         allocation_info: AllocationCreateInfo,
-    ) -> VkResult<Self> {
+    ) -> Result<Self, AllocationError> {
+        let mut format_list = properties.format_list_create_info();
+        let mut create_info = properties.create_info();
+        if let Some(format_list) = format_list.as_mut() {
+            create_info = create_info.push_next(format_list);
+        }
+
         let (handle, allocation_handle) = unsafe {
             alloc_access
                 .memory_allocator()
-                .vma_create_image(&properties.create_info(), &allocation_info)
+                .vma_create_image(&create_info, &allocation_info)
         }?;
 
+        let counted = alloc_access
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Image);
         let memory_allocation =
             MemoryAllocation::from_vma_allocation(allocation_handle, alloc_access);
 
@@ -36,16 +54,201 @@ impl Image {
             handle,
             properties,
             memory_allocation,
+            counted,
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Creates an image and clears all mip levels and array layers to zero using
+    /// `vkCmdClearColorImage` or `vkCmdClearDepthStencilImage`. The image is transitioned to
+    /// `final_layout` and this blocks until the commands have completed on `queue`.
+    ///
+    /// `vk::ImageUsageFlags::TRANSFER_DST` is added to the usage flags in `properties`.
+    pub fn new_zeroed(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        mut properties: ImageProperties,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        final_layout: vk::ImageLayout,
+    ) -> Result<Self, ResourceInitError> {
+        properties.usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        let image = Self::new(alloc_access, properties, allocation_info)
+            .map_err(ResourceInitError::Creation)?;
+
+        let subresource_range = image.properties.subresource_range();
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            image.record_transition_to_transfer_dst(command_buffer);
+
+            if subresource_range
+                .aspect_mask
+                .contains(vk::ImageAspectFlags::COLOR)
+            {
+                command_buffer.clear_color_image(
+                    &image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue::default(),
+                    &[subresource_range],
+                );
+            } else {
+                command_buffer.clear_depth_stencil_image(
+                    &image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearDepthStencilValue::default(),
+                    &[subresource_range],
+                );
+            }
+
+            image.record_transition_from_transfer_dst(command_buffer, final_layout);
+        })?;
+
+        Ok(image)
+    }
+
+    /// Creates an image and uploads `data` to the first mip level of every array layer via a
+    /// temporary staging buffer. `data` is expected to be tightly packed. Other mip levels are
+    /// left uninitialized. The image is transitioned to `final_layout` and this blocks until the
+    /// commands have completed on `queue`.
+    ///
+    /// `data.len()` must equal [`image_copy_data_size`] of the level 0 extent and array layers.
+    /// Combined depth/stencil formats aren't supported as each aspect needs its own copy.
+    ///
+    /// `vk::ImageUsageFlags::TRANSFER_DST` is added to the usage flags in `properties`.
+    pub fn new_with_data(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        mut properties: ImageProperties,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        data: &[u8],
+        final_layout: vk::ImageLayout,
+    ) -> Result<Self, ResourceInitError> {
+        let aspect_mask = single_copy_aspect(properties.format).ok_or(
+            ResourceInitError::CombinedDepthStencilFormat(properties.format),
+        )?;
+        let expected_size = image_copy_data_size(
+            properties.format,
+            properties.dimensions.extent_3d(),
+            properties.dimensions.array_layers(),
+        )
+        .ok_or(ResourceInitError::UnknownTexelSize(properties.format))?;
+        if data.len() as vk::DeviceSize != expected_size {
+            return Err(ResourceInitError::DataSizeMismatch {
+                data_size: data.len(),
+                expected_size,
+            });
+        }
+
+        properties.usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        let image = Self::new(alloc_access.clone(), properties, allocation_info)
+            .map_err(ResourceInitError::Creation)?;
+
+        let staging_buffer = new_staging_buffer(alloc_access, data)?;
+
+        let image_subresource = vk::ImageSubresourceLayers {
+            layer_count: image.properties.dimensions.array_layers(),
+            ..default_subresource_layers(aspect_mask)
+        };
+        let copy_region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource,
+            image_offset: vk::Offset3D::default(),
+            image_extent: image.properties.dimensions.extent_3d(),
+        };
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            image.record_transition_to_transfer_dst(command_buffer);
+            command_buffer.copy_buffer_to_image(
+                &staging_buffer,
+                &image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+            image.record_transition_from_transfer_dst(command_buffer, final_layout);
+        })?;
+
+        Ok(image)
+    }
+
+    /// Creates a block compressed (BC, ETC2/EAC or ASTC) image and uploads every mip level and
+    /// array layer from `data` via a temporary staging buffer. `data` must contain each mip level
+    /// one after the other starting with level 0, each level containing all array layers of
+    /// tightly packed blocks (the layout of e.g. KTX2 level data). See
+    /// [`compressed_mip_chain_copy_regions`] for the expected size. The image is transitioned to
+    /// `final_layout` and this blocks until the commands have completed on `queue`.
+    ///
+    /// `vk::ImageUsageFlags::TRANSFER_DST` is added to the usage flags in `properties`.
+    pub fn new_with_compressed_data(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        mut properties: ImageProperties,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        data: &[u8],
+        final_layout: vk::ImageLayout,
+    ) -> Result<Self, ResourceInitError> {
+        let (copy_regions, expected_size) = compressed_mip_chain_copy_regions(
+            properties.format,
+            properties.dimensions,
+            properties.mip_levels,
+        )
+        .ok_or(ResourceInitError::FormatNotCompressed(properties.format))?;
+        if data.len() as vk::DeviceSize != expected_size {
+            return Err(ResourceInitError::DataSizeMismatch {
+                data_size: data.len(),
+                expected_size,
+            });
+        }
+
+        properties.usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        let image = Self::new(alloc_access.clone(), properties, allocation_info)
+            .map_err(ResourceInitError::Creation)?;
+
+        let staging_buffer = new_staging_buffer(alloc_access, data)?;
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            image.record_transition_to_transfer_dst(command_buffer);
+            command_buffer.copy_buffer_to_image(
+                &staging_buffer,
+                &image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copy_regions,
+            );
+            image.record_transition_from_transfer_dst(command_buffer, final_layout);
+        })?;
+
+        Ok(image)
+    }
+
+    /// Same as [`Self::new`] but the memory is attributed to `tag` (e.g. "meshes") in
+    /// [`MemoryAllocator::tagged_memory_report`](crate::MemoryAllocator::tagged_memory_report).
+    /// The tag is stored in the allocation user data.
+    pub fn new_tagged(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        properties: ImageProperties,
+        allocation_info: AllocationCreateInfo,
+        tag: &'static str,
+    ) -> Result<Self, AllocationError> {
+        let image = Self::new(alloc_access, properties, allocation_info)?;
+        unsafe {
+            image
+                .allocator_access()
+                .memory_allocator()
+                .set_allocation_tag(image.memory_allocation.handle(), tag);
+        }
+        Ok(image)
+    }
+
     /// # Safety
     /// Make sure your `p_next` chain contains valid pointers.
     pub unsafe fn new_from_create_info(
         alloc_access: Arc<dyn AllocatorAccess>,
         image_create_info: vk::ImageCreateInfo,
         allocation_info: AllocationCreateInfo,
-    ) -> VkResult<Self> {
+    ) -> Result<Self, AllocationError> {
         let properties = ImageProperties::from_create_info(&image_create_info);
 
         let (handle, allocation_handle) = unsafe {
@@ -54,6 +257,10 @@ impl Image {
                 .vma_create_image(&image_create_info, &allocation_info)
         }?;
 
+        let counted = alloc_access
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Image);
         let memory_allocation =
             MemoryAllocation::from_vma_allocation(allocation_handle, alloc_access);
 
@@ -61,22 +268,383 @@ impl Image {
             handle,
             properties,
             memory_allocation,
+            counted,
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Wraps a `VkImage` created elsewhere e.g. by existing ash code or other middleware.
+    ///
+    /// `allocation` is the vma allocation bound to `handle` (made with the allocator of
+    /// `alloc_access`), or `None` if the memory is managed elsewhere. With
+    /// [`HandleOwnership::Owned`] the image (and `allocation`) are destroyed on drop.
+    ///
+    /// # Safety
+    /// - `handle` must be a valid `VkImage` created with the device of `alloc_access` and described by
+    ///   `properties`.
+    /// - if `allocation` is `None`, the memory access functions of [`AllocationAccess`] and
+    ///   [`Self::memory_allocation`] must not be used.
+    /// - with [`HandleOwnership::Borrowed`] the image must outlive `self`.
+    pub unsafe fn from_raw(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        handle: vk::Image,
+        properties: ImageProperties,
+        allocation: Option<ffi::VmaAllocation>,
+        ownership: HandleOwnership,
+    ) -> Self {
+        let counted = ownership.is_owned()
+            && alloc_access
+                .device()
+                .object_counters()
+                .record_created(CountedObjectType::Image);
+        let memory_allocation = match allocation {
+            Some(allocation_handle) => {
+                MemoryAllocation::from_vma_allocation(allocation_handle, alloc_access)
+            }
+            None => MemoryAllocation::null(alloc_access),
+        };
+
+        Self {
+            handle,
+            properties,
+            memory_allocation,
+            counted,
+            ownership,
+        }
+    }
+
     /// Create a (preferably) lazily-allocated transient attachment image.
     pub fn new_tranient(
         memory_allocator: Arc<MemoryAllocator>,
         dimensions: ImageDimensions,
         format: vk::Format,
         additional_usage: vk::ImageUsageFlags,
-    ) -> VkResult<Self> {
+    ) -> Result<Self, AllocationError> {
         let (properties, allocation_info) =
             transient_image_info(dimensions, format, additional_usage);
 
         Self::new(memory_allocator, properties, allocation_info)
     }
 
+    /// Device local color attachment image with `COLOR_ATTACHMENT | additional_usage` usage
+    /// (e.g. add `SAMPLED` to read it in a later pass) and a view of the whole image.
+    pub fn new_color_attachment(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        dimensions: ImageDimensions,
+        format: vk::Format,
+        additional_usage: vk::ImageUsageFlags,
+    ) -> VkResult<(Arc<Self>, Arc<ImageView<Self>>)> {
+        let properties = ImageProperties::new_default(
+            format,
+            dimensions,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | additional_usage,
+        );
+        Self::new_with_default_view(alloc_access, properties)
+    }
+
+    /// Device local depth/stencil attachment image with `DEPTH_STENCIL_ATTACHMENT |
+    /// additional_usage` usage and a view of the whole image. See
+    /// [`guaranteed_depth_stencil_format`] and [`guaranteed_pure_depth_format`] for `format`.
+    ///
+    /// If `additional_usage` contains `SAMPLED`, the view only covers the depth aspect because
+    /// sampled views can't include both depth and stencil.
+    pub fn new_depth_attachment(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        dimensions: ImageDimensions,
+        format: vk::Format,
+        additional_usage: vk::ImageUsageFlags,
+    ) -> VkResult<(Arc<Self>, Arc<ImageView<Self>>)> {
+        let properties = ImageProperties::new_default(
+            format,
+            dimensions,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | additional_usage,
+        );
+        Self::new_with_default_view(alloc_access, properties)
+    }
+
+    /// Device local optimal tiling texture with `SAMPLED | TRANSFER_DST` usage (plus
+    /// `TRANSFER_SRC` when `mip_levels > 1` so the mip chain can be generated with blits) and a
+    /// view of the whole image. `mip_levels` is clamped to
+    /// [`ImageDimensions::max_mip_levels`].
+    pub fn new_sampled_texture(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        dimensions: ImageDimensions,
+        format: vk::Format,
+        mip_levels: u32,
+    ) -> VkResult<(Arc<Self>, Arc<ImageView<Self>>)> {
+        let mip_levels = mip_levels.clamp(1, dimensions.max_mip_levels());
+        let mut usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST;
+        if mip_levels > 1 {
+            usage |= vk::ImageUsageFlags::TRANSFER_SRC;
+        }
+
+        let properties = ImageProperties {
+            mip_levels,
+            ..ImageProperties::new_default(format, dimensions, usage)
+        };
+        Self::new_with_default_view(alloc_access, properties)
+    }
+
+    /// Device local storage image with `STORAGE | additional_usage` usage and a view of the whole
+    /// image.
+    pub fn new_storage_image(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        dimensions: ImageDimensions,
+        format: vk::Format,
+        additional_usage: vk::ImageUsageFlags,
+    ) -> VkResult<(Arc<Self>, Arc<ImageView<Self>>)> {
+        let properties = ImageProperties::new_default(
+            format,
+            dimensions,
+            vk::ImageUsageFlags::STORAGE | additional_usage,
+        );
+        Self::new_with_default_view(alloc_access, properties)
+    }
+
+    fn new_with_default_view(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        properties: ImageProperties,
+    ) -> VkResult<(Arc<Self>, Arc<ImageView<Self>>)> {
+        let view_properties = default_view_properties(&properties);
+        let image = Arc::new(Self::new(
+            alloc_access,
+            properties,
+            allocation_info_device_local(),
+        )?);
+        let image_view = Arc::new(ImageView::new(image.clone(), view_properties)?);
+        Ok((image, image_view))
+    }
+
+    /// Uploads tightly packed `data` to mip level 0 of `array_layer` via a temporary staging
+    /// buffer. `data` must be exactly the size of one layer of mip level 0 (see
+    /// [`image_copy_data_size`]) and the format must have a single aspect. The layer is
+    /// transitioned from `old_layout` to `TRANSFER_DST_OPTIMAL` and then to `final_layout`.
+    /// Blocks until the commands have completed on `queue`. Requires `TRANSFER_DST` usage.
+    pub fn upload_layer(
+        &self,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        array_layer: u32,
+        data: &[u8],
+        old_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Result<(), ResourceInitError> {
+        debug_assert!(
+            array_layer < self.properties.dimensions.array_layers(),
+            "array layer {} out of range",
+            array_layer
+        );
+        let format = self.properties.format;
+        let aspect_mask = single_copy_aspect(format)
+            .ok_or(ResourceInitError::CombinedDepthStencilFormat(format))?;
+        let layer_size = image_copy_data_size(format, self.properties.dimensions.extent_3d(), 1)
+            .ok_or(ResourceInitError::UnknownTexelSize(format))?;
+        if data.len() as vk::DeviceSize != layer_size {
+            return Err(ResourceInitError::DataSizeMismatch {
+                data_size: data.len(),
+                expected_size: layer_size,
+            });
+        }
+
+        let staging_buffer = new_staging_buffer(self.allocator_access().clone(), data)?;
+
+        let image_subresource = vk::ImageSubresourceLayers {
+            base_array_layer: array_layer,
+            ..default_subresource_layers(aspect_mask)
+        };
+        let copy_region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource,
+            image_offset: vk::Offset3D::default(),
+            image_extent: self.properties.dimensions.extent_3d(),
+        };
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask,
+            base_array_layer: array_layer,
+            layer_count: 1,
+            ..self.properties.subresource_range()
+        };
+
+        let to_transfer_dst = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .old_layout(old_layout)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(subresource_range);
+        let from_transfer_dst = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(final_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(subresource_range);
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_dst],
+            );
+            command_buffer.copy_buffer_to_image(
+                &staging_buffer,
+                self,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[from_transfer_dst],
+            );
+        })
+    }
+
+    /// Same as [`Self::upload_layer`] for `face` of a cube map. For cube map arrays use
+    /// [`Self::upload_layer`] with [`CubeFace::array_layer`].
+    pub fn upload_cube_face(
+        &self,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        face: CubeFace,
+        data: &[u8],
+        old_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Result<(), ResourceInitError> {
+        debug_assert!(
+            self.properties.is_cube_compatible(),
+            "uploading a cube face to an image that isn't cube compatible"
+        );
+        self.upload_layer(
+            command_pool,
+            queue,
+            face.array_layer(0),
+            data,
+            old_layout,
+            final_layout,
+        )
+    }
+
+    fn record_transition_to_transfer_dst(&self, command_buffer: &CommandBuffer) {
+        let image_barrier = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(self.properties.subresource_range());
+
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_barrier],
+        );
+    }
+
+    fn record_transition_from_transfer_dst(
+        &self,
+        command_buffer: &CommandBuffer,
+        final_layout: vk::ImageLayout,
+    ) {
+        let image_barrier = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(final_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.handle)
+            .subresource_range(self.properties.subresource_range());
+
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[image_barrier],
+        );
+    }
+
+    /// Offset, size and row/array/depth pitches of a subresource in the image memory. Only valid
+    /// for `LINEAR` (or `DRM_FORMAT_MODIFIER_EXT`) tiling images. `aspect` must be a single
+    /// aspect e.g. `COLOR`, `DEPTH` or `PLANE_0`.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetImageSubresourceLayout.html>
+    pub fn subresource_layout(
+        &self,
+        aspect: vk::ImageAspectFlags,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> vk::SubresourceLayout {
+        debug_assert!(
+            matches!(
+                self.properties.tiling,
+                vk::ImageTiling::LINEAR | vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT
+            ),
+            "subresource_layout requires LINEAR or DRM_FORMAT_MODIFIER_EXT tiling"
+        );
+        debug_assert!(mip_level < self.properties.mip_levels);
+        debug_assert!(array_layer < self.properties.dimensions.array_layers());
+
+        let subresource = vk::ImageSubresource {
+            aspect_mask: aspect,
+            mip_level,
+            array_layer,
+        };
+        unsafe {
+            self.device()
+                .inner()
+                .get_image_subresource_layout(self.handle, subresource)
+        }
+    }
+
+    /// Maps a subresource of a host visible `LINEAR` tiling image for row pitch aware CPU access.
+    /// See [`LinearImageView`].
+    pub fn linear_view(
+        &mut self,
+        aspect: vk::ImageAspectFlags,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Result<LinearImageView<'_>, LinearImageError> {
+        LinearImageView::new(self, aspect, mip_level, array_layer)
+    }
+
+    /// See [`ImageProperties::mip_levels_iter`].
+    #[inline]
+    pub fn mip_levels_iter(&self) -> impl Iterator<Item = ImageMipLevel> {
+        self.properties.mip_levels_iter()
+    }
+
+    /// See [`ImageProperties::layers_iter`].
+    #[inline]
+    pub fn layers_iter(&self, mip_level: u32) -> impl Iterator<Item = ImageSubresource> {
+        self.properties.layers_iter(mip_level)
+    }
+
+    /// See [`ImageProperties::subresources_iter`].
+    #[inline]
+    pub fn subresources_iter(&self) -> impl Iterator<Item = ImageSubresource> + '_ {
+        self.properties.subresources_iter()
+    }
+
     // Getters
 
     #[inline]
@@ -93,6 +661,11 @@ impl Image {
     pub fn memory_allocation(&self) -> &MemoryAllocation {
         &self.memory_allocation
     }
+
+    #[inline]
+    pub fn ownership(&self) -> HandleOwnership {
+        self.ownership
+    }
 }
 
 impl ImageAccess for Image {
@@ -105,6 +678,11 @@ impl ImageAccess for Image {
     fn dimensions(&self) -> ImageDimensions {
         self.properties.dimensions
     }
+
+    #[inline]
+    fn supports_view_format(&self, format: vk::Format) -> bool {
+        self.properties.supports_view_format(format)
+    }
 }
 
 impl AllocationAccess for Image {
@@ -127,12 +705,19 @@ impl DeviceOwned for Image {
 
 impl Drop for Image {
     fn drop(&mut self) {
+        if !self.ownership.is_owned() {
+            return;
+        }
         unsafe {
             self.allocator_access()
                 .clone()
                 .memory_allocator()
                 .vma_destroy_image(self.handle, self.memory_allocation.handle());
         }
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::Image, self.counted);
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
@@ -160,6 +745,51 @@ pub fn transient_image_info(
     (properties, allocation_info)
 }
 
+/// View properties covering the whole image. Depth/stencil images with `SAMPLED` usage only
+/// get the depth aspect.
+fn default_view_properties(image_properties: &ImageProperties) -> ImageViewProperties {
+    let mut view_properties = ImageViewProperties::from_image_properties_default(image_properties);
+    let aspect_mask = &mut view_properties.subresource_range.aspect_mask;
+    if image_properties
+        .usage
+        .contains(vk::ImageUsageFlags::SAMPLED)
+        && aspect_mask.contains(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL)
+    {
+        *aspect_mask = vk::ImageAspectFlags::DEPTH;
+    }
+    view_properties
+}
+
+// ~~ Cube Faces ~~
+
+/// Cube map faces in array layer order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    PositiveX = 0,
+    NegativeX = 1,
+    PositiveY = 2,
+    NegativeY = 3,
+    PositiveZ = 4,
+    NegativeZ = 5,
+}
+
+impl CubeFace {
+    pub const ALL: [Self; 6] = [
+        Self::PositiveX,
+        Self::NegativeX,
+        Self::PositiveY,
+        Self::NegativeY,
+        Self::PositiveZ,
+        Self::NegativeZ,
+    ];
+
+    /// Array layer of this face in cube `cube_index` of a cube (array) image.
+    #[inline]
+    pub fn array_layer(self, cube_index: u32) -> u32 {
+        cube_index * CUBE_FACE_COUNT + self as u32
+    }
+}
+
 // ~~ Image Properties ~~
 
 /// Note: default values for `format`, `dimensions` and `usage` are nothing!
@@ -175,6 +805,11 @@ pub struct ImageProperties {
     pub sharing_mode: vk::SharingMode,
     pub queue_family_indices: Vec<u32>,
     pub initial_layout: vk::ImageLayout,
+    /// Formats that views of a `MUTABLE_FORMAT` image can have, chained as a
+    /// `vk::ImageFormatListCreateInfo` (`VK_KHR_image_format_list` or Vulkan 1.2) when not empty.
+    /// Lets drivers keep optimizations (e.g. compression) that they'd otherwise have to disable
+    /// for any-format reinterpretation. See [`Self::new_mutable_format`].
+    pub view_formats: Vec<vk::Format>,
 }
 
 impl Default for ImageProperties {
@@ -187,6 +822,7 @@ impl Default for ImageProperties {
             queue_family_indices: Vec::new(),
             initial_layout: vk::ImageLayout::UNDEFINED,
             flags: vk::ImageCreateFlags::empty(),
+            view_formats: Vec::new(),
 
             // nonsense defaults. make sure you override these!
             format: vk::Format::default(),
@@ -208,6 +844,44 @@ impl ImageProperties {
         }
     }
 
+    /// Iterates over each mip level with its extent.
+    pub fn mip_levels_iter(&self) -> impl Iterator<Item = ImageMipLevel> {
+        let dimensions = self.dimensions;
+        let aspect_mask = aspect_mask_from_format(self.format);
+        let array_layer_count = dimensions.array_layers();
+        (0..self.mip_levels).map(move |mip_level| ImageMipLevel {
+            mip_level,
+            extent: mip_level_extent(dimensions, mip_level),
+            aspect_mask,
+            array_layer_count,
+        })
+    }
+
+    /// Iterates over each array layer (e.g. cube face) of `mip_level`. The subresources cover all
+    /// aspects of the format, pick one with [`ImageSubresource::buffer_image_copy`] for copies.
+    pub fn layers_iter(&self, mip_level: u32) -> impl Iterator<Item = ImageSubresource> {
+        debug_assert!(
+            mip_level < self.mip_levels,
+            "mip level {} out of range for an image with {} mip levels",
+            mip_level,
+            self.mip_levels
+        );
+        let extent = mip_level_extent(self.dimensions, mip_level);
+        let aspect_mask = aspect_mask_from_format(self.format);
+        (0..self.dimensions.array_layers()).map(move |array_layer| ImageSubresource {
+            mip_level,
+            array_layer,
+            extent,
+            aspect_mask,
+        })
+    }
+
+    /// Iterates over every subresource, layers first then mip levels, which matches the usual
+    /// layout of packed texture data (e.g. KTX2 stores each level's layers together).
+    pub fn subresources_iter(&self) -> impl Iterator<Item = ImageSubresource> + '_ {
+        (0..self.mip_levels).flat_map(move |mip_level| self.layers_iter(mip_level))
+    }
+
     #[inline]
     pub fn new_default(
         format: vk::Format,
@@ -222,7 +896,188 @@ impl ImageProperties {
         }
     }
 
-    pub fn create_info(&self) -> vk::ImageCreateInfo {
+    /// Properties with the `PROTECTED` flag. Allocate with
+    /// [`allocation_info_protected`](crate::allocation_info_protected).
+    pub fn new_protected(
+        format: vk::Format,
+        dimensions: ImageDimensions,
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        Self {
+            flags: vk::ImageCreateFlags::PROTECTED,
+            ..Self::new_default(format, dimensions, usage)
+        }
+    }
+
+    /// Properties with `MUTABLE_FORMAT` set so the image can be viewed as any of `view_formats`
+    /// (`format` is added if missing) e.g. an `R8G8B8A8_UNORM` storage image written by a compute
+    /// shader and sampled through an `R8G8B8A8_SRGB` view. The formats must be in the same
+    /// compatibility class.
+    pub fn new_mutable_format(
+        format: vk::Format,
+        dimensions: ImageDimensions,
+        usage: vk::ImageUsageFlags,
+        mut view_formats: Vec<vk::Format>,
+    ) -> Self {
+        if !view_formats.contains(&format) {
+            view_formats.insert(0, format);
+        }
+        Self {
+            flags: vk::ImageCreateFlags::MUTABLE_FORMAT,
+            view_formats,
+            ..Self::new_default(format, dimensions, usage)
+        }
+    }
+
+    /// Properties for a compressed image which a compute shader can write encoded blocks to
+    /// through a block texel view (see [`ImageViewProperties::new_block_texel_view`]). The usage
+    /// is `STORAGE | additional_usage` (e.g. add `SAMPLED`).
+    ///
+    /// Sets `MUTABLE_FORMAT` and `BLOCK_TEXEL_VIEW_COMPATIBLE` for the uncompressed view, and
+    /// `EXTENDED_USAGE` (Vulkan 1.1 or `VK_KHR_maintenance2`) because compressed formats don't
+    /// support `STORAGE` themselves. [`Self::view_formats`] is the compressed format and
+    /// [`CompressedBlockInfo::block_texel_view_format`]. Returns `None` if `format` isn't
+    /// compressed.
+    pub fn new_block_texel_view_compatible(
+        format: vk::Format,
+        dimensions: ImageDimensions,
+        additional_usage: vk::ImageUsageFlags,
+    ) -> Option<Self> {
+        let block_info = CompressedBlockInfo::from_format(format)?;
+        Some(Self {
+            flags: vk::ImageCreateFlags::MUTABLE_FORMAT
+                | vk::ImageCreateFlags::BLOCK_TEXEL_VIEW_COMPATIBLE
+                | vk::ImageCreateFlags::EXTENDED_USAGE,
+            view_formats: vec![format, block_info.block_texel_view_format()],
+            ..Self::new_default(
+                format,
+                dimensions,
+                vk::ImageUsageFlags::STORAGE | additional_usage,
+            )
+        })
+    }
+
+    /// Properties for a cube map with `CUBE_COMPATIBLE` set and 6 array layers. View it with
+    /// [`ImageViewProperties::new_cube`].
+    pub fn new_cube_map(format: vk::Format, size: u32, usage: vk::ImageUsageFlags) -> Self {
+        Self {
+            flags: vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            ..Self::new_default(format, ImageDimensions::new_cube(size), usage)
+        }
+    }
+
+    /// Properties for `cube_count` cube maps with `CUBE_COMPATIBLE` set and 6 array layers per
+    /// cube. Viewing with [`ImageViewProperties::new_cube`] results in a `CUBE_ARRAY` view which
+    /// requires the `imageCubeArray` feature.
+    pub fn new_cube_map_array(
+        format: vk::Format,
+        size: u32,
+        cube_count: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        Self {
+            flags: vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            ..Self::new_default(
+                format,
+                ImageDimensions::new_cube_array(size, cube_count),
+                usage,
+            )
+        }
+    }
+
+    /// Properties for a 3D image with `TYPE_2D_ARRAY_COMPATIBLE` set so depth slices can be
+    /// viewed as 2D images with [`ImageViewProperties::new_3d_slices`]. Rendering to such views
+    /// only needs Vulkan 1.1 or `VK_KHR_maintenance1`. To use single slice views in sampled or
+    /// storage image descriptors, see [`Self::with_2d_view_of_3d`].
+    pub fn new_3d_slice_viewable(
+        format: vk::Format,
+        width: u32,
+        height: u32,
+        depth: u32,
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        Self {
+            flags: vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE,
+            ..Self::new_default(format, ImageDimensions::new_3d(width, height, depth), usage)
+        }
+    }
+
+    /// Properties for a `VK_EXT_fragment_density_map` attachment of `width_height` texels (see
+    /// [`FragmentDensityMapSupport::density_map_extent`](crate::FragmentDensityMapSupport::density_map_extent)).
+    /// `usage` is added to `FRAGMENT_DENSITY_MAP_EXT` e.g. `TRANSFER_DST` to upload the densities.
+    /// The format must support `FRAGMENT_DENSITY_MAP_EXT`, `R8G8_UNORM` is the usual choice.
+    pub fn new_fragment_density_map(
+        format: vk::Format,
+        width_height: [u32; 2],
+        usage: vk::ImageUsageFlags,
+    ) -> Self {
+        Self::new_default(
+            format,
+            ImageDimensions::new_2d(width_height[0], width_height[1]),
+            vk::ImageUsageFlags::FRAGMENT_DENSITY_MAP_EXT | usage,
+        )
+    }
+
+    /// Sets `SUBSAMPLED_EXT` which is required for images rendered to with a fragment density
+    /// map and then sampled in a later pass. Those samplers need
+    /// `vk::SamplerCreateFlags::SUBSAMPLED_EXT` too.
+    pub fn with_subsampled(mut self) -> Self {
+        self.flags |= vk::ImageCreateFlags::SUBSAMPLED_EXT;
+        self
+    }
+
+    /// Sets `TYPE_2D_VIEW_COMPATIBLE_EXT` on a 3D image so single slice 2D views can be used in
+    /// sampled or storage image descriptors. Requires `VK_EXT_image_2d_view_of_3d` and its
+    /// `image2DViewOf3D` feature (and `sampler2DViewOf3D` for sampled images).
+    pub fn with_2d_view_of_3d(mut self) -> Self {
+        self.flags |= vk::ImageCreateFlags::TYPE_2D_VIEW_COMPATIBLE_EXT;
+        self
+    }
+
+    /// Adds the `ATTACHMENT_FEEDBACK_LOOP_EXT` usage which is required for images used in the
+    /// `ATTACHMENT_FEEDBACK_LOOP_OPTIMAL_EXT` layout i.e. read in shaders while also bound as a
+    /// color or depth/stencil attachment. The image also needs a `COLOR_ATTACHMENT` or
+    /// `DEPTH_STENCIL_ATTACHMENT` usage and a `SAMPLED` or `INPUT_ATTACHMENT` usage. See
+    /// [`AttachmentFeedbackLoopSupport`](crate::AttachmentFeedbackLoopSupport).
+    pub fn with_attachment_feedback_loop(mut self) -> Self {
+        self.usage |= vk::ImageUsageFlags::ATTACHMENT_FEEDBACK_LOOP_EXT;
+        self
+    }
+
+    /// Whether `CUBE_COMPATIBLE` is set and the dimensions are square 2D with a multiple of 6
+    /// array layers.
+    pub fn is_cube_compatible(&self) -> bool {
+        self.flags.contains(vk::ImageCreateFlags::CUBE_COMPATIBLE)
+            && self.dimensions.is_cube_compatible()
+    }
+
+    /// Whether views of the image can have `format`: either the image format, or the image has
+    /// `MUTABLE_FORMAT` set and `format` is in [`Self::view_formats`] (any format if the list is
+    /// empty).
+    pub fn supports_view_format(&self, format: vk::Format) -> bool {
+        if format == self.format {
+            return true;
+        }
+        if !self.flags.contains(vk::ImageCreateFlags::MUTABLE_FORMAT) {
+            return false;
+        }
+        self.view_formats.is_empty() || self.view_formats.contains(&format)
+    }
+
+    /// Chain this to [`Self::create_info`] when `view_formats` isn't empty.
+    pub fn format_list_create_info(&self) -> Option<vk::ImageFormatListCreateInfo<'_>> {
+        if self.view_formats.is_empty() {
+            return None;
+        }
+        debug_assert!(
+            self.flags.contains(vk::ImageCreateFlags::MUTABLE_FORMAT)
+                || self.view_formats == [self.format],
+            "view_formats other than the image format require MUTABLE_FORMAT"
+        );
+        Some(vk::ImageFormatListCreateInfo::default().view_formats(&self.view_formats))
+    }
+
+    pub fn create_info(&self) -> vk::ImageCreateInfo<'_> {
         vk::ImageCreateInfo::default()
             .flags(self.flags)
             .image_type(self.dimensions.image_type())
@@ -238,7 +1093,46 @@ impl ImageProperties {
             .queue_family_indices(&self.queue_family_indices)
     }
 
-    fn from_create_info(value: &vk::ImageCreateInfo) -> Self {
+    /// Memory requirements of an image created with these properties, queried without creating
+    /// one, e.g. to check a texture against
+    /// [`MemoryAllocator::fits_in_budget`](crate::MemoryAllocator::fits_in_budget) before
+    /// streaming it in. Returns `None` unless vulkan 1.3 or `VK_KHR_maintenance4` is available
+    /// (see [`device_memory_requirements_available`]). Not for `DISJOINT` multi-planar images,
+    /// which have requirements per plane.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetDeviceImageMemoryRequirements.html>
+    pub fn memory_requirements(&self, device: &Device) -> Option<vk::MemoryRequirements> {
+        if !device_memory_requirements_available(device) {
+            return None;
+        }
+        let mut format_list = self.format_list_create_info();
+        let mut create_info = self.create_info();
+        if let Some(format_list) = format_list.as_mut() {
+            create_info = create_info.push_next(format_list);
+        }
+        let requirements_info =
+            vk::DeviceImageMemoryRequirements::default().create_info(&create_info);
+        let mut memory_requirements = vk::MemoryRequirements2::default();
+        if device.effective_api_version() >= ApiVersion::V1_3 {
+            unsafe {
+                device.inner().get_device_image_memory_requirements(
+                    &requirements_info,
+                    &mut memory_requirements,
+                )
+            }
+        } else {
+            let maintenance_4_fns = device.extension_loader::<khr::maintenance4::Device>();
+            unsafe {
+                maintenance_4_fns.get_device_image_memory_requirements(
+                    &requirements_info,
+                    &mut memory_requirements,
+                )
+            }
+        }
+        Some(memory_requirements.memory_requirements)
+    }
+
+    pub fn from_create_info(value: &vk::ImageCreateInfo) -> Self {
         let dimensions =
             ImageDimensions::new_from_extent_and_layers(value.extent, value.array_layers);
 
@@ -248,6 +1142,24 @@ impl ImageProperties {
             queue_family_indices.push(queue_family_index);
         }
 
+        let mut view_formats = Vec::<vk::Format>::new();
+        let mut p_next = value.p_next as *const vk::BaseInStructure;
+        while let Some(next) = unsafe { p_next.as_ref() } {
+            if next.s_type == vk::StructureType::IMAGE_FORMAT_LIST_CREATE_INFO {
+                let format_list = unsafe { &*(p_next as *const vk::ImageFormatListCreateInfo) };
+                if !format_list.p_view_formats.is_null() {
+                    view_formats = unsafe {
+                        std::slice::from_raw_parts(
+                            format_list.p_view_formats,
+                            format_list.view_format_count as usize,
+                        )
+                    }
+                    .to_vec();
+                }
+            }
+            p_next = next.p_next;
+        }
+
         Self {
             mip_levels: value.mip_levels,
             samples: value.samples,
@@ -259,10 +1171,17 @@ impl ImageProperties {
             format: value.format,
             dimensions,
             usage: value.usage,
+            view_formats,
         }
     }
 }
 
+impl From<&vk::ImageCreateInfo<'_>> for ImageProperties {
+    fn from(value: &vk::ImageCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 // Helper Functions
 
 /// Returns a depth stencil format guarenteed by the vulkan spec to be supported as a depth stencil
@@ -366,3 +1285,201 @@ pub fn aspect_mask_from_format(format: vk::Format) -> vk::ImageAspectFlags {
 
     aspect
 }
+
+/// Extent of `mip_level` of an image with `dimensions`. Each dimension (including depth for 3D
+/// images) is halved per level and clamped to 1.
+pub fn mip_level_extent(dimensions: ImageDimensions, mip_level: u32) -> vk::Extent3D {
+    let extent = dimensions.extent_3d();
+    let level_dimension = |dimension: u32| dimension.checked_shr(mip_level).unwrap_or(0).max(1);
+    vk::Extent3D {
+        width: level_dimension(extent.width),
+        height: level_dimension(extent.height),
+        depth: level_dimension(extent.depth),
+    }
+}
+
+/// Copy region for uploading a box of texels to a 3D image from tightly packed buffer data at
+/// `buffer_offset`. 3D images only have one array layer; depth is part of `image_offset` and
+/// `image_extent`.
+pub fn buffer_image_copy_3d(
+    buffer_offset: vk::DeviceSize,
+    aspect_mask: vk::ImageAspectFlags,
+    mip_level: u32,
+    image_offset: vk::Offset3D,
+    image_extent: vk::Extent3D,
+) -> vk::BufferImageCopy {
+    vk::BufferImageCopy {
+        buffer_offset,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: vk::ImageSubresourceLayers {
+            mip_level,
+            ..default_subresource_layers(aspect_mask)
+        },
+        image_offset,
+        image_extent,
+    }
+}
+
+/// Copy region for uploading `slice_count` whole depth slices starting at `first_slice` of
+/// `mip_level` of a 3D image, from tightly packed buffer data at `buffer_offset`.
+pub fn buffer_image_copy_3d_slices(
+    buffer_offset: vk::DeviceSize,
+    image_properties: &ImageProperties,
+    mip_level: u32,
+    first_slice: u32,
+    slice_count: u32,
+) -> vk::BufferImageCopy {
+    let level_extent = mip_level_extent(image_properties.dimensions, mip_level);
+    debug_assert!(
+        first_slice + slice_count <= level_extent.depth,
+        "slices {}..{} out of range for mip level depth {}",
+        first_slice,
+        first_slice + slice_count,
+        level_extent.depth
+    );
+    buffer_image_copy_3d(
+        buffer_offset,
+        aspect_mask_from_format(image_properties.format),
+        mip_level,
+        vk::Offset3D {
+            x: 0,
+            y: 0,
+            z: first_slice as i32,
+        },
+        vk::Extent3D {
+            depth: slice_count,
+            ..level_extent
+        },
+    )
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn default_view_properties_aspect() {
+    let dimensions = ImageDimensions::new_2d(64, 64);
+
+    let depth_stencil = ImageProperties::new_default(
+        vk::Format::D24_UNORM_S8_UINT,
+        dimensions,
+        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+    );
+    assert_eq!(
+        default_view_properties(&depth_stencil)
+            .subresource_range
+            .aspect_mask,
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    );
+
+    let sampled_depth_stencil = ImageProperties {
+        usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        ..depth_stencil
+    };
+    assert_eq!(
+        default_view_properties(&sampled_depth_stencil)
+            .subresource_range
+            .aspect_mask,
+        vk::ImageAspectFlags::DEPTH
+    );
+
+    let texture = ImageProperties {
+        mip_levels: 4,
+        ..ImageProperties::new_default(
+            vk::Format::R8G8B8A8_SRGB,
+            dimensions,
+            vk::ImageUsageFlags::SAMPLED,
+        )
+    };
+    let texture_view = default_view_properties(&texture);
+    assert_eq!(
+        texture_view.subresource_range.aspect_mask,
+        vk::ImageAspectFlags::COLOR
+    );
+    assert_eq!(texture_view.subresource_range.level_count, 4);
+    assert_eq!(texture_view.view_type, vk::ImageViewType::TYPE_2D);
+}
+
+#[test]
+fn cube_map_properties() {
+    let cube_map = ImageProperties::new_cube_map(
+        vk::Format::R16G16B16A16_SFLOAT,
+        256,
+        vk::ImageUsageFlags::SAMPLED,
+    );
+    assert!(cube_map.is_cube_compatible());
+    assert_eq!(cube_map.create_info().array_layers, 6);
+
+    let not_cube = ImageProperties {
+        flags: vk::ImageCreateFlags::empty(),
+        ..cube_map
+    };
+    assert!(!not_cube.is_cube_compatible());
+
+    assert_eq!(CubeFace::PositiveX.array_layer(0), 0);
+    assert_eq!(CubeFace::NegativeZ.array_layer(2), 17);
+}
+
+#[test]
+fn mutable_format_view_formats() {
+    let storage = ImageProperties::new_mutable_format(
+        vk::Format::R8G8B8A8_UNORM,
+        ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+        vec![vk::Format::R8G8B8A8_SRGB],
+    );
+    assert!(storage.supports_view_format(vk::Format::R8G8B8A8_UNORM));
+    assert!(storage.supports_view_format(vk::Format::R8G8B8A8_SRGB));
+    assert!(!storage.supports_view_format(vk::Format::R32_UINT));
+
+    let format_list = storage.format_list_create_info().unwrap();
+    assert_eq!(format_list.view_format_count, 2);
+    let mut format_list = format_list;
+    let create_info = storage.create_info().push_next(&mut format_list);
+    let round_trip = ImageProperties::from_create_info(&create_info);
+    assert_eq!(round_trip.view_formats, storage.view_formats);
+
+    let immutable = ImageProperties::new_default(
+        vk::Format::R8G8B8A8_UNORM,
+        ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::SAMPLED,
+    );
+    assert!(!immutable.supports_view_format(vk::Format::R8G8B8A8_SRGB));
+    assert!(immutable.format_list_create_info().is_none());
+}
+
+#[test]
+fn copy_regions_3d() {
+    let volume = ImageProperties::new_3d_slice_viewable(
+        vk::Format::R16_SFLOAT,
+        64,
+        32,
+        16,
+        vk::ImageUsageFlags::SAMPLED,
+    );
+
+    assert_eq!(volume.flags, vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE);
+    assert!(volume
+        .clone()
+        .with_2d_view_of_3d()
+        .flags
+        .contains(vk::ImageCreateFlags::TYPE_2D_VIEW_COMPATIBLE_EXT));
+
+    let mip_2 = mip_level_extent(volume.dimensions, 2);
+    assert_eq!((mip_2.width, mip_2.height, mip_2.depth), (16, 8, 4));
+    let mip_6 = mip_level_extent(volume.dimensions, 6);
+    assert_eq!((mip_6.width, mip_6.height, mip_6.depth), (1, 1, 1));
+
+    let region = buffer_image_copy_3d_slices(0, &volume, 1, 2, 3);
+    assert_eq!(region.image_offset.z, 2);
+    assert_eq!(
+        (
+            region.image_extent.width,
+            region.image_extent.height,
+            region.image_extent.depth
+        ),
+        (32, 16, 3)
+    );
+    assert_eq!(region.image_subresource.mip_level, 1);
+    assert_eq!(region.image_subresource.layer_count, 1);
+}
@@ -8,6 +8,13 @@ use std::{error, fmt};
 pub trait ImageAccess: DeviceOwned + Send + Sync {
     fn handle(&self) -> vk::Image;
     fn dimensions(&self) -> ImageDimensions;
+
+    /// Whether views of the image can have `format`. Used to validate format reinterpretation
+    /// when creating an [`ImageView`](crate::ImageView). Defaults to `true` for images whose
+    /// create info isn't known.
+    fn supports_view_format(&self, _format: vk::Format) -> bool {
+        true
+    }
 }
 
 // ~~ Image Access Error ~~
@@ -0,0 +1,566 @@
+use crate::{
+    aspect_mask_from_format, default_component_mapping, format_numeric_type, CommandBuffer,
+    ComputePipeline, ComputePipelineProperties, DescriptorPool, DescriptorPoolProperties,
+    DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutError,
+    DescriptorSetLayoutProperties, Device, DeviceOwned, FormatNumericType, ImageViewAccess,
+    PipelineLayout, PipelineLayoutProperties, ShaderError, ShaderModule, ShaderStage,
+};
+use ash::vk;
+use std::{error, fmt, io::Cursor, sync::Arc};
+
+/// Spir-V compiled from `shaders/convert_image.comp`.
+const CONVERT_IMAGE_SPIRV: &[u8] = include_bytes!("./shaders/convert_image.comp.spv");
+/// Must match the `local_size_x` and `local_size_y` of `shaders/convert_image.comp`.
+const CONVERT_IMAGE_WORKGROUP_SIZE: u32 = 8;
+
+/// How [`ImageConverter::convert_image`] copies between two image views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageConvertMethod {
+    /// `vkCmdBlitImage`. Requires identity component mappings, matching numeric formats
+    /// (float/unorm/snorm vs uint vs sint) and blit support for both formats. Handles e.g.
+    /// BGRA <-> RGBA and unorm <-> float conversions.
+    Blit,
+    /// A compute dispatch that fetches from `src` (applying its component mapping) and stores to
+    /// `dst`. Handles swizzles and float/normalized format conversions that blits can't. Requires
+    /// `shaderStorageImageWriteWithoutFormat` and storage image support for the dst format.
+    Compute,
+}
+
+/// Copies between two color image views of the same extent, converting formats and applying
+/// swizzles. Picks [`ImageConvertMethod::Blit`] when possible and falls back to
+/// [`ImageConvertMethod::Compute`] otherwise, based on format support queries. Handy for
+/// screenshot export and texture import.
+///
+/// Assumes both images have optimal tiling. Barriers before and after the conversion are up to
+/// the caller.
+pub struct ImageConverter {
+    pipeline: ComputePipeline,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    descriptor_pool: Arc<DescriptorPool>,
+}
+
+impl ImageConverter {
+    /// `max_conversions_in_flight` is the number of compute conversions whose
+    /// [`ImageConversion`] can be alive at once.
+    pub fn new(
+        device: Arc<Device>,
+        max_conversions_in_flight: u32,
+    ) -> Result<Self, ImageConvertError> {
+        let bindings = vec![
+            DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+            DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+        ];
+        let descriptor_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(bindings),
+            )
+            .map_err(ImageConvertError::DescriptorSetLayout)?,
+        );
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: std::mem::size_of::<[i32; 2]>() as u32,
+        };
+        let pipeline_layout = Arc::new(
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutProperties::new(
+                    vec![descriptor_set_layout.clone()],
+                    vec![push_constant_range],
+                ),
+            )
+            .map_err(ImageConvertError::Vulkan)?,
+        );
+
+        let shader_module = Arc::new(
+            ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(CONVERT_IMAGE_SPIRV))
+                .map_err(ImageConvertError::Shader)?,
+        );
+        let shader_stage =
+            ShaderStage::compute(shader_module).map_err(ImageConvertError::Shader)?;
+        let pipeline = ComputePipeline::new(
+            pipeline_layout,
+            ComputePipelineProperties::default(),
+            &shader_stage,
+            None,
+        )
+        .map_err(ImageConvertError::Vulkan)?;
+
+        let max_sets = max_conversions_in_flight.max(1);
+        let pool_sizes = vec![
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::SAMPLED_IMAGE,
+                descriptor_count: max_sets,
+            },
+            vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: max_sets,
+            },
+        ];
+        let descriptor_pool_properties = DescriptorPoolProperties {
+            flags: vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+            max_sets,
+            pool_sizes,
+        };
+        let descriptor_pool = Arc::new(
+            DescriptorPool::new(device, descriptor_pool_properties)
+                .map_err(ImageConvertError::Vulkan)?,
+        );
+
+        Ok(Self {
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+        })
+    }
+
+    /// The method [`Self::convert_image`] will use for these views. Use this to decide which
+    /// layouts to transition the images to beforehand:
+    /// - [`ImageConvertMethod::Blit`]: `src` in `TRANSFER_SRC_OPTIMAL` or `GENERAL` and `dst` in
+    ///   `TRANSFER_DST_OPTIMAL` or `GENERAL`.
+    /// - [`ImageConvertMethod::Compute`]: `src` in `SHADER_READ_ONLY_OPTIMAL` or `GENERAL` and
+    ///   `dst` in `GENERAL`.
+    pub fn conversion_method(
+        &self,
+        src_view: &dyn ImageViewAccess,
+        dst_view: &dyn ImageViewAccess,
+    ) -> Result<ImageConvertMethod, ImageConvertError> {
+        let src_properties = src_view.properties();
+        let dst_properties = dst_view.properties();
+
+        for format in [src_properties.format, dst_properties.format] {
+            if aspect_mask_from_format(format) != vk::ImageAspectFlags::COLOR {
+                return Err(ImageConvertError::NonColorFormat(format));
+            }
+        }
+
+        let src_extent = view_extent(src_view);
+        let dst_extent = view_extent(dst_view);
+        if src_extent != dst_extent {
+            return Err(ImageConvertError::ExtentMismatch {
+                src_extent,
+                dst_extent,
+            });
+        }
+
+        let device = self.device();
+        let src_features = optimal_tiling_features(device, src_properties.format);
+        let dst_features = optimal_tiling_features(device, dst_properties.format);
+        let storage_write_without_format = device
+            .enabled_features()
+            .features_1_0
+            .shader_storage_image_write_without_format
+            == vk::TRUE;
+
+        select_conversion_method(&ConversionSupport {
+            src_format: src_properties.format,
+            dst_format: dst_properties.format,
+            identity_component_mappings: is_identity_mapping(src_properties.component_mapping)
+                && is_identity_mapping(dst_properties.component_mapping),
+            views_2d: src_properties.view_type == vk::ImageViewType::TYPE_2D
+                && dst_properties.view_type == vk::ImageViewType::TYPE_2D,
+            src_features,
+            dst_features,
+            storage_write_without_format,
+        })
+    }
+
+    /// Records a copy from `src_view` to `dst_view` using the method returned by
+    /// [`Self::conversion_method`]. Only the first mip level and array layer of each view are
+    /// converted for the compute method.
+    ///
+    /// Keep the returned [`ImageConversion`] alive until `command_buffer` has finished executing.
+    pub fn convert_image(
+        &self,
+        command_buffer: &CommandBuffer,
+        src_view: &dyn ImageViewAccess,
+        src_layout: vk::ImageLayout,
+        dst_view: &dyn ImageViewAccess,
+        dst_layout: vk::ImageLayout,
+    ) -> Result<ImageConversion, ImageConvertError> {
+        let method = self.conversion_method(src_view, dst_view)?;
+        let extent = view_extent(src_view);
+
+        match method {
+            ImageConvertMethod::Blit => {
+                debug_assert!(matches!(
+                    src_layout,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL | vk::ImageLayout::GENERAL
+                ));
+                debug_assert!(matches!(
+                    dst_layout,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL | vk::ImageLayout::GENERAL
+                ));
+
+                let src_subresource = view_subresource_layers(src_view);
+                let dst_subresource = view_subresource_layers(dst_view);
+                let layer_count = src_subresource.layer_count.min(dst_subresource.layer_count);
+                let blit_offsets = [
+                    vk::Offset3D::default(),
+                    vk::Offset3D {
+                        x: extent.width as i32,
+                        y: extent.height as i32,
+                        z: 1,
+                    },
+                ];
+                let region = vk::ImageBlit {
+                    src_subresource: src_subresource.layer_count(layer_count),
+                    src_offsets: blit_offsets,
+                    dst_subresource: dst_subresource.layer_count(layer_count),
+                    dst_offsets: blit_offsets,
+                };
+
+                command_buffer.blit_image(
+                    src_view.image_access().as_ref(),
+                    src_layout,
+                    dst_view.image_access().as_ref(),
+                    dst_layout,
+                    &[region],
+                    vk::Filter::NEAREST,
+                );
+
+                Ok(ImageConversion {
+                    method,
+                    descriptor_set: None,
+                })
+            }
+
+            ImageConvertMethod::Compute => {
+                debug_assert!(matches!(
+                    src_layout,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+                        | vk::ImageLayout::READ_ONLY_OPTIMAL
+                        | vk::ImageLayout::GENERAL
+                ));
+                debug_assert_eq!(dst_layout, vk::ImageLayout::GENERAL);
+
+                let descriptor_set = self
+                    .descriptor_pool
+                    .allocate_descriptor_set(self.descriptor_set_layout.clone())
+                    .map_err(ImageConvertError::Vulkan)?;
+
+                let src_image_info = [vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: src_view.handle(),
+                    image_layout: src_layout,
+                }];
+                let dst_image_info = [vk::DescriptorImageInfo {
+                    sampler: vk::Sampler::null(),
+                    image_view: dst_view.handle(),
+                    image_layout: dst_layout,
+                }];
+                let descriptor_writes = [
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(0)
+                        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                        .image_info(&src_image_info),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(1)
+                        .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+                        .image_info(&dst_image_info),
+                ];
+                self.device().update_descriptor_sets(descriptor_writes, []);
+
+                let push_constants = [extent.width as i32, extent.height as i32];
+                let push_constant_bytes: Vec<u8> = push_constants
+                    .iter()
+                    .flat_map(|value| value.to_ne_bytes())
+                    .collect();
+                self.pipeline.record_dispatch(
+                    command_buffer,
+                    &descriptor_set,
+                    &push_constant_bytes,
+                    [
+                        extent.width.div_ceil(CONVERT_IMAGE_WORKGROUP_SIZE),
+                        extent.height.div_ceil(CONVERT_IMAGE_WORKGROUP_SIZE),
+                        1,
+                    ],
+                );
+
+                Ok(ImageConversion {
+                    method,
+                    descriptor_set: Some(descriptor_set),
+                })
+            }
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+}
+
+impl DeviceOwned for ImageConverter {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pipeline.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.pipeline.handle_raw()
+    }
+}
+
+/// What a recorded conversion used. Blit and copy conversions hold nothing; compute conversions
+/// hold the descriptor set referencing the source and destination views, which must stay alive
+/// until the conversion's command buffer has completed.
+pub struct ImageConversion {
+    method: ImageConvertMethod,
+    descriptor_set: Option<DescriptorSet>,
+}
+
+impl ImageConversion {
+    // Getters
+
+    #[inline]
+    pub fn method(&self) -> ImageConvertMethod {
+        self.method
+    }
+
+    #[inline]
+    pub fn descriptor_set(&self) -> Option<&DescriptorSet> {
+        self.descriptor_set.as_ref()
+    }
+}
+
+// Helper Functions
+
+/// Format and view properties that determine how two views can be converted.
+struct ConversionSupport {
+    src_format: vk::Format,
+    dst_format: vk::Format,
+    identity_component_mappings: bool,
+    views_2d: bool,
+    src_features: vk::FormatFeatureFlags,
+    dst_features: vk::FormatFeatureFlags,
+    storage_write_without_format: bool,
+}
+
+fn select_conversion_method(
+    support: &ConversionSupport,
+) -> Result<ImageConvertMethod, ImageConvertError> {
+    let src_numeric_type = format_numeric_type(support.src_format);
+    let dst_numeric_type = format_numeric_type(support.dst_format);
+
+    let blit_supported = support.identity_component_mappings
+        && src_numeric_type == dst_numeric_type
+        && support
+            .src_features
+            .contains(vk::FormatFeatureFlags::BLIT_SRC)
+        && support
+            .dst_features
+            .contains(vk::FormatFeatureFlags::BLIT_DST);
+    if blit_supported {
+        return Ok(ImageConvertMethod::Blit);
+    }
+
+    let compute_supported = support.views_2d
+        && support.storage_write_without_format
+        && src_numeric_type == FormatNumericType::Float
+        && dst_numeric_type == FormatNumericType::Float
+        && support
+            .src_features
+            .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE)
+        && support
+            .dst_features
+            .contains(vk::FormatFeatureFlags::STORAGE_IMAGE);
+    if compute_supported {
+        return Ok(ImageConvertMethod::Compute);
+    }
+
+    Err(ImageConvertError::UnsupportedConversion {
+        src_format: support.src_format,
+        dst_format: support.dst_format,
+    })
+}
+
+fn optimal_tiling_features(device: &Device, format: vk::Format) -> vk::FormatFeatureFlags {
+    let physical_device = device.physical_device();
+    unsafe {
+        physical_device
+            .instance()
+            .inner()
+            .get_physical_device_format_properties(physical_device.handle(), format)
+    }
+    .optimal_tiling_features
+}
+
+fn is_identity_mapping(component_mapping: vk::ComponentMapping) -> bool {
+    let default_mapping = default_component_mapping();
+    let is_identity = |swizzle: vk::ComponentSwizzle, default_swizzle: vk::ComponentSwizzle| {
+        swizzle == vk::ComponentSwizzle::IDENTITY || swizzle == default_swizzle
+    };
+    is_identity(component_mapping.r, default_mapping.r)
+        && is_identity(component_mapping.g, default_mapping.g)
+        && is_identity(component_mapping.b, default_mapping.b)
+        && is_identity(component_mapping.a, default_mapping.a)
+}
+
+/// Extent of the base mip level of the view.
+fn view_extent(image_view: &dyn ImageViewAccess) -> vk::Extent2D {
+    let dimensions = image_view.image_access().dimensions();
+    let base_mip_level = image_view.properties().subresource_range.base_mip_level;
+    vk::Extent2D {
+        width: (dimensions.width() >> base_mip_level).max(1),
+        height: (dimensions.height() >> base_mip_level).max(1),
+    }
+}
+
+fn view_subresource_layers(image_view: &dyn ImageViewAccess) -> vk::ImageSubresourceLayers {
+    let subresource_range = image_view.properties().subresource_range;
+    let layer_count = if subresource_range.layer_count == vk::REMAINING_ARRAY_LAYERS {
+        image_view.image_access().dimensions().array_layers() - subresource_range.base_array_layer
+    } else {
+        subresource_range.layer_count
+    };
+    vk::ImageSubresourceLayers {
+        aspect_mask: subresource_range.aspect_mask,
+        mip_level: subresource_range.base_mip_level,
+        base_array_layer: subresource_range.base_array_layer,
+        layer_count,
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum ImageConvertError {
+    NonColorFormat(vk::Format),
+    ExtentMismatch {
+        src_extent: vk::Extent2D,
+        dst_extent: vk::Extent2D,
+    },
+    /// Neither a blit nor the compute path supports these formats/views on this device.
+    UnsupportedConversion {
+        src_format: vk::Format,
+        dst_format: vk::Format,
+    },
+    Shader(ShaderError),
+    DescriptorSetLayout(DescriptorSetLayoutError),
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for ImageConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonColorFormat(format) => write!(
+                f,
+                "image conversion only supports color formats but got {:?}",
+                format
+            ),
+            Self::ExtentMismatch {
+                src_extent,
+                dst_extent,
+            } => write!(
+                f,
+                "image conversion requires equal extents but src is {}x{} and dst is {}x{}",
+                src_extent.width, src_extent.height, dst_extent.width, dst_extent.height
+            ),
+            Self::UnsupportedConversion {
+                src_format,
+                dst_format,
+            } => write!(
+                f,
+                "conversion from {:?} to {:?} isn't supported by blits or the compute path",
+                src_format, dst_format
+            ),
+            Self::Shader(e) => write!(f, "failed to create image conversion shader: {}", e),
+            Self::Vulkan(e) => write!(f, "image conversion vulkan call failed: {}", e),
+            Self::DescriptorSetLayout(e) => write!(
+                f,
+                "image conversion descriptor set layout creation failed: {}",
+                e
+            ),
+        }
+    }
+}
+
+impl error::Error for ImageConvertError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Shader(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+            Self::DescriptorSetLayout(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn conversion_method_selection() {
+    let all_features = vk::FormatFeatureFlags::BLIT_SRC
+        | vk::FormatFeatureFlags::BLIT_DST
+        | vk::FormatFeatureFlags::SAMPLED_IMAGE
+        | vk::FormatFeatureFlags::STORAGE_IMAGE;
+    let bgra_to_rgba = ConversionSupport {
+        src_format: vk::Format::B8G8R8A8_UNORM,
+        dst_format: vk::Format::R8G8B8A8_UNORM,
+        identity_component_mappings: true,
+        views_2d: true,
+        src_features: all_features,
+        dst_features: all_features,
+        storage_write_without_format: true,
+    };
+    assert_eq!(
+        select_conversion_method(&bgra_to_rgba).unwrap(),
+        ImageConvertMethod::Blit
+    );
+
+    let swizzled = ConversionSupport {
+        identity_component_mappings: false,
+        ..bgra_to_rgba
+    };
+    assert_eq!(
+        select_conversion_method(&swizzled).unwrap(),
+        ImageConvertMethod::Compute
+    );
+
+    let no_blit_dst = ConversionSupport {
+        dst_format: vk::Format::R16G16B16A16_SFLOAT,
+        dst_features: vk::FormatFeatureFlags::STORAGE_IMAGE,
+        ..bgra_to_rgba
+    };
+    assert_eq!(
+        select_conversion_method(&no_blit_dst).unwrap(),
+        ImageConvertMethod::Compute
+    );
+
+    let uint_to_float = ConversionSupport {
+        src_format: vk::Format::R8G8B8A8_UINT,
+        ..bgra_to_rgba
+    };
+    assert!(matches!(
+        select_conversion_method(&uint_to_float),
+        Err(ImageConvertError::UnsupportedConversion { .. })
+    ));
+}
+
+#[test]
+fn convert_image_spirv_has_compute_entry_point() {
+    let spirv = ash::util::read_spv(&mut Cursor::new(CONVERT_IMAGE_SPIRV)).unwrap();
+    let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+    assert_eq!(entry_points.len(), 1);
+    assert_eq!(entry_points[0].stage, vk::ShaderStageFlags::COMPUTE);
+}
@@ -18,6 +18,9 @@ pub enum ImageDimensions {
     },
 }
 
+/// Number of array layers in a cube map.
+pub const CUBE_FACE_COUNT: u32 = 6;
+
 impl ImageDimensions {
     pub fn new_from_extent_and_layers(extent_3d: vk::Extent3D, array_layers: u32) -> Self {
         if array_layers > 1 {
@@ -67,6 +70,31 @@ impl ImageDimensions {
         }
     }
 
+    /// 2D dimensions with 6 array layers, one per cube face. Use with
+    /// [`ImageProperties::new_cube_map`](crate::ImageProperties::new_cube_map) which sets the
+    /// required `CUBE_COMPATIBLE` flag.
+    pub fn new_cube(size: u32) -> Self {
+        Self::new_2d_array(size, size, CUBE_FACE_COUNT)
+    }
+
+    /// 2D dimensions with 6 array layers for each of `cube_count` cubes.
+    pub fn new_cube_array(size: u32, cube_count: u32) -> Self {
+        Self::new_2d_array(size, size, CUBE_FACE_COUNT * cube_count)
+    }
+
+    /// Whether these dimensions can back a cube (array) image: square 2D with a multiple of 6
+    /// array layers.
+    pub fn is_cube_compatible(&self) -> bool {
+        match *self {
+            Self::Dim2d {
+                width,
+                height,
+                array_layers,
+            } => width == height && array_layers > 0 && array_layers % CUBE_FACE_COUNT == 0,
+            _ => false,
+        }
+    }
+
     pub fn new_3d(width: u32, height: u32, depth: u32) -> Self {
         Self::Dim3d {
             width,
@@ -123,6 +151,12 @@ impl ImageDimensions {
         self.width() * self.height() * self.depth() * self.array_layers()
     }
 
+    /// Number of mip levels in a full mip chain down to 1x1x1.
+    pub fn max_mip_levels(&self) -> u32 {
+        let largest_dimension = self.width().max(self.height()).max(self.depth()).max(1);
+        u32::BITS - largest_dimension.leading_zeros()
+    }
+
     pub fn image_type(&self) -> vk::ImageType {
         match *self {
             ImageDimensions::Dim1d { .. } => vk::ImageType::TYPE_1D,
@@ -155,6 +189,16 @@ impl ImageDimensions {
             max_depth: 1., // not to be confused with `self.depth()`
         }
     }
+
+    pub fn whole_rect(&self) -> vk::Rect2D {
+        vk::Rect2D {
+            extent: vk::Extent2D {
+                width: self.width(),
+                height: self.height(),
+            },
+            offset: vk::Offset2D { x: 0, y: 0 },
+        }
+    }
 }
 
 impl Default for ImageDimensions {
@@ -165,3 +209,23 @@ impl Default for ImageDimensions {
         }
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn max_mip_levels_covers_largest_dimension() {
+    assert_eq!(ImageDimensions::new_2d(1, 1).max_mip_levels(), 1);
+    assert_eq!(ImageDimensions::new_2d(256, 256).max_mip_levels(), 9);
+    assert_eq!(ImageDimensions::new_2d(300, 17).max_mip_levels(), 9);
+    assert_eq!(ImageDimensions::new_3d(4, 2, 64).max_mip_levels(), 7);
+}
+
+#[test]
+fn cube_dimensions() {
+    assert!(ImageDimensions::new_cube(128).is_cube_compatible());
+    assert_eq!(ImageDimensions::new_cube_array(128, 3).array_layers(), 18);
+    assert!(ImageDimensions::new_cube_array(128, 3).is_cube_compatible());
+    assert!(!ImageDimensions::new_2d_array(128, 64, 6).is_cube_compatible());
+    assert!(!ImageDimensions::new_2d_array(128, 128, 4).is_cube_compatible());
+    assert!(!ImageDimensions::new_3d(8, 8, 6).is_cube_compatible());
+}
@@ -0,0 +1,573 @@
+use crate::{
+    allocation_info_cpu_accessible, default_subresource_layers, is_format_srgb,
+    record_submit_and_wait, AllocationAccess, AllocationError, AllocatorAccess, Buffer,
+    BufferProperties, CommandPool, Image, ImageAccess, MemoryAllocation, MemoryError, Queue,
+    ResourceInitError,
+};
+use ash::vk;
+#[cfg(any(feature = "png", feature = "exr"))]
+use std::path::Path;
+use std::{error, fmt, ptr, sync::Arc};
+
+/// Texels of mip level 0, array layer 0 of an image read back to the host. Rows may be padded
+/// (see `row_pitch`) when read directly from a linear tiling image.
+///
+/// Supported formats are 8-bit R/RG/RGBA/BGRA (UNORM and SRGB), `A2B10G10R10_UNORM_PACK32`,
+/// `R16G16B16A16_SFLOAT`, `R32_SFLOAT`, `R32G32B32A32_SFLOAT`, `D16_UNORM` and `D32_SFLOAT`.
+#[derive(Debug, Clone)]
+pub struct ImageReadback {
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes between the start of each row. At least `width` times the texel size.
+    pub row_pitch: usize,
+    pub data: Vec<u8>,
+}
+
+impl ImageReadback {
+    /// Copies mip level 0, array layer 0 of `image` into a temporary host visible buffer allocated
+    /// with `alloc_access` and blocks until the copy has completed on `queue`. `image` must have
+    /// been created with `TRANSFER_SRC` usage and be in `current_layout`, which it's transitioned
+    /// back to afterwards. Works with swapchain images (pass the surface format as `format`) as
+    /// long as the swapchain was created with `TRANSFER_SRC` usage.
+    pub fn new_from_image(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        image: &dyn ImageAccess,
+        format: vk::Format,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+        current_layout: vk::ImageLayout,
+    ) -> Result<Self, ImageReadbackError> {
+        let texel_layout = TexelLayout::from_format(format)
+            .ok_or(ImageReadbackError::UnsupportedFormat(format))?;
+        let extent = image.dimensions().extent_3d();
+        let row_pitch = extent.width as usize * texel_layout.size();
+        let data_size = row_pitch * extent.height as usize;
+
+        let mut readback_buffer = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(
+                data_size as vk::DeviceSize,
+                vk::BufferUsageFlags::TRANSFER_DST,
+            ),
+            allocation_info_cpu_accessible(),
+        )
+        .map_err(ImageReadbackError::BufferCreation)?;
+
+        let aspect_mask = texel_layout.aspect_mask();
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let copy_region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: default_subresource_layers(aspect_mask),
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D { depth: 1, ..extent },
+        };
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            let to_transfer_src = vk::ImageMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .old_layout(current_layout)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.handle())
+                .subresource_range(subresource_range);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_src],
+            );
+
+            command_buffer.copy_image_to_buffer(
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                &readback_buffer,
+                &[copy_region],
+            );
+
+            let to_current_layout = vk::ImageMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .new_layout(current_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.handle())
+                .subresource_range(subresource_range);
+            let to_host = vk::BufferMemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(readback_buffer.handle())
+                .size(vk::WHOLE_SIZE);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS | vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[to_host],
+                &[to_current_layout],
+            );
+        })
+        .map_err(ImageReadbackError::Copy)?;
+
+        let data = read_mapped_bytes(readback_buffer.memory_allocation_mut(), 0, data_size)
+            .map_err(ImageReadbackError::Memory)?;
+
+        Ok(Self {
+            format,
+            width: extent.width,
+            height: extent.height,
+            row_pitch,
+            data,
+        })
+    }
+
+    /// Reads mip level 0, array layer 0 of a host visible `LINEAR` tiling image straight from its
+    /// memory, using the row pitch reported by `vkGetImageSubresourceLayout`. The image must not
+    /// be in use by the device and its memory must be host visible.
+    pub fn new_from_linear_image(image: &mut Image) -> Result<Self, ImageReadbackError> {
+        let properties = image.properties();
+        if properties.tiling != vk::ImageTiling::LINEAR {
+            return Err(ImageReadbackError::NotLinearTiling(properties.tiling));
+        }
+        let format = properties.format;
+        let texel_layout = TexelLayout::from_format(format)
+            .ok_or(ImageReadbackError::UnsupportedFormat(format))?;
+        let width = properties.dimensions.width();
+        let height = properties.dimensions.height();
+
+        let layout = image.subresource_layout(texel_layout.aspect_mask(), 0, 0);
+        let row_pitch = layout.row_pitch as usize;
+        let data_size = row_pitch * (height as usize - 1) + width as usize * texel_layout.size();
+
+        let data = read_mapped_bytes(
+            image.memory_allocation_mut(),
+            layout.offset as usize,
+            data_size,
+        )
+        .map_err(ImageReadbackError::Memory)?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            row_pitch,
+            data,
+        })
+    }
+
+    /// Texel bytes of row `y` excluding any row padding.
+    pub fn row(&self, y: u32) -> &[u8] {
+        let texel_size = TexelLayout::from_format(self.format).map_or(0, |layout| layout.size());
+        let start = y as usize * self.row_pitch;
+        &self.data[start..start + self.width as usize * texel_size]
+    }
+
+    /// Linear RGBA values of each texel, rows top to bottom. SRGB formats are decoded; UNORM
+    /// formats are assumed to hold linear values as Vulkan treats them. Single channel and depth
+    /// formats are replicated to RGB with alpha 1.
+    pub fn to_rgba_f32(&self) -> Result<Vec<[f32; 4]>, ImageReadbackError> {
+        let texel_layout = TexelLayout::from_format(self.format)
+            .ok_or(ImageReadbackError::UnsupportedFormat(self.format))?;
+        let srgb = is_format_srgb(self.format);
+
+        let mut texels = Vec::with_capacity(self.width as usize * self.height as usize);
+        for y in 0..self.height {
+            let row = self.row(y);
+            for texel_bytes in row.chunks_exact(texel_layout.size()) {
+                let mut texel = texel_layout.decode(texel_bytes);
+                if srgb {
+                    for channel in &mut texel[..3] {
+                        *channel = srgb_to_linear(*channel);
+                    }
+                }
+                texels.push(texel);
+            }
+        }
+        Ok(texels)
+    }
+
+    /// Tightly packed 8-bit RGBA texels with sRGB encoded color (alpha stays linear), as expected
+    /// by png files. SRGB formats are passed through without a round trip through float.
+    pub fn to_rgba8_srgb(&self) -> Result<Vec<u8>, ImageReadbackError> {
+        match self.format {
+            vk::Format::R8G8B8A8_SRGB => Ok(self.packed_rgba8(false)),
+            vk::Format::B8G8R8A8_SRGB => Ok(self.packed_rgba8(true)),
+            _ => Ok(self
+                .to_rgba_f32()?
+                .into_iter()
+                .flat_map(|[r, g, b, a]| {
+                    [
+                        unorm_to_u8(linear_to_srgb(r)),
+                        unorm_to_u8(linear_to_srgb(g)),
+                        unorm_to_u8(linear_to_srgb(b)),
+                        unorm_to_u8(a),
+                    ]
+                })
+                .collect()),
+        }
+    }
+
+    /// Writes an 8-bit sRGB png. See [`Self::to_rgba8_srgb`].
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), ImageReadbackError> {
+        let rgba8 = self.to_rgba8_srgb()?;
+
+        let file = std::fs::File::create(path).map_err(ImageReadbackError::FileCreation)?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder
+            .write_header()
+            .map_err(ImageReadbackError::PngEncoding)?;
+        writer
+            .write_image_data(&rgba8)
+            .map_err(ImageReadbackError::PngEncoding)
+    }
+
+    /// Writes a 32-bit float linear RGBA exr. See [`Self::to_rgba_f32`].
+    #[cfg(feature = "exr")]
+    pub fn write_exr(&self, path: impl AsRef<Path>) -> Result<(), ImageReadbackError> {
+        let texels = self.to_rgba_f32()?;
+        let width = self.width as usize;
+        exr::prelude::write_rgba_file(path, width, self.height as usize, |x, y| {
+            let [r, g, b, a] = texels[y * width + x];
+            (r, g, b, a)
+        })
+        .map_err(ImageReadbackError::ExrEncoding)
+    }
+
+    fn packed_rgba8(&self, swap_red_blue: bool) -> Vec<u8> {
+        let mut rgba8 = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for y in 0..self.height {
+            for texel in self.row(y).chunks_exact(4) {
+                if swap_red_blue {
+                    rgba8.extend_from_slice(&[texel[2], texel[1], texel[0], texel[3]]);
+                } else {
+                    rgba8.extend_from_slice(texel);
+                }
+            }
+        }
+        rgba8
+    }
+}
+
+/// Reads back mip level 0, array layer 0 of `image` (see [`ImageReadback::new_from_image`]) and
+/// writes it to an 8-bit sRGB png at `path`. Handy for bug reports and ci artifacts.
+#[cfg(feature = "png")]
+pub fn dump_image_to_png(
+    alloc_access: Arc<dyn AllocatorAccess>,
+    image: &dyn ImageAccess,
+    format: vk::Format,
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+    current_layout: vk::ImageLayout,
+    path: impl AsRef<Path>,
+) -> Result<(), ImageReadbackError> {
+    ImageReadback::new_from_image(
+        alloc_access,
+        image,
+        format,
+        command_pool,
+        queue,
+        current_layout,
+    )?
+    .write_png(path)
+}
+
+/// Reads back mip level 0, array layer 0 of `image` (see [`ImageReadback::new_from_image`]) and
+/// writes it to a linear 32-bit float exr at `path`. Use this over png for hdr render targets.
+#[cfg(feature = "exr")]
+pub fn dump_image_to_exr(
+    alloc_access: Arc<dyn AllocatorAccess>,
+    image: &dyn ImageAccess,
+    format: vk::Format,
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+    current_layout: vk::ImageLayout,
+    path: impl AsRef<Path>,
+) -> Result<(), ImageReadbackError> {
+    ImageReadback::new_from_image(
+        alloc_access,
+        image,
+        format,
+        command_pool,
+        queue,
+        current_layout,
+    )?
+    .write_exr(path)
+}
+
+// Helper Functions
+
+/// Texel encodings supported by [`ImageReadback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TexelLayout {
+    R8,
+    Rg8,
+    Rgba8,
+    Bgra8,
+    A2b10g10r10,
+    Rgba16Float,
+    R32Float,
+    Rgba32Float,
+    D16,
+    D32Float,
+}
+
+impl TexelLayout {
+    fn from_format(format: vk::Format) -> Option<Self> {
+        let texel_layout = match format {
+            vk::Format::R8_UNORM | vk::Format::R8_SRGB => Self::R8,
+            vk::Format::R8G8_UNORM | vk::Format::R8G8_SRGB => Self::Rg8,
+            vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => Self::Rgba8,
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => Self::Bgra8,
+            vk::Format::A2B10G10R10_UNORM_PACK32 => Self::A2b10g10r10,
+            vk::Format::R16G16B16A16_SFLOAT => Self::Rgba16Float,
+            vk::Format::R32_SFLOAT => Self::R32Float,
+            vk::Format::R32G32B32A32_SFLOAT => Self::Rgba32Float,
+            vk::Format::D16_UNORM => Self::D16,
+            vk::Format::D32_SFLOAT => Self::D32Float,
+            _ => return None,
+        };
+        Some(texel_layout)
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::R8 => 1,
+            Self::Rg8 | Self::D16 => 2,
+            Self::Rgba8 | Self::Bgra8 | Self::A2b10g10r10 | Self::R32Float | Self::D32Float => 4,
+            Self::Rgba16Float => 8,
+            Self::Rgba32Float => 16,
+        }
+    }
+
+    fn aspect_mask(self) -> vk::ImageAspectFlags {
+        match self {
+            Self::D16 | Self::D32Float => vk::ImageAspectFlags::DEPTH,
+            _ => vk::ImageAspectFlags::COLOR,
+        }
+    }
+
+    /// RGBA values without any sRGB decoding.
+    fn decode(self, bytes: &[u8]) -> [f32; 4] {
+        let unorm8 = |byte: u8| byte as f32 / 255.;
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let f32_at =
+            |i: usize| f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+
+        match self {
+            Self::R8 => {
+                let r = unorm8(bytes[0]);
+                [r, r, r, 1.]
+            }
+            Self::Rg8 => [unorm8(bytes[0]), unorm8(bytes[1]), 0., 1.],
+            Self::Rgba8 => [
+                unorm8(bytes[0]),
+                unorm8(bytes[1]),
+                unorm8(bytes[2]),
+                unorm8(bytes[3]),
+            ],
+            Self::Bgra8 => [
+                unorm8(bytes[2]),
+                unorm8(bytes[1]),
+                unorm8(bytes[0]),
+                unorm8(bytes[3]),
+            ],
+            Self::A2b10g10r10 => {
+                let packed = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                let unorm10 = |shift: u32| ((packed >> shift) & 0x3ff) as f32 / 1023.;
+                [
+                    unorm10(0),
+                    unorm10(10),
+                    unorm10(20),
+                    (packed >> 30) as f32 / 3.,
+                ]
+            }
+            Self::Rgba16Float => [
+                f16_to_f32(u16_at(0)),
+                f16_to_f32(u16_at(2)),
+                f16_to_f32(u16_at(4)),
+                f16_to_f32(u16_at(6)),
+            ],
+            Self::R32Float | Self::D32Float => {
+                let r = f32_at(0);
+                [r, r, r, 1.]
+            }
+            Self::Rgba32Float => [f32_at(0), f32_at(4), f32_at(8), f32_at(12)],
+            Self::D16 => {
+                let depth = u16_at(0) as f32 / u16::MAX as f32;
+                [depth, depth, depth, 1.]
+            }
+        }
+    }
+}
+
+pub(crate) fn read_mapped_bytes(
+    memory_allocation: &mut MemoryAllocation,
+    allocation_offset: usize,
+    data_size: usize,
+) -> Result<Vec<u8>, MemoryError> {
+    memory_allocation.invalidate_allocation(allocation_offset, data_size)?;
+    let mapped_memory = unsafe { memory_allocation.map_memory() }?;
+    let mut data = vec![0u8; data_size];
+    unsafe {
+        ptr::copy_nonoverlapping(
+            mapped_memory.add(allocation_offset),
+            data.as_mut_ptr(),
+            data_size,
+        );
+        memory_allocation.unmap_memory();
+    }
+    Ok(data)
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0. => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1. + mantissa / 1024.) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn unorm_to_u8(value: f32) -> u8 {
+    (value.clamp(0., 1.) * 255. + 0.5) as u8
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum ImageReadbackError {
+    UnsupportedFormat(vk::Format),
+    NotLinearTiling(vk::ImageTiling),
+    BufferCreation(AllocationError),
+    Copy(ResourceInitError),
+    Memory(MemoryError),
+    #[cfg(any(feature = "png", feature = "exr"))]
+    FileCreation(std::io::Error),
+    #[cfg(feature = "png")]
+    PngEncoding(png::EncodingError),
+    #[cfg(feature = "exr")]
+    ExrEncoding(exr::error::Error),
+}
+
+impl fmt::Display for ImageReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => {
+                write!(f, "image readback doesn't support format {:?}", format)
+            }
+            Self::NotLinearTiling(tiling) => write!(
+                f,
+                "direct readback requires LINEAR tiling but the image has {:?} tiling",
+                tiling
+            ),
+            Self::BufferCreation(e) => write!(f, "failed to create readback buffer: {}", e),
+            Self::Copy(e) => write!(f, "failed to copy image to readback buffer: {}", e),
+            Self::Memory(e) => write!(f, "failed to read readback memory: {}", e),
+            #[cfg(any(feature = "png", feature = "exr"))]
+            Self::FileCreation(e) => write!(f, "failed to create image dump file: {}", e),
+            #[cfg(feature = "png")]
+            Self::PngEncoding(e) => write!(f, "failed to encode png: {}", e),
+            #[cfg(feature = "exr")]
+            Self::ExrEncoding(e) => write!(f, "failed to encode exr: {}", e),
+        }
+    }
+}
+
+impl error::Error for ImageReadbackError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::UnsupportedFormat(_) => None,
+            Self::NotLinearTiling(_) => None,
+            Self::BufferCreation(e) => Some(e),
+            Self::Copy(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            #[cfg(any(feature = "png", feature = "exr"))]
+            Self::FileCreation(e) => Some(e),
+            #[cfg(feature = "png")]
+            Self::PngEncoding(e) => Some(e),
+            #[cfg(feature = "exr")]
+            Self::ExrEncoding(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn image_readback_conversion() {
+    // 2x1 BGRA8 image with 4 bytes of row padding
+    let readback = ImageReadback {
+        format: vk::Format::B8G8R8A8_SRGB,
+        width: 2,
+        height: 1,
+        row_pitch: 12,
+        data: vec![10, 20, 30, 255, 0, 0, 255, 128, 0, 0, 0, 0],
+    };
+    assert_eq!(readback.row(0).len(), 8);
+    assert_eq!(
+        readback.to_rgba8_srgb().unwrap(),
+        vec![30, 20, 10, 255, 255, 0, 0, 128]
+    );
+
+    let half_float = ImageReadback {
+        format: vk::Format::R16G16B16A16_SFLOAT,
+        width: 1,
+        height: 1,
+        row_pitch: 8,
+        // 0.5, 1.0, 2.0, 1.0
+        data: [0x3800u16, 0x3c00, 0x4000, 0x3c00]
+            .iter()
+            .flat_map(|half| half.to_le_bytes())
+            .collect(),
+    };
+    assert_eq!(half_float.to_rgba_f32().unwrap(), vec![[0.5, 1., 2., 1.]]);
+    // linear 0.5 encodes to ~188 in srgb and 2.0 clamps to 255
+    assert_eq!(
+        half_float.to_rgba8_srgb().unwrap(),
+        vec![188, 255, 255, 255]
+    );
+
+    let unsupported = ImageReadback {
+        format: vk::Format::BC1_RGB_UNORM_BLOCK,
+        ..half_float
+    };
+    assert!(unsupported.to_rgba_f32().is_err());
+}
@@ -0,0 +1,189 @@
+use ash::vk;
+
+/// One mip level of an image covering all array layers. See
+/// [`ImageProperties::mip_levels_iter`](crate::ImageProperties::mip_levels_iter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageMipLevel {
+    pub mip_level: u32,
+    pub extent: vk::Extent3D,
+    pub aspect_mask: vk::ImageAspectFlags,
+    pub array_layer_count: u32,
+}
+
+impl ImageMipLevel {
+    /// All array layers of this mip level e.g. for a copy region.
+    pub fn subresource_layers(&self) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: self.aspect_mask,
+            mip_level: self.mip_level,
+            base_array_layer: 0,
+            layer_count: self.array_layer_count,
+        }
+    }
+
+    /// All array layers of this mip level e.g. for a layout transition barrier.
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: self.aspect_mask,
+            base_mip_level: self.mip_level,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: self.array_layer_count,
+        }
+    }
+
+    /// `[min, max]` corner offsets covering the whole level, as used by `vk::ImageBlit`.
+    pub fn blit_offsets(&self) -> [vk::Offset3D; 2] {
+        blit_offsets(self.extent)
+    }
+
+    /// Blit region downsampling the previous level into this one when generating a mip chain.
+    /// `None` for mip level 0.
+    pub fn blit_from_previous(&self, previous: &ImageMipLevel) -> Option<vk::ImageBlit> {
+        if self.mip_level == 0 {
+            return None;
+        }
+        Some(vk::ImageBlit {
+            src_subresource: previous.subresource_layers(),
+            src_offsets: previous.blit_offsets(),
+            dst_subresource: self.subresource_layers(),
+            dst_offsets: self.blit_offsets(),
+        })
+    }
+}
+
+/// A single mip level and array layer (e.g. one cube face) of an image. See
+/// [`ImageProperties::layers_iter`](crate::ImageProperties::layers_iter) and
+/// [`ImageProperties::subresources_iter`](crate::ImageProperties::subresources_iter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSubresource {
+    pub mip_level: u32,
+    pub array_layer: u32,
+    pub extent: vk::Extent3D,
+    /// All aspects of the format (e.g. `DEPTH | STENCIL`) as used by barriers. Buffer/image
+    /// copies need a single aspect, see [`Self::buffer_image_copy`].
+    pub aspect_mask: vk::ImageAspectFlags,
+}
+
+impl ImageSubresource {
+    pub fn subresource_layers(&self) -> vk::ImageSubresourceLayers {
+        vk::ImageSubresourceLayers {
+            aspect_mask: self.aspect_mask,
+            mip_level: self.mip_level,
+            base_array_layer: self.array_layer,
+            layer_count: 1,
+        }
+    }
+
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: self.aspect_mask,
+            base_mip_level: self.mip_level,
+            level_count: 1,
+            base_array_layer: self.array_layer,
+            layer_count: 1,
+        }
+    }
+
+    /// `[min, max]` corner offsets covering the whole subresource, as used by `vk::ImageBlit`.
+    pub fn blit_offsets(&self) -> [vk::Offset3D; 2] {
+        blit_offsets(self.extent)
+    }
+
+    /// Copy region for uploading `aspect_mask` of this whole subresource from tightly packed
+    /// buffer data at `buffer_offset`. Buffer/image copies must name exactly one aspect so
+    /// combined depth/stencil formats need a copy per aspect. Returns `None` if `aspect_mask`
+    /// isn't a single aspect of this subresource.
+    pub fn buffer_image_copy(
+        &self,
+        aspect_mask: vk::ImageAspectFlags,
+        buffer_offset: vk::DeviceSize,
+    ) -> Option<vk::BufferImageCopy> {
+        if aspect_mask.as_raw().count_ones() != 1 || !self.aspect_mask.contains(aspect_mask) {
+            return None;
+        }
+        Some(vk::BufferImageCopy {
+            buffer_offset,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask,
+                ..self.subresource_layers()
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: self.extent,
+        })
+    }
+}
+
+// Helper Functions
+
+fn blit_offsets(extent: vk::Extent3D) -> [vk::Offset3D; 2] {
+    [
+        vk::Offset3D::default(),
+        vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: extent.depth as i32,
+        },
+    ]
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn image_subresource_iteration() {
+    let cube_map = crate::ImageProperties {
+        mip_levels: 3,
+        ..crate::ImageProperties::new_cube_map(
+            vk::Format::R8G8B8A8_UNORM,
+            64,
+            vk::ImageUsageFlags::SAMPLED,
+        )
+    };
+
+    let mip_levels: Vec<ImageMipLevel> = cube_map.mip_levels_iter().collect();
+    assert_eq!(mip_levels.len(), 3);
+    assert_eq!(mip_levels[2].extent.width, 16);
+    assert_eq!(mip_levels[2].subresource_range().layer_count, 6);
+    assert!(mip_levels[0].blit_from_previous(&mip_levels[0]).is_none());
+    let blit = mip_levels[1].blit_from_previous(&mip_levels[0]).unwrap();
+    assert_eq!(blit.src_offsets[1].x, 64);
+    assert_eq!(blit.dst_offsets[1].x, 32);
+    assert_eq!(blit.dst_offsets[1].z, 1);
+
+    let faces: Vec<ImageSubresource> = cube_map.layers_iter(1).collect();
+    assert_eq!(faces.len(), 6);
+    assert_eq!(faces[5].subresource_layers().base_array_layer, 5);
+    assert_eq!(faces[5].extent.height, 32);
+
+    let copy = faces[5]
+        .buffer_image_copy(vk::ImageAspectFlags::COLOR, 256)
+        .unwrap();
+    assert_eq!(copy.image_subresource.base_array_layer, 5);
+    assert_eq!(copy.buffer_offset, 256);
+
+    let depth_stencil = crate::ImageProperties::new_default(
+        vk::Format::D24_UNORM_S8_UINT,
+        crate::ImageDimensions::new_2d(64, 64),
+        vk::ImageUsageFlags::TRANSFER_DST,
+    );
+    let layer = depth_stencil.layers_iter(0).next().unwrap();
+    let both_aspects = vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+    assert_eq!(layer.aspect_mask, both_aspects);
+    assert!(layer.buffer_image_copy(both_aspects, 0).is_none());
+    let stencil_copy = layer
+        .buffer_image_copy(vk::ImageAspectFlags::STENCIL, 0)
+        .unwrap();
+    assert_eq!(
+        stencil_copy.image_subresource.aspect_mask,
+        vk::ImageAspectFlags::STENCIL
+    );
+
+    let subresources: Vec<ImageSubresource> = cube_map.subresources_iter().collect();
+    assert_eq!(subresources.len(), 18);
+    assert_eq!(
+        (subresources[7].mip_level, subresources[7].array_layer),
+        (1, 1)
+    );
+}
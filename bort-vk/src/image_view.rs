@@ -1,14 +1,28 @@
-use crate::{Device, DeviceOwned, ImageAccess, ImageProperties, ALLOCATION_CALLBACK_NONE};
+use crate::{
+    CompressedBlockInfo, Device, DeviceOwned, ImageAccess, ImageProperties,
+    ALLOCATION_CALLBACK_NONE, CUBE_FACE_COUNT,
+};
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 /// Unifies image views with different types of images
 pub trait ImageViewAccess: DeviceOwned + Send + Sync {
     fn handle(&self) -> vk::ImageView;
     fn image_access(&self) -> Arc<dyn ImageAccess>;
+    fn properties(&self) -> &ImageViewProperties;
+
+    /// Viewport covering the whole image. Handy for dynamic viewport state.
+    fn whole_viewport(&self) -> vk::Viewport {
+        self.image_access().dimensions().whole_viewport()
+    }
+
+    /// Rect covering the whole image. Handy for dynamic scissor state or render areas.
+    fn whole_rect(&self) -> vk::Rect2D {
+        self.image_access().dimensions().whole_rect()
+    }
 }
 
 pub struct ImageView<I: ImageAccess + 'static> {
@@ -20,7 +34,14 @@ pub struct ImageView<I: ImageAccess + 'static> {
 }
 
 impl<I: ImageAccess + 'static> ImageView<I> {
-    pub fn new(image: Arc<I>, properties: ImageViewProperties) -> VkResult<Self> {
+    /// Returns [`ImageViewError::UnsupportedFormat`] if the image can't be viewed with
+    /// `properties.format` (see [`ImageAccess::supports_view_format`]).
+    pub fn new(image: Arc<I>, properties: ImageViewProperties) -> Result<Self, ImageViewError> {
+        if !image.supports_view_format(properties.format) {
+            return Err(ImageViewError::UnsupportedFormat {
+                format: properties.format,
+            });
+        }
         let create_info = properties.create_info(image.handle());
 
         let handle = unsafe {
@@ -28,7 +49,8 @@ impl<I: ImageAccess + 'static> ImageView<I> {
                 .device()
                 .inner()
                 .create_image_view(&create_info, ALLOCATION_CALLBACK_NONE)
-        }?;
+        }
+        .map_err(ImageViewError::Creation)?;
 
         Ok(Self {
             handle,
@@ -78,6 +100,10 @@ impl<I: ImageAccess + 'static> ImageViewAccess for ImageView<I> {
     fn image_access(&self) -> Arc<dyn ImageAccess> {
         self.image.clone()
     }
+
+    fn properties(&self) -> &ImageViewProperties {
+        &self.properties
+    }
 }
 
 impl<I: ImageAccess + 'static> DeviceOwned for ImageView<I> {
@@ -140,11 +166,146 @@ impl ImageViewProperties {
         }
     }
 
+    /// `CUBE` view of a cube map or `CUBE_ARRAY` view of a cube map array (requires the
+    /// `imageCubeArray` feature) covering all mip levels and layers. `image_properties` should
+    /// come from [`ImageProperties::new_cube_map`] or
+    /// [`ImageProperties::new_cube_map_array`].
+    pub fn new_cube(image_properties: &ImageProperties) -> Self {
+        debug_assert!(
+            image_properties.is_cube_compatible(),
+            "cube view of an image that isn't cube compatible"
+        );
+        let subresource_range = image_properties.subresource_range();
+        let view_type = if subresource_range.layer_count == CUBE_FACE_COUNT {
+            vk::ImageViewType::CUBE
+        } else {
+            vk::ImageViewType::CUBE_ARRAY
+        };
+
+        Self {
+            format: image_properties.format,
+            subresource_range,
+            view_type,
+            ..Self::default()
+        }
+    }
+
+    /// 2D (`slice_count == 1`) or 2D array view of depth slices `first_slice..first_slice +
+    /// slice_count` of `mip_level` of a 3D image created with `TYPE_2D_ARRAY_COMPATIBLE` (see
+    /// [`ImageProperties::new_3d_slice_viewable`]). The slices are selected with the array layer
+    /// range as the spec requires. Only single slice views of images created with
+    /// `TYPE_2D_VIEW_COMPATIBLE_EXT` (see [`ImageProperties::with_2d_view_of_3d`]) can be used in
+    /// sampled or storage image descriptors; other views are limited to framebuffer attachments.
+    pub fn new_3d_slices(
+        image_properties: &ImageProperties,
+        mip_level: u32,
+        first_slice: u32,
+        slice_count: u32,
+    ) -> Self {
+        debug_assert!(
+            image_properties
+                .flags
+                .contains(vk::ImageCreateFlags::TYPE_2D_ARRAY_COMPATIBLE)
+                && image_properties.dimensions.image_type() == vk::ImageType::TYPE_3D,
+            "2D views of 3D images require a 3D image created with TYPE_2D_ARRAY_COMPATIBLE"
+        );
+        let view_type = if slice_count == 1 {
+            vk::ImageViewType::TYPE_2D
+        } else {
+            vk::ImageViewType::TYPE_2D_ARRAY
+        };
+        let subresource_range = vk::ImageSubresourceRange {
+            base_mip_level: mip_level,
+            level_count: 1,
+            base_array_layer: first_slice,
+            layer_count: slice_count,
+            ..image_properties.subresource_range()
+        };
+
+        Self {
+            format: image_properties.format,
+            subresource_range,
+            view_type,
+            ..Self::default()
+        }
+    }
+
+    /// Uncompressed view of `mip_level` (all array layers) of a compressed image created with
+    /// [`ImageProperties::new_block_texel_view_compatible`] where each texel is one block with
+    /// [`CompressedBlockInfo::block_texel_view_format`]. Its size in texels is given by
+    /// [`block_texel_view_extent`](crate::block_texel_view_extent). Block texel views must cover
+    /// exactly one mip level. Returns `None` if the image format isn't compressed.
+    pub fn new_block_texel_view(
+        image_properties: &ImageProperties,
+        mip_level: u32,
+    ) -> Option<Self> {
+        debug_assert!(
+            image_properties
+                .flags
+                .contains(vk::ImageCreateFlags::BLOCK_TEXEL_VIEW_COMPATIBLE),
+            "block texel views require an image created with BLOCK_TEXEL_VIEW_COMPATIBLE"
+        );
+        let block_info = CompressedBlockInfo::from_format(image_properties.format)?;
+        let subresource_range = vk::ImageSubresourceRange {
+            base_mip_level: mip_level,
+            level_count: 1,
+            ..image_properties.subresource_range()
+        };
+
+        Some(Self {
+            format: block_info.block_texel_view_format(),
+            subresource_range,
+            view_type: image_properties.dimensions.default_image_view_type(),
+            ..Self::default()
+        })
+    }
+
+    /// View of only the depth aspect of a depth or depth/stencil image e.g. for sampling depth.
+    /// Sampled views can't include both depth and stencil.
+    pub fn new_depth_aspect(image_properties: &ImageProperties) -> Self {
+        Self::new_single_aspect(image_properties, vk::ImageAspectFlags::DEPTH)
+    }
+
+    /// View of only the stencil aspect of a stencil or depth/stencil image e.g. for reading
+    /// stencil values in a shader (with a `UINT` sampled type).
+    pub fn new_stencil_aspect(image_properties: &ImageProperties) -> Self {
+        Self::new_single_aspect(image_properties, vk::ImageAspectFlags::STENCIL)
+    }
+
+    /// View of a `VK_EXT_fragment_density_map` attachment. If `dynamic` the densities are read
+    /// during rendering instead of at the start of the render pass, allowing updates up until the
+    /// commands are executed. This requires the `fragmentDensityMapDynamic` feature.
+    pub fn new_fragment_density_map(image_properties: &ImageProperties, dynamic: bool) -> Self {
+        let mut view_properties = Self::from_image_properties_default(image_properties);
+        if dynamic {
+            view_properties.flags |= vk::ImageViewCreateFlags::FRAGMENT_DENSITY_MAP_DYNAMIC_EXT;
+        }
+        view_properties
+    }
+
+    fn new_single_aspect(
+        image_properties: &ImageProperties,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Self {
+        let mut view_properties = Self::from_image_properties_default(image_properties);
+        debug_assert!(
+            view_properties
+                .subresource_range
+                .aspect_mask
+                .contains(aspect_mask),
+            "image format {:?} doesn't have the {:?} aspect",
+            image_properties.format,
+            aspect_mask
+        );
+        view_properties.subresource_range.aspect_mask = aspect_mask;
+        view_properties
+    }
+
     pub fn write_create_info<'a>(
         &'a self,
         create_info: vk::ImageViewCreateInfo<'a>,
         image_handle: vk::Image,
-    ) -> vk::ImageViewCreateInfo {
+    ) -> vk::ImageViewCreateInfo<'a> {
         create_info
             .flags(self.flags)
             .image(image_handle)
@@ -154,7 +315,7 @@ impl ImageViewProperties {
             .subresource_range(self.subresource_range)
     }
 
-    pub fn create_info(&self, image_handle: vk::Image) -> vk::ImageViewCreateInfo {
+    pub fn create_info(&self, image_handle: vk::Image) -> vk::ImageViewCreateInfo<'_> {
         self.write_create_info(vk::ImageViewCreateInfo::default(), image_handle)
     }
 
@@ -169,6 +330,12 @@ impl ImageViewProperties {
     }
 }
 
+impl From<&vk::ImageViewCreateInfo<'_>> for ImageViewProperties {
+    fn from(value: &vk::ImageViewCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 // Helper Functions
 
 pub fn default_component_mapping() -> vk::ComponentMapping {
@@ -198,3 +365,46 @@ pub fn default_subresource_layers(aspect_mask: vk::ImageAspectFlags) -> vk::Imag
         layer_count: 1,
     }
 }
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImageViewError {
+    /// The view format is neither the image format nor one the image allows reinterpreting as
+    /// (see [`ImageProperties::view_formats`]).
+    UnsupportedFormat {
+        format: vk::Format,
+    },
+    Creation(vk::Result),
+}
+
+impl From<ImageViewError> for vk::Result {
+    fn from(error: ImageViewError) -> Self {
+        match error {
+            ImageViewError::UnsupportedFormat { .. } => vk::Result::ERROR_FORMAT_NOT_SUPPORTED,
+            ImageViewError::Creation(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for ImageViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat { format } => write!(
+                f,
+                "view format {:?} isn't supported by the image (see `ImageProperties::view_formats`)",
+                format
+            ),
+            Self::Creation(e) => write!(f, "image view creation failed: {}", e),
+        }
+    }
+}
+
+impl error::Error for ImageViewError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::UnsupportedFormat { .. } => None,
+            Self::Creation(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use crate::{
+    allocation_info_cpu_accessible, default_subresource_layers, image_copy_data_size,
+    read_mapped_bytes, single_copy_aspect, transfer_write_memory_barrier, AllocationAccess,
+    AllocationError, AllocatorAccess, Buffer, BufferProperties, CommandBuffer, CommandPools,
+    DeviceOwned, Fence, Image, ImageAccess, ImageReadback, ImageReadbackError, MemoryError, Queue,
+};
+use ash::vk;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error, fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
+
+/// Smallest staging buffer allocated by [`ImmediateContext`].
+const MIN_STAGING_BUFFER_SIZE: vk::DeviceSize = 64 * 1024;
+
+/// Blocking helpers for tools which don't have a frame loop e.g. asset bakers, thumbnail
+/// generators and CLI utilities. Every call records a command buffer, submits it to the queue and
+/// waits for it to complete before returning, trading throughput for simplicity.
+///
+/// The fence and a host visible staging buffer (grown to fit the largest transfer so far) are
+/// owned by the context and guarded by a mutex, so an `ImmediateContext` can be shared between
+/// threads. Calls from multiple threads are serialized. Each calling thread records with its own
+/// command pool from [`CommandPools`].
+pub struct ImmediateContext {
+    queue: Arc<Queue>,
+    command_pools: CommandPools,
+    alloc_access: Arc<dyn AllocatorAccess>,
+    state: Mutex<ImmediateState>,
+}
+
+struct ImmediateState {
+    /// Allocated from the pool of each calling thread.
+    command_buffers: HashMap<ThreadId, CommandBuffer>,
+    fence: Fence,
+    /// `TRANSFER_SRC | TRANSFER_DST` host visible buffer reused for uploads and readbacks.
+    staging_buffer: Option<Buffer>,
+}
+
+impl ImmediateContext {
+    /// Staging buffers are allocated from `alloc_access`. Any queue family supporting transfer
+    /// operations works but [`Self::with_commands`] can only record commands supported by the
+    /// queue's family.
+    pub fn new(
+        queue: Arc<Queue>,
+        alloc_access: Arc<dyn AllocatorAccess>,
+    ) -> Result<Self, ImmediateContextError> {
+        let device = queue.device().clone();
+
+        let command_pools =
+            CommandPools::new(device.clone(), vk::CommandPoolCreateFlags::TRANSIENT);
+        let command_buffer = command_pools
+            .for_queue(&queue)
+            .and_then(|command_pool| {
+                command_pool.allocate_command_buffer(vk::CommandBufferLevel::PRIMARY)
+            })
+            .map_err(ImmediateContextError::Vulkan)?;
+        let fence = Fence::new_unsignalled(device).map_err(ImmediateContextError::Vulkan)?;
+
+        Ok(Self {
+            queue,
+            command_pools,
+            alloc_access,
+            state: Mutex::new(ImmediateState {
+                command_buffers: HashMap::from([(thread::current().id(), command_buffer)]),
+                fence,
+                staging_buffer: None,
+            }),
+        })
+    }
+
+    /// Records commands with `record_commands`, submits them and blocks until they have
+    /// completed. Returns whatever `record_commands` returns.
+    pub fn with_commands<R>(
+        &self,
+        record_commands: impl FnOnce(&CommandBuffer) -> R,
+    ) -> Result<R, ImmediateContextError> {
+        let mut state = self.lock_state();
+        let ImmediateState {
+            command_buffers,
+            fence,
+            ..
+        } = &mut *state;
+        self.submit_and_wait(command_buffers, fence, record_commands)
+    }
+
+    /// Copies `data` to `buffer` starting at `dst_offset` via the staging buffer. `buffer` must
+    /// have `TRANSFER_DST` usage. The written range is made visible to all subsequent commands.
+    pub fn upload_buffer(
+        &self,
+        buffer: &Buffer,
+        dst_offset: vk::DeviceSize,
+        data: &[u8],
+    ) -> Result<(), ImmediateContextError> {
+        let size = data.len() as vk::DeviceSize;
+        check_buffer_range(dst_offset, size, buffer.properties().size)?;
+        if size == 0 {
+            return Ok(());
+        }
+
+        let mut state = self.lock_state();
+        let ImmediateState {
+            command_buffers,
+            fence,
+            staging_buffer,
+        } = &mut *state;
+        let staging_buffer = self.staging_buffer_with_capacity(staging_buffer, size)?;
+        staging_buffer
+            .memory_allocation_mut()
+            .write_bytes(data, 0)
+            .map_err(ImmediateContextError::StagingMemory)?;
+
+        let copy_region = vk::BufferCopy {
+            src_offset: 0,
+            dst_offset,
+            size,
+        };
+        self.submit_and_wait(command_buffers, fence, |command_buffer| {
+            command_buffer.copy_buffer(staging_buffer, buffer, &[copy_region]);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[transfer_write_memory_barrier()],
+                &[],
+                &[],
+            );
+        })
+    }
+
+    /// Uploads tightly packed `data` to mip level 0 of every array layer of `image` via the
+    /// staging buffer. The image is transitioned from `old_layout` to `TRANSFER_DST_OPTIMAL` and
+    /// then to `final_layout`. `image` must have `TRANSFER_DST` usage.
+    ///
+    /// `data.len()` must equal [`image_copy_data_size`] of the level 0 extent and array layers.
+    /// Combined depth/stencil formats aren't supported as each aspect needs its own copy.
+    pub fn upload_texture(
+        &self,
+        image: &Image,
+        data: &[u8],
+        old_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Result<(), ImmediateContextError> {
+        let image_properties = image.properties();
+        let aspect_mask = single_copy_aspect(image_properties.format).ok_or(
+            ImmediateContextError::CombinedDepthStencilFormat(image_properties.format),
+        )?;
+        let expected_size = image_copy_data_size(
+            image_properties.format,
+            image_properties.dimensions.extent_3d(),
+            image_properties.dimensions.array_layers(),
+        )
+        .ok_or(ImmediateContextError::UnknownTexelSize(
+            image_properties.format,
+        ))?;
+        if data.len() as vk::DeviceSize != expected_size {
+            return Err(ImmediateContextError::DataSizeMismatch {
+                data_size: data.len(),
+                expected_size,
+            });
+        }
+
+        let mut state = self.lock_state();
+        let ImmediateState {
+            command_buffers,
+            fence,
+            staging_buffer,
+        } = &mut *state;
+        let staging_buffer =
+            self.staging_buffer_with_capacity(staging_buffer, data.len() as vk::DeviceSize)?;
+        staging_buffer
+            .memory_allocation_mut()
+            .write_bytes(data, 0)
+            .map_err(ImmediateContextError::StagingMemory)?;
+
+        let copy_region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                layer_count: image_properties.dimensions.array_layers(),
+                ..default_subresource_layers(aspect_mask)
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: image_properties.dimensions.extent_3d(),
+        };
+        let subresource_range = image_properties.subresource_range();
+
+        let to_transfer_dst = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .old_layout(old_layout)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image.handle())
+            .subresource_range(subresource_range);
+        let from_transfer_dst = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(final_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image.handle())
+            .subresource_range(subresource_range);
+
+        self.submit_and_wait(command_buffers, fence, |command_buffer| {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_dst],
+            );
+            command_buffer.copy_buffer_to_image(
+                staging_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[from_transfer_dst],
+            );
+        })
+    }
+
+    /// Copies `size` bytes of `buffer` starting at `offset` to the host via the staging buffer.
+    /// `buffer` must have `TRANSFER_SRC` usage.
+    pub fn read_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> Result<Vec<u8>, ImmediateContextError> {
+        check_buffer_range(offset, size, buffer.properties().size)?;
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut state = self.lock_state();
+        let ImmediateState {
+            command_buffers,
+            fence,
+            staging_buffer,
+        } = &mut *state;
+        let staging_buffer = self.staging_buffer_with_capacity(staging_buffer, size)?;
+
+        let copy_region = vk::BufferCopy {
+            src_offset: offset,
+            dst_offset: 0,
+            size,
+        };
+        let to_transfer_src = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+        let to_host = vk::BufferMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(staging_buffer.handle())
+            .size(size);
+
+        self.submit_and_wait(command_buffers, fence, |command_buffer| {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[to_transfer_src],
+                &[],
+                &[],
+            );
+            command_buffer.copy_buffer(buffer, staging_buffer, &[copy_region]);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[to_host],
+                &[],
+            );
+        })?;
+
+        read_mapped_bytes(staging_buffer.memory_allocation_mut(), 0, size as usize)
+            .map_err(ImmediateContextError::StagingMemory)
+    }
+
+    /// Reads back mip level 0, array layer 0 of `image`. See [`ImageReadback::new_from_image`].
+    pub fn read_image(
+        &self,
+        image: &dyn ImageAccess,
+        format: vk::Format,
+        current_layout: vk::ImageLayout,
+    ) -> Result<ImageReadback, ImmediateContextError> {
+        // the readback allocates its command buffer from our pool
+        let _state = self.lock_state();
+        let command_pool = self
+            .command_pools
+            .for_queue(&self.queue)
+            .map_err(ImmediateContextError::Vulkan)?;
+        ImageReadback::new_from_image(
+            self.alloc_access.clone(),
+            image,
+            format,
+            &command_pool,
+            &self.queue,
+            current_layout,
+        )
+        .map_err(ImmediateContextError::Readback)
+    }
+
+    // Helper Functions
+
+    fn lock_state(&self) -> MutexGuard<'_, ImmediateState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Must be called while holding the state lock. Records with the calling thread's command
+    /// buffer, allocating it on first use.
+    fn submit_and_wait<R>(
+        &self,
+        command_buffers: &mut HashMap<ThreadId, CommandBuffer>,
+        fence: &Fence,
+        record_commands: impl FnOnce(&CommandBuffer) -> R,
+    ) -> Result<R, ImmediateContextError> {
+        let command_pool = self
+            .command_pools
+            .for_queue(&self.queue)
+            .map_err(ImmediateContextError::Vulkan)?;
+        command_pool
+            .reset(vk::CommandPoolResetFlags::empty())
+            .map_err(ImmediateContextError::Vulkan)?;
+        let command_buffer = match command_buffers.entry(thread::current().id()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                command_pool
+                    .allocate_command_buffer(vk::CommandBufferLevel::PRIMARY)
+                    .map_err(ImmediateContextError::Vulkan)?,
+            ),
+        };
+
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        command_buffer
+            .begin(&begin_info)
+            .map_err(ImmediateContextError::Vulkan)?;
+        let output = record_commands(command_buffer);
+        command_buffer
+            .end()
+            .map_err(ImmediateContextError::Vulkan)?;
+
+        let submit_command_buffers = [command_buffer.handle()];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&submit_command_buffers);
+        self.queue
+            .submit(&[submit_info], Some(fence))
+            .map_err(ImmediateContextError::Vulkan)?;
+
+        fence
+            .wait(u64::MAX)
+            .map_err(ImmediateContextError::Vulkan)?;
+        fence.reset().map_err(ImmediateContextError::Vulkan)?;
+        Ok(output)
+    }
+
+    /// Replaces the staging buffer with a bigger one if it can't fit `required_size` bytes.
+    fn staging_buffer_with_capacity<'a>(
+        &self,
+        staging_buffer: &'a mut Option<Buffer>,
+        required_size: vk::DeviceSize,
+    ) -> Result<&'a mut Buffer, ImmediateContextError> {
+        let current_size = staging_buffer
+            .as_ref()
+            .map(|buffer| buffer.properties().size);
+        if let Some(new_size) = grown_staging_buffer_size(current_size, required_size) {
+            // drop the old buffer first. it isn't in use because every submission is waited on
+            *staging_buffer = None;
+            let buffer = Buffer::new(
+                self.alloc_access.clone(),
+                BufferProperties::new_default(
+                    new_size,
+                    vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST,
+                ),
+                allocation_info_cpu_accessible(),
+            )
+            .map_err(ImmediateContextError::StagingBufferCreation)?;
+            *staging_buffer = Some(buffer);
+        }
+        Ok(staging_buffer
+            .as_mut()
+            .expect("staging buffer created above"))
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    #[inline]
+    pub fn allocator_access(&self) -> &Arc<dyn AllocatorAccess> {
+        &self.alloc_access
+    }
+
+    /// Size of the staging buffer in bytes, or 0 if nothing has been transferred yet.
+    pub fn staging_buffer_size(&self) -> vk::DeviceSize {
+        self.lock_state()
+            .staging_buffer
+            .as_ref()
+            .map_or(0, |buffer| buffer.properties().size)
+    }
+}
+
+// Helper Functions
+
+/// The size to grow the staging buffer to in order to fit `required_size` bytes, or `None` if
+/// the current buffer is big enough. Sizes are rounded up to a power of two so a sequence of
+/// slightly bigger transfers doesn't reallocate every time.
+fn grown_staging_buffer_size(
+    current_size: Option<vk::DeviceSize>,
+    required_size: vk::DeviceSize,
+) -> Option<vk::DeviceSize> {
+    if current_size.is_some_and(|current_size| current_size >= required_size) {
+        return None;
+    }
+    Some(
+        required_size
+            .max(MIN_STAGING_BUFFER_SIZE)
+            .next_power_of_two(),
+    )
+}
+
+fn check_buffer_range(
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    buffer_size: vk::DeviceSize,
+) -> Result<(), ImmediateContextError> {
+    let in_range = offset
+        .checked_add(size)
+        .is_some_and(|end| end <= buffer_size);
+    if !in_range {
+        return Err(ImmediateContextError::RangeOutOfBounds {
+            offset,
+            size,
+            buffer_size,
+        });
+    }
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum ImmediateContextError {
+    Vulkan(vk::Result),
+    StagingBufferCreation(AllocationError),
+    StagingMemory(MemoryError),
+    DataSizeMismatch {
+        data_size: usize,
+        expected_size: vk::DeviceSize,
+    },
+    /// The texel size of the image format isn't known. See
+    /// [`format_texel_size`](crate::format_texel_size).
+    UnknownTexelSize(vk::Format),
+    /// Buffer/image copies of combined depth/stencil formats need a copy per aspect.
+    CombinedDepthStencilFormat(vk::Format),
+    RangeOutOfBounds {
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        buffer_size: vk::DeviceSize,
+    },
+    Readback(ImageReadbackError),
+}
+
+impl fmt::Display for ImmediateContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => write!(f, "immediate context vulkan call failed: {}", e),
+            Self::StagingBufferCreation(e) => {
+                write!(f, "failed to create staging buffer: {}", e)
+            }
+            Self::StagingMemory(e) => write!(f, "failed to access staging buffer memory: {}", e),
+            Self::DataSizeMismatch {
+                data_size,
+                expected_size,
+            } => write!(
+                f,
+                "data size {} doesn't match the expected size {}",
+                data_size, expected_size
+            ),
+            Self::UnknownTexelSize(format) => {
+                write!(f, "texel size of format {:?} is unknown", format)
+            }
+            Self::CombinedDepthStencilFormat(format) => write!(
+                f,
+                "can't upload to combined depth/stencil format {:?} with a single copy",
+                format
+            ),
+            Self::RangeOutOfBounds {
+                offset,
+                size,
+                buffer_size,
+            } => write!(
+                f,
+                "range of {} bytes at offset {} is out of bounds for a buffer of size {}",
+                size, offset, buffer_size
+            ),
+            Self::Readback(e) => write!(f, "failed to read back image: {}", e),
+        }
+    }
+}
+
+impl error::Error for ImmediateContextError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            Self::StagingBufferCreation(e) => Some(e),
+            Self::StagingMemory(e) => Some(e),
+            Self::DataSizeMismatch { .. } => None,
+            Self::UnknownTexelSize(_) => None,
+            Self::CombinedDepthStencilFormat(_) => None,
+            Self::RangeOutOfBounds { .. } => None,
+            Self::Readback(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn immediate_context_staging_buffer_growth() {
+    assert_eq!(
+        grown_staging_buffer_size(None, 16),
+        Some(MIN_STAGING_BUFFER_SIZE)
+    );
+    assert_eq!(
+        grown_staging_buffer_size(Some(MIN_STAGING_BUFFER_SIZE), 16),
+        None
+    );
+    assert_eq!(
+        grown_staging_buffer_size(Some(MIN_STAGING_BUFFER_SIZE), MIN_STAGING_BUFFER_SIZE + 1),
+        Some(MIN_STAGING_BUFFER_SIZE * 2)
+    );
+
+    assert!(check_buffer_range(0, 16, 16).is_ok());
+    assert!(check_buffer_range(8, 16, 16).is_err());
+    assert!(check_buffer_range(u64::MAX, 2, 16).is_err());
+}
@@ -0,0 +1,322 @@
+use crate::{
+    AllocatorAccess, Buffer, BufferProperties, CommandPool, InputAssemblyState, Queue,
+    ResourceInitError,
+};
+use ash::vk;
+use bort_vma::AllocationCreateInfo;
+use std::{error, fmt, sync::Arc};
+
+/// Builds index data for a `vk::IndexType::UINT16` or `vk::IndexType::UINT32` index buffer.
+/// Strips pushed with `push_strip` are separated by the primitive restart value.
+#[derive(Debug, Clone)]
+pub struct IndexBufferBuilder {
+    topology: vk::PrimitiveTopology,
+    index_type: vk::IndexType,
+    indices: Vec<u32>,
+    strip_count: usize,
+}
+
+impl IndexBufferBuilder {
+    pub fn new(
+        topology: vk::PrimitiveTopology,
+        index_type: vk::IndexType,
+    ) -> Result<Self, IndexBufferError> {
+        if primitive_restart_value(index_type).is_none() {
+            return Err(IndexBufferError::UnsupportedIndexType(index_type));
+        }
+
+        Ok(Self {
+            topology,
+            index_type,
+            indices: Vec::new(),
+            strip_count: 0,
+        })
+    }
+
+    /// Appends indices without inserting a restart value. For list topologies.
+    pub fn push_indices(&mut self, indices: &[u32]) -> Result<(), IndexBufferError> {
+        self.check_indices(indices)?;
+        self.indices.extend_from_slice(indices);
+        Ok(())
+    }
+
+    /// Appends a strip (or fan). If this isn't the first strip, the primitive restart value is
+    /// inserted before it.
+    pub fn push_strip(&mut self, strip: &[u32]) -> Result<(), IndexBufferError> {
+        self.check_indices(strip)?;
+        if self.strip_count > 0 {
+            self.indices.push(self.restart_value());
+        }
+        self.indices.extend_from_slice(strip);
+        self.strip_count += 1;
+        Ok(())
+    }
+
+    /// Converts a triangle list to strips with `triangle_list_to_strips` and pushes them.
+    pub fn push_triangle_list_as_strips(
+        &mut self,
+        triangle_list: &[u32],
+    ) -> Result<(), IndexBufferError> {
+        for strip in triangle_list_to_strips(triangle_list) {
+            self.push_strip(&strip)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `input_assembly_state` is compatible with the index data:
+    /// - the topologies match
+    /// - `primitive_restart_enable` is set if more than one strip was pushed
+    /// - primitive restart isn't enabled for list topologies (requires
+    ///   `VK_EXT_primitive_topology_list_restart`)
+    pub fn validate(
+        &self,
+        input_assembly_state: &InputAssemblyState,
+    ) -> Result<(), IndexBufferError> {
+        if input_assembly_state.topology != self.topology {
+            return Err(IndexBufferError::TopologyMismatch {
+                builder_topology: self.topology,
+                pipeline_topology: input_assembly_state.topology,
+            });
+        }
+
+        if self.strip_count > 1 && !input_assembly_state.primitive_restart_enable {
+            return Err(IndexBufferError::PrimitiveRestartNotEnabled);
+        }
+
+        if input_assembly_state.primitive_restart_enable && is_list_topology(self.topology) {
+            return Err(IndexBufferError::PrimitiveRestartWithListTopology(
+                self.topology,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The index data encoded according to `index_type`.
+    pub fn bytes(&self) -> Vec<u8> {
+        match self.index_type {
+            vk::IndexType::UINT16 => self
+                .indices
+                .iter()
+                .flat_map(|&index| (index as u16).to_ne_bytes())
+                .collect(),
+            _ => self
+                .indices
+                .iter()
+                .flat_map(|&index| index.to_ne_bytes())
+                .collect(),
+        }
+    }
+
+    /// Creates a device buffer with `INDEX_BUFFER` usage containing the index data. See
+    /// `Buffer::new_with_data`.
+    pub fn build(
+        &self,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        allocation_info: AllocationCreateInfo,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+    ) -> Result<Buffer, ResourceInitError> {
+        let bytes = self.bytes();
+        let properties = BufferProperties::new_default(
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+        );
+        Buffer::new_with_data(
+            alloc_access,
+            properties,
+            allocation_info,
+            command_pool,
+            queue,
+            &bytes,
+        )
+    }
+
+    fn check_indices(&self, indices: &[u32]) -> Result<(), IndexBufferError> {
+        let restart_value = self.restart_value();
+        if let Some(&index) = indices.iter().find(|&&index| index >= restart_value) {
+            return Err(IndexBufferError::IndexOutOfRange {
+                index,
+                index_type: self.index_type,
+            });
+        }
+        Ok(())
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn topology(&self) -> vk::PrimitiveTopology {
+        self.topology
+    }
+
+    #[inline]
+    pub fn index_type(&self) -> vk::IndexType {
+        self.index_type
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// The value to pass to `vkCmdDrawIndexed`.
+    #[inline]
+    pub fn index_count(&self) -> u32 {
+        self.indices.len() as u32
+    }
+
+    #[inline]
+    pub fn restart_value(&self) -> u32 {
+        primitive_restart_value(self.index_type).unwrap_or(u32::MAX)
+    }
+}
+
+// Helper Functions
+
+/// The index value that restarts primitive assembly for `index_type`. Returns `None` for
+/// unsupported index types.
+pub fn primitive_restart_value(index_type: vk::IndexType) -> Option<u32> {
+    match index_type {
+        vk::IndexType::UINT16 => Some(u16::MAX as u32),
+        vk::IndexType::UINT32 => Some(u32::MAX),
+        _ => None,
+    }
+}
+
+pub fn is_list_topology(topology: vk::PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        vk::PrimitiveTopology::POINT_LIST
+            | vk::PrimitiveTopology::LINE_LIST
+            | vk::PrimitiveTopology::TRIANGLE_LIST
+            | vk::PrimitiveTopology::LINE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::TRIANGLE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::PATCH_LIST
+    )
+}
+
+/// Greedily converts a triangle list to triangle strips, preserving winding order. Consecutive
+/// triangles that share an edge with the end of the current strip get appended to it, otherwise
+/// a new strip is started. Any trailing indices that don't form a full triangle are ignored.
+pub fn triangle_list_to_strips(triangle_list: &[u32]) -> Vec<Vec<u32>> {
+    let mut strips = Vec::<Vec<u32>>::new();
+
+    for triangle in triangle_list.chunks_exact(3) {
+        let appended = strips
+            .last_mut()
+            .map(|strip| try_append_triangle_to_strip(strip, triangle))
+            .unwrap_or(false);
+
+        if !appended {
+            strips.push(triangle.to_vec());
+        }
+    }
+
+    strips
+}
+
+/// The triangle added by the next strip vertex `z` is `(a, b, z)` where `(a, b)` are the last two
+/// strip vertices, swapped for odd triangles to keep the winding consistent.
+fn try_append_triangle_to_strip(strip: &mut Vec<u32>, triangle: &[u32]) -> bool {
+    let strip_len = strip.len();
+    let next_triangle_index = strip_len - 2;
+    let (a, b) = if next_triangle_index & 1 == 0 {
+        (strip[strip_len - 2], strip[strip_len - 1])
+    } else {
+        (strip[strip_len - 1], strip[strip_len - 2])
+    };
+
+    for rotation in 0..3 {
+        let rotated = [
+            triangle[rotation],
+            triangle[(rotation + 1) % 3],
+            triangle[(rotation + 2) % 3],
+        ];
+        if rotated[0] == a && rotated[1] == b {
+            strip.push(rotated[2]);
+            return true;
+        }
+    }
+
+    false
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum IndexBufferError {
+    UnsupportedIndexType(vk::IndexType),
+    IndexOutOfRange {
+        index: u32,
+        index_type: vk::IndexType,
+    },
+    TopologyMismatch {
+        builder_topology: vk::PrimitiveTopology,
+        pipeline_topology: vk::PrimitiveTopology,
+    },
+    PrimitiveRestartNotEnabled,
+    PrimitiveRestartWithListTopology(vk::PrimitiveTopology),
+}
+
+impl fmt::Display for IndexBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedIndexType(index_type) => write!(
+                f,
+                "index type {:?} isn't supported. use UINT16 or UINT32",
+                index_type
+            ),
+            Self::IndexOutOfRange { index, index_type } => write!(
+                f,
+                "index {} is out of range or equal to the primitive restart value for index type {:?}",
+                index, index_type
+            ),
+            Self::TopologyMismatch {
+                builder_topology,
+                pipeline_topology,
+            } => write!(
+                f,
+                "index data was built for topology {:?} but the pipeline uses {:?}",
+                builder_topology, pipeline_topology
+            ),
+            Self::PrimitiveRestartNotEnabled => write!(
+                f,
+                "index data contains multiple strips but primitive restart isn't enabled"
+            ),
+            Self::PrimitiveRestartWithListTopology(topology) => write!(
+                f,
+                "primitive restart enabled for list topology {:?} which requires VK_EXT_primitive_topology_list_restart",
+                topology
+            ),
+        }
+    }
+}
+
+impl error::Error for IndexBufferError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn triangle_list_to_strips_joins_quad() {
+    // two triangles of a quad sharing the edge 1-2
+    let strips = triangle_list_to_strips(&[0, 1, 2, 2, 1, 3]);
+    assert_eq!(strips, vec![vec![0, 1, 2, 3]]);
+}
+
+#[test]
+fn triangle_list_to_strips_splits_disjoint() {
+    let strips = triangle_list_to_strips(&[0, 1, 2, 3, 4, 5]);
+    assert_eq!(strips, vec![vec![0, 1, 2], vec![3, 4, 5]]);
+}
+
+#[test]
+fn index_builder_inserts_restart() {
+    let mut builder =
+        IndexBufferBuilder::new(vk::PrimitiveTopology::TRIANGLE_STRIP, vk::IndexType::UINT16)
+            .unwrap();
+    builder.push_strip(&[0, 1, 2]).unwrap();
+    builder.push_strip(&[3, 4, 5]).unwrap();
+    assert_eq!(builder.indices(), &[0, 1, 2, 0xFFFF, 3, 4, 5]);
+    assert!(builder.validate(&InputAssemblyState::default()).is_err());
+}
@@ -0,0 +1,478 @@
+use crate::{
+    allocation_info_device_local, AllocationError, AllocatorAccess, Buffer, BufferProperties,
+    CommandBuffer, ComputePipeline, ComputePipelineProperties, DescriptorPool,
+    DescriptorPoolProperties, DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device, DeviceOwned, PipelineLayout,
+    PipelineLayoutProperties, ShaderError, ShaderModule, ShaderStage,
+};
+use ash::vk;
+use std::{error, fmt, io::Cursor, mem, sync::Arc};
+
+/// Spir-V compiled from `shaders/cull_draws.comp`.
+const CULL_DRAWS_SPIRV: &[u8] = include_bytes!("./shaders/cull_draws.comp.spv");
+/// Must match the `local_size_x` of `shaders/cull_draws.comp`.
+const CULL_DRAWS_WORKGROUP_SIZE: u32 = 64;
+const FRUSTUM_PLANE_COUNT: usize = 6;
+/// Size of the push constants of `shaders/cull_draws.comp`: 6 planes and the object count.
+const CULL_PUSH_CONSTANTS_SIZE: usize = mem::size_of::<[[f32; 4]; FRUSTUM_PLANE_COUNT]>() + 4;
+const DRAW_COMMAND_STRIDE: u32 = mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+const CULL_BINDING_COUNT: u32 = 4;
+
+/// A GPU frustum culling pass for multi-draw indirect rendering. A compute dispatch tests a
+/// bounding sphere per object against the view frustum and compacts the
+/// `vk::DrawIndexedIndirectCommand`s of the visible objects into a second buffer along with a
+/// draw count, which is then drawn with a single `vkCmdDrawIndexedIndirectCount`.
+///
+/// Usage:
+/// 1. create [`IndirectCullBindings`] with [`Self::create_bindings`] for a buffer of object
+///    bounding spheres and a buffer of draw commands (one per object).
+/// 2. call [`Self::record_cull`] outside of a render pass each frame.
+/// 3. call [`Self::record_draw`] inside the render pass with the graphics pipeline and vertex
+///    and index buffers bound.
+///
+/// Requires vulkan 1.2 and the `drawIndirectCount` and `multiDrawIndirect` features.
+pub struct IndirectCullPass {
+    pipeline: ComputePipeline,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    descriptor_pool: Arc<DescriptorPool>,
+}
+
+impl IndirectCullPass {
+    /// `max_bindings` is the number of [`IndirectCullBindings`] which can be alive at once.
+    pub fn new(device: Arc<Device>, max_bindings: u32) -> Result<Self, IndirectCullError> {
+        let bindings = (0..CULL_BINDING_COUNT)
+            .map(|binding| DescriptorSetLayoutBinding {
+                binding,
+                descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            })
+            .collect();
+        let descriptor_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(bindings),
+            )
+            .map_err(IndirectCullError::DescriptorSetLayout)?,
+        );
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: CULL_PUSH_CONSTANTS_SIZE as u32,
+        };
+        let pipeline_layout = Arc::new(
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutProperties::new(
+                    vec![descriptor_set_layout.clone()],
+                    vec![push_constant_range],
+                ),
+            )
+            .map_err(IndirectCullError::Vulkan)?,
+        );
+
+        let shader_module = Arc::new(
+            ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(CULL_DRAWS_SPIRV))
+                .map_err(IndirectCullError::Shader)?,
+        );
+        let shader_stage =
+            ShaderStage::compute(shader_module).map_err(IndirectCullError::Shader)?;
+        let pipeline = ComputePipeline::new(
+            pipeline_layout,
+            ComputePipelineProperties::default(),
+            &shader_stage,
+            None,
+        )
+        .map_err(IndirectCullError::Vulkan)?;
+
+        let max_sets = max_bindings.max(1);
+        let descriptor_pool_properties = DescriptorPoolProperties {
+            flags: vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+            max_sets,
+            pool_sizes: vec![vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::STORAGE_BUFFER,
+                descriptor_count: max_sets * CULL_BINDING_COUNT,
+            }],
+        };
+        let descriptor_pool = Arc::new(
+            DescriptorPool::new(device, descriptor_pool_properties)
+                .map_err(IndirectCullError::Vulkan)?,
+        );
+
+        Ok(Self {
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+        })
+    }
+
+    /// Creates the culled draw command and draw count buffers (device local) for `object_count`
+    /// objects and a descriptor set binding them with the inputs:
+    /// - `object_bounds`: a `[f32; 4]` bounding sphere (xyz = center, w = radius) per object in
+    ///   the same space as the frustum planes passed to [`Self::record_cull`]. Requires
+    ///   `STORAGE_BUFFER` usage.
+    /// - `draw_commands`: a `vk::DrawIndexedIndirectCommand` per object. Requires
+    ///   `STORAGE_BUFFER` usage.
+    pub fn create_bindings(
+        &self,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        object_bounds: Arc<Buffer>,
+        draw_commands: Arc<Buffer>,
+        object_count: u32,
+    ) -> Result<IndirectCullBindings, IndirectCullError> {
+        let required_bounds_size = object_count as vk::DeviceSize * 16;
+        let required_draws_size = object_count as vk::DeviceSize * DRAW_COMMAND_STRIDE as u64;
+        for (buffer_size, required_size) in [
+            (object_bounds.properties().size, required_bounds_size),
+            (draw_commands.properties().size, required_draws_size),
+        ] {
+            if buffer_size < required_size {
+                return Err(IndirectCullError::BufferTooSmall {
+                    buffer_size,
+                    required_size,
+                });
+            }
+        }
+
+        let culled_draw_commands = Buffer::new(
+            alloc_access.clone(),
+            BufferProperties::new_default(
+                required_draws_size.max(DRAW_COMMAND_STRIDE as u64),
+                vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER,
+            ),
+            allocation_info_device_local(),
+        )
+        .map_err(IndirectCullError::BufferCreation)?;
+        let draw_count = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(
+                mem::size_of::<u32>() as vk::DeviceSize,
+                vk::BufferUsageFlags::STORAGE_BUFFER
+                    | vk::BufferUsageFlags::INDIRECT_BUFFER
+                    | vk::BufferUsageFlags::TRANSFER_DST,
+            ),
+            allocation_info_device_local(),
+        )
+        .map_err(IndirectCullError::BufferCreation)?;
+
+        let descriptor_set = self
+            .descriptor_pool
+            .allocate_descriptor_set(self.descriptor_set_layout.clone())
+            .map_err(IndirectCullError::Vulkan)?;
+
+        let buffer_infos = [
+            object_bounds.as_ref(),
+            draw_commands.as_ref(),
+            &culled_draw_commands,
+            &draw_count,
+        ]
+        .map(|buffer| {
+            [vk::DescriptorBufferInfo {
+                buffer: buffer.handle(),
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }]
+        });
+        let descriptor_writes = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer_info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set.handle())
+                    .dst_binding(binding as u32)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(buffer_info)
+            });
+        self.device().update_descriptor_sets(descriptor_writes, []);
+
+        Ok(IndirectCullBindings {
+            descriptor_set,
+            object_bounds,
+            draw_commands,
+            culled_draw_commands,
+            draw_count,
+            object_count,
+        })
+    }
+
+    /// Records the culling dispatch and the barriers around it. Must be recorded outside of a
+    /// render pass. `frustum_planes` are inward facing planes (xyz = normal, w = distance), see
+    /// [`frustum_planes_from_view_projection`].
+    ///
+    /// Waits for any previous indirect draws from `bindings` on the same queue before
+    /// overwriting the culled draws, and makes the results available to later indirect draws.
+    pub fn record_cull(
+        &self,
+        command_buffer: &CommandBuffer,
+        bindings: &IndirectCullBindings,
+        frustum_planes: &[[f32; 4]; FRUSTUM_PLANE_COUNT],
+    ) {
+        // previous frame's indirect reads must finish before the count is reset
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[],
+        );
+        command_buffer.fill_buffer(&bindings.draw_count, 0, vk::WHOLE_SIZE, 0);
+
+        let count_reset_barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE);
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[count_reset_barrier],
+            &[],
+            &[],
+        );
+
+        self.pipeline.record_dispatch(
+            command_buffer,
+            &bindings.descriptor_set,
+            &cull_push_constant_bytes(frustum_planes, bindings.object_count),
+            [
+                bindings.object_count.div_ceil(CULL_DRAWS_WORKGROUP_SIZE),
+                1,
+                1,
+            ],
+        );
+
+        let cull_barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::INDIRECT_COMMAND_READ);
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::DependencyFlags::empty(),
+            &[cull_barrier],
+            &[],
+            &[],
+        );
+    }
+
+    /// Draws the commands which passed culling in the last [`Self::record_cull`].
+    pub fn record_draw(&self, command_buffer: &CommandBuffer, bindings: &IndirectCullBindings) {
+        command_buffer.draw_indexed_indirect_count(
+            &bindings.culled_draw_commands,
+            0,
+            &bindings.draw_count,
+            0,
+            bindings.object_count,
+            DRAW_COMMAND_STRIDE,
+        );
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline(&self) -> &ComputePipeline {
+        &self.pipeline
+    }
+
+    #[inline]
+    pub fn descriptor_set_layout(&self) -> &Arc<DescriptorSetLayout> {
+        &self.descriptor_set_layout
+    }
+}
+
+impl DeviceOwned for IndirectCullPass {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.pipeline.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.pipeline.handle_raw()
+    }
+}
+
+/// Per-scene state of an [`IndirectCullPass`]: the object bounds and draw commands it reads and
+/// the compacted draw commands and draw count it writes. Both the cull dispatch and the indirect
+/// draw reference these buffers, so keep the bindings alive until every command buffer either was
+/// recorded to has completed.
+pub struct IndirectCullBindings {
+    descriptor_set: DescriptorSet,
+    object_bounds: Arc<Buffer>,
+    draw_commands: Arc<Buffer>,
+    culled_draw_commands: Buffer,
+    draw_count: Buffer,
+    object_count: u32,
+}
+
+impl IndirectCullBindings {
+    // Getters
+
+    #[inline]
+    pub fn descriptor_set(&self) -> &DescriptorSet {
+        &self.descriptor_set
+    }
+
+    #[inline]
+    pub fn object_bounds(&self) -> &Arc<Buffer> {
+        &self.object_bounds
+    }
+
+    #[inline]
+    pub fn draw_commands(&self) -> &Arc<Buffer> {
+        &self.draw_commands
+    }
+
+    /// The compacted draw commands of visible objects.
+    #[inline]
+    pub fn culled_draw_commands(&self) -> &Buffer {
+        &self.culled_draw_commands
+    }
+
+    /// A single `u32` with the number of commands in [`Self::culled_draw_commands`].
+    #[inline]
+    pub fn draw_count(&self) -> &Buffer {
+        &self.draw_count
+    }
+
+    #[inline]
+    pub fn object_count(&self) -> u32 {
+        self.object_count
+    }
+}
+
+/// Extracts the normalized, inward facing frustum planes (left, right, bottom, top, near, far)
+/// from a column-major view-projection matrix with vulkan clip space (depth 0 to 1).
+pub fn frustum_planes_from_view_projection(
+    view_projection: [[f32; 4]; 4],
+) -> [[f32; 4]; FRUSTUM_PLANE_COUNT] {
+    let row = |i: usize| view_projection.map(|column| column[i]);
+    let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+    let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+    let planes = [
+        add(row(3), row(0)),
+        sub(row(3), row(0)),
+        add(row(3), row(1)),
+        sub(row(3), row(1)),
+        row(2),
+        sub(row(3), row(2)),
+    ];
+    planes.map(|plane| {
+        let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+        if length > 0. {
+            plane.map(|value| value / length)
+        } else {
+            plane
+        }
+    })
+}
+
+/// CPU equivalent of the culling test in `shaders/cull_draws.comp`.
+pub fn is_sphere_in_frustum(
+    frustum_planes: &[[f32; 4]; FRUSTUM_PLANE_COUNT],
+    sphere: [f32; 4],
+) -> bool {
+    frustum_planes.iter().all(|plane| {
+        plane[0] * sphere[0] + plane[1] * sphere[1] + plane[2] * sphere[2] + plane[3] >= -sphere[3]
+    })
+}
+
+// Helper Functions
+
+fn cull_push_constant_bytes(
+    frustum_planes: &[[f32; 4]; FRUSTUM_PLANE_COUNT],
+    object_count: u32,
+) -> Vec<u8> {
+    let mut bytes: Vec<u8> = frustum_planes
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    bytes.extend_from_slice(&object_count.to_ne_bytes());
+    bytes
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum IndirectCullError {
+    BufferTooSmall {
+        buffer_size: vk::DeviceSize,
+        required_size: vk::DeviceSize,
+    },
+    Shader(ShaderError),
+    DescriptorSetLayout(DescriptorSetLayoutError),
+    Vulkan(vk::Result),
+    BufferCreation(AllocationError),
+}
+
+impl fmt::Display for IndirectCullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferTooSmall {
+                buffer_size,
+                required_size,
+            } => write!(
+                f,
+                "indirect cull input buffer size {} is smaller than the required size {}",
+                buffer_size, required_size
+            ),
+            Self::Shader(e) => write!(f, "failed to create indirect cull shader: {}", e),
+            Self::Vulkan(e) => write!(f, "indirect cull vulkan call failed: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create indirect cull buffer: {}", e),
+            Self::DescriptorSetLayout(e) => write!(
+                f,
+                "indirect cull descriptor set layout creation failed: {}",
+                e
+            ),
+        }
+    }
+}
+
+impl error::Error for IndirectCullError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Shader(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::DescriptorSetLayout(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn frustum_planes_cull_spheres() {
+    let identity = [
+        [1., 0., 0., 0.],
+        [0., 1., 0., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+    ];
+    let planes = frustum_planes_from_view_projection(identity);
+    assert_eq!(planes[0], [1., 0., 0., 1.]);
+    assert_eq!(planes[4], [0., 0., 1., 0.]);
+    assert_eq!(planes[5], [0., 0., -1., 1.]);
+
+    assert!(is_sphere_in_frustum(&planes, [0., 0., 0.5, 0.1]));
+    assert!(is_sphere_in_frustum(&planes, [1.05, 0., 0.5, 0.1]));
+    assert!(!is_sphere_in_frustum(&planes, [1.2, 0., 0.5, 0.1]));
+    assert!(!is_sphere_in_frustum(&planes, [0., 0., -0.2, 0.1]));
+
+    assert_eq!(
+        cull_push_constant_bytes(&planes, 3).len(),
+        CULL_PUSH_CONSTANTS_SIZE
+    );
+}
+
+#[test]
+fn cull_draws_spirv_has_compute_entry_point() {
+    let spirv = ash::util::read_spv(&mut Cursor::new(CULL_DRAWS_SPIRV)).unwrap();
+    let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+    assert_eq!(entry_points.len(), 1);
+    assert_eq!(entry_points[0].stage, vk::ShaderStageFlags::COMPUTE);
+}
@@ -1,7 +1,13 @@
-use crate::{PhysicalDevice, PhysicalDeviceFeatures, ALLOCATION_CALLBACK_NONE};
+use crate::{
+    extension_loader::ExtensionLoaderCache, CooperativeMatrixConfiguration, DebugPrintfConfig,
+    HandleOwnership, InstanceDebugMessenger, InstanceExtensionLoader, PhysicalDevice,
+    PhysicalDeviceFeatures, ALLOCATION_CALLBACK_NONE, VALIDATION_LAYER_NAME,
+};
 use ash::{
-    ext::metal_surface,
-    khr::{android_surface, surface, wayland_surface, win32_surface, xcb_surface, xlib_surface},
+    ext::{headless_surface, metal_surface},
+    khr::{
+        self, android_surface, surface, wayland_surface, win32_surface, xcb_surface, xlib_surface,
+    },
     prelude::VkResult,
     vk::{self, make_api_version},
     Entry,
@@ -14,7 +20,8 @@ use std::{
     error,
     ffi::{CStr, CString},
     fmt,
-    os::raw::c_char,
+    os::raw::{c_char, c_void},
+    ptr,
     sync::Arc,
 };
 
@@ -30,11 +37,22 @@ impl ApiVersion {
     pub const V1_1: Self = Self { major: 1, minor: 1 };
     pub const V1_2: Self = Self { major: 1, minor: 2 };
     pub const V1_3: Self = Self { major: 1, minor: 3 };
+    /// Note: the ash version used here predates vulkan 1.4 so there are no 1.4 feature/property
+    /// structs. Version-gated paths treat 1.4 like 1.3; promoted extensions like
+    /// `VK_KHR_maintenance5` can be enabled with their extension feature structs instead.
     pub const V1_4: Self = Self { major: 1, minor: 4 };
 
     pub const fn as_vk_uint(&self) -> u32 {
         make_api_version(0, self.major, self.minor, 0)
     }
+
+    /// Ignores the variant and patch numbers.
+    pub const fn from_vk_uint(api_version: u32) -> Self {
+        Self {
+            major: vk::api_version_major(api_version),
+            minor: vk::api_version_minor(api_version),
+        }
+    }
 }
 
 pub struct Instance {
@@ -42,8 +60,12 @@ pub struct Instance {
     /// The maximum version of vulkan that the application is designed to use.
     /// [More info here](https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkApplicationInfo.html)
     max_api_version: ApiVersion,
+    /// `max_api_version` clamped to the version supported by the loader.
+    effective_api_version: ApiVersion,
     enabled_extensions: Vec<CString>,
     enabled_layers: Vec<CString>,
+    extension_loaders: ExtensionLoaderCache,
+    ownership: HandleOwnership,
 
     // dependencies
     entry: Arc<Entry>,
@@ -53,6 +75,7 @@ impl Instance {
     /// This function will figure out the required surface extensions based on `display_handle`
     /// e.g. VK_KHR_surface and platform specific ones like VK_KHR_win32_surface. Will also check
     /// if the display extensions and extension_names are supported.
+    #[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
     pub fn new_with_display_extensions(
         entry: Arc<Entry>,
         max_api_version: ApiVersion,
@@ -60,6 +83,20 @@ impl Instance {
         layer_names: Vec<CString>,
         other_extension_names: Vec<CString>,
     ) -> Result<Self, InstanceError> {
+        let extension_names =
+            Self::with_display_extensions(&entry, display_handle, other_extension_names)?;
+        Self::new(entry, max_api_version, layer_names, extension_names)
+    }
+
+    /// `other_extension_names` plus the surface extensions required for `display_handle`, as
+    /// used by [`Self::new_with_display_extensions`]. Returns
+    /// [`InstanceError::ExtensionsNotPresent`] if any of them aren't supported.
+    #[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
+    pub fn with_display_extensions(
+        entry: &Entry,
+        display_handle: RawDisplayHandle,
+        other_extension_names: Vec<CString>,
+    ) -> Result<Vec<CString>, InstanceError> {
         let display_extension_name_cstrs = Self::required_surface_extensions(display_handle)?;
         let mut display_extension_names: Vec<CString> = display_extension_name_cstrs
             .iter()
@@ -70,24 +107,182 @@ impl Instance {
         extension_names.append(&mut display_extension_names);
 
         let unsupported_display_extensions =
-            Self::any_unsupported_extensions(&entry, None, extension_names.clone())
+            Self::any_unsupported_extensions(entry, None, extension_names.clone())
                 .map_err(InstanceError::Creation)?;
         if !unsupported_display_extensions.is_empty() {
             return Err(InstanceError::ExtensionsNotPresent(
                 unsupported_display_extensions,
             ));
         }
-
-        Self::new(entry, max_api_version, layer_names, extension_names)
+        Ok(extension_names)
     }
 
     /// Doesn't check for extension/layer support.
+    ///
+    /// `max_api_version` is clamped to the version supported by the loader (see
+    /// [`Self::loader_api_version`]) and the result is available from
+    /// [`Self::effective_api_version`].
     pub fn new(
         entry: Arc<Entry>,
         max_api_version: ApiVersion,
         layer_names: Vec<CString>,
         extension_names: Vec<CString>,
     ) -> Result<Self, InstanceError> {
+        unsafe {
+            Self::new_with_p_next(
+                entry,
+                max_api_version,
+                layer_names,
+                extension_names,
+                None,
+                ptr::null(),
+            )
+        }
+    }
+
+    /// Same as [`Self::new`] but chains `debug_messenger` to the instance create info so that
+    /// messages emitted during instance creation and destruction are reported (see
+    /// [`InstanceDebugMessenger`]). Adds `VK_EXT_debug_utils` to `extension_names`.
+    pub fn new_with_debug_messenger(
+        entry: Arc<Entry>,
+        max_api_version: ApiVersion,
+        layer_names: Vec<CString>,
+        extension_names: Vec<CString>,
+        debug_messenger: InstanceDebugMessenger,
+    ) -> Result<Self, InstanceError> {
+        unsafe {
+            Self::new_with_p_next(
+                entry,
+                max_api_version,
+                layer_names,
+                extension_names,
+                Some(debug_messenger),
+                ptr::null(),
+            )
+        }
+    }
+
+    /// Same as [`Self::new`] but also enables the validation layer with shader `debugPrintfEXT`
+    /// support. Adds `VK_LAYER_KHRONOS_validation` and `VK_EXT_validation_features` (plus
+    /// `VK_EXT_layer_settings` if the layer supports it) to `layer_names` and `extension_names`.
+    ///
+    /// Printf output is reported through the debug callback; create a
+    /// [`DebugCallback`](crate::DebugCallback) with
+    /// [`debug_printf_callback`](crate::debug_printf_callback) to route it to
+    /// [`DEBUG_PRINTF_LOG_TARGET`](crate::DEBUG_PRINTF_LOG_TARGET). Devices also need
+    /// [`debug_printf_device_extensions`](crate::debug_printf_device_extensions) and
+    /// [`enable_debug_printf_features`](crate::enable_debug_printf_features).
+    pub fn new_with_debug_printf(
+        entry: Arc<Entry>,
+        max_api_version: ApiVersion,
+        mut layer_names: Vec<CString>,
+        mut extension_names: Vec<CString>,
+        config: DebugPrintfConfig,
+    ) -> Result<Self, InstanceError> {
+        if !layer_names
+            .iter()
+            .any(|layer_name| layer_name.as_c_str() == VALIDATION_LAYER_NAME)
+        {
+            layer_names.push(VALIDATION_LAYER_NAME.to_owned());
+        }
+
+        let unsupported_layer_extensions = Self::any_unsupported_extensions(
+            &entry,
+            Some(VALIDATION_LAYER_NAME),
+            vec![
+                vk::EXT_VALIDATION_FEATURES_NAME.to_owned(),
+                vk::EXT_LAYER_SETTINGS_NAME.to_owned(),
+            ],
+        )
+        .map_err(InstanceError::Creation)?;
+        if unsupported_layer_extensions
+            .iter()
+            .any(|name| name.as_c_str() == vk::EXT_VALIDATION_FEATURES_NAME)
+        {
+            return Err(InstanceError::ExtensionsNotPresent(
+                unsupported_layer_extensions,
+            ));
+        }
+        let layer_settings_supported = unsupported_layer_extensions.is_empty();
+
+        let mut required_extensions = vec![vk::EXT_VALIDATION_FEATURES_NAME];
+        if layer_settings_supported {
+            required_extensions.push(vk::EXT_LAYER_SETTINGS_NAME);
+        }
+        for required_extension in required_extensions {
+            if !extension_names
+                .iter()
+                .any(|name| name.as_c_str() == required_extension)
+            {
+                extension_names.push(required_extension.to_owned());
+            }
+        }
+
+        let printf_to_stdout = [vk::FALSE];
+        let printf_verbose = [config.verbose as vk::Bool32];
+        let printf_buffer_size = [config.buffer_size];
+        let layer_settings = [
+            layer_setting_bool32(c"printf_to_stdout", &printf_to_stdout),
+            layer_setting_bool32(c"printf_verbose", &printf_verbose),
+            vk::LayerSettingEXT {
+                p_layer_name: VALIDATION_LAYER_NAME.as_ptr(),
+                p_setting_name: c"printf_buffer_size".as_ptr(),
+                ty: vk::LayerSettingTypeEXT::UINT32,
+                value_count: 1,
+                p_values: printf_buffer_size.as_ptr() as *const c_void,
+                ..Default::default()
+            },
+        ];
+        let layer_settings_create_info =
+            vk::LayerSettingsCreateInfoEXT::default().settings(&layer_settings);
+
+        let mut validation_features = vk::ValidationFeaturesEXT::default()
+            .enabled_validation_features(&DebugPrintfConfig::ENABLED_VALIDATION_FEATURES)
+            .disabled_validation_features(config.disabled_validation_features());
+        if layer_settings_supported {
+            validation_features.p_next = &layer_settings_create_info as *const _ as *const c_void;
+        }
+
+        unsafe {
+            Self::new_with_p_next(
+                entry,
+                max_api_version,
+                layer_names,
+                extension_names,
+                config.debug_messenger,
+                &validation_features as *const _ as *const c_void,
+            )
+        }
+    }
+
+    /// `debug_messenger` is chained in front of `p_next`.
+    ///
+    /// # Safety
+    /// `p_next` must be null or point to a valid chain of structs extending
+    /// `vk::InstanceCreateInfo`.
+    unsafe fn new_with_p_next(
+        entry: Arc<Entry>,
+        max_api_version: ApiVersion,
+        layer_names: Vec<CString>,
+        mut extension_names: Vec<CString>,
+        debug_messenger: Option<InstanceDebugMessenger>,
+        p_next: *const c_void,
+    ) -> Result<Self, InstanceError> {
+        if max_api_version < ApiVersion::V1_0 {
+            return Err(InstanceError::InvalidApiVersion(max_api_version));
+        }
+        let loader_api_version =
+            Self::loader_api_version(&entry).map_err(InstanceError::Creation)?;
+        let effective_api_version = max_api_version.min(loader_api_version);
+
+        if debug_messenger.is_some()
+            && !extension_names
+                .iter()
+                .any(|name| name.as_c_str() == vk::EXT_DEBUG_UTILS_NAME)
+        {
+            extension_names.push(vk::EXT_DEBUG_UTILS_NAME.to_owned());
+        }
+
         let layer_name_ptrs: Vec<*const c_char> =
             layer_names.iter().map(|cstring| cstring.as_ptr()).collect();
         let extension_name_ptrs: Vec<*const c_char> = extension_names
@@ -95,12 +290,26 @@ impl Instance {
             .map(|cstring| cstring.as_ptr())
             .collect();
 
-        let appinfo = vk::ApplicationInfo::default().api_version(max_api_version.as_vk_uint());
-        let create_info = vk::InstanceCreateInfo::default()
+        // a 1.0 loader fails instance creation for any other version
+        let appinfo =
+            vk::ApplicationInfo::default().api_version(effective_api_version.as_vk_uint());
+        let mut create_info = vk::InstanceCreateInfo::default()
             .application_info(&appinfo)
             .enabled_layer_names(&layer_name_ptrs)
             .enabled_extension_names(&extension_name_ptrs);
 
+        // also used by the loader for messages during vkDestroyInstance
+        let mut debug_messenger_create_info = debug_messenger
+            .as_ref()
+            .map(InstanceDebugMessenger::create_info);
+        create_info.p_next = match debug_messenger_create_info.as_mut() {
+            Some(debug_messenger_create_info) => {
+                debug_messenger_create_info.p_next = p_next;
+                debug_messenger_create_info as *const _ as *const c_void
+            }
+            None => p_next,
+        };
+
         let instance_inner =
             unsafe { entry.create_instance(&create_info, ALLOCATION_CALLBACK_NONE) }
                 .map_err(InstanceError::Creation)?;
@@ -109,8 +318,11 @@ impl Instance {
             entry,
             inner: instance_inner,
             max_api_version,
+            effective_api_version,
             enabled_extensions: extension_names,
             enabled_layers: layer_names,
+            extension_loaders: ExtensionLoaderCache::default(),
+            ownership: HandleOwnership::Owned,
         })
     }
 
@@ -128,23 +340,82 @@ impl Instance {
 
         let max_api_version = if !create_info.p_application_info.is_null() {
             let api_version_combined = unsafe { *create_info.p_application_info }.api_version;
-            ApiVersion {
-                major: vk::api_version_major(api_version_combined),
-                minor: vk::api_version_minor(api_version_combined),
-            }
+            ApiVersion::from_vk_uint(api_version_combined)
         } else {
             ApiVersion { major: 0, minor: 0 }
         };
+        let loader_api_version = Self::loader_api_version(&entry).unwrap_or(ApiVersion::V1_0);
+        let effective_api_version = max_api_version
+            .max(ApiVersion::V1_0)
+            .min(loader_api_version);
 
         Ok(Self {
             inner: instance_inner,
             max_api_version,
+            effective_api_version,
             entry,
             enabled_extensions,
             enabled_layers,
+            extension_loaders: ExtensionLoaderCache::default(),
+            ownership: HandleOwnership::Owned,
         })
     }
 
+    /// Wraps a `VkInstance` created elsewhere e.g. by OpenXR's `xrCreateVulkanInstanceKHR` or
+    /// existing ash code. With [`HandleOwnership::Owned`] the instance is destroyed on drop.
+    ///
+    /// `max_api_version`, `enabled_extensions` and `enabled_layers` should match what the instance
+    /// was created with as they're used to check support for some functionality.
+    ///
+    /// # Safety
+    /// - `handle` must be a valid `VkInstance` created with `entry`.
+    /// - with [`HandleOwnership::Borrowed`] the instance must outlive `self` and every object
+    ///   created with it through bort.
+    pub unsafe fn from_handle(
+        entry: Arc<Entry>,
+        handle: vk::Instance,
+        max_api_version: ApiVersion,
+        enabled_extensions: Vec<CString>,
+        enabled_layers: Vec<CString>,
+        ownership: HandleOwnership,
+    ) -> Self {
+        let inner = unsafe { ash::Instance::load(entry.static_fn(), handle) };
+
+        let loader_api_version = Self::loader_api_version(&entry).unwrap_or(ApiVersion::V1_0);
+        let effective_api_version = max_api_version
+            .max(ApiVersion::V1_0)
+            .min(loader_api_version);
+
+        Self {
+            inner,
+            max_api_version,
+            effective_api_version,
+            entry,
+            enabled_extensions,
+            enabled_layers,
+            extension_loaders: ExtensionLoaderCache::default(),
+            ownership,
+        }
+    }
+
+    /// The highest instance-level api version supported by the loader. 1.0 loaders don't
+    /// implement `vkEnumerateInstanceVersion`.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkEnumerateInstanceVersion.html>
+    pub fn loader_api_version(entry: &Entry) -> VkResult<ApiVersion> {
+        let api_version = unsafe { entry.try_enumerate_instance_version() }?;
+        Ok(api_version
+            .map(ApiVersion::from_vk_uint)
+            .unwrap_or(ApiVersion::V1_0))
+    }
+
+    /// The api version usable with `physical_device`: the effective instance version clamped to
+    /// the version supported by the device.
+    pub fn physical_device_api_version(&self, physical_device: &PhysicalDevice) -> ApiVersion {
+        self.effective_api_version
+            .min(physical_device.api_version())
+    }
+
     /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkEnumerateInstanceLayerProperties.html>
     pub fn layer_avilable(entry: &Entry, layer_name: CString) -> VkResult<bool> {
         let layer_properties = unsafe { entry.enumerate_instance_layer_properties()? };
@@ -204,7 +475,7 @@ impl Instance {
     pub fn physical_device_features(
         &self,
         physical_device: &PhysicalDevice,
-    ) -> PhysicalDeviceFeatures {
+    ) -> PhysicalDeviceFeatures<'_> {
         PhysicalDeviceFeatures {
             features_1_0: self.physical_device_features_1_0(physical_device),
             features_1_1: self
@@ -216,6 +487,18 @@ impl Instance {
             features_1_3: self
                 .physical_device_features_1_3(physical_device)
                 .unwrap_or_default(),
+            robustness_2: self.physical_device_robustness_2_features(physical_device),
+            pipeline_robustness: self.physical_device_pipeline_robustness_features(physical_device),
+            maintenance_5: self.physical_device_maintenance_5_features(physical_device),
+            maintenance_6: self.physical_device_maintenance_6_features(physical_device),
+            cooperative_matrix: self.physical_device_cooperative_matrix_features(physical_device),
+            attachment_feedback_loop_layout: self
+                .physical_device_attachment_feedback_loop_layout_features(physical_device),
+            attachment_feedback_loop_dynamic_state: self
+                .physical_device_attachment_feedback_loop_dynamic_state_features(physical_device),
+            device_coherent_memory: self
+                .physical_device_device_coherent_memory_features(physical_device),
+            memory_priority: self.physical_device_memory_priority_features(physical_device),
         }
     }
 
@@ -234,8 +517,8 @@ impl Instance {
     pub fn physical_device_features_1_1(
         &self,
         physical_device: &PhysicalDevice,
-    ) -> Option<vk::PhysicalDeviceVulkan11Features> {
-        if self.max_api_version < ApiVersion::V1_1 {
+    ) -> Option<vk::PhysicalDeviceVulkan11Features<'_>> {
+        if self.physical_device_api_version(physical_device) < ApiVersion::V1_1 {
             return None;
         }
 
@@ -253,8 +536,8 @@ impl Instance {
     pub fn physical_device_features_1_2(
         &self,
         physical_device: &PhysicalDevice,
-    ) -> Option<vk::PhysicalDeviceVulkan12Features> {
-        if self.max_api_version < ApiVersion::V1_2 {
+    ) -> Option<vk::PhysicalDeviceVulkan12Features<'_>> {
+        if self.physical_device_api_version(physical_device) < ApiVersion::V1_2 {
             return None;
         }
 
@@ -272,8 +555,8 @@ impl Instance {
     pub fn physical_device_features_1_3(
         &self,
         physical_device: &PhysicalDevice,
-    ) -> Option<vk::PhysicalDeviceVulkan13Features> {
-        if self.max_api_version < ApiVersion::V1_3 {
+    ) -> Option<vk::PhysicalDeviceVulkan13Features<'_>> {
+        if self.physical_device_api_version(physical_device) < ApiVersion::V1_3 {
             return None;
         }
 
@@ -287,6 +570,323 @@ impl Instance {
         Some(features_1_3)
     }
 
+    /// Features struct `T` for a device extension. Returns `None` if api version < 1.1 or
+    /// `extension_name` isn't supported by `physical_device`.
+    pub fn physical_device_extension_features<T>(
+        &self,
+        physical_device: &PhysicalDevice,
+        extension_name: &CStr,
+    ) -> Option<T>
+    where
+        T: vk::ExtendsPhysicalDeviceFeatures2 + Default,
+    {
+        if self.physical_device_api_version(physical_device) < ApiVersion::V1_1
+            || !physical_device.supports_extension(extension_name.to_owned())
+        {
+            return None;
+        }
+
+        let mut extension_features = T::default();
+        let mut features =
+            vk::PhysicalDeviceFeatures2::default().push_next(&mut extension_features);
+        unsafe {
+            self.inner
+                .get_physical_device_features2(physical_device.handle(), &mut features)
+        };
+
+        Some(extension_features)
+    }
+
+    /// Properties struct `T` for a device extension. Returns `None` if api version < 1.1 or
+    /// `extension_name` isn't supported by `physical_device`.
+    pub fn physical_device_extension_properties<T>(
+        &self,
+        physical_device: &PhysicalDevice,
+        extension_name: &CStr,
+    ) -> Option<T>
+    where
+        T: vk::ExtendsPhysicalDeviceProperties2 + Default,
+    {
+        if self.physical_device_api_version(physical_device) < ApiVersion::V1_1
+            || !physical_device.supports_extension(extension_name.to_owned())
+        {
+            return None;
+        }
+
+        let mut extension_properties = T::default();
+        let mut properties =
+            vk::PhysicalDeviceProperties2::default().push_next(&mut extension_properties);
+        unsafe {
+            self.inner
+                .get_physical_device_properties2(physical_device.handle(), &mut properties)
+        };
+
+        Some(extension_properties)
+    }
+
+    /// `VK_EXT_robustness2` features.
+    pub fn physical_device_robustness_2_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceRobustness2FeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_ROBUSTNESS2_NAME)
+    }
+
+    /// `VK_EXT_robustness2` properties e.g. the alignment required for robust buffer access.
+    pub fn physical_device_robustness_2_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceRobustness2PropertiesEXT<'static>> {
+        self.physical_device_extension_properties(physical_device, vk::EXT_ROBUSTNESS2_NAME)
+    }
+
+    /// `VK_EXT_pipeline_robustness` features.
+    pub fn physical_device_pipeline_robustness_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDevicePipelineRobustnessFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_PIPELINE_ROBUSTNESS_NAME)
+    }
+
+    /// `VK_EXT_pipeline_robustness` properties i.e. the default robustness behaviors used when
+    /// a pipeline specifies `vk::PipelineRobustnessBufferBehaviorEXT::DEVICE_DEFAULT`.
+    pub fn physical_device_pipeline_robustness_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDevicePipelineRobustnessPropertiesEXT<'static>> {
+        self.physical_device_extension_properties(physical_device, vk::EXT_PIPELINE_ROBUSTNESS_NAME)
+    }
+
+    /// `VK_EXT_line_rasterization` features.
+    pub fn physical_device_line_rasterization_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceLineRasterizationFeaturesKHR<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_LINE_RASTERIZATION_NAME)
+    }
+
+    /// `VK_EXT_conservative_rasterization` properties e.g. the maximum extra primitive
+    /// overestimation size. This extension has no features struct so `Some` means it's supported.
+    pub fn physical_device_conservative_rasterization_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceConservativeRasterizationPropertiesEXT<'static>> {
+        self.physical_device_extension_properties(
+            physical_device,
+            vk::EXT_CONSERVATIVE_RASTERIZATION_NAME,
+        )
+    }
+
+    /// `VK_EXT_depth_clip_enable` features.
+    pub fn physical_device_depth_clip_enable_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceDepthClipEnableFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_DEPTH_CLIP_ENABLE_NAME)
+    }
+
+    /// `VK_EXT_depth_clip_control` features.
+    pub fn physical_device_depth_clip_control_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceDepthClipControlFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_DEPTH_CLIP_CONTROL_NAME)
+    }
+
+    /// `VK_EXT_depth_bias_control` features.
+    pub fn physical_device_depth_bias_control_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceDepthBiasControlFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_DEPTH_BIAS_CONTROL_NAME)
+    }
+
+    /// `VK_EXT_fragment_density_map` features.
+    pub fn physical_device_fragment_density_map_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceFragmentDensityMapFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_FRAGMENT_DENSITY_MAP_NAME)
+    }
+
+    /// `VK_EXT_fragment_density_map` properties e.g. the range of fragment area each density map
+    /// texel can cover.
+    pub fn physical_device_fragment_density_map_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceFragmentDensityMapPropertiesEXT<'static>> {
+        self.physical_device_extension_properties(
+            physical_device,
+            vk::EXT_FRAGMENT_DENSITY_MAP_NAME,
+        )
+    }
+
+    /// `VK_KHR_ray_tracing_pipeline` properties e.g. the shader group handle size and the
+    /// alignments required by shader binding tables.
+    pub fn physical_device_ray_tracing_pipeline_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceRayTracingPipelinePropertiesKHR<'static>> {
+        self.physical_device_extension_properties(
+            physical_device,
+            vk::KHR_RAY_TRACING_PIPELINE_NAME,
+        )
+    }
+
+    /// `VK_EXT_provoking_vertex` features.
+    pub fn physical_device_provoking_vertex_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceProvokingVertexFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_PROVOKING_VERTEX_NAME)
+    }
+
+    /// `VK_KHR_maintenance5` features.
+    pub fn physical_device_maintenance_5_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMaintenance5FeaturesKHR<'static>> {
+        self.physical_device_extension_features(physical_device, vk::KHR_MAINTENANCE5_NAME)
+    }
+
+    /// `VK_KHR_maintenance5` properties e.g. whether early fragment tests and depth/stencil
+    /// writes behave as expected when combined.
+    pub fn physical_device_maintenance_5_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMaintenance5PropertiesKHR<'static>> {
+        self.physical_device_extension_properties(physical_device, vk::KHR_MAINTENANCE5_NAME)
+    }
+
+    /// `VK_KHR_maintenance6` features.
+    pub fn physical_device_maintenance_6_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMaintenance6FeaturesKHR<'static>> {
+        self.physical_device_extension_features(physical_device, vk::KHR_MAINTENANCE6_NAME)
+    }
+
+    /// `VK_KHR_maintenance6` properties e.g. `maxCombinedImageSamplerDescriptorCount`.
+    pub fn physical_device_maintenance_6_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMaintenance6PropertiesKHR<'static>> {
+        self.physical_device_extension_properties(physical_device, vk::KHR_MAINTENANCE6_NAME)
+    }
+
+    /// `VK_EXT_attachment_feedback_loop_layout` features.
+    pub fn physical_device_attachment_feedback_loop_layout_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT<'static>> {
+        self.physical_device_extension_features(
+            physical_device,
+            vk::EXT_ATTACHMENT_FEEDBACK_LOOP_LAYOUT_NAME,
+        )
+    }
+
+    /// `VK_EXT_attachment_feedback_loop_dynamic_state` features.
+    pub fn physical_device_attachment_feedback_loop_dynamic_state_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT<'static>> {
+        self.physical_device_extension_features(
+            physical_device,
+            vk::EXT_ATTACHMENT_FEEDBACK_LOOP_DYNAMIC_STATE_NAME,
+        )
+    }
+
+    /// `VK_AMD_device_coherent_memory` features.
+    pub fn physical_device_device_coherent_memory_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceCoherentMemoryFeaturesAMD<'static>> {
+        self.physical_device_extension_features(
+            physical_device,
+            vk::AMD_DEVICE_COHERENT_MEMORY_NAME,
+        )
+    }
+
+    /// `VK_EXT_memory_priority` features.
+    pub fn physical_device_memory_priority_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMemoryPriorityFeaturesEXT<'static>> {
+        self.physical_device_extension_features(physical_device, vk::EXT_MEMORY_PRIORITY_NAME)
+    }
+
+    /// `VK_KHR_cooperative_matrix` features.
+    pub fn physical_device_cooperative_matrix_features(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceCooperativeMatrixFeaturesKHR<'static>> {
+        self.physical_device_extension_features(physical_device, vk::KHR_COOPERATIVE_MATRIX_NAME)
+    }
+
+    /// `VK_KHR_cooperative_matrix` properties i.e. the shader stages supporting cooperative
+    /// matrix instructions.
+    pub fn physical_device_cooperative_matrix_properties(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceCooperativeMatrixPropertiesKHR<'static>> {
+        self.physical_device_extension_properties(physical_device, vk::KHR_COOPERATIVE_MATRIX_NAME)
+    }
+
+    /// Supported cooperative matrix configurations from
+    /// `vkGetPhysicalDeviceCooperativeMatrixPropertiesKHR`. Empty if `VK_KHR_cooperative_matrix`
+    /// isn't supported.
+    pub fn physical_device_cooperative_matrix_configurations(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> VkResult<Vec<CooperativeMatrixConfiguration>> {
+        if !physical_device.supports_extension(vk::KHR_COOPERATIVE_MATRIX_NAME.to_owned()) {
+            return Ok(Vec::new());
+        }
+        let cooperative_matrix_fns = self.extension_loader::<khr::cooperative_matrix::Instance>();
+        let properties = unsafe {
+            cooperative_matrix_fns
+                .get_physical_device_cooperative_matrix_properties(physical_device.handle())
+        }?;
+        Ok(properties
+            .iter()
+            .map(CooperativeMatrixConfiguration::from)
+            .collect())
+    }
+
+    /// Format support with 64-bit feature flags, which includes features only expressible with
+    /// `vk::FormatFeatureFlags2` and formats added by later extensions e.g. `VK_KHR_maintenance5`.
+    /// Returns `None` if api version < 1.3 and `VK_KHR_format_feature_flags2` isn't supported.
+    pub fn physical_device_format_properties_3(
+        &self,
+        physical_device: &PhysicalDevice,
+        format: vk::Format,
+    ) -> Option<vk::FormatProperties3<'static>> {
+        let api_version = self.physical_device_api_version(physical_device);
+        let supported = api_version >= ApiVersion::V1_3
+            || (api_version >= ApiVersion::V1_1
+                && physical_device
+                    .supports_extension(vk::KHR_FORMAT_FEATURE_FLAGS2_NAME.to_owned()));
+        if !supported {
+            return None;
+        }
+
+        let mut format_properties_3 = vk::FormatProperties3::default();
+        {
+            let mut format_properties_2 =
+                vk::FormatProperties2::default().push_next(&mut format_properties_3);
+            unsafe {
+                self.inner.get_physical_device_format_properties2(
+                    physical_device.handle(),
+                    format,
+                    &mut format_properties_2,
+                )
+            };
+        }
+        format_properties_3.p_next = ptr::null_mut();
+
+        Some(format_properties_3)
+    }
+
     pub fn enumerate_physical_devices(&self) -> VkResult<Vec<vk::PhysicalDevice>> {
         unsafe { self.inner.enumerate_physical_devices() }
     }
@@ -301,6 +901,7 @@ impl Instance {
     ///
     /// _Note: this function was copied from [ash](https://github.com/ash-rs/ash) to allow for better
     /// dependency control._
+    #[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
     pub fn required_surface_extensions(
         display_handle: RawDisplayHandle,
     ) -> Result<&'static [&'static CStr], InstanceError> {
@@ -321,6 +922,21 @@ impl Instance {
     pub const SURFACE_EXTS_XCB: [&'static CStr; 2] = [surface::NAME, xcb_surface::NAME];
     pub const SURFACE_EXTS_ANDROID: [&'static CStr; 2] = [surface::NAME, android_surface::NAME];
     pub const SURFACE_EXTS_METAL: [&'static CStr; 2] = [surface::NAME, metal_surface::NAME];
+    /// See [`Surface::new_headless`](crate::Surface::new_headless).
+    pub const SURFACE_EXTS_HEADLESS: [&'static CStr; 2] = [surface::NAME, headless_surface::NAME];
+
+    /// Returns a cached ash extension loader e.g. `ash::khr::surface::Instance`, creating it on
+    /// first use. Make sure the extension was enabled when creating the instance.
+    pub fn extension_loader<T: InstanceExtensionLoader>(&self) -> Arc<T> {
+        self.extension_loaders
+            .get_or_load(|| T::load(&self.entry, &self.inner))
+    }
+
+    /// Stops the instance from being destroyed on drop, e.g. when handing an adopted handle back
+    /// to its creator after a failure.
+    pub(crate) fn release_ownership(&mut self) {
+        self.ownership = HandleOwnership::Borrowed;
+    }
 
     // Getters
 
@@ -336,6 +952,14 @@ impl Instance {
         self.max_api_version
     }
 
+    /// `max_api_version` clamped to the version supported by the loader. Use this (or
+    /// [`Self::physical_device_api_version`]) to decide which version-gated functionality is
+    /// available.
+    #[inline]
+    pub fn effective_api_version(&self) -> ApiVersion {
+        self.effective_api_version
+    }
+
     #[inline]
     pub fn entry(&self) -> &Arc<Entry> {
         &self.entry
@@ -350,22 +974,49 @@ impl Instance {
     pub fn enabled_layers(&self) -> &Vec<CString> {
         &self.enabled_layers
     }
+
+    #[inline]
+    pub fn ownership(&self) -> HandleOwnership {
+        self.ownership
+    }
 }
 
 impl Drop for Instance {
     fn drop(&mut self) {
+        if !self.ownership.is_owned() {
+            return;
+        }
         unsafe {
             self.inner.destroy_instance(ALLOCATION_CALLBACK_NONE);
         }
     }
 }
 
+// Helper Functions
+
+/// A `VK_EXT_layer_settings` boolean setting for the validation layer.
+fn layer_setting_bool32<'a>(
+    setting_name: &'a CStr,
+    value: &'a [vk::Bool32; 1],
+) -> vk::LayerSettingEXT<'a> {
+    vk::LayerSettingEXT {
+        p_layer_name: VALIDATION_LAYER_NAME.as_ptr(),
+        p_setting_name: setting_name.as_ptr(),
+        ty: vk::LayerSettingTypeEXT::BOOL32,
+        value_count: 1,
+        p_values: value.as_ptr() as *const c_void,
+        ..Default::default()
+    }
+}
+
 // ~~ Error ~~
 
 #[derive(Debug, Clone)]
 pub enum InstanceError {
     UnsupportedRawDisplayHandle,
     ExtensionsNotPresent(Vec<CString>),
+    /// The requested api version is below 1.0.
+    InvalidApiVersion(ApiVersion),
     Creation(vk::Result),
 }
 
@@ -382,6 +1033,11 @@ impl fmt::Display for InstanceError {
                     extension_names
                 )
             }
+            Self::InvalidApiVersion(api_version) => write!(
+                f,
+                "invalid api version {}.{}. must be at least 1.0",
+                api_version.major, api_version.minor
+            ),
             Self::Creation(e) => {
                 write!(f, "failed to create device {}", e)
             }
@@ -394,6 +1050,7 @@ impl error::Error for InstanceError {
         match self {
             Self::UnsupportedRawDisplayHandle => None,
             Self::ExtensionsNotPresent(_) => None,
+            Self::InvalidApiVersion(_) => None,
             Self::Creation(e) => Some(e),
         }
     }
@@ -405,3 +1062,14 @@ impl error::Error for InstanceError {
 fn api_version_ordering() {
     assert!(ApiVersion::V1_1 < ApiVersion::V1_2);
 }
+
+#[test]
+fn api_version_vk_uint_round_trip() {
+    let api_version = ApiVersion::from_vk_uint(vk::make_api_version(0, 1, 3, 281));
+    assert_eq!(api_version, ApiVersion::V1_3);
+    assert_eq!(
+        ApiVersion::from_vk_uint(ApiVersion::V1_2.as_vk_uint()),
+        ApiVersion::V1_2
+    );
+    assert_eq!(ApiVersion::V1_4.min(ApiVersion::V1_3), ApiVersion::V1_3);
+}
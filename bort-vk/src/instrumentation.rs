@@ -0,0 +1,25 @@
+//! Internal helpers for the optional `tracing` feature. With the feature disabled spans compile to
+//! nothing and their field expressions aren't evaluated.
+
+use ash::vk;
+
+/// Allocations at least this big emit a `tracing` event when the `tracing` feature is enabled.
+pub const TRACING_ALLOCATION_SIZE_THRESHOLD: vk::DeviceSize = 16 * 1024 * 1024;
+
+/// Enters an info level span named `$name` which is exited when the returned guard is dropped.
+macro_rules! trace_span {
+    ($name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        let span_guard = tracing::info_span!(target: "bort_vk", $name $(, $($fields)*)?).entered();
+        #[cfg(not(feature = "tracing"))]
+        let span_guard = $crate::instrumentation::NoSpan;
+        span_guard
+    }};
+}
+
+pub(crate) use trace_span;
+
+/// Stand-in for `tracing::span::EnteredSpan` when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[must_use]
+pub(crate) struct NoSpan;
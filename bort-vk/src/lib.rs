@@ -6,74 +6,252 @@ pub use raw_window_handle_05 as raw_window_handle;
 #[cfg(feature = "raw-window-handle-06")]
 pub use raw_window_handle_06 as raw_window_handle;
 
+mod acceleration_structure;
+mod async_compute;
+mod attachment_feedback_loop;
+mod borrowed_resource;
 mod buffer;
+mod buffer_access;
+mod buffer_upload;
+mod capture;
 mod command_buffer;
 mod command_pool;
 mod common;
+mod compressed_format;
+#[cfg(feature = "compute-passes")]
+mod compute_passes;
+mod cooperative_matrix;
+mod copy_scheduler;
+mod copy_validation;
+mod deadline;
 mod debug_callback;
+mod debug_names;
+mod debug_printf;
+mod deferred_operation;
+mod defrag_scheduler;
+mod deletion_queue;
+mod depth_stencil_layout;
 mod descriptor_layout;
+mod descriptor_layout_cache;
 mod descriptor_pool;
 mod descriptor_set;
+mod descriptor_update_template;
 mod device;
+mod device_recovery;
+mod device_resources;
+mod dynamic_uniform_buffer;
+mod error_recovery;
+mod extension_loader;
 mod fence;
+mod fence_pool;
+mod format_size;
+mod fragment_density_map;
+mod frame_timing;
 mod framebuffer;
+mod framebuffer_cache;
+mod gpu;
+mod gpu_heap;
+#[cfg(feature = "compute-passes")]
+mod gpu_reduce;
+mod handle_audit;
+mod hang_tracer;
 mod image;
 mod image_access;
+#[cfg(feature = "compute-passes")]
+mod image_convert;
 mod image_dimensions;
+mod image_readback;
+mod image_subresource;
 mod image_view;
+mod immediate_context;
+mod index_buffer;
+#[cfg(feature = "compute-passes")]
+mod indirect_cull;
 mod instance;
+mod instrumentation;
+mod linear_image;
+#[cfg(feature = "presentation")]
+mod low_latency;
 mod memory_access;
 mod memory_allocation;
 mod memory_allocator;
+mod memory_choice;
 mod memory_defragmentation;
 mod memory_pool;
+mod object_counters;
+mod opacity_micromap;
+mod pass_scope;
+mod per_frame;
 mod physical_device;
 mod pipeline_access;
 mod pipeline_cache;
+mod pipeline_cache_store;
+#[cfg(feature = "pipeline-compiler")]
+mod pipeline_compiler;
 mod pipeline_compute;
 mod pipeline_graphics;
 mod pipeline_layout;
+mod pipeline_lint;
+mod pipeline_robustness;
+#[cfg(feature = "presentation")]
+mod present_queue;
+#[cfg(feature = "present-thread")]
+mod present_thread;
+mod projection;
+mod protected_memory;
+mod push_constant_writer;
+mod query_pool;
 mod queue;
 mod render_pass;
+mod resource_init;
+mod resource_registry;
 mod sampler;
+mod sampler_cache;
+mod self_test;
 mod semaphore;
+mod shader_binding_table;
 mod shader_module;
+mod shutdown;
+mod submission_graph;
+#[cfg(feature = "presentation")]
 mod surface;
+#[cfg(feature = "presentation")]
 mod swapchain;
+mod sync_validator;
+#[cfg(feature = "texture-streaming")]
+mod texture_array_streamer;
+#[cfg(feature = "ui")]
+mod ui_renderer;
+mod virtual_block;
+mod xr_interop;
+
+/// The most commonly used types. `use bort_vk::prelude::*;`
+pub mod prelude;
+
+/// Requires one of the `raw-window-handle` features.
+#[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
+pub mod windowed;
 
 // so you can access everything from the `bort_vma` namespace instead of typing something like
 // `bort_vma::pipeline_compute::ComputePipeline`
+pub use acceleration_structure::*;
+pub use async_compute::*;
+pub use attachment_feedback_loop::*;
+pub use borrowed_resource::*;
 pub use buffer::*;
+pub use buffer_access::*;
+pub use buffer_upload::*;
+pub use capture::*;
 pub use command_buffer::*;
 pub use command_pool::*;
 pub use common::*;
+pub use compressed_format::*;
+#[cfg(feature = "compute-passes")]
+pub use compute_passes::*;
+pub use cooperative_matrix::*;
+pub use copy_scheduler::*;
+pub use copy_validation::*;
+pub use deadline::*;
 pub use debug_callback::*;
+pub use debug_names::*;
+pub use debug_printf::*;
+pub use deferred_operation::*;
+pub use defrag_scheduler::*;
+pub use deletion_queue::*;
+pub use depth_stencil_layout::*;
 pub use descriptor_layout::*;
+pub use descriptor_layout_cache::*;
 pub use descriptor_pool::*;
 pub use descriptor_set::*;
+pub use descriptor_update_template::*;
 pub use device::*;
+pub use device_recovery::*;
+pub use device_resources::*;
+pub use dynamic_uniform_buffer::*;
+pub use error_recovery::*;
+pub use extension_loader::*;
 pub use fence::*;
+pub use fence_pool::*;
+pub use format_size::*;
+pub use fragment_density_map::*;
+pub use frame_timing::*;
 pub use framebuffer::*;
+pub use framebuffer_cache::*;
+pub use gpu::*;
+pub use gpu_heap::*;
+#[cfg(feature = "compute-passes")]
+pub use gpu_reduce::*;
+pub use handle_audit::*;
+pub use hang_tracer::*;
 pub use image::*;
 pub use image_access::*;
+#[cfg(feature = "compute-passes")]
+pub use image_convert::*;
 pub use image_dimensions::*;
+pub use image_readback::*;
+pub use image_subresource::*;
 pub use image_view::*;
+pub use immediate_context::*;
+pub use index_buffer::*;
+#[cfg(feature = "compute-passes")]
+pub use indirect_cull::*;
 pub use instance::*;
+pub use instrumentation::TRACING_ALLOCATION_SIZE_THRESHOLD;
+pub use linear_image::*;
+#[cfg(feature = "presentation")]
+pub use low_latency::*;
 pub use memory_access::*;
 pub use memory_allocation::*;
 pub use memory_allocator::*;
+pub use memory_choice::*;
 pub use memory_defragmentation::*;
 pub use memory_pool::*;
+pub use object_counters::*;
+pub use opacity_micromap::*;
+pub use pass_scope::*;
+pub use per_frame::*;
 pub use physical_device::*;
 pub use pipeline_access::*;
 pub use pipeline_cache::*;
+pub use pipeline_cache_store::*;
+#[cfg(feature = "pipeline-compiler")]
+pub use pipeline_compiler::*;
 pub use pipeline_compute::*;
 pub use pipeline_graphics::*;
 pub use pipeline_layout::*;
+pub use pipeline_lint::*;
+pub use pipeline_robustness::*;
+#[cfg(feature = "presentation")]
+pub use present_queue::*;
+#[cfg(feature = "present-thread")]
+pub use present_thread::*;
+pub use projection::*;
+pub use protected_memory::*;
+pub use push_constant_writer::*;
+pub use query_pool::*;
 pub use queue::*;
 pub use render_pass::*;
+pub use resource_init::*;
+pub use resource_registry::*;
 pub use sampler::*;
+pub use sampler_cache::*;
+pub use self_test::*;
 pub use semaphore::*;
+pub use shader_binding_table::*;
 pub use shader_module::*;
+pub use shutdown::*;
+pub use submission_graph::*;
+#[cfg(feature = "presentation")]
 pub use surface::*;
+#[cfg(feature = "presentation")]
 pub use swapchain::*;
+pub use sync_validator::*;
+#[cfg(feature = "texture-streaming")]
+pub use texture_array_streamer::*;
+#[cfg(feature = "ui")]
+pub use ui_renderer::*;
+pub use virtual_block::*;
+pub use xr_interop::*;
+
+// allocator statistics types so user code doesn't have to depend on `bort_vma` directly
+pub use bort_vma::{DetailedStatistics, HeapBudget, Statistics, TotalStatistics};
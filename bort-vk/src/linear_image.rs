@@ -0,0 +1,388 @@
+use crate::{mip_level_extent, AllocationAccess, Image, MemoryAllocation, MemoryError};
+use ash::vk;
+#[cfg(feature = "bytemuck")]
+use bytemuck::Pod;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+#[cfg(feature = "bytemuck")]
+use std::mem;
+use std::{error, fmt, ops::Range, slice};
+
+/// A mapped subresource of a host visible `LINEAR` tiling image with row pitch aware access,
+/// e.g. for uploading video frames or CPU side compositing without a staging buffer. The memory
+/// is invalidated when mapped, flushed on drop if anything was written and then unmapped.
+///
+/// Row accessors address the first depth slice of 3D images; the packed copy functions cover all
+/// depth slices. The image must not be in use by the device while the view exists.
+pub struct LinearImageView<'a> {
+    memory_allocation: &'a mut MemoryAllocation,
+    /// Start of the subresource in the mapped allocation.
+    data: *mut u8,
+    layout: vk::SubresourceLayout,
+    extent: vk::Extent3D,
+    written: bool,
+}
+
+impl<'a> LinearImageView<'a> {
+    /// See [`Image::linear_view`].
+    pub fn new(
+        image: &'a mut Image,
+        aspect: vk::ImageAspectFlags,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Result<Self, LinearImageError> {
+        let properties = image.properties();
+        if properties.tiling != vk::ImageTiling::LINEAR {
+            return Err(LinearImageError::NotLinearTiling(properties.tiling));
+        }
+        if mip_level >= properties.mip_levels || array_layer >= properties.dimensions.array_layers()
+        {
+            return Err(LinearImageError::SubresourceOutOfRange {
+                mip_level,
+                array_layer,
+            });
+        }
+        let extent = mip_level_extent(properties.dimensions, mip_level);
+        let layout = image.subresource_layout(aspect, mip_level, array_layer);
+
+        let memory_allocation = image.memory_allocation_mut();
+        let memory_property_flags = memory_allocation.memory_property_flags();
+        if !memory_property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            return Err(LinearImageError::Memory(MemoryError::NotHostVisible {
+                memory_property_flags,
+            }));
+        }
+
+        memory_allocation
+            .invalidate_allocation(layout.offset as usize, layout.size as usize)
+            .map_err(LinearImageError::Memory)?;
+        let mapped_memory =
+            unsafe { memory_allocation.map_memory() }.map_err(LinearImageError::Memory)?;
+        let data = unsafe { mapped_memory.add(layout.offset as usize) };
+
+        Ok(Self {
+            memory_allocation,
+            data,
+            layout,
+            extent,
+            written: false,
+        })
+    }
+
+    /// All bytes of the subresource including row, depth and array padding.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.data, self.layout.size as usize) }
+    }
+
+    /// All bytes of the subresource including row, depth and array padding.
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.written = true;
+        unsafe { slice::from_raw_parts_mut(self.data, self.layout.size as usize) }
+    }
+
+    /// Bytes of row `y` including any padding up to the next row. Panics if `y` is out of range.
+    pub fn row_bytes(&self, y: u32) -> &[u8] {
+        let range = self.padded_row_range(y);
+        &self.as_bytes()[range]
+    }
+
+    /// Bytes of row `y` including any padding up to the next row. Panics if `y` is out of range.
+    pub fn row_bytes_mut(&mut self, y: u32) -> &mut [u8] {
+        let range = self.padded_row_range(y);
+        &mut self.as_bytes_mut()[range]
+    }
+
+    /// The `width` texels of row `y`. `T` must be the size of a texel of the image format e.g.
+    /// `[u8; 4]` for `R8G8B8A8_UNORM`. Panics if `y` is out of range.
+    #[cfg(feature = "bytemuck")]
+    pub fn row<T: Pod>(&self, y: u32) -> &[T] {
+        let range = self.texel_row_range(y, mem::size_of::<T>());
+        bytemuck::cast_slice(&self.as_bytes()[range])
+    }
+
+    /// The `width` texels of row `y`. `T` must be the size of a texel of the image format e.g.
+    /// `[u8; 4]` for `R8G8B8A8_UNORM`. Panics if `y` is out of range.
+    #[cfg(feature = "bytemuck")]
+    pub fn row_mut<T: Pod>(&mut self, y: u32) -> &mut [T] {
+        let range = self.texel_row_range(y, mem::size_of::<T>());
+        bytemuck::cast_slice_mut(&mut self.as_bytes_mut()[range])
+    }
+
+    /// Copies tightly packed texels (rows of `width * texel_size` bytes, then depth slices) into
+    /// the image, skipping the row and depth padding.
+    pub fn write_packed_bytes(
+        &mut self,
+        packed: &[u8],
+        texel_size: usize,
+    ) -> Result<(), LinearImageError> {
+        let layout = self.layout;
+        let extent = self.extent;
+        copy_packed_into_pitched(self.as_bytes_mut(), &layout, extent, packed, texel_size)
+    }
+
+    /// Copies the texels into a tightly packed vec (rows of `width * texel_size` bytes, then
+    /// depth slices), skipping the row and depth padding.
+    pub fn read_packed_bytes(&self, texel_size: usize) -> Result<Vec<u8>, LinearImageError> {
+        copy_pitched_into_packed(self.as_bytes(), &self.layout, self.extent, texel_size)
+    }
+
+    /// Typed version of [`Self::write_packed_bytes`].
+    #[cfg(feature = "bytemuck")]
+    pub fn write_packed<T: Pod>(&mut self, texels: &[T]) -> Result<(), LinearImageError> {
+        self.write_packed_bytes(bytemuck::cast_slice(texels), mem::size_of::<T>())
+    }
+
+    /// Typed version of [`Self::read_packed_bytes`].
+    #[cfg(feature = "bytemuck")]
+    pub fn read_packed<T: Pod>(&self) -> Result<Vec<T>, LinearImageError> {
+        let packed_bytes = self.read_packed_bytes(mem::size_of::<T>())?;
+        Ok(bytemuck::pod_collect_to_vec(&packed_bytes))
+    }
+
+    fn padded_row_range(&self, y: u32) -> Range<usize> {
+        assert!(
+            y < self.extent.height,
+            "row {} out of range for a linear image of height {}",
+            y,
+            self.extent.height
+        );
+        let start = y as usize * self.layout.row_pitch as usize;
+        let end = (start + self.layout.row_pitch as usize).min(self.layout.size as usize);
+        start..end
+    }
+
+    #[cfg(feature = "bytemuck")]
+    fn texel_row_range(&self, y: u32, texel_size: usize) -> Range<usize> {
+        let padded_row_range = self.padded_row_range(y);
+        let row_size = self.extent.width as usize * texel_size;
+        assert!(
+            row_size <= padded_row_range.len(),
+            "texel size {} is too large for the row pitch {}",
+            texel_size,
+            self.layout.row_pitch
+        );
+        padded_row_range.start..padded_row_range.start + row_size
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn layout(&self) -> vk::SubresourceLayout {
+        self.layout
+    }
+
+    #[inline]
+    pub fn row_pitch(&self) -> usize {
+        self.layout.row_pitch as usize
+    }
+
+    #[inline]
+    pub fn depth_pitch(&self) -> usize {
+        self.layout.depth_pitch as usize
+    }
+
+    /// Extent of the mip level.
+    #[inline]
+    pub fn extent(&self) -> vk::Extent3D {
+        self.extent
+    }
+}
+
+impl Drop for LinearImageView<'_> {
+    fn drop(&mut self) {
+        if self.written {
+            let flush_res = self
+                .memory_allocation
+                .flush_allocation(self.layout.offset as usize, self.layout.size as usize);
+            if let Err(e) = flush_res {
+                warn!("failed to flush linear image memory: {}", e);
+            }
+        }
+        unsafe { self.memory_allocation.unmap_memory() };
+    }
+}
+
+// Helper Functions
+
+fn copy_packed_into_pitched(
+    pitched: &mut [u8],
+    layout: &vk::SubresourceLayout,
+    extent: vk::Extent3D,
+    packed: &[u8],
+    texel_size: usize,
+) -> Result<(), LinearImageError> {
+    let row_size = packed_row_size(layout, extent, texel_size)?;
+    let expected_size = row_size * extent.height as usize * extent.depth as usize;
+    if packed.len() != expected_size {
+        return Err(LinearImageError::PackedSizeMismatch {
+            expected_size,
+            packed_size: packed.len(),
+        });
+    }
+
+    for (row_index, packed_row) in packed.chunks_exact(row_size).enumerate() {
+        let start = pitched_row_offset(layout, extent, row_index);
+        pitched[start..start + row_size].copy_from_slice(packed_row);
+    }
+    Ok(())
+}
+
+fn copy_pitched_into_packed(
+    pitched: &[u8],
+    layout: &vk::SubresourceLayout,
+    extent: vk::Extent3D,
+    texel_size: usize,
+) -> Result<Vec<u8>, LinearImageError> {
+    let row_size = packed_row_size(layout, extent, texel_size)?;
+    let row_count = extent.height as usize * extent.depth as usize;
+    let mut packed = Vec::with_capacity(row_size * row_count);
+    for row_index in 0..row_count {
+        let start = pitched_row_offset(layout, extent, row_index);
+        packed.extend_from_slice(&pitched[start..start + row_size]);
+    }
+    Ok(packed)
+}
+
+/// Size of a row of `width` texels, which has to fit in the row pitch.
+fn packed_row_size(
+    layout: &vk::SubresourceLayout,
+    extent: vk::Extent3D,
+    texel_size: usize,
+) -> Result<usize, LinearImageError> {
+    (extent.width as usize)
+        .checked_mul(texel_size)
+        .filter(|&row_size| row_size <= layout.row_pitch as usize)
+        .ok_or(LinearImageError::TexelSizeTooLarge {
+            texel_size,
+            row_pitch: layout.row_pitch,
+        })
+}
+
+/// Offset of the `row_index`th row counting through all depth slices.
+fn pitched_row_offset(
+    layout: &vk::SubresourceLayout,
+    extent: vk::Extent3D,
+    row_index: usize,
+) -> usize {
+    let height = extent.height.max(1) as usize;
+    let z = row_index / height;
+    let y = row_index % height;
+    z * layout.depth_pitch as usize + y * layout.row_pitch as usize
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum LinearImageError {
+    NotLinearTiling(vk::ImageTiling),
+    SubresourceOutOfRange {
+        mip_level: u32,
+        array_layer: u32,
+    },
+    PackedSizeMismatch {
+        expected_size: usize,
+        packed_size: usize,
+    },
+    TexelSizeTooLarge {
+        texel_size: usize,
+        row_pitch: vk::DeviceSize,
+    },
+    Memory(MemoryError),
+}
+
+impl fmt::Display for LinearImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotLinearTiling(tiling) => write!(
+                f,
+                "linear image access requires LINEAR tiling but the image has {:?} tiling",
+                tiling
+            ),
+            Self::SubresourceOutOfRange {
+                mip_level,
+                array_layer,
+            } => write!(
+                f,
+                "mip level {} array layer {} is outside of the image",
+                mip_level, array_layer
+            ),
+            Self::PackedSizeMismatch {
+                expected_size,
+                packed_size,
+            } => write!(
+                f,
+                "packed texel data is {} bytes but the subresource needs {} bytes",
+                packed_size, expected_size
+            ),
+            Self::TexelSizeTooLarge {
+                texel_size,
+                row_pitch,
+            } => write!(
+                f,
+                "a texel size of {} bytes is too large for the row pitch of {} bytes",
+                texel_size, row_pitch
+            ),
+            Self::Memory(e) => write!(f, "failed to access linear image memory: {}", e),
+        }
+    }
+}
+
+impl error::Error for LinearImageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Memory(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn linear_image_packed_copies_skip_padding() {
+    // 2x2x2 image of 2 byte texels with 2 bytes of row padding and 4 bytes of depth padding
+    let layout = vk::SubresourceLayout {
+        offset: 0,
+        size: 28,
+        row_pitch: 6,
+        array_pitch: 0,
+        depth_pitch: 16,
+    };
+    let extent = vk::Extent3D {
+        width: 2,
+        height: 2,
+        depth: 2,
+    };
+    let packed: Vec<u8> = (1..=16).collect();
+
+    let mut pitched = vec![0u8; layout.size as usize];
+    copy_packed_into_pitched(&mut pitched, &layout, extent, &packed, 2).unwrap();
+    assert_eq!(&pitched[0..6], &[1, 2, 3, 4, 0, 0]);
+    assert_eq!(&pitched[6..10], &[5, 6, 7, 8]);
+    assert_eq!(&pitched[16..20], &[9, 10, 11, 12]);
+    assert_eq!(&pitched[22..26], &[13, 14, 15, 16]);
+
+    assert_eq!(
+        copy_pitched_into_packed(&pitched, &layout, extent, 2).unwrap(),
+        packed
+    );
+    assert!(matches!(
+        copy_pitched_into_packed(&pitched, &layout, extent, 4),
+        Err(LinearImageError::TexelSizeTooLarge {
+            texel_size: 4,
+            row_pitch: 6
+        })
+    ));
+    assert!(matches!(
+        copy_pitched_into_packed(&pitched, &layout, extent, usize::MAX),
+        Err(LinearImageError::TexelSizeTooLarge { .. })
+    ));
+
+    assert!(matches!(
+        copy_packed_into_pitched(&mut pitched, &layout, extent, &packed[1..], 2),
+        Err(LinearImageError::PackedSizeMismatch {
+            expected_size: 16,
+            packed_size: 15
+        })
+    ));
+}
@@ -0,0 +1,376 @@
+use crate::{Device, FrameTimingRecorder, Queue, Semaphore, Swapchain};
+use ash::{nv, prelude::VkResult, vk};
+use std::{sync::Arc, time::Duration};
+
+/// `VK_NV_low_latency2` sleep mode settings. See [`LowLatency::set_sleep_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySleepMode {
+    /// Enables the driver's latency reduction i.e. [`LowLatency::sleep`] delays the start of the
+    /// frame so that input is sampled as late as possible.
+    pub low_latency_mode: bool,
+    /// Asks the driver to keep clocks high to reduce latency at the cost of power.
+    pub low_latency_boost: bool,
+    /// Minimum time between frames in microseconds (a frame rate limit). 0 for no limit.
+    pub minimum_interval_us: u32,
+}
+
+impl Default for LatencySleepMode {
+    fn default() -> Self {
+        Self {
+            low_latency_mode: true,
+            low_latency_boost: false,
+            minimum_interval_us: 0,
+        }
+    }
+}
+
+impl LatencySleepMode {
+    pub fn sleep_mode_info(&self) -> vk::LatencySleepModeInfoNV<'static> {
+        vk::LatencySleepModeInfoNV::default()
+            .low_latency_mode(self.low_latency_mode)
+            .low_latency_boost(self.low_latency_boost)
+            .minimum_interval_us(self.minimum_interval_us)
+    }
+}
+
+/// Driver reported timestamps (in microseconds) for a recent frame. See
+/// [`LowLatency::latency_timings`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyTimings {
+    pub present_id: u64,
+    pub input_sample_time_us: u64,
+    pub sim_start_time_us: u64,
+    pub sim_end_time_us: u64,
+    pub render_submit_start_time_us: u64,
+    pub render_submit_end_time_us: u64,
+    pub present_start_time_us: u64,
+    pub present_end_time_us: u64,
+    pub driver_start_time_us: u64,
+    pub driver_end_time_us: u64,
+    pub os_render_queue_start_time_us: u64,
+    pub os_render_queue_end_time_us: u64,
+    pub gpu_render_start_time_us: u64,
+    pub gpu_render_end_time_us: u64,
+}
+
+impl LatencyTimings {
+    /// Time from sampling input to the end of GPU rendering. `None` if either timestamp is
+    /// missing (e.g. the [`vk::LatencyMarkerNV::INPUT_SAMPLE`] marker wasn't set).
+    pub fn input_to_render_end(&self) -> Option<Duration> {
+        duration_between_us(self.input_sample_time_us, self.gpu_render_end_time_us)
+    }
+
+    /// Time from the start of simulation to the end of GPU rendering.
+    pub fn sim_start_to_render_end(&self) -> Option<Duration> {
+        duration_between_us(self.sim_start_time_us, self.gpu_render_end_time_us)
+    }
+
+    /// Time the GPU spent rendering the frame.
+    pub fn gpu_render_time(&self) -> Option<Duration> {
+        duration_between_us(self.gpu_render_start_time_us, self.gpu_render_end_time_us)
+    }
+}
+
+impl From<&vk::LatencyTimingsFrameReportNV<'_>> for LatencyTimings {
+    fn from(report: &vk::LatencyTimingsFrameReportNV<'_>) -> Self {
+        Self {
+            present_id: report.present_id,
+            input_sample_time_us: report.input_sample_time_us,
+            sim_start_time_us: report.sim_start_time_us,
+            sim_end_time_us: report.sim_end_time_us,
+            render_submit_start_time_us: report.render_submit_start_time_us,
+            render_submit_end_time_us: report.render_submit_end_time_us,
+            present_start_time_us: report.present_start_time_us,
+            present_end_time_us: report.present_end_time_us,
+            driver_start_time_us: report.driver_start_time_us,
+            driver_end_time_us: report.driver_end_time_us,
+            os_render_queue_start_time_us: report.os_render_queue_start_time_us,
+            os_render_queue_end_time_us: report.os_render_queue_end_time_us,
+            gpu_render_start_time_us: report.gpu_render_start_time_us,
+            gpu_render_end_time_us: report.gpu_render_end_time_us,
+        }
+    }
+}
+
+/// Input latency reduction with `VK_NV_low_latency2`.
+///
+/// Requires the `VK_NV_low_latency2` and `VK_KHR_present_id` device extensions, the `presentId`
+/// feature, timeline semaphores and a swapchain created with
+/// [`SwapchainProperties::low_latency_mode`](crate::SwapchainProperties::low_latency_mode).
+///
+/// [`Self::begin_frame`] drives a frame together with a [`FrameTimingRecorder`]: it sleeps, begins
+/// the recorder's frame and returns a [`LowLatencyFrame`] which sets the markers below and records
+/// the matching timestamps. To do it by hand instead:
+/// 1. [`Self::sleep`] before sampling input
+/// 2. [`Self::set_marker`] with `INPUT_SAMPLE` and `SIMULATION_START`/`SIMULATION_END` around
+///    game logic
+/// 3. `RENDERSUBMIT_START`/`RENDERSUBMIT_END` around command recording and submission, chaining
+///    [`Self::submission_present_id`] to the submit info
+/// 4. `PRESENT_START`/`PRESENT_END` around present, chaining a `vk::PresentIdKHR` with the same id
+///    to the present info
+///
+/// The sleep mode is per swapchain so call [`Self::set_sleep_mode`] again after recreating the
+/// swapchain.
+///
+/// Note: `VK_AMD_anti_lag` isn't available in the ash version used by this crate.
+pub struct LowLatency {
+    loader: Arc<nv::low_latency2::Device>,
+    sleep_semaphore: Semaphore,
+    sleep_value: u64,
+}
+
+impl LowLatency {
+    /// Creates the timeline semaphore signalled by the driver when a frame should start.
+    pub fn new(device: Arc<Device>) -> VkResult<Self> {
+        let loader = device.extension_loader::<nv::low_latency2::Device>();
+        let sleep_semaphore = Semaphore::new_timeline(device, 0)?;
+        Ok(Self {
+            loader,
+            sleep_semaphore,
+            sleep_value: 0,
+        })
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkSetLatencySleepModeNV.html>
+    pub fn set_sleep_mode(
+        &self,
+        swapchain: &Swapchain,
+        sleep_mode: LatencySleepMode,
+    ) -> VkResult<()> {
+        debug_assert!(
+            swapchain.properties().low_latency_mode,
+            "swapchain wasn't created with low latency mode enabled"
+        );
+        let sleep_mode_info = sleep_mode.sleep_mode_info();
+        unsafe {
+            self.loader
+                .set_latency_sleep_mode(swapchain.handle(), Some(&sleep_mode_info))
+        }
+    }
+
+    /// Blocks until the driver decides the next frame should start, or `timeout_nanoseconds` has
+    /// passed. Call this before sampling input each frame.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkLatencySleepNV.html>
+    pub fn sleep(&mut self, swapchain: &Swapchain, timeout_nanoseconds: u64) -> VkResult<()> {
+        self.sleep_value += 1;
+        let sleep_info = vk::LatencySleepInfoNV::default()
+            .signal_semaphore(self.sleep_semaphore.handle())
+            .value(self.sleep_value);
+        unsafe { self.loader.latency_sleep(swapchain.handle(), &sleep_info) }?;
+        self.sleep_semaphore
+            .wait_value(self.sleep_value, timeout_nanoseconds)
+    }
+
+    /// [`Self::sleep`]s until the driver wants the next frame to start, then begins a frame on
+    /// `frame_timing`. Use the returned frame to set the markers of each stage.
+    pub fn begin_frame<'a>(
+        &'a mut self,
+        swapchain: &'a Swapchain,
+        frame_timing: &mut FrameTimingRecorder,
+        timeout_nanoseconds: u64,
+    ) -> VkResult<LowLatencyFrame<'a>> {
+        self.sleep(swapchain, timeout_nanoseconds)?;
+        let frame_id = frame_timing.begin_frame();
+        Ok(LowLatencyFrame {
+            low_latency: self,
+            swapchain,
+            frame_id,
+        })
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkSetLatencyMarkerNV.html>
+    pub fn set_marker(&self, swapchain: &Swapchain, present_id: u64, marker: vk::LatencyMarkerNV) {
+        let marker_info = vk::SetLatencyMarkerInfoNV::default()
+            .present_id(present_id)
+            .marker(marker);
+        unsafe {
+            self.loader
+                .set_latency_marker(swapchain.handle(), &marker_info)
+        };
+    }
+
+    /// Timings of the most recent frames which had markers set (up to 64 frames).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetLatencyTimingsNV.html>
+    pub fn latency_timings(&self, swapchain: &Swapchain) -> Vec<LatencyTimings> {
+        let mut marker_info = vk::GetLatencyMarkerInfoNV::default();
+        unsafe {
+            self.loader
+                .get_latency_timings(swapchain.handle(), &mut marker_info)
+        };
+
+        let mut reports =
+            vec![vk::LatencyTimingsFrameReportNV::default(); marker_info.timing_count as usize];
+        let mut marker_info = vk::GetLatencyMarkerInfoNV::default().timings(&mut reports);
+        unsafe {
+            self.loader
+                .get_latency_timings(swapchain.handle(), &mut marker_info)
+        };
+        let timing_count = marker_info.timing_count as usize;
+
+        reports[..timing_count]
+            .iter()
+            .map(LatencyTimings::from)
+            .collect()
+    }
+
+    /// Tells the driver that work submitted to `queue` is outside of the frame's render/present
+    /// path (e.g. async loading) so it's ignored by latency reduction.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkQueueNotifyOutOfBandNV.html>
+    pub fn notify_out_of_band(&self, queue: &Queue, queue_type: vk::OutOfBandQueueTypeNV) {
+        let queue_type_info = vk::OutOfBandQueueTypeInfoNV::default().queue_type(queue_type);
+        let _queue_sync = queue.lock_external_sync();
+        unsafe {
+            self.loader
+                .queue_notify_out_of_band(queue.handle(), &queue_type_info)
+        };
+    }
+
+    /// Chain this to the `vk::SubmitInfo` of the frame's render submission so the driver can
+    /// associate it with `present_id`.
+    #[inline]
+    pub fn submission_present_id(present_id: u64) -> vk::LatencySubmissionPresentIdNV<'static> {
+        vk::LatencySubmissionPresentIdNV::default().present_id(present_id)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn sleep_semaphore(&self) -> &Semaphore {
+        &self.sleep_semaphore
+    }
+}
+
+/// A frame begun with [`LowLatency::begin_frame`]. Each stage sets its `VK_NV_low_latency2`
+/// markers and records the matching [`FrameTimingRecorder`] timestamp, so the driver's
+/// [`LatencyTimings`] line up with the recorder's frames via [`Self::present_id`].
+pub struct LowLatencyFrame<'a> {
+    low_latency: &'a LowLatency,
+    swapchain: &'a Swapchain,
+    frame_id: u64,
+}
+
+impl LowLatencyFrame<'_> {
+    /// Sets the `INPUT_SAMPLE` marker. Call right before reading input.
+    pub fn sample_input(&self) {
+        self.set_marker(vk::LatencyMarkerNV::INPUT_SAMPLE);
+    }
+
+    /// Runs `simulate` (game logic) between the `SIMULATION_START` and `SIMULATION_END` markers.
+    pub fn simulate<R>(&self, simulate: impl FnOnce() -> R) -> R {
+        self.set_marker(vk::LatencyMarkerNV::SIMULATION_START);
+        let res = simulate();
+        self.set_marker(vk::LatencyMarkerNV::SIMULATION_END);
+        res
+    }
+
+    /// Runs `record_and_submit` between the `RENDERSUBMIT_START` and `RENDERSUBMIT_END` markers
+    /// and records the submit time. Chain the `vk::LatencySubmissionPresentIdNV` passed to it to
+    /// the frame's `vk::SubmitInfo`.
+    pub fn render_submit<R>(
+        &self,
+        frame_timing: &mut FrameTimingRecorder,
+        record_and_submit: impl FnOnce(&mut vk::LatencySubmissionPresentIdNV<'static>) -> R,
+    ) -> R {
+        self.set_marker(vk::LatencyMarkerNV::RENDERSUBMIT_START);
+        let mut submission_present_id = LowLatency::submission_present_id(self.present_id());
+        let res = record_and_submit(&mut submission_present_id);
+        self.set_marker(vk::LatencyMarkerNV::RENDERSUBMIT_END);
+        frame_timing.record_submit(self.frame_id);
+        res
+    }
+
+    /// Presents with [`Swapchain::queue_present`] between the `PRESENT_START` and `PRESENT_END`
+    /// markers, chaining a `vk::PresentIdKHR` with [`Self::present_id`], and records the present
+    /// time. `present_info` must only reference the frame's swapchain.
+    pub fn present(
+        &self,
+        frame_timing: &mut FrameTimingRecorder,
+        queue: &Queue,
+        present_info: &vk::PresentInfoKHR,
+    ) -> VkResult<bool> {
+        self.set_marker(vk::LatencyMarkerNV::PRESENT_START);
+        let present_ids = [self.present_id()];
+        let mut present_id_info = vk::PresentIdKHR::default().present_ids(&present_ids);
+        let present_info = present_info.push_next(&mut present_id_info);
+        let res = self.swapchain.queue_present(queue, &present_info);
+        self.set_marker(vk::LatencyMarkerNV::PRESENT_END);
+        frame_timing.record_present(self.frame_id);
+        res
+    }
+
+    fn set_marker(&self, marker: vk::LatencyMarkerNV) {
+        self.low_latency
+            .set_marker(self.swapchain, self.present_id(), marker);
+    }
+
+    // Getters
+
+    /// The [`FrameTimingRecorder`] frame id.
+    #[inline]
+    pub fn frame_id(&self) -> u64 {
+        self.frame_id
+    }
+
+    /// The present id used for the markers, submission and present. Matches
+    /// [`LatencyTimings::present_id`].
+    #[inline]
+    pub fn present_id(&self) -> u64 {
+        present_id_for_frame(self.frame_id)
+    }
+}
+
+// Helper Functions
+
+/// Present ids must be non-zero but the first [`FrameTimingRecorder`] frame id is 0.
+#[inline]
+fn present_id_for_frame(frame_id: u64) -> u64 {
+    frame_id + 1
+}
+
+fn duration_between_us(start_us: u64, end_us: u64) -> Option<Duration> {
+    if start_us == 0 || end_us == 0 {
+        return None;
+    }
+    end_us.checked_sub(start_us).map(Duration::from_micros)
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn latency_timings_durations() {
+    let report = vk::LatencyTimingsFrameReportNV {
+        present_id: 4,
+        input_sample_time_us: 1_000,
+        sim_start_time_us: 1_100,
+        gpu_render_start_time_us: 3_000,
+        gpu_render_end_time_us: 9_000,
+        ..Default::default()
+    };
+    let timings = LatencyTimings::from(&report);
+    assert_eq!(timings.present_id, 4);
+    assert_eq!(
+        timings.input_to_render_end(),
+        Some(Duration::from_micros(8_000))
+    );
+    assert_eq!(
+        timings.gpu_render_time(),
+        Some(Duration::from_micros(6_000))
+    );
+
+    let missing_input = LatencyTimings {
+        input_sample_time_us: 0,
+        ..timings
+    };
+    assert_eq!(missing_input.input_to_render_end(), None);
+}
+
+#[test]
+fn low_latency_present_ids_are_non_zero() {
+    let mut frame_timing = FrameTimingRecorder::new(4);
+    let frame_id = frame_timing.begin_frame();
+    assert_eq!(frame_id, 0);
+    assert_eq!(present_id_for_frame(frame_id), 1);
+}
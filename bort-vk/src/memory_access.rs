@@ -1,4 +1,4 @@
-use crate::{Device, MemoryAllocation, MemoryAllocator, MemoryError};
+use crate::{AllocationError, Device, MemoryAllocation, MemoryAllocator, MemoryError};
 use ash::{prelude::VkResult, vk};
 use bort_vma::{ffi, AllocationCreateInfo};
 #[cfg(feature = "bytemuck")]
@@ -230,7 +230,7 @@ pub trait AllocatorAccess: Send + Sync {
         &self,
         buffer_info: &ash::vk::BufferCreateInfo,
         create_info: &AllocationCreateInfo,
-    ) -> VkResult<(ash::vk::Buffer, ffi::VmaAllocation)> {
+    ) -> Result<(ash::vk::Buffer, ffi::VmaAllocation), AllocationError> {
         let mut create_info: ffi::VmaAllocationCreateInfo = create_info.into();
         create_info.pool = self.memory_pool_handle();
         let mut buffer = vk::Buffer::null();
@@ -243,7 +243,11 @@ pub trait AllocatorAccess: Send + Sync {
             &mut allocation_handle,
             std::ptr::null_mut(),
         )
-        .result()?;
+        .result()
+        .map_err(|e| {
+            self.memory_allocator()
+                .on_allocation_failure(e, || format!("buffer of {} bytes", buffer_info.size))
+        })?;
 
         Ok((buffer, allocation_handle))
     }
@@ -258,7 +262,7 @@ pub trait AllocatorAccess: Send + Sync {
         buffer_info: &ash::vk::BufferCreateInfo,
         create_info: &AllocationCreateInfo,
         min_alignment: vk::DeviceSize,
-    ) -> VkResult<(ash::vk::Buffer, ffi::VmaAllocation)> {
+    ) -> Result<(ash::vk::Buffer, ffi::VmaAllocation), AllocationError> {
         let mut create_info: ffi::VmaAllocationCreateInfo = create_info.into();
         create_info.pool = self.memory_pool_handle();
         let mut buffer = vk::Buffer::null();
@@ -272,7 +276,11 @@ pub trait AllocatorAccess: Send + Sync {
             &mut allocation_handle,
             std::ptr::null_mut(),
         )
-        .result()?;
+        .result()
+        .map_err(|e| {
+            self.memory_allocator()
+                .on_allocation_failure(e, || format!("buffer of {} bytes", buffer_info.size))
+        })?;
 
         Ok((buffer, allocation_handle))
     }
@@ -298,7 +306,7 @@ pub trait AllocatorAccess: Send + Sync {
         &self,
         image_info: &ash::vk::ImageCreateInfo,
         create_info: &AllocationCreateInfo,
-    ) -> VkResult<(ash::vk::Image, ffi::VmaAllocation)> {
+    ) -> Result<(ash::vk::Image, ffi::VmaAllocation), AllocationError> {
         let mut create_info: ffi::VmaAllocationCreateInfo = create_info.into();
         create_info.pool = self.memory_pool_handle();
         let mut image = vk::Image::null();
@@ -311,7 +319,20 @@ pub trait AllocatorAccess: Send + Sync {
             &mut allocation_handle,
             std::ptr::null_mut(),
         )
-        .result()?;
+        .result()
+        .map_err(|e| {
+            self.memory_allocator().on_allocation_failure(e, || {
+                format!(
+                    "image {:?} {}x{}x{} with {} mip levels and {} layers",
+                    image_info.format,
+                    image_info.extent.width,
+                    image_info.extent.height,
+                    image_info.extent.depth,
+                    image_info.mip_levels,
+                    image_info.array_layers
+                )
+            })
+        })?;
 
         Ok((image, allocation_handle))
     }
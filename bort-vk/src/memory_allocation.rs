@@ -1,9 +1,10 @@
 use crate::{device::Device, AllocatorAccess};
 use ash::vk;
-use bort_vma::{ffi, AllocationCreateInfo};
+use bort_vma::{ffi, Allocation, AllocationCreateFlags, AllocationCreateInfo};
 #[cfg(feature = "bytemuck")]
 use bytemuck::{NoUninit, Pod, PodCastError};
-use std::{error, fmt, mem, ptr, sync::Arc};
+use log::warn;
+use std::{error, ffi::CStr, fmt, mem, ptr, sync::Arc};
 
 // ~~ Memory Allocation ~~
 
@@ -53,6 +54,18 @@ impl MemoryAllocation {
         debug_assert!(memory_info.memory_type < physical_device_mem_props.memory_type_count);
         let memory_type = physical_device_mem_props.memory_types[memory_info.memory_type as usize];
 
+        #[cfg(feature = "tracing")]
+        if size >= crate::TRACING_ALLOCATION_SIZE_THRESHOLD {
+            tracing::info!(
+                target: "bort_vk",
+                size,
+                memory_type_index = memory_info.memory_type,
+                property_flags = ?memory_type.property_flags,
+                name = ?memory_info.name,
+                "large memory allocation"
+            );
+        }
+
         Self {
             handle,
             memory_type,
@@ -61,6 +74,18 @@ impl MemoryAllocation {
         }
     }
 
+    /// Stands in for memory that isn't managed by vma e.g. for
+    /// [`Image::from_raw`](crate::Image::from_raw). The null handle is ignored by
+    /// `vmaDestroyImage`/`vmaDestroyBuffer`.
+    pub(crate) fn null(allocator_access: Arc<dyn AllocatorAccess>) -> Self {
+        Self {
+            handle: ptr::null_mut(),
+            memory_type: vk::MemoryType::default(),
+            size: 0,
+            allocator_access,
+        }
+    }
+
     #[cfg(feature = "bytemuck")]
     pub fn write_into_bytes<T>(
         &mut self,
@@ -162,6 +187,10 @@ impl MemoryAllocation {
         flush_res
     }
 
+    /// Reads `element_count` elements from this memory allocation. Will invalidate if memory
+    /// isn't host coherent.
+    ///
+    /// If memory wasn't created with `vk::MemoryPropertyFlags::HOST_VISIBLE` this will fail.
     #[cfg(feature = "bytemuck")]
     pub fn read_vec<T>(
         &mut self,
@@ -178,6 +207,11 @@ impl MemoryAllocation {
         let offset_mapped_memory: *mut T =
             unsafe { self.map_memory_with_offset_unchecked(allocation_offset)? };
 
+        if let Err(e) = self.invalidate_allocation(allocation_offset, data_size) {
+            unsafe { self.unmap_memory() };
+            return Err(e);
+        }
+
         let mut output_vec = Vec::<T>::new();
         output_vec.resize_with(element_count, T::zeroed);
         let output_vec_ptr = output_vec.as_mut_ptr();
@@ -188,7 +222,7 @@ impl MemoryAllocation {
         Ok(output_vec)
     }
 
-    /// Writes `data` to this memory allocation. Will flush if memory isn't host coherent.
+    /// Reads a `T` from this memory allocation. Will invalidate if memory isn't host coherent.
     ///
     /// If memory wasn't created with `vk::MemoryPropertyFlags::HOST_VISIBLE` this will fail.
     pub fn read_struct<T>(&mut self, allocation_offset: usize) -> Result<T, MemoryError> {
@@ -198,6 +232,11 @@ impl MemoryAllocation {
         let offset_mapped_memory: *mut T =
             unsafe { self.map_memory_with_offset_unchecked(allocation_offset)? };
 
+        if let Err(e) = self.invalidate_allocation(allocation_offset, data_size) {
+            unsafe { self.unmap_memory() };
+            return Err(e);
+        }
+
         let read_data = unsafe { ptr::read::<T>(offset_mapped_memory) };
 
         unsafe { self.unmap_memory() };
@@ -209,6 +248,17 @@ impl MemoryAllocation {
         data_size: usize,
         allocation_offset: usize,
     ) -> Result<(), MemoryError> {
+        let memory_property_flags = self.memory_property_flags();
+        if !memory_property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+            warn!(
+                "attempted host access of memory allocation without HOST_VISIBLE memory properties: {:?}",
+                memory_property_flags
+            );
+            return Err(MemoryError::NotHostVisible {
+                memory_property_flags,
+            });
+        }
+
         let allocation_size = self.size as usize; // allocation size won't be anywhere near the max of usize/isize despite being u64
         let allocation_write_size = allocation_size.checked_sub(allocation_offset).ok_or(
             MemoryError::AllocationOffsetTooBig {
@@ -252,6 +302,11 @@ impl MemoryAllocation {
         &mut self,
         allocation_offset: usize,
     ) -> Result<*mut T, MemoryError> {
+        debug_assert!(
+            self.memory_property_flags()
+                .contains(vk::MemoryPropertyFlags::HOST_VISIBLE),
+            "mapping memory that isn't HOST_VISIBLE"
+        );
         let mapped_memory = unsafe { self.map_memory() }?;
         #[allow(clippy::ptr_offset_with_cast)] // want the interface to be unsigned because negative
         // offsets don't make sense when offsetting from the start of the allocation and if the offset
@@ -275,6 +330,42 @@ impl MemoryAllocation {
             .map_err(MemoryError::Flushing)
     }
 
+    /// Invalidates allocated memory so device writes are visible to the host. Note that the VMA
+    /// function only runs is the memory is host visible and isn't host coherent.
+    #[inline]
+    pub fn invalidate_allocation(
+        &mut self,
+        allocation_offset: usize,
+        data_size: usize,
+    ) -> Result<(), MemoryError> {
+        self.allocator_access
+            .memory_allocator()
+            .vma_invalidate_allocation(self.handle, allocation_offset, data_size)
+            .map_err(MemoryError::Invalidating)
+    }
+
+    /// Returns true if host writes need to be flushed and device writes need to be invalidated
+    /// (i.e. the memory is host visible but not host coherent).
+    #[inline]
+    pub fn requires_flush(&self) -> bool {
+        let memory_property_flags = self.memory_property_flags();
+        memory_property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+            && !memory_property_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+    }
+
+    /// Sets the name of the allocation, shown in `MemoryAllocator::build_stats_json` and
+    /// debugging tools. See [`bort_vma::Allocation::set_name`].
+    pub fn set_name(&mut self, name: &str) {
+        let allocator_handle = self.allocator_access.memory_allocator().handle();
+        unsafe { Allocation(self.handle).set_name(allocator_handle, name) }
+    }
+
+    /// Sets custom user data for this allocation. See [`bort_vma::Allocation::set_user_data`].
+    pub fn set_user_data(&mut self, user_data: u64) {
+        let allocator_handle = self.allocator_access.memory_allocator().handle();
+        unsafe { Allocation(self.handle).set_user_data(allocator_handle, user_data) }
+    }
+
     // Getters
 
     /// Access the `bort_vma::Allocation` handle that `self` contains.
@@ -288,12 +379,44 @@ impl MemoryAllocation {
         self.memory_type
     }
 
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
     /// Returns self as a dynamic allocation type.
     #[inline]
     pub fn allocator_access(&self) -> &Arc<dyn AllocatorAccess> {
         &self.allocator_access
     }
 
+    /// The name set with [`Self::set_name`], if any.
+    pub fn name(&self) -> Option<String> {
+        let allocator_handle = self.allocator_access.memory_allocator().handle();
+        unsafe { Allocation(self.handle).name(allocator_handle) }
+    }
+
+    /// The user data set with [`Self::set_user_data`]. Defaults to 0.
+    pub fn user_data(&self) -> u64 {
+        let allocator_handle = self.allocator_access.memory_allocator().handle();
+        unsafe { Allocation(self.handle).user_data(allocator_handle) }
+    }
+
+    /// Current parameters of the allocation. Note that the memory and offset can change after
+    /// defragmentation.
+    pub fn allocation_info(&self) -> AllocationInfo {
+        self.allocator_access
+            .memory_allocator()
+            .vma_get_allocation_info(self.handle)
+    }
+
+    /// The tag set with `MemoryAllocator::set_allocation_tag`, if any.
+    pub fn tag(&self) -> Option<&'static str> {
+        self.allocator_access
+            .memory_allocator()
+            .allocation_tag(self.handle)
+    }
+
     #[inline]
     pub fn memory_property_flags(&self) -> vk::MemoryPropertyFlags {
         self.memory_type.property_flags
@@ -346,7 +469,9 @@ pub struct AllocationInfo {
     /// Custom general-purpose pointer that was passed as VmaAllocationCreateInfo::pUserData or set using vmaSetAllocationUserData().
     ///
     /// It can change after call to vmaSetAllocationUserData() for this allocation.
-    pub user_data: usize,
+    pub user_data: u64,
+    /// Custom allocation name that was set with vmaSetAllocationName().
+    pub name: Option<String>,
 }
 
 impl From<&ffi::VmaAllocationInfo> for AllocationInfo {
@@ -357,7 +482,16 @@ impl From<&ffi::VmaAllocationInfo> for AllocationInfo {
             offset: info.offset,
             size: info.size,
             mapped_data: info.pMappedData,
-            user_data: info.pUserData as _,
+            user_data: info.pUserData as usize as u64,
+            name: if info.pName.is_null() {
+                None
+            } else {
+                Some(
+                    unsafe { CStr::from_ptr(info.pName) }
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            },
         }
     }
 }
@@ -394,6 +528,52 @@ pub fn allocation_info_cpu_accessible() -> AllocationCreateInfo {
     )
 }
 
+/// Same as [`allocation_info_cpu_accessible`] but the memory stays persistently mapped
+/// (`AllocationCreateFlags::MAPPED`). Good for memory written by the cpu every frame e.g.
+/// uniform and staging buffers.
+pub fn allocation_info_cpu_accessible_mapped() -> AllocationCreateInfo {
+    AllocationCreateInfo {
+        flags: AllocationCreateFlags::MAPPED,
+        ..allocation_info_cpu_accessible()
+    }
+}
+
+/// For allocating memory only accessed by the gpu. Prefers DEVICE_LOCAL memory but doesn't
+/// require it. Good for vertex, index and storage buffers written via transfer commands.
+pub fn allocation_info_device_local() -> AllocationCreateInfo {
+    allocation_info_from_flags(
+        vk::MemoryPropertyFlags::empty(),
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )
+}
+
+/// For allocating protected memory (vulkan 1.1 `protectedMemory` feature) for resources created
+/// with a `PROTECTED` flag e.g. [`BufferProperties::new_protected`](crate::BufferProperties::new_protected).
+/// Protected memory is never host visible.
+pub fn allocation_info_protected() -> AllocationCreateInfo {
+    allocation_info_from_flags(
+        vk::MemoryPropertyFlags::PROTECTED,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )
+}
+
+/// For debugging gpu crashes with `VK_AMD_device_coherent_memory`: host visible memory which the
+/// device writes to without caching (`DEVICE_COHERENT_AMD`, preferably `DEVICE_UNCACHED_AMD`) so
+/// that e.g. buffer markers are visible to the cpu even if the device hangs right after writing
+/// them. Persistently mapped. Requires
+/// [`PhysicalDeviceFeatures::with_device_coherent_memory`](crate::PhysicalDeviceFeatures::with_device_coherent_memory)
+/// otherwise allocation fails. Much slower than regular memory so don't use it outside debugging.
+pub fn allocation_info_device_coherent() -> AllocationCreateInfo {
+    AllocationCreateInfo {
+        flags: AllocationCreateFlags::MAPPED,
+        required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT
+            | vk::MemoryPropertyFlags::DEVICE_COHERENT_AMD,
+        preferred_flags: vk::MemoryPropertyFlags::DEVICE_UNCACHED_AMD,
+        ..Default::default()
+    }
+}
+
 // ~~ Memory Error ~~
 
 #[derive(Debug, Clone)]
@@ -409,6 +589,10 @@ pub enum MemoryError {
         allocation_offset: usize,
     },
     Flushing(vk::Result),
+    Invalidating(vk::Result),
+    NotHostVisible {
+        memory_property_flags: vk::MemoryPropertyFlags,
+    },
     #[cfg(feature = "bytemuck")]
     PodCastError(PodCastError),
 }
@@ -434,6 +618,14 @@ impl fmt::Display for MemoryError {
                 allocation_offset, allocation_size
             ),
             Self::Flushing(e) => write!(f, "failed to flush memory: {}", e),
+            Self::Invalidating(e) => write!(f, "failed to invalidate memory: {}", e),
+            Self::NotHostVisible {
+                memory_property_flags,
+            } => write!(
+                f,
+                "memory isn't HOST_VISIBLE so it can't be accessed by the host. memory properties = {:?}",
+                memory_property_flags
+            ),
             #[cfg(feature = "bytemuck")]
             Self::PodCastError(e) => write!(f, "slice cast failed: {}", e),
         }
@@ -447,6 +639,8 @@ impl error::Error for MemoryError {
             Self::DataSizeTooBig { .. } => None,
             Self::AllocationOffsetTooBig { .. } => None,
             Self::Flushing(e) => Some(e),
+            Self::Invalidating(e) => Some(e),
+            Self::NotHostVisible { .. } => None,
             #[cfg(feature = "bytemuck")]
             Self::PodCastError(e) => Some(e),
         }
@@ -1,7 +1,10 @@
 //! See [here](https://asawicki.info/news_1740_vulkan_memory_types_on_pc_and_how_to_use_them) for advice
 //! on vulkan memory types on PC.
 
-use crate::{device::Device, AllocationInfo, AllocatorAccess, ApiVersion, DefragmentationContext};
+use crate::{
+    device::Device, AllocationInfo, AllocatorAccess, ApiVersion, CommandPool,
+    DefragmentationContext, DeletionQueue, Feature,
+};
 use ash::{
     khr::{bind_memory2, get_memory_requirements2, get_physical_device_properties2, maintenance4},
     prelude::VkResult,
@@ -9,13 +12,28 @@ use ash::{
         self, PFN_vkBindBufferMemory2, PFN_vkBindImageMemory2, PFN_vkGetBufferMemoryRequirements2,
         PFN_vkGetDeviceBufferMemoryRequirements, PFN_vkGetDeviceImageMemoryRequirements,
         PFN_vkGetImageMemoryRequirements2, PFN_vkGetPhysicalDeviceMemoryProperties2,
-        KHR_BIND_MEMORY2_NAME, KHR_GET_MEMORY_REQUIREMENTS2_NAME,
+        EXT_MEMORY_BUDGET_NAME, KHR_BIND_MEMORY2_NAME, KHR_GET_MEMORY_REQUIREMENTS2_NAME,
         KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_NAME, KHR_MAINTENANCE4_NAME,
     },
 };
-use bort_vma::{ffi, AllocatorCreateInfo};
+use bort_vma::{
+    ffi, Allocation, AllocationCreateInfo, AllocatorCreateFlags, AllocatorCreateInfo, HeapBudget,
+    TotalStatistics,
+};
 use log::warn;
-use std::{mem, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    error,
+    ffi::{c_char, CStr},
+    fmt, fs, io, mem,
+    path::Path,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 /// so it's easy to find all allocation callback args, just in case I want to use them in the future.
 pub const ALLOCATION_CALLBACK_NONE: Option<&ash::vk::AllocationCallbacks> = None;
@@ -25,6 +43,11 @@ pub const ALLOCATION_CALLBACK_NONE: Option<&ash::vk::AllocationCallbacks> = None
 pub struct MemoryAllocator {
     /// pointer to internal VmaAllocator instance
     handle: ffi::VmaAllocator,
+    memory_budget_enabled: bool,
+    device_coherent_memory_enabled: bool,
+    budget_warning_state: Mutex<BudgetWarningState>,
+    allocation_tags: Mutex<AllocationTagRegistry>,
+    out_of_memory_reports_enabled: AtomicBool,
 
     // dependencies
     device: Arc<Device>,
@@ -35,7 +58,7 @@ pub(crate) fn new_vma_allocator(
     device: &Device,
     mut create_info: AllocatorCreateInfo,
 ) -> VkResult<ffi::VmaAllocator> {
-    if device.instance().max_api_version() < ApiVersion::V1_1
+    if device.effective_api_version() < ApiVersion::V1_1
         && (!device
             .enabled_extensions()
             .contains(&KHR_GET_MEMORY_REQUIREMENTS2_NAME.to_owned())
@@ -53,7 +76,7 @@ pub(crate) fn new_vma_allocator(
         warn!("\tKHR_GET_PHYSICAL_DEVICE_PROPERTIES2");
     }
 
-    if device.instance().max_api_version() < ApiVersion::V1_3
+    if device.effective_api_version() < ApiVersion::V1_3
         && !device
             .enabled_extensions()
             .contains(&KHR_MAINTENANCE4_NAME.to_owned())
@@ -149,7 +172,7 @@ fn get_fns_get_memory_requirements2(
     PFN_vkGetBufferMemoryRequirements2,
     PFN_vkGetImageMemoryRequirements2,
 ) {
-    if device.instance().max_api_version() < ApiVersion::V1_1
+    if device.effective_api_version() < ApiVersion::V1_1
         && device
             .enabled_extensions()
             .contains(&KHR_GET_MEMORY_REQUIREMENTS2_NAME.to_owned())
@@ -172,7 +195,7 @@ fn get_fns_bind_memory2(
     device: &Device,
     create_info: &AllocatorCreateInfo<'_>,
 ) -> (PFN_vkBindBufferMemory2, PFN_vkBindImageMemory2) {
-    if device.instance().max_api_version() < ApiVersion::V1_1
+    if device.effective_api_version() < ApiVersion::V1_1
         && device
             .enabled_extensions()
             .contains(&KHR_BIND_MEMORY2_NAME.to_owned())
@@ -194,7 +217,7 @@ fn get_fns_get_physical_device_properties2(
     device: &Device,
     create_info: &AllocatorCreateInfo<'_>,
 ) -> PFN_vkGetPhysicalDeviceMemoryProperties2 {
-    if device.instance().max_api_version() < ApiVersion::V1_1
+    if device.effective_api_version() < ApiVersion::V1_1
         && device
             .enabled_extensions()
             .contains(&KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_NAME.to_owned())
@@ -221,7 +244,7 @@ fn get_fns_maintenance4(
     PFN_vkGetDeviceBufferMemoryRequirements,
     PFN_vkGetDeviceImageMemoryRequirements,
 ) {
-    if device.instance().max_api_version() < ApiVersion::V1_3
+    if device.effective_api_version() < ApiVersion::V1_3
         && device
             .enabled_extensions()
             .contains(&KHR_MAINTENANCE4_NAME.to_owned())
@@ -249,16 +272,86 @@ fn get_fns_maintenance4(
     }
 }
 
+/// Returns true if the device level memory requirement queries (`vkGetDeviceBufferMemoryRequirements`
+/// and `vkGetDeviceImageMemoryRequirements`) are available i.e. with vulkan 1.3 or
+/// `VK_KHR_maintenance4`. See [`BufferProperties::memory_requirements`](crate::BufferProperties::memory_requirements)
+/// and [`ImageProperties::memory_requirements`](crate::ImageProperties::memory_requirements).
+pub fn device_memory_requirements_available(device: &Device) -> bool {
+    device.effective_api_version() >= ApiVersion::V1_3
+        || device
+            .enabled_extensions()
+            .contains(&KHR_MAINTENANCE4_NAME.to_owned())
+}
+
+/// Returns true if `VK_EXT_memory_budget` is enabled on `device` and its dependency
+/// (`VK_KHR_get_physical_device_properties2` or vulkan 1.1) is available.
+pub fn memory_budget_extension_enabled(device: &Device) -> bool {
+    let budget_extension_enabled = device
+        .enabled_extensions()
+        .contains(&EXT_MEMORY_BUDGET_NAME.to_owned());
+    let properties2_available = device.effective_api_version() >= ApiVersion::V1_1
+        || device
+            .instance()
+            .enabled_extensions()
+            .contains(&KHR_GET_PHYSICAL_DEVICE_PROPERTIES2_NAME.to_owned());
+    budget_extension_enabled && properties2_available
+}
+
+/// Returns true if `VK_AMD_device_coherent_memory` and its `deviceCoherentMemory` feature are
+/// enabled on `device`.
+pub fn device_coherent_memory_enabled(device: &Device) -> bool {
+    device.feature_enabled(Feature::DeviceCoherentMemory)
+}
+
+/// The VMA allocator flags matching the extensions and features enabled on `device` so the
+/// allocator and device agree on what's enabled:
+/// - `EXT_MEMORY_BUDGET` if [`memory_budget_extension_enabled`]
+/// - `AMD_DEVICE_COHERENT_MEMORY` if [`Feature::DeviceCoherentMemory`] is enabled
+/// - `BUFFER_DEVICE_ADDRESS` if [`Feature::BufferDeviceAddress`] is enabled
+/// - `EXT_MEMORY_PRIORITY` if [`Feature::MemoryPriority`] is enabled
+pub fn allocator_flags_for_device(device: &Device) -> AllocatorCreateFlags {
+    let mut allocator_flags = AllocatorCreateFlags::empty();
+    if memory_budget_extension_enabled(device) {
+        allocator_flags |= AllocatorCreateFlags::EXT_MEMORY_BUDGET;
+    }
+    if device.feature_enabled(Feature::DeviceCoherentMemory) {
+        allocator_flags |= AllocatorCreateFlags::AMD_DEVICE_COHERENT_MEMORY;
+    }
+    if device.feature_enabled(Feature::BufferDeviceAddress) {
+        allocator_flags |= AllocatorCreateFlags::BUFFER_DEVICE_ADDRESS;
+    }
+    if device.feature_enabled(Feature::MemoryPriority) {
+        allocator_flags |= AllocatorCreateFlags::EXT_MEMORY_PRIORITY;
+    }
+    allocator_flags
+}
+
 impl MemoryAllocator {
+    /// Sets the VMA `EXT_MEMORY_BUDGET` flag if `VK_EXT_memory_budget` is enabled on `device`
+    /// so [`Self::current_usage`] returns budgets fetched from the driver rather than estimates.
+    ///
+    /// Also sets the VMA `AMD_DEVICE_COHERENT_MEMORY` flag if `VK_AMD_device_coherent_memory` is
+    /// enabled (see [`PhysicalDeviceFeatures::with_device_coherent_memory`](crate::PhysicalDeviceFeatures::with_device_coherent_memory))
+    /// so that [`allocation_info_device_coherent`](crate::allocation_info_device_coherent) can be
+    /// used. Without the flag VMA never picks the `DEVICE_COHERENT_AMD` memory types.
+    ///
+    /// `BUFFER_DEVICE_ADDRESS` and `EXT_MEMORY_PRIORITY` are set when the matching device
+    /// features are enabled, see [`allocator_flags_for_device`].
     pub fn new(device: Arc<Device>) -> VkResult<Self> {
-        let api_version_uint = device.instance().max_api_version().as_vk_uint();
-        let allocator_info = AllocatorCreateInfo::new(
+        // the bundled vma doesn't know about versions above 1.3 and asserts on them
+        let api_version_uint = device
+            .effective_api_version()
+            .min(ApiVersion::V1_3)
+            .as_vk_uint();
+        let mut allocator_info = AllocatorCreateInfo::new(
             device.instance().inner(),
             device.inner(),
             device.physical_device().handle(),
         )
         .vulkan_api_version(api_version_uint);
 
+        allocator_info = allocator_info.flags(allocator_flags_for_device(&device));
+
         unsafe { Self::new_from_create_info(device.clone(), allocator_info) }
     }
 
@@ -268,8 +361,229 @@ impl MemoryAllocator {
         device: Arc<Device>,
         create_info: AllocatorCreateInfo,
     ) -> VkResult<Self> {
+        let memory_budget_enabled =
+            create_info.inner.flags & AllocatorCreateFlags::EXT_MEMORY_BUDGET.bits() != 0;
+        let device_coherent_memory_enabled =
+            create_info.inner.flags & AllocatorCreateFlags::AMD_DEVICE_COHERENT_MEMORY.bits() != 0;
         let handle = new_vma_allocator(&device, create_info)?;
-        Ok(Self { handle, device })
+        Ok(Self {
+            handle,
+            memory_budget_enabled,
+            device_coherent_memory_enabled,
+            budget_warning_state: Mutex::new(BudgetWarningState::default()),
+            allocation_tags: Mutex::new(AllocationTagRegistry::default()),
+            out_of_memory_reports_enabled: AtomicBool::new(false),
+            device,
+        })
+    }
+
+    /// Snapshot of the current usage and budget of each memory heap. If a budget warning
+    /// threshold has been set with [`Self::set_budget_warning_threshold`], a warning is logged
+    /// for each heap that has crossed the threshold since the last call.
+    ///
+    /// Usage and budget are only estimates unless `VK_EXT_memory_budget` is enabled (see
+    /// [`Self::memory_budget_enabled`]).
+    pub fn current_usage(&self) -> VkResult<MemoryUsageSnapshot> {
+        let snapshot = self.usage_snapshot()?;
+
+        if let Ok(mut budget_warning_state) = self.budget_warning_state.lock() {
+            budget_warning_state.check(&snapshot);
+        }
+
+        Ok(snapshot)
+    }
+
+    fn usage_snapshot(&self) -> VkResult<MemoryUsageSnapshot> {
+        let heap_budgets = self.get_heap_budgets()?;
+        let memory_properties = self.device.physical_device().memory_properties();
+
+        let heaps: Vec<HeapUsage> = heap_budgets
+            .iter()
+            .enumerate()
+            .map(|(heap_index, heap_budget)| HeapUsage {
+                heap_index: heap_index as u32,
+                flags: memory_properties.memory_heaps[heap_index].flags,
+                usage: heap_budget.usage,
+                budget: heap_budget.budget,
+                block_bytes: heap_budget.statistics.block_bytes,
+                allocation_bytes: heap_budget.statistics.allocation_bytes,
+            })
+            .collect();
+        Ok(MemoryUsageSnapshot { heaps })
+    }
+
+    /// Attributes the memory of `allocation_handle` to `tag` (e.g. "textures", "meshes") in
+    /// [`Self::tagged_memory_report`]. Tags are kept in a table owned by this allocator (the
+    /// allocation user data is left alone). The memory is no longer counted once the allocation
+    /// is freed via this allocator or destroyed by a defragmentation move.
+    ///
+    /// Tagging an allocation that is already tagged moves it to the new tag.
+    ///
+    /// # Safety
+    /// `allocation_handle` must be a valid allocation created by this allocator.
+    pub unsafe fn set_allocation_tag(
+        &self,
+        allocation_handle: ffi::VmaAllocation,
+        tag: &'static str,
+    ) {
+        let size = self.vma_get_allocation_info(allocation_handle).size;
+        if let Ok(mut allocation_tags) = self.allocation_tags.lock() {
+            allocation_tags.remove(allocation_handle);
+            allocation_tags.add(allocation_handle, tag, size);
+        }
+    }
+
+    /// The tag set with [`Self::set_allocation_tag`], if any.
+    pub fn allocation_tag(&self, allocation_handle: ffi::VmaAllocation) -> Option<&'static str> {
+        let allocation_tags = self.allocation_tags.lock().ok()?;
+        allocation_tags.tag(allocation_handle)
+    }
+
+    /// Live bytes and allocation count for each tag set with [`Self::set_allocation_tag`], sorted
+    /// from the most to the least live bytes. Tags whose allocations have all been freed are
+    /// included with zero bytes.
+    pub fn tagged_memory_report(&self) -> Vec<TaggedMemoryUsage> {
+        let mut report = match self.allocation_tags.lock() {
+            Ok(allocation_tags) => allocation_tags.usages.clone(),
+            Err(_) => return Vec::new(),
+        };
+        report.sort_by_key(|usage| Reverse(usage.live_bytes));
+        report
+    }
+
+    /// Called before `allocation_handle` is freed.
+    pub(crate) fn untrack_tagged_allocation(&self, allocation_handle: ffi::VmaAllocation) {
+        if allocation_handle.is_null() {
+            return;
+        }
+        if let Ok(mut allocation_tags) = self.allocation_tags.lock() {
+            allocation_tags.remove(allocation_handle);
+        }
+    }
+
+    /// Log a warning when the usage of a heap crosses `threshold_percentage` (e.g. `90.0`) of its
+    /// budget. Checked during [`Self::current_usage`]. Pass `None` to disable the warnings.
+    pub fn set_budget_warning_threshold(&self, threshold_percentage: Option<f32>) {
+        if let Ok(mut budget_warning_state) = self.budget_warning_state.lock() {
+            budget_warning_state.threshold_percentage = threshold_percentage;
+            budget_warning_state.heaps_over_threshold.clear();
+        }
+    }
+
+    /// Opt-in: when creating a buffer or image fails with `ERROR_OUT_OF_DEVICE_MEMORY`, capture an
+    /// [`OutOfMemoryReport`] (heap usage and budgets, VMA statistics and the largest allocation
+    /// tags), log it as a warning and return it in the [`AllocationError`] of e.g.
+    /// [`Buffer::new`](crate::Buffer::new).
+    ///
+    /// Gathering the report calculates VMA statistics which is slow, but only happens on failure.
+    pub fn set_out_of_memory_reports(&self, enabled: bool) {
+        self.out_of_memory_reports_enabled
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn out_of_memory_reports_enabled(&self) -> bool {
+        self.out_of_memory_reports_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Captures the current memory state for diagnosing an allocation failure of
+    /// `resource_description` (e.g. "buffer of 256 MiB").
+    pub fn capture_out_of_memory_report(
+        &self,
+        resource_description: impl Into<String>,
+    ) -> OutOfMemoryReport {
+        let total_statistics = self.calculate_statistics().ok().map(|stats| stats.total);
+        OutOfMemoryReport {
+            resource_description: resource_description.into(),
+            usage: self.usage_snapshot().ok(),
+            block_count: total_statistics
+                .as_ref()
+                .map_or(0, |total| total.statistics.block_count),
+            allocation_count: total_statistics
+                .as_ref()
+                .map_or(0, |total| total.statistics.allocation_count),
+            block_bytes: total_statistics
+                .as_ref()
+                .map_or(0, |total| total.statistics.block_bytes),
+            allocation_bytes: total_statistics
+                .as_ref()
+                .map_or(0, |total| total.statistics.allocation_bytes),
+            largest_allocation_size: total_statistics
+                .as_ref()
+                .map_or(0, |total| total.allocation_size_max),
+            tagged_usage: self
+                .tagged_memory_report()
+                .into_iter()
+                .filter(|usage| usage.live_bytes > 0)
+                .take(OUT_OF_MEMORY_REPORT_TAG_COUNT)
+                .collect(),
+        }
+    }
+
+    /// Called when creating a resource fails. `resource_description` is only evaluated if a
+    /// report is captured.
+    pub(crate) fn on_allocation_failure(
+        &self,
+        result: vk::Result,
+        resource_description: impl FnOnce() -> String,
+    ) -> AllocationError {
+        if result != vk::Result::ERROR_OUT_OF_DEVICE_MEMORY || !self.out_of_memory_reports_enabled()
+        {
+            return AllocationError {
+                result,
+                out_of_memory_report: None,
+            };
+        }
+        let report = self.capture_out_of_memory_report(resource_description());
+        warn!("{}", report);
+        AllocationError {
+            result,
+            out_of_memory_report: Some(Box::new(report)),
+        }
+    }
+
+    /// Tries to return memory to the system, e.g. when [`MemoryUsageSnapshot::is_over_threshold`]
+    /// reports a heap is close to its budget or the OS reports memory pressure.
+    ///
+    /// Flushes `deletion_queues` so **only call this once the device is idle** (e.g. after
+    /// [`Device::wait_idle`]) and trims `command_pools` (skipped unless the device uses vulkan
+    /// 1.1 or has `VK_KHR_maintenance1` enabled, see [`CommandPool::supports_trim`]). VMA
+    /// releases the `vk::DeviceMemory` blocks left empty by the flushed resources itself, except
+    /// for blocks kept alive by custom pools with a minimum block count.
+    pub fn handle_memory_pressure(
+        &self,
+        command_pools: &[&CommandPool],
+        deletion_queues: &mut [&mut DeletionQueue],
+    ) -> VkResult<MemoryPressureReport> {
+        let block_bytes_before = self.total_block_bytes()?;
+
+        let dropped_resource_count = deletion_queues
+            .iter_mut()
+            .map(|deletion_queue| deletion_queue.flush())
+            .sum();
+
+        let mut trimmed_command_pool_count = 0;
+        for command_pool in command_pools {
+            if command_pool.supports_trim() {
+                command_pool.trim();
+                trimmed_command_pool_count += 1;
+            }
+        }
+
+        let block_bytes_after = self.total_block_bytes()?;
+        Ok(MemoryPressureReport {
+            dropped_resource_count,
+            trimmed_command_pool_count,
+            freed_block_bytes: block_bytes_before.saturating_sub(block_bytes_after),
+        })
+    }
+
+    fn total_block_bytes(&self) -> VkResult<vk::DeviceSize> {
+        let heap_budgets = self.get_heap_budgets()?;
+        Ok(heap_budgets
+            .iter()
+            .map(|heap_budget| heap_budget.statistics.block_bytes)
+            .sum())
     }
 
     /// The allocator fetches `ash::vk::PhysicalDeviceProperties` from the physical device.
@@ -304,34 +618,93 @@ impl MemoryAllocator {
     }
 
     /// Retrieves statistics from current state of the `Allocator`.
-    pub fn calculate_statistics(&self) -> VkResult<ffi::VmaTotalStatistics> {
+    pub fn calculate_statistics(&self) -> VkResult<TotalStatistics> {
         unsafe {
             let mut vma_stats: ffi::VmaTotalStatistics = mem::zeroed();
             ffi::vmaCalculateStatistics(self.handle, &mut vma_stats);
-            Ok(vma_stats)
+            Ok(vma_stats.into())
+        }
+    }
+
+    /// Builds a JSON string of the allocator statistics via `vmaBuildStatsString`. Can be loaded
+    /// into VMA's visualization tools (e.g. `GpuMemDumpVis.py`) for offline analysis.
+    ///
+    /// If `detailed` is true, the JSON includes a detailed map of every allocation and free range
+    /// which can be slow to build with many allocations.
+    pub fn build_stats_json(&self, detailed: bool) -> String {
+        unsafe {
+            let mut stats_string_ptr: *mut c_char = ptr::null_mut();
+            ffi::vmaBuildStatsString(self.handle, &mut stats_string_ptr, detailed as vk::Bool32);
+            if stats_string_ptr.is_null() {
+                return String::new();
+            }
+
+            let stats_json = CStr::from_ptr(stats_string_ptr)
+                .to_string_lossy()
+                .into_owned();
+            ffi::vmaFreeStatsString(self.handle, stats_string_ptr);
+            stats_json
         }
     }
 
+    /// Writes [`Self::build_stats_json`] to a file at `path`, replacing it if it already exists.
+    pub fn write_stats_json(&self, path: impl AsRef<Path>, detailed: bool) -> io::Result<()> {
+        fs::write(path, self.build_stats_json(detailed))
+    }
+
     /// Retrieves information about current memory usage and budget for all memory heaps.
     ///
     /// This function is called "get" not "calculate" because it is very fast, suitable to be called
-    /// every frame or every allocation. For more detailed statistics use vmaCalculateStatistics().
+    /// every frame or every allocation. For more detailed statistics use [`Self::calculate_statistics`].
     ///
     /// Note that when using allocator from multiple threads, returned information may immediately
     /// become outdated.
-    pub fn get_heap_budgets(&self) -> VkResult<Vec<ffi::VmaBudget>> {
+    pub fn get_heap_budgets(&self) -> VkResult<Vec<HeapBudget>> {
         unsafe {
             let len = self.get_memory_properties().memory_heap_count as usize;
             let mut vma_budgets: Vec<ffi::VmaBudget> = Vec::with_capacity(len);
             ffi::vmaGetHeapBudgets(self.handle, vma_budgets.as_mut_ptr());
             vma_budgets.set_len(len);
-            Ok(vma_budgets)
+            Ok(vma_budgets.into_iter().map(HeapBudget::from).collect())
         }
     }
 
+    /// Whether a resource with `memory_requirements` (e.g. from
+    /// [`ImageProperties::memory_requirements`](crate::ImageProperties::memory_requirements))
+    /// allocated with `allocation_info` would fit in the remaining budget of the memory heap it
+    /// would be allocated from, without creating it. Returns `ERROR_FEATURE_NOT_PRESENT` if no
+    /// memory type is suitable.
+    ///
+    /// The budget is only an estimate unless `VK_EXT_memory_budget` is enabled (see
+    /// [`Self::memory_budget_enabled`]) and doesn't account for other threads allocating at the
+    /// same time.
+    pub fn fits_in_budget(
+        &self,
+        memory_requirements: vk::MemoryRequirements,
+        allocation_info: &AllocationCreateInfo,
+    ) -> VkResult<bool> {
+        let memory_type_index = unsafe {
+            self.find_memory_type_index(memory_requirements.memory_type_bits, allocation_info)
+        }?;
+        let heap_index = self
+            .device()
+            .physical_device()
+            .memory_properties()
+            .memory_types[memory_type_index as usize]
+            .heap_index;
+        let heap_budgets = self.get_heap_budgets()?;
+        let fits = heap_budgets
+            .get(heap_index as usize)
+            .is_some_and(|heap_budget| {
+                heap_budget.usage.saturating_add(memory_requirements.size) <= heap_budget.budget
+            });
+        Ok(fits)
+    }
+
     /// Frees memory previously allocated using `Allocator::allocate_memory`,
     /// `Allocator::allocate_memory_for_buffer`, or `Allocator::allocate_memory_for_image`.
     pub unsafe fn vma_free_memory(&self, allocation_handle: ffi::VmaAllocation) {
+        self.untrack_tagged_allocation(allocation_handle);
         ffi::vmaFreeMemory(self.handle, allocation_handle);
     }
 
@@ -345,6 +718,9 @@ impl MemoryAllocator {
     ///
     /// Allocations in 'allocations' slice can come from any memory pools and types.
     pub unsafe fn vma_free_memory_pages(&self, allocation_handles: &mut [ffi::VmaAllocation]) {
+        for &allocation_handle in allocation_handles.iter() {
+            self.untrack_tagged_allocation(allocation_handle);
+        }
         ffi::vmaFreeMemoryPages(
             self.handle,
             allocation_handles.len(),
@@ -373,24 +749,35 @@ impl MemoryAllocator {
         }
     }
 
-    /// Sets user data in given allocation to new value.
+    /// Sets user data in given allocation to new value. The value is opaque to VMA so you can
+    /// use it however you want - e.g. as an ordinal number or some handle to your own data.
+    /// Read it back with [`Self::vma_get_allocation_info`].
     ///
-    /// If the allocation was created with `AllocationCreateFlags::USER_DATA_COPY_STRING`,
-    /// `user_data` must be either null, or pointer to a null-terminated string. The function
-    /// makes local copy of the string and sets it as allocation's user data. String
-    /// passed as user data doesn't need to be valid for whole lifetime of the allocation -
-    /// you can free it after this call. String previously pointed by allocation's
-    /// user data is freed from memory.
+    /// User data is stored as a pointer so on 32-bit targets the upper 32 bits are discarded.
     ///
-    /// If the flag was not used, the value of pointer `user_data` is just copied to
-    /// allocation's user data. It is opaque, so you can use it however you want - e.g.
-    /// as a pointer, ordinal number or some handle to you own data.
+    /// # Safety
+    /// `allocation_handle` must be a valid allocation created by this allocator and must not have
+    /// been created with the deprecated `AllocationCreateFlags::USER_DATA_COPY_STRING` (which
+    /// treats user data as a string pointer, use [`Self::vma_set_allocation_name`] instead).
     pub unsafe fn vma_set_allocation_user_data(
         &self,
         allocation_handle: ffi::VmaAllocation,
-        user_data: *mut ::std::os::raw::c_void,
+        user_data: u64,
     ) {
-        ffi::vmaSetAllocationUserData(self.handle, allocation_handle, user_data);
+        Allocation(allocation_handle).set_user_data(self.handle, user_data);
+    }
+
+    /// Sets the name of the allocation, shown in [`Self::build_stats_json`] and debugging tools.
+    /// VMA makes a copy of the string. Any nul bytes in `name` truncate it.
+    ///
+    /// # Safety
+    /// `allocation_handle` must be a valid allocation created by this allocator.
+    pub unsafe fn vma_set_allocation_name(
+        &self,
+        allocation_handle: ffi::VmaAllocation,
+        name: &str,
+    ) {
+        Allocation(allocation_handle).set_name(self.handle, name);
     }
 
     /// Maps memory represented by given allocation and returns pointer to it.
@@ -627,6 +1014,7 @@ impl MemoryAllocator {
         buffer: ash::vk::Buffer,
         allocation_handle: ffi::VmaAllocation,
     ) {
+        self.untrack_tagged_allocation(allocation_handle);
         ffi::vmaDestroyBuffer(self.handle, buffer, allocation_handle);
     }
 
@@ -645,6 +1033,7 @@ impl MemoryAllocator {
         image: ash::vk::Image,
         allocation_handle: ffi::VmaAllocation,
     ) {
+        self.untrack_tagged_allocation(allocation_handle);
         ffi::vmaDestroyImage(self.handle, image, allocation_handle);
     }
     /// Flushes memory of given set of allocations."]
@@ -703,7 +1092,7 @@ impl MemoryAllocator {
     pub unsafe fn begin_defragmentation(
         &self,
         info: &ffi::VmaDefragmentationInfo,
-    ) -> VkResult<DefragmentationContext> {
+    ) -> VkResult<DefragmentationContext<'_>> {
         let mut handle: ffi::VmaDefragmentationContext = std::ptr::null_mut();
         ffi::vmaBeginDefragmentation(self.handle, info, &mut handle).result()?;
         Ok(DefragmentationContext::new(handle, self))
@@ -717,6 +1106,18 @@ impl MemoryAllocator {
     pub fn handle(&self) -> ffi::VmaAllocator {
         self.handle
     }
+
+    /// Whether the allocator was created with the VMA `EXT_MEMORY_BUDGET` flag.
+    #[inline]
+    pub fn memory_budget_enabled(&self) -> bool {
+        self.memory_budget_enabled
+    }
+
+    /// Whether the allocator was created with the VMA `AMD_DEVICE_COHERENT_MEMORY` flag.
+    #[inline]
+    pub fn device_coherent_memory_enabled(&self) -> bool {
+        self.device_coherent_memory_enabled
+    }
 }
 
 /// Custom `Drop` implementation to clean up internal allocation instance
@@ -732,6 +1133,273 @@ impl Drop for MemoryAllocator {
 unsafe impl Send for MemoryAllocator {}
 unsafe impl Sync for MemoryAllocator {}
 
+// ~~ Allocation Tags ~~
+
+/// Memory attributed to an allocation tag. See [`MemoryAllocator::tagged_memory_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaggedMemoryUsage {
+    pub tag: &'static str,
+    pub live_bytes: vk::DeviceSize,
+    pub allocation_count: u32,
+}
+
+#[derive(Default)]
+struct AllocationTagRegistry {
+    usages: Vec<TaggedMemoryUsage>,
+    /// Index into `usages` and size of each tagged allocation, keyed by allocation handle.
+    tagged_allocations: HashMap<usize, (usize, vk::DeviceSize)>,
+}
+
+impl AllocationTagRegistry {
+    /// The allocation must not already be tagged.
+    fn add(
+        &mut self,
+        allocation_handle: ffi::VmaAllocation,
+        tag: &'static str,
+        size: vk::DeviceSize,
+    ) {
+        let index = match self.usages.iter().position(|usage| usage.tag == tag) {
+            Some(index) => index,
+            None => {
+                self.usages.push(TaggedMemoryUsage {
+                    tag,
+                    live_bytes: 0,
+                    allocation_count: 0,
+                });
+                self.usages.len() - 1
+            }
+        };
+        let usage = &mut self.usages[index];
+        usage.live_bytes += size;
+        usage.allocation_count += 1;
+        self.tagged_allocations
+            .insert(allocation_handle as usize, (index, size));
+    }
+
+    /// Does nothing if the allocation isn't tagged.
+    fn remove(&mut self, allocation_handle: ffi::VmaAllocation) {
+        let Some((index, size)) = self
+            .tagged_allocations
+            .remove(&(allocation_handle as usize))
+        else {
+            return;
+        };
+        let usage = &mut self.usages[index];
+        usage.live_bytes = usage.live_bytes.saturating_sub(size);
+        usage.allocation_count = usage.allocation_count.saturating_sub(1);
+    }
+
+    fn tag(&self, allocation_handle: ffi::VmaAllocation) -> Option<&'static str> {
+        let (index, _) = self.tagged_allocations.get(&(allocation_handle as usize))?;
+        Some(self.usages[*index].tag)
+    }
+}
+
+// ~~ Memory Usage ~~
+
+/// Usage and budget of each memory heap at a point in time. See
+/// [`MemoryAllocator::current_usage`].
+#[derive(Debug, Clone)]
+pub struct MemoryUsageSnapshot {
+    pub heaps: Vec<HeapUsage>,
+}
+
+impl MemoryUsageSnapshot {
+    /// Heaps with `vk::MemoryHeapFlags::DEVICE_LOCAL`.
+    pub fn device_local_heaps(&self) -> impl Iterator<Item = &HeapUsage> {
+        self.heaps
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+    }
+
+    /// Whether any heap is using at least `threshold_percentage` (e.g. `90.0`) of its budget. Use
+    /// to decide when to call [`MemoryAllocator::handle_memory_pressure`].
+    pub fn is_over_threshold(&self, threshold_percentage: f32) -> bool {
+        self.heaps
+            .iter()
+            .any(|heap| heap.usage_percentage() >= threshold_percentage)
+    }
+
+    /// The heap with the highest usage relative to its budget.
+    pub fn most_used_heap(&self) -> Option<&HeapUsage> {
+        self.heaps
+            .iter()
+            .max_by(|a, b| a.usage_percentage().total_cmp(&b.usage_percentage()))
+    }
+}
+
+/// Memory state captured when an allocation fails. See
+/// [`MemoryAllocator::set_out_of_memory_reports`]. The `Display` impl formats it for logs.
+#[derive(Debug, Clone)]
+pub struct OutOfMemoryReport {
+    /// The resource that failed to allocate e.g. "buffer of 1048576 bytes".
+    pub resource_description: String,
+    /// `None` if the heap budgets couldn't be queried.
+    pub usage: Option<MemoryUsageSnapshot>,
+    /// Number of `vk::DeviceMemory` blocks allocated by VMA.
+    pub block_count: u32,
+    pub allocation_count: u32,
+    pub block_bytes: vk::DeviceSize,
+    pub allocation_bytes: vk::DeviceSize,
+    pub largest_allocation_size: vk::DeviceSize,
+    /// The allocation tags with the most live bytes, see
+    /// [`MemoryAllocator::set_allocation_tag`].
+    pub tagged_usage: Vec<TaggedMemoryUsage>,
+}
+
+/// Error creating a buffer or image with its memory. Converts into the `vk::Result` so it can be
+/// propagated with `?` from functions returning `VkResult`.
+#[derive(Debug, Clone)]
+pub struct AllocationError {
+    pub result: vk::Result,
+    /// Captured if the result is `ERROR_OUT_OF_DEVICE_MEMORY` and
+    /// [`MemoryAllocator::set_out_of_memory_reports`] is enabled.
+    pub out_of_memory_report: Option<Box<OutOfMemoryReport>>,
+}
+
+impl From<vk::Result> for AllocationError {
+    fn from(result: vk::Result) -> Self {
+        Self {
+            result,
+            out_of_memory_report: None,
+        }
+    }
+}
+
+impl From<AllocationError> for vk::Result {
+    fn from(error: AllocationError) -> Self {
+        error.result
+    }
+}
+
+impl fmt::Display for AllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.out_of_memory_report {
+            Some(report) => write!(f, "{}\n{}", self.result, report),
+            None => write!(f, "{}", self.result),
+        }
+    }
+}
+
+impl error::Error for AllocationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.result)
+    }
+}
+
+/// Max number of allocation tags included in an [`OutOfMemoryReport`].
+pub const OUT_OF_MEMORY_REPORT_TAG_COUNT: usize = 8;
+
+impl fmt::Display for OutOfMemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "out of device memory allocating {}",
+            self.resource_description
+        )?;
+        match &self.usage {
+            Some(usage) => {
+                for heap in &usage.heaps {
+                    writeln!(
+                        f,
+                        "  heap {} ({:?}): {} / {} bytes ({:.1}%), vma blocks {} bytes, vma allocations {} bytes",
+                        heap.heap_index,
+                        heap.flags,
+                        heap.usage,
+                        heap.budget,
+                        heap.usage_percentage(),
+                        heap.block_bytes,
+                        heap.allocation_bytes
+                    )?;
+                }
+            }
+            None => writeln!(f, "  heap budgets unavailable")?,
+        }
+        write!(
+            f,
+            "  vma: {} blocks ({} bytes), {} allocations ({} bytes), largest allocation {} bytes",
+            self.block_count,
+            self.block_bytes,
+            self.allocation_count,
+            self.allocation_bytes,
+            self.largest_allocation_size
+        )?;
+        for tagged_usage in &self.tagged_usage {
+            write!(
+                f,
+                "\n  tag \"{}\": {} bytes in {} allocations",
+                tagged_usage.tag, tagged_usage.live_bytes, tagged_usage.allocation_count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`MemoryAllocator::handle_memory_pressure`] managed to free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryPressureReport {
+    /// Resources dropped from the deletion queues.
+    pub dropped_resource_count: usize,
+    pub trimmed_command_pool_count: usize,
+    /// Decrease in bytes of `vk::DeviceMemory` blocks allocated by VMA.
+    pub freed_block_bytes: vk::DeviceSize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeapUsage {
+    pub heap_index: u32,
+    pub flags: vk::MemoryHeapFlags,
+    /// Estimated memory usage of the program in bytes (including memory not allocated by VMA if
+    /// `VK_EXT_memory_budget` is enabled).
+    pub usage: vk::DeviceSize,
+    /// Estimated memory available to the program in bytes.
+    pub budget: vk::DeviceSize,
+    /// Bytes allocated in `vk::DeviceMemory` blocks by VMA.
+    pub block_bytes: vk::DeviceSize,
+    /// Bytes occupied by VMA allocations.
+    pub allocation_bytes: vk::DeviceSize,
+}
+
+impl HeapUsage {
+    pub fn usage_percentage(&self) -> f32 {
+        if self.budget == 0 {
+            return 0.;
+        }
+        (self.usage as f64 / self.budget as f64 * 100.) as f32
+    }
+}
+
+#[derive(Default)]
+struct BudgetWarningState {
+    threshold_percentage: Option<f32>,
+    heaps_over_threshold: Vec<bool>,
+}
+
+impl BudgetWarningState {
+    /// Only warns when a heap crosses the threshold so we don't spam the log every frame.
+    fn check(&mut self, snapshot: &MemoryUsageSnapshot) {
+        let Some(threshold_percentage) = self.threshold_percentage else {
+            return;
+        };
+        self.heaps_over_threshold
+            .resize(snapshot.heaps.len(), false);
+
+        for heap in &snapshot.heaps {
+            let usage_percentage = heap.usage_percentage();
+            let over_threshold = usage_percentage >= threshold_percentage;
+            let was_over_threshold = self.heaps_over_threshold[heap.heap_index as usize];
+
+            if over_threshold && !was_over_threshold {
+                warn!(
+                    "memory heap {} ({:?}) usage is at {:.1}% of its budget ({} / {} bytes)",
+                    heap.heap_index, heap.flags, usage_percentage, heap.usage, heap.budget
+                );
+            }
+            self.heaps_over_threshold[heap.heap_index as usize] = over_threshold;
+        }
+    }
+}
+
 impl AllocatorAccess for MemoryAllocator {
     #[inline]
     fn device(&self) -> &Arc<Device> {
@@ -748,3 +1416,82 @@ impl AllocatorAccess for MemoryAllocator {
         std::ptr::null_mut()
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn allocation_tag_registry_aggregates_per_tag() {
+    // fake handles, only used as keys
+    let handle = |address: usize| address as ffi::VmaAllocation;
+    let (texture_a, mesh, texture_b) = (handle(0x10), handle(0x20), handle(0x30));
+
+    let mut registry = AllocationTagRegistry::default();
+    registry.add(texture_a, "textures", 256);
+    registry.add(mesh, "meshes", 64);
+    registry.add(texture_b, "textures", 128);
+    assert_eq!(registry.usages.len(), 2);
+    assert_eq!(registry.tag(mesh), Some("meshes"));
+    assert_eq!(registry.tag(handle(0x40)), None);
+
+    registry.remove(texture_a);
+    registry.remove(texture_a);
+    registry.remove(handle(0x40));
+    assert_eq!(registry.tag(texture_a), None);
+    assert_eq!(
+        registry.usages[0],
+        TaggedMemoryUsage {
+            tag: "textures",
+            live_bytes: 128,
+            allocation_count: 1,
+        }
+    );
+}
+
+#[test]
+fn memory_usage_snapshot_threshold() {
+    let heap = |heap_index, usage| HeapUsage {
+        heap_index,
+        flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+        usage,
+        budget: 1000,
+        block_bytes: usage,
+        allocation_bytes: usage,
+    };
+    let snapshot = MemoryUsageSnapshot {
+        heaps: vec![heap(0, 500), heap(1, 920)],
+    };
+    assert!(snapshot.is_over_threshold(90.));
+    assert!(!snapshot.is_over_threshold(95.));
+}
+
+#[test]
+fn out_of_memory_report_display() {
+    let report = OutOfMemoryReport {
+        resource_description: "buffer of 1024 bytes".to_string(),
+        usage: Some(MemoryUsageSnapshot {
+            heaps: vec![HeapUsage {
+                heap_index: 0,
+                flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+                usage: 990,
+                budget: 1000,
+                block_bytes: 900,
+                allocation_bytes: 850,
+            }],
+        }),
+        block_count: 3,
+        allocation_count: 12,
+        block_bytes: 900,
+        allocation_bytes: 850,
+        largest_allocation_size: 512,
+        tagged_usage: vec![TaggedMemoryUsage {
+            tag: "textures",
+            live_bytes: 600,
+            allocation_count: 4,
+        }],
+    };
+    let report_text = report.to_string();
+    assert!(report_text.starts_with("out of device memory allocating buffer of 1024 bytes"));
+    assert!(report_text.contains("heap 0"));
+    assert!(report_text.contains("largest allocation 512 bytes"));
+    assert!(report_text.contains("tag \"textures\": 600 bytes in 4 allocations"));
+}
@@ -0,0 +1,280 @@
+use crate::{AllocatorAccess, ALLOCATION_CALLBACK_NONE};
+use ash::{prelude::VkResult, vk};
+use bort_vma::AllocationCreateInfo;
+use std::fmt;
+
+/// Why a memory type can't be used for an allocation. See [`MemoryChoiceReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryTypeRejection {
+    /// Not in `vk::MemoryRequirements::memory_type_bits` of the buffer/image.
+    ExcludedByResourceTypeBits,
+    /// Not in `AllocationCreateInfo::memory_type_bits`.
+    ExcludedByAllocationTypeBits,
+    /// Missing some of `AllocationCreateInfo::required_flags`.
+    MissingRequiredFlags(vk::MemoryPropertyFlags),
+}
+
+impl fmt::Display for MemoryTypeRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExcludedByResourceTypeBits => {
+                write!(f, "excluded by the resource memory requirements")
+            }
+            Self::ExcludedByAllocationTypeBits => {
+                write!(f, "excluded by allocation info memory_type_bits")
+            }
+            Self::MissingRequiredFlags(flags) => write!(f, "missing required flags {:?}", flags),
+        }
+    }
+}
+
+/// One memory type of the physical device and whether it could be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryTypeCandidate {
+    pub memory_type_index: u32,
+    pub property_flags: vk::MemoryPropertyFlags,
+    pub heap_index: u32,
+    pub heap_size: vk::DeviceSize,
+    pub heap_flags: vk::MemoryHeapFlags,
+    /// `None` if the memory type satisfies all hard requirements.
+    pub rejection: Option<MemoryTypeRejection>,
+    /// Flags of `AllocationCreateInfo::preferred_flags` this memory type lacks.
+    pub missing_preferred_flags: vk::MemoryPropertyFlags,
+}
+
+/// Explains which memory type an allocation would land in and why the others weren't chosen.
+/// Useful when allocations end up in unexpectedly slow memory. See
+/// [`explain_memory_type_choice_for_buffer`] and [`explain_memory_type_choice_for_image`].
+///
+/// Acceptable memory types which weren't chosen lost out on `preferred_flags` or the memory usage
+/// heuristics of `AllocationCreateInfo::usage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryChoiceReport {
+    pub resource_memory_type_bits: u32,
+    pub candidates: Vec<MemoryTypeCandidate>,
+    /// `None` if no memory type satisfies the requirements (`ERROR_FEATURE_NOT_PRESENT`).
+    pub chosen_memory_type_index: Option<u32>,
+}
+
+impl MemoryChoiceReport {
+    pub fn chosen(&self) -> Option<&MemoryTypeCandidate> {
+        let chosen_index = self.chosen_memory_type_index?;
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.memory_type_index == chosen_index)
+    }
+
+    /// Memory types which satisfy all hard requirements.
+    pub fn acceptable(&self) -> impl Iterator<Item = &MemoryTypeCandidate> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.rejection.is_none())
+    }
+}
+
+impl fmt::Display for MemoryChoiceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for candidate in &self.candidates {
+            write!(
+                f,
+                "memory type {} (heap {}, {} MiB, {:?}) {:?}: ",
+                candidate.memory_type_index,
+                candidate.heap_index,
+                candidate.heap_size / (1024 * 1024),
+                candidate.heap_flags,
+                candidate.property_flags
+            )?;
+            if Some(candidate.memory_type_index) == self.chosen_memory_type_index {
+                write!(f, "chosen")?;
+            } else if let Some(rejection) = candidate.rejection {
+                write!(f, "rejected, {}", rejection)?;
+            } else {
+                write!(f, "acceptable but not preferred")?;
+            }
+            if !candidate.missing_preferred_flags.is_empty() {
+                write!(
+                    f,
+                    " (missing preferred flags {:?})",
+                    candidate.missing_preferred_flags
+                )?;
+            }
+            writeln!(f)?;
+        }
+        if self.chosen_memory_type_index.is_none() {
+            writeln!(f, "no suitable memory type found")?;
+        }
+        Ok(())
+    }
+}
+
+/// Explains the memory type choice for a buffer created with `buffer_info` and
+/// `allocation_info`. A temporary buffer is created to query its memory requirements.
+pub fn explain_memory_type_choice_for_buffer(
+    alloc_access: &dyn AllocatorAccess,
+    buffer_info: &vk::BufferCreateInfo,
+    allocation_info: &AllocationCreateInfo,
+) -> VkResult<MemoryChoiceReport> {
+    let device = alloc_access.device().inner();
+    let memory_type_bits = unsafe {
+        let buffer = device.create_buffer(buffer_info, ALLOCATION_CALLBACK_NONE)?;
+        let memory_requirements = device.get_buffer_memory_requirements(buffer);
+        device.destroy_buffer(buffer, ALLOCATION_CALLBACK_NONE);
+        memory_requirements.memory_type_bits
+    };
+    explain_memory_type_choice(alloc_access, memory_type_bits, allocation_info)
+}
+
+/// Explains the memory type choice for an image created with `image_info` and
+/// `allocation_info`. A temporary image is created to query its memory requirements.
+pub fn explain_memory_type_choice_for_image(
+    alloc_access: &dyn AllocatorAccess,
+    image_info: &vk::ImageCreateInfo,
+    allocation_info: &AllocationCreateInfo,
+) -> VkResult<MemoryChoiceReport> {
+    let device = alloc_access.device().inner();
+    let memory_type_bits = unsafe {
+        let image = device.create_image(image_info, ALLOCATION_CALLBACK_NONE)?;
+        let memory_requirements = device.get_image_memory_requirements(image);
+        device.destroy_image(image, ALLOCATION_CALLBACK_NONE);
+        memory_requirements.memory_type_bits
+    };
+    explain_memory_type_choice(alloc_access, memory_type_bits, allocation_info)
+}
+
+/// Explains the memory type choice for a resource with `resource_memory_type_bits` from its
+/// `vk::MemoryRequirements`.
+pub fn explain_memory_type_choice(
+    alloc_access: &dyn AllocatorAccess,
+    resource_memory_type_bits: u32,
+    allocation_info: &AllocationCreateInfo,
+) -> VkResult<MemoryChoiceReport> {
+    let chosen_memory_type_index = match unsafe {
+        alloc_access.find_memory_type_index(resource_memory_type_bits, allocation_info)
+    } {
+        Ok(memory_type_index) => Some(memory_type_index),
+        Err(vk::Result::ERROR_FEATURE_NOT_PRESENT) => None,
+        Err(e) => return Err(e),
+    };
+
+    let memory_properties = alloc_access.device().physical_device().memory_properties();
+    Ok(MemoryChoiceReport {
+        resource_memory_type_bits,
+        candidates: memory_type_candidates(
+            memory_properties,
+            resource_memory_type_bits,
+            allocation_info,
+        ),
+        chosen_memory_type_index,
+    })
+}
+
+// Helper Functions
+
+fn memory_type_candidates(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    resource_memory_type_bits: u32,
+    allocation_info: &AllocationCreateInfo,
+) -> Vec<MemoryTypeCandidate> {
+    // 0 means any memory type is accepted
+    let allocation_type_bits = match allocation_info.memory_type_bits {
+        0 => u32::MAX,
+        bits => bits,
+    };
+
+    memory_properties
+        .memory_types_as_slice()
+        .iter()
+        .enumerate()
+        .map(|(memory_type_index, memory_type)| {
+            let type_bit = 1 << memory_type_index;
+            let missing_required_flags =
+                allocation_info.required_flags & !memory_type.property_flags;
+            let rejection = if resource_memory_type_bits & type_bit == 0 {
+                Some(MemoryTypeRejection::ExcludedByResourceTypeBits)
+            } else if allocation_type_bits & type_bit == 0 {
+                Some(MemoryTypeRejection::ExcludedByAllocationTypeBits)
+            } else if !missing_required_flags.is_empty() {
+                Some(MemoryTypeRejection::MissingRequiredFlags(
+                    missing_required_flags,
+                ))
+            } else {
+                None
+            };
+
+            let heap = memory_properties.memory_heaps[memory_type.heap_index as usize];
+            MemoryTypeCandidate {
+                memory_type_index: memory_type_index as u32,
+                property_flags: memory_type.property_flags,
+                heap_index: memory_type.heap_index,
+                heap_size: heap.size,
+                heap_flags: heap.flags,
+                rejection,
+                missing_preferred_flags: allocation_info.preferred_flags
+                    & !memory_type.property_flags,
+            }
+        })
+        .collect()
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn memory_type_candidate_rejections() {
+    let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+        memory_type_count: 3,
+        memory_heap_count: 2,
+        ..Default::default()
+    };
+    memory_properties.memory_heaps[0] = vk::MemoryHeap {
+        size: 8 << 30,
+        flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+    };
+    memory_properties.memory_heaps[1] = vk::MemoryHeap {
+        size: 16 << 30,
+        flags: vk::MemoryHeapFlags::empty(),
+    };
+    memory_properties.memory_types[0] = vk::MemoryType {
+        property_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        heap_index: 0,
+    };
+    memory_properties.memory_types[1] = vk::MemoryType {
+        property_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+            | vk::MemoryPropertyFlags::HOST_COHERENT,
+        heap_index: 1,
+    };
+    memory_properties.memory_types[2] = vk::MemoryType {
+        property_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL
+            | vk::MemoryPropertyFlags::HOST_VISIBLE,
+        heap_index: 0,
+    };
+
+    let allocation_info = AllocationCreateInfo {
+        required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE,
+        preferred_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ..Default::default()
+    };
+    let report = MemoryChoiceReport {
+        resource_memory_type_bits: 0b011,
+        candidates: memory_type_candidates(&memory_properties, 0b011, &allocation_info),
+        chosen_memory_type_index: Some(1),
+    };
+
+    assert_eq!(
+        report.candidates[0].rejection,
+        Some(MemoryTypeRejection::MissingRequiredFlags(
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+        ))
+    );
+    assert_eq!(report.candidates[1].rejection, None);
+    assert_eq!(
+        report.candidates[1].missing_preferred_flags,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+    );
+    assert_eq!(
+        report.candidates[2].rejection,
+        Some(MemoryTypeRejection::ExcludedByResourceTypeBits)
+    );
+    assert_eq!(report.acceptable().count(), 1);
+    assert_eq!(report.chosen().unwrap().heap_size, 16 << 30);
+    assert!(report.to_string().contains("memory type 1 (heap 1"));
+}
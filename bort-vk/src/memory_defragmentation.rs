@@ -1,10 +1,13 @@
-use crate::MemoryAllocator;
+use crate::{AllocationInfo, MemoryAllocator};
 use ash::vk;
 use bort_vma::ffi;
+use std::{cell::Cell, slice};
 
 pub struct DefragmentationContext<'a> {
     handle: ffi::VmaDefragmentationContext,
     allocator: &'a MemoryAllocator,
+    pass_in_progress: Cell<bool>,
+    finished: Cell<bool>,
 }
 
 impl<'a> Drop for DefragmentationContext<'a> {
@@ -21,7 +24,34 @@ impl<'a> DefragmentationContext<'a> {
         handle: ffi::VmaDefragmentationContext,
         allocator: &'a MemoryAllocator,
     ) -> Self {
-        Self { handle, allocator }
+        Self {
+            handle,
+            allocator,
+            pass_in_progress: Cell::new(false),
+            finished: Cell::new(false),
+        }
+    }
+
+    /// Iterates over defragmentation passes until no more moves are possible. Each
+    /// [`DefragmentationPass`] ends when it is dropped (or with [`DefragmentationPass::end`]) so
+    /// make sure the moves have been performed by then.
+    ///
+    /// ```ignore
+    /// for mut pass in defragmentation_context.passes() {
+    ///     for mut defrag_move in pass.moves() {
+    ///         if !can_move(defrag_move.src_allocation()) {
+    ///             defrag_move.ignore();
+    ///         }
+    ///         // recreate the resource at `defrag_move.dst_memory()` and copy the data...
+    ///     }
+    ///     // wait for the copies to finish before the pass is dropped
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    /// If the previous pass is still in progress when the next one is requested.
+    pub fn passes(&self) -> DefragmentationPasses<'_, 'a> {
+        DefragmentationPasses { context: self }
     }
 
     /// Ends defragmentation process.
@@ -56,6 +86,7 @@ impl<'a> DefragmentationContext<'a> {
             std::slice::from_raw_parts_mut(pass_info.pMoves, pass_info.moveCount as usize)
         };
         mover(moves);
+        untrack_destroyed_allocations(self.allocator, moves);
 
         let result = unsafe {
             ffi::vmaEndDefragmentationPass(self.allocator.handle(), self.handle, &mut pass_info)
@@ -64,3 +95,255 @@ impl<'a> DefragmentationContext<'a> {
         return result == vk::Result::INCOMPLETE;
     }
 }
+
+// ~~ Defragmentation Passes ~~
+
+/// Iterator over the passes of a [`DefragmentationContext`]. See
+/// [`DefragmentationContext::passes`].
+pub struct DefragmentationPasses<'c, 'a> {
+    context: &'c DefragmentationContext<'a>,
+}
+
+impl<'c, 'a> Iterator for DefragmentationPasses<'c, 'a> {
+    type Item = DefragmentationPass<'c, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.context.finished.get() {
+            return None;
+        }
+        assert!(
+            !self.context.pass_in_progress.get(),
+            "the previous defragmentation pass must end before the next one begins"
+        );
+
+        let mut pass_info = ffi::VmaDefragmentationPassMoveInfo {
+            moveCount: 0,
+            pMoves: std::ptr::null_mut(),
+        };
+        let result = unsafe {
+            ffi::vmaBeginDefragmentationPass(
+                self.context.allocator.handle(),
+                self.context.handle,
+                &mut pass_info,
+            )
+        };
+        if result != vk::Result::INCOMPLETE {
+            self.context.finished.set(true);
+            return None;
+        }
+
+        self.context.pass_in_progress.set(true);
+        Some(DefragmentationPass {
+            context: self.context,
+            pass_info,
+            ended: false,
+        })
+    }
+}
+
+/// A single defragmentation pass. For each move you should either create a new buffer/image at
+/// the destination memory and copy the data over, or mark the move with
+/// [`DefragmentationMove::ignore`] or [`DefragmentationMove::destroy`].
+pub struct DefragmentationPass<'c, 'a> {
+    context: &'c DefragmentationContext<'a>,
+    pass_info: ffi::VmaDefragmentationPassMoveInfo,
+    ended: bool,
+}
+
+impl<'c, 'a> DefragmentationPass<'c, 'a> {
+    /// The moves requested by this pass.
+    pub fn moves(&mut self) -> impl Iterator<Item = DefragmentationMove<'_>> {
+        let allocator = self.context.allocator;
+        self.raw_moves_mut()
+            .iter_mut()
+            .map(move |raw| DefragmentationMove::new(raw, allocator))
+    }
+
+    #[inline]
+    pub fn move_count(&self) -> usize {
+        self.pass_info.moveCount as usize
+    }
+
+    /// Ends the pass. Returns `true` if more passes are possible.
+    pub fn end(mut self) -> bool {
+        self.end_pass()
+    }
+
+    fn raw_moves_mut(&mut self) -> &mut [ffi::VmaDefragmentationMove] {
+        if self.pass_info.pMoves.is_null() {
+            return &mut [];
+        }
+        unsafe { slice::from_raw_parts_mut(self.pass_info.pMoves, self.move_count()) }
+    }
+
+    fn end_pass(&mut self) -> bool {
+        if self.ended {
+            return !self.context.finished.get();
+        }
+        self.ended = true;
+
+        let allocator = self.context.allocator;
+        untrack_destroyed_allocations(allocator, self.raw_moves_mut());
+        let result = unsafe {
+            ffi::vmaEndDefragmentationPass(
+                self.context.allocator.handle(),
+                self.context.handle,
+                &mut self.pass_info,
+            )
+        };
+        self.context.pass_in_progress.set(false);
+
+        let more_passes = result == vk::Result::INCOMPLETE;
+        if !more_passes {
+            self.context.finished.set(true);
+        }
+        more_passes
+    }
+}
+
+impl<'c, 'a> Drop for DefragmentationPass<'c, 'a> {
+    fn drop(&mut self) {
+        self.end_pass();
+    }
+}
+
+/// What happens to an allocation when its defragmentation pass ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefragmentationMoveOperation {
+    /// The resource has been recreated at the destination and the data copied over. The source
+    /// allocation will point to the new memory. This is the default.
+    Copy,
+    /// The allocation can't be moved. It will remain unchanged.
+    Ignore,
+    /// The resource has been destroyed and the source allocation will be freed.
+    Destroy,
+}
+
+impl DefragmentationMoveOperation {
+    pub fn from_vk(operation: ffi::VmaDefragmentationMoveOperation) -> Self {
+        match operation {
+            ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_COPY => {
+                Self::Copy
+            }
+            ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_IGNORE => {
+                Self::Ignore
+            }
+            ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_DESTROY => {
+                Self::Destroy
+            }
+        }
+    }
+
+    pub fn to_vk(self) -> ffi::VmaDefragmentationMoveOperation {
+        match self {
+            Self::Copy => {
+                ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_COPY
+            }
+            Self::Ignore => {
+                ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_IGNORE
+            }
+            Self::Destroy => {
+                ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_DESTROY
+            }
+        }
+    }
+}
+
+/// Allocations of `Destroy` moves are freed when the pass ends.
+fn untrack_destroyed_allocations(
+    allocator: &MemoryAllocator,
+    moves: &[ffi::VmaDefragmentationMove],
+) {
+    for raw in moves {
+        if raw.operation
+            == ffi::VmaDefragmentationMoveOperation::VMA_DEFRAGMENTATION_MOVE_OPERATION_DESTROY
+        {
+            allocator.untrack_tagged_allocation(raw.srcAllocation);
+        }
+    }
+}
+
+/// Describes an allocation that a [`DefragmentationPass`] wants to move.
+pub struct DefragmentationMove<'p> {
+    raw: &'p mut ffi::VmaDefragmentationMove,
+    src_info: AllocationInfo,
+    dst_info: AllocationInfo,
+}
+
+impl<'p> DefragmentationMove<'p> {
+    fn new(raw: &'p mut ffi::VmaDefragmentationMove, allocator: &MemoryAllocator) -> Self {
+        let src_info = allocator.vma_get_allocation_info(raw.srcAllocation);
+        let dst_info = allocator.vma_get_allocation_info(raw.dstTmpAllocation);
+        Self {
+            raw,
+            src_info,
+            dst_info,
+        }
+    }
+
+    /// Keep the allocation where it is.
+    pub fn ignore(&mut self) {
+        self.set_operation(DefragmentationMoveOperation::Ignore);
+    }
+
+    /// The resource has been destroyed so the allocation should be freed.
+    pub fn destroy(&mut self) {
+        self.set_operation(DefragmentationMoveOperation::Destroy);
+    }
+
+    #[inline]
+    pub fn set_operation(&mut self, operation: DefragmentationMoveOperation) {
+        self.raw.operation = operation.to_vk();
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn operation(&self) -> DefragmentationMoveOperation {
+        DefragmentationMoveOperation::from_vk(self.raw.operation)
+    }
+
+    /// The allocation being moved.
+    #[inline]
+    pub fn src_allocation(&self) -> ffi::VmaAllocation {
+        self.raw.srcAllocation
+    }
+
+    /// Temporary allocation at the destination that can be used to bind a new buffer/image
+    /// e.g. with `MemoryAllocator::vma_bind_buffer_memory`. Don't store this, it is destroyed
+    /// when the pass ends.
+    #[inline]
+    pub fn dst_tmp_allocation(&self) -> ffi::VmaAllocation {
+        self.raw.dstTmpAllocation
+    }
+
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.src_info.size
+    }
+
+    #[inline]
+    pub fn src_memory(&self) -> vk::DeviceMemory {
+        self.src_info.device_memory
+    }
+
+    #[inline]
+    pub fn src_offset(&self) -> vk::DeviceSize {
+        self.src_info.offset
+    }
+
+    #[inline]
+    pub fn dst_memory(&self) -> vk::DeviceMemory {
+        self.dst_info.device_memory
+    }
+
+    #[inline]
+    pub fn dst_offset(&self) -> vk::DeviceSize {
+        self.dst_info.offset
+    }
+
+    #[inline]
+    pub fn memory_type(&self) -> u32 {
+        self.src_info.memory_type
+    }
+}
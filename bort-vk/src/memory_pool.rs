@@ -1,8 +1,16 @@
-use crate::{AllocatorAccess, Device, MemoryAllocator};
+use crate::{AllocatorAccess, BufferProperties, Device, ImageProperties, MemoryAllocator};
 use ash::{prelude::VkResult, vk};
-use bort_vma::ffi;
+use bort_vma::{ffi, AllocationCreateInfo, AllocatorPool, DetailedStatistics, Statistics};
 use std::{ffi::CStr, sync::Arc};
 
+/// A VMA custom pool. Wrap it in an `Arc` and pass it as the `alloc_access` argument of e.g.
+/// `Buffer::new` or `Image::new` to allocate from the pool instead of the default allocator.
+/// Owns the pool handle; [`Self::allocator_pool`] gives access to the rest of the
+/// [`bort_vma::AllocatorPool`] API.
+///
+/// Once a pool with a `max_block_count` is full, allocating from it fails with
+/// `ERROR_OUT_OF_DEVICE_MEMORY` rather than falling back to other memory, which makes pools
+/// handy for giving e.g. streaming resources a fixed budget separate from render targets.
 pub struct MemoryPool {
     handle: ffi::VmaPool,
     properties: MemoryPoolPropeties,
@@ -45,16 +53,47 @@ impl MemoryPool {
         })
     }
 
+    /// Creates a pool with a memory type suitable for buffers like `buffer_properties` allocated
+    /// with `allocation_info`. `properties.memory_type_index` is overwritten.
+    pub fn new_for_buffers(
+        memory_allocator: Arc<MemoryAllocator>,
+        buffer_properties: &BufferProperties,
+        allocation_info: &AllocationCreateInfo,
+        mut properties: MemoryPoolPropeties,
+    ) -> VkResult<Self> {
+        properties.memory_type_index = unsafe {
+            memory_allocator.find_memory_type_index_for_buffer_info(
+                &buffer_properties.create_info(),
+                allocation_info,
+            )
+        }?;
+        Self::new(memory_allocator, properties)
+    }
+
+    /// Creates a pool with a memory type suitable for images like `image_properties` allocated
+    /// with `allocation_info`. `properties.memory_type_index` is overwritten.
+    pub fn new_for_images(
+        memory_allocator: Arc<MemoryAllocator>,
+        image_properties: &ImageProperties,
+        allocation_info: &AllocationCreateInfo,
+        mut properties: MemoryPoolPropeties,
+    ) -> VkResult<Self> {
+        properties.memory_type_index = unsafe {
+            memory_allocator.find_memory_type_index_for_image_info(
+                image_properties.create_info(),
+                allocation_info,
+            )
+        }?;
+        Self::new(memory_allocator, properties)
+    }
+
     pub fn set_name(&self, name: Option<&CStr>) {
         if self.handle.is_null() {
             return;
         }
         unsafe {
-            ffi::vmaSetPoolName(
-                self.memory_allocator.handle(),
-                self.handle,
-                name.map_or(std::ptr::null(), CStr::as_ptr),
-            );
+            self.allocator_pool()
+                .set_name(self.memory_allocator.handle(), name)
         }
     }
 
@@ -62,63 +101,58 @@ impl MemoryPool {
         if self.handle.is_null() {
             return None;
         }
-        let mut ptr: *const ::std::os::raw::c_char = std::ptr::null();
-        unsafe {
-            ffi::vmaGetPoolName(self.memory_allocator.handle(), self.handle, &mut ptr);
-            if ptr.is_null() {
-                return None;
-            }
-            Some(CStr::from_ptr(ptr))
-        }
+        unsafe { self.allocator_pool().name(self.memory_allocator.handle()) }
     }
 
-    /// Retrieves statistics of existing `AllocatorPool` object.
-    pub fn get_statistics(&self) -> VkResult<ffi::VmaStatistics> {
-        unsafe {
-            let mut pool_stats: ffi::VmaStatistics = std::mem::zeroed();
-            ffi::vmaGetPoolStatistics(self.memory_allocator.handle(), self.handle, &mut pool_stats);
-            Ok(pool_stats)
-        }
+    /// Basic statistics of this pool. Fast enough to call every frame, e.g. to monitor how much of
+    /// a streaming pool's budget is in use.
+    pub fn statistics(&self) -> VkResult<Statistics> {
+        Ok(unsafe {
+            self.allocator_pool()
+                .statistics(self.memory_allocator.handle())
+        })
     }
 
-    /// Retrieves statistics of existing `AllocatorPool` object.
-    pub fn calculate_statistics(&self) -> VkResult<ffi::VmaDetailedStatistics> {
-        unsafe {
-            let mut pool_stats: ffi::VmaDetailedStatistics = std::mem::zeroed();
-            ffi::vmaCalculatePoolStatistics(
-                self.memory_allocator.handle(),
-                self.handle,
-                &mut pool_stats,
-            );
-            Ok(pool_stats)
-        }
+    /// Detailed statistics of this pool including free range sizes. Slower than
+    /// [`Self::statistics`] as it iterates over all blocks and allocations in the pool.
+    pub fn calculate_statistics(&self) -> VkResult<DetailedStatistics> {
+        Ok(unsafe {
+            self.allocator_pool()
+                .calculate_statistics(self.memory_allocator.handle())
+        })
     }
 
-    /// Checks magic number in margins around all allocations in given memory pool in search for corruptions.
+    /// Checks magic number in margins around all allocations in this pool in search for corruptions.
     ///
     /// Corruption detection is enabled only when `VMA_DEBUG_DETECT_CORRUPTION` macro is defined to nonzero,
     /// `VMA_DEBUG_MARGIN` is defined to nonzero and the pool is created in memory type that is
     /// `ash::vk::MemoryPropertyFlags::HOST_VISIBLE` and `ash::vk::MemoryPropertyFlags::HOST_COHERENT`.
+    /// Otherwise [`PoolCorruptionCheck::NotEnabled`] is returned. `VMA_ASSERT` is also fired
+    /// when corruption is found.
     ///
-    /// Possible error values:
-    ///
-    /// - `ash::vk::Result::ERROR_FEATURE_NOT_PRESENT` - corruption detection is not enabled for specified pool.
-    /// - `ash::vk::Result::ERROR_VALIDATION_FAILED_EXT` - corruption detection has been performed and found memory corruptions around one of the allocations.
-    ///   `VMA_ASSERT` is also fired in that case.
-    /// - Other value: Error returned by Vulkan, e.g. memory mapping failure.
-    pub fn check_corruption(&self) -> VkResult<()> {
-        unsafe { ffi::vmaCheckPoolCorruption(self.memory_allocator.handle(), self.handle).result() }
+    /// Errors are other values returned by Vulkan e.g. memory mapping failure.
+    pub fn check_corruption(&self) -> VkResult<PoolCorruptionCheck> {
+        let res = unsafe {
+            self.allocator_pool()
+                .check_corruption(self.memory_allocator.handle())
+        };
+        PoolCorruptionCheck::from_result(res)
     }
 
     // Getters
 
-    /// Access the `bort_vma::AllocatorPool` struct that `self` contains. Allows you to access pool-related
-    /// vma related functions.
     #[inline]
     pub fn handle(&self) -> ffi::VmaPool {
         self.handle
     }
 
+    /// The [`bort_vma::AllocatorPool`] for the pool handle. Allows you to access pool-related vma
+    /// functions. Only valid while `self` is alive.
+    #[inline]
+    pub fn allocator_pool(&self) -> AllocatorPool {
+        AllocatorPool(self.handle)
+    }
+
     #[inline]
     pub fn properties(&self) -> MemoryPoolPropeties {
         self.properties
@@ -153,6 +187,29 @@ impl Drop for MemoryPool {
     }
 }
 
+/// Outcome of [`MemoryPool::check_corruption`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolCorruptionCheck {
+    /// The margins around all allocations in the pool are intact.
+    NoCorruption,
+    /// Corruption was found around at least one of the allocations.
+    CorruptionFound,
+    /// Corruption detection isn't enabled for this pool.
+    NotEnabled,
+}
+
+impl PoolCorruptionCheck {
+    /// Interprets the result of `vmaCheckPoolCorruption`.
+    pub fn from_result(res: vk::Result) -> VkResult<Self> {
+        match res {
+            vk::Result::SUCCESS => Ok(Self::NoCorruption),
+            vk::Result::ERROR_VALIDATION_FAILED_EXT => Ok(Self::CorruptionFound),
+            vk::Result::ERROR_FEATURE_NOT_PRESENT => Ok(Self::NotEnabled),
+            err => Err(err),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct MemoryPoolPropeties {
     /// Use combination of `VmaPoolCreateFlagBits`.
@@ -213,6 +270,37 @@ impl Default for MemoryPoolPropeties {
 }
 
 impl MemoryPoolPropeties {
+    /// A pool of at most `max_block_count` blocks of `block_size` bytes e.g. for streaming
+    /// resources that should stay within a fixed budget. Allocations fail with
+    /// `ERROR_OUT_OF_DEVICE_MEMORY` once the pool is full.
+    pub fn new_fixed_budget(block_size: vk::DeviceSize, max_block_count: usize) -> Self {
+        Self {
+            block_size,
+            max_block_count,
+            ..Self::default()
+        }
+    }
+
+    /// A pool with automatically sized blocks (dedicated allocations allowed) and the highest
+    /// priority e.g. for render targets. The priority only has an effect if the allocator was
+    /// created with `VK_EXT_memory_priority`.
+    pub fn new_high_priority() -> Self {
+        Self {
+            priority: 1.,
+            ..Self::default()
+        }
+    }
+
+    /// Total size of the pool once it's full i.e. `block_size * max_block_count`. `None` if either
+    /// is left to VMA (0) so the pool has no fixed budget.
+    pub fn budget(&self) -> Option<vk::DeviceSize> {
+        if self.block_size == 0 || self.max_block_count == 0 {
+            return None;
+        }
+        self.block_size
+            .checked_mul(self.max_block_count as vk::DeviceSize)
+    }
+
     pub fn create_info(&self) -> ffi::VmaPoolCreateInfo {
         ffi::VmaPoolCreateInfo {
             flags: self.flags.bits(),
@@ -238,3 +326,33 @@ impl MemoryPoolPropeties {
         }
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn memory_pool_presets_round_trip() {
+    let fixed_budget = MemoryPoolPropeties::new_fixed_budget(1 << 20, 4);
+    let round_trip = MemoryPoolPropeties::from_create_info(&fixed_budget.create_info());
+    assert_eq!(round_trip.block_size, 1 << 20);
+    assert_eq!(round_trip.max_block_count, 4);
+    assert_eq!(round_trip.min_block_count, 0);
+    assert_eq!(fixed_budget.budget(), Some(4 << 20));
+
+    let high_priority = MemoryPoolPropeties::new_high_priority();
+    assert_eq!(high_priority.create_info().blockSize, 0);
+    assert_eq!(high_priority.create_info().priority, 1.);
+    assert_eq!(high_priority.budget(), None);
+
+    assert_eq!(
+        PoolCorruptionCheck::from_result(vk::Result::ERROR_VALIDATION_FAILED_EXT),
+        Ok(PoolCorruptionCheck::CorruptionFound)
+    );
+    assert_eq!(
+        PoolCorruptionCheck::from_result(vk::Result::ERROR_FEATURE_NOT_PRESENT),
+        Ok(PoolCorruptionCheck::NotEnabled)
+    );
+    assert_eq!(
+        PoolCorruptionCheck::from_result(vk::Result::ERROR_MEMORY_MAP_FAILED),
+        Err(vk::Result::ERROR_MEMORY_MAP_FAILED)
+    );
+}
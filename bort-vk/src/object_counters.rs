@@ -0,0 +1,242 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+/// Object types counted by [`ObjectCounters`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CountedObjectType {
+    Pipeline,
+    DescriptorSet,
+    CommandBuffer,
+    Image,
+    Buffer,
+}
+
+impl CountedObjectType {
+    pub const ALL: [Self; 5] = [
+        Self::Pipeline,
+        Self::DescriptorSet,
+        Self::CommandBuffer,
+        Self::Image,
+        Self::Buffer,
+    ];
+
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for CountedObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Pipeline => "pipeline",
+            Self::DescriptorSet => "descriptor set",
+            Self::CommandBuffer => "command buffer",
+            Self::Image => "image",
+            Self::Buffer => "buffer",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Per-device registry of how many objects of each [`CountedObjectType`] have been created and
+/// destroyed, and the peak number alive at once. Handy for spotting leaks and unbounded growth
+/// during soak tests.
+///
+/// Counting is disabled by default; enable it with [`Device::set_object_counting`](crate::Device::set_object_counting).
+/// Objects created while counting was disabled aren't counted when destroyed.
+#[derive(Debug, Default)]
+pub struct ObjectCounters {
+    enabled: AtomicBool,
+    counters: [ObjectCounter; CountedObjectType::ALL.len()],
+}
+
+impl ObjectCounters {
+    #[inline]
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the object was counted. Pass this to [`Self::record_destroyed`] so that
+    /// objects created before counting was enabled don't throw off the live counts.
+    pub(crate) fn record_created(&self, object_type: CountedObjectType) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+        self.counters[object_type.index()].record_created();
+        true
+    }
+
+    pub(crate) fn record_destroyed(&self, object_type: CountedObjectType, counted: bool) {
+        if counted {
+            self.counters[object_type.index()].record_destroyed();
+        }
+    }
+
+    /// Zeroes the created and destroyed counts and restarts the peak from the number of objects
+    /// currently alive. Live counts are kept so objects counted before the reset are still
+    /// reported as alive until they're destroyed, meaning `live` can exceed
+    /// `created - destroyed` afterwards.
+    pub fn reset(&self) {
+        for counter in &self.counters {
+            counter.created.store(0, Ordering::Relaxed);
+            counter.destroyed.store(0, Ordering::Relaxed);
+            let live = counter.live.load(Ordering::Relaxed);
+            counter.peak_live.store(live, Ordering::Relaxed);
+        }
+    }
+
+    pub fn counts(&self, object_type: CountedObjectType) -> ObjectCounts {
+        self.counters[object_type.index()].counts(object_type)
+    }
+
+    pub fn report(&self) -> ObjectCountReport {
+        ObjectCountReport {
+            counts: CountedObjectType::ALL.map(|object_type| self.counts(object_type)),
+        }
+    }
+}
+
+/// A snapshot of the counts for one [`CountedObjectType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectCounts {
+    pub object_type: CountedObjectType,
+    pub created: u64,
+    pub destroyed: u64,
+    /// Number of counted objects alive when the snapshot was taken, including ones created before
+    /// the last [`ObjectCounters::reset`].
+    pub live: u64,
+    /// Highest number of objects alive at once.
+    pub peak_live: u64,
+}
+
+impl ObjectCounts {
+    #[inline]
+    pub fn live(&self) -> u64 {
+        self.live
+    }
+}
+
+/// A snapshot of all counts of an [`ObjectCounters`]. The `Display` impl prints a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectCountReport {
+    pub counts: [ObjectCounts; CountedObjectType::ALL.len()],
+}
+
+impl ObjectCountReport {
+    pub fn get(&self, object_type: CountedObjectType) -> ObjectCounts {
+        self.counts[object_type.index()]
+    }
+
+    /// Object types with objects still alive.
+    pub fn live_types(&self) -> impl Iterator<Item = &ObjectCounts> {
+        self.counts.iter().filter(|counts| counts.live() > 0)
+    }
+}
+
+impl fmt::Display for ObjectCountReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>10} {:>10} {:>10} {:>10}",
+            "object type", "created", "destroyed", "live", "peak live"
+        )?;
+        for counts in &self.counts {
+            writeln!(
+                f,
+                "{:<16} {:>10} {:>10} {:>10} {:>10}",
+                counts.object_type.to_string(),
+                counts.created,
+                counts.destroyed,
+                counts.live(),
+                counts.peak_live
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Helper Functions
+
+#[derive(Debug, Default)]
+struct ObjectCounter {
+    created: AtomicU64,
+    destroyed: AtomicU64,
+    live: AtomicU64,
+    peak_live: AtomicU64,
+}
+
+impl ObjectCounter {
+    fn record_created(&self) {
+        self.created.fetch_add(1, Ordering::Relaxed);
+        // `live` is updated in a single atomic op so concurrent creations each see a distinct
+        // value and the peak can't be missed
+        let live = self.live.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_live.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn record_destroyed(&self) {
+        self.destroyed.fetch_add(1, Ordering::Relaxed);
+        self.live.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn counts(&self, object_type: CountedObjectType) -> ObjectCounts {
+        ObjectCounts {
+            object_type,
+            created: self.created.load(Ordering::Relaxed),
+            destroyed: self.destroyed.load(Ordering::Relaxed),
+            live: self.live.load(Ordering::Relaxed),
+            peak_live: self.peak_live.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn object_counters_peak_live() {
+    let counters = ObjectCounters::default();
+    assert!(!counters.record_created(CountedObjectType::Buffer));
+
+    counters.set_enabled(true);
+    let counted: Vec<bool> = (0..3)
+        .map(|_| counters.record_created(CountedObjectType::Buffer))
+        .collect();
+    for &counted in &counted[..2] {
+        counters.record_destroyed(CountedObjectType::Buffer, counted);
+    }
+    counters.record_created(CountedObjectType::Buffer);
+    // created before counting was enabled
+    counters.record_destroyed(CountedObjectType::Buffer, false);
+
+    let report = counters.report();
+    let buffers = report.get(CountedObjectType::Buffer);
+    assert_eq!(
+        (buffers.created, buffers.destroyed, buffers.live()),
+        (4, 2, 2)
+    );
+    assert_eq!(buffers.peak_live, 3);
+    assert_eq!(report.live_types().count(), 1);
+    assert_eq!(report.get(CountedObjectType::Image).created, 0);
+
+    // objects alive across a reset are still counted as live
+    counters.reset();
+    let buffers = counters.counts(CountedObjectType::Buffer);
+    assert_eq!(
+        (buffers.created, buffers.live(), buffers.peak_live),
+        (0, 2, 2)
+    );
+    counters.record_destroyed(CountedObjectType::Buffer, counted[2]);
+    let buffers = counters.counts(CountedObjectType::Buffer);
+    assert_eq!(
+        (buffers.destroyed, buffers.live(), buffers.peak_live),
+        (1, 1, 2)
+    );
+}
@@ -0,0 +1,605 @@
+use crate::{
+    allocation_info_cpu_accessible, allocation_info_device_local, AllocationAccess,
+    AllocationError, AllocatorAccess, Buffer, BufferProperties, CommandBuffer, DeletionQueue,
+    Device, DeviceOwned, MemoryError, PhysicalDevice, ALLOCATION_CALLBACK_NONE,
+};
+use ash::{
+    ext,
+    prelude::VkResult,
+    vk::{self, Handle},
+    RawPtr,
+};
+use std::{error, fmt, mem, sync::Arc};
+
+/// Device addresses of micromap build inputs and scratch memory must be 256 byte aligned.
+const MICROMAP_BUILD_BUFFER_ALIGNMENT: vk::DeviceSize = 256;
+
+/// `VK_EXT_opacity_micromap` capabilities of a physical device. Opacity micromaps store
+/// per-micro-triangle opacity for ray tracing triangle geometry so that alpha tested geometry
+/// can skip most any-hit shader invocations.
+///
+/// Setup:
+/// - enable `VK_EXT_opacity_micromap` and the `micromap` feature (plus the acceleration structure
+///   and buffer device address requirements)
+/// - build an [`OpacityMicromap`] with an [`OpacityMicromapBuilder`]
+/// - chain [`OpacityMicromap::triangles_attachment`] into the
+///   `vk::AccelerationStructureGeometryTrianglesDataKHR` of the geometry and build the bottom
+///   level acceleration structure after a [`micromap_build_barrier`]
+#[derive(Debug, Clone, Copy)]
+pub struct OpacityMicromapSupport {
+    pub features: vk::PhysicalDeviceOpacityMicromapFeaturesEXT<'static>,
+    pub properties: vk::PhysicalDeviceOpacityMicromapPropertiesEXT<'static>,
+}
+
+impl OpacityMicromapSupport {
+    /// `None` if `physical_device` doesn't support `VK_EXT_opacity_micromap`.
+    pub fn query(physical_device: &PhysicalDevice) -> Option<Self> {
+        let instance = physical_device.instance();
+        let features = instance
+            .physical_device_extension_features(physical_device, vk::EXT_OPACITY_MICROMAP_NAME)?;
+        let properties = instance
+            .physical_device_extension_properties(physical_device, vk::EXT_OPACITY_MICROMAP_NAME)?;
+        Some(Self {
+            features,
+            properties,
+        })
+    }
+
+    #[inline]
+    pub fn is_supported(&self) -> bool {
+        self.features.micromap == vk::TRUE
+    }
+
+    /// Highest subdivision level supported for `format`.
+    pub fn max_subdivision_level(&self, format: vk::OpacityMicromapFormatEXT) -> u32 {
+        match format {
+            vk::OpacityMicromapFormatEXT::TYPE_2_STATE => {
+                self.properties.max_opacity2_state_subdivision_level
+            }
+            _ => self.properties.max_opacity4_state_subdivision_level,
+        }
+    }
+}
+
+/// A `VK_EXT_opacity_micromap` micromap and the buffer backing it.
+pub struct Micromap {
+    handle: vk::MicromapEXT,
+    ty: vk::MicromapTypeEXT,
+    buffer: Buffer,
+    loader: Arc<ext::opacity_micromap::Device>,
+}
+
+impl Micromap {
+    /// Creates a device local buffer of `size` bytes and a micromap covering it. `size` usually
+    /// comes from [`micromap_build_sizes`].
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        ty: vk::MicromapTypeEXT,
+        size: vk::DeviceSize,
+    ) -> VkResult<Self> {
+        let loader = alloc_access
+            .device()
+            .extension_loader::<ext::opacity_micromap::Device>();
+        let buffer = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(
+                size,
+                vk::BufferUsageFlags::MICROMAP_STORAGE_EXT
+                    | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            ),
+            allocation_info_device_local(),
+        )?;
+
+        let create_info = vk::MicromapCreateInfoEXT::default()
+            .buffer(buffer.handle())
+            .size(size)
+            .ty(ty);
+        let mut handle = vk::MicromapEXT::null();
+        unsafe {
+            (loader.fp().create_micromap_ext)(
+                loader.device(),
+                &create_info,
+                ALLOCATION_CALLBACK_NONE.as_raw_ptr(),
+                &mut handle,
+            )
+        }
+        .result()?;
+
+        Ok(Self {
+            handle,
+            ty,
+            buffer,
+            loader,
+        })
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> vk::MicromapEXT {
+        self.handle
+    }
+
+    #[inline]
+    pub fn ty(&self) -> vk::MicromapTypeEXT {
+        self.ty
+    }
+
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+}
+
+impl DeviceOwned for Micromap {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.buffer.device()
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+impl Drop for Micromap {
+    fn drop(&mut self) {
+        unsafe {
+            (self.loader.fp().destroy_micromap_ext)(
+                self.loader.device(),
+                self.handle,
+                ALLOCATION_CALLBACK_NONE.as_raw_ptr(),
+            )
+        }
+    }
+}
+
+/// Sizes required to build a micromap on the device.
+///
+/// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetMicromapBuildSizesEXT.html>
+pub fn micromap_build_sizes(
+    device: &Device,
+    build_info: &vk::MicromapBuildInfoEXT,
+) -> vk::MicromapBuildSizesInfoEXT<'static> {
+    let loader = device.extension_loader::<ext::opacity_micromap::Device>();
+    let mut size_info = vk::MicromapBuildSizesInfoEXT::default();
+    unsafe {
+        (loader.fp().get_micromap_build_sizes_ext)(
+            loader.device(),
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            build_info,
+            &mut size_info,
+        )
+    };
+    size_info
+}
+
+/// Synchronization2 barrier between micromap builds and acceleration structure builds using the
+/// micromaps. Micromap builds can't be synchronized with the legacy pipeline barrier flags.
+pub fn micromap_build_barrier() -> vk::MemoryBarrier2<'static> {
+    vk::MemoryBarrier2::default()
+        .src_stage_mask(vk::PipelineStageFlags2::MICROMAP_BUILD_EXT)
+        .src_access_mask(vk::AccessFlags2::MICROMAP_WRITE_EXT)
+        .dst_stage_mask(vk::PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR)
+        .dst_access_mask(vk::AccessFlags2::MICROMAP_READ_EXT)
+}
+
+/// Collects per-triangle opacity values and records an opacity micromap build.
+///
+/// Each triangle is subdivided into `4^subdivision_level` micro-triangles (in the bird curve
+/// order described by the `VK_EXT_opacity_micromap` spec) with one opacity value each:
+/// - `TYPE_2_STATE`: 0 = transparent, 1 = opaque.
+/// - `TYPE_4_STATE`: additionally 2 = unknown transparent and 3 = unknown opaque, which invoke
+///   the any-hit shader.
+///
+/// Create it with [`Self::new_for_device`] to reject subdivision levels above the device limits.
+#[derive(Debug, Clone, Default)]
+pub struct OpacityMicromapBuilder {
+    data: Vec<u8>,
+    triangles: Vec<vk::MicromapTriangleEXT>,
+    usage_counts: Vec<vk::MicromapUsageEXT>,
+    /// `maxOpacity2StateSubdivisionLevel` and `maxOpacity4StateSubdivisionLevel`.
+    max_subdivision_levels: Option<[u32; 2]>,
+}
+
+impl OpacityMicromapBuilder {
+    /// Only subdivision levels that would overflow the micro-triangle count are rejected. Prefer
+    /// [`Self::new_for_device`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects triangles with subdivision levels above the limits in `support`.
+    pub fn new_for_device(support: &OpacityMicromapSupport) -> Self {
+        Self {
+            max_subdivision_levels: Some([
+                support.max_subdivision_level(vk::OpacityMicromapFormatEXT::TYPE_2_STATE),
+                support.max_subdivision_level(vk::OpacityMicromapFormatEXT::TYPE_4_STATE),
+            ]),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a micromap triangle with one opacity value per micro-triangle. Returns the index of
+    /// the micromap triangle.
+    pub fn add_triangle(
+        &mut self,
+        format: vk::OpacityMicromapFormatEXT,
+        subdivision_level: u16,
+        opacity_values: &[u8],
+    ) -> Result<u32, MicromapError> {
+        let max_subdivision_level = self.max_subdivision_level(format);
+        let micro_triangle_count = micro_triangle_count(subdivision_level)
+            .filter(|_| subdivision_level as u32 <= max_subdivision_level)
+            .ok_or(MicromapError::SubdivisionLevelTooHigh {
+                subdivision_level,
+                max_subdivision_level,
+            })?;
+        if opacity_values.len() != micro_triangle_count {
+            return Err(MicromapError::OpacityValueCount {
+                expected: micro_triangle_count,
+                actual: opacity_values.len(),
+            });
+        }
+        let bits_per_value = opacity_bits_per_value(format);
+        let max_value = (1 << bits_per_value) - 1;
+        if let Some(&value) = opacity_values.iter().find(|&&value| value > max_value) {
+            return Err(MicromapError::InvalidOpacityValue { value, format });
+        }
+
+        let triangle_index = self.triangles.len() as u32;
+        self.triangles.push(vk::MicromapTriangleEXT {
+            data_offset: self.data.len() as u32,
+            subdivision_level,
+            format: format.as_raw() as u16,
+        });
+        self.data
+            .extend(pack_opacity_values(opacity_values, bits_per_value));
+
+        match self.usage_counts.iter_mut().find(|usage| {
+            usage.subdivision_level == subdivision_level as u32
+                && usage.format == format.as_raw() as u32
+        }) {
+            Some(usage) => usage.count += 1,
+            None => self.usage_counts.push(vk::MicromapUsageEXT {
+                count: 1,
+                subdivision_level: subdivision_level as u32,
+                format: format.as_raw() as u32,
+            }),
+        }
+
+        Ok(triangle_index)
+    }
+
+    /// Records the micromap build. The input and scratch buffers are pushed to
+    /// `deletion_queue`. Record a [`micromap_build_barrier`] before building acceleration
+    /// structures using the micromap.
+    pub fn build(
+        &self,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        command_buffer: &CommandBuffer,
+        flags: vk::BuildMicromapFlagsEXT,
+        deletion_queue: &mut DeletionQueue,
+    ) -> Result<OpacityMicromap, MicromapError> {
+        if self.triangles.is_empty() {
+            return Err(MicromapError::NoTriangles);
+        }
+        let device = alloc_access.device().clone();
+        let input_usage = vk::BufferUsageFlags::MICROMAP_BUILD_INPUT_READ_ONLY_EXT
+            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+
+        let data_buffer = new_input_buffer(alloc_access.clone(), &self.data, input_usage)?;
+        let triangle_bytes: Vec<u8> = self
+            .triangles
+            .iter()
+            .flat_map(|triangle| {
+                let mut bytes = triangle.data_offset.to_ne_bytes().to_vec();
+                bytes.extend_from_slice(&triangle.subdivision_level.to_ne_bytes());
+                bytes.extend_from_slice(&triangle.format.to_ne_bytes());
+                bytes
+            })
+            .collect();
+        let triangle_buffer = new_input_buffer(alloc_access.clone(), &triangle_bytes, input_usage)?;
+
+        let mut build_info = vk::MicromapBuildInfoEXT::default()
+            .ty(vk::MicromapTypeEXT::OPACITY_MICROMAP)
+            .flags(flags)
+            .mode(vk::BuildMicromapModeEXT::BUILD)
+            .usage_counts(&self.usage_counts)
+            .data(vk::DeviceOrHostAddressConstKHR {
+                device_address: data_buffer.device_address(),
+            })
+            .triangle_array(vk::DeviceOrHostAddressConstKHR {
+                device_address: triangle_buffer.device_address(),
+            })
+            .triangle_array_stride(mem::size_of::<vk::MicromapTriangleEXT>() as vk::DeviceSize);
+        let build_sizes = micromap_build_sizes(&device, &build_info);
+
+        let micromap = Micromap::new(
+            alloc_access.clone(),
+            vk::MicromapTypeEXT::OPACITY_MICROMAP,
+            build_sizes.micromap_size,
+        )
+        .map_err(MicromapError::Creation)?;
+        let scratch_buffer = Buffer::new_with_alignment(
+            alloc_access,
+            BufferProperties::new_default(
+                build_sizes.build_scratch_size.max(4),
+                vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            ),
+            allocation_info_device_local(),
+            MICROMAP_BUILD_BUFFER_ALIGNMENT,
+        )
+        .map_err(MicromapError::BufferCreation)?;
+
+        build_info =
+            build_info
+                .dst_micromap(micromap.handle())
+                .scratch_data(vk::DeviceOrHostAddressKHR {
+                    device_address: scratch_buffer.device_address(),
+                });
+        let loader = device.extension_loader::<ext::opacity_micromap::Device>();
+        unsafe { (loader.fp().cmd_build_micromaps_ext)(command_buffer.handle(), 1, &build_info) };
+
+        deletion_queue.push(data_buffer);
+        deletion_queue.push(triangle_buffer);
+        deletion_queue.push(scratch_buffer);
+
+        Ok(OpacityMicromap {
+            micromap,
+            usage_counts: self.usage_counts.clone(),
+            triangle_count: self.triangles.len() as u32,
+        })
+    }
+
+    /// The device limit for `format` if known, otherwise the highest level whose micro-triangle
+    /// count fits in a `usize`.
+    fn max_subdivision_level(&self, format: vk::OpacityMicromapFormatEXT) -> u32 {
+        match (self.max_subdivision_levels, format) {
+            (Some([two_state, _]), vk::OpacityMicromapFormatEXT::TYPE_2_STATE) => two_state,
+            (Some([_, four_state]), _) => four_state,
+            (None, _) => usize::BITS / 2 - 1,
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn triangle_count(&self) -> u32 {
+        self.triangles.len() as u32
+    }
+
+    /// Number of micromap triangles per subdivision level and format.
+    #[inline]
+    pub fn usage_counts(&self) -> &[vk::MicromapUsageEXT] {
+        &self.usage_counts
+    }
+}
+
+/// An opacity [`Micromap`] built by an [`OpacityMicromapBuilder`].
+pub struct OpacityMicromap {
+    micromap: Micromap,
+    usage_counts: Vec<vk::MicromapUsageEXT>,
+    triangle_count: u32,
+}
+
+impl OpacityMicromap {
+    /// Attaches the micromap to triangle geometry where geometry triangle `i` uses micromap
+    /// triangle `base_triangle + i`. Chain it into the `p_next` of the
+    /// `vk::AccelerationStructureGeometryTrianglesDataKHR` and use the same struct for the build
+    /// size query and the build.
+    pub fn triangles_attachment(
+        &self,
+        base_triangle: u32,
+    ) -> vk::AccelerationStructureTrianglesOpacityMicromapEXT<'_> {
+        vk::AccelerationStructureTrianglesOpacityMicromapEXT::default()
+            .index_type(vk::IndexType::NONE_KHR)
+            .base_triangle(base_triangle)
+            .usage_counts(&self.usage_counts)
+            .micromap(self.micromap.handle())
+    }
+
+    /// Attaches the micromap to triangle geometry with an index buffer mapping each geometry
+    /// triangle to a micromap triangle (offset by `base_triangle`) or an
+    /// `vk::OpacityMicromapSpecialIndexEXT`. `usage_counts` are the counts of the micromap
+    /// triangles referenced by the index buffer.
+    pub fn triangles_attachment_indexed<'a>(
+        &'a self,
+        index_buffer_address: vk::DeviceAddress,
+        index_type: vk::IndexType,
+        index_stride: vk::DeviceSize,
+        base_triangle: u32,
+        usage_counts: &'a [vk::MicromapUsageEXT],
+    ) -> vk::AccelerationStructureTrianglesOpacityMicromapEXT<'a> {
+        vk::AccelerationStructureTrianglesOpacityMicromapEXT::default()
+            .index_type(index_type)
+            .index_buffer(vk::DeviceOrHostAddressConstKHR {
+                device_address: index_buffer_address,
+            })
+            .index_stride(index_stride)
+            .base_triangle(base_triangle)
+            .usage_counts(usage_counts)
+            .micromap(self.micromap.handle())
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn micromap(&self) -> &Micromap {
+        &self.micromap
+    }
+
+    #[inline]
+    pub fn usage_counts(&self) -> &[vk::MicromapUsageEXT] {
+        &self.usage_counts
+    }
+
+    #[inline]
+    pub fn triangle_count(&self) -> u32 {
+        self.triangle_count
+    }
+}
+
+// Helper Functions
+
+/// `4^subdivision_level`. `None` on overflow.
+fn micro_triangle_count(subdivision_level: u16) -> Option<usize> {
+    1_usize.checked_shl(2 * subdivision_level as u32)
+}
+
+fn opacity_bits_per_value(format: vk::OpacityMicromapFormatEXT) -> u32 {
+    match format {
+        vk::OpacityMicromapFormatEXT::TYPE_2_STATE => 1,
+        _ => 2,
+    }
+}
+
+/// Packs values from the least significant bit of each byte.
+fn pack_opacity_values(opacity_values: &[u8], bits_per_value: u32) -> Vec<u8> {
+    let values_per_byte = (8 / bits_per_value) as usize;
+    opacity_values
+        .chunks(values_per_byte)
+        .map(|values| {
+            values
+                .iter()
+                .enumerate()
+                .fold(0_u8, |byte, (index, &value)| {
+                    byte | (value << (index as u32 * bits_per_value))
+                })
+        })
+        .collect()
+}
+
+fn new_input_buffer(
+    alloc_access: Arc<dyn AllocatorAccess>,
+    data: &[u8],
+    usage: vk::BufferUsageFlags,
+) -> Result<Buffer, MicromapError> {
+    let mut buffer = Buffer::new_with_alignment(
+        alloc_access,
+        BufferProperties::new_default(data.len() as vk::DeviceSize, usage),
+        allocation_info_cpu_accessible(),
+        MICROMAP_BUILD_BUFFER_ALIGNMENT,
+    )
+    .map_err(MicromapError::BufferCreation)?;
+    buffer
+        .memory_allocation_mut()
+        .write_bytes(data, 0)
+        .map_err(MicromapError::Memory)?;
+    Ok(buffer)
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum MicromapError {
+    NoTriangles,
+    /// A triangle needs one value per micro-triangle i.e. `4^subdivision_level`.
+    OpacityValueCount {
+        expected: usize,
+        actual: usize,
+    },
+    InvalidOpacityValue {
+        value: u8,
+        format: vk::OpacityMicromapFormatEXT,
+    },
+    /// Above the device limit for the format (see [`OpacityMicromapSupport::max_subdivision_level`]).
+    SubdivisionLevelTooHigh {
+        subdivision_level: u16,
+        max_subdivision_level: u32,
+    },
+    Creation(vk::Result),
+    BufferCreation(AllocationError),
+    Memory(MemoryError),
+}
+
+impl fmt::Display for MicromapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTriangles => write!(f, "opacity micromap has no triangles"),
+            Self::OpacityValueCount { expected, actual } => write!(
+                f,
+                "expected {} opacity values (one per micro-triangle) but got {}",
+                expected, actual
+            ),
+            Self::InvalidOpacityValue { value, format } => write!(
+                f,
+                "opacity value {} is invalid for micromap format {:?}",
+                value, format
+            ),
+            Self::SubdivisionLevelTooHigh {
+                subdivision_level,
+                max_subdivision_level,
+            } => write!(
+                f,
+                "micromap subdivision level {} exceeds the maximum of {}",
+                subdivision_level, max_subdivision_level
+            ),
+            Self::Creation(e) => write!(f, "failed to create micromap: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create micromap buffer: {}", e),
+            Self::Memory(e) => write!(f, "failed to write micromap build input: {}", e),
+        }
+    }
+}
+
+impl error::Error for MicromapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Creation(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn opacity_micromap_packing_and_usage_counts() {
+    let mut builder = OpacityMicromapBuilder::new();
+    let two_state = vk::OpacityMicromapFormatEXT::TYPE_2_STATE;
+    let four_state = vk::OpacityMicromapFormatEXT::TYPE_4_STATE;
+
+    assert_eq!(
+        builder.add_triangle(two_state, 1, &[1, 0, 1, 1]).unwrap(),
+        0
+    );
+    assert_eq!(
+        builder.add_triangle(four_state, 1, &[3, 2, 1, 0]).unwrap(),
+        1
+    );
+    assert_eq!(builder.add_triangle(two_state, 1, &[0; 4]).unwrap(), 2);
+    assert!(matches!(
+        builder.add_triangle(two_state, 2, &[0; 4]),
+        Err(MicromapError::OpacityValueCount {
+            expected: 16,
+            actual: 4
+        })
+    ));
+    assert!(builder.add_triangle(two_state, 0, &[2]).is_err());
+    assert!(matches!(
+        builder.add_triangle(two_state, u16::MAX, &[]),
+        Err(MicromapError::SubdivisionLevelTooHigh { .. })
+    ));
+    let mut limited = OpacityMicromapBuilder {
+        max_subdivision_levels: Some([1, 0]),
+        ..Default::default()
+    };
+    assert!(limited.add_triangle(two_state, 1, &[0; 4]).is_ok());
+    assert!(matches!(
+        limited.add_triangle(four_state, 1, &[0; 4]),
+        Err(MicromapError::SubdivisionLevelTooHigh {
+            subdivision_level: 1,
+            max_subdivision_level: 0
+        })
+    ));
+
+    assert_eq!(builder.data, vec![0b1101, 0b00_01_10_11, 0]);
+    assert_eq!(builder.triangles[1].data_offset, 1);
+    assert_eq!(builder.triangles[2].data_offset, 2);
+    assert_eq!(builder.usage_counts().len(), 2);
+    assert_eq!(builder.usage_counts()[0].count, 2);
+    assert_eq!(builder.triangle_count(), 3);
+}
@@ -0,0 +1,455 @@
+use crate::{CommandBuffer, ImageAccess};
+use ash::vk;
+use std::collections::HashMap;
+
+/// How a pass uses an image. Determines the pipeline stages, access flags and layout used for
+/// barriers emitted by [`PassScope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageUsage {
+    ColorAttachmentWrite,
+    /// Read and written by blending or load op `LOAD`.
+    ColorAttachmentReadWrite,
+    DepthStencilAttachmentWrite,
+    /// Depth testing without depth writes.
+    DepthStencilAttachmentRead,
+    /// Sampled or input attachment read in a fragment shader.
+    FragmentShaderRead,
+    /// Sampled in a vertex shader e.g. a height map.
+    VertexShaderRead,
+    ComputeShaderRead,
+    /// Storage image written (and possibly read) in a compute shader.
+    ComputeShaderWrite,
+    TransferRead,
+    TransferWrite,
+    /// Presented to a swapchain. Use as the last usage of the frame.
+    Present,
+}
+
+impl ImageUsage {
+    pub fn stage_mask(&self) -> vk::PipelineStageFlags {
+        match self {
+            Self::ColorAttachmentWrite | Self::ColorAttachmentReadWrite => {
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+            }
+            Self::DepthStencilAttachmentWrite | Self::DepthStencilAttachmentRead => {
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
+            }
+            Self::FragmentShaderRead => vk::PipelineStageFlags::FRAGMENT_SHADER,
+            Self::VertexShaderRead => vk::PipelineStageFlags::VERTEX_SHADER,
+            Self::ComputeShaderRead | Self::ComputeShaderWrite => {
+                vk::PipelineStageFlags::COMPUTE_SHADER
+            }
+            Self::TransferRead | Self::TransferWrite => vk::PipelineStageFlags::TRANSFER,
+            Self::Present => vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        }
+    }
+
+    pub fn access_mask(&self) -> vk::AccessFlags {
+        match self {
+            Self::ColorAttachmentWrite => vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            Self::ColorAttachmentReadWrite => {
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+            }
+            Self::DepthStencilAttachmentWrite => {
+                vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+            }
+            Self::DepthStencilAttachmentRead => vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+            Self::FragmentShaderRead | Self::VertexShaderRead | Self::ComputeShaderRead => {
+                vk::AccessFlags::SHADER_READ
+            }
+            Self::ComputeShaderWrite => {
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE
+            }
+            Self::TransferRead => vk::AccessFlags::TRANSFER_READ,
+            Self::TransferWrite => vk::AccessFlags::TRANSFER_WRITE,
+            Self::Present => vk::AccessFlags::empty(),
+        }
+    }
+
+    pub fn layout(&self) -> vk::ImageLayout {
+        match self {
+            Self::ColorAttachmentWrite | Self::ColorAttachmentReadWrite => {
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+            }
+            Self::DepthStencilAttachmentWrite => vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            Self::DepthStencilAttachmentRead => vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            Self::FragmentShaderRead | Self::VertexShaderRead | Self::ComputeShaderRead => {
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            }
+            Self::ComputeShaderWrite => vk::ImageLayout::GENERAL,
+            Self::TransferRead => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Self::TransferWrite => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            Self::Present => vk::ImageLayout::PRESENT_SRC_KHR,
+        }
+    }
+
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Self::ColorAttachmentWrite
+                | Self::ColorAttachmentReadWrite
+                | Self::DepthStencilAttachmentWrite
+                | Self::ComputeShaderWrite
+                | Self::TransferWrite
+        )
+    }
+
+    /// Aspect used for barriers unless overridden with [`ImageUsageTracker::set_aspect_mask`].
+    pub fn default_aspect_mask(&self) -> vk::ImageAspectFlags {
+        match self {
+            Self::DepthStencilAttachmentWrite | Self::DepthStencilAttachmentRead => {
+                vk::ImageAspectFlags::DEPTH
+            }
+            _ => vk::ImageAspectFlags::COLOR,
+        }
+    }
+}
+
+/// The combined usages declared for an image in one pass.
+#[derive(Debug, Clone, Copy)]
+struct PassImageUsage {
+    stage_mask: vk::PipelineStageFlags,
+    access_mask: vk::AccessFlags,
+    layout: vk::ImageLayout,
+    is_write: bool,
+    default_aspect_mask: vk::ImageAspectFlags,
+}
+
+impl From<ImageUsage> for PassImageUsage {
+    fn from(usage: ImageUsage) -> Self {
+        Self {
+            stage_mask: usage.stage_mask(),
+            access_mask: usage.access_mask(),
+            layout: usage.layout(),
+            is_write: usage.is_write(),
+            default_aspect_mask: usage.default_aspect_mask(),
+        }
+    }
+}
+
+/// Remembers the last usage of each image across passes so that [`PassScope`] only emits the
+/// barriers that are actually needed. Images start in `UNDEFINED` layout (i.e. their contents
+/// are discarded on first use) unless set with [`Self::set_layout`].
+///
+/// Only tracks whole images (all mip levels and array layers) on a single queue.
+#[derive(Default)]
+pub struct ImageUsageTracker {
+    images: HashMap<vk::Image, TrackedImage>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedImage {
+    layout: vk::ImageLayout,
+    aspect_mask: Option<vk::ImageAspectFlags>,
+    /// Stage and access of the last write, if any.
+    last_write: Option<(vk::PipelineStageFlags, vk::AccessFlags)>,
+    /// Stages which have read the image since the last write or layout transition.
+    read_stages: vk::PipelineStageFlags,
+    /// Stages and accesses the last write has been made visible to.
+    visible_stages: vk::PipelineStageFlags,
+    visible_access: vk::AccessFlags,
+}
+
+impl Default for TrackedImage {
+    fn default() -> Self {
+        Self {
+            layout: vk::ImageLayout::UNDEFINED,
+            aspect_mask: None,
+            last_write: None,
+            read_stages: vk::PipelineStageFlags::empty(),
+            visible_stages: vk::PipelineStageFlags::empty(),
+            visible_access: vk::AccessFlags::empty(),
+        }
+    }
+}
+
+impl ImageUsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start declaring the image usages of a pass.
+    pub fn begin_pass(&mut self) -> PassScope<'_> {
+        PassScope {
+            tracker: self,
+            usages: Vec::new(),
+        }
+    }
+
+    /// Set the current layout of `image` e.g. for an image uploaded with
+    /// [`Image::new_with_data`](crate::Image::new_with_data). Assumes any previous writes have
+    /// completed.
+    pub fn set_layout(&mut self, image: &dyn ImageAccess, layout: vk::ImageLayout) {
+        let tracked = self.images.entry(image.handle()).or_default();
+        *tracked = TrackedImage {
+            layout,
+            aspect_mask: tracked.aspect_mask,
+            ..TrackedImage::default()
+        };
+    }
+
+    /// Override the aspect used for barriers on `image` e.g. `DEPTH | STENCIL` for combined
+    /// depth/stencil formats.
+    pub fn set_aspect_mask(&mut self, image: &dyn ImageAccess, aspect_mask: vk::ImageAspectFlags) {
+        self.images.entry(image.handle()).or_default().aspect_mask = Some(aspect_mask);
+    }
+
+    /// Stop tracking `image` e.g. before it's destroyed.
+    pub fn forget(&mut self, image: &dyn ImageAccess) {
+        self.images.remove(&image.handle());
+    }
+
+    /// The tracked layout of `image`. `None` if it isn't tracked.
+    pub fn layout(&self, image: &dyn ImageAccess) -> Option<vk::ImageLayout> {
+        self.images
+            .get(&image.handle())
+            .map(|tracked| tracked.layout)
+    }
+
+    /// Updates the tracked state of `image` for `usage`, returning the barrier required first.
+    fn transition(
+        &mut self,
+        image: vk::Image,
+        usage: PassImageUsage,
+    ) -> Option<(vk::PipelineStageFlags, vk::ImageMemoryBarrier<'static>)> {
+        let tracked = self.images.entry(image).or_default();
+        let stage_mask = usage.stage_mask;
+        let access_mask = usage.access_mask;
+        let layout = usage.layout;
+        let layout_change = tracked.layout != layout;
+
+        if !usage.is_write && !layout_change {
+            let write_visible = tracked.last_write.is_none()
+                || (tracked.visible_stages.contains(stage_mask)
+                    && tracked.visible_access.contains(access_mask));
+            if write_visible {
+                tracked.read_stages |= stage_mask;
+                return None;
+            }
+        }
+
+        let (write_stages, write_access) = tracked
+            .last_write
+            .unwrap_or((vk::PipelineStageFlags::empty(), vk::AccessFlags::empty()));
+        let mut src_stage_mask = write_stages;
+        if usage.is_write || layout_change {
+            // write-after-read hazards and layout transitions must also wait for reads
+            src_stage_mask |= tracked.read_stages;
+        }
+        if src_stage_mask.is_empty() {
+            src_stage_mask = vk::PipelineStageFlags::TOP_OF_PIPE;
+        }
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: tracked.aspect_mask.unwrap_or(usage.default_aspect_mask),
+            base_mip_level: 0,
+            level_count: vk::REMAINING_MIP_LEVELS,
+            base_array_layer: 0,
+            layer_count: vk::REMAINING_ARRAY_LAYERS,
+        };
+        let barrier = vk::ImageMemoryBarrier::default()
+            .src_access_mask(write_access)
+            .dst_access_mask(access_mask)
+            .old_layout(tracked.layout)
+            .new_layout(layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range);
+
+        tracked.layout = layout;
+        if usage.is_write {
+            tracked.last_write = Some((stage_mask, access_mask));
+            tracked.read_stages = vk::PipelineStageFlags::empty();
+            tracked.visible_stages = stage_mask;
+            tracked.visible_access = access_mask;
+        } else if layout_change {
+            tracked.read_stages = stage_mask;
+            tracked.visible_stages = stage_mask;
+            tracked.visible_access = access_mask;
+        } else {
+            tracked.read_stages |= stage_mask;
+            tracked.visible_stages |= stage_mask;
+            tracked.visible_access |= access_mask;
+        }
+
+        Some((src_stage_mask, barrier))
+    }
+}
+
+/// Declares how a pass reads and writes images, then records the minimal set of barriers from
+/// their previous usages. Created with [`ImageUsageTracker::begin_pass`]. Declaring the same image
+/// more than once merges the usages into one barrier; the usages must share a layout (e.g.
+/// [`ImageUsage::ComputeShaderRead`] and [`ImageUsage::ComputeShaderWrite`] don't).
+///
+/// The tracker is only updated when the scope is finished with [`Self::record_barriers`] or
+/// [`Self::into_barriers`].
+///
+/// ```ignore
+/// let mut pass = tracker.begin_pass();
+/// pass.reads(&shadow_map, ImageUsage::FragmentShaderRead);
+/// pass.writes(&color_target, ImageUsage::ColorAttachmentWrite);
+/// pass.record_barriers(&command_buffer);
+/// // record the pass...
+/// ```
+#[must_use = "barriers are only emitted by `record_barriers` or `into_barriers`"]
+pub struct PassScope<'a> {
+    tracker: &'a mut ImageUsageTracker,
+    /// Combined usages of each declared image in declaration order.
+    usages: Vec<(vk::Image, PassImageUsage)>,
+}
+
+impl PassScope<'_> {
+    pub fn reads(&mut self, image: &dyn ImageAccess, usage: ImageUsage) -> &mut Self {
+        debug_assert!(!usage.is_write(), "{:?} is a write usage", usage);
+        self.declare(image.handle(), usage)
+    }
+
+    pub fn writes(&mut self, image: &dyn ImageAccess, usage: ImageUsage) -> &mut Self {
+        debug_assert!(usage.is_write(), "{:?} is a read usage", usage);
+        self.declare(image.handle(), usage)
+    }
+
+    /// Records the barriers required by the declared usages. Does nothing if none are needed.
+    pub fn record_barriers(self, command_buffer: &CommandBuffer) -> PassBarriers {
+        let barriers = self.into_barriers();
+        barriers.record(command_buffer);
+        barriers
+    }
+
+    /// Updates the tracker with the declared usages and returns the barriers they require
+    /// without recording them.
+    pub fn into_barriers(self) -> PassBarriers {
+        let mut barriers = PassBarriers::default();
+        for (image, usage) in self.usages {
+            if let Some((src_stage_mask, barrier)) = self.tracker.transition(image, usage) {
+                barriers.src_stage_mask |= src_stage_mask;
+                barriers.dst_stage_mask |= usage.stage_mask;
+                barriers.image_barriers.push(barrier);
+            }
+        }
+        barriers
+    }
+
+    fn declare(&mut self, image: vk::Image, usage: ImageUsage) -> &mut Self {
+        let existing = self
+            .usages
+            .iter_mut()
+            .find(|(declared_image, _)| *declared_image == image);
+        match existing {
+            Some((_, declared)) => {
+                debug_assert_eq!(
+                    declared.layout,
+                    usage.layout(),
+                    "{:?} needs a different layout to the other usages of the image in this pass",
+                    usage
+                );
+                declared.stage_mask |= usage.stage_mask();
+                declared.access_mask |= usage.access_mask();
+                declared.is_write |= usage.is_write();
+            }
+            None => self.usages.push((image, usage.into())),
+        }
+        self
+    }
+}
+
+/// Barriers emitted by a [`PassScope`], merged into one `vkCmdPipelineBarrier` call.
+#[derive(Debug, Clone, Default)]
+pub struct PassBarriers {
+    pub src_stage_mask: vk::PipelineStageFlags,
+    pub dst_stage_mask: vk::PipelineStageFlags,
+    pub image_barriers: Vec<vk::ImageMemoryBarrier<'static>>,
+}
+
+impl PassBarriers {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.image_barriers.is_empty()
+    }
+
+    pub fn record(&self, command_buffer: &CommandBuffer) {
+        if self.is_empty() {
+            return;
+        }
+        command_buffer.pipeline_barrier(
+            self.src_stage_mask,
+            self.dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &self.image_barriers,
+        );
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn pass_scope_minimal_barriers() {
+    use ash::vk::Handle;
+
+    let mut tracker = ImageUsageTracker::new();
+    let color = vk::Image::from_raw(1);
+    let shadow_map = vk::Image::from_raw(2);
+
+    // shadow pass: undefined -> depth attachment
+    let mut pass = tracker.begin_pass();
+    pass.declare(shadow_map, ImageUsage::DepthStencilAttachmentWrite);
+    let barriers = pass.into_barriers();
+    assert_eq!(barriers.image_barriers.len(), 1);
+    assert_eq!(
+        barriers.image_barriers[0].old_layout,
+        vk::ImageLayout::UNDEFINED
+    );
+    assert_eq!(
+        barriers.image_barriers[0].subresource_range.aspect_mask,
+        vk::ImageAspectFlags::DEPTH
+    );
+
+    // main pass: shadow map read after write, color target first use
+    let mut pass = tracker.begin_pass();
+    pass.declare(shadow_map, ImageUsage::FragmentShaderRead)
+        .declare(color, ImageUsage::ColorAttachmentWrite);
+    let barriers = pass.into_barriers();
+    assert_eq!(barriers.image_barriers.len(), 2);
+    assert!(barriers
+        .src_stage_mask
+        .contains(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS));
+    assert_eq!(
+        barriers.image_barriers[0].new_layout,
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+    );
+
+    // reading the shadow map again in the same stage and layout needs no barrier
+    let mut pass = tracker.begin_pass();
+    pass.declare(shadow_map, ImageUsage::FragmentShaderRead);
+    assert!(pass.into_barriers().is_empty());
+
+    // writing it again must wait for the fragment shader reads
+    let mut pass = tracker.begin_pass();
+    pass.declare(shadow_map, ImageUsage::DepthStencilAttachmentWrite);
+    let barriers = pass.into_barriers();
+    assert_eq!(barriers.image_barriers.len(), 1);
+    assert!(barriers
+        .src_stage_mask
+        .contains(vk::PipelineStageFlags::FRAGMENT_SHADER));
+
+    // declaring the color target twice merges the usages into one barrier
+    let mut pass = tracker.begin_pass();
+    pass.declare(color, ImageUsage::FragmentShaderRead)
+        .declare(color, ImageUsage::FragmentShaderRead);
+    let barriers = pass.into_barriers();
+    assert_eq!(barriers.image_barriers.len(), 1);
+    let mut pass = tracker.begin_pass();
+    pass.declare(color, ImageUsage::ColorAttachmentWrite)
+        .declare(color, ImageUsage::ColorAttachmentReadWrite);
+    let barriers = pass.into_barriers();
+    assert_eq!(barriers.image_barriers.len(), 1);
+    assert_eq!(
+        barriers.image_barriers[0].dst_access_mask,
+        vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    );
+}
@@ -0,0 +1,276 @@
+use std::{
+    ops::{Index, IndexMut},
+    slice,
+};
+
+/// Index of a frame in flight. Only valid for indexing [`PerFrame`], so it can't be mixed up with
+/// a [`SwapchainImageIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct FrameIndex(usize);
+
+impl FrameIndex {
+    #[inline]
+    pub fn value(&self) -> usize {
+        self.0
+    }
+}
+
+/// Index of a swapchain image as returned by
+/// [`Swapchain::aquire_next_image`](crate::Swapchain::aquire_next_image). Only valid for indexing
+/// [`PerSwapchainImage`], so it can't be mixed up with a [`FrameIndex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SwapchainImageIndex(u32);
+
+impl SwapchainImageIndex {
+    #[inline]
+    pub fn new(acquired_image_index: u32) -> Self {
+        Self(acquired_image_index)
+    }
+
+    /// The index to pass to `vk::PresentInfoKHR::image_indices`.
+    #[inline]
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for SwapchainImageIndex {
+    fn from(acquired_image_index: u32) -> Self {
+        Self(acquired_image_index)
+    }
+}
+
+/// One `T` per frame in flight e.g. command buffers, fences and acquire semaphores which are
+/// reused once the frame that last used them has finished on the GPU. Keeps track of the current frame, see
+/// [`Self::advance`].
+///
+/// The frames in flight count is independent of the swapchain image count. For resources tied
+/// to a swapchain image (e.g. framebuffers) use [`PerSwapchainImage`] instead.
+#[derive(Debug, Clone)]
+pub struct PerFrame<T> {
+    frames: Vec<T>,
+    current: FrameIndex,
+}
+
+impl<T> PerFrame<T> {
+    /// Creates `frame_count` elements by calling `f` with each frame index. `frame_count` must be
+    /// greater than 0.
+    pub fn from_fn(frame_count: usize, f: impl FnMut(FrameIndex) -> T) -> Self {
+        debug_assert!(frame_count > 0, "frame count must be greater than 0");
+        let frames = (0..frame_count).map(FrameIndex).map(f).collect();
+        Self {
+            frames,
+            current: FrameIndex(0),
+        }
+    }
+
+    /// Same as [`Self::from_fn`] but stops at and returns the first error.
+    pub fn try_from_fn<E>(
+        frame_count: usize,
+        f: impl FnMut(FrameIndex) -> Result<T, E>,
+    ) -> Result<Self, E> {
+        debug_assert!(frame_count > 0, "frame count must be greater than 0");
+        let frames = (0..frame_count)
+            .map(FrameIndex)
+            .map(f)
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            frames,
+            current: FrameIndex(0),
+        })
+    }
+
+    /// Moves on to the next frame in flight, wrapping around after the last one. Returns the new
+    /// current frame index.
+    pub fn advance(&mut self) -> FrameIndex {
+        self.current = FrameIndex((self.current.0 + 1) % self.frames.len());
+        self.current
+    }
+
+    #[inline]
+    pub fn current(&self) -> &T {
+        &self.frames[self.current.0]
+    }
+
+    #[inline]
+    pub fn current_mut(&mut self) -> &mut T {
+        &mut self.frames[self.current.0]
+    }
+
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.frames.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.frames.iter_mut()
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn current_index(&self) -> FrameIndex {
+        self.current
+    }
+
+    /// The number of frames in flight.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl<T> Index<FrameIndex> for PerFrame<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: FrameIndex) -> &T {
+        &self.frames[index.0]
+    }
+}
+
+impl<T> IndexMut<FrameIndex> for PerFrame<T> {
+    #[inline]
+    fn index_mut(&mut self, index: FrameIndex) -> &mut T {
+        &mut self.frames[index.0]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PerFrame<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.frames.iter()
+    }
+}
+
+/// One `T` per swapchain image e.g. framebuffers, image views or the semaphores a present waits
+/// on (a present has no fence so a per-frame semaphore may still be in use when its frame slot
+/// comes around again, but it's done with once its image is acquired again). Recreate it when the
+/// swapchain is recreated because the image count may change.
+///
+/// For resources which are reused once a frame has finished (e.g. command buffers) use
+/// [`PerFrame`] instead.
+#[derive(Debug, Clone)]
+pub struct PerSwapchainImage<T> {
+    images: Vec<T>,
+}
+
+impl<T> PerSwapchainImage<T> {
+    /// `elements` must be in swapchain image order.
+    pub fn from_vec(elements: Vec<T>) -> Self {
+        Self { images: elements }
+    }
+
+    /// Creates `image_count` elements by calling `f` with each swapchain image index.
+    pub fn from_fn(image_count: u32, f: impl FnMut(SwapchainImageIndex) -> T) -> Self {
+        let images = (0..image_count).map(SwapchainImageIndex).map(f).collect();
+        Self { images }
+    }
+
+    /// Same as [`Self::from_fn`] but stops at and returns the first error.
+    pub fn try_from_fn<E>(
+        image_count: u32,
+        f: impl FnMut(SwapchainImageIndex) -> Result<T, E>,
+    ) -> Result<Self, E> {
+        let images = (0..image_count)
+            .map(SwapchainImageIndex)
+            .map(f)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { images })
+    }
+
+    /// Returns `None` if `index` is out of range e.g. an index acquired before the swapchain was
+    /// recreated with fewer images.
+    #[inline]
+    pub fn get(&self, index: SwapchainImageIndex) -> Option<&T> {
+        self.images.get(index.0 as usize)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.images.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.images.iter_mut()
+    }
+
+    // Getters
+
+    /// The number of swapchain images.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
+
+impl<T> Default for PerSwapchainImage<T> {
+    fn default() -> Self {
+        Self { images: Vec::new() }
+    }
+}
+
+impl<T> Index<SwapchainImageIndex> for PerSwapchainImage<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: SwapchainImageIndex) -> &T {
+        &self.images[index.0 as usize]
+    }
+}
+
+impl<T> IndexMut<SwapchainImageIndex> for PerSwapchainImage<T> {
+    #[inline]
+    fn index_mut(&mut self, index: SwapchainImageIndex) -> &mut T {
+        &mut self.images[index.0 as usize]
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PerSwapchainImage<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.images.iter()
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn per_frame_advance_wraps() {
+    let mut frames = PerFrame::from_fn(2, |frame_index| frame_index.value() * 10);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(*frames.current(), 0);
+    assert_eq!(frames.advance(), FrameIndex(1));
+    assert_eq!(*frames.current(), 10);
+    assert_eq!(frames.advance(), FrameIndex(0));
+
+    *frames.current_mut() = 5;
+    assert_eq!(frames[FrameIndex(0)], 5);
+
+    let failed: Result<PerFrame<u32>, &str> = PerFrame::try_from_fn(3, |_| Err("nope"));
+    assert!(failed.is_err());
+}
+
+#[test]
+fn per_swapchain_image_indexing() {
+    let images = PerSwapchainImage::from_fn(3, |image_index| image_index.value() + 1);
+    assert_eq!(images.len(), 3);
+    assert_eq!(images[SwapchainImageIndex::new(2)], 3);
+    assert_eq!(images.get(SwapchainImageIndex::new(3)), None);
+}
@@ -1,9 +1,16 @@
-use crate::{c_string_to_string, ApiVersion, Instance};
-use ash::vk::{self, api_version_major, api_version_minor};
+use crate::{
+    c_string_to_string, ApiVersion, CompressedBlockInfo, CompressionFamily,
+    CooperativeMatrixConfiguration, Instance,
+};
+use ash::{
+    prelude::VkResult,
+    vk::{self, api_version_major, api_version_minor},
+};
 use std::{
+    cmp::min,
     error,
     ffi::{CStr, CString},
-    fmt,
+    fmt, mem, ptr,
     str::Utf8Error,
     sync::Arc,
 };
@@ -17,6 +24,7 @@ pub struct PhysicalDevice {
     queue_family_properties: Vec<vk::QueueFamilyProperties>,
     memory_properties: vk::PhysicalDeviceMemoryProperties,
     extension_properties: Vec<ExtensionProperties>,
+    properties_all: PhysicalDeviceProperties,
 
     // dependencies
     instance: Arc<Instance>,
@@ -55,6 +63,9 @@ impl PhysicalDevice {
             .map(ExtensionProperties::new)
             .collect();
 
+        let properties_all =
+            PhysicalDeviceProperties::query(&instance, handle, properties, &extension_properties);
+
         Ok(Self {
             handle,
             properties,
@@ -63,11 +74,23 @@ impl PhysicalDevice {
             queue_family_properties,
             memory_properties,
             extension_properties,
+            properties_all,
 
             instance,
         })
     }
 
+    /// The highest api version supported by the device (`vk::PhysicalDeviceProperties::api_version`).
+    pub fn api_version(&self) -> ApiVersion {
+        ApiVersion::from_vk_uint(self.properties.api_version)
+    }
+
+    /// The api version usable with this device i.e. [`Self::api_version`] clamped to
+    /// [`Instance::effective_api_version`].
+    pub fn effective_api_version(&self) -> ApiVersion {
+        self.instance.physical_device_api_version(self)
+    }
+
     pub fn supports_min_api_ver(&self, api_version: ApiVersion) -> bool {
         let supported_major = api_version_major(self.properties.api_version);
         let supported_minor = api_version_minor(self.properties.api_version);
@@ -92,6 +115,49 @@ impl PhysicalDevice {
             .any(|props| props.extension_name == extension_name)
     }
 
+    /// Whether BC compressed formats are supported (`textureCompressionBC`). The feature must also
+    /// be enabled when creating the device.
+    pub fn supports_bc(&self) -> bool {
+        self.instance
+            .physical_device_features_1_0(self)
+            .texture_compression_bc
+            == vk::TRUE
+    }
+
+    /// Whether ASTC LDR compressed formats are supported (`textureCompressionASTC_LDR`). The
+    /// feature must also be enabled when creating the device.
+    pub fn supports_astc_ldr(&self) -> bool {
+        self.instance
+            .physical_device_features_1_0(self)
+            .texture_compression_astc_ldr
+            == vk::TRUE
+    }
+
+    /// Whether ETC2 and EAC compressed formats are supported (`textureCompressionETC2`). The
+    /// feature must also be enabled when creating the device.
+    pub fn supports_etc2(&self) -> bool {
+        self.instance
+            .physical_device_features_1_0(self)
+            .texture_compression_etc2
+            == vk::TRUE
+    }
+
+    /// Whether the feature needed for the compression family of `format` is supported. Returns
+    /// `true` for uncompressed formats. Format support should still be checked with
+    /// `vkGetPhysicalDeviceFormatProperties`.
+    pub fn supports_compressed_format(&self, format: vk::Format) -> bool {
+        match CompressedBlockInfo::from_format(format).map(|block_info| block_info.family) {
+            None => true,
+            Some(CompressionFamily::Bc) => self.supports_bc(),
+            Some(CompressionFamily::Etc2) => self.supports_etc2(),
+            Some(CompressionFamily::AstcLdr) => self.supports_astc_ldr(),
+            Some(CompressionFamily::AstcHdr) => self
+                .instance
+                .physical_device_features_1_3(self)
+                .is_some_and(|features| features.texture_compression_astc_hdr == vk::TRUE),
+        }
+    }
+
     // Getters
 
     pub fn handle(&self) -> vk::PhysicalDevice {
@@ -102,6 +168,125 @@ impl PhysicalDevice {
         &self.properties
     }
 
+    /// Core 1.0-1.3, subgroup and descriptor indexing properties. Queried once on creation.
+    pub fn properties_all(&self) -> &PhysicalDeviceProperties {
+        &self.properties_all
+    }
+
+    #[inline]
+    pub fn limits(&self) -> &vk::PhysicalDeviceLimits {
+        &self.properties.limits
+    }
+
+    #[inline]
+    pub fn max_push_constants_size(&self) -> u32 {
+        self.properties.limits.max_push_constants_size
+    }
+
+    /// Number of nanoseconds it takes for a timestamp query value to be incremented by 1.
+    #[inline]
+    pub fn timestamp_period(&self) -> f32 {
+        self.properties.limits.timestamp_period
+    }
+
+    #[inline]
+    pub fn min_uniform_buffer_offset_alignment(&self) -> vk::DeviceSize {
+        self.properties.limits.min_uniform_buffer_offset_alignment
+    }
+
+    #[inline]
+    pub fn min_storage_buffer_offset_alignment(&self) -> vk::DeviceSize {
+        self.properties.limits.min_storage_buffer_offset_alignment
+    }
+
+    #[inline]
+    pub fn non_coherent_atom_size(&self) -> vk::DeviceSize {
+        self.properties.limits.non_coherent_atom_size
+    }
+
+    #[inline]
+    pub fn max_bound_descriptor_sets(&self) -> u32 {
+        self.properties.limits.max_bound_descriptor_sets
+    }
+
+    #[inline]
+    pub fn max_image_dimension_2d(&self) -> u32 {
+        self.properties.limits.max_image_dimension2_d
+    }
+
+    #[inline]
+    pub fn max_compute_work_group_size(&self) -> [u32; 3] {
+        self.properties.limits.max_compute_work_group_size
+    }
+
+    /// Default subgroup size. `None` if the api version is 1.0.
+    #[inline]
+    pub fn subgroup_size(&self) -> Option<u32> {
+        self.properties_all
+            .subgroup
+            .map(|subgroup| subgroup.subgroup_size)
+    }
+
+    /// Operations supported by subgroups (e.g. `ARITHMETIC`, `BALLOT`, `SHUFFLE`). `None` if the
+    /// api version is 1.0.
+    #[inline]
+    pub fn subgroup_supported_operations(&self) -> Option<vk::SubgroupFeatureFlags> {
+        self.properties_all
+            .subgroup
+            .map(|subgroup| subgroup.supported_operations)
+    }
+
+    /// Shader stages which support subgroup operations. `None` if the api version is 1.0.
+    #[inline]
+    pub fn subgroup_supported_stages(&self) -> Option<vk::ShaderStageFlags> {
+        self.properties_all
+            .subgroup
+            .map(|subgroup| subgroup.supported_stages)
+    }
+
+    /// Whether quad subgroup operations are supported in all stages rather than just fragment and
+    /// compute. `None` if the api version is 1.0.
+    #[inline]
+    pub fn subgroup_quad_operations_in_all_stages(&self) -> Option<bool> {
+        self.properties_all
+            .subgroup
+            .map(|subgroup| subgroup.quad_operations_in_all_stages == vk::TRUE)
+    }
+
+    /// Minimum and maximum subgroup sizes selectable with subgroup size control. `None` if the api
+    /// version is below 1.3.
+    #[inline]
+    pub fn subgroup_size_range(&self) -> Option<(u32, u32)> {
+        self.properties_all
+            .properties_1_3
+            .map(|properties| (properties.min_subgroup_size, properties.max_subgroup_size))
+    }
+
+    /// `None` if neither vulkan 1.2 nor `VK_EXT_descriptor_indexing` is supported.
+    #[inline]
+    pub fn max_update_after_bind_descriptors_in_all_pools(&self) -> Option<u32> {
+        self.properties_all
+            .descriptor_indexing
+            .map(|properties| properties.max_update_after_bind_descriptors_in_all_pools)
+    }
+
+    /// Shader stages which support cooperative matrix instructions. `None` if
+    /// `VK_KHR_cooperative_matrix` isn't supported.
+    pub fn cooperative_matrix_supported_stages(&self) -> Option<vk::ShaderStageFlags> {
+        self.instance
+            .physical_device_cooperative_matrix_properties(self)
+            .map(|properties| properties.cooperative_matrix_supported_stages)
+    }
+
+    /// The matrix sizes, component types and scopes supported by `VK_KHR_cooperative_matrix`.
+    /// Empty if the extension isn't supported.
+    pub fn cooperative_matrix_configurations(
+        &self,
+    ) -> VkResult<Vec<CooperativeMatrixConfiguration>> {
+        self.instance
+            .physical_device_cooperative_matrix_configurations(self)
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -140,12 +325,707 @@ impl ExtensionProperties {
     }
 }
 
+/// Device features that can be queried with [`Device::feature_enabled`](crate::Device::feature_enabled).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Vulkan 1.0 `robustBufferAccess`.
+    RobustBufferAccess,
+    /// Vulkan 1.0 `samplerAnisotropy`.
+    SamplerAnisotropy,
+    /// Vulkan 1.1 `protectedMemory`.
+    ProtectedMemory,
+    /// Vulkan 1.1 `shaderDrawParameters`.
+    ShaderDrawParameters,
+    /// Vulkan 1.2 `descriptorIndexing`.
+    DescriptorIndexing,
+    /// Vulkan 1.2 `timelineSemaphore` or `VK_KHR_timeline_semaphore`.
+    TimelineSemaphore,
+    /// Vulkan 1.2 `bufferDeviceAddress` or `VK_KHR_buffer_device_address`.
+    BufferDeviceAddress,
+    /// Vulkan 1.3 `synchronization2` or `VK_KHR_synchronization2`.
+    Synchronization2,
+    /// Vulkan 1.3 `dynamicRendering` or `VK_KHR_dynamic_rendering`.
+    DynamicRendering,
+    /// Vulkan 1.3 `maintenance4` or `VK_KHR_maintenance4`.
+    Maintenance4,
+    /// `VK_EXT_robustness2` `robustBufferAccess2`.
+    RobustBufferAccess2,
+    /// `VK_EXT_robustness2` `nullDescriptor`.
+    NullDescriptor,
+    /// `VK_EXT_pipeline_robustness` `pipelineRobustness`.
+    PipelineRobustness,
+    /// `VK_KHR_maintenance5` `maintenance5`.
+    Maintenance5,
+    /// `VK_KHR_maintenance6` `maintenance6`.
+    Maintenance6,
+    /// `VK_KHR_cooperative_matrix` `cooperativeMatrix`.
+    CooperativeMatrix,
+    /// `VK_EXT_attachment_feedback_loop_layout` `attachmentFeedbackLoopLayout`.
+    AttachmentFeedbackLoopLayout,
+    /// `VK_EXT_attachment_feedback_loop_dynamic_state` `attachmentFeedbackLoopDynamicState`.
+    AttachmentFeedbackLoopDynamicState,
+    /// `VK_AMD_device_coherent_memory` `deviceCoherentMemory`.
+    DeviceCoherentMemory,
+    /// `VK_EXT_memory_priority` `memoryPriority`.
+    MemoryPriority,
+}
+
+impl Feature {
+    /// The api version this feature is core in. `None` for features only available through an
+    /// extension.
+    pub fn core_api_version(self) -> Option<ApiVersion> {
+        match self {
+            Self::RobustBufferAccess | Self::SamplerAnisotropy => Some(ApiVersion::V1_0),
+            Self::ProtectedMemory | Self::ShaderDrawParameters => Some(ApiVersion::V1_1),
+            Self::DescriptorIndexing | Self::TimelineSemaphore | Self::BufferDeviceAddress => {
+                Some(ApiVersion::V1_2)
+            }
+            Self::Synchronization2 | Self::DynamicRendering | Self::Maintenance4 => {
+                Some(ApiVersion::V1_3)
+            }
+            Self::Maintenance5 | Self::Maintenance6 => Some(ApiVersion::V1_4),
+            Self::RobustBufferAccess2
+            | Self::NullDescriptor
+            | Self::PipelineRobustness
+            | Self::CooperativeMatrix
+            | Self::AttachmentFeedbackLoopLayout
+            | Self::AttachmentFeedbackLoopDynamicState
+            | Self::DeviceCoherentMemory
+            | Self::MemoryPriority => None,
+        }
+    }
+
+    /// The device extension which needs to be enabled for this feature when the device api
+    /// version is `api_version`. `None` if the feature is core in `api_version`.
+    pub fn required_extension(self, api_version: ApiVersion) -> Option<&'static CStr> {
+        if self
+            .core_api_version()
+            .is_some_and(|core_version| api_version >= core_version)
+        {
+            return None;
+        }
+        match self {
+            Self::RobustBufferAccess
+            | Self::SamplerAnisotropy
+            | Self::ProtectedMemory
+            | Self::ShaderDrawParameters => None,
+            Self::DescriptorIndexing => Some(vk::EXT_DESCRIPTOR_INDEXING_NAME),
+            Self::TimelineSemaphore => Some(vk::KHR_TIMELINE_SEMAPHORE_NAME),
+            Self::BufferDeviceAddress => Some(vk::KHR_BUFFER_DEVICE_ADDRESS_NAME),
+            Self::Synchronization2 => Some(vk::KHR_SYNCHRONIZATION2_NAME),
+            Self::DynamicRendering => Some(vk::KHR_DYNAMIC_RENDERING_NAME),
+            Self::Maintenance4 => Some(vk::KHR_MAINTENANCE4_NAME),
+            Self::RobustBufferAccess2 | Self::NullDescriptor => Some(vk::EXT_ROBUSTNESS2_NAME),
+            Self::PipelineRobustness => Some(vk::EXT_PIPELINE_ROBUSTNESS_NAME),
+            Self::Maintenance5 => Some(vk::KHR_MAINTENANCE5_NAME),
+            Self::Maintenance6 => Some(vk::KHR_MAINTENANCE6_NAME),
+            Self::CooperativeMatrix => Some(vk::KHR_COOPERATIVE_MATRIX_NAME),
+            Self::AttachmentFeedbackLoopLayout => {
+                Some(vk::EXT_ATTACHMENT_FEEDBACK_LOOP_LAYOUT_NAME)
+            }
+            Self::AttachmentFeedbackLoopDynamicState => {
+                Some(vk::EXT_ATTACHMENT_FEEDBACK_LOOP_DYNAMIC_STATE_NAME)
+            }
+            Self::DeviceCoherentMemory => Some(vk::AMD_DEVICE_COHERENT_MEMORY_NAME),
+            Self::MemoryPriority => Some(vk::EXT_MEMORY_PRIORITY_NAME),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug)]
 pub struct PhysicalDeviceFeatures<'a> {
     pub features_1_0: vk::PhysicalDeviceFeatures,
     pub features_1_1: vk::PhysicalDeviceVulkan11Features<'a>,
     pub features_1_2: vk::PhysicalDeviceVulkan12Features<'a>,
     pub features_1_3: vk::PhysicalDeviceVulkan13Features<'a>,
+
+    /// `VK_EXT_robustness2` features e.g. `robust_buffer_access2`, `robust_image_access2` and
+    /// `null_descriptor`. Ignored if `None` or if the instance api version is 1.0. Skipped by
+    /// [`Device::new`](crate::Device::new) unless `VK_EXT_robustness2` is in the enabled device
+    /// extensions.
+    pub robustness_2: Option<vk::PhysicalDeviceRobustness2FeaturesEXT<'a>>,
+    /// `VK_EXT_pipeline_robustness` features. Ignored if `None` or if the instance api version is
+    /// 1.0. Skipped by [`Device::new`](crate::Device::new) unless `VK_EXT_pipeline_robustness` is
+    /// in the enabled device extensions.
+    pub pipeline_robustness: Option<vk::PhysicalDevicePipelineRobustnessFeaturesEXT<'a>>,
+    /// `VK_KHR_maintenance5` features (core in vulkan 1.4). Ignored if `None` or if the instance
+    /// api version is 1.0. Skipped by [`Device::new`](crate::Device::new) unless
+    /// `VK_KHR_maintenance5` is in the enabled device extensions.
+    pub maintenance_5: Option<vk::PhysicalDeviceMaintenance5FeaturesKHR<'a>>,
+    /// `VK_KHR_maintenance6` features (core in vulkan 1.4). Ignored if `None` or if the instance
+    /// api version is 1.0. Skipped by [`Device::new`](crate::Device::new) unless
+    /// `VK_KHR_maintenance6` is in the enabled device extensions.
+    pub maintenance_6: Option<vk::PhysicalDeviceMaintenance6FeaturesKHR<'a>>,
+    /// `VK_KHR_cooperative_matrix` features. Ignored if `None` or if the instance api version is
+    /// 1.0. Skipped by [`Device::new`](crate::Device::new) unless `VK_KHR_cooperative_matrix` is in
+    /// the enabled device extensions.
+    pub cooperative_matrix: Option<vk::PhysicalDeviceCooperativeMatrixFeaturesKHR<'a>>,
+    /// `VK_EXT_attachment_feedback_loop_layout` features. Ignored if `None` or if the instance api
+    /// version is 1.0. Skipped by [`Device::new`](crate::Device::new) unless
+    /// `VK_EXT_attachment_feedback_loop_layout` is in the enabled device extensions.
+    pub attachment_feedback_loop_layout:
+        Option<vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT<'a>>,
+    /// `VK_EXT_attachment_feedback_loop_dynamic_state` features. Ignored if `None` or if the
+    /// instance api version is 1.0. Skipped by [`Device::new`](crate::Device::new) unless
+    /// `VK_EXT_attachment_feedback_loop_dynamic_state` is in the enabled device extensions.
+    pub attachment_feedback_loop_dynamic_state:
+        Option<vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT<'a>>,
+    /// `VK_AMD_device_coherent_memory` features. Ignored if `None` or if the instance api version
+    /// is 1.0. Skipped by [`Device::new`](crate::Device::new) unless
+    /// `VK_AMD_device_coherent_memory` is in the enabled device extensions.
+    pub device_coherent_memory: Option<vk::PhysicalDeviceCoherentMemoryFeaturesAMD<'a>>,
+    /// `VK_EXT_memory_priority` features. Ignored if `None` or if the instance api version is 1.0.
+    /// Skipped by [`Device::new`](crate::Device::new) unless `VK_EXT_memory_priority` is in the
+    /// enabled device extensions.
+    pub memory_priority: Option<vk::PhysicalDeviceMemoryPriorityFeaturesEXT<'a>>,
+}
+
+impl<'a> PhysicalDeviceFeatures<'a> {
+    /// Enables `robustBufferAccess`. With this enabled, out-of-bounds buffer accesses in shaders
+    /// can't read or write memory outside of the bound buffer range but the returned values are
+    /// implementation dependent (e.g. any value in the buffer or zero).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/html/vkspec.html#features-robustBufferAccess>
+    pub fn with_robust_buffer_access(mut self) -> Self {
+        self.features_1_0.robust_buffer_access = vk::TRUE;
+        self
+    }
+
+    /// Enables the `VK_EXT_robustness2` features. With `robust_buffer_access_2`, out-of-bounds
+    /// buffer reads return zero (or (0,0,0,x) for vertex inputs) and writes are discarded.
+    /// `robust_image_access_2` does the same for images and `null_descriptor` allows descriptors
+    /// to be written with `vk::*::null()` handles (reads return zero).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_EXT_robustness2.html>
+    pub fn with_robustness_2(
+        mut self,
+        robust_buffer_access_2: bool,
+        robust_image_access_2: bool,
+        null_descriptor: bool,
+    ) -> Self {
+        self.robustness_2 = Some(
+            vk::PhysicalDeviceRobustness2FeaturesEXT::default()
+                .robust_buffer_access2(robust_buffer_access_2)
+                .robust_image_access2(robust_image_access_2)
+                .null_descriptor(null_descriptor),
+        );
+        self
+    }
+
+    /// Enables the vulkan 1.1 `protectedMemory` feature for protected buffers, images, command
+    /// pools and queues. The queue create infos also need the `PROTECTED` flag to retrieve
+    /// protected capable queues with [`Queue::new_protected`](crate::Queue::new_protected).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/html/vkspec.html#memory-protected-memory>
+    pub fn with_protected_memory(mut self) -> Self {
+        self.features_1_1 = self.features_1_1.protected_memory(true);
+        self
+    }
+
+    /// Enables the vulkan 1.2 descriptor indexing features needed for bindless descriptor arrays
+    /// (see [`DescriptorSetLayoutBinding::new_bindless`](crate::DescriptorSetLayoutBinding::new_bindless)):
+    /// runtime sized arrays, non-uniform indexing of sampled image, storage buffer and uniform
+    /// buffer arrays, partially bound bindings and updating sampled images and storage buffers
+    /// after bind. Requires an api version of 1.2 or above.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_EXT_descriptor_indexing.html>
+    pub fn with_descriptor_indexing(mut self) -> Self {
+        self.features_1_2 = self
+            .features_1_2
+            .descriptor_indexing(true)
+            .runtime_descriptor_array(true)
+            .shader_sampled_image_array_non_uniform_indexing(true)
+            .shader_storage_buffer_array_non_uniform_indexing(true)
+            .shader_uniform_buffer_array_non_uniform_indexing(true)
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_update_unused_while_pending(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .descriptor_binding_storage_buffer_update_after_bind(true);
+        self
+    }
+
+    /// Enables `VK_EXT_pipeline_robustness` which allows robustness behavior to be specified per
+    /// pipeline with [`PipelineRobustness`](crate::PipelineRobustness).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_EXT_pipeline_robustness.html>
+    pub fn with_pipeline_robustness(mut self) -> Self {
+        self.pipeline_robustness = Some(
+            vk::PhysicalDevicePipelineRobustnessFeaturesEXT::default().pipeline_robustness(true),
+        );
+        self
+    }
+
+    /// Enables `VK_KHR_maintenance5` e.g. for
+    /// [`CommandBuffer::bind_index_buffer_2`](crate::CommandBuffer::bind_index_buffer_2) and the
+    /// `A8_UNORM_KHR`/`A1B5G5R5_UNORM_PACK16_KHR` formats.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_KHR_maintenance5.html>
+    pub fn with_maintenance_5(mut self) -> Self {
+        self.maintenance_5 =
+            Some(vk::PhysicalDeviceMaintenance5FeaturesKHR::default().maintenance5(true));
+        self
+    }
+
+    /// Enables `VK_KHR_maintenance6`. Combined with the `VK_EXT_robustness2` `nullDescriptor`
+    /// feature this allows binding a null index buffer.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_KHR_maintenance6.html>
+    pub fn with_maintenance_6(mut self) -> Self {
+        self.maintenance_6 =
+            Some(vk::PhysicalDeviceMaintenance6FeaturesKHR::default().maintenance6(true));
+        self
+    }
+
+    /// Enables `VK_KHR_cooperative_matrix` which allows shaders to use `OpTypeCooperativeMatrixKHR`
+    /// for subgroup scoped matrix multiply-accumulate (e.g. tensor core paths). Supported matrix
+    /// sizes and component types are listed by
+    /// [`PhysicalDevice::cooperative_matrix_configurations`].
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_KHR_cooperative_matrix.html>
+    pub fn with_cooperative_matrix(mut self, robust_buffer_access: bool) -> Self {
+        self.cooperative_matrix = Some(
+            vk::PhysicalDeviceCooperativeMatrixFeaturesKHR::default()
+                .cooperative_matrix(true)
+                .cooperative_matrix_robust_buffer_access(robust_buffer_access),
+        );
+        self
+    }
+
+    /// Enables `VK_EXT_attachment_feedback_loop_layout` so an image can be read in shaders while
+    /// it's bound as a color or depth/stencil attachment in the
+    /// `ATTACHMENT_FEEDBACK_LOOP_OPTIMAL_EXT` layout. With `dynamic_state`,
+    /// `VK_EXT_attachment_feedback_loop_dynamic_state` is enabled too which allows toggling the
+    /// feedback loop with
+    /// [`CommandBuffer::set_attachment_feedback_loop_enable`](crate::CommandBuffer::set_attachment_feedback_loop_enable).
+    /// See [`AttachmentFeedbackLoopSupport`](crate::AttachmentFeedbackLoopSupport).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_EXT_attachment_feedback_loop_layout.html>
+    pub fn with_attachment_feedback_loop(mut self, dynamic_state: bool) -> Self {
+        self.attachment_feedback_loop_layout = Some(
+            vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT::default()
+                .attachment_feedback_loop_layout(true),
+        );
+        if dynamic_state {
+            self.attachment_feedback_loop_dynamic_state = Some(
+                vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT::default()
+                    .attachment_feedback_loop_dynamic_state(true),
+            );
+        }
+        self
+    }
+
+    /// Enables `VK_AMD_device_coherent_memory` which exposes `DEVICE_COHERENT_AMD` and
+    /// `DEVICE_UNCACHED_AMD` memory types. Writes to these bypass the gpu caches so e.g. buffer
+    /// markers reach memory before a hang. Slow; intended for debugging gpu crashes. The memory
+    /// allocator enables the matching VMA flag automatically, see
+    /// [`allocation_info_device_coherent`](crate::allocation_info_device_coherent).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_AMD_device_coherent_memory.html>
+    pub fn with_device_coherent_memory(mut self) -> Self {
+        self.device_coherent_memory = Some(
+            vk::PhysicalDeviceCoherentMemoryFeaturesAMD::default().device_coherent_memory(true),
+        );
+        self
+    }
+
+    /// Enables `VK_EXT_memory_priority` so allocations can be given a priority relative to each
+    /// other which the driver can use when deciding what to evict from device local memory. The
+    /// memory allocator enables the matching VMA flag automatically, see
+    /// [`Device::feature_enabled`](crate::Device::feature_enabled).
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VK_EXT_memory_priority.html>
+    pub fn with_memory_priority(mut self) -> Self {
+        self.memory_priority =
+            Some(vk::PhysicalDeviceMemoryPriorityFeaturesEXT::default().memory_priority(true));
+        self
+    }
+
+    /// Enables the vulkan 1.2 `bufferDeviceAddress` feature for
+    /// `vkGetBufferDeviceAddress` and `BufferUsageFlags::SHADER_DEVICE_ADDRESS`. The memory
+    /// allocator enables the matching VMA flag automatically. Requires an api version of 1.2 or
+    /// above.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/html/vkspec.html#features-bufferDeviceAddress>
+    pub fn with_buffer_device_address(mut self) -> Self {
+        self.features_1_2 = self.features_1_2.buffer_device_address(true);
+        self
+    }
+
+    /// Returns true if `feature` is set in these features. Doesn't check whether the required
+    /// extension or api version is available, see
+    /// [`Device::feature_enabled`](crate::Device::feature_enabled) for that.
+    pub fn contains(&self, feature: Feature) -> bool {
+        let enabled = match feature {
+            Feature::RobustBufferAccess => self.features_1_0.robust_buffer_access,
+            Feature::SamplerAnisotropy => self.features_1_0.sampler_anisotropy,
+            Feature::ProtectedMemory => self.features_1_1.protected_memory,
+            Feature::ShaderDrawParameters => self.features_1_1.shader_draw_parameters,
+            Feature::DescriptorIndexing => self.features_1_2.descriptor_indexing,
+            Feature::TimelineSemaphore => self.features_1_2.timeline_semaphore,
+            Feature::BufferDeviceAddress => self.features_1_2.buffer_device_address,
+            Feature::Synchronization2 => self.features_1_3.synchronization2,
+            Feature::DynamicRendering => self.features_1_3.dynamic_rendering,
+            Feature::Maintenance4 => self.features_1_3.maintenance4,
+            Feature::RobustBufferAccess2 => self
+                .robustness_2
+                .map_or(vk::FALSE, |features| features.robust_buffer_access2),
+            Feature::NullDescriptor => self
+                .robustness_2
+                .map_or(vk::FALSE, |features| features.null_descriptor),
+            Feature::PipelineRobustness => self
+                .pipeline_robustness
+                .map_or(vk::FALSE, |features| features.pipeline_robustness),
+            Feature::Maintenance5 => self
+                .maintenance_5
+                .map_or(vk::FALSE, |features| features.maintenance5),
+            Feature::Maintenance6 => self
+                .maintenance_6
+                .map_or(vk::FALSE, |features| features.maintenance6),
+            Feature::CooperativeMatrix => self
+                .cooperative_matrix
+                .map_or(vk::FALSE, |features| features.cooperative_matrix),
+            Feature::AttachmentFeedbackLoopLayout => self
+                .attachment_feedback_loop_layout
+                .map_or(vk::FALSE, |features| {
+                    features.attachment_feedback_loop_layout
+                }),
+            Feature::AttachmentFeedbackLoopDynamicState => self
+                .attachment_feedback_loop_dynamic_state
+                .map_or(vk::FALSE, |features| {
+                    features.attachment_feedback_loop_dynamic_state
+                }),
+            Feature::DeviceCoherentMemory => self
+                .device_coherent_memory
+                .map_or(vk::FALSE, |features| features.device_coherent_memory),
+            Feature::MemoryPriority => self
+                .memory_priority
+                .map_or(vk::FALSE, |features| features.memory_priority),
+        };
+        enabled == vk::TRUE
+    }
+
+    /// A copy with all the `p_next` pointers nulled so it can be stored independently of the
+    /// p_next chain it was part of.
+    pub fn to_detached(&self) -> PhysicalDeviceFeatures<'static> {
+        let mut features_1_1 = self.features_1_1;
+        features_1_1.p_next = ptr::null_mut();
+        let mut features_1_2 = self.features_1_2;
+        features_1_2.p_next = ptr::null_mut();
+        let mut features_1_3 = self.features_1_3;
+        features_1_3.p_next = ptr::null_mut();
+        let robustness_2 = self.robustness_2.map(|mut robustness_2| {
+            robustness_2.p_next = ptr::null_mut();
+            robustness_2
+        });
+        let pipeline_robustness = self.pipeline_robustness.map(|mut pipeline_robustness| {
+            pipeline_robustness.p_next = ptr::null_mut();
+            pipeline_robustness
+        });
+        let maintenance_5 = self.maintenance_5.map(|mut maintenance_5| {
+            maintenance_5.p_next = ptr::null_mut();
+            maintenance_5
+        });
+        let maintenance_6 = self.maintenance_6.map(|mut maintenance_6| {
+            maintenance_6.p_next = ptr::null_mut();
+            maintenance_6
+        });
+        let cooperative_matrix = self.cooperative_matrix.map(|mut cooperative_matrix| {
+            cooperative_matrix.p_next = ptr::null_mut();
+            cooperative_matrix
+        });
+        let attachment_feedback_loop_layout =
+            self.attachment_feedback_loop_layout
+                .map(|mut attachment_feedback_loop_layout| {
+                    attachment_feedback_loop_layout.p_next = ptr::null_mut();
+                    attachment_feedback_loop_layout
+                });
+        let attachment_feedback_loop_dynamic_state = self
+            .attachment_feedback_loop_dynamic_state
+            .map(|mut attachment_feedback_loop_dynamic_state| {
+                attachment_feedback_loop_dynamic_state.p_next = ptr::null_mut();
+                attachment_feedback_loop_dynamic_state
+            });
+        let device_coherent_memory =
+            self.device_coherent_memory
+                .map(|mut device_coherent_memory| {
+                    device_coherent_memory.p_next = ptr::null_mut();
+                    device_coherent_memory
+                });
+        let memory_priority = self.memory_priority.map(|mut memory_priority| {
+            memory_priority.p_next = ptr::null_mut();
+            memory_priority
+        });
+
+        // safety: the lifetimes only apply to the p_next pointers which have been nulled
+        unsafe {
+            PhysicalDeviceFeatures {
+                features_1_0: self.features_1_0,
+                features_1_1: mem::transmute::<
+                    vk::PhysicalDeviceVulkan11Features<'_>,
+                    vk::PhysicalDeviceVulkan11Features<'static>,
+                >(features_1_1),
+                features_1_2: mem::transmute::<
+                    vk::PhysicalDeviceVulkan12Features<'_>,
+                    vk::PhysicalDeviceVulkan12Features<'static>,
+                >(features_1_2),
+                features_1_3: mem::transmute::<
+                    vk::PhysicalDeviceVulkan13Features<'_>,
+                    vk::PhysicalDeviceVulkan13Features<'static>,
+                >(features_1_3),
+                robustness_2: mem::transmute::<
+                    Option<vk::PhysicalDeviceRobustness2FeaturesEXT<'_>>,
+                    Option<vk::PhysicalDeviceRobustness2FeaturesEXT<'static>>,
+                >(robustness_2),
+                pipeline_robustness: mem::transmute::<
+                    Option<vk::PhysicalDevicePipelineRobustnessFeaturesEXT<'_>>,
+                    Option<vk::PhysicalDevicePipelineRobustnessFeaturesEXT<'static>>,
+                >(pipeline_robustness),
+                maintenance_5: mem::transmute::<
+                    Option<vk::PhysicalDeviceMaintenance5FeaturesKHR<'_>>,
+                    Option<vk::PhysicalDeviceMaintenance5FeaturesKHR<'static>>,
+                >(maintenance_5),
+                maintenance_6: mem::transmute::<
+                    Option<vk::PhysicalDeviceMaintenance6FeaturesKHR<'_>>,
+                    Option<vk::PhysicalDeviceMaintenance6FeaturesKHR<'static>>,
+                >(maintenance_6),
+                cooperative_matrix: mem::transmute::<
+                    Option<vk::PhysicalDeviceCooperativeMatrixFeaturesKHR<'_>>,
+                    Option<vk::PhysicalDeviceCooperativeMatrixFeaturesKHR<'static>>,
+                >(cooperative_matrix),
+                attachment_feedback_loop_layout: mem::transmute::<
+                    Option<vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT<'_>>,
+                    Option<vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT<'static>>,
+                >(attachment_feedback_loop_layout),
+                attachment_feedback_loop_dynamic_state: mem::transmute::<
+                    Option<vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT<'_>>,
+                    Option<
+                        vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT<'static>,
+                    >,
+                >(
+                    attachment_feedback_loop_dynamic_state
+                ),
+                device_coherent_memory: mem::transmute::<
+                    Option<vk::PhysicalDeviceCoherentMemoryFeaturesAMD<'_>>,
+                    Option<vk::PhysicalDeviceCoherentMemoryFeaturesAMD<'static>>,
+                >(device_coherent_memory),
+                memory_priority: mem::transmute::<
+                    Option<vk::PhysicalDeviceMemoryPriorityFeaturesEXT<'_>>,
+                    Option<vk::PhysicalDeviceMemoryPriorityFeaturesEXT<'static>>,
+                >(memory_priority),
+            }
+        }
+    }
+}
+
+impl PhysicalDeviceFeatures<'static> {
+    /// Reads the features enabled by `create_info` from `p_enabled_features` and the p_next
+    /// chain. Promoted feature structs (e.g. `PhysicalDeviceBufferDeviceAddressFeatures`) are
+    /// merged into the matching vulkan 1.2/1.3 feature struct. Other feature structs not covered
+    /// by `PhysicalDeviceFeatures` are ignored.
+    ///
+    /// # Safety
+    /// No busted pointers in `create_info` or its p_next chain.
+    pub unsafe fn from_device_create_info(create_info: &vk::DeviceCreateInfo) -> Self {
+        let mut features = PhysicalDeviceFeatures::default();
+        if !create_info.p_enabled_features.is_null() {
+            features.features_1_0 = unsafe { *create_info.p_enabled_features };
+        }
+
+        let mut next_ptr = create_info.p_next as *const vk::BaseInStructure;
+        while !next_ptr.is_null() {
+            let next = unsafe { &*next_ptr };
+            match next.s_type {
+                vk::StructureType::PHYSICAL_DEVICE_FEATURES_2 => {
+                    let features_2 = unsafe { &*(next_ptr as *const vk::PhysicalDeviceFeatures2) };
+                    features.features_1_0 = features_2.features;
+                }
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_FEATURES => {
+                    features.features_1_1 =
+                        unsafe { *(next_ptr as *const vk::PhysicalDeviceVulkan11Features) };
+                }
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES => {
+                    features.features_1_2 =
+                        unsafe { *(next_ptr as *const vk::PhysicalDeviceVulkan12Features) };
+                }
+                vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES => {
+                    features.features_1_3 =
+                        unsafe { *(next_ptr as *const vk::PhysicalDeviceVulkan13Features) };
+                }
+                vk::StructureType::PHYSICAL_DEVICE_ROBUSTNESS_2_FEATURES_EXT => {
+                    features.robustness_2 = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceRobustness2FeaturesEXT)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_PIPELINE_ROBUSTNESS_FEATURES_EXT => {
+                    features.pipeline_robustness = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDevicePipelineRobustnessFeaturesEXT)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_MAINTENANCE_5_FEATURES_KHR => {
+                    features.maintenance_5 = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceMaintenance5FeaturesKHR)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_MAINTENANCE_6_FEATURES_KHR => {
+                    features.maintenance_6 = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceMaintenance6FeaturesKHR)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_COOPERATIVE_MATRIX_FEATURES_KHR => {
+                    features.cooperative_matrix = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceCooperativeMatrixFeaturesKHR)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_ATTACHMENT_FEEDBACK_LOOP_LAYOUT_FEATURES_EXT => {
+                    features.attachment_feedback_loop_layout = Some(unsafe {
+                        *(next_ptr
+                            as *const vk::PhysicalDeviceAttachmentFeedbackLoopLayoutFeaturesEXT)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_ATTACHMENT_FEEDBACK_LOOP_DYNAMIC_STATE_FEATURES_EXT => {
+                    features.attachment_feedback_loop_dynamic_state = Some(unsafe {
+                        *(next_ptr
+                            as *const vk::PhysicalDeviceAttachmentFeedbackLoopDynamicStateFeaturesEXT)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_COHERENT_MEMORY_FEATURES_AMD => {
+                    features.device_coherent_memory = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceCoherentMemoryFeaturesAMD)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_MEMORY_PRIORITY_FEATURES_EXT => {
+                    features.memory_priority = Some(unsafe {
+                        *(next_ptr as *const vk::PhysicalDeviceMemoryPriorityFeaturesEXT)
+                    });
+                }
+                vk::StructureType::PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES => {
+                    let timeline_semaphore = unsafe {
+                        &*(next_ptr as *const vk::PhysicalDeviceTimelineSemaphoreFeatures)
+                    };
+                    features.features_1_2.timeline_semaphore |=
+                        timeline_semaphore.timeline_semaphore;
+                }
+                vk::StructureType::PHYSICAL_DEVICE_BUFFER_DEVICE_ADDRESS_FEATURES => {
+                    let buffer_device_address = unsafe {
+                        &*(next_ptr as *const vk::PhysicalDeviceBufferDeviceAddressFeatures)
+                    };
+                    features.features_1_2.buffer_device_address |=
+                        buffer_device_address.buffer_device_address;
+                    features.features_1_2.buffer_device_address_capture_replay |=
+                        buffer_device_address.buffer_device_address_capture_replay;
+                    features.features_1_2.buffer_device_address_multi_device |=
+                        buffer_device_address.buffer_device_address_multi_device;
+                }
+                vk::StructureType::PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES => {
+                    let synchronization_2 = unsafe {
+                        &*(next_ptr as *const vk::PhysicalDeviceSynchronization2Features)
+                    };
+                    features.features_1_3.synchronization2 |= synchronization_2.synchronization2;
+                }
+                vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES => {
+                    let dynamic_rendering = unsafe {
+                        &*(next_ptr as *const vk::PhysicalDeviceDynamicRenderingFeatures)
+                    };
+                    features.features_1_3.dynamic_rendering |= dynamic_rendering.dynamic_rendering;
+                }
+                vk::StructureType::PHYSICAL_DEVICE_MAINTENANCE_4_FEATURES => {
+                    let maintenance_4 = unsafe {
+                        &*(next_ptr as *const vk::PhysicalDeviceMaintenance4Features)
+                    };
+                    features.features_1_3.maintenance4 |= maintenance_4.maintenance4;
+                }
+                _ => (),
+            }
+            next_ptr = next.p_next;
+        }
+
+        features.to_detached()
+    }
+}
+
+/// Physical device properties from `vkGetPhysicalDeviceProperties2`. Structs that aren't
+/// supported by the instance/device api version (or extensions) are `None`. All `p_next` pointers
+/// are null.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct PhysicalDeviceProperties {
+    pub properties_1_0: vk::PhysicalDeviceProperties,
+    /// Requires api version 1.2.
+    pub properties_1_1: Option<vk::PhysicalDeviceVulkan11Properties<'static>>,
+    /// Requires api version 1.2.
+    pub properties_1_2: Option<vk::PhysicalDeviceVulkan12Properties<'static>>,
+    /// Requires api version 1.3.
+    pub properties_1_3: Option<vk::PhysicalDeviceVulkan13Properties<'static>>,
+    /// Requires api version 1.1.
+    pub subgroup: Option<vk::PhysicalDeviceSubgroupProperties<'static>>,
+    /// Requires api version 1.2 or `VK_EXT_descriptor_indexing`.
+    pub descriptor_indexing: Option<vk::PhysicalDeviceDescriptorIndexingProperties<'static>>,
+}
+
+impl PhysicalDeviceProperties {
+    fn query(
+        instance: &Instance,
+        handle: vk::PhysicalDevice,
+        properties_1_0: vk::PhysicalDeviceProperties,
+        extension_properties: &[ExtensionProperties],
+    ) -> Self {
+        let api_version = min(
+            instance.effective_api_version(),
+            ApiVersion::from_vk_uint(properties_1_0.api_version),
+        );
+        if api_version < ApiVersion::V1_1 {
+            return Self {
+                properties_1_0,
+                ..Default::default()
+            };
+        }
+
+        let supports_descriptor_indexing = api_version >= ApiVersion::V1_2
+            || extension_properties
+                .iter()
+                .any(|props| props.extension_name.as_c_str() == vk::EXT_DESCRIPTOR_INDEXING_NAME);
+
+        let mut properties_1_1 = vk::PhysicalDeviceVulkan11Properties::default();
+        let mut properties_1_2 = vk::PhysicalDeviceVulkan12Properties::default();
+        let mut properties_1_3 = vk::PhysicalDeviceVulkan13Properties::default();
+        let mut subgroup = vk::PhysicalDeviceSubgroupProperties::default();
+        let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingProperties::default();
+
+        {
+            let mut properties_2 =
+                vk::PhysicalDeviceProperties2::default().push_next(&mut subgroup);
+            if supports_descriptor_indexing {
+                properties_2 = properties_2.push_next(&mut descriptor_indexing);
+            }
+            if api_version >= ApiVersion::V1_2 {
+                properties_2 = properties_2
+                    .push_next(&mut properties_1_1)
+                    .push_next(&mut properties_1_2);
+            }
+            if api_version >= ApiVersion::V1_3 {
+                properties_2 = properties_2.push_next(&mut properties_1_3);
+            }
+            unsafe {
+                instance
+                    .inner()
+                    .get_physical_device_properties2(handle, &mut properties_2)
+            };
+        }
+
+        properties_1_1.p_next = ptr::null_mut();
+        properties_1_2.p_next = ptr::null_mut();
+        properties_1_3.p_next = ptr::null_mut();
+        subgroup.p_next = ptr::null_mut();
+        descriptor_indexing.p_next = ptr::null_mut();
+
+        Self {
+            properties_1_0,
+            properties_1_1: (api_version >= ApiVersion::V1_2).then_some(properties_1_1),
+            properties_1_2: (api_version >= ApiVersion::V1_2).then_some(properties_1_2),
+            properties_1_3: (api_version >= ApiVersion::V1_3).then_some(properties_1_3),
+            subgroup: Some(subgroup),
+            descriptor_indexing: supports_descriptor_indexing.then_some(descriptor_indexing),
+        }
+    }
 }
 
 // ~~ Errors ~~
@@ -179,3 +1059,42 @@ impl error::Error for PhysicalDeviceError {
         }
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn feature_required_extension_depends_on_api_version() {
+    assert_eq!(
+        Feature::BufferDeviceAddress.required_extension(ApiVersion::V1_1),
+        Some(vk::KHR_BUFFER_DEVICE_ADDRESS_NAME)
+    );
+    assert_eq!(
+        Feature::BufferDeviceAddress.required_extension(ApiVersion::V1_2),
+        None
+    );
+    assert_eq!(
+        Feature::MemoryPriority.required_extension(ApiVersion::V1_4),
+        Some(vk::EXT_MEMORY_PRIORITY_NAME)
+    );
+    assert_eq!(
+        Feature::ProtectedMemory.required_extension(ApiVersion::V1_0),
+        None
+    );
+}
+
+#[test]
+fn features_from_device_create_info_merge_promoted_structs() {
+    let mut buffer_device_address =
+        vk::PhysicalDeviceBufferDeviceAddressFeatures::default().buffer_device_address(true);
+    let mut memory_priority =
+        vk::PhysicalDeviceMemoryPriorityFeaturesEXT::default().memory_priority(true);
+    let create_info = vk::DeviceCreateInfo::default()
+        .push_next(&mut buffer_device_address)
+        .push_next(&mut memory_priority);
+
+    let features = unsafe { PhysicalDeviceFeatures::from_device_create_info(&create_info) };
+    assert!(features.contains(Feature::BufferDeviceAddress));
+    assert!(features.contains(Feature::MemoryPriority));
+    assert!(!features.contains(Feature::Synchronization2));
+    assert!(!features.contains(Feature::DeviceCoherentMemory));
+}
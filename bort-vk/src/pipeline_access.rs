@@ -1,10 +1,52 @@
-use crate::{DeviceOwned, PipelineLayout};
+use crate::{DescriptorSetLayout, DeviceOwned, PipelineLayout};
 use ash::vk;
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 /// Unifies different types of pipeline
 pub trait PipelineAccess: DeviceOwned + Send + Sync {
     fn handle(&self) -> vk::Pipeline;
     fn pipeline_layout(&self) -> &Arc<PipelineLayout>;
     fn bind_point(&self) -> vk::PipelineBindPoint;
+
+    /// The descriptor set layouts of the retained pipeline layout, indexed by set number.
+    #[inline]
+    fn descriptor_set_layouts(&self) -> &[Arc<DescriptorSetLayout>] {
+        &self.pipeline_layout().properties().set_layouts
+    }
+
+    /// The push constant ranges of the retained pipeline layout.
+    #[inline]
+    fn push_constant_ranges(&self) -> &[vk::PushConstantRange] {
+        &self.pipeline_layout().properties().push_constant_ranges
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum PipelineRecreationError {
+    /// The pipeline wasn't created with one of the constructors that retain shader stages.
+    ShaderStagesNotRetained,
+    Creation(vk::Result),
+}
+
+impl fmt::Display for PipelineRecreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ShaderStagesNotRetained => write!(
+                f,
+                "pipeline can't be recreated because its shader stages weren't retained"
+            ),
+            Self::Creation(e) => write!(f, "failed to recreate pipeline: {}", e),
+        }
+    }
+}
+
+impl error::Error for PipelineRecreationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::ShaderStagesNotRetained => None,
+            Self::Creation(e) => Some(e),
+        }
+    }
 }
@@ -3,10 +3,22 @@ use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
+/// A `vk::PipelineCache` which can be shared between threads (e.g. in an `Arc`) for concurrent
+/// pipeline creation.
+///
+/// By default the implementation synchronizes access to the cache internally. Caches created with
+/// `EXTERNALLY_SYNCHRONIZED` (see [`Self::new_externally_synchronized`]) skip the driver's locking
+/// and are instead guarded by a mutex which the pipeline creation functions of this crate lock for
+/// the duration of the call. Use [`Self::lock_external_sync`] when passing [`Self::handle`] to
+/// vulkan functions directly.
+///
+/// See [`PipelineCacheStore`](crate::PipelineCacheStore) for persisting caches between runs.
 pub struct PipelineCache {
     handle: vk::PipelineCache,
+    /// `Some` if created with `EXTERNALLY_SYNCHRONIZED`.
+    external_sync: Option<Mutex<()>>,
 
     // dependencies
     device: Arc<Device>,
@@ -20,7 +32,41 @@ impl PipelineCache {
                 .create_pipeline_cache(&create_info, ALLOCATION_CALLBACK_NONE)
         }?;
 
-        Ok(Self { handle, device })
+        let external_sync = create_info
+            .flags
+            .contains(vk::PipelineCacheCreateFlags::EXTERNALLY_SYNCHRONIZED)
+            .then(|| Mutex::new(()));
+
+        Ok(Self {
+            handle,
+            external_sync,
+            device,
+        })
+    }
+
+    /// Creates an empty cache with `EXTERNALLY_SYNCHRONIZED`, which avoids the driver's internal
+    /// locking. Worthwhile when pipelines are mostly created from one thread at a time, e.g. a
+    /// [`PipelineCompiler`](crate::PipelineCompiler) with a single worker thread. Requires vulkan
+    /// 1.3 or the `pipelineCreationCacheControl` feature.
+    pub fn new_externally_synchronized(device: Arc<Device>) -> VkResult<Self> {
+        let create_info = vk::PipelineCacheCreateInfo::default()
+            .flags(vk::PipelineCacheCreateFlags::EXTERNALLY_SYNCHRONIZED);
+        Self::new(device, create_info)
+    }
+
+    /// Locks the mutex guarding an `EXTERNALLY_SYNCHRONIZED` cache. Hold the guard while calling
+    /// vulkan functions with [`Self::handle`] directly. Returns `None` for internally synchronized
+    /// caches which don't need locking.
+    pub fn lock_external_sync(&self) -> Option<MutexGuard<'_, ()>> {
+        self.external_sync
+            .as_ref()
+            .map(|external_sync| external_sync.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetPipelineCacheData.html>
+    pub fn get_data(&self) -> VkResult<Vec<u8>> {
+        let _external_sync = self.lock_external_sync();
+        unsafe { self.device.inner().get_pipeline_cache_data(self.handle) }
     }
 
     // Getters
@@ -28,6 +74,12 @@ impl PipelineCache {
     pub fn handle(&self) -> vk::PipelineCache {
         self.handle
     }
+
+    /// Whether the cache was created with `EXTERNALLY_SYNCHRONIZED`.
+    #[inline]
+    pub fn is_externally_synchronized(&self) -> bool {
+        self.external_sync.is_some()
+    }
 }
 
 impl DeviceOwned for PipelineCache {
@@ -0,0 +1,481 @@
+use crate::{Device, DeviceOwned, PhysicalDevice, PipelineCache};
+use ash::vk;
+use log::warn;
+use std::{
+    env, error, fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+const HEADER_MAGIC: [u8; 8] = *b"BORTPLC\0";
+const HEADER_FORMAT_VERSION: u32 = 1;
+/// magic, format version, flags, vendor id, device id, driver version, pipeline cache uuid,
+/// uncompressed data size, checksum.
+const HEADER_SIZE: usize = 8 + 4 + 4 + 4 + 4 + 4 + vk::UUID_SIZE + 8 + 8;
+const HEADER_FLAG_ZSTD: u32 = 1;
+/// Upper bound on the data size read from a header. The size is used as the decompression buffer
+/// capacity so a corrupt or malicious header mustn't be able to request an arbitrarily large
+/// allocation. Driver pipeline caches are far smaller than this in practice.
+pub const MAX_PIPELINE_CACHE_DATA_SIZE: u64 = 1 << 30;
+
+/// Identifies the driver and device a pipeline cache blob was created with. Cache data is only
+/// valid for the exact same values, e.g. it becomes stale after a driver update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PipelineCacheIdentity {
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_version: u32,
+    pub pipeline_cache_uuid: [u8; vk::UUID_SIZE],
+}
+
+impl PipelineCacheIdentity {
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> Self {
+        let properties = physical_device.properties();
+        Self {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_version: properties.driver_version,
+            pipeline_cache_uuid: properties.pipeline_cache_uuid,
+        }
+    }
+
+    /// File name unique to the vendor, device and pipeline cache uuid. The driver version is
+    /// checked from the header instead so updates replace the old file rather than adding to it.
+    pub fn file_name(&self) -> String {
+        let uuid_hex: String = self
+            .pipeline_cache_uuid
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!(
+            "{:04x}-{:04x}-{}.pipeline_cache",
+            self.vendor_id, self.device_id, uuid_hex
+        )
+    }
+}
+
+/// Wraps `vkGetPipelineCacheData` output in a header containing `identity`, the data size and a
+/// checksum of the data so stale or corrupted files can be detected with
+/// [`decode_pipeline_cache_blob`]. With `compress` and the `zstd` feature, the data is zstd
+/// compressed. `compress` is ignored without the `zstd` feature.
+pub fn encode_pipeline_cache_blob(
+    data: &[u8],
+    identity: &PipelineCacheIdentity,
+    compress: bool,
+) -> Result<Vec<u8>, PipelineCacheStoreError> {
+    let (flags, payload) = compress_payload(data, compress)?;
+
+    let mut blob = Vec::with_capacity(HEADER_SIZE + payload.len());
+    blob.extend_from_slice(&HEADER_MAGIC);
+    blob.extend_from_slice(&HEADER_FORMAT_VERSION.to_le_bytes());
+    blob.extend_from_slice(&flags.to_le_bytes());
+    blob.extend_from_slice(&identity.vendor_id.to_le_bytes());
+    blob.extend_from_slice(&identity.device_id.to_le_bytes());
+    blob.extend_from_slice(&identity.driver_version.to_le_bytes());
+    blob.extend_from_slice(&identity.pipeline_cache_uuid);
+    blob.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    blob.extend_from_slice(&checksum(data).to_le_bytes());
+    blob.extend_from_slice(&payload);
+    Ok(blob)
+}
+
+/// Returns the pipeline cache data from a blob written by [`encode_pipeline_cache_blob`] after
+/// checking the header matches `identity` and the data matches the checksum. Blobs claiming more
+/// than [`MAX_PIPELINE_CACHE_DATA_SIZE`] bytes of data are rejected before decompressing.
+pub fn decode_pipeline_cache_blob(
+    blob: &[u8],
+    identity: &PipelineCacheIdentity,
+) -> Result<Vec<u8>, PipelineCacheStoreError> {
+    if blob.len() < HEADER_SIZE || blob[..HEADER_MAGIC.len()] != HEADER_MAGIC {
+        return Err(PipelineCacheStoreError::InvalidHeader);
+    }
+    let mut reader = HeaderReader {
+        bytes: &blob[HEADER_MAGIC.len()..HEADER_SIZE],
+    };
+    let format_version = reader.read_u32();
+    if format_version != HEADER_FORMAT_VERSION {
+        return Err(PipelineCacheStoreError::InvalidHeader);
+    }
+    let flags = reader.read_u32();
+    let blob_identity = PipelineCacheIdentity {
+        vendor_id: reader.read_u32(),
+        device_id: reader.read_u32(),
+        driver_version: reader.read_u32(),
+        pipeline_cache_uuid: reader.read_uuid(),
+    };
+    if blob_identity != *identity {
+        return Err(PipelineCacheStoreError::IdentityMismatch {
+            expected: *identity,
+            found: blob_identity,
+        });
+    }
+    let data_size = reader.read_u64();
+    let expected_checksum = reader.read_u64();
+    if data_size > MAX_PIPELINE_CACHE_DATA_SIZE {
+        return Err(PipelineCacheStoreError::DataTooLarge { data_size });
+    }
+
+    let data = decompress_payload(&blob[HEADER_SIZE..], flags, data_size)?;
+    if data.len() as u64 != data_size || checksum(&data) != expected_checksum {
+        return Err(PipelineCacheStoreError::ChecksumMismatch);
+    }
+    Ok(data)
+}
+
+/// Loads and saves pipeline caches in a directory with one file per device (see
+/// [`PipelineCacheIdentity::file_name`]). Files are written to a temporary file first and then
+/// renamed over the old file so a crash mid-write never leaves a truncated cache behind.
+pub struct PipelineCacheStore {
+    directory: PathBuf,
+    compress: bool,
+}
+
+impl PipelineCacheStore {
+    /// Stores caches in `directory`, creating it on the first save if needed. Compression is
+    /// enabled by default when the `zstd` feature is.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            compress: cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Stores caches in a per-user cache directory e.g. `$XDG_CACHE_HOME/<application_name>` or
+    /// `~/.cache/<application_name>` on linux, `~/Library/Caches/<application_name>` on macos and
+    /// `%LOCALAPPDATA%\<application_name>` on windows. Returns `None` if the relevant
+    /// environment variables aren't set.
+    pub fn new_user_cache_directory(application_name: &str) -> Option<Self> {
+        user_cache_directory().map(|directory| Self::new(directory.join(application_name)))
+    }
+
+    /// Whether saved caches are zstd compressed. Ignored without the `zstd` feature.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// The file the cache for `physical_device` is stored in.
+    pub fn cache_path(&self, physical_device: &PhysicalDevice) -> PathBuf {
+        self.directory
+            .join(PipelineCacheIdentity::from_physical_device(physical_device).file_name())
+    }
+
+    /// Reads the stored cache data for `physical_device`. Returns `Ok(None)` if there is no
+    /// stored cache.
+    pub fn read_data(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Result<Option<Vec<u8>>, PipelineCacheStoreError> {
+        let path = self.cache_path(physical_device);
+        let blob = match fs::read(&path) {
+            Ok(blob) => blob,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(PipelineCacheStoreError::Io { e, path }),
+        };
+        let identity = PipelineCacheIdentity::from_physical_device(physical_device);
+        decode_pipeline_cache_blob(&blob, &identity).map(Some)
+    }
+
+    /// Creates a pipeline cache from the stored data for the device. Falls back to an empty cache
+    /// (with a warning) if the stored file is missing, stale or corrupted.
+    pub fn load(&self, device: Arc<Device>) -> Result<PipelineCache, PipelineCacheStoreError> {
+        let initial_data = match self.read_data(device.physical_device()) {
+            Ok(initial_data) => initial_data.unwrap_or_default(),
+            Err(e) => {
+                warn!("ignoring stored pipeline cache: {}", e);
+                Vec::new()
+            }
+        };
+        let create_info = vk::PipelineCacheCreateInfo::default().initial_data(&initial_data);
+        PipelineCache::new(device, create_info).map_err(PipelineCacheStoreError::Creation)
+    }
+
+    /// Writes the contents of `pipeline_cache` to [`Self::cache_path`], atomically replacing any
+    /// previous file.
+    pub fn save(&self, pipeline_cache: &PipelineCache) -> Result<(), PipelineCacheStoreError> {
+        let device = pipeline_cache.device();
+        let data = pipeline_cache
+            .get_data()
+            .map_err(PipelineCacheStoreError::GetData)?;
+        let identity = PipelineCacheIdentity::from_physical_device(device.physical_device());
+        let blob = encode_pipeline_cache_blob(&data, &identity, self.compress)?;
+
+        fs::create_dir_all(&self.directory).map_err(|e| PipelineCacheStoreError::Io {
+            e,
+            path: self.directory.clone(),
+        })?;
+        let path = self.cache_path(device.physical_device());
+        write_atomic(&path, &blob)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    #[inline]
+    pub fn compress(&self) -> bool {
+        self.compress
+    }
+}
+
+// Helper Functions
+
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), PipelineCacheStoreError> {
+    let temp_path = path.with_extension("pipeline_cache.tmp");
+    let io_error = |e, path: &Path| PipelineCacheStoreError::Io {
+        e,
+        path: path.to_owned(),
+    };
+
+    let mut file = File::create(&temp_path).map_err(|e| io_error(e, &temp_path))?;
+    file.write_all(contents)
+        .and_then(|()| file.sync_all())
+        .map_err(|e| io_error(e, &temp_path))?;
+    drop(file);
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        io_error(e, path)
+    })
+}
+
+fn user_cache_directory() -> Option<PathBuf> {
+    let non_empty_var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(target_os = "windows") {
+        non_empty_var("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty_var("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+    } else {
+        non_empty_var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty_var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    }
+}
+
+/// 64-bit FNV-1a. Only detects accidental corruption.
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(feature = "zstd")]
+fn compress_payload(
+    data: &[u8],
+    compress: bool,
+) -> Result<(u32, Vec<u8>), PipelineCacheStoreError> {
+    if !compress {
+        return Ok((0, data.to_vec()));
+    }
+    let payload = zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL)
+        .map_err(PipelineCacheStoreError::Compression)?;
+    Ok((HEADER_FLAG_ZSTD, payload))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress_payload(
+    data: &[u8],
+    _compress: bool,
+) -> Result<(u32, Vec<u8>), PipelineCacheStoreError> {
+    Ok((0, data.to_vec()))
+}
+
+fn decompress_payload(
+    payload: &[u8],
+    flags: u32,
+    data_size: u64,
+) -> Result<Vec<u8>, PipelineCacheStoreError> {
+    if flags & HEADER_FLAG_ZSTD == 0 {
+        return Ok(payload.to_vec());
+    }
+    #[cfg(feature = "zstd")]
+    {
+        // bounded by `MAX_PIPELINE_CACHE_DATA_SIZE`
+        let capacity = usize::try_from(data_size)
+            .map_err(|_| PipelineCacheStoreError::DataTooLarge { data_size })?;
+        zstd::bulk::decompress(payload, capacity).map_err(PipelineCacheStoreError::Compression)
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        let _ = data_size;
+        Err(PipelineCacheStoreError::CompressionUnsupported)
+    }
+}
+
+struct HeaderReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl HeaderReader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        taken.try_into().expect("split_at returns N bytes")
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn read_uuid(&mut self) -> [u8; vk::UUID_SIZE] {
+        self.take()
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum PipelineCacheStoreError {
+    Io {
+        e: io::Error,
+        path: PathBuf,
+    },
+    /// The file isn't a pipeline cache blob written by this crate (or by an incompatible version).
+    InvalidHeader,
+    /// The blob was written for a different device or driver version.
+    IdentityMismatch {
+        expected: PipelineCacheIdentity,
+        found: PipelineCacheIdentity,
+    },
+    /// The data doesn't match the size or checksum in the header e.g. the file was truncated.
+    ChecksumMismatch,
+    /// The header claims more than [`MAX_PIPELINE_CACHE_DATA_SIZE`] bytes of data.
+    DataTooLarge {
+        data_size: u64,
+    },
+    /// The blob is zstd compressed but the `zstd` feature isn't enabled.
+    CompressionUnsupported,
+    Compression(io::Error),
+    GetData(vk::Result),
+    Creation(vk::Result),
+}
+
+impl fmt::Display for PipelineCacheStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { e, path } => {
+                write!(f, "pipeline cache file {} io error: {}", path.display(), e)
+            }
+            Self::InvalidHeader => write!(f, "pipeline cache blob has an invalid header"),
+            Self::IdentityMismatch { expected, found } => write!(
+                f,
+                "pipeline cache blob was created for {:?} but the device is {:?}",
+                found, expected
+            ),
+            Self::ChecksumMismatch => {
+                write!(f, "pipeline cache data doesn't match the header checksum")
+            }
+            Self::DataTooLarge { data_size } => write!(
+                f,
+                "pipeline cache blob header claims {} bytes of data which exceeds the limit of {}",
+                data_size, MAX_PIPELINE_CACHE_DATA_SIZE
+            ),
+            Self::CompressionUnsupported => write!(
+                f,
+                "pipeline cache blob is zstd compressed but the zstd feature isn't enabled"
+            ),
+            Self::Compression(e) => write!(f, "pipeline cache (de)compression failed: {}", e),
+            Self::GetData(e) => write!(f, "failed to get pipeline cache data: {}", e),
+            Self::Creation(e) => write!(f, "pipeline cache creation failed: {}", e),
+        }
+    }
+}
+
+impl error::Error for PipelineCacheStoreError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io { e, .. } => Some(e),
+            Self::InvalidHeader => None,
+            Self::IdentityMismatch { .. } => None,
+            Self::ChecksumMismatch => None,
+            Self::DataTooLarge { .. } => None,
+            Self::CompressionUnsupported => None,
+            Self::Compression(e) => Some(e),
+            Self::GetData(e) => Some(e),
+            Self::Creation(e) => Some(e),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[cfg(test)]
+const TEST_IDENTITY: PipelineCacheIdentity = PipelineCacheIdentity {
+    vendor_id: 0x10de,
+    device_id: 0x2684,
+    driver_version: 42,
+    pipeline_cache_uuid: [7; vk::UUID_SIZE],
+};
+
+#[test]
+fn pipeline_cache_blob_round_trip() {
+    let data: Vec<u8> = (0..1024_u32).map(|i| (i % 13) as u8).collect();
+    for compress in [false, true] {
+        let blob = encode_pipeline_cache_blob(&data, &TEST_IDENTITY, compress).unwrap();
+        assert_eq!(
+            decode_pipeline_cache_blob(&blob, &TEST_IDENTITY).unwrap(),
+            data
+        );
+    }
+}
+
+#[test]
+fn pipeline_cache_blob_rejects_stale_and_corrupt_data() {
+    let data = vec![3_u8; 64];
+    let mut blob = encode_pipeline_cache_blob(&data, &TEST_IDENTITY, false).unwrap();
+
+    let updated_driver = PipelineCacheIdentity {
+        driver_version: 43,
+        ..TEST_IDENTITY
+    };
+    assert!(matches!(
+        decode_pipeline_cache_blob(&blob, &updated_driver),
+        Err(PipelineCacheStoreError::IdentityMismatch { .. })
+    ));
+
+    *blob.last_mut().unwrap() ^= 0xff;
+    assert!(matches!(
+        decode_pipeline_cache_blob(&blob, &TEST_IDENTITY),
+        Err(PipelineCacheStoreError::ChecksumMismatch)
+    ));
+
+    // data size is the second to last header field
+    let data_size_offset = HEADER_SIZE - 16;
+    let mut oversized = blob.clone();
+    oversized[data_size_offset..data_size_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(
+        decode_pipeline_cache_blob(&oversized, &TEST_IDENTITY),
+        Err(PipelineCacheStoreError::DataTooLarge {
+            data_size: u64::MAX
+        })
+    ));
+
+    blob.truncate(HEADER_SIZE - 1);
+    assert!(matches!(
+        decode_pipeline_cache_blob(&blob, &TEST_IDENTITY),
+        Err(PipelineCacheStoreError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn pipeline_cache_store_writes_atomically() {
+    let directory = env::temp_dir().join(format!("bort-pipeline-cache-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(TEST_IDENTITY.file_name());
+
+    write_atomic(&path, b"first").unwrap();
+    write_atomic(&path, b"second").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"second");
+    assert!(!path.with_extension("pipeline_cache.tmp").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
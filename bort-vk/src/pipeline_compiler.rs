@@ -0,0 +1,286 @@
+use crate::{
+    ComputePipeline, ComputePipelineProperties, GraphicsPipeline, GraphicsPipelineProperties,
+    PipelineCache, PipelineLayout, RenderPass, RetainedShaderStage, ShaderStage,
+};
+use ash::{prelude::VkResult, vk};
+use log::warn;
+use std::{
+    io,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+type CompileJob = Box<dyn FnOnce() + Send>;
+
+/// Creates pipelines on a pool of background threads so on-demand pipeline creation doesn't
+/// cause frame hitches. Each request returns a [`PendingPipeline`] which can be polled each
+/// frame (drawing with a fallback pipeline meanwhile) or blocked on.
+///
+/// Pipelines are created with [`GraphicsPipeline::new_recreatable`] /
+/// [`ComputePipeline::new_recreatable`] using the compiler's pipeline cache, if any. Use
+/// [`Self::compile_with`] for anything else, e.g. ray tracing pipelines created with a
+/// [`DeferredOperation`](crate::DeferredOperation).
+pub struct PipelineCompiler {
+    job_sender: Option<Sender<CompileJob>>,
+    workers: Vec<JoinHandle<()>>,
+    pipeline_cache: Option<Arc<PipelineCache>>,
+}
+
+impl PipelineCompiler {
+    /// Spawns `thread_count` (at least 1) worker threads. `pipeline_cache` is used for all
+    /// pipelines created by the compiler and may be shared with other threads. An internally
+    /// synchronized cache lets the workers create pipelines in parallel, whereas an
+    /// [externally synchronized](PipelineCache::new_externally_synchronized) one is locked for
+    /// each pipeline creation so is best suited to a single worker thread.
+    pub fn new(
+        thread_count: usize,
+        pipeline_cache: Option<Arc<PipelineCache>>,
+    ) -> io::Result<Self> {
+        let (job_sender, job_receiver) = mpsc::channel::<CompileJob>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..thread_count.max(1))
+            .map(|worker_index| {
+                let job_receiver = job_receiver.clone();
+                thread::Builder::new()
+                    .name(format!("bort-pipeline-compiler-{}", worker_index))
+                    .spawn(move || worker_loop(&job_receiver))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            job_sender: Some(job_sender),
+            workers,
+            pipeline_cache,
+        })
+    }
+
+    /// Queues the creation of a graphics pipeline. `fallback` is returned by
+    /// [`PendingPipeline::get_or_fallback`] until the pipeline is ready.
+    pub fn compile_graphics(
+        &self,
+        pipeline_layout: Arc<PipelineLayout>,
+        properties: GraphicsPipelineProperties,
+        shader_stages: Vec<RetainedShaderStage>,
+        render_pass: Arc<RenderPass>,
+        fallback: Option<Arc<GraphicsPipeline>>,
+    ) -> PendingPipeline<GraphicsPipeline> {
+        self.compile_with(
+            move |pipeline_cache| {
+                let shader_stages: Vec<ShaderStage> = shader_stages
+                    .iter()
+                    .map(RetainedShaderStage::shader_stage)
+                    .collect();
+                GraphicsPipeline::new_recreatable(
+                    pipeline_layout,
+                    properties,
+                    &shader_stages,
+                    &render_pass,
+                    pipeline_cache,
+                )
+            },
+            fallback,
+        )
+    }
+
+    /// Queues the creation of a compute pipeline. `fallback` is returned by
+    /// [`PendingPipeline::get_or_fallback`] until the pipeline is ready.
+    pub fn compile_compute(
+        &self,
+        pipeline_layout: Arc<PipelineLayout>,
+        properties: ComputePipelineProperties,
+        shader_stage: RetainedShaderStage,
+        fallback: Option<Arc<ComputePipeline>>,
+    ) -> PendingPipeline<ComputePipeline> {
+        self.compile_with(
+            move |pipeline_cache| {
+                ComputePipeline::new_recreatable(
+                    pipeline_layout,
+                    properties,
+                    &shader_stage.shader_stage(),
+                    pipeline_cache,
+                )
+            },
+            fallback,
+        )
+    }
+
+    /// Runs `create_pipeline` on a worker thread, passing it the compiler's pipeline cache. Hold
+    /// [`PipelineCache::lock_external_sync`] if passing the cache handle to vulkan directly.
+    pub fn compile_with<P: Send + Sync + 'static>(
+        &self,
+        create_pipeline: impl FnOnce(Option<&PipelineCache>) -> VkResult<P> + Send + 'static,
+        fallback: Option<Arc<P>>,
+    ) -> PendingPipeline<P> {
+        let (result_sender, result_receiver) = mpsc::channel();
+        let pipeline_cache = self.pipeline_cache.clone();
+
+        let job: CompileJob = Box::new(move || {
+            let result = create_pipeline(pipeline_cache.as_deref());
+            // the pending pipeline may have been dropped, in which case the result isn't needed
+            let _ = result_sender.send(result);
+        });
+
+        let queued = self
+            .job_sender
+            .as_ref()
+            .map(|job_sender| job_sender.send(job).is_ok())
+            .unwrap_or(false);
+
+        let state = if queued {
+            PendingState::Compiling(result_receiver)
+        } else {
+            // all workers have stopped (e.g. one panicked while holding the job receiver lock)
+            PendingState::Failed(vk::Result::ERROR_UNKNOWN)
+        };
+        PendingPipeline { state, fallback }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline_cache(&self) -> Option<&Arc<PipelineCache>> {
+        self.pipeline_cache.as_ref()
+    }
+
+    #[inline]
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl Drop for PipelineCompiler {
+    /// Waits for queued pipelines to finish compiling.
+    fn drop(&mut self) {
+        // dropping the sender ends the worker loops once the queue is empty
+        self.job_sender = None;
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                warn!("pipeline compiler thread panicked");
+            }
+        }
+    }
+}
+
+/// A pipeline being created by a [`PipelineCompiler`].
+pub struct PendingPipeline<P> {
+    state: PendingState<P>,
+    fallback: Option<Arc<P>>,
+}
+
+enum PendingState<P> {
+    Compiling(Receiver<VkResult<P>>),
+    Ready(Arc<P>),
+    Failed(vk::Result),
+}
+
+impl<P> PendingPipeline<P> {
+    /// A pending pipeline that is already complete. Handy for code paths that sometimes create
+    /// pipelines synchronously.
+    pub fn from_ready(pipeline: Arc<P>) -> Self {
+        Self {
+            state: PendingState::Ready(pipeline),
+            fallback: None,
+        }
+    }
+
+    /// Checks whether compilation has finished without blocking. Returns `None` while the
+    /// pipeline is still compiling.
+    pub fn poll(&mut self) -> Option<VkResult<&Arc<P>>> {
+        if let PendingState::Compiling(result_receiver) = &self.state {
+            let result = match result_receiver.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => Err(vk::Result::ERROR_UNKNOWN),
+            };
+            self.set_result(result);
+        }
+        self.completed_result()
+    }
+
+    /// Blocks until compilation has finished.
+    pub fn wait(&mut self) -> VkResult<&Arc<P>> {
+        if let PendingState::Compiling(result_receiver) = &self.state {
+            let result = result_receiver
+                .recv()
+                .unwrap_or(Err(vk::Result::ERROR_UNKNOWN));
+            self.set_result(result);
+        }
+        self.completed_result()
+            .expect("pipeline compilation completed")
+    }
+
+    /// The compiled pipeline if it's ready, otherwise the fallback pipeline (also if compilation
+    /// failed). Doesn't block.
+    pub fn get_or_fallback(&mut self) -> Option<&Arc<P>> {
+        let _ = self.poll();
+        match &self.state {
+            PendingState::Ready(pipeline) => Some(pipeline),
+            PendingState::Compiling(_) | PendingState::Failed(_) => self.fallback.as_ref(),
+        }
+    }
+
+    pub fn is_compiling(&mut self) -> bool {
+        self.poll().is_none()
+    }
+
+    fn set_result(&mut self, result: VkResult<P>) {
+        self.state = match result {
+            Ok(pipeline) => PendingState::Ready(Arc::new(pipeline)),
+            Err(e) => PendingState::Failed(e),
+        };
+    }
+
+    fn completed_result(&self) -> Option<VkResult<&Arc<P>>> {
+        match &self.state {
+            PendingState::Compiling(_) => None,
+            PendingState::Ready(pipeline) => Some(Ok(pipeline)),
+            PendingState::Failed(e) => Some(Err(*e)),
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn fallback(&self) -> Option<&Arc<P>> {
+        self.fallback.as_ref()
+    }
+}
+
+// Helper Functions
+
+fn worker_loop(job_receiver: &Mutex<Receiver<CompileJob>>) {
+    loop {
+        // only hold the lock while waiting for a job so other workers can compile in parallel
+        let job = match job_receiver.lock() {
+            Ok(job_receiver) => job_receiver.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn pending_pipeline_uses_fallback_until_ready() {
+    let (result_sender, result_receiver) = mpsc::channel();
+    let mut pending = PendingPipeline {
+        state: PendingState::Compiling(result_receiver),
+        fallback: Some(Arc::new(0_u32)),
+    };
+
+    assert!(pending.is_compiling());
+    assert_eq!(pending.get_or_fallback().map(|p| **p), Some(0));
+
+    result_sender.send(Ok(1_u32)).unwrap();
+    assert_eq!(pending.poll().map(|res| res.map(|p| **p)), Some(Ok(1)));
+    assert_eq!(pending.get_or_fallback().map(|p| **p), Some(1));
+    assert_eq!(pending.wait().map(|p| **p), Ok(1));
+}
@@ -1,6 +1,7 @@
 use crate::{
-    Device, DeviceOwned, PipelineAccess, PipelineCache, PipelineLayout, ShaderStage,
-    ALLOCATION_CALLBACK_NONE,
+    instrumentation::trace_span, CountedObjectType, Device, DeviceOwned, PipelineAccess,
+    PipelineCache, PipelineLayout, PipelineRecreationError, PipelineRobustness,
+    RetainedShaderStage, ShaderStage, ALLOCATION_CALLBACK_NONE,
 };
 use ash::{
     prelude::VkResult,
@@ -11,6 +12,9 @@ use std::sync::Arc;
 pub struct ComputePipeline {
     handle: vk::Pipeline,
     properties: ComputePipelineProperties,
+    /// Only `Some` for pipelines created with [`Self::new_recreatable`].
+    shader_stage: Option<RetainedShaderStage>,
+    counted: bool,
 
     // dependencies
     pipeline_layout: Arc<PipelineLayout>,
@@ -24,17 +28,102 @@ impl ComputePipeline {
         shader_stage: &ShaderStage,
         pipeline_cache: Option<&PipelineCache>,
     ) -> VkResult<Self> {
-        let create_info = properties
+        let handle =
+            Self::create_handle(&pipeline_layout, &properties, shader_stage, pipeline_cache)?;
+
+        let counted = pipeline_layout
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Pipeline);
+        Ok(Self {
+            handle,
+            properties,
+            shader_stage: None,
+            pipeline_layout,
+            counted,
+        })
+    }
+
+    /// Same as [`Self::new`] but keeps a copy of the shader stage (and a reference to its shader
+    /// module) so it can be inspected with [`Self::shader_stage`] and the pipeline can be rebuilt
+    /// with [`Self::recreate`].
+    pub fn new_recreatable(
+        pipeline_layout: Arc<PipelineLayout>,
+        properties: ComputePipelineProperties,
+        shader_stage: &ShaderStage,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> VkResult<Self> {
+        let mut pipeline = Self::new(pipeline_layout, properties, shader_stage, pipeline_cache)?;
+        pipeline.shader_stage = Some(shader_stage.into());
+        Ok(pipeline)
+    }
+
+    /// Creates a new pipeline with the same layout, properties and shader stage as `self`.
+    /// `self` stays valid so it can be used until the new pipeline is swapped in.
+    ///
+    /// Requires `self` to have been created with [`Self::new_recreatable`].
+    pub fn recreate(
+        &self,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> Result<Self, PipelineRecreationError> {
+        let retained_shader_stage = self
+            .shader_stage
+            .as_ref()
+            .ok_or(PipelineRecreationError::ShaderStagesNotRetained)?;
+
+        let handle = Self::create_handle(
+            &self.pipeline_layout,
+            &self.properties,
+            &retained_shader_stage.shader_stage(),
+            pipeline_cache,
+        )
+        .map_err(PipelineRecreationError::Creation)?;
+
+        Ok(Self {
+            handle,
+            properties: self.properties.clone(),
+            shader_stage: self.shader_stage.clone(),
+            pipeline_layout: self.pipeline_layout.clone(),
+            counted: self
+                .device()
+                .object_counters()
+                .record_created(CountedObjectType::Pipeline),
+        })
+    }
+
+    fn create_handle(
+        pipeline_layout: &PipelineLayout,
+        properties: &ComputePipelineProperties,
+        shader_stage: &ShaderStage,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> VkResult<vk::Pipeline> {
+        let mut create_info = properties
             .create_info()
-            .stage(shader_stage.create_info())
+            .stage(shader_stage.try_create_info()?)
             .layout(pipeline_layout.handle());
 
+        let mut robustness_vk = properties
+            .robustness
+            .map(|robustness| robustness.create_info());
+        if let Some(robustness_vk) = robustness_vk.as_mut() {
+            create_info = create_info.push_next(robustness_vk);
+        }
+
         let cache_handle = if let Some(pipeline_cache) = pipeline_cache {
             pipeline_cache.handle()
         } else {
             vk::PipelineCache::null()
         };
+        let _cache_sync = pipeline_cache.and_then(PipelineCache::lock_external_sync);
 
+        let _span = trace_span!(
+            "create_compute_pipelines",
+            pipeline_count = 1,
+            layout_name = pipeline_layout
+                .device()
+                .debug_name(pipeline_layout.handle())
+                .as_deref()
+        );
         let handles = unsafe {
             pipeline_layout.device().inner().create_compute_pipelines(
                 cache_handle,
@@ -43,18 +132,17 @@ impl ComputePipeline {
             )
         }
         .map_err(|(_pipelines, err_code)| err_code)?;
-        let handle = handles[0];
-
-        Ok(Self {
-            handle,
-            properties,
-            pipeline_layout,
-        })
+        Ok(handles[0])
     }
 
     pub fn properties(&self) -> &ComputePipelineProperties {
         &self.properties
     }
+
+    /// The retained shader stage. `None` unless created with [`Self::new_recreatable`].
+    pub fn shader_stage(&self) -> Option<&RetainedShaderStage> {
+        self.shader_stage.as_ref()
+    }
 }
 
 impl PipelineAccess for ComputePipeline {
@@ -90,16 +178,38 @@ impl Drop for ComputePipeline {
                 .inner()
                 .destroy_pipeline(self.handle, ALLOCATION_CALLBACK_NONE)
         }
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::Pipeline, self.counted);
+        self.device().debug_names().remove(self.handle);
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct ComputePipelineProperties {
     pub flags: vk::PipelineCreateFlags,
+    /// Chains a `vk::PipelineRobustnessCreateInfoEXT` to the pipeline create info if `Some`.
+    /// Requires `VK_EXT_pipeline_robustness`. See [`PipelineRobustness`].
+    pub robustness: Option<PipelineRobustness>,
 }
 
 impl ComputePipelineProperties {
-    pub fn create_info(&self) -> vk::ComputePipelineCreateInfo {
+    pub fn create_info(&self) -> vk::ComputePipelineCreateInfo<'_> {
         vk::ComputePipelineCreateInfo::default().flags(self.flags)
     }
+
+    /// Note: `robustness` is left as `None` because it's in the `p_next` chain.
+    pub fn from_create_info(value: &vk::ComputePipelineCreateInfo) -> Self {
+        Self {
+            flags: value.flags,
+            robustness: None,
+        }
+    }
+}
+
+impl From<&vk::ComputePipelineCreateInfo<'_>> for ComputePipelineProperties {
+    fn from(value: &vk::ComputePipelineCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
 }
@@ -1,16 +1,20 @@
 use crate::{
-    Device, DeviceOwned, PipelineAccess, PipelineCache, PipelineLayout, RenderPass, ShaderStage,
-    ALLOCATION_CALLBACK_NONE,
+    instrumentation::trace_span, CountedObjectType, Device, DeviceOwned, PhysicalDevice,
+    PipelineAccess, PipelineCache, PipelineLayout, PipelineRecreationError, PipelineRobustness,
+    RenderPass, RetainedShaderStage, ShaderStage, ALLOCATION_CALLBACK_NONE,
 };
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error, fmt, sync::Arc};
 
 pub struct GraphicsPipeline {
     handle: vk::Pipeline,
     properties: GraphicsPipelineProperties,
+    /// Only `Some` for pipelines created with [`Self::new_recreatable`].
+    shader_stages: Option<Vec<RetainedShaderStage>>,
+    counted: bool,
 
     // dependencies
     pipeline_layout: Arc<PipelineLayout>,
@@ -25,29 +29,142 @@ impl GraphicsPipeline {
         render_pass: &RenderPass,
         pipeline_cache: Option<&PipelineCache>,
     ) -> VkResult<Self> {
+        let handle = Self::create_handle(
+            &pipeline_layout,
+            &properties,
+            shader_stages,
+            render_pass,
+            pipeline_cache,
+        )?;
+
+        let counted = pipeline_layout
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Pipeline);
+        Ok(Self {
+            handle,
+            properties,
+            shader_stages: None,
+            pipeline_layout,
+            counted,
+        })
+    }
+
+    /// Same as [`Self::new`] but keeps copies of the shader stages (and references to their
+    /// shader modules) so they can be inspected with [`Self::shader_stages`] and the pipeline can
+    /// be rebuilt with [`Self::recreate_for_render_pass`].
+    pub fn new_recreatable(
+        pipeline_layout: Arc<PipelineLayout>,
+        properties: GraphicsPipelineProperties,
+        shader_stages: &[ShaderStage],
+        render_pass: &RenderPass,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> VkResult<Self> {
+        let mut pipeline = Self::new(
+            pipeline_layout,
+            properties,
+            shader_stages,
+            render_pass,
+            pipeline_cache,
+        )?;
+        pipeline.shader_stages = Some(shader_stages.iter().map(Into::into).collect());
+        Ok(pipeline)
+    }
+
+    /// Creates a new pipeline with the same layout, properties and shader stages as `self` that
+    /// is compatible with `render_pass` e.g. after the swapchain surface format changed. `self`
+    /// stays valid so it can be used until the new pipeline is swapped in.
+    ///
+    /// Requires `self` to have been created with [`Self::new_recreatable`].
+    pub fn recreate_for_render_pass(
+        &self,
+        render_pass: &RenderPass,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> Result<Self, PipelineRecreationError> {
+        let retained_shader_stages = self
+            .shader_stages
+            .as_ref()
+            .ok_or(PipelineRecreationError::ShaderStagesNotRetained)?;
+        let shader_stages: Vec<ShaderStage> = retained_shader_stages
+            .iter()
+            .map(|shader_stage| shader_stage.shader_stage())
+            .collect();
+
+        let handle = Self::create_handle(
+            &self.pipeline_layout,
+            &self.properties,
+            &shader_stages,
+            render_pass,
+            pipeline_cache,
+        )
+        .map_err(PipelineRecreationError::Creation)?;
+
+        Ok(Self {
+            handle,
+            properties: self.properties.clone(),
+            shader_stages: self.shader_stages.clone(),
+            pipeline_layout: self.pipeline_layout.clone(),
+            counted: self
+                .device()
+                .object_counters()
+                .record_created(CountedObjectType::Pipeline),
+        })
+    }
+
+    fn create_handle(
+        pipeline_layout: &PipelineLayout,
+        properties: &GraphicsPipelineProperties,
+        shader_stages: &[ShaderStage],
+        render_pass: &RenderPass,
+        pipeline_cache: Option<&PipelineCache>,
+    ) -> VkResult<vk::Pipeline> {
+        debug_assert_required_features(
+            &pipeline_layout.device().enabled_features().features_1_0,
+            properties,
+            shader_stages,
+        );
+
         // populate vkPipelineShaderStageCreateInfo
         let shader_stages_vk = shader_stages
             .iter()
-            .map(|stage| stage.create_info())
-            .collect::<Vec<vk::PipelineShaderStageCreateInfo>>();
+            .map(|stage| stage.try_create_info())
+            .collect::<VkResult<Vec<vk::PipelineShaderStageCreateInfo>>>()?;
 
         // populate the sub-structs of vkGraphicsPipelineCreateInfo defined by GraphicsPipelineProperties
-        let properties_vk = properties.vk_create_infos();
+        let mut extension_properties_vk = properties.vk_extension_create_infos();
+        let mut properties_vk = properties.vk_create_infos();
+        properties_vk.chain_extension_create_infos(&mut extension_properties_vk);
 
         // use these and other args to populate the fields of vkGraphicsPipelineCreateInfo
         let create_info =
             properties.write_create_info(vk::GraphicsPipelineCreateInfo::default(), &properties_vk);
-        let create_info = create_info
+        let mut create_info = create_info
             .stages(&shader_stages_vk)
             .render_pass(render_pass.handle())
             .layout(pipeline_layout.handle());
 
+        let mut robustness_vk = properties
+            .robustness
+            .map(|robustness| robustness.create_info());
+        if let Some(robustness_vk) = robustness_vk.as_mut() {
+            create_info = create_info.push_next(robustness_vk);
+        }
+
         let cache_handle = if let Some(pipeline_cache) = pipeline_cache {
             pipeline_cache.handle()
         } else {
             vk::PipelineCache::null()
         };
-
+        let _cache_sync = pipeline_cache.and_then(PipelineCache::lock_external_sync);
+
+        let _span = trace_span!(
+            "create_graphics_pipelines",
+            pipeline_count = 1,
+            layout_name = pipeline_layout
+                .device()
+                .debug_name(pipeline_layout.handle())
+                .as_deref()
+        );
         let handle_res = unsafe {
             pipeline_layout.device().inner().create_graphics_pipelines(
                 cache_handle,
@@ -58,11 +175,7 @@ impl GraphicsPipeline {
         // note: cbf taking VK_PIPELINE_COMPILE_REQUIRED into account rn...
         let handle = handle_res.map_err(|(_pipelines, err_code)| err_code)?[0];
 
-        Ok(Self {
-            handle,
-            properties,
-            pipeline_layout,
-        })
+        Ok(handle)
     }
 
     /// # Safety
@@ -84,7 +197,16 @@ impl GraphicsPipeline {
         } else {
             vk::PipelineCache::null()
         };
-
+        let _cache_sync = pipeline_cache.and_then(PipelineCache::lock_external_sync);
+
+        let _span = trace_span!(
+            "create_graphics_pipelines",
+            pipeline_count = 1,
+            layout_name = pipeline_layout
+                .device()
+                .debug_name(pipeline_layout.handle())
+                .as_deref()
+        );
         let handle_res = unsafe {
             pipeline_layout.device().inner().create_graphics_pipelines(
                 cache_handle,
@@ -95,10 +217,16 @@ impl GraphicsPipeline {
         // note: cbf taking VK_PIPELINE_COMPILE_REQUIRED into account rn...
         let handle = handle_res.map_err(|(_pipelines, err_code)| err_code)?[0];
 
+        let counted = pipeline_layout
+            .device()
+            .object_counters()
+            .record_created(CountedObjectType::Pipeline);
         Ok(Self {
             handle,
             properties,
+            shader_stages: None,
             pipeline_layout,
+            counted,
         })
     }
 
@@ -109,13 +237,28 @@ impl GraphicsPipeline {
         pipeline_cache: Option<&PipelineCache>,
     ) -> VkResult<Vec<Self>> {
         let pipeline_count = per_pipeline_params.len();
+        for params in &per_pipeline_params {
+            debug_assert_required_features(
+                &device.enabled_features().features_1_0,
+                &params.properties,
+                &params.shader_stages,
+            );
+        }
 
         // populate the sub-structs of vkGraphicsPipelineCreateInfo defined by GraphicsPipelineProperties
+        let mut pipeline_extension_properties_vk: Vec<GraphicsPipelineExtensionCreateInfosVk> =
+            per_pipeline_params
+                .iter()
+                .map(|params| params.properties.vk_extension_create_infos())
+                .collect();
         let mut pipeline_properties_vk = Vec::<GraphicsPipelinePropertiesCreateInfosVk>::new();
-        for pipeline_index in 0..pipeline_count {
-            let properties_vk = per_pipeline_params[pipeline_index]
+        for (pipeline_index, extension_properties_vk) in
+            pipeline_extension_properties_vk.iter_mut().enumerate()
+        {
+            let mut properties_vk = per_pipeline_params[pipeline_index]
                 .properties
                 .vk_create_infos();
+            properties_vk.chain_extension_create_infos(extension_properties_vk);
             pipeline_properties_vk.push(properties_vk);
         }
 
@@ -126,11 +269,24 @@ impl GraphicsPipeline {
                 [pipeline_index]
                 .shader_stages
                 .iter()
-                .map(|stage| stage.create_info())
-                .collect();
+                .map(|stage| stage.try_create_info())
+                .collect::<VkResult<_>>()?;
             shader_stage_handles.push(shader_stages_vk);
         }
 
+        // populate the optional vkPipelineRobustnessCreateInfoEXT structs
+        let mut robustness_create_infos: Vec<Option<vk::PipelineRobustnessCreateInfoEXT>> =
+            per_pipeline_params
+                .iter()
+                .map(|params| {
+                    params
+                        .properties
+                        .robustness
+                        .map(|robustness| robustness.create_info())
+                })
+                .collect();
+        let mut robustness_create_infos_iter = robustness_create_infos.iter_mut();
+
         // use these and other args to populate the fields of vkGraphicsPipelineCreateInfo
         let mut create_infos = Vec::<vk::GraphicsPipelineCreateInfo>::new();
         for pipeline_index in 0..pipeline_count {
@@ -141,11 +297,15 @@ impl GraphicsPipeline {
                     &pipeline_properties_vk[pipeline_index],
                 );
 
-            let create_info = create_info
+            let mut create_info = create_info
                 .stages(&shader_stage_handles[pipeline_index])
                 .render_pass(per_pipeline_params[pipeline_index].render_pass.handle())
                 .layout(per_pipeline_params[pipeline_index].pipeline_layout.handle());
 
+            if let Some(Some(robustness_vk)) = robustness_create_infos_iter.next() {
+                create_info = create_info.push_next(robustness_vk);
+            }
+
             create_infos.push(create_info);
         }
 
@@ -159,7 +319,12 @@ impl GraphicsPipeline {
         } else {
             vk::PipelineCache::null()
         };
+        let _cache_sync = pipeline_cache.and_then(PipelineCache::lock_external_sync);
 
+        let _span = trace_span!(
+            "create_graphics_pipelines",
+            pipeline_count = create_infos.len()
+        );
         let pipeline_handles = unsafe {
             device.inner().create_graphics_pipelines(
                 cache_handle,
@@ -175,7 +340,11 @@ impl GraphicsPipeline {
             .map(|(index, params)| Self {
                 handle: pipeline_handles[index],
                 properties: params.properties,
+                shader_stages: None,
                 pipeline_layout: params.pipeline_layout.clone(),
+                counted: device
+                    .object_counters()
+                    .record_created(CountedObjectType::Pipeline),
             })
             .collect();
 
@@ -188,6 +357,12 @@ impl GraphicsPipeline {
     pub fn properties(&self) -> &GraphicsPipelineProperties {
         &self.properties
     }
+
+    /// The retained shader stages. `None` unless created with [`Self::new_recreatable`].
+    #[inline]
+    pub fn shader_stages(&self) -> Option<&[RetainedShaderStage]> {
+        self.shader_stages.as_deref()
+    }
 }
 
 impl PipelineAccess for GraphicsPipeline {
@@ -226,9 +401,90 @@ impl Drop for GraphicsPipeline {
                 .inner()
                 .destroy_pipeline(self.handle, ALLOCATION_CALLBACK_NONE)
         }
+        self.device()
+            .object_counters()
+            .record_destroyed(CountedObjectType::Pipeline, self.counted);
+        self.device().debug_names().remove(self.handle);
+        self.handle = self.device().handle_audit().poisoned(self.handle);
     }
 }
 
+/// Panics in debug builds if the pipeline requires a device feature that wasn't enabled.
+fn debug_assert_required_features(
+    enabled_features: &vk::PhysicalDeviceFeatures,
+    properties: &GraphicsPipelineProperties,
+    shader_stages: &[ShaderStage],
+) {
+    if cfg!(debug_assertions) {
+        let shader_stage_flags = shader_stages
+            .iter()
+            .fold(vk::ShaderStageFlags::empty(), |flags, shader_stage| {
+                flags | shader_stage.stage
+            });
+        if let Some(feature_name) =
+            first_missing_feature(enabled_features, properties, shader_stage_flags)
+        {
+            panic!(
+                "graphics pipeline requires the {} feature which wasn't enabled at device creation",
+                feature_name
+            );
+        }
+    }
+}
+
+/// Returns the name of the first device feature required by the pipeline that isn't enabled.
+fn first_missing_feature(
+    enabled_features: &vk::PhysicalDeviceFeatures,
+    properties: &GraphicsPipelineProperties,
+    shader_stage_flags: vk::ShaderStageFlags,
+) -> Option<&'static str> {
+    let uses_tessellation = shader_stage_flags.intersects(
+        vk::ShaderStageFlags::TESSELLATION_CONTROL | vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+    );
+    let required_features = [
+        (
+            uses_tessellation,
+            enabled_features.tessellation_shader,
+            "tessellationShader",
+        ),
+        (
+            shader_stage_flags.contains(vk::ShaderStageFlags::GEOMETRY),
+            enabled_features.geometry_shader,
+            "geometryShader",
+        ),
+        (
+            properties.rasterization_state.polygon_mode != vk::PolygonMode::FILL,
+            enabled_features.fill_mode_non_solid,
+            "fillModeNonSolid",
+        ),
+        (
+            properties.rasterization_state.depth_clamp_enable,
+            enabled_features.depth_clamp,
+            "depthClamp",
+        ),
+        (
+            properties.multisample_state.sample_shading_enable,
+            enabled_features.sample_rate_shading,
+            "sampleRateShading",
+        ),
+        (
+            properties.depth_stencil_state.depth_bounds_test_enable,
+            enabled_features.depth_bounds,
+            "depthBounds",
+        ),
+        (
+            properties.color_blend_state.logic_op.is_some(),
+            enabled_features.logic_op,
+            "logicOp",
+        ),
+    ];
+
+    required_features
+        .into_iter()
+        .find(|&(required, enabled, _)| required && enabled == vk::FALSE)
+        .map(|(_, _, feature_name)| feature_name)
+}
+
 // Properties
 
 /// Allows usage of `from_create_info_ptr`
@@ -272,6 +528,9 @@ pub struct GraphicsPipelineProperties {
     pub depth_stencil_state: DepthStencilState,
     pub color_blend_state: ColorBlendState,
     pub dynamic_state: DynamicState,
+    /// Chains a `vk::PipelineRobustnessCreateInfoEXT` to the pipeline create info if `Some`.
+    /// Requires `VK_EXT_pipeline_robustness`. See [`PipelineRobustness`].
+    pub robustness: Option<PipelineRobustness>,
 }
 impl GraphicsPipelineProperties {
     /// Returns the `create_info` arg containing references to the structs in `properties_vk`.
@@ -303,9 +562,47 @@ impl GraphicsPipelineProperties {
             .dynamic_state(&properties_vk.dynamic_state_vk)
     }
 
+    /// Returns the extension structs that get chained to the `p_next` of the create infos
+    /// returned by `Self::vk_create_infos`. See
+    /// `GraphicsPipelinePropertiesCreateInfosVk::chain_extension_create_infos`.
+    pub fn vk_extension_create_infos(&self) -> GraphicsPipelineExtensionCreateInfosVk<'static> {
+        GraphicsPipelineExtensionCreateInfosVk {
+            line_rasterization_vk: self
+                .rasterization_state
+                .line_rasterization
+                .map(|line_rasterization| line_rasterization.create_info()),
+            depth_clip_state_vk: self.rasterization_state.depth_clip_enable.map(
+                |depth_clip_enable| {
+                    vk::PipelineRasterizationDepthClipStateCreateInfoEXT::default()
+                        .depth_clip_enable(depth_clip_enable)
+                },
+            ),
+            depth_clip_control_vk: self.rasterization_state.depth_clip_negative_one_to_one.map(
+                |negative_one_to_one| {
+                    vk::PipelineViewportDepthClipControlCreateInfoEXT::default()
+                        .negative_one_to_one(negative_one_to_one)
+                },
+            ),
+            depth_bias_representation_vk: self
+                .rasterization_state
+                .depth_bias_representation
+                .map(|depth_bias_representation| depth_bias_representation.create_info()),
+            conservative_rasterization_vk: self
+                .rasterization_state
+                .conservative_rasterization
+                .map(|conservative_rasterization| conservative_rasterization.create_info()),
+            provoking_vertex_vk: self.rasterization_state.provoking_vertex_mode.map(
+                |provoking_vertex_mode| {
+                    vk::PipelineRasterizationProvokingVertexStateCreateInfoEXT::default()
+                        .provoking_vertex_mode(provoking_vertex_mode)
+                },
+            ),
+        }
+    }
+
     /// Returns a set of vk::*CreateInfo structs populated by the members of `self`.
     /// Use this with `Self::write_create_info` to populate a `GraphicsPipelineCreateInfo`.
-    pub fn vk_create_infos(&self) -> GraphicsPipelinePropertiesCreateInfosVk {
+    pub fn vk_create_infos(&self) -> GraphicsPipelinePropertiesCreateInfosVk<'_> {
         GraphicsPipelinePropertiesCreateInfosVk {
             // write vk create-info for each member to `properties_vk`
             vertex_input_state_vk: self.vertex_input_state.create_info(),
@@ -337,6 +634,8 @@ impl GraphicsPipelineProperties {
     ///     - [`MultisampleState::from_create_info_ptr`]
     ///     - [`ColorBlendState::from_create_info_ptr`]
     ///     - [`DynamicState::from_create_info_ptr`]
+    ///
+    /// Note: the `p_next` chain isn't read so `robustness` will be `None`.
     pub unsafe fn from_create_info(value: &vk::GraphicsPipelineCreateInfo) -> Self {
         let vertex_input_state = if !value.p_vertex_input_state.is_null() {
             let vk_create_info = unsafe { *value.p_vertex_input_state };
@@ -385,6 +684,7 @@ impl GraphicsPipelineProperties {
             depth_stencil_state,
             color_blend_state,
             dynamic_state,
+            robustness: None,
         }
     }
 }
@@ -429,7 +729,7 @@ impl ColorBlendState {
             .blend_constants(self.blend_constants)
     }
 
-    pub fn create_info(&self) -> vk::PipelineColorBlendStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineColorBlendStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineColorBlendStateCreateInfo::default())
     }
 
@@ -527,6 +827,50 @@ impl Default for DepthStencilState {
     }
 }
 impl DepthStencilState {
+    /// Depth test with `depth_compare_op` and depth writes e.g. `LESS` for opaque geometry.
+    pub fn new_depth_test(depth_compare_op: vk::CompareOp) -> Self {
+        Self {
+            depth_test_enable: true,
+            depth_write_enable: true,
+            depth_compare_op,
+            ..Default::default()
+        }
+    }
+
+    /// Depth test with `depth_compare_op` without depth writes e.g. for transparent geometry
+    /// drawn after the opaque pass. Can be used with a `DEPTH_READ_ONLY_*` attachment layout,
+    /// see [`depth_stencil_attachment_layout`](crate::depth_stencil_attachment_layout).
+    pub fn new_depth_read_only(depth_compare_op: vk::CompareOp) -> Self {
+        Self {
+            depth_test_enable: true,
+            depth_write_enable: false,
+            depth_compare_op,
+            ..Default::default()
+        }
+    }
+
+    /// Depth test and writes for reverse-Z (near plane at depth 1, far plane at 0). Clear depth
+    /// to 0. See [`perspective_reverse_z_infinite`](crate::perspective_reverse_z_infinite) for
+    /// the rest of the reverse-Z setup.
+    pub fn new_reverse_z() -> Self {
+        Self::new_depth_test(vk::CompareOp::GREATER_OR_EQUAL)
+    }
+
+    /// Reverse-Z depth test without depth writes e.g. for transparent geometry.
+    pub fn new_reverse_z_read_only() -> Self {
+        Self::new_depth_read_only(vk::CompareOp::GREATER_OR_EQUAL)
+    }
+
+    /// Stencil test only, using `stencil_op_state` for both front and back faces.
+    pub fn new_stencil_only(stencil_op_state: vk::StencilOpState) -> Self {
+        Self {
+            stencil_test_enable: true,
+            front: stencil_op_state,
+            back: stencil_op_state,
+            ..Default::default()
+        }
+    }
+
     pub fn write_create_info<'a>(
         &self,
         create_info: vk::PipelineDepthStencilStateCreateInfo<'a>,
@@ -537,14 +881,14 @@ impl DepthStencilState {
             .depth_write_enable(self.depth_write_enable)
             .depth_compare_op(self.depth_compare_op)
             .depth_bounds_test_enable(self.depth_bounds_test_enable)
-            .stencil_test_enable(self.depth_bounds_test_enable)
+            .stencil_test_enable(self.stencil_test_enable)
             .front(self.front)
             .back(self.back)
             .min_depth_bounds(self.min_depth_bounds)
             .max_depth_bounds(self.max_depth_bounds)
     }
 
-    pub fn create_info(&self) -> vk::PipelineDepthStencilStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineDepthStencilStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineDepthStencilStateCreateInfo::default())
     }
 }
@@ -565,6 +909,12 @@ impl<'a> FromCreateInfo<vk::PipelineDepthStencilStateCreateInfo<'a>> for DepthSt
     }
 }
 
+impl From<&vk::PipelineDepthStencilStateCreateInfo<'_>> for DepthStencilState {
+    fn from(value: &vk::PipelineDepthStencilStateCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkPipelineDynamicStateCreateInfo.html>"]
 #[derive(Debug, Clone)]
 pub struct DynamicState {
@@ -596,7 +946,7 @@ impl DynamicState {
             .dynamic_states(&self.dynamic_states)
     }
 
-    pub fn create_info(&self) -> vk::PipelineDynamicStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineDynamicStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineDynamicStateCreateInfo::default())
     }
 
@@ -643,7 +993,7 @@ impl InputAssemblyState {
             .primitive_restart_enable(self.primitive_restart_enable)
     }
 
-    pub fn create_info(&self) -> vk::PipelineInputAssemblyStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineInputAssemblyStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineInputAssemblyStateCreateInfo::default())
     }
 }
@@ -657,6 +1007,12 @@ impl<'a> FromCreateInfo<vk::PipelineInputAssemblyStateCreateInfo<'a>> for InputA
     }
 }
 
+impl From<&vk::PipelineInputAssemblyStateCreateInfo<'_>> for InputAssemblyState {
+    fn from(value: &vk::PipelineInputAssemblyStateCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkPipelineMultisampleStateCreateInfo.html>"]
 #[derive(Debug, Clone)]
 pub struct MultisampleState {
@@ -696,7 +1052,7 @@ impl MultisampleState {
             .alpha_to_one_enable(self.alpha_to_one_enable)
     }
 
-    pub fn create_info(&self) -> vk::PipelineMultisampleStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineMultisampleStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineMultisampleStateCreateInfo::default())
     }
 
@@ -742,6 +1098,26 @@ pub struct RasterizationState {
     pub depth_bias_clamp: f32,
     pub depth_bias_slope_factor: f32,
     pub line_width: f32,
+    /// `VK_EXT_line_rasterization` state. Chained to the rasterization state create info if
+    /// `Some`.
+    pub line_rasterization: Option<LineRasterization>,
+    /// `VK_EXT_depth_clip_enable`: controls depth clipping independently of
+    /// `depth_clamp_enable`. Chained to the rasterization state create info if `Some`.
+    pub depth_clip_enable: Option<bool>,
+    /// `VK_EXT_depth_clip_control`: `Some(true)` means clip space depth is in the range [-1, 1]
+    /// (OpenGL convention) instead of [0, 1]. Chained to the _viewport_ state create info if
+    /// `Some`.
+    pub depth_clip_negative_one_to_one: Option<bool>,
+    /// `VK_EXT_depth_bias_control` state. Chained to the rasterization state create info if
+    /// `Some`.
+    pub depth_bias_representation: Option<DepthBiasRepresentation>,
+    /// `VK_EXT_conservative_rasterization` state. Chained to the rasterization state create info
+    /// if `Some`.
+    pub conservative_rasterization: Option<ConservativeRasterization>,
+    /// `VK_EXT_provoking_vertex`: which vertex of a primitive flat shaded attributes come from.
+    /// `LAST_VERTEX` matches OpenGL/D3D9 conventions. Chained to the rasterization state create
+    /// info if `Some`.
+    pub provoking_vertex_mode: Option<vk::ProvokingVertexModeEXT>,
 }
 impl Default for RasterizationState {
     fn default() -> Self {
@@ -757,10 +1133,173 @@ impl Default for RasterizationState {
             depth_bias_clamp: 0.,
             depth_bias_slope_factor: 1.,
             line_width: 1.,
+            line_rasterization: None,
+            depth_clip_enable: None,
+            depth_clip_negative_one_to_one: None,
+            depth_bias_representation: None,
+            conservative_rasterization: None,
+            provoking_vertex_mode: None,
         }
     }
 }
 impl RasterizationState {
+    /// No culling and a custom line width. Line widths other than 1.0 require the `wideLines`
+    /// feature. Use `validate` to check `line_width` against the device limits.
+    pub fn new_lines(line_width: f32) -> Self {
+        Self {
+            cull_mode: vk::CullModeFlags::NONE,
+            line_width,
+            ..Default::default()
+        }
+    }
+
+    /// Like `new_lines` but also chains `VK_EXT_line_rasterization` state with the specified
+    /// mode e.g. `vk::LineRasterizationModeEXT::BRESENHAM` for pixel-exact debug lines.
+    pub fn new_lines_with_mode(
+        line_width: f32,
+        line_rasterization_mode: vk::LineRasterizationModeEXT,
+    ) -> Self {
+        Self {
+            line_rasterization: Some(LineRasterization::new(line_rasterization_mode)),
+            ..Self::new_lines(line_width)
+        }
+    }
+
+    /// Wireframe rendering with `vk::PolygonMode::LINE` and no culling, handy for debug views.
+    /// Requires the `fillModeNonSolid` feature.
+    pub fn new_wireframe() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::LINE,
+            cull_mode: vk::CullModeFlags::NONE,
+            ..Default::default()
+        }
+    }
+
+    /// Like `new_wireframe` but only rasterizes the vertices as points. Requires the
+    /// `fillModeNonSolid` feature.
+    pub fn new_points() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::POINT,
+            ..Self::new_wireframe()
+        }
+    }
+
+    /// Sets the `VK_EXT_provoking_vertex` mode. `LAST_VERTEX` requires the `provokingVertexLast`
+    /// feature.
+    pub fn with_provoking_vertex_mode(
+        mut self,
+        provoking_vertex_mode: vk::ProvokingVertexModeEXT,
+    ) -> Self {
+        self.provoking_vertex_mode = Some(provoking_vertex_mode);
+        self
+    }
+
+    /// Enables line stippling. Adds default `VK_EXT_line_rasterization` state if
+    /// `line_rasterization` is `None`.
+    pub fn with_line_stipple(
+        mut self,
+        line_stipple_factor: u32,
+        line_stipple_pattern: u16,
+    ) -> Self {
+        let mut line_rasterization = self.line_rasterization.unwrap_or_default();
+        line_rasterization.stippled_line_enable = true;
+        line_rasterization.line_stipple_factor = line_stipple_factor;
+        line_rasterization.line_stipple_pattern = line_stipple_pattern;
+        self.line_rasterization = Some(line_rasterization);
+        self
+    }
+
+    /// Checks this state against the limits and features supported by `physical_device`:
+    /// - the `fillModeNonSolid` feature is supported if `polygon_mode` isn't `FILL`
+    /// - `line_width` is within `lineWidthRange`
+    /// - the `wideLines` feature is supported if `line_width` isn't 1.0
+    /// - `VK_EXT_line_rasterization` and the requested line modes are supported if
+    ///   `line_rasterization` is `Some`
+    /// - `VK_EXT_depth_clip_enable`, `VK_EXT_depth_clip_control` and `VK_EXT_depth_bias_control`
+    ///   and their relevant features are supported if the corresponding members are `Some`
+    /// - `VK_EXT_conservative_rasterization` is supported and the extra overestimation size is
+    ///   within limits if `conservative_rasterization` is `Some`
+    /// - `VK_EXT_provoking_vertex` and the `provokingVertexLast` feature are supported if
+    ///   `provoking_vertex_mode` is `Some(LAST_VERTEX)`
+    ///
+    /// Note: this checks what is _supported_ by the physical device, not what was enabled when
+    /// creating the device.
+    pub fn validate(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Result<(), RasterizationStateError> {
+        let instance = physical_device.instance();
+        let limits = physical_device.properties().limits;
+        let features_1_0 = instance.physical_device_features_1_0(physical_device);
+
+        check_polygon_mode(self.polygon_mode, features_1_0.fill_mode_non_solid)?;
+
+        let [min_line_width, max_line_width] = limits.line_width_range;
+        if self.line_width < min_line_width || self.line_width > max_line_width {
+            return Err(RasterizationStateError::LineWidthOutOfRange {
+                line_width: self.line_width,
+                line_width_range: limits.line_width_range,
+            });
+        }
+
+        if self.line_width != 1. && features_1_0.wide_lines == vk::FALSE {
+            return Err(RasterizationStateError::WideLinesNotSupported {
+                line_width: self.line_width,
+            });
+        }
+
+        if let Some(line_rasterization) = self.line_rasterization {
+            let line_features = instance
+                .physical_device_line_rasterization_features(physical_device)
+                .ok_or(RasterizationStateError::LineRasterizationNotSupported)?;
+            line_rasterization.validate(&line_features)?;
+        }
+
+        if self.depth_clip_enable.is_some() {
+            let depth_clip_supported = instance
+                .physical_device_depth_clip_enable_features(physical_device)
+                .map(|features| features.depth_clip_enable == vk::TRUE)
+                .unwrap_or(false);
+            if !depth_clip_supported {
+                return Err(RasterizationStateError::DepthClipEnableNotSupported);
+            }
+        }
+
+        if self.depth_clip_negative_one_to_one.is_some() {
+            let depth_clip_control_supported = instance
+                .physical_device_depth_clip_control_features(physical_device)
+                .map(|features| features.depth_clip_control == vk::TRUE)
+                .unwrap_or(false);
+            if !depth_clip_control_supported {
+                return Err(RasterizationStateError::DepthClipControlNotSupported);
+            }
+        }
+
+        if let Some(depth_bias_representation) = self.depth_bias_representation {
+            let depth_bias_features = instance
+                .physical_device_depth_bias_control_features(physical_device)
+                .filter(|features| features.depth_bias_control == vk::TRUE)
+                .ok_or(RasterizationStateError::DepthBiasControlNotSupported)?;
+            depth_bias_representation.validate(&depth_bias_features)?;
+        }
+
+        if let Some(conservative_rasterization) = self.conservative_rasterization {
+            let conservative_properties = instance
+                .physical_device_conservative_rasterization_properties(physical_device)
+                .ok_or(RasterizationStateError::ConservativeRasterizationNotSupported)?;
+            conservative_rasterization.validate(&conservative_properties)?;
+        }
+
+        if let Some(provoking_vertex_mode) = self.provoking_vertex_mode {
+            let provoking_vertex_features = instance
+                .physical_device_provoking_vertex_features(physical_device)
+                .ok_or(RasterizationStateError::ProvokingVertexNotSupported)?;
+            check_provoking_vertex_mode(provoking_vertex_mode, &provoking_vertex_features)?;
+        }
+
+        Ok(())
+    }
+
     pub fn write_create_info<'a>(
         &self,
         create_info: vk::PipelineRasterizationStateCreateInfo<'a>,
@@ -779,7 +1318,7 @@ impl RasterizationState {
             .line_width(self.line_width)
     }
 
-    pub fn create_info(&self) -> vk::PipelineRasterizationStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineRasterizationStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineRasterizationStateCreateInfo::default())
     }
 }
@@ -797,7 +1336,304 @@ impl<'a> FromCreateInfo<vk::PipelineRasterizationStateCreateInfo<'a>> for Raster
             depth_bias_clamp: value.depth_bias_clamp,
             depth_bias_slope_factor: value.depth_bias_slope_factor,
             line_width: value.line_width,
+            line_rasterization: None,
+            depth_clip_enable: None,
+            depth_clip_negative_one_to_one: None,
+            depth_bias_representation: None,
+            conservative_rasterization: None,
+            provoking_vertex_mode: None,
+        }
+    }
+}
+
+impl From<&vk::PipelineRasterizationStateCreateInfo<'_>> for RasterizationState {
+    fn from(value: &vk::PipelineRasterizationStateCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+fn check_polygon_mode(
+    polygon_mode: vk::PolygonMode,
+    fill_mode_non_solid: vk::Bool32,
+) -> Result<(), RasterizationStateError> {
+    if polygon_mode != vk::PolygonMode::FILL && fill_mode_non_solid == vk::FALSE {
+        return Err(RasterizationStateError::PolygonModeNotSupported(
+            polygon_mode,
+        ));
+    }
+    Ok(())
+}
+
+fn check_provoking_vertex_mode(
+    provoking_vertex_mode: vk::ProvokingVertexModeEXT,
+    provoking_vertex_features: &vk::PhysicalDeviceProvokingVertexFeaturesEXT,
+) -> Result<(), RasterizationStateError> {
+    if provoking_vertex_mode == vk::ProvokingVertexModeEXT::LAST_VERTEX
+        && provoking_vertex_features.provoking_vertex_last == vk::FALSE
+    {
+        return Err(RasterizationStateError::ProvokingVertexLastNotSupported);
+    }
+    Ok(())
+}
+
+/// `VK_EXT_conservative_rasterization` state chained to the rasterization state create info.
+///
+/// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkPipelineRasterizationConservativeStateCreateInfoEXT.html>
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConservativeRasterization {
+    pub conservative_rasterization_mode: vk::ConservativeRasterizationModeEXT,
+    pub extra_primitive_overestimation_size: f32,
+}
+impl Default for ConservativeRasterization {
+    fn default() -> Self {
+        Self {
+            conservative_rasterization_mode: vk::ConservativeRasterizationModeEXT::OVERESTIMATE,
+            extra_primitive_overestimation_size: 0.,
+        }
+    }
+}
+impl ConservativeRasterization {
+    pub fn new(conservative_rasterization_mode: vk::ConservativeRasterizationModeEXT) -> Self {
+        Self {
+            conservative_rasterization_mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn write_create_info<'a>(
+        &self,
+        create_info: vk::PipelineRasterizationConservativeStateCreateInfoEXT<'a>,
+    ) -> vk::PipelineRasterizationConservativeStateCreateInfoEXT<'a> {
+        create_info
+            .conservative_rasterization_mode(self.conservative_rasterization_mode)
+            .extra_primitive_overestimation_size(self.extra_primitive_overestimation_size)
+    }
+
+    pub fn create_info(&self) -> vk::PipelineRasterizationConservativeStateCreateInfoEXT<'static> {
+        self.write_create_info(vk::PipelineRasterizationConservativeStateCreateInfoEXT::default())
+    }
+
+    pub fn from_create_info(
+        value: &vk::PipelineRasterizationConservativeStateCreateInfoEXT,
+    ) -> Self {
+        Self {
+            conservative_rasterization_mode: value.conservative_rasterization_mode,
+            extra_primitive_overestimation_size: value.extra_primitive_overestimation_size,
+        }
+    }
+
+    /// Checks `extra_primitive_overestimation_size` against the device limit and that
+    /// underestimation is supported if requested.
+    pub fn validate(
+        &self,
+        conservative_properties: &vk::PhysicalDeviceConservativeRasterizationPropertiesEXT,
+    ) -> Result<(), RasterizationStateError> {
+        if self.conservative_rasterization_mode
+            == vk::ConservativeRasterizationModeEXT::UNDERESTIMATE
+            && conservative_properties.primitive_underestimation == vk::FALSE
+        {
+            return Err(RasterizationStateError::PrimitiveUnderestimationNotSupported);
+        }
+
+        let max_size = conservative_properties.max_extra_primitive_overestimation_size;
+        if self.extra_primitive_overestimation_size < 0.
+            || self.extra_primitive_overestimation_size > max_size
+        {
+            return Err(
+                RasterizationStateError::ExtraPrimitiveOverestimationSizeOutOfRange {
+                    extra_primitive_overestimation_size: self.extra_primitive_overestimation_size,
+                    max_extra_primitive_overestimation_size: max_size,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&vk::PipelineRasterizationConservativeStateCreateInfoEXT<'_>>
+    for ConservativeRasterization
+{
+    fn from(value: &vk::PipelineRasterizationConservativeStateCreateInfoEXT<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+/// `VK_EXT_depth_bias_control` state chained to the rasterization state create info.
+///
+/// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkDepthBiasRepresentationInfoEXT.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthBiasRepresentation {
+    pub depth_bias_representation: vk::DepthBiasRepresentationEXT,
+    pub depth_bias_exact: bool,
+}
+impl Default for DepthBiasRepresentation {
+    fn default() -> Self {
+        Self {
+            depth_bias_representation:
+                vk::DepthBiasRepresentationEXT::LEAST_REPRESENTABLE_VALUE_FORMAT,
+            depth_bias_exact: false,
+        }
+    }
+}
+impl DepthBiasRepresentation {
+    pub fn write_create_info<'a>(
+        &self,
+        create_info: vk::DepthBiasRepresentationInfoEXT<'a>,
+    ) -> vk::DepthBiasRepresentationInfoEXT<'a> {
+        create_info
+            .depth_bias_representation(self.depth_bias_representation)
+            .depth_bias_exact(self.depth_bias_exact)
+    }
+
+    pub fn create_info(&self) -> vk::DepthBiasRepresentationInfoEXT<'static> {
+        self.write_create_info(vk::DepthBiasRepresentationInfoEXT::default())
+    }
+
+    pub fn from_create_info(value: &vk::DepthBiasRepresentationInfoEXT) -> Self {
+        Self {
+            depth_bias_representation: value.depth_bias_representation,
+            depth_bias_exact: value.depth_bias_exact != 0,
+        }
+    }
+
+    /// Checks that the representation (and exact depth bias if enabled) are supported in
+    /// `depth_bias_features`.
+    pub fn validate(
+        &self,
+        depth_bias_features: &vk::PhysicalDeviceDepthBiasControlFeaturesEXT,
+    ) -> Result<(), RasterizationStateError> {
+        let representation_supported = match self.depth_bias_representation {
+            vk::DepthBiasRepresentationEXT::LEAST_REPRESENTABLE_VALUE_FORCE_UNORM => {
+                depth_bias_features.least_representable_value_force_unorm_representation
+            }
+            vk::DepthBiasRepresentationEXT::FLOAT => depth_bias_features.float_representation,
+            _ => vk::TRUE,
+        };
+        if representation_supported == vk::FALSE {
+            return Err(
+                RasterizationStateError::DepthBiasRepresentationNotSupported(
+                    self.depth_bias_representation,
+                ),
+            );
         }
+
+        if self.depth_bias_exact && depth_bias_features.depth_bias_exact == vk::FALSE {
+            return Err(RasterizationStateError::DepthBiasExactNotSupported);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&vk::DepthBiasRepresentationInfoEXT<'_>> for DepthBiasRepresentation {
+    fn from(value: &vk::DepthBiasRepresentationInfoEXT<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+/// `VK_EXT_line_rasterization` state chained to the rasterization state create info.
+///
+/// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkPipelineRasterizationLineStateCreateInfoKHR.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRasterization {
+    pub line_rasterization_mode: vk::LineRasterizationModeEXT,
+    pub stippled_line_enable: bool,
+    pub line_stipple_factor: u32,
+    pub line_stipple_pattern: u16,
+}
+impl Default for LineRasterization {
+    fn default() -> Self {
+        Self {
+            line_rasterization_mode: vk::LineRasterizationModeEXT::DEFAULT,
+            stippled_line_enable: false,
+            line_stipple_factor: 1,
+            line_stipple_pattern: u16::MAX,
+        }
+    }
+}
+impl LineRasterization {
+    pub fn new(line_rasterization_mode: vk::LineRasterizationModeEXT) -> Self {
+        Self {
+            line_rasterization_mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn write_create_info<'a>(
+        &self,
+        create_info: vk::PipelineRasterizationLineStateCreateInfoKHR<'a>,
+    ) -> vk::PipelineRasterizationLineStateCreateInfoKHR<'a> {
+        create_info
+            .line_rasterization_mode(self.line_rasterization_mode)
+            .stippled_line_enable(self.stippled_line_enable)
+            .line_stipple_factor(self.line_stipple_factor)
+            .line_stipple_pattern(self.line_stipple_pattern)
+    }
+
+    pub fn create_info(&self) -> vk::PipelineRasterizationLineStateCreateInfoKHR<'static> {
+        self.write_create_info(vk::PipelineRasterizationLineStateCreateInfoKHR::default())
+    }
+
+    pub fn from_create_info(value: &vk::PipelineRasterizationLineStateCreateInfoKHR) -> Self {
+        Self {
+            line_rasterization_mode: value.line_rasterization_mode,
+            stippled_line_enable: value.stippled_line_enable != 0,
+            line_stipple_factor: value.line_stipple_factor,
+            line_stipple_pattern: value.line_stipple_pattern,
+        }
+    }
+
+    /// Checks that the line mode (and stippling if enabled) are supported in `line_features`.
+    pub fn validate(
+        &self,
+        line_features: &vk::PhysicalDeviceLineRasterizationFeaturesKHR,
+    ) -> Result<(), RasterizationStateError> {
+        let (mode_supported, stippled_supported) = match self.line_rasterization_mode {
+            vk::LineRasterizationModeEXT::RECTANGULAR => (
+                line_features.rectangular_lines,
+                line_features.stippled_rectangular_lines,
+            ),
+            vk::LineRasterizationModeEXT::BRESENHAM => (
+                line_features.bresenham_lines,
+                line_features.stippled_bresenham_lines,
+            ),
+            vk::LineRasterizationModeEXT::RECTANGULAR_SMOOTH => (
+                line_features.smooth_lines,
+                line_features.stippled_smooth_lines,
+            ),
+            // the default mode is always supported but stippling requires one of the stipple features
+            _ => (
+                vk::TRUE,
+                line_features.stippled_rectangular_lines
+                    | line_features.stippled_bresenham_lines
+                    | line_features.stippled_smooth_lines,
+            ),
+        };
+
+        if mode_supported == vk::FALSE {
+            return Err(RasterizationStateError::LineRasterizationModeNotSupported(
+                self.line_rasterization_mode,
+            ));
+        }
+        if self.stippled_line_enable && stippled_supported == vk::FALSE {
+            return Err(RasterizationStateError::StippledLinesNotSupported(
+                self.line_rasterization_mode,
+            ));
+        }
+        if self.stippled_line_enable && !(1..=256).contains(&self.line_stipple_factor) {
+            return Err(RasterizationStateError::InvalidLineStippleFactor(
+                self.line_stipple_factor,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&vk::PipelineRasterizationLineStateCreateInfoKHR<'_>> for LineRasterization {
+    fn from(value: &vk::PipelineRasterizationLineStateCreateInfoKHR<'_>) -> Self {
+        Self::from_create_info(value)
     }
 }
 
@@ -825,7 +1661,7 @@ impl TessellationState {
             .patch_control_points(self.patch_control_points)
     }
 
-    pub fn create_info(&self) -> vk::PipelineTessellationStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineTessellationStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineTessellationStateCreateInfo::default())
     }
 }
@@ -838,6 +1674,12 @@ impl<'a> FromCreateInfo<vk::PipelineTessellationStateCreateInfo<'a>> for Tessell
     }
 }
 
+impl From<&vk::PipelineTessellationStateCreateInfo<'_>> for TessellationState {
+    fn from(value: &vk::PipelineTessellationStateCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
 #[doc = "<https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/VkPipelineVertexInputStateCreateInfo.html>"]
 #[derive(Debug, Clone)]
 pub struct VertexInputState {
@@ -865,7 +1707,7 @@ impl VertexInputState {
             .vertex_attribute_descriptions(&self.vertex_attribute_descriptions)
     }
 
-    pub fn create_info(&self) -> vk::PipelineVertexInputStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineVertexInputStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineVertexInputStateCreateInfo::default())
     }
 
@@ -930,7 +1772,7 @@ impl ViewportState {
             .scissors(&self.scissors)
     }
 
-    pub fn create_info(&self) -> vk::PipelineViewportStateCreateInfo {
+    pub fn create_info(&self) -> vk::PipelineViewportStateCreateInfo<'_> {
         self.write_create_info(vk::PipelineViewportStateCreateInfo::default())
     }
 
@@ -985,6 +1827,45 @@ pub struct GraphicsPipelinePropertiesCreateInfosVk<'a> {
     pub color_blend_state_vk: vk::PipelineColorBlendStateCreateInfo<'a>,
     pub dynamic_state_vk: vk::PipelineDynamicStateCreateInfo<'a>,
 }
+impl<'a> GraphicsPipelinePropertiesCreateInfosVk<'a> {
+    /// Pushes the `Some` members of `extension_create_infos` onto the `p_next` chains of the
+    /// relevant create info structs in `self`.
+    pub fn chain_extension_create_infos(
+        &mut self,
+        extension_create_infos: &'a mut GraphicsPipelineExtensionCreateInfosVk<'_>,
+    ) {
+        if let Some(line_rasterization_vk) = extension_create_infos.line_rasterization_vk.as_mut() {
+            self.rasterization_state_vk =
+                self.rasterization_state_vk.push_next(line_rasterization_vk);
+        }
+        if let Some(depth_clip_state_vk) = extension_create_infos.depth_clip_state_vk.as_mut() {
+            self.rasterization_state_vk =
+                self.rasterization_state_vk.push_next(depth_clip_state_vk);
+        }
+        if let Some(depth_bias_representation_vk) =
+            extension_create_infos.depth_bias_representation_vk.as_mut()
+        {
+            self.rasterization_state_vk = self
+                .rasterization_state_vk
+                .push_next(depth_bias_representation_vk);
+        }
+        if let Some(conservative_rasterization_vk) = extension_create_infos
+            .conservative_rasterization_vk
+            .as_mut()
+        {
+            self.rasterization_state_vk = self
+                .rasterization_state_vk
+                .push_next(conservative_rasterization_vk);
+        }
+        if let Some(provoking_vertex_vk) = extension_create_infos.provoking_vertex_vk.as_mut() {
+            self.rasterization_state_vk =
+                self.rasterization_state_vk.push_next(provoking_vertex_vk);
+        }
+        if let Some(depth_clip_control_vk) = extension_create_infos.depth_clip_control_vk.as_mut() {
+            self.viewport_state_vk = self.viewport_state_vk.push_next(depth_clip_control_vk);
+        }
+    }
+}
 impl<'a> Default for GraphicsPipelinePropertiesCreateInfosVk<'a> {
     fn default() -> Self {
         Self {
@@ -1000,3 +1881,184 @@ impl<'a> Default for GraphicsPipelinePropertiesCreateInfosVk<'a> {
         }
     }
 }
+
+/// Extension structs for the `p_next` chains of the create infos in
+/// `GraphicsPipelinePropertiesCreateInfosVk`. Populated by
+/// `GraphicsPipelineProperties::vk_extension_create_infos` and chained with
+/// `GraphicsPipelinePropertiesCreateInfosVk::chain_extension_create_infos`.
+#[derive(Default)]
+pub struct GraphicsPipelineExtensionCreateInfosVk<'a> {
+    pub line_rasterization_vk: Option<vk::PipelineRasterizationLineStateCreateInfoKHR<'a>>,
+    pub depth_clip_state_vk: Option<vk::PipelineRasterizationDepthClipStateCreateInfoEXT<'a>>,
+    pub depth_clip_control_vk: Option<vk::PipelineViewportDepthClipControlCreateInfoEXT<'a>>,
+    pub depth_bias_representation_vk: Option<vk::DepthBiasRepresentationInfoEXT<'a>>,
+    pub conservative_rasterization_vk:
+        Option<vk::PipelineRasterizationConservativeStateCreateInfoEXT<'a>>,
+    pub provoking_vertex_vk: Option<vk::PipelineRasterizationProvokingVertexStateCreateInfoEXT<'a>>,
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum RasterizationStateError {
+    LineWidthOutOfRange {
+        line_width: f32,
+        line_width_range: [f32; 2],
+    },
+    WideLinesNotSupported {
+        line_width: f32,
+    },
+    LineRasterizationNotSupported,
+    LineRasterizationModeNotSupported(vk::LineRasterizationModeEXT),
+    StippledLinesNotSupported(vk::LineRasterizationModeEXT),
+    InvalidLineStippleFactor(u32),
+    DepthClipEnableNotSupported,
+    DepthClipControlNotSupported,
+    DepthBiasControlNotSupported,
+    DepthBiasRepresentationNotSupported(vk::DepthBiasRepresentationEXT),
+    DepthBiasExactNotSupported,
+    ConservativeRasterizationNotSupported,
+    PrimitiveUnderestimationNotSupported,
+    ExtraPrimitiveOverestimationSizeOutOfRange {
+        extra_primitive_overestimation_size: f32,
+        max_extra_primitive_overestimation_size: f32,
+    },
+    /// Polygon modes other than `FILL` require the `fillModeNonSolid` feature.
+    PolygonModeNotSupported(vk::PolygonMode),
+    ProvokingVertexNotSupported,
+    ProvokingVertexLastNotSupported,
+}
+
+impl fmt::Display for RasterizationStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LineWidthOutOfRange {
+                line_width,
+                line_width_range,
+            } => write!(
+                f,
+                "line width {} is outside of the supported range {:?}",
+                line_width, line_width_range
+            ),
+            Self::WideLinesNotSupported { line_width } => write!(
+                f,
+                "line width {} requested but the wideLines feature isn't supported",
+                line_width
+            ),
+            Self::LineRasterizationNotSupported => write!(
+                f,
+                "line rasterization state requested but VK_EXT_line_rasterization isn't supported"
+            ),
+            Self::LineRasterizationModeNotSupported(mode) => {
+                write!(f, "line rasterization mode {:?} isn't supported", mode)
+            }
+            Self::StippledLinesNotSupported(mode) => write!(
+                f,
+                "stippled lines aren't supported for line rasterization mode {:?}",
+                mode
+            ),
+            Self::InvalidLineStippleFactor(factor) => write!(
+                f,
+                "line stipple factor {} must be in the range [1, 256]",
+                factor
+            ),
+            Self::DepthClipEnableNotSupported => write!(
+                f,
+                "depth clip enable state requested but VK_EXT_depth_clip_enable or the depthClipEnable feature isn't supported"
+            ),
+            Self::DepthClipControlNotSupported => write!(
+                f,
+                "depth clip control state requested but VK_EXT_depth_clip_control or the depthClipControl feature isn't supported"
+            ),
+            Self::DepthBiasControlNotSupported => write!(
+                f,
+                "depth bias representation requested but VK_EXT_depth_bias_control or the depthBiasControl feature isn't supported"
+            ),
+            Self::DepthBiasRepresentationNotSupported(representation) => write!(
+                f,
+                "depth bias representation {:?} isn't supported",
+                representation
+            ),
+            Self::DepthBiasExactNotSupported => {
+                write!(f, "exact depth bias requested but depthBiasExact isn't supported")
+            }
+            Self::ConservativeRasterizationNotSupported => write!(
+                f,
+                "conservative rasterization requested but VK_EXT_conservative_rasterization isn't supported"
+            ),
+            Self::PrimitiveUnderestimationNotSupported => write!(
+                f,
+                "underestimate conservative rasterization mode requested but primitiveUnderestimation isn't supported"
+            ),
+            Self::ExtraPrimitiveOverestimationSizeOutOfRange {
+                extra_primitive_overestimation_size,
+                max_extra_primitive_overestimation_size,
+            } => write!(
+                f,
+                "extra primitive overestimation size {} is outside of the supported range [0, {}]",
+                extra_primitive_overestimation_size, max_extra_primitive_overestimation_size
+            ),
+            Self::PolygonModeNotSupported(polygon_mode) => write!(
+                f,
+                "polygon mode {:?} requested but the fillModeNonSolid feature isn't supported",
+                polygon_mode
+            ),
+            Self::ProvokingVertexNotSupported => write!(
+                f,
+                "provoking vertex mode requested but VK_EXT_provoking_vertex isn't supported"
+            ),
+            Self::ProvokingVertexLastNotSupported => write!(
+                f,
+                "last vertex provoking vertex mode requested but provokingVertexLast isn't supported"
+            ),
+        }
+    }
+}
+
+impl error::Error for RasterizationStateError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn first_missing_feature_checks_tessellation() {
+    let properties = GraphicsPipelineProperties::default();
+    let mut enabled_features = vk::PhysicalDeviceFeatures::default();
+    let tessellation_stages =
+        vk::ShaderStageFlags::TESSELLATION_CONTROL | vk::ShaderStageFlags::VERTEX;
+    assert_eq!(
+        first_missing_feature(&enabled_features, &properties, tessellation_stages),
+        Some("tessellationShader")
+    );
+
+    enabled_features.tessellation_shader = vk::TRUE;
+    assert_eq!(
+        first_missing_feature(&enabled_features, &properties, tessellation_stages),
+        None
+    );
+}
+
+#[test]
+fn wireframe_preset_and_provoking_vertex_checks() {
+    let wireframe = RasterizationState::new_wireframe()
+        .with_provoking_vertex_mode(vk::ProvokingVertexModeEXT::LAST_VERTEX);
+    assert!(check_polygon_mode(wireframe.polygon_mode, vk::FALSE).is_err());
+    assert!(check_polygon_mode(wireframe.polygon_mode, vk::TRUE).is_ok());
+    assert!(check_polygon_mode(vk::PolygonMode::FILL, vk::FALSE).is_ok());
+
+    let mut provoking_vertex_features = vk::PhysicalDeviceProvokingVertexFeaturesEXT::default();
+    let provoking_vertex_mode = wireframe.provoking_vertex_mode.unwrap();
+    assert!(
+        check_provoking_vertex_mode(provoking_vertex_mode, &provoking_vertex_features).is_err()
+    );
+    provoking_vertex_features.provoking_vertex_last = vk::TRUE;
+    assert!(check_provoking_vertex_mode(provoking_vertex_mode, &provoking_vertex_features).is_ok());
+
+    let properties = GraphicsPipelineProperties {
+        rasterization_state: wireframe,
+        ..Default::default()
+    };
+    assert!(properties
+        .vk_extension_create_infos()
+        .provoking_vertex_vk
+        .is_some());
+}
@@ -102,4 +102,25 @@ impl PipelineLayoutProperties {
             .map(|layout| layout.handle())
             .collect()
     }
+
+    /// Note: leaves `set_layouts` empty because the create info only provides handles.
+    pub fn from_create_info(value: &vk::PipelineLayoutCreateInfo) -> Self {
+        let mut push_constant_ranges = Vec::<vk::PushConstantRange>::new();
+        for i in 0..value.push_constant_range_count {
+            let push_constant_range = unsafe { *value.p_push_constant_ranges.offset(i as isize) };
+            push_constant_ranges.push(push_constant_range);
+        }
+
+        Self {
+            flags: value.flags,
+            set_layouts: Vec::new(), // because the create info only provides handles
+            push_constant_ranges,
+        }
+    }
+}
+
+impl From<&vk::PipelineLayoutCreateInfo<'_>> for PipelineLayoutProperties {
+    fn from(value: &vk::PipelineLayoutCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
 }
@@ -0,0 +1,539 @@
+//! Checks the resources a pipeline uses against physical device limits before the pipeline (or
+//! its layout/descriptor pools) is created. Exceeding e.g. `maxPerStageDescriptorSamplers` is
+//! undefined behaviour which, depending on the driver, shows up as a crash or a generic
+//! `VK_ERROR_OUT_OF_POOL_MEMORY` much later on.
+
+use crate::PipelineLayoutProperties;
+use ash::vk;
+use std::fmt;
+
+/// Resource usage of a pipeline, usually gathered from shader reflection. Can also be built from
+/// a pipeline layout with [`Self::from_pipeline_layout`].
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReflection {
+    pub descriptor_bindings: Vec<ReflectedDescriptorBinding>,
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    /// Number of vertex input attributes (locations) consumed by the vertex shader.
+    pub vertex_input_attribute_count: u32,
+    /// Empty for compute pipelines.
+    pub rasterization_samples: vk::SampleCountFlags,
+    pub color_attachment_samples: Vec<vk::SampleCountFlags>,
+    pub depth_stencil_attachment_samples: Option<vk::SampleCountFlags>,
+}
+
+impl PipelineReflection {
+    /// The descriptor bindings and push constant ranges of `layout_properties`.
+    pub fn from_pipeline_layout(layout_properties: &PipelineLayoutProperties) -> Self {
+        let descriptor_bindings = layout_properties
+            .set_layouts
+            .iter()
+            .enumerate()
+            .flat_map(|(set, set_layout)| {
+                set_layout.properties().bindings.iter().map(move |binding| {
+                    ReflectedDescriptorBinding {
+                        set: set as u32,
+                        binding: binding.binding,
+                        descriptor_type: binding.descriptor_type,
+                        descriptor_count: binding.descriptor_count,
+                        stage_flags: binding.stage_flags,
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            descriptor_bindings,
+            push_constant_ranges: layout_properties.push_constant_ranges.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReflectedDescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub descriptor_count: u32,
+    pub stage_flags: vk::ShaderStageFlags,
+}
+
+/// Per-stage descriptor limits from `vk::PhysicalDeviceLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerStageDescriptorLimit {
+    Samplers,
+    UniformBuffers,
+    StorageBuffers,
+    SampledImages,
+    StorageImages,
+    InputAttachments,
+    Resources,
+}
+
+impl PerStageDescriptorLimit {
+    const ALL: [Self; 7] = [
+        Self::Samplers,
+        Self::UniformBuffers,
+        Self::StorageBuffers,
+        Self::SampledImages,
+        Self::StorageImages,
+        Self::InputAttachments,
+        Self::Resources,
+    ];
+
+    pub fn limit(self, limits: &vk::PhysicalDeviceLimits) -> u32 {
+        match self {
+            Self::Samplers => limits.max_per_stage_descriptor_samplers,
+            Self::UniformBuffers => limits.max_per_stage_descriptor_uniform_buffers,
+            Self::StorageBuffers => limits.max_per_stage_descriptor_storage_buffers,
+            Self::SampledImages => limits.max_per_stage_descriptor_sampled_images,
+            Self::StorageImages => limits.max_per_stage_descriptor_storage_images,
+            Self::InputAttachments => limits.max_per_stage_descriptor_input_attachments,
+            Self::Resources => limits.max_per_stage_resources,
+        }
+    }
+
+    /// Whether descriptors of `descriptor_type` count towards this limit.
+    pub fn counts(self, descriptor_type: vk::DescriptorType) -> bool {
+        use vk::DescriptorType as Ty;
+        match self {
+            Self::Samplers => matches!(descriptor_type, Ty::SAMPLER | Ty::COMBINED_IMAGE_SAMPLER),
+            Self::UniformBuffers => matches!(
+                descriptor_type,
+                Ty::UNIFORM_BUFFER | Ty::UNIFORM_BUFFER_DYNAMIC
+            ),
+            Self::StorageBuffers => matches!(
+                descriptor_type,
+                Ty::STORAGE_BUFFER | Ty::STORAGE_BUFFER_DYNAMIC
+            ),
+            Self::SampledImages => matches!(
+                descriptor_type,
+                Ty::SAMPLED_IMAGE | Ty::COMBINED_IMAGE_SAMPLER | Ty::UNIFORM_TEXEL_BUFFER
+            ),
+            Self::StorageImages => matches!(
+                descriptor_type,
+                Ty::STORAGE_IMAGE | Ty::STORAGE_TEXEL_BUFFER
+            ),
+            Self::InputAttachments => descriptor_type == Ty::INPUT_ATTACHMENT,
+            Self::Resources => [
+                Self::UniformBuffers,
+                Self::StorageBuffers,
+                Self::SampledImages,
+                Self::StorageImages,
+                Self::InputAttachments,
+            ]
+            .iter()
+            .any(|limit| limit.counts(descriptor_type)),
+        }
+    }
+
+    fn limit_name(self) -> &'static str {
+        match self {
+            Self::Samplers => "maxPerStageDescriptorSamplers",
+            Self::UniformBuffers => "maxPerStageDescriptorUniformBuffers",
+            Self::StorageBuffers => "maxPerStageDescriptorStorageBuffers",
+            Self::SampledImages => "maxPerStageDescriptorSampledImages",
+            Self::StorageImages => "maxPerStageDescriptorStorageImages",
+            Self::InputAttachments => "maxPerStageDescriptorInputAttachments",
+            Self::Resources => "maxPerStageResources",
+        }
+    }
+}
+
+/// Checks `reflection` against the limits in `properties` and returns a warning for each limit
+/// that would be exceeded. An empty vec means nothing obviously wrong was found.
+pub fn lint_pipeline(
+    properties: &vk::PhysicalDeviceProperties,
+    reflection: &PipelineReflection,
+) -> Vec<PipelineLintWarning> {
+    let limits = &properties.limits;
+    let mut warnings = Vec::new();
+
+    lint_descriptor_sets(limits, reflection, &mut warnings);
+    lint_push_constants(limits, reflection, &mut warnings);
+
+    if reflection.vertex_input_attribute_count > limits.max_vertex_input_attributes {
+        warnings.push(PipelineLintWarning::TooManyVertexAttributes {
+            count: reflection.vertex_input_attribute_count,
+            limit: limits.max_vertex_input_attributes,
+        });
+    }
+
+    lint_sample_counts(limits, reflection, &mut warnings);
+
+    warnings
+}
+
+// Helper Functions
+
+fn lint_descriptor_sets(
+    limits: &vk::PhysicalDeviceLimits,
+    reflection: &PipelineReflection,
+    warnings: &mut Vec<PipelineLintWarning>,
+) {
+    if let Some(max_set) = reflection
+        .descriptor_bindings
+        .iter()
+        .map(|binding| binding.set)
+        .max()
+    {
+        if max_set >= limits.max_bound_descriptor_sets {
+            warnings.push(PipelineLintWarning::TooManyDescriptorSets {
+                set: max_set,
+                limit: limits.max_bound_descriptor_sets,
+            });
+        }
+    }
+
+    let used_stages = reflection
+        .descriptor_bindings
+        .iter()
+        .fold(vk::ShaderStageFlags::empty(), |stages, binding| {
+            stages | binding.stage_flags
+        });
+    for stage_bit in 0..u32::BITS {
+        let stage = vk::ShaderStageFlags::from_raw(1 << stage_bit);
+        if !used_stages.contains(stage) {
+            continue;
+        }
+        for per_stage_limit in PerStageDescriptorLimit::ALL {
+            let count: u32 = reflection
+                .descriptor_bindings
+                .iter()
+                .filter(|binding| {
+                    binding.stage_flags.contains(stage)
+                        && per_stage_limit.counts(binding.descriptor_type)
+                })
+                .map(|binding| binding.descriptor_count)
+                .sum();
+            let limit = per_stage_limit.limit(limits);
+            if count > limit {
+                warnings.push(PipelineLintWarning::PerStageDescriptorLimit {
+                    stage,
+                    kind: per_stage_limit,
+                    count,
+                    limit,
+                });
+            }
+        }
+    }
+
+    let dynamic_buffer_limits = [
+        (
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+            limits.max_descriptor_set_uniform_buffers_dynamic,
+        ),
+        (
+            vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+            limits.max_descriptor_set_storage_buffers_dynamic,
+        ),
+    ];
+    for (descriptor_type, limit) in dynamic_buffer_limits {
+        let count: u32 = reflection
+            .descriptor_bindings
+            .iter()
+            .filter(|binding| binding.descriptor_type == descriptor_type)
+            .map(|binding| binding.descriptor_count)
+            .sum();
+        if count > limit {
+            warnings.push(PipelineLintWarning::TooManyDynamicBuffers {
+                descriptor_type,
+                count,
+                limit,
+            });
+        }
+    }
+}
+
+fn lint_push_constants(
+    limits: &vk::PhysicalDeviceLimits,
+    reflection: &PipelineReflection,
+    warnings: &mut Vec<PipelineLintWarning>,
+) {
+    for range in &reflection.push_constant_ranges {
+        let end = range.offset as u64 + range.size as u64;
+        if end > limits.max_push_constants_size as u64 {
+            warnings.push(PipelineLintWarning::PushConstantsTooLarge {
+                stage_flags: range.stage_flags,
+                end: end as u32,
+                limit: limits.max_push_constants_size,
+            });
+        }
+    }
+}
+
+fn lint_sample_counts(
+    limits: &vk::PhysicalDeviceLimits,
+    reflection: &PipelineReflection,
+    warnings: &mut Vec<PipelineLintWarning>,
+) {
+    let rasterization_samples = reflection.rasterization_samples;
+    if rasterization_samples.is_empty() {
+        return;
+    }
+
+    for (index, &samples) in reflection.color_attachment_samples.iter().enumerate() {
+        if samples != rasterization_samples {
+            warnings.push(PipelineLintWarning::AttachmentSampleCountMismatch {
+                attachment: AttachmentSlot::Color(index as u32),
+                samples,
+                rasterization_samples,
+            });
+        }
+        if !limits.framebuffer_color_sample_counts.contains(samples) {
+            warnings.push(PipelineLintWarning::UnsupportedSampleCount {
+                attachment: AttachmentSlot::Color(index as u32),
+                samples,
+                supported: limits.framebuffer_color_sample_counts,
+            });
+        }
+    }
+
+    if let Some(samples) = reflection.depth_stencil_attachment_samples {
+        if samples != rasterization_samples {
+            warnings.push(PipelineLintWarning::AttachmentSampleCountMismatch {
+                attachment: AttachmentSlot::DepthStencil,
+                samples,
+                rasterization_samples,
+            });
+        }
+        let supported =
+            limits.framebuffer_depth_sample_counts & limits.framebuffer_stencil_sample_counts;
+        if !supported.contains(samples) {
+            warnings.push(PipelineLintWarning::UnsupportedSampleCount {
+                attachment: AttachmentSlot::DepthStencil,
+                samples,
+                supported,
+            });
+        }
+    }
+
+    let no_attachments = reflection.color_attachment_samples.is_empty()
+        && reflection.depth_stencil_attachment_samples.is_none();
+    if no_attachments
+        && !limits
+            .framebuffer_no_attachments_sample_counts
+            .contains(rasterization_samples)
+    {
+        warnings.push(PipelineLintWarning::UnsupportedSampleCount {
+            attachment: AttachmentSlot::None,
+            samples: rasterization_samples,
+            supported: limits.framebuffer_no_attachments_sample_counts,
+        });
+    }
+}
+
+// ~~ Warnings ~~
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentSlot {
+    Color(u32),
+    DepthStencil,
+    /// Rendering without any attachments.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineLintWarning {
+    PerStageDescriptorLimit {
+        stage: vk::ShaderStageFlags,
+        kind: PerStageDescriptorLimit,
+        count: u32,
+        limit: u32,
+    },
+    /// `set` is the highest set index used.
+    TooManyDescriptorSets {
+        set: u32,
+        limit: u32,
+    },
+    TooManyDynamicBuffers {
+        descriptor_type: vk::DescriptorType,
+        count: u32,
+        limit: u32,
+    },
+    /// `end` is the offset + size of the push constant range.
+    PushConstantsTooLarge {
+        stage_flags: vk::ShaderStageFlags,
+        end: u32,
+        limit: u32,
+    },
+    TooManyVertexAttributes {
+        count: u32,
+        limit: u32,
+    },
+    AttachmentSampleCountMismatch {
+        attachment: AttachmentSlot,
+        samples: vk::SampleCountFlags,
+        rasterization_samples: vk::SampleCountFlags,
+    },
+    UnsupportedSampleCount {
+        attachment: AttachmentSlot,
+        samples: vk::SampleCountFlags,
+        supported: vk::SampleCountFlags,
+    },
+}
+
+impl fmt::Display for PipelineLintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PerStageDescriptorLimit {
+                stage,
+                kind,
+                count,
+                limit,
+            } => write!(
+                f,
+                "{:?} stage uses {} descriptors counting towards {} but the device limit is {}. \
+                try merging bindings into arrays/bindless descriptors or splitting the work across pipelines",
+                stage,
+                count,
+                kind.limit_name(),
+                limit
+            ),
+            Self::TooManyDescriptorSets { set, limit } => write!(
+                f,
+                "descriptor set index {} is used but maxBoundDescriptorSets is {}. \
+                try merging descriptor sets",
+                set, limit
+            ),
+            Self::TooManyDynamicBuffers {
+                descriptor_type,
+                count,
+                limit,
+            } => write!(
+                f,
+                "pipeline uses {} {:?} descriptors but the device limit is {}. \
+                try using regular buffers with offsets passed via push constants",
+                count, descriptor_type, limit
+            ),
+            Self::PushConstantsTooLarge {
+                stage_flags,
+                end,
+                limit,
+            } => write!(
+                f,
+                "push constant range for {:?} ends at byte {} but maxPushConstantsSize is {}. \
+                try moving the larger members to a uniform buffer",
+                stage_flags, end, limit
+            ),
+            Self::TooManyVertexAttributes { count, limit } => write!(
+                f,
+                "vertex shader uses {} input attributes but maxVertexInputAttributes is {}. \
+                try packing attributes or pulling vertex data from a storage buffer",
+                count, limit
+            ),
+            Self::AttachmentSampleCountMismatch {
+                attachment,
+                samples,
+                rasterization_samples,
+            } => write!(
+                f,
+                "{:?} attachment has {:?} samples but the pipeline rasterization samples is {:?}",
+                attachment, samples, rasterization_samples
+            ),
+            Self::UnsupportedSampleCount {
+                attachment,
+                samples,
+                supported,
+            } => write!(
+                f,
+                "{:?} samples aren't supported for {:?} attachment (supported: {:?})",
+                samples, attachment, supported
+            ),
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn lint_pipeline_reports_exceeded_limits() {
+    let mut properties = vk::PhysicalDeviceProperties::default();
+    properties.limits.max_per_stage_descriptor_samplers = 16;
+    properties.limits.max_per_stage_descriptor_sampled_images = 16;
+    properties.limits.max_per_stage_resources = 128;
+    properties.limits.max_bound_descriptor_sets = 4;
+    properties.limits.max_push_constants_size = 128;
+    properties.limits.max_vertex_input_attributes = 16;
+    properties.limits.framebuffer_color_sample_counts =
+        vk::SampleCountFlags::TYPE_1 | vk::SampleCountFlags::TYPE_4;
+
+    let reflection = PipelineReflection {
+        descriptor_bindings: vec![ReflectedDescriptorBinding {
+            set: 0,
+            binding: 0,
+            descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 32,
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+        }],
+        push_constant_ranges: vec![vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 64,
+            size: 128,
+        }],
+        vertex_input_attribute_count: 4,
+        rasterization_samples: vk::SampleCountFlags::TYPE_4,
+        color_attachment_samples: vec![vk::SampleCountFlags::TYPE_4, vk::SampleCountFlags::TYPE_1],
+        depth_stencil_attachment_samples: None,
+    };
+
+    let warnings = lint_pipeline(&properties, &reflection);
+    assert!(
+        warnings.contains(&PipelineLintWarning::PerStageDescriptorLimit {
+            stage: vk::ShaderStageFlags::FRAGMENT,
+            kind: PerStageDescriptorLimit::Samplers,
+            count: 32,
+            limit: 16,
+        })
+    );
+    assert!(
+        warnings.contains(&PipelineLintWarning::PerStageDescriptorLimit {
+            stage: vk::ShaderStageFlags::FRAGMENT,
+            kind: PerStageDescriptorLimit::SampledImages,
+            count: 32,
+            limit: 16,
+        })
+    );
+    assert!(
+        warnings.contains(&PipelineLintWarning::PushConstantsTooLarge {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            end: 192,
+            limit: 128,
+        })
+    );
+    assert!(
+        warnings.contains(&PipelineLintWarning::AttachmentSampleCountMismatch {
+            attachment: AttachmentSlot::Color(1),
+            samples: vk::SampleCountFlags::TYPE_1,
+            rasterization_samples: vk::SampleCountFlags::TYPE_4,
+        })
+    );
+    assert_eq!(warnings.len(), 4);
+}
+
+#[test]
+fn lint_pipeline_accepts_pipeline_within_limits() {
+    let mut properties = vk::PhysicalDeviceProperties::default();
+    properties.limits.max_per_stage_descriptor_uniform_buffers = 12;
+    properties.limits.max_per_stage_resources = 128;
+    properties.limits.max_bound_descriptor_sets = 4;
+    properties.limits.max_push_constants_size = 128;
+
+    let reflection = PipelineReflection {
+        descriptor_bindings: vec![ReflectedDescriptorBinding {
+            set: 3,
+            binding: 0,
+            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_count: 12,
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+        }],
+        push_constant_ranges: vec![vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: 128,
+        }],
+        ..Default::default()
+    };
+
+    assert!(lint_pipeline(&properties, &reflection).is_empty());
+}
@@ -0,0 +1,89 @@
+use ash::vk;
+
+/// Per-pipeline robustness behavior. Requires `VK_EXT_pipeline_robustness` to be enabled on the
+/// device along with the `pipelineRobustness` feature (see
+/// [`PhysicalDeviceFeatures::with_pipeline_robustness`](crate::PhysicalDeviceFeatures::with_pipeline_robustness)).
+///
+/// Out-of-bounds behavior for each setting:
+/// - `DISABLED`: out-of-bounds accesses are undefined behavior.
+/// - `ROBUST_BUFFER_ACCESS`/`ROBUST_IMAGE_ACCESS`: accesses can't escape the bound resource but
+///   return implementation dependent values.
+/// - `ROBUST_BUFFER_ACCESS_2`/`ROBUST_IMAGE_ACCESS_2`: out-of-bounds reads return zero and writes
+///   are discarded.
+/// - `DEVICE_DEFAULT`: whatever the device was created with. Query these defaults with
+///   [`Instance::physical_device_pipeline_robustness_properties`](crate::Instance::physical_device_pipeline_robustness_properties).
+///
+/// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/VkPipelineRobustnessCreateInfoEXT.html>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineRobustness {
+    pub storage_buffers: vk::PipelineRobustnessBufferBehaviorEXT,
+    pub uniform_buffers: vk::PipelineRobustnessBufferBehaviorEXT,
+    pub vertex_inputs: vk::PipelineRobustnessBufferBehaviorEXT,
+    pub images: vk::PipelineRobustnessImageBehaviorEXT,
+}
+
+impl Default for PipelineRobustness {
+    fn default() -> Self {
+        Self {
+            storage_buffers: vk::PipelineRobustnessBufferBehaviorEXT::DEVICE_DEFAULT,
+            uniform_buffers: vk::PipelineRobustnessBufferBehaviorEXT::DEVICE_DEFAULT,
+            vertex_inputs: vk::PipelineRobustnessBufferBehaviorEXT::DEVICE_DEFAULT,
+            images: vk::PipelineRobustnessImageBehaviorEXT::DEVICE_DEFAULT,
+        }
+    }
+}
+
+impl PipelineRobustness {
+    /// Out-of-bounds reads return zero and writes are discarded for all resources. Requires the
+    /// `VK_EXT_robustness2` `robustBufferAccess2` and `robustImageAccess2` features to be
+    /// supported.
+    pub fn new_robust_2() -> Self {
+        Self {
+            storage_buffers: vk::PipelineRobustnessBufferBehaviorEXT::ROBUST_BUFFER_ACCESS_2,
+            uniform_buffers: vk::PipelineRobustnessBufferBehaviorEXT::ROBUST_BUFFER_ACCESS_2,
+            vertex_inputs: vk::PipelineRobustnessBufferBehaviorEXT::ROBUST_BUFFER_ACCESS_2,
+            images: vk::PipelineRobustnessImageBehaviorEXT::ROBUST_IMAGE_ACCESS_2,
+        }
+    }
+
+    /// Disables robustness for all resources, even if the device was created with robustness
+    /// features enabled. Handy for performance critical pipelines with trusted inputs.
+    pub fn new_disabled() -> Self {
+        Self {
+            storage_buffers: vk::PipelineRobustnessBufferBehaviorEXT::DISABLED,
+            uniform_buffers: vk::PipelineRobustnessBufferBehaviorEXT::DISABLED,
+            vertex_inputs: vk::PipelineRobustnessBufferBehaviorEXT::DISABLED,
+            images: vk::PipelineRobustnessImageBehaviorEXT::DISABLED,
+        }
+    }
+
+    pub fn write_create_info<'a>(
+        &self,
+        create_info: vk::PipelineRobustnessCreateInfoEXT<'a>,
+    ) -> vk::PipelineRobustnessCreateInfoEXT<'a> {
+        create_info
+            .storage_buffers(self.storage_buffers)
+            .uniform_buffers(self.uniform_buffers)
+            .vertex_inputs(self.vertex_inputs)
+            .images(self.images)
+    }
+
+    pub fn create_info(&self) -> vk::PipelineRobustnessCreateInfoEXT<'static> {
+        self.write_create_info(vk::PipelineRobustnessCreateInfoEXT::default())
+    }
+
+    pub fn from_create_info(value: &vk::PipelineRobustnessCreateInfoEXT) -> Self {
+        Self {
+            storage_buffers: value.storage_buffers,
+            uniform_buffers: value.uniform_buffers,
+            vertex_inputs: value.vertex_inputs,
+            images: value.images,
+        }
+    }
+}
+
+impl From<&vk::PipelineRobustnessCreateInfoEXT<'_>> for PipelineRobustness {
+    fn from(value: &vk::PipelineRobustnessCreateInfoEXT<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
@@ -0,0 +1,39 @@
+//! The most commonly used types and traits, for a glob import:
+//! ```
+//! use bort_vk::prelude::*;
+//! ```
+//! Everything else is still available from the crate root.
+
+// traits
+pub use crate::{
+    AllocationAccess, AllocatorAccess, DeviceOwned, ImageAccess, ImageViewAccess, PipelineAccess,
+};
+
+// instance and device
+pub use crate::{ApiVersion, Device, Instance, PhysicalDevice, Queue};
+
+// presentation
+#[cfg(feature = "presentation")]
+pub use crate::{Surface, Swapchain, SwapchainImage, SwapchainProperties};
+
+// memory and resources
+pub use crate::{
+    Buffer, BufferProperties, Image, ImageDimensions, ImageProperties, ImageView,
+    ImageViewProperties, MemoryAllocator, Sampler, SamplerProperties,
+};
+
+// commands and sync
+pub use crate::{CommandBuffer, CommandPool, CommandPoolProperties, Fence, Semaphore};
+
+// descriptors
+pub use crate::{
+    DescriptorPool, DescriptorPoolProperties, DescriptorSet, DescriptorSetLayout,
+    DescriptorSetLayoutProperties,
+};
+
+// pipelines and render passes
+pub use crate::{
+    ComputePipeline, ComputePipelineProperties, Framebuffer, FramebufferProperties,
+    GraphicsPipeline, GraphicsPipelineProperties, PipelineLayout, PipelineLayoutProperties,
+    RenderPass, ShaderModule, ShaderStage, Subpass,
+};
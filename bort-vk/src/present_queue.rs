@@ -0,0 +1,244 @@
+use crate::{CommandBuffer, PhysicalDevice, Queue, Surface, SwapchainProperties};
+use ash::vk;
+
+/// How swapchain images are shared between the queue family rendering to them and the queue
+/// family presenting them. These families differ on some systems, notably hybrid iGPU/dGPU
+/// laptops where the discrete GPU's graphics family can't present directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentQueueSharing {
+    /// Rendering and presentation use the same queue family so nothing extra is needed.
+    SameFamily,
+    /// Swapchain images are created with `vk::SharingMode::CONCURRENT` between both families.
+    /// Simplest option and usually free on the systems where this comes up.
+    Concurrent {
+        graphics_family_index: u32,
+        present_family_index: u32,
+    },
+    /// Swapchain images are `vk::SharingMode::EXCLUSIVE` and ownership is transferred to the
+    /// present family each frame with [`Self::record_release`] on the graphics queue and
+    /// [`Self::record_acquire`] on the present queue.
+    OwnershipTransfer {
+        graphics_family_index: u32,
+        present_family_index: u32,
+    },
+}
+
+impl PresentQueueSharing {
+    /// Concurrent sharing if the queues belong to different families.
+    pub fn new(graphics_queue: &Queue, present_queue: &Queue) -> Self {
+        Self::from_family_indices(
+            graphics_queue.family_index(),
+            present_queue.family_index(),
+            true,
+        )
+    }
+
+    /// Exclusive sharing with queue family ownership transfers if the queues belong to different
+    /// families.
+    pub fn new_ownership_transfer(graphics_queue: &Queue, present_queue: &Queue) -> Self {
+        Self::from_family_indices(
+            graphics_queue.family_index(),
+            present_queue.family_index(),
+            false,
+        )
+    }
+
+    pub fn from_family_indices(
+        graphics_family_index: u32,
+        present_family_index: u32,
+        prefer_concurrent: bool,
+    ) -> Self {
+        if graphics_family_index == present_family_index {
+            Self::SameFamily
+        } else if prefer_concurrent {
+            Self::Concurrent {
+                graphics_family_index,
+                present_family_index,
+            }
+        } else {
+            Self::OwnershipTransfer {
+                graphics_family_index,
+                present_family_index,
+            }
+        }
+    }
+
+    /// Sets the sharing mode and queue family indices of `swapchain_properties`. These are
+    /// retained when the swapchain is recreated.
+    pub fn apply(&self, swapchain_properties: &mut SwapchainProperties) {
+        match *self {
+            Self::Concurrent {
+                graphics_family_index,
+                present_family_index,
+            } => {
+                swapchain_properties.sharing_mode = vk::SharingMode::CONCURRENT;
+                swapchain_properties.queue_family_indices =
+                    vec![graphics_family_index, present_family_index];
+            }
+            Self::SameFamily | Self::OwnershipTransfer { .. } => {
+                swapchain_properties.sharing_mode = vk::SharingMode::EXCLUSIVE;
+                swapchain_properties.queue_family_indices = Vec::new();
+            }
+        }
+    }
+
+    #[inline]
+    pub fn requires_ownership_transfer(&self) -> bool {
+        matches!(self, Self::OwnershipTransfer { .. })
+    }
+
+    /// Barrier releasing `swapchain_image` from the graphics family and transitioning it from
+    /// `old_layout` to `PRESENT_SRC_KHR`. `None` unless ownership transfers are required.
+    pub fn release_barrier(
+        &self,
+        swapchain_image: vk::Image,
+        old_layout: vk::ImageLayout,
+    ) -> Option<vk::ImageMemoryBarrier<'static>> {
+        let barrier = self.ownership_transfer_barrier(swapchain_image, old_layout)?;
+        Some(barrier.src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE))
+    }
+
+    /// Barrier acquiring `swapchain_image` on the present family. `old_layout` must match the one
+    /// passed to [`Self::release_barrier`]. `None` unless ownership transfers are required.
+    pub fn acquire_barrier(
+        &self,
+        swapchain_image: vk::Image,
+        old_layout: vk::ImageLayout,
+    ) -> Option<vk::ImageMemoryBarrier<'static>> {
+        self.ownership_transfer_barrier(swapchain_image, old_layout)
+    }
+
+    /// Records [`Self::release_barrier`] into a command buffer submitted to the graphics queue
+    /// after rendering to `swapchain_image`. Does nothing unless ownership transfers are required.
+    pub fn record_release(
+        &self,
+        command_buffer: &CommandBuffer,
+        swapchain_image: vk::Image,
+        old_layout: vk::ImageLayout,
+    ) {
+        if let Some(barrier) = self.release_barrier(swapchain_image, old_layout) {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+        }
+    }
+
+    /// Records [`Self::acquire_barrier`] into a command buffer submitted to the present queue,
+    /// which should wait on a semaphore signalled by the graphics submission and signal the
+    /// semaphore waited on by `vkQueuePresentKHR`. Does nothing unless ownership transfers are
+    /// required.
+    pub fn record_acquire(
+        &self,
+        command_buffer: &CommandBuffer,
+        swapchain_image: vk::Image,
+        old_layout: vk::ImageLayout,
+    ) {
+        if let Some(barrier) = self.acquire_barrier(swapchain_image, old_layout) {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+        }
+    }
+
+    fn ownership_transfer_barrier(
+        &self,
+        swapchain_image: vk::Image,
+        old_layout: vk::ImageLayout,
+    ) -> Option<vk::ImageMemoryBarrier<'static>> {
+        let Self::OwnershipTransfer {
+            graphics_family_index,
+            present_family_index,
+        } = *self
+        else {
+            return None;
+        };
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: vk::REMAINING_ARRAY_LAYERS,
+        };
+        Some(
+            vk::ImageMemoryBarrier::default()
+                .old_layout(old_layout)
+                .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .src_queue_family_index(graphics_family_index)
+                .dst_queue_family_index(present_family_index)
+                .image(swapchain_image)
+                .subresource_range(subresource_range),
+        )
+    }
+}
+
+// Helper Functions
+
+/// Index of a queue family which can present to `surface`, preferring `graphics_family_index` so
+/// that [`PresentQueueSharing::SameFamily`] can be used where possible.
+pub fn present_queue_family_index(
+    physical_device: &PhysicalDevice,
+    surface: &Surface,
+    graphics_family_index: u32,
+) -> Option<u32> {
+    let supports_present = |queue_family_index: u32| {
+        surface
+            .get_physical_device_surface_support(physical_device, queue_family_index)
+            .unwrap_or(false)
+    };
+
+    if supports_present(graphics_family_index) {
+        return Some(graphics_family_index);
+    }
+    (0..physical_device.queue_family_properties().len() as u32).find(|&i| supports_present(i))
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn present_queue_sharing_configuration() {
+    let mut swapchain_properties = SwapchainProperties::default();
+
+    let same_family = PresentQueueSharing::from_family_indices(0, 0, true);
+    assert_eq!(same_family, PresentQueueSharing::SameFamily);
+    same_family.apply(&mut swapchain_properties);
+    assert_eq!(
+        swapchain_properties.sharing_mode,
+        vk::SharingMode::EXCLUSIVE
+    );
+
+    let concurrent = PresentQueueSharing::from_family_indices(0, 2, true);
+    concurrent.apply(&mut swapchain_properties);
+    assert_eq!(
+        swapchain_properties.sharing_mode,
+        vk::SharingMode::CONCURRENT
+    );
+    assert_eq!(swapchain_properties.queue_family_indices, vec![0, 2]);
+    assert!(concurrent
+        .release_barrier(vk::Image::null(), vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .is_none());
+
+    let ownership_transfer = PresentQueueSharing::from_family_indices(0, 2, false);
+    ownership_transfer.apply(&mut swapchain_properties);
+    assert_eq!(
+        swapchain_properties.sharing_mode,
+        vk::SharingMode::EXCLUSIVE
+    );
+    assert!(swapchain_properties.queue_family_indices.is_empty());
+    let release = ownership_transfer
+        .release_barrier(vk::Image::null(), vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .unwrap();
+    assert_eq!(release.src_queue_family_index, 0);
+    assert_eq!(release.dst_queue_family_index, 2);
+    assert_eq!(release.new_layout, vk::ImageLayout::PRESENT_SRC_KHR);
+}
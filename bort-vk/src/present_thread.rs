@@ -0,0 +1,253 @@
+use crate::{FrameOutcome, Queue, Semaphore, Swapchain, SwapchainImageIndex};
+use ash::vk;
+use log::warn;
+use std::{
+    error, fmt, io,
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Presents swapchain images on a dedicated thread. The render thread acquires an image, submits
+/// its rendering commands (signalling a semaphore) and hands the image index and semaphore to
+/// [`Self::present`], which returns immediately. `vkQueuePresentKHR` can block for a while on
+/// some platforms so this keeps the render thread from hitching.
+///
+/// The results of presents are reported back as [`PresentOutcome`]s, see
+/// [`Self::poll_outcomes`]. To recreate the swapchain, call [`Self::shutdown`], recreate it and
+/// start a new `PresentThread`.
+///
+/// [`Swapchain`] and [`Queue`] lock internally where vulkan requires external synchronization
+/// so the render thread can keep acquiring from the swapchain and submitting to the queue. A
+/// blocking acquire only holds the swapchain lock for short intervals (see
+/// [`Swapchain::aquire_next_image`]) so it doesn't stall the present which would free an image.
+pub struct PresentThread {
+    request_sender: Option<Sender<PresentRequest>>,
+    outcome_receiver: Receiver<PresentOutcome>,
+    join_handle: Option<JoinHandle<()>>,
+    swapchain: Arc<Swapchain>,
+}
+
+impl PresentThread {
+    pub fn new(swapchain: Arc<Swapchain>, queue: Arc<Queue>) -> io::Result<Self> {
+        let (request_sender, request_receiver) = mpsc::channel::<PresentRequest>();
+        let (outcome_sender, outcome_receiver) = mpsc::channel();
+
+        let thread_swapchain = swapchain.clone();
+        let join_handle = thread::Builder::new()
+            .name("bort-present".to_string())
+            .spawn(move || {
+                for request in request_receiver {
+                    let outcome = present_request(&thread_swapchain, &queue, &request);
+                    if outcome_sender.send(outcome).is_err() {
+                        // nobody is listening, keep presenting
+                        continue;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            request_sender: Some(request_sender),
+            outcome_receiver,
+            join_handle: Some(join_handle),
+            swapchain,
+        })
+    }
+
+    /// Queues `image_index` to be presented once `wait_semaphores` are signalled. The semaphores
+    /// must stay alive until the corresponding [`PresentOutcome`] has been received.
+    pub fn present(
+        &self,
+        image_index: SwapchainImageIndex,
+        wait_semaphores: &[&Semaphore],
+    ) -> Result<(), PresentThreadError> {
+        let request = PresentRequest {
+            image_index,
+            wait_semaphores: wait_semaphores
+                .iter()
+                .map(|semaphore| semaphore.handle())
+                .collect(),
+        };
+        self.request_sender
+            .as_ref()
+            .ok_or(PresentThreadError::Disconnected)?
+            .send(request)
+            .map_err(|_| PresentThreadError::Disconnected)
+    }
+
+    /// Returns the outcomes of presents completed since the last call without blocking.
+    pub fn poll_outcomes(&self) -> Result<Vec<PresentOutcome>, PresentThreadError> {
+        let mut outcomes = Vec::new();
+        loop {
+            match self.outcome_receiver.try_recv() {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(TryRecvError::Empty) => return Ok(outcomes),
+                Err(TryRecvError::Disconnected) if !outcomes.is_empty() => return Ok(outcomes),
+                Err(TryRecvError::Disconnected) => return Err(PresentThreadError::Disconnected),
+            }
+        }
+    }
+
+    /// Blocks until the next present has completed.
+    pub fn wait_outcome(&self) -> Result<PresentOutcome, PresentThreadError> {
+        self.outcome_receiver
+            .recv()
+            .map_err(|_| PresentThreadError::Disconnected)
+    }
+
+    /// Whether any present completed since the last poll requires the swapchain to be recreated.
+    /// Discards the outcomes.
+    pub fn poll_needs_recreation(&self) -> Result<bool, PresentThreadError> {
+        let outcomes = self.poll_outcomes()?;
+        Ok(outcomes.iter().any(PresentOutcome::needs_recreation))
+    }
+
+    /// Waits for all queued presents to complete and stops the thread. Returns the outcomes not
+    /// yet received.
+    pub fn shutdown(mut self) -> Vec<PresentOutcome> {
+        self.stop_thread();
+        self.outcome_receiver.try_iter().collect()
+    }
+
+    fn stop_thread(&mut self) {
+        // dropping the sender ends the request loop once the queued requests are processed
+        self.request_sender = None;
+        if let Some(join_handle) = self.join_handle.take() {
+            if join_handle.join().is_err() {
+                warn!("present thread panicked");
+            }
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn swapchain(&self) -> &Arc<Swapchain> {
+        &self.swapchain
+    }
+}
+
+impl Drop for PresentThread {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+struct PresentRequest {
+    image_index: SwapchainImageIndex,
+    wait_semaphores: Vec<vk::Semaphore>,
+}
+
+/// Result of a present performed by a [`PresentThread`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentOutcome {
+    Presented(SwapchainImageIndex),
+    /// Presented but the swapchain no longer matches the surface exactly. Should be recreated.
+    Suboptimal(SwapchainImageIndex),
+    /// Not presented because the swapchain no longer matches the surface. Must be recreated.
+    OutOfDate(SwapchainImageIndex),
+    Error {
+        image_index: SwapchainImageIndex,
+        result: vk::Result,
+    },
+}
+
+impl PresentOutcome {
+    fn from_present_result(
+        image_index: SwapchainImageIndex,
+        res: Result<bool, vk::Result>,
+    ) -> Self {
+        match res {
+            Ok(false) => Self::Presented(image_index),
+            Ok(true) | Err(vk::Result::SUBOPTIMAL_KHR) => Self::Suboptimal(image_index),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Self::OutOfDate(image_index),
+            Err(result) => Self::Error {
+                image_index,
+                result,
+            },
+        }
+    }
+
+    /// Whether the swapchain should be recreated.
+    pub fn needs_recreation(&self) -> bool {
+        matches!(self, Self::Suboptimal(_) | Self::OutOfDate(_))
+    }
+
+    /// What the render loop should do about this present.
+    pub fn frame_outcome(&self) -> FrameOutcome {
+        match *self {
+            Self::Presented(_) => FrameOutcome::Presented,
+            Self::Suboptimal(_) | Self::OutOfDate(_) => FrameOutcome::RecreateSwapchain,
+            Self::Error { result, .. } => FrameOutcome::from_vk_result(result),
+        }
+    }
+
+    pub fn image_index(&self) -> SwapchainImageIndex {
+        match *self {
+            Self::Presented(image_index)
+            | Self::Suboptimal(image_index)
+            | Self::OutOfDate(image_index)
+            | Self::Error { image_index, .. } => image_index,
+        }
+    }
+}
+
+// Helper Functions
+
+fn present_request(
+    swapchain: &Swapchain,
+    queue: &Queue,
+    request: &PresentRequest,
+) -> PresentOutcome {
+    let swapchains = [swapchain.handle()];
+    let image_indices = [request.image_index.value()];
+    let present_info = vk::PresentInfoKHR::default()
+        .wait_semaphores(&request.wait_semaphores)
+        .swapchains(&swapchains)
+        .image_indices(&image_indices);
+
+    let present_res = swapchain.queue_present(queue, &present_info);
+    PresentOutcome::from_present_result(request.image_index, present_res)
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum PresentThreadError {
+    /// The present thread has stopped.
+    Disconnected,
+}
+
+impl fmt::Display for PresentThreadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected => write!(f, "the present thread has stopped"),
+        }
+    }
+}
+
+impl error::Error for PresentThreadError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn present_outcome_from_present_result() {
+    let image_index = SwapchainImageIndex::new(1);
+    assert_eq!(
+        PresentOutcome::from_present_result(image_index, Ok(false)),
+        PresentOutcome::Presented(image_index)
+    );
+    assert!(PresentOutcome::from_present_result(image_index, Ok(true)).needs_recreation());
+    assert!(PresentOutcome::from_present_result(
+        image_index,
+        Err(vk::Result::ERROR_OUT_OF_DATE_KHR)
+    )
+    .needs_recreation());
+    let device_lost =
+        PresentOutcome::from_present_result(image_index, Err(vk::Result::ERROR_DEVICE_LOST));
+    assert!(!device_lost.needs_recreation());
+    assert_eq!(device_lost.image_index(), image_index);
+    assert_eq!(device_lost.frame_outcome(), FrameOutcome::DeviceLost);
+}
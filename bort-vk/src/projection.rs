@@ -0,0 +1,114 @@
+//! Projection matrices and the matching pipeline/render pass state for reverse-Z rendering.
+//!
+//! Reverse-Z maps the near plane to depth 1 and the far plane (or infinity) to depth 0. Floating
+//! point depth buffers have the most precision near 0, which then lines up with the distant
+//! geometry where a perspective projection has the least, giving close to uniform precision over
+//! the whole view distance. Getting it right takes all of the following together:
+//! - a reverse-Z projection matrix e.g. [`perspective_reverse_z_infinite`]
+//! - a `GREATER_OR_EQUAL` depth test ([`REVERSE_Z_DEPTH_COMPARE_OP`],
+//!   [`DepthStencilState::new_reverse_z`])
+//! - clearing depth to 0 instead of 1 ([`reverse_z_depth_clear_value`])
+//! - a floating point depth format e.g. `D32_SFLOAT` (unorm formats get no benefit)
+//! - a regular `0..1` viewport depth range ([`reverse_z_viewport`]). Don't also swap
+//!   `min_depth`/`max_depth`, the projection already does the reversal.
+//!
+//! Matrices are column major `[[f32; 4]; 4]` (`matrix[column][row]`) as expected by glsl/hlsl
+//! `mat4`/`float4x4` uniforms and most rust math crates, for right handed view spaces looking down
+//! -Z. The y axis is flipped to match vulkan's y-down clip space.
+
+use crate::{ClearValue, DepthStencilState};
+use ash::vk;
+
+/// Depth compare op for reverse-Z: closer fragments have greater depth values.
+pub const REVERSE_Z_DEPTH_COMPARE_OP: vk::CompareOp = vk::CompareOp::GREATER_OR_EQUAL;
+
+/// The far plane depth value with reverse-Z.
+pub const REVERSE_Z_FAR_DEPTH: f32 = 0.;
+
+/// Reverse-Z perspective projection with a far plane at `far`. `fov_y` is the vertical field of
+/// view in radians and `aspect_ratio` is width / height.
+pub fn perspective_reverse_z(fov_y: f32, aspect_ratio: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    debug_assert!(near > 0. && far > near);
+    let focal_length = 1. / (fov_y / 2.).tan();
+    let depth_scale = near / (far - near);
+    let depth_offset = far * depth_scale;
+    [
+        [focal_length / aspect_ratio, 0., 0., 0.],
+        [0., -focal_length, 0., 0.],
+        [0., 0., depth_scale, -1.],
+        [0., 0., depth_offset, 0.],
+    ]
+}
+
+/// Reverse-Z perspective projection with the far plane at infinity. Depth approaches 0 as the
+/// view distance increases and never reaches it so there's no far plane clipping.
+pub fn perspective_reverse_z_infinite(fov_y: f32, aspect_ratio: f32, near: f32) -> [[f32; 4]; 4] {
+    debug_assert!(near > 0.);
+    let focal_length = 1. / (fov_y / 2.).tan();
+    [
+        [focal_length / aspect_ratio, 0., 0., 0.],
+        [0., -focal_length, 0., 0.],
+        [0., 0., 0., -1.],
+        [0., 0., near, 0.],
+    ]
+}
+
+/// Depth clear value for reverse-Z (depth 0 i.e. the far plane).
+pub fn reverse_z_depth_clear_value(stencil: u32) -> ClearValue {
+    ClearValue::DepthStencil {
+        depth: REVERSE_Z_FAR_DEPTH,
+        stencil,
+    }
+}
+
+/// Viewport covering `extent` with the regular `0..1` depth range. The reversal is done by the
+/// projection matrix.
+pub fn reverse_z_viewport(extent: vk::Extent2D) -> vk::Viewport {
+    vk::Viewport {
+        x: 0.,
+        y: 0.,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        min_depth: 0.,
+        max_depth: 1.,
+    }
+}
+
+/// Depth test and writes for opaque geometry, or only depth testing for transparent geometry,
+/// with [`REVERSE_Z_DEPTH_COMPARE_OP`].
+pub fn reverse_z_depth_stencil_state(depth_write: bool) -> DepthStencilState {
+    if depth_write {
+        DepthStencilState::new_reverse_z()
+    } else {
+        DepthStencilState::new_reverse_z_read_only()
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn reverse_z_projection_depths() {
+    let project_depth = |projection: &[[f32; 4]; 4], view_z: f32| {
+        let clip_z = projection[2][2] * view_z + projection[3][2];
+        let clip_w = projection[2][3] * view_z + projection[3][3];
+        clip_z / clip_w
+    };
+    let fov_y = std::f32::consts::FRAC_PI_2;
+
+    let projection = perspective_reverse_z(fov_y, 16. / 9., 0.1, 100.);
+    assert!((project_depth(&projection, -0.1) - 1.).abs() < 1e-6);
+    assert!(project_depth(&projection, -100.).abs() < 1e-6);
+    assert!(project_depth(&projection, -1.) > project_depth(&projection, -10.));
+
+    let infinite_projection = perspective_reverse_z_infinite(fov_y, 1., 0.1);
+    assert!((project_depth(&infinite_projection, -0.1) - 1.).abs() < 1e-6);
+    let far_depth = project_depth(&infinite_projection, -1e6);
+    assert!(far_depth > REVERSE_Z_FAR_DEPTH && far_depth < 1e-6);
+
+    // vulkan clip space is y-down
+    assert!(projection[1][1] < 0.);
+    assert_eq!(
+        reverse_z_depth_stencil_state(true).depth_compare_op,
+        REVERSE_Z_DEPTH_COMPARE_OP
+    );
+}
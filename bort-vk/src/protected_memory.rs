@@ -0,0 +1,191 @@
+//! Protected memory (vulkan 1.1 `protectedMemory` feature) prevents protected resources from
+//! being read by the host or copied into unprotected resources, e.g. for DRM video playback.
+//! Mixing protected and unprotected objects in a submission is invalid usage that validation
+//! layers can't always catch, so [`validate_protected_submit`] checks it up front.
+//!
+//! See [`PhysicalDeviceFeatures::with_protected_memory`](crate::PhysicalDeviceFeatures::with_protected_memory),
+//! [`Queue::new_protected`](crate::Queue::new_protected) and
+//! [`Queue::submit_protected`](crate::Queue::submit_protected).
+
+use crate::{Buffer, CommandBuffer, CommandPool, DeviceOwned, Image};
+use ash::vk;
+use std::{error, fmt};
+
+/// Objects which can be created with a `PROTECTED` flag.
+pub trait ProtectedResource: DeviceOwned {
+    fn is_protected(&self) -> bool;
+}
+
+impl ProtectedResource for Buffer {
+    fn is_protected(&self) -> bool {
+        self.properties()
+            .flags
+            .contains(vk::BufferCreateFlags::PROTECTED)
+    }
+}
+
+impl ProtectedResource for Image {
+    fn is_protected(&self) -> bool {
+        self.properties()
+            .flags
+            .contains(vk::ImageCreateFlags::PROTECTED)
+    }
+}
+
+impl ProtectedResource for CommandPool {
+    fn is_protected(&self) -> bool {
+        self.properties()
+            .flags
+            .contains(vk::CommandPoolCreateFlags::PROTECTED)
+    }
+}
+
+impl ProtectedResource for CommandBuffer {
+    fn is_protected(&self) -> bool {
+        self.command_pool().is_protected()
+    }
+}
+
+/// A resource accessed by the command buffers of a submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubmissionResource {
+    pub handle_raw: u64,
+    pub protected: bool,
+    /// Whether the submission writes to the resource.
+    pub written: bool,
+}
+
+impl SubmissionResource {
+    pub fn read(resource: &dyn ProtectedResource) -> Self {
+        Self {
+            handle_raw: resource.handle_raw(),
+            protected: resource.is_protected(),
+            written: false,
+        }
+    }
+
+    pub fn written(resource: &dyn ProtectedResource) -> Self {
+        Self {
+            handle_raw: resource.handle_raw(),
+            protected: resource.is_protected(),
+            written: true,
+        }
+    }
+}
+
+/// Checks the protected memory rules for a submission:
+/// - protected submissions may only contain command buffers from protected pools and vice versa
+/// - unprotected submissions can't access protected resources
+/// - protected submissions can't write to unprotected resources
+pub fn validate_protected_submit(
+    protected_submit: bool,
+    command_buffers: &[&CommandBuffer],
+    resources: &[SubmissionResource],
+) -> Result<(), ProtectedMemoryError> {
+    for command_buffer in command_buffers {
+        if command_buffer.is_protected() != protected_submit {
+            return Err(ProtectedMemoryError::CommandBufferMismatch {
+                command_buffer: command_buffer.handle(),
+                protected_submit,
+            });
+        }
+    }
+
+    for resource in resources {
+        if !protected_submit && resource.protected {
+            return Err(ProtectedMemoryError::ProtectedResourceInUnprotectedSubmit {
+                handle_raw: resource.handle_raw,
+            });
+        }
+        if protected_submit && resource.written && !resource.protected {
+            return Err(ProtectedMemoryError::UnprotectedWriteInProtectedSubmit {
+                handle_raw: resource.handle_raw,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum ProtectedMemoryError {
+    /// The command buffer's pool protection doesn't match the submission.
+    CommandBufferMismatch {
+        command_buffer: vk::CommandBuffer,
+        protected_submit: bool,
+    },
+    ProtectedResourceInUnprotectedSubmit {
+        handle_raw: u64,
+    },
+    UnprotectedWriteInProtectedSubmit {
+        handle_raw: u64,
+    },
+}
+
+impl fmt::Display for ProtectedMemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandBufferMismatch {
+                command_buffer,
+                protected_submit: true,
+            } => write!(
+                f,
+                "command buffer {:?} in a protected submission wasn't allocated from a PROTECTED command pool",
+                command_buffer
+            ),
+            Self::CommandBufferMismatch {
+                command_buffer,
+                protected_submit: false,
+            } => write!(
+                f,
+                "command buffer {:?} was allocated from a PROTECTED command pool but the submission isn't protected",
+                command_buffer
+            ),
+            Self::ProtectedResourceInUnprotectedSubmit { handle_raw } => write!(
+                f,
+                "protected resource {:#x} is accessed by an unprotected submission",
+                handle_raw
+            ),
+            Self::UnprotectedWriteInProtectedSubmit { handle_raw } => write!(
+                f,
+                "unprotected resource {:#x} is written by a protected submission which could leak protected data",
+                handle_raw
+            ),
+        }
+    }
+}
+
+impl error::Error for ProtectedMemoryError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn validate_protected_submit_checks_resources() {
+    let resource = |handle_raw, protected, written| SubmissionResource {
+        handle_raw,
+        protected,
+        written,
+    };
+
+    let protected_video = resource(1, true, false);
+    let protected_output = resource(2, true, true);
+    let unprotected_lut = resource(3, false, false);
+    let unprotected_output = resource(4, false, true);
+
+    assert!(validate_protected_submit(
+        true,
+        &[],
+        &[protected_video, protected_output, unprotected_lut]
+    )
+    .is_ok());
+    assert!(matches!(
+        validate_protected_submit(true, &[], &[protected_video, unprotected_output]),
+        Err(ProtectedMemoryError::UnprotectedWriteInProtectedSubmit { handle_raw: 4 })
+    ));
+    assert!(matches!(
+        validate_protected_submit(false, &[], &[unprotected_output, protected_video]),
+        Err(ProtectedMemoryError::ProtectedResourceInUnprotectedSubmit { handle_raw: 1 })
+    ));
+}
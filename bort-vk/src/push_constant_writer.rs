@@ -0,0 +1,317 @@
+use crate::{CommandBuffer, PipelineLayout, PushConstantMember};
+use ash::vk;
+#[cfg(feature = "bytemuck")]
+use bytemuck::NoUninit;
+use std::{collections::HashMap, error, fmt, sync::Arc};
+
+/// Stages push constant values for a [`PipelineLayout`] and records them with the minimal number
+/// of `vkCmdPushConstants` calls, grouping bytes by the stages of the ranges covering them so
+/// that multi-stage range splits are always valid.
+///
+/// Values can be set by push constant range index or by field name. Field names are declared with
+/// [`Self::define_field`] or read from shader reflection data with [`Self::with_fields`] e.g.
+/// `writer.with_fields(shader_module.push_constant_members().unwrap_or_default())`.
+pub struct PushConstantWriter {
+    pipeline_layout: Arc<PipelineLayout>,
+    data: Vec<u8>,
+    /// One entry per 4 byte word of `data`.
+    dirty_words: Vec<bool>,
+    field_offsets: HashMap<String, u32>,
+}
+
+impl PushConstantWriter {
+    pub fn new(pipeline_layout: Arc<PipelineLayout>) -> Self {
+        let size = pipeline_layout
+            .properties()
+            .push_constant_ranges
+            .iter()
+            .map(|range| range.offset + range.size)
+            .max()
+            .unwrap_or(0) as usize;
+
+        Self {
+            pipeline_layout,
+            data: vec![0; size],
+            dirty_words: vec![false; size.div_ceil(4)],
+            field_offsets: HashMap::new(),
+        }
+    }
+
+    /// Adds the named fields from shader reflection data e.g.
+    /// [`ShaderModule::push_constant_members`](crate::ShaderModule::push_constant_members).
+    pub fn with_fields<'a>(
+        mut self,
+        members: impl IntoIterator<Item = &'a PushConstantMember>,
+    ) -> Self {
+        for member in members {
+            self.define_field(member.name.clone(), member.offset);
+        }
+        self
+    }
+
+    /// Declares a field at `offset` bytes from the start of the push constant block.
+    pub fn define_field(&mut self, name: impl Into<String>, offset: u32) {
+        self.field_offsets.insert(name.into(), offset);
+    }
+
+    /// Sets `bytes` at `offset` bytes into the push constant range at `range_index` of the
+    /// pipeline layout.
+    pub fn set_range_bytes(
+        &mut self,
+        range_index: usize,
+        offset: u32,
+        bytes: &[u8],
+    ) -> Result<&mut Self, PushConstantError> {
+        let ranges = &self.pipeline_layout.properties().push_constant_ranges;
+        let range = ranges
+            .get(range_index)
+            .ok_or(PushConstantError::RangeIndexOutOfBounds {
+                range_index,
+                range_count: ranges.len(),
+            })?;
+        if offset as usize + bytes.len() > range.size as usize {
+            return Err(PushConstantError::OutsideRange {
+                offset: range.offset + offset,
+                size: bytes.len() as u32,
+            });
+        }
+        self.set_bytes(range.offset + offset, bytes)
+    }
+
+    /// Sets `bytes` at the offset of the field called `name`.
+    pub fn set_field_bytes(
+        &mut self,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<&mut Self, PushConstantError> {
+        let offset = *self
+            .field_offsets
+            .get(name)
+            .ok_or_else(|| PushConstantError::UnknownField(name.to_string()))?;
+        self.set_bytes(offset, bytes)
+    }
+
+    #[cfg(feature = "bytemuck")]
+    pub fn set_range<T: NoUninit>(
+        &mut self,
+        range_index: usize,
+        offset: u32,
+        value: T,
+    ) -> Result<&mut Self, PushConstantError> {
+        self.set_range_bytes(range_index, offset, bytemuck::bytes_of(&value))
+    }
+
+    #[cfg(feature = "bytemuck")]
+    pub fn set_field<T: NoUninit>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<&mut Self, PushConstantError> {
+        self.set_field_bytes(name, bytemuck::bytes_of(&value))
+    }
+
+    /// Sets `bytes` at `offset` bytes from the start of the push constant block. Every byte must
+    /// be covered by a push constant range.
+    pub fn set_bytes(&mut self, offset: u32, bytes: &[u8]) -> Result<&mut Self, PushConstantError> {
+        let outside_range = PushConstantError::OutsideRange {
+            offset,
+            size: bytes.len() as u32,
+        };
+        let start = offset as usize;
+        let end = start + bytes.len();
+        if bytes.is_empty() || end > self.data.len() {
+            return Err(outside_range);
+        }
+        let first_word = start / 4;
+        let end_word = end.div_ceil(4);
+        let uncovered_word =
+            (first_word..end_word).any(|word_index| self.word_stage_flags(word_index).is_empty());
+        if uncovered_word {
+            return Err(outside_range);
+        }
+
+        self.data[start..end].copy_from_slice(bytes);
+        self.dirty_words[first_word..end_word].fill(true);
+        Ok(self)
+    }
+
+    /// Records the values set since the last call. Returns the number of `vkCmdPushConstants`
+    /// calls made.
+    pub fn record(&mut self, command_buffer: &CommandBuffer) -> usize {
+        let updates = pending_updates(
+            &self.pipeline_layout.properties().push_constant_ranges,
+            &self.dirty_words,
+        );
+        for &(stage_flags, offset, size) in &updates {
+            let start = offset as usize;
+            command_buffer.push_constants(
+                &self.pipeline_layout,
+                stage_flags,
+                offset,
+                &self.data[start..start + size as usize],
+            );
+        }
+        self.dirty_words.fill(false);
+        updates.len()
+    }
+
+    /// Marks every value as changed e.g. after binding a new command buffer.
+    pub fn mark_all_dirty(&mut self) {
+        for word_index in 0..self.dirty_words.len() {
+            self.dirty_words[word_index] = !self.word_stage_flags(word_index).is_empty();
+        }
+    }
+
+    fn word_stage_flags(&self, word_index: usize) -> vk::ShaderStageFlags {
+        word_stage_flags(
+            &self.pipeline_layout.properties().push_constant_ranges,
+            word_index,
+        )
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline_layout(&self) -> &Arc<PipelineLayout> {
+        &self.pipeline_layout
+    }
+
+    /// The staged push constant block.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+// Helper Functions
+
+/// `(stage_flags, offset, size)` of each push constant call needed for the dirty words.
+fn pending_updates(
+    push_constant_ranges: &[vk::PushConstantRange],
+    dirty_words: &[bool],
+) -> Vec<(vk::ShaderStageFlags, u32, u32)> {
+    let mut updates = Vec::<(vk::ShaderStageFlags, u32, u32)>::new();
+    let mut previous_word_pushed = false;
+    for (word_index, &dirty) in dirty_words.iter().enumerate() {
+        if !dirty {
+            previous_word_pushed = false;
+            continue;
+        }
+        let stage_flags = word_stage_flags(push_constant_ranges, word_index);
+        match updates.last_mut() {
+            Some((last_stage_flags, _, last_size))
+                if previous_word_pushed && *last_stage_flags == stage_flags =>
+            {
+                *last_size += 4
+            }
+            _ => updates.push((stage_flags, word_index as u32 * 4, 4)),
+        }
+        previous_word_pushed = true;
+    }
+    updates
+}
+
+/// Union of the stages of the ranges covering a word. Every range overlapping a push must be
+/// included in its stage flags, and range offsets and sizes are multiples of 4.
+fn word_stage_flags(
+    push_constant_ranges: &[vk::PushConstantRange],
+    word_index: usize,
+) -> vk::ShaderStageFlags {
+    let offset = word_index as u32 * 4;
+    push_constant_ranges
+        .iter()
+        .filter(|range| range.offset <= offset && offset < range.offset + range.size)
+        .fold(vk::ShaderStageFlags::empty(), |stage_flags, range| {
+            stage_flags | range.stage_flags
+        })
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushConstantError {
+    RangeIndexOutOfBounds {
+        range_index: usize,
+        range_count: usize,
+    },
+    /// Some of the bytes aren't covered by a push constant range.
+    OutsideRange {
+        offset: u32,
+        size: u32,
+    },
+    UnknownField(String),
+}
+
+impl fmt::Display for PushConstantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RangeIndexOutOfBounds {
+                range_index,
+                range_count,
+            } => write!(
+                f,
+                "push constant range index {} is out of bounds for a pipeline layout with {} ranges",
+                range_index, range_count
+            ),
+            Self::OutsideRange { offset, size } => write!(
+                f,
+                "push constant write of {} bytes at offset {} isn't covered by the pipeline layout push constant ranges",
+                size, offset
+            ),
+            Self::UnknownField(name) => write!(f, "unknown push constant field '{}'", name),
+        }
+    }
+}
+
+impl error::Error for PushConstantError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn push_constant_updates_grouped_by_stage() {
+    let push_constant_ranges = [
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: 16,
+        },
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+            offset: 16,
+            size: 16,
+        },
+        vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::FRAGMENT,
+            offset: 32,
+            size: 8,
+        },
+    ];
+    let vertex_fragment = vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT;
+
+    // all words dirty: one call per stage group
+    let updates = pending_updates(&push_constant_ranges, &[true; 10]);
+    assert_eq!(
+        updates,
+        vec![
+            (vk::ShaderStageFlags::VERTEX, 0, 16),
+            (vertex_fragment, 16, 16),
+            (vk::ShaderStageFlags::FRAGMENT, 32, 8),
+        ]
+    );
+
+    // gaps split calls within a group
+    let mut dirty_words = [false; 10];
+    dirty_words[0] = true;
+    dirty_words[2] = true;
+    dirty_words[3] = true;
+    let updates = pending_updates(&push_constant_ranges, &dirty_words);
+    assert_eq!(
+        updates,
+        vec![
+            (vk::ShaderStageFlags::VERTEX, 0, 4),
+            (vk::ShaderStageFlags::VERTEX, 8, 8),
+        ]
+    );
+
+    assert!(word_stage_flags(&push_constant_ranges, 10).is_empty());
+}
@@ -0,0 +1,560 @@
+use crate::{ApiVersion, Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use ash::{
+    ext,
+    prelude::VkResult,
+    vk::{self, Handle, EXT_HOST_QUERY_RESET_NAME},
+};
+#[cfg(feature = "bytemuck")]
+use bytemuck::Pod;
+#[cfg(feature = "bytemuck")]
+use std::mem;
+use std::{error, fmt, ops::Range, sync::Arc};
+
+pub struct QueryPool {
+    handle: vk::QueryPool,
+    properties: QueryPoolProperties,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl QueryPool {
+    pub fn new(device: Arc<Device>, properties: QueryPoolProperties) -> VkResult<Self> {
+        let create_info = properties.create_info();
+
+        let handle = unsafe {
+            device
+                .inner()
+                .create_query_pool(&create_info, ALLOCATION_CALLBACK_NONE)
+        }?;
+
+        Ok(Self {
+            handle,
+            properties,
+            device,
+        })
+    }
+
+    /// # Safety
+    /// Make sure your `p_next` chain contains valid pointers.
+    pub unsafe fn new_from_create_info(
+        device: Arc<Device>,
+        create_info: vk::QueryPoolCreateInfo,
+    ) -> VkResult<Self> {
+        let properties = QueryPoolProperties::from_create_info(&create_info);
+
+        let handle = unsafe {
+            device
+                .inner()
+                .create_query_pool(&create_info, ALLOCATION_CALLBACK_NONE)
+        }?;
+
+        Ok(Self {
+            handle,
+            properties,
+            device,
+        })
+    }
+
+    /// Resets the queries in `query_range` from the host, avoiding a command buffer round trip
+    /// between frames. Requires the vulkan 1.2 `hostQueryReset` feature or
+    /// `VK_EXT_host_query_reset`, see [`HostQueryResetSupport`]. Otherwise use
+    /// [`CommandBuffer::reset_query_pool`](crate::CommandBuffer::reset_query_pool).
+    ///
+    /// The queries must not be in use by any pending command buffers.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkResetQueryPool.html>
+    pub fn reset_host(&self, query_range: Range<u32>) -> Result<(), QueryPoolError> {
+        self.check_range(&query_range)?;
+        let query_count = query_range.end - query_range.start;
+
+        match HostQueryResetSupport::for_device(&self.device) {
+            HostQueryResetSupport::Core => unsafe {
+                self.device
+                    .inner()
+                    .reset_query_pool(self.handle, query_range.start, query_count);
+            },
+            HostQueryResetSupport::Extension => {
+                let host_query_reset_fns = self
+                    .device
+                    .extension_loader::<ext::host_query_reset::Device>();
+                unsafe {
+                    (host_query_reset_fns.fp().reset_query_pool_ext)(
+                        self.device.inner().handle(),
+                        self.handle,
+                        query_range.start,
+                        query_count,
+                    );
+                }
+            }
+            HostQueryResetSupport::Unsupported => {
+                return Err(QueryPoolError::HostQueryResetUnsupported)
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the results of the queries in `query_range` to `data`. `data` must hold
+    /// `query_range.len()` elements of `T`, where `T` matches the `TYPE_64` and
+    /// `WITH_AVAILABILITY` result flags e.g. `u64` or `[u64; 2]`. Returns
+    /// [`QueryPoolError::DataLengthMismatch`] otherwise.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetQueryPoolResults.html>
+    pub fn get_results<T>(
+        &self,
+        query_range: Range<u32>,
+        data: &mut [T],
+        flags: vk::QueryResultFlags,
+    ) -> Result<(), QueryPoolError> {
+        self.check_range(&query_range)?;
+        if data.len() != query_range.len() {
+            return Err(QueryPoolError::DataLengthMismatch {
+                data_len: data.len(),
+                query_count: query_range.end - query_range.start,
+            });
+        }
+        unsafe {
+            self.device
+                .inner()
+                .get_query_pool_results(self.handle, query_range.start, data, flags)
+        }
+        .map_err(QueryPoolError::Vk)
+    }
+
+    /// Reads the results of `count` queries starting at `first` into `T`s, working out the stride
+    /// from the query type and `flags` so it can't be mismatched with `T`. `T` must hold exactly
+    /// [`QueryPoolProperties::values_per_query`] values of `u64` if `flags` contains `TYPE_64` or
+    /// `u32` otherwise e.g. `u64` for timestamps or `[u32; 3]` for three pipeline statistics.
+    ///
+    /// If `flags` contains `WITH_AVAILABILITY` the availability of each query is read too and
+    /// `NOT_READY` isn't treated as an error: unavailable queries have
+    /// [`QueryResult::available`] set to `false` and an undefined (or with `PARTIAL`, partial)
+    /// value. Otherwise every returned query is available.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetQueryPoolResults.html>
+    #[cfg(feature = "bytemuck")]
+    pub fn get_results_typed<T: Pod>(
+        &self,
+        first: u32,
+        count: u32,
+        flags: vk::QueryResultFlags,
+    ) -> Result<Vec<QueryResult<T>>, QueryPoolError> {
+        let query_range = first..first.saturating_add(count);
+        self.check_range(&query_range)?;
+        let layout = QueryResultLayout::new(self.properties.values_per_query(), flags);
+        layout.check_result_type::<T>()?;
+
+        let mut data = vec![0u8; layout.stride() * count as usize];
+        let res = unsafe {
+            (self.device.inner().fp_v1_0().get_query_pool_results)(
+                self.device.inner().handle(),
+                self.handle,
+                first,
+                count,
+                data.len(),
+                data.as_mut_ptr().cast(),
+                layout.stride() as vk::DeviceSize,
+                flags,
+            )
+        };
+        match res {
+            vk::Result::SUCCESS => {}
+            vk::Result::NOT_READY if layout.with_availability => {}
+            e => return Err(QueryPoolError::Vk(e)),
+        }
+
+        Ok(layout.read_results(&data))
+    }
+
+    pub(crate) fn check_range(&self, query_range: &Range<u32>) -> Result<(), QueryPoolError> {
+        if query_range.start > query_range.end || query_range.end > self.properties.query_count {
+            return Err(QueryPoolError::RangeOutOfBounds {
+                query_range: query_range.clone(),
+                query_count: self.properties.query_count,
+            });
+        }
+        Ok(())
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> vk::QueryPool {
+        self.handle
+    }
+
+    #[inline]
+    pub fn properties(&self) -> &QueryPoolProperties {
+        &self.properties
+    }
+}
+
+impl DeviceOwned for QueryPool {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    #[inline]
+    fn handle_raw(&self) -> u64 {
+        self.handle.as_raw()
+    }
+}
+
+impl Drop for QueryPool {
+    fn drop(&mut self) {
+        unsafe {
+            self.device
+                .inner()
+                .destroy_query_pool(self.handle, ALLOCATION_CALLBACK_NONE)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryPoolProperties {
+    pub flags: vk::QueryPoolCreateFlags,
+    pub query_type: vk::QueryType,
+    pub query_count: u32,
+    pub pipeline_statistics: vk::QueryPipelineStatisticFlags,
+}
+
+impl QueryPoolProperties {
+    /// Number of values each query writes (excluding availability): one per enabled statistic for
+    /// pipeline statistics queries, two for transform feedback stream queries and one otherwise.
+    pub fn values_per_query(&self) -> usize {
+        match self.query_type {
+            vk::QueryType::PIPELINE_STATISTICS => {
+                self.pipeline_statistics.as_raw().count_ones() as usize
+            }
+            vk::QueryType::TRANSFORM_FEEDBACK_STREAM_EXT => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Default for QueryPoolProperties {
+    fn default() -> Self {
+        Self {
+            flags: vk::QueryPoolCreateFlags::empty(),
+            query_type: vk::QueryType::TIMESTAMP,
+            query_count: 0,
+            pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+        }
+    }
+}
+
+impl QueryPoolProperties {
+    pub fn new_timestamp(query_count: u32) -> Self {
+        Self {
+            query_count,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_occlusion(query_count: u32) -> Self {
+        Self {
+            query_type: vk::QueryType::OCCLUSION,
+            query_count,
+            ..Default::default()
+        }
+    }
+
+    /// For [`CommandBuffer::write_acceleration_structures_properties`](crate::CommandBuffer::write_acceleration_structures_properties)
+    /// before compacting acceleration structures. Requires `VK_KHR_acceleration_structure`.
+    pub fn new_acceleration_structure_compacted_size(query_count: u32) -> Self {
+        Self {
+            query_type: vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+            query_count,
+            ..Default::default()
+        }
+    }
+
+    /// For [`CommandBuffer::write_acceleration_structures_properties`](crate::CommandBuffer::write_acceleration_structures_properties)
+    /// before serializing acceleration structures. Requires `VK_KHR_acceleration_structure`.
+    pub fn new_acceleration_structure_serialization_size(query_count: u32) -> Self {
+        Self {
+            query_type: vk::QueryType::ACCELERATION_STRUCTURE_SERIALIZATION_SIZE_KHR,
+            query_count,
+            ..Default::default()
+        }
+    }
+
+    pub fn write_create_info<'a>(
+        &'a self,
+        create_info: vk::QueryPoolCreateInfo<'a>,
+    ) -> vk::QueryPoolCreateInfo<'a> {
+        create_info
+            .flags(self.flags)
+            .query_type(self.query_type)
+            .query_count(self.query_count)
+            .pipeline_statistics(self.pipeline_statistics)
+    }
+
+    pub fn create_info(&self) -> vk::QueryPoolCreateInfo<'_> {
+        self.write_create_info(vk::QueryPoolCreateInfo::default())
+    }
+
+    pub fn from_create_info(value: &vk::QueryPoolCreateInfo) -> Self {
+        Self {
+            flags: value.flags,
+            query_type: value.query_type,
+            query_count: value.query_count,
+            pipeline_statistics: value.pipeline_statistics,
+        }
+    }
+}
+
+impl From<&vk::QueryPoolCreateInfo<'_>> for QueryPoolProperties {
+    fn from(value: &vk::QueryPoolCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+/// How a device can reset queries from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostQueryResetSupport {
+    /// Vulkan 1.2 with the `hostQueryReset` feature enabled.
+    Core,
+    /// `VK_EXT_host_query_reset` is enabled. Make sure its `hostQueryReset` feature was enabled
+    /// too.
+    Extension,
+    Unsupported,
+}
+
+impl HostQueryResetSupport {
+    pub fn for_device(device: &Device) -> Self {
+        let extension_enabled = device
+            .enabled_extensions()
+            .contains(&EXT_HOST_QUERY_RESET_NAME.to_owned());
+        Self::from_enabled(
+            device.effective_api_version(),
+            device.enabled_features().features_1_2.host_query_reset == vk::TRUE,
+            extension_enabled,
+        )
+    }
+
+    fn from_enabled(
+        api_version: ApiVersion,
+        core_feature_enabled: bool,
+        extension_enabled: bool,
+    ) -> Self {
+        if api_version >= ApiVersion::V1_2 && core_feature_enabled {
+            Self::Core
+        } else if extension_enabled {
+            Self::Extension
+        } else {
+            Self::Unsupported
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        *self != Self::Unsupported
+    }
+}
+
+// Typed Results
+
+/// A query result read with [`QueryPool::get_results_typed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryResult<T> {
+    pub value: T,
+    /// Always `true` unless `WITH_AVAILABILITY` was requested.
+    pub available: bool,
+}
+
+/// Where the values and availability of each query are in the data written by
+/// `vkGetQueryPoolResults`.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy)]
+struct QueryResultLayout {
+    values_per_query: usize,
+    /// 8 with `TYPE_64`, 4 otherwise.
+    value_size: usize,
+    with_availability: bool,
+}
+
+#[cfg(feature = "bytemuck")]
+impl QueryResultLayout {
+    fn new(values_per_query: usize, flags: vk::QueryResultFlags) -> Self {
+        let value_size = if flags.contains(vk::QueryResultFlags::TYPE_64) {
+            mem::size_of::<u64>()
+        } else {
+            mem::size_of::<u32>()
+        };
+        Self {
+            values_per_query,
+            value_size,
+            with_availability: flags.contains(vk::QueryResultFlags::WITH_AVAILABILITY),
+        }
+    }
+
+    /// Size of the values of one query.
+    fn values_size(&self) -> usize {
+        self.values_per_query * self.value_size
+    }
+
+    /// The availability value follows the query values.
+    fn stride(&self) -> usize {
+        self.values_size() + self.with_availability as usize * self.value_size
+    }
+
+    fn check_result_type<T>(&self) -> Result<(), QueryPoolError> {
+        if mem::size_of::<T>() != self.values_size() {
+            return Err(QueryPoolError::ResultSizeMismatch {
+                result_size: mem::size_of::<T>(),
+                expected_size: self.values_size(),
+            });
+        }
+        Ok(())
+    }
+
+    fn read_results<T: Pod>(&self, data: &[u8]) -> Vec<QueryResult<T>> {
+        data.chunks_exact(self.stride())
+            .map(|query_data| {
+                let (values, availability) = query_data.split_at(self.values_size());
+                QueryResult {
+                    value: bytemuck::pod_read_unaligned(values),
+                    available: !self.with_availability || availability.iter().any(|&b| b != 0),
+                }
+            })
+            .collect()
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum QueryPoolError {
+    Vk(vk::Result),
+    /// Neither the vulkan 1.2 `hostQueryReset` feature nor `VK_EXT_host_query_reset` is enabled.
+    HostQueryResetUnsupported,
+    RangeOutOfBounds {
+        query_range: Range<u32>,
+        query_count: u32,
+    },
+    /// The slice passed to [`QueryPool::get_results`] doesn't hold one element per query.
+    DataLengthMismatch {
+        data_len: usize,
+        query_count: u32,
+    },
+    /// The result type passed to [`QueryPool::get_results_typed`] doesn't match the size of the
+    /// query values.
+    ResultSizeMismatch {
+        result_size: usize,
+        expected_size: usize,
+    },
+}
+
+impl fmt::Display for QueryPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vk(e) => write!(f, "{}", e),
+            Self::HostQueryResetUnsupported => write!(
+                f,
+                "host query reset requires the vulkan 1.2 hostQueryReset feature or VK_EXT_host_query_reset"
+            ),
+            Self::RangeOutOfBounds {
+                query_range,
+                query_count,
+            } => write!(
+                f,
+                "query range {:?} is out of bounds for a query pool with {} queries",
+                query_range, query_count
+            ),
+            Self::DataLengthMismatch {
+                data_len,
+                query_count,
+            } => write!(
+                f,
+                "query result slice has {} elements but {} queries were requested",
+                data_len, query_count
+            ),
+            Self::ResultSizeMismatch {
+                result_size,
+                expected_size,
+            } => write!(
+                f,
+                "query result type is {} bytes but the query values are {} bytes",
+                result_size, expected_size
+            ),
+        }
+    }
+}
+
+impl error::Error for QueryPoolError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vk(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn host_query_reset_support_detection() {
+    assert_eq!(
+        HostQueryResetSupport::from_enabled(ApiVersion::V1_2, true, false),
+        HostQueryResetSupport::Core
+    );
+    assert_eq!(
+        HostQueryResetSupport::from_enabled(ApiVersion::V1_1, true, true),
+        HostQueryResetSupport::Extension
+    );
+    assert_eq!(
+        HostQueryResetSupport::from_enabled(ApiVersion::V1_3, false, true),
+        HostQueryResetSupport::Extension
+    );
+    assert!(!HostQueryResetSupport::from_enabled(ApiVersion::V1_3, false, false).is_supported());
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+fn query_result_layout_strides() {
+    let properties = QueryPoolProperties {
+        query_type: vk::QueryType::PIPELINE_STATISTICS,
+        pipeline_statistics: vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES
+            | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS
+            | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS,
+        ..Default::default()
+    };
+    assert_eq!(properties.values_per_query(), 3);
+    assert_eq!(QueryPoolProperties::new_timestamp(4).values_per_query(), 1);
+
+    let layout = QueryResultLayout::new(
+        3,
+        vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+    );
+    assert_eq!(layout.stride(), 32);
+    assert!(layout.check_result_type::<[u64; 3]>().is_ok());
+    assert!(matches!(
+        layout.check_result_type::<[u32; 3]>(),
+        Err(QueryPoolError::ResultSizeMismatch {
+            result_size: 12,
+            expected_size: 24
+        })
+    ));
+
+    // two 32 bit timestamp queries with availability, the second unavailable
+    let layout = QueryResultLayout::new(1, vk::QueryResultFlags::WITH_AVAILABILITY);
+    assert_eq!(layout.stride(), 8);
+    let data: Vec<u8> = [7u32, 1, 0, 0]
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    assert_eq!(
+        layout.read_results::<u32>(&data),
+        vec![
+            QueryResult {
+                value: 7,
+                available: true
+            },
+            QueryResult {
+                value: 0,
+                available: false
+            }
+        ]
+    );
+}
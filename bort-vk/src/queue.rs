@@ -1,14 +1,29 @@
-use crate::{Device, DeviceError, DeviceOwned, Fence};
+use crate::{
+    instrumentation::trace_span, submission_sync_guards, Checkpoint, CommandBuffer, CommandPool,
+    CountedObjectType, Deadline, Device, DeviceError, DeviceOwned, Fence, ResourceAccess,
+    SyncHazard, SyncValidator, WaitStatus,
+};
 use ash::{
+    nv,
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
+/// A device queue. Commands which require the queue to be externally synchronized (submits,
+/// presents and waiting idle) lock an internal mutex so a `Queue` can be shared between threads
+/// e.g. submitting from a render thread and presenting from a [`PresentThread`](crate::PresentThread).
 pub struct Queue {
     handle: vk::Queue,
     family_index: u32,
     queue_index: u32,
+    /// Held during calls which require the queue to be externally synchronized.
+    external_sync: Mutex<()>,
+    /// Unsignalled fences reused by `submit_one_time`.
+    fence_pool: Mutex<Vec<Fence>>,
+    /// Fence of a `wait_idle_with_timeout` call that timed out. Kept until it signals because
+    /// destroying a pending fence isn't allowed.
+    pending_idle_fence: Mutex<Option<Fence>>,
 
     // dependencies
     device: Arc<Device>,
@@ -34,6 +49,9 @@ impl Queue {
             handle,
             family_index,
             queue_index,
+            external_sync: Mutex::new(()),
+            fence_pool: Mutex::new(Vec::new()),
+            pending_idle_fence: Mutex::new(None),
             device,
         })
     }
@@ -46,6 +64,55 @@ impl Queue {
             handle,
             family_index: queue_info.queue_family_index,
             queue_index: queue_info.queue_index,
+            external_sync: Mutex::new(()),
+            fence_pool: Mutex::new(Vec::new()),
+            pending_idle_fence: Mutex::new(None),
+            device,
+        }
+    }
+
+    /// Retrieves a protected capable queue with vkGetDeviceQueue2. The device must have been
+    /// created with the `protectedMemory` feature and a queue create info for `family_index` with
+    /// the `PROTECTED` flag.
+    pub fn new_protected(
+        device: Arc<Device>,
+        family_index: u32,
+        queue_index: u32,
+    ) -> Result<Self, QueueError> {
+        let queue_info = vk::DeviceQueueInfo2::default()
+            .flags(vk::DeviceQueueCreateFlags::PROTECTED)
+            .queue_family_index(family_index)
+            .queue_index(queue_index);
+        let queue = Self::new_v2(device, queue_info);
+        if queue.handle == vk::Queue::null() {
+            return Err(QueueError::NoQueueHandle {
+                device_handle: queue.device.inner().handle(),
+                family_index,
+                queue_index,
+            });
+        }
+        Ok(queue)
+    }
+
+    /// Wraps a `VkQueue` retrieved elsewhere e.g. by existing ash code. Queues are owned by their
+    /// device so nothing is destroyed on drop.
+    ///
+    /// # Safety
+    /// `handle` must be the queue at `queue_index` of `family_index` of `device`. Other code
+    /// using the queue must synchronize with [`Self::lock_external_sync`].
+    pub unsafe fn from_handle(
+        device: Arc<Device>,
+        handle: vk::Queue,
+        family_index: u32,
+        queue_index: u32,
+    ) -> Self {
+        Self {
+            handle,
+            family_index,
+            queue_index,
+            external_sync: Mutex::new(()),
+            fence_pool: Mutex::new(Vec::new()),
+            pending_idle_fence: Mutex::new(None),
             device,
         }
     }
@@ -55,7 +122,20 @@ impl Queue {
         submit_infos: &[vk::SubmitInfo<'_>],
         fence: Option<&Fence>,
     ) -> VkResult<()> {
+        let _span = trace_span!(
+            "queue_submit",
+            queue = ?self.handle,
+            queue_name = self.device.debug_name(self.handle).as_deref(),
+            submit_count = submit_infos.len()
+        );
+        if cfg!(debug_assertions) {
+            self.debug_assert_command_buffers_not_poisoned(submit_infos);
+        }
         let fence_handle = fence.map(|f| f.handle());
+        self.device
+            .submission_recorder()
+            .record_submits(self.handle, submit_infos, fence_handle);
+        let _external_sync = self.lock_external_sync();
         unsafe {
             self.device.inner().queue_submit(
                 self.handle,
@@ -65,10 +145,191 @@ impl Queue {
         }
     }
 
+    /// Same as [`Self::submit`] but first checks `accesses` (the resources read and written by the
+    /// command buffers of `submit_infos`) against the pending submissions of `sync_validator`,
+    /// then tracks the submission until the fence or semaphores it signals are reached. Returns
+    /// the hazards found, which are also logged. Submits regardless of hazards.
+    pub fn submit_validated(
+        &self,
+        sync_validator: &mut SyncValidator,
+        submit_infos: &[vk::SubmitInfo<'_>],
+        fence: Option<&Fence>,
+        accesses: Vec<ResourceAccess>,
+    ) -> VkResult<Vec<SyncHazard>> {
+        if !sync_validator.enabled() {
+            self.submit(submit_infos, fence)?;
+            return Ok(Vec::new());
+        }
+
+        let (waits, signals) =
+            submission_sync_guards(self.handle, submit_infos, fence.map(|f| f.handle()));
+        let hazards = sync_validator.validate_submission(self.handle, &accesses, &waits);
+        self.submit(submit_infos, fence)?;
+        sync_validator.record_submission(self.handle, accesses, signals);
+        Ok(hazards)
+    }
+
+    /// Same as [`Self::submit`] but each batch is a protected submission
+    /// (`vk::ProtectedSubmitInfo`). The command buffers must have been allocated from a
+    /// `PROTECTED` command pool and this queue must be protected capable (see
+    /// [`Self::new_protected`]). Use [`validate_protected_submit`](crate::validate_protected_submit)
+    /// to check the resources used by the batch beforehand. The `vk::ProtectedSubmitInfo` is
+    /// prepended to any existing `p_next` chain of `submit_infos` (e.g. a
+    /// `vk::TimelineSemaphoreSubmitInfo`), which must not already contain one.
+    pub fn submit_protected(
+        &self,
+        submit_infos: &[vk::SubmitInfo<'_>],
+        fence: Option<&Fence>,
+    ) -> VkResult<()> {
+        // one per batch so each can point at that batch's existing chain. collected before
+        // taking pointers so they don't move
+        let protected_submit_infos: Vec<vk::ProtectedSubmitInfo> = submit_infos
+            .iter()
+            .map(|submit_info| {
+                let mut protected_submit_info =
+                    vk::ProtectedSubmitInfo::default().protected_submit(true);
+                protected_submit_info.p_next = submit_info.p_next;
+                protected_submit_info
+            })
+            .collect();
+        let chained_submit_infos: Vec<vk::SubmitInfo> = submit_infos
+            .iter()
+            .zip(&protected_submit_infos)
+            .map(|(submit_info, protected_submit_info)| {
+                let mut submit_info = *submit_info;
+                submit_info.p_next =
+                    (protected_submit_info as *const vk::ProtectedSubmitInfo).cast();
+                submit_info
+            })
+            .collect();
+        self.submit(&chained_submit_infos, fence)
+    }
+
+    /// Allocates a primary command buffer from `command_pool`, begins it with `ONE_TIME_SUBMIT`,
+    /// records commands with `record_commands`, submits it to this queue and blocks until it has
+    /// completed. The command buffer is freed afterwards. Handy for setup-time uploads and layout
+    /// transitions.
+    ///
+    /// The fence waited on is reused across calls.
+    pub fn submit_one_time<F>(
+        &self,
+        command_pool: &Arc<CommandPool>,
+        record_commands: F,
+    ) -> VkResult<()>
+    where
+        F: FnOnce(&CommandBuffer) -> VkResult<()>,
+    {
+        command_pool.debug_assert_queue_family(self);
+        let command_buffer =
+            CommandBuffer::new(command_pool.clone(), vk::CommandBufferLevel::PRIMARY)?;
+
+        let begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        command_buffer.begin(&begin_info)?;
+        record_commands(&command_buffer)?;
+        command_buffer.end()?;
+
+        let pooled_fence = self.fence_pool.lock().ok().and_then(|mut pool| pool.pop());
+        let fence = match pooled_fence {
+            Some(fence) => fence,
+            None => Fence::new_unsignalled(self.device.clone())?,
+        };
+
+        let submit_command_buffers = [command_buffer.handle()];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&submit_command_buffers);
+        self.submit(&[submit_info], Some(&fence))?;
+
+        // the fence isn't returned to the pool on error because it may still be pending
+        fence.wait(u64::MAX)?;
+        fence.reset()?;
+        if let Ok(mut pool) = self.fence_pool.lock() {
+            pool.push(fence);
+        }
+        Ok(())
+    }
+
     pub fn wait_idle(&self) -> Result<(), DeviceError> {
         self.device.queue_wait_idle(self)
     }
 
+    /// Same as [`Self::wait_idle`] but gives up at `deadline`, returning [`WaitStatus::TimedOut`].
+    /// `vkQueueWaitIdle` has no timeout so this submits an empty batch with a fence and waits
+    /// on that instead.
+    ///
+    /// If the wait times out the fence is kept by the queue and the next call waits for it to
+    /// signal before submitting a new batch. Concurrent calls are serialized.
+    pub fn wait_idle_with_timeout(&self, deadline: impl Into<Deadline>) -> VkResult<WaitStatus> {
+        let deadline = deadline.into();
+        let mut pending_idle_fence = self
+            .pending_idle_fence
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(fence) = pending_idle_fence.as_ref() {
+            let wait_status = fence.wait_until(deadline)?;
+            if !wait_status.is_complete() {
+                return Ok(wait_status);
+            }
+            fence.reset()?;
+        }
+
+        let fence = match pending_idle_fence.take() {
+            Some(fence) => fence,
+            None => {
+                let pooled_fence = self.fence_pool.lock().ok().and_then(|mut pool| pool.pop());
+                match pooled_fence {
+                    Some(fence) => fence,
+                    None => Fence::new_unsignalled(self.device.clone())?,
+                }
+            }
+        };
+        self.submit(&[], Some(&fence))?;
+
+        // keep the fence until it has signalled, including when waiting fails
+        let fence = pending_idle_fence.insert(fence);
+        let wait_status = fence.wait_until(deadline)?;
+        if wait_status.is_complete() {
+            fence.reset()?;
+            if let (Some(fence), Ok(mut pool)) = (pending_idle_fence.take(), self.fence_pool.lock())
+            {
+                pool.push(fence);
+            }
+        }
+        Ok(wait_status)
+    }
+
+    /// The last checkpoints reached on this queue, recorded with
+    /// [`CommandBuffer::set_checkpoint`]. Typically called after `ERROR_DEVICE_LOST` to find
+    /// where the device hung (see [`HangTracer`](crate::HangTracer)). Requires
+    /// `VK_NV_device_diagnostic_checkpoints` to be enabled.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkGetQueueCheckpointDataNV.html>
+    pub fn checkpoint_data(&self) -> Vec<Checkpoint> {
+        let checkpoint_fns = self
+            .device
+            .extension_loader::<nv::device_diagnostic_checkpoints::Device>();
+        let checkpoint_count = unsafe { checkpoint_fns.get_queue_checkpoint_data_len(self.handle) };
+        let mut checkpoint_data = vec![vk::CheckpointDataNV::default(); checkpoint_count];
+        unsafe { checkpoint_fns.get_queue_checkpoint_data(self.handle, &mut checkpoint_data) };
+        checkpoint_data
+            .iter()
+            .map(|checkpoint| Checkpoint {
+                stage: checkpoint.stage,
+                marker: checkpoint.p_checkpoint_marker as usize,
+            })
+            .collect()
+    }
+
+    /// Locks the mutex guarding calls which require this queue to be externally synchronized.
+    /// Hold the guard while calling such vulkan functions (e.g. `vkQueueSubmit2`) directly with
+    /// [`Self::handle`]. Bort functions taking a `&Queue` already lock it so don't call them while
+    /// holding the guard.
+    pub fn lock_external_sync(&self) -> MutexGuard<'_, ()> {
+        self.external_sync
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     // Getters
 
     pub fn handle(&self) -> vk::Queue {
@@ -82,6 +343,35 @@ impl Queue {
     pub fn queue_index(&self) -> u32 {
         self.queue_index
     }
+
+    // Helper Functions
+
+    /// Panics if the safety audit is enabled (see [`Device::set_safety_audit`]) and a command
+    /// buffer in `submit_infos` was read from a dropped [`CommandBuffer`].
+    fn debug_assert_command_buffers_not_poisoned(&self, submit_infos: &[vk::SubmitInfo<'_>]) {
+        let handle_audit = self.device.handle_audit();
+        if !handle_audit.is_enabled() {
+            return;
+        }
+        for submit_info in submit_infos {
+            if submit_info.p_command_buffers.is_null() {
+                continue;
+            }
+            let command_buffers = unsafe {
+                std::slice::from_raw_parts(
+                    submit_info.p_command_buffers,
+                    submit_info.command_buffer_count as usize,
+                )
+            };
+            for command_buffer in command_buffers {
+                handle_audit.assert_not_poisoned(
+                    CountedObjectType::CommandBuffer,
+                    command_buffer.as_raw(),
+                    "submit",
+                );
+            }
+        }
+    }
 }
 
 impl DeviceOwned for Queue {
@@ -1,9 +1,13 @@
-use crate::{Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use crate::{
+    aspect_mask_from_format, format_numeric_type, DescriptorSet, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutProperties, Device, DeviceOwned, FormatNumericType, Framebuffer,
+    ALLOCATION_CALLBACK_NONE,
+};
 use ash::{
     prelude::VkResult,
     vk::{self, Handle},
 };
-use std::sync::Arc;
+use std::{error::Error, fmt, sync::Arc};
 
 #[derive(Clone)]
 pub struct RenderPass {
@@ -20,29 +24,79 @@ impl RenderPass {
         attachment_descriptions: Vec<vk::AttachmentDescription>,
         subpasses: Vec<Subpass>,
         subpass_dependencies: Vec<vk::SubpassDependency>,
-    ) -> VkResult<Self> {
-        let subpass_descriptions: Vec<vk::SubpassDescription> = subpasses
+    ) -> Result<Self, RenderPassError> {
+        Self::new_from_properties(
+            device,
+            RenderPassProperties {
+                attachment_descriptions,
+                subpasses,
+                subpass_dependencies,
+                fragment_density_map_attachment: None,
+            },
+        )
+    }
+
+    /// Like `new` but with a `VK_EXT_fragment_density_map` attachment which controls the
+    /// fragment density of every subpass. `fragment_density_map_attachment.attachment` indexes
+    /// `attachment_descriptions` (see [`fragment_density_map_attachment_description`]) and its
+    /// layout should be `FRAGMENT_DENSITY_MAP_OPTIMAL_EXT`.
+    pub fn new_with_fragment_density_map(
+        device: Arc<Device>,
+        attachment_descriptions: Vec<vk::AttachmentDescription>,
+        subpasses: Vec<Subpass>,
+        subpass_dependencies: Vec<vk::SubpassDependency>,
+        fragment_density_map_attachment: vk::AttachmentReference,
+    ) -> Result<Self, RenderPassError> {
+        Self::new_from_properties(
+            device,
+            RenderPassProperties {
+                attachment_descriptions,
+                subpasses,
+                subpass_dependencies,
+                fragment_density_map_attachment: Some(fragment_density_map_attachment),
+            },
+        )
+    }
+
+    /// Returns an error if the subpasses reference attachments inconsistently (see
+    /// [`RenderPassProperties::validate`]).
+    pub fn new_from_properties(
+        device: Arc<Device>,
+        properties: RenderPassProperties,
+    ) -> Result<Self, RenderPassError> {
+        properties.validate()?;
+
+        let subpass_descriptions: Vec<vk::SubpassDescription> = properties
+            .subpasses
             .iter()
             .map(|subpass| subpass.subpass_description())
             .collect();
-        let render_pass_info = vk::RenderPassCreateInfo::default()
-            .attachments(&attachment_descriptions)
+        let mut render_pass_info = vk::RenderPassCreateInfo::default()
+            .attachments(&properties.attachment_descriptions)
             .subpasses(&subpass_descriptions)
-            .dependencies(&subpass_dependencies);
+            .dependencies(&properties.subpass_dependencies);
+
+        let mut fragment_density_map_info =
+            properties
+                .fragment_density_map_attachment
+                .map(|attachment_reference| {
+                    vk::RenderPassFragmentDensityMapCreateInfoEXT::default()
+                        .fragment_density_map_attachment(attachment_reference)
+                });
+        if let Some(fragment_density_map_info) = fragment_density_map_info.as_mut() {
+            render_pass_info = render_pass_info.push_next(fragment_density_map_info);
+        }
 
         let handle = unsafe {
             device
                 .inner()
                 .create_render_pass(&render_pass_info, ALLOCATION_CALLBACK_NONE)
-        }?;
+        }
+        .map_err(RenderPassError::Creation)?;
 
         Ok(Self {
             handle,
-            properties: RenderPassProperties {
-                attachment_descriptions,
-                subpasses,
-                subpass_dependencies,
-            },
+            properties,
             device,
         })
     }
@@ -84,6 +138,142 @@ impl RenderPass {
     pub fn properties(&self) -> &RenderPassProperties {
         &self.properties
     }
+
+    /// Returns a builder for a `vk::RenderPassBeginInfo` targeting `framebuffer`. The render area
+    /// defaults to the whole framebuffer.
+    pub fn begin_info_builder<'a>(
+        &'a self,
+        framebuffer: &Framebuffer,
+    ) -> RenderPassBeginInfoBuilder<'a> {
+        RenderPassBeginInfoBuilder::new(self, framebuffer)
+    }
+
+    /// Descriptor set layout with one `INPUT_ATTACHMENT` binding per input attachment of
+    /// subpass `subpass_index`. Input attachment `i` (the `input_attachment_index` in the shader)
+    /// is assigned binding `first_binding + i`.
+    pub fn input_attachment_layout_properties(
+        &self,
+        subpass_index: usize,
+        first_binding: u32,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> Result<DescriptorSetLayoutProperties, RenderPassError> {
+        let subpass = self.subpass(subpass_index)?;
+        let bindings = (0..subpass.input_attachments.len() as u32)
+            .map(|i| DescriptorSetLayoutBinding {
+                binding: first_binding + i,
+                descriptor_type: vk::DescriptorType::INPUT_ATTACHMENT,
+                descriptor_count: 1,
+                stage_flags,
+                ..Default::default()
+            })
+            .collect();
+        Ok(DescriptorSetLayoutProperties::new_default(bindings))
+    }
+
+    /// Image infos for each input attachment of subpass `subpass_index` using the image views of
+    /// `framebuffer` and the layouts specified in the subpass attachment references. Unused
+    /// attachment references (`vk::ATTACHMENT_UNUSED`) get a null image view.
+    ///
+    /// Pass the result to [`input_attachment_descriptor_writes`] with the same `first_binding`
+    /// used for [`Self::input_attachment_layout_properties`].
+    pub fn input_attachment_image_infos(
+        &self,
+        subpass_index: usize,
+        framebuffer: &Framebuffer,
+    ) -> Result<Vec<vk::DescriptorImageInfo>, RenderPassError> {
+        let subpass = self.subpass(subpass_index)?;
+        let framebuffer_attachments = &framebuffer.properties().attachments;
+
+        subpass
+            .input_attachments
+            .iter()
+            .map(|attachment_reference| {
+                if attachment_reference.attachment == vk::ATTACHMENT_UNUSED {
+                    return Ok(vk::DescriptorImageInfo::default());
+                }
+                let image_view = framebuffer_attachments
+                    .get(attachment_reference.attachment as usize)
+                    .ok_or(RenderPassError::FramebufferAttachmentMissing {
+                        attachment_index: attachment_reference.attachment,
+                        framebuffer_attachment_count: framebuffer_attachments.len(),
+                    })?;
+                Ok(vk::DescriptorImageInfo::default()
+                    .image_view(image_view.handle())
+                    .image_layout(attachment_reference.layout))
+            })
+            .collect()
+    }
+
+    /// Builds a `vk::ClearAttachment` for [`CommandBuffer::clear_attachments`](crate::CommandBuffer::clear_attachments)
+    /// from a render pass attachment index (an index into `attachment_descriptions`) rather than
+    /// the subpass-relative color attachment index.
+    pub fn clear_attachment(
+        &self,
+        subpass_index: usize,
+        attachment_index: u32,
+        clear_value: ClearValue,
+    ) -> Result<vk::ClearAttachment, RenderPassError> {
+        let subpass = self.subpass(subpass_index)?;
+        let format = self
+            .properties
+            .attachment_descriptions
+            .get(attachment_index as usize)
+            .map(|attachment_description| attachment_description.format)
+            .ok_or(RenderPassError::AttachmentIndexOutOfRange {
+                attachment_index,
+                attachment_count: self.properties.attachment_descriptions.len(),
+            })?;
+        let aspect = aspect_mask_from_format(format);
+        let is_depth_stencil =
+            aspect.intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL);
+        if !clear_value.matches_format(format) {
+            return Err(RenderPassError::ClearValueFormatMismatch {
+                attachment_index: attachment_index as usize,
+                format,
+                clear_value,
+            });
+        }
+
+        if is_depth_stencil {
+            let is_subpass_depth_attachment = subpass
+                .depth_attachment
+                .is_some_and(|reference| reference.attachment == attachment_index);
+            if !is_subpass_depth_attachment {
+                return Err(RenderPassError::AttachmentNotInSubpass {
+                    attachment_index,
+                    subpass_index,
+                });
+            }
+            return Ok(vk::ClearAttachment {
+                aspect_mask: aspect,
+                color_attachment: 0,
+                clear_value: clear_value.into(),
+            });
+        }
+
+        let color_attachment = subpass
+            .color_attachments
+            .iter()
+            .position(|reference| reference.attachment == attachment_index)
+            .ok_or(RenderPassError::AttachmentNotInSubpass {
+                attachment_index,
+                subpass_index,
+            })?;
+        Ok(vk::ClearAttachment {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            color_attachment: color_attachment as u32,
+            clear_value: clear_value.into(),
+        })
+    }
+
+    fn subpass(&self, subpass_index: usize) -> Result<&Subpass, RenderPassError> {
+        self.properties.subpasses.get(subpass_index).ok_or(
+            RenderPassError::SubpassIndexOutOfRange {
+                subpass_index,
+                subpass_count: self.properties.subpasses.len(),
+            },
+        )
+    }
 }
 
 impl DeviceOwned for RenderPass {
@@ -113,9 +303,20 @@ pub struct RenderPassProperties {
     pub attachment_descriptions: Vec<vk::AttachmentDescription>,
     pub subpasses: Vec<Subpass>,
     pub subpass_dependencies: Vec<vk::SubpassDependency>,
+    /// `VK_EXT_fragment_density_map` attachment. Chained to the render pass create info if
+    /// `Some`.
+    pub fragment_density_map_attachment: Option<vk::AttachmentReference>,
 }
 
 impl RenderPassProperties {
+    /// Checks each subpass against `attachment_descriptions`. See [`Subpass::validate`].
+    pub fn validate(&self) -> Result<(), RenderPassError> {
+        for (subpass_index, subpass) in self.subpasses.iter().enumerate() {
+            subpass.validate(subpass_index, &self.attachment_descriptions)?;
+        }
+        Ok(())
+    }
+
     /// # Safety
     ///
     /// For each subpass:
@@ -123,6 +324,10 @@ impl RenderPassProperties {
     ///   `subpass_description.color_attachment_count` many elements.
     /// - if `subpass_description.p_input_attachments` is not null it must point to an array with
     ///   `subpass_description.input_attachment_count` many elements.
+    /// - the same for the resolve and preserve attachment arrays (see
+    ///   [`Subpass::from_subpass_description`]).
+    ///
+    /// The `p_next` chain must contain valid pointers.
     pub unsafe fn from_create_info(create_info: &vk::RenderPassCreateInfo) -> Self {
         let mut attachment_descriptions = Vec::<vk::AttachmentDescription>::new();
         if !create_info.p_attachments.is_null() {
@@ -149,19 +354,42 @@ impl RenderPassProperties {
             }
         }
 
+        let mut fragment_density_map_attachment = None;
+        let mut next = create_info.p_next as *const vk::BaseInStructure;
+        while let Some(structure) = unsafe { next.as_ref() } {
+            if structure.s_type
+                == vk::StructureType::RENDER_PASS_FRAGMENT_DENSITY_MAP_CREATE_INFO_EXT
+            {
+                let fragment_density_map_info =
+                    unsafe { &*(next as *const vk::RenderPassFragmentDensityMapCreateInfoEXT) };
+                fragment_density_map_attachment =
+                    Some(fragment_density_map_info.fragment_density_map_attachment);
+            }
+            next = structure.p_next;
+        }
+
         Self {
             attachment_descriptions,
             subpasses,
             subpass_dependencies,
+            fragment_density_map_attachment,
         }
     }
 }
 
+/// Attachment references of a graphics subpass. `attachment` members index the render pass
+/// `attachment_descriptions` (or are `vk::ATTACHMENT_UNUSED`).
 #[derive(Debug, Default, Clone)]
 pub struct Subpass {
     pub color_attachments: Vec<vk::AttachmentReference>,
     pub depth_attachment: Option<vk::AttachmentReference>,
     pub input_attachments: Vec<vk::AttachmentReference>,
+    /// Either empty or one per color attachment. Multisampled color attachment `i` is resolved
+    /// into `resolve_attachments[i]` at the end of the subpass.
+    pub resolve_attachments: Vec<vk::AttachmentReference>,
+    /// Attachments which aren't used by this subpass but whose contents must be preserved
+    /// through it for a later subpass.
+    pub preserve_attachments: Vec<u32>,
 }
 
 impl Subpass {
@@ -174,15 +402,124 @@ impl Subpass {
             color_attachments: color_attachments.into(),
             depth_attachment,
             input_attachments: input_attachments.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Resolve targets for the color attachments. Must be the same length as the color
+    /// attachments, use `vk::ATTACHMENT_UNUSED` for color attachments that aren't resolved.
+    pub fn with_resolve_attachments(
+        mut self,
+        resolve_attachments: &[vk::AttachmentReference],
+    ) -> Self {
+        self.resolve_attachments = resolve_attachments.into();
+        self
+    }
+
+    pub fn with_preserve_attachments(mut self, preserve_attachments: &[u32]) -> Self {
+        self.preserve_attachments = preserve_attachments.into();
+        self
+    }
+
+    /// Every attachment index referenced by this subpass (excluding preserve attachments and
+    /// `vk::ATTACHMENT_UNUSED` references).
+    pub fn used_attachment_indices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.color_attachments
+            .iter()
+            .chain(&self.input_attachments)
+            .chain(&self.resolve_attachments)
+            .chain(&self.depth_attachment)
+            .map(|reference| reference.attachment)
+            .filter(|&attachment_index| attachment_index != vk::ATTACHMENT_UNUSED)
+    }
+
+    /// Checks the attachment references of subpass `subpass_index` against the render pass
+    /// `attachment_descriptions`:
+    /// - every reference is `vk::ATTACHMENT_UNUSED` or in range.
+    /// - there are no resolve attachments or exactly one per color attachment.
+    /// - resolved color attachments are multisampled and resolve attachments aren't.
+    /// - preserve attachments are in range and not otherwise used by the subpass.
+    pub fn validate(
+        &self,
+        subpass_index: usize,
+        attachment_descriptions: &[vk::AttachmentDescription],
+    ) -> Result<(), RenderPassError> {
+        let attachment_count = attachment_descriptions.len();
+        let check_in_range = |attachment_index: u32| {
+            if attachment_index as usize >= attachment_count {
+                return Err(RenderPassError::SubpassAttachmentOutOfRange {
+                    subpass_index,
+                    attachment_index,
+                    attachment_count,
+                });
+            }
+            Ok(())
+        };
+
+        for attachment_index in self.used_attachment_indices() {
+            check_in_range(attachment_index)?;
+        }
+
+        if !self.resolve_attachments.is_empty() {
+            if self.resolve_attachments.len() != self.color_attachments.len() {
+                return Err(RenderPassError::ResolveAttachmentCountMismatch {
+                    subpass_index,
+                    resolve_attachment_count: self.resolve_attachments.len(),
+                    color_attachment_count: self.color_attachments.len(),
+                });
+            }
+
+            for (color_reference, resolve_reference) in
+                self.color_attachments.iter().zip(&self.resolve_attachments)
+            {
+                if resolve_reference.attachment == vk::ATTACHMENT_UNUSED {
+                    continue;
+                }
+                let color_samples = (color_reference.attachment != vk::ATTACHMENT_UNUSED)
+                    .then(|| attachment_descriptions[color_reference.attachment as usize].samples);
+                let resolve_samples =
+                    attachment_descriptions[resolve_reference.attachment as usize].samples;
+                let color_is_multisampled =
+                    color_samples.is_some_and(|samples| samples != vk::SampleCountFlags::TYPE_1);
+                if !color_is_multisampled || resolve_samples != vk::SampleCountFlags::TYPE_1 {
+                    return Err(RenderPassError::ResolveSampleCountMismatch {
+                        subpass_index,
+                        color_attachment_index: color_reference.attachment,
+                        resolve_attachment_index: resolve_reference.attachment,
+                    });
+                }
+            }
         }
+
+        for &attachment_index in &self.preserve_attachments {
+            if attachment_index == vk::ATTACHMENT_UNUSED {
+                return Err(RenderPassError::PreserveAttachmentUnused { subpass_index });
+            }
+            check_in_range(attachment_index)?;
+            if self
+                .used_attachment_indices()
+                .any(|used_index| used_index == attachment_index)
+            {
+                return Err(RenderPassError::PreserveAttachmentInUse {
+                    subpass_index,
+                    attachment_index,
+                });
+            }
+        }
+
+        Ok(())
     }
 
     /// # Safety
     ///
     /// - if `subpass_description.p_color_attachments` is not null it must point to an array with
     ///   `subpass_description.color_attachment_count` many elements.
+    /// - if `subpass_description.p_resolve_attachments` is not null it must point to an array
+    ///   with `subpass_description.color_attachment_count` many elements.
     /// - if `subpass_description.p_input_attachments` is not null it must point to an array with
     ///   `subpass_description.input_attachment_count` many elements.
+    /// - if `subpass_description.p_preserve_attachments` is not null it must point to an array
+    ///   with `subpass_description.preserve_attachment_count` many elements.
     pub unsafe fn from_subpass_description(subpass_description: &vk::SubpassDescription) -> Self {
         let mut color_attachments = Vec::<vk::AttachmentReference>::new();
         if !subpass_description.p_color_attachments.is_null() {
@@ -210,27 +547,522 @@ impl Subpass {
             }
         }
 
+        let mut resolve_attachments = Vec::<vk::AttachmentReference>::new();
+        if !subpass_description.p_resolve_attachments.is_null() {
+            for i in 0..subpass_description.color_attachment_count {
+                let vk_attachment =
+                    unsafe { *subpass_description.p_resolve_attachments.offset(i as isize) };
+                resolve_attachments.push(vk_attachment);
+            }
+        }
+
+        let mut preserve_attachments = Vec::<u32>::new();
+        if !subpass_description.p_preserve_attachments.is_null() {
+            for i in 0..subpass_description.preserve_attachment_count {
+                let attachment_index = unsafe {
+                    *subpass_description
+                        .p_preserve_attachments
+                        .offset(i as isize)
+                };
+                preserve_attachments.push(attachment_index);
+            }
+        }
+
         Self {
             color_attachments,
             depth_attachment,
             input_attachments,
+            resolve_attachments,
+            preserve_attachments,
         }
     }
 
-    pub fn subpass_description(&self) -> vk::SubpassDescription {
+    pub fn subpass_description(&self) -> vk::SubpassDescription<'_> {
         let mut subpass_description =
             vk::SubpassDescription::default().pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
 
         if self.color_attachments.len() > 0 {
             subpass_description = subpass_description.color_attachments(&self.color_attachments);
         }
+        // note: the resolve attachments array is read with `color_attachment_count` elements so
+        // it's left null if the lengths don't match (see `validate`). the ash setter isn't used
+        // because it overwrites `color_attachment_count`.
+        if self.resolve_attachments.len() > 0
+            && self.resolve_attachments.len() == self.color_attachments.len()
+        {
+            subpass_description.p_resolve_attachments = self.resolve_attachments.as_ptr();
+        }
         if self.input_attachments.len() > 0 {
             subpass_description = subpass_description.input_attachments(&self.input_attachments);
         }
         if let Some(depth_attachment) = &self.depth_attachment {
             subpass_description = subpass_description.depth_stencil_attachment(depth_attachment);
         }
+        if self.preserve_attachments.len() > 0 {
+            subpass_description =
+                subpass_description.preserve_attachments(&self.preserve_attachments);
+        }
 
         subpass_description
     }
 }
+
+// Clear Values
+
+/// Typed alternative to the `vk::ClearValue` union. Use the variant that matches the attachment
+/// format: `ColorF32` for float/unorm/snorm color formats, `ColorU32` for uint color formats,
+/// `ColorI32` for sint color formats and `DepthStencil` for depth and/or stencil formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearValue {
+    ColorF32([f32; 4]),
+    ColorU32([u32; 4]),
+    ColorI32([i32; 4]),
+    DepthStencil { depth: f32, stencil: u32 },
+}
+
+impl ClearValue {
+    #[inline]
+    pub fn is_color(&self) -> bool {
+        !self.is_depth_stencil()
+    }
+
+    #[inline]
+    pub fn is_depth_stencil(&self) -> bool {
+        matches!(self, Self::DepthStencil { .. })
+    }
+
+    /// Whether this is the variant to clear an attachment with `format` with.
+    pub fn matches_format(&self, format: vk::Format) -> bool {
+        let aspect = aspect_mask_from_format(format);
+        if aspect.intersects(vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL) {
+            return self.is_depth_stencil();
+        }
+        let numeric_type = format_numeric_type(format);
+        match self {
+            Self::ColorF32(_) => numeric_type == FormatNumericType::Float,
+            Self::ColorU32(_) => numeric_type == FormatNumericType::UInt,
+            Self::ColorI32(_) => numeric_type == FormatNumericType::SInt,
+            Self::DepthStencil { .. } => false,
+        }
+    }
+}
+
+impl Default for ClearValue {
+    fn default() -> Self {
+        Self::ColorF32([0.; 4])
+    }
+}
+
+impl From<ClearValue> for vk::ClearValue {
+    fn from(value: ClearValue) -> Self {
+        match value {
+            ClearValue::ColorF32(float32) => vk::ClearValue {
+                color: vk::ClearColorValue { float32 },
+            },
+            ClearValue::ColorU32(uint32) => vk::ClearValue {
+                color: vk::ClearColorValue { uint32 },
+            },
+            ClearValue::ColorI32(int32) => vk::ClearValue {
+                color: vk::ClearColorValue { int32 },
+            },
+            ClearValue::DepthStencil { depth, stencil } => vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue { depth, stencil },
+            },
+        }
+    }
+}
+
+// Render Pass Begin Info
+
+/// Pairs clear values with render pass attachments by index. [`Self::build`] checks that every
+/// attachment with a `CLEAR` load op has a clear value and that each clear value variant matches
+/// its attachment format.
+#[derive(Clone)]
+pub struct RenderPassBeginInfoBuilder<'a> {
+    render_pass: &'a RenderPass,
+    framebuffer_handle: vk::Framebuffer,
+    render_area: vk::Rect2D,
+    clear_values: Vec<Option<ClearValue>>,
+    vk_clear_values: Vec<vk::ClearValue>,
+}
+
+impl<'a> RenderPassBeginInfoBuilder<'a> {
+    pub fn new(render_pass: &'a RenderPass, framebuffer: &Framebuffer) -> Self {
+        let attachment_count = render_pass.properties().attachment_descriptions.len();
+        Self {
+            render_pass,
+            framebuffer_handle: framebuffer.handle(),
+            render_area: framebuffer.whole_rect(),
+            clear_values: vec![None; attachment_count],
+            vk_clear_values: Vec::new(),
+        }
+    }
+
+    pub fn render_area(mut self, render_area: vk::Rect2D) -> Self {
+        self.render_area = render_area;
+        self
+    }
+
+    /// Sets the clear value for the attachment at `attachment_index`. Out of range indices are
+    /// reported by [`Self::build`].
+    pub fn clear_value(mut self, attachment_index: usize, clear_value: ClearValue) -> Self {
+        if attachment_index >= self.clear_values.len() {
+            self.clear_values.resize(attachment_index + 1, None);
+        }
+        self.clear_values[attachment_index] = Some(clear_value);
+        self
+    }
+
+    /// Sets clear values for attachments `0..clear_values.len()`.
+    pub fn clear_values(mut self, clear_values: &[ClearValue]) -> Self {
+        for (attachment_index, &clear_value) in clear_values.iter().enumerate() {
+            self = self.clear_value(attachment_index, clear_value);
+        }
+        self
+    }
+
+    /// Validates the clear values against the render pass attachments and returns the begin info.
+    /// Attachments that don't need clearing and have no clear value get a zeroed entry.
+    pub fn build(&mut self) -> Result<vk::RenderPassBeginInfo<'_>, RenderPassError> {
+        let attachment_descriptions = &self.render_pass.properties().attachment_descriptions;
+
+        if self.clear_values.len() > attachment_descriptions.len() {
+            return Err(RenderPassError::ClearValueCountMismatch {
+                clear_value_count: self.clear_values.len(),
+                attachment_count: attachment_descriptions.len(),
+            });
+        }
+
+        self.vk_clear_values.clear();
+        for (attachment_index, attachment_description) in attachment_descriptions.iter().enumerate()
+        {
+            let aspect = aspect_mask_from_format(attachment_description.format);
+            let needs_clear = attachment_description.load_op == vk::AttachmentLoadOp::CLEAR
+                || (aspect.contains(vk::ImageAspectFlags::STENCIL)
+                    && attachment_description.stencil_load_op == vk::AttachmentLoadOp::CLEAR);
+
+            match self.clear_values[attachment_index] {
+                Some(clear_value) => {
+                    if !clear_value.matches_format(attachment_description.format) {
+                        return Err(RenderPassError::ClearValueFormatMismatch {
+                            attachment_index,
+                            format: attachment_description.format,
+                            clear_value,
+                        });
+                    }
+                    self.vk_clear_values.push(clear_value.into());
+                }
+                None => {
+                    if needs_clear {
+                        return Err(RenderPassError::MissingClearValue { attachment_index });
+                    }
+                    self.vk_clear_values.push(vk::ClearValue::default());
+                }
+            }
+        }
+
+        Ok(vk::RenderPassBeginInfo::default()
+            .render_pass(self.render_pass.handle())
+            .framebuffer(self.framebuffer_handle)
+            .render_area(self.render_area)
+            .clear_values(&self.vk_clear_values))
+    }
+}
+
+/// A `vk::ClearRect` covering `rect` in the first array layer.
+pub fn clear_rect_2d(rect: vk::Rect2D) -> vk::ClearRect {
+    vk::ClearRect {
+        rect,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+// Input Attachments
+
+/// One `INPUT_ATTACHMENT` write per entry of `image_infos` (see
+/// [`RenderPass::input_attachment_image_infos`]) starting at `first_binding`. Unused attachments
+/// (null image views) are skipped. Submit with [`Device::update_descriptor_sets`].
+pub fn input_attachment_descriptor_writes<'a>(
+    descriptor_set: &DescriptorSet,
+    first_binding: u32,
+    image_infos: &'a [vk::DescriptorImageInfo],
+) -> Vec<vk::WriteDescriptorSet<'a>> {
+    image_infos
+        .iter()
+        .enumerate()
+        .filter(|(_, image_info)| image_info.image_view != vk::ImageView::null())
+        .map(|(i, image_info)| {
+            vk::WriteDescriptorSet::default()
+                .dst_set(descriptor_set.handle())
+                .dst_binding(first_binding + i as u32)
+                .descriptor_type(vk::DescriptorType::INPUT_ATTACHMENT)
+                .image_info(std::slice::from_ref(image_info))
+        })
+        .collect()
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum RenderPassError {
+    Creation(vk::Result),
+    ClearValueCountMismatch {
+        clear_value_count: usize,
+        attachment_count: usize,
+    },
+    MissingClearValue {
+        attachment_index: usize,
+    },
+    ClearValueFormatMismatch {
+        attachment_index: usize,
+        format: vk::Format,
+        clear_value: ClearValue,
+    },
+    AttachmentIndexOutOfRange {
+        attachment_index: u32,
+        attachment_count: usize,
+    },
+    AttachmentNotInSubpass {
+        attachment_index: u32,
+        subpass_index: usize,
+    },
+    SubpassIndexOutOfRange {
+        subpass_index: usize,
+        subpass_count: usize,
+    },
+    SubpassAttachmentOutOfRange {
+        subpass_index: usize,
+        attachment_index: u32,
+        attachment_count: usize,
+    },
+    ResolveAttachmentCountMismatch {
+        subpass_index: usize,
+        resolve_attachment_count: usize,
+        color_attachment_count: usize,
+    },
+    /// Resolving requires a multisampled color attachment and a single sampled resolve
+    /// attachment.
+    ResolveSampleCountMismatch {
+        subpass_index: usize,
+        color_attachment_index: u32,
+        resolve_attachment_index: u32,
+    },
+    PreserveAttachmentUnused {
+        subpass_index: usize,
+    },
+    PreserveAttachmentInUse {
+        subpass_index: usize,
+        attachment_index: u32,
+    },
+    /// The framebuffer doesn't have an image view for an attachment referenced by the subpass.
+    /// Note that framebuffers created with `new_from_create_info` don't store their attachments.
+    FramebufferAttachmentMissing {
+        attachment_index: u32,
+        framebuffer_attachment_count: usize,
+    },
+}
+
+impl fmt::Display for RenderPassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Creation(e) => write!(f, "failed to create render pass: {}", e),
+            Self::ClearValueCountMismatch {
+                clear_value_count,
+                attachment_count,
+            } => write!(
+                f,
+                "{} clear values were provided but the render pass only has {} attachments",
+                clear_value_count, attachment_count
+            ),
+            Self::MissingClearValue { attachment_index } => write!(
+                f,
+                "attachment {} has a CLEAR load op but no clear value was provided",
+                attachment_index
+            ),
+            Self::ClearValueFormatMismatch {
+                attachment_index,
+                format,
+                clear_value,
+            } => write!(
+                f,
+                "clear value {:?} doesn't match the format {:?} of attachment {}",
+                clear_value, format, attachment_index
+            ),
+            Self::AttachmentIndexOutOfRange {
+                attachment_index,
+                attachment_count,
+            } => write!(
+                f,
+                "attachment index {} is out of range for a render pass with {} attachments",
+                attachment_index, attachment_count
+            ),
+            Self::AttachmentNotInSubpass {
+                attachment_index,
+                subpass_index,
+            } => write!(
+                f,
+                "attachment {} isn't used as a color or depth/stencil attachment by subpass {}",
+                attachment_index, subpass_index
+            ),
+            Self::SubpassIndexOutOfRange {
+                subpass_index,
+                subpass_count,
+            } => write!(
+                f,
+                "subpass index {} is out of range for a render pass with {} subpasses",
+                subpass_index, subpass_count
+            ),
+            Self::SubpassAttachmentOutOfRange {
+                subpass_index,
+                attachment_index,
+                attachment_count,
+            } => write!(
+                f,
+                "subpass {} references attachment {} but the render pass only has {} attachments",
+                subpass_index, attachment_index, attachment_count
+            ),
+            Self::ResolveAttachmentCountMismatch {
+                subpass_index,
+                resolve_attachment_count,
+                color_attachment_count,
+            } => write!(
+                f,
+                "subpass {} has {} resolve attachments but {} color attachments",
+                subpass_index, resolve_attachment_count, color_attachment_count
+            ),
+            Self::ResolveSampleCountMismatch {
+                subpass_index,
+                color_attachment_index,
+                resolve_attachment_index,
+            } => write!(
+                f,
+                "subpass {} resolves attachment {} into attachment {} but the source must be multisampled and the destination single sampled",
+                subpass_index, color_attachment_index, resolve_attachment_index
+            ),
+            Self::PreserveAttachmentUnused { subpass_index } => write!(
+                f,
+                "subpass {} has a preserve attachment of ATTACHMENT_UNUSED",
+                subpass_index
+            ),
+            Self::PreserveAttachmentInUse {
+                subpass_index,
+                attachment_index,
+            } => write!(
+                f,
+                "subpass {} preserves attachment {} which it also uses",
+                subpass_index, attachment_index
+            ),
+            Self::FramebufferAttachmentMissing {
+                attachment_index,
+                framebuffer_attachment_count,
+            } => write!(
+                f,
+                "attachment {} is referenced by the subpass but the framebuffer only has {} attachments",
+                attachment_index, framebuffer_attachment_count
+            ),
+        }
+    }
+}
+
+impl Error for RenderPassError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Creation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn clear_value_writes_matching_union_member() {
+    let depth_stencil: vk::ClearValue = ClearValue::DepthStencil {
+        depth: 1.,
+        stencil: 7,
+    }
+    .into();
+    let depth_stencil = unsafe { depth_stencil.depth_stencil };
+    assert_eq!(depth_stencil.depth, 1.);
+    assert_eq!(depth_stencil.stencil, 7);
+
+    let color: vk::ClearValue = ClearValue::ColorU32([1, 2, 3, 4]).into();
+    assert_eq!(unsafe { color.color.uint32 }, [1, 2, 3, 4]);
+
+    let color = ClearValue::ColorF32([0.; 4]);
+    assert!(color.matches_format(vk::Format::R8G8B8A8_UNORM));
+    assert!(!color.matches_format(vk::Format::R32_UINT));
+    assert!(!color.matches_format(vk::Format::D32_SFLOAT));
+    assert!(ClearValue::ColorU32([0; 4]).matches_format(vk::Format::R32_UINT));
+    assert!(!ClearValue::ColorU32([0; 4]).matches_format(vk::Format::R16G16_SINT));
+    assert!(ClearValue::ColorI32([0; 4]).matches_format(vk::Format::R16G16_SINT));
+}
+
+#[test]
+fn subpass_validation_checks_resolve_and_preserve_attachments() {
+    let attachment = |samples| vk::AttachmentDescription {
+        format: vk::Format::R8G8B8A8_UNORM,
+        samples,
+        ..Default::default()
+    };
+    let reference = |attachment| vk::AttachmentReference {
+        attachment,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    let attachment_descriptions = [
+        attachment(vk::SampleCountFlags::TYPE_4),
+        attachment(vk::SampleCountFlags::TYPE_1),
+        attachment(vk::SampleCountFlags::TYPE_1),
+    ];
+
+    let resolving_subpass =
+        Subpass::new(&[reference(0)], None, &[]).with_resolve_attachments(&[reference(1)]);
+    assert!(resolving_subpass
+        .validate(0, &attachment_descriptions)
+        .is_ok());
+    let description = resolving_subpass.subpass_description();
+    assert_eq!(description.color_attachment_count, 1);
+    let round_trip = unsafe { Subpass::from_subpass_description(&description) };
+    assert_eq!(round_trip.resolve_attachments[0].attachment, 1);
+
+    let single_sampled_source =
+        Subpass::new(&[reference(2)], None, &[]).with_resolve_attachments(&[reference(1)]);
+    assert!(matches!(
+        single_sampled_source.validate(0, &attachment_descriptions),
+        Err(RenderPassError::ResolveSampleCountMismatch { .. })
+    ));
+
+    let count_mismatch = Subpass::new(&[reference(0)], None, &[])
+        .with_resolve_attachments(&[reference(1), reference(2)]);
+    assert!(matches!(
+        count_mismatch.validate(0, &attachment_descriptions),
+        Err(RenderPassError::ResolveAttachmentCountMismatch { .. })
+    ));
+    let mismatched_description = count_mismatch.subpass_description();
+    assert_eq!(mismatched_description.color_attachment_count, 1);
+    assert!(mismatched_description.p_resolve_attachments.is_null());
+
+    let preserving_subpass =
+        Subpass::new(&[reference(1)], None, &[]).with_preserve_attachments(&[2]);
+    assert!(preserving_subpass
+        .validate(1, &attachment_descriptions)
+        .is_ok());
+    let preserve_in_use = Subpass::new(&[reference(1)], None, &[]).with_preserve_attachments(&[1]);
+    assert!(matches!(
+        preserve_in_use.validate(1, &attachment_descriptions),
+        Err(RenderPassError::PreserveAttachmentInUse {
+            attachment_index: 1,
+            ..
+        })
+    ));
+
+    let out_of_range = Subpass::new(&[reference(3)], None, &[]);
+    assert!(matches!(
+        out_of_range.validate(2, &attachment_descriptions),
+        Err(RenderPassError::SubpassAttachmentOutOfRange {
+            subpass_index: 2,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,167 @@
+use crate::{
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, CommandBuffer, CommandPool,
+    DeviceOwned, Fence, MemoryError, Queue,
+};
+use ash::vk;
+use std::{error, fmt, sync::Arc};
+
+/// Records commands into a new primary command buffer, submits them to `queue` and waits for
+/// completion. Used by the `new_zeroed`/`new_with_data` constructors of `Buffer` and `Image`.
+pub(crate) fn record_submit_and_wait(
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+    record_commands: impl FnOnce(&CommandBuffer),
+) -> Result<(), ResourceInitError> {
+    command_pool.debug_assert_queue_family(queue);
+    let command_buffer = CommandBuffer::new(command_pool.clone(), vk::CommandBufferLevel::PRIMARY)
+        .map_err(ResourceInitError::CommandBufferAllocation)?;
+
+    let begin_info =
+        vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+    command_buffer
+        .begin(&begin_info)
+        .map_err(ResourceInitError::CommandBufferRecording)?;
+
+    record_commands(&command_buffer);
+
+    command_buffer
+        .end()
+        .map_err(ResourceInitError::CommandBufferRecording)?;
+
+    let fence =
+        Fence::new_unsignalled(queue.device().clone()).map_err(ResourceInitError::FenceCreation)?;
+
+    let submit_command_buffers = [command_buffer.handle()];
+    let submit_info = vk::SubmitInfo::default().command_buffers(&submit_command_buffers);
+    queue
+        .submit(&[submit_info], Some(&fence))
+        .map_err(ResourceInitError::Submission)?;
+
+    fence.wait(u64::MAX).map_err(ResourceInitError::FenceWait)
+}
+
+/// Creates a host visible buffer with `TRANSFER_SRC` usage containing `data`.
+pub(crate) fn new_staging_buffer(
+    alloc_access: Arc<dyn AllocatorAccess>,
+    data: &[u8],
+) -> Result<Buffer, ResourceInitError> {
+    let mut staging_buffer = Buffer::new_staging(alloc_access, data.len() as vk::DeviceSize)
+        .map_err(ResourceInitError::StagingBufferCreation)?;
+
+    staging_buffer
+        .memory_allocation_mut()
+        .write_bytes(data, 0)
+        .map_err(ResourceInitError::StagingBufferWrite)?;
+
+    Ok(staging_buffer)
+}
+
+/// Makes transfer writes available and visible to all subsequent commands.
+pub(crate) fn transfer_write_memory_barrier() -> vk::MemoryBarrier<'static> {
+    vk::MemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum ResourceInitError {
+    Creation(AllocationError),
+    HostWrite(MemoryError),
+    StagingBufferCreation(AllocationError),
+    StagingBufferWrite(MemoryError),
+    CommandBufferAllocation(vk::Result),
+    CommandBufferRecording(vk::Result),
+    FenceCreation(vk::Result),
+    Submission(vk::Result),
+    FenceWait(vk::Result),
+    DataSizeTooBig {
+        data_size: usize,
+        resource_size: vk::DeviceSize,
+    },
+    DataSizeMismatch {
+        data_size: usize,
+        expected_size: vk::DeviceSize,
+    },
+    FormatNotCompressed(vk::Format),
+    /// The texel size of the format isn't known so the upload size can't be checked. See
+    /// [`format_texel_size`](crate::format_texel_size).
+    UnknownTexelSize(vk::Format),
+    /// Buffer/image copies of combined depth/stencil formats need a copy per aspect.
+    CombinedDepthStencilFormat(vk::Format),
+}
+
+impl fmt::Display for ResourceInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Creation(e) => write!(f, "failed to create resource: {}", e),
+            Self::HostWrite(e) => write!(f, "failed to write initial data to resource: {}", e),
+            Self::StagingBufferCreation(e) => {
+                write!(f, "failed to create staging buffer: {}", e)
+            }
+            Self::StagingBufferWrite(e) => write!(f, "failed to write to staging buffer: {}", e),
+            Self::CommandBufferAllocation(e) => {
+                write!(f, "failed to allocate initialization command buffer: {}", e)
+            }
+            Self::CommandBufferRecording(e) => {
+                write!(f, "failed to record initialization command buffer: {}", e)
+            }
+            Self::FenceCreation(e) => write!(f, "failed to create fence: {}", e),
+            Self::Submission(e) => write!(f, "failed to submit initialization commands: {}", e),
+            Self::FenceWait(e) => write!(
+                f,
+                "failed to wait for initialization commands to complete: {}",
+                e
+            ),
+            Self::DataSizeTooBig {
+                data_size,
+                resource_size,
+            } => write!(
+                f,
+                "initial data size {} is larger than the resource size {}",
+                data_size, resource_size
+            ),
+            Self::DataSizeMismatch {
+                data_size,
+                expected_size,
+            } => write!(
+                f,
+                "initial data size {} doesn't match the expected size {}",
+                data_size, expected_size
+            ),
+            Self::FormatNotCompressed(format) => {
+                write!(f, "format {:?} isn't a block compressed format", format)
+            }
+            Self::UnknownTexelSize(format) => {
+                write!(f, "texel size of format {:?} is unknown", format)
+            }
+            Self::CombinedDepthStencilFormat(format) => write!(
+                f,
+                "can't upload to combined depth/stencil format {:?} with a single copy",
+                format
+            ),
+        }
+    }
+}
+
+impl error::Error for ResourceInitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Creation(e) => Some(e),
+            Self::HostWrite(e) => Some(e),
+            Self::StagingBufferCreation(e) => Some(e),
+            Self::StagingBufferWrite(e) => Some(e),
+            Self::CommandBufferAllocation(e) => Some(e),
+            Self::CommandBufferRecording(e) => Some(e),
+            Self::FenceCreation(e) => Some(e),
+            Self::Submission(e) => Some(e),
+            Self::FenceWait(e) => Some(e),
+            Self::DataSizeTooBig { .. } => None,
+            Self::DataSizeMismatch { .. } => None,
+            Self::FormatNotCompressed(_) => None,
+            Self::UnknownTexelSize(_) => None,
+            Self::CombinedDepthStencilFormat(_) => None,
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use crate::{Buffer, DeferDrop, DescriptorSet, PipelineAccess};
+use std::{error, fmt, hash, marker::PhantomData, sync::Arc};
+
+/// A copyable reference to a resource in a [`RegistrySlots`] (usually part of a
+/// [`ResourceRegistry`]). Handles are plain data so they can be stored in e.g. material or
+/// scene structs instead of `Arc`s.
+///
+/// The slot index is paired with a generation which changes when the resource is removed, so a
+/// handle to a removed resource is detected as stale instead of silently referring to whatever
+/// reuses the slot.
+pub struct RegistryHandle<T: ?Sized> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+pub type PipelineHandle = RegistryHandle<dyn PipelineAccess>;
+pub type DescriptorSetHandle = RegistryHandle<DescriptorSet>;
+pub type BufferHandle = RegistryHandle<Buffer>;
+
+impl<T: ?Sized> RegistryHandle<T> {
+    /// Packs the index and generation into a `u64` e.g. to store the handle in a GPU buffer or
+    /// serialize it.
+    #[inline]
+    pub fn to_bits(self) -> u64 {
+        ((self.generation as u64) << 32) | self.index as u64
+    }
+
+    /// Inverse of [`Self::to_bits`]. Stale or made up handles are rejected when used.
+    #[inline]
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+            _marker: PhantomData,
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    #[inline]
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+// manual impls so `T` doesn't need to implement these traits
+
+impl<T: ?Sized> Clone for RegistryHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for RegistryHandle<T> {}
+
+impl<T: ?Sized> PartialEq for RegistryHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T: ?Sized> Eq for RegistryHandle<T> {}
+
+impl<T: ?Sized> hash::Hash for RegistryHandle<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for RegistryHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryHandle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+struct Slot<T: ?Sized> {
+    generation: u32,
+    /// `None` when the slot is free.
+    resource: Option<Arc<T>>,
+}
+
+/// Generational slot storage of one resource type. Freed slots are reused by later inserts with
+/// an incremented generation.
+pub struct RegistrySlots<T: ?Sized> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<u32>,
+    len: usize,
+}
+
+impl<T: ?Sized> RegistrySlots<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, resource: Arc<T>) -> RegistryHandle<T> {
+        self.len += 1;
+        let index = match self.free_indices.pop() {
+            Some(index) => {
+                self.slots[index as usize].resource = Some(resource);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    resource: Some(resource),
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        RegistryHandle {
+            index,
+            generation: self.slots[index as usize].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// `None` if `handle` is stale.
+    pub fn get(&self, handle: RegistryHandle<T>) -> Option<&Arc<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.resource.as_ref())
+    }
+
+    #[inline]
+    pub fn contains(&self, handle: RegistryHandle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Swaps the resource referenced by `handle` for `resource` (e.g. a pipeline recreated after
+    /// a shader reload). Existing copies of `handle` stay valid and refer to the new resource.
+    /// Returns the previous resource.
+    pub fn replace(
+        &mut self,
+        handle: RegistryHandle<T>,
+        resource: Arc<T>,
+    ) -> Result<Arc<T>, RegistryError> {
+        let slot = self.live_slot_mut(handle)?;
+        Ok(slot.resource.replace(resource).expect("live slot"))
+    }
+
+    /// Removes the resource, invalidating all copies of `handle`.
+    pub fn remove(&mut self, handle: RegistryHandle<T>) -> Result<Arc<T>, RegistryError> {
+        let slot = self.live_slot_mut(handle)?;
+        let resource = slot.resource.take().expect("live slot");
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_indices.push(handle.index);
+        self.len -= 1;
+        Ok(resource)
+    }
+
+    /// Live handles and their resources.
+    pub fn iter(&self) -> impl Iterator<Item = (RegistryHandle<T>, &Arc<T>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let resource = slot.resource.as_ref()?;
+            let handle = RegistryHandle {
+                index: index as u32,
+                generation: slot.generation,
+                _marker: PhantomData,
+            };
+            Some((handle, resource))
+        })
+    }
+
+    /// Removes all resources, invalidating every handle.
+    pub fn clear(&mut self) -> Vec<Arc<T>> {
+        let mut removed = Vec::with_capacity(self.len);
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if let Some(resource) = slot.resource.take() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_indices.push(index as u32);
+                removed.push(resource);
+            }
+        }
+        self.len = 0;
+        removed
+    }
+
+    fn live_slot_mut(&mut self, handle: RegistryHandle<T>) -> Result<&mut Slot<T>, RegistryError> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.resource.is_some())
+            .ok_or(RegistryError::StaleHandle {
+                index: handle.index,
+                generation: handle.generation,
+            })
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T: ?Sized> Default for RegistrySlots<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stores pipelines, descriptor sets and buffers behind copyable generational handles (see
+/// [`RegistryHandle`]) for data driven renderers. Removed and replaced resources are handed to
+/// `deferred_drop` (e.g. a [`DeviceResources`](crate::DeviceResources)) rather than destroyed
+/// while a frame in flight may still be using them, so a pipeline can be hot-swapped after a
+/// shader reload without invalidating the handles referring to it.
+pub struct ResourceRegistry {
+    pipelines: RegistrySlots<dyn PipelineAccess>,
+    descriptor_sets: RegistrySlots<DescriptorSet>,
+    buffers: RegistrySlots<Buffer>,
+    deferred_drop: Arc<dyn DeferDrop>,
+}
+
+impl ResourceRegistry {
+    pub fn new(deferred_drop: Arc<dyn DeferDrop>) -> Self {
+        Self {
+            pipelines: RegistrySlots::new(),
+            descriptor_sets: RegistrySlots::new(),
+            buffers: RegistrySlots::new(),
+            deferred_drop,
+        }
+    }
+
+    // Pipelines
+
+    pub fn insert_pipeline(&mut self, pipeline: Arc<dyn PipelineAccess>) -> PipelineHandle {
+        self.pipelines.insert(pipeline)
+    }
+
+    #[inline]
+    pub fn pipeline(&self, handle: PipelineHandle) -> Option<&Arc<dyn PipelineAccess>> {
+        self.pipelines.get(handle)
+    }
+
+    /// Hot-swaps the pipeline referenced by `handle`. The old pipeline is dropped once no frame
+    /// in flight can be using it.
+    pub fn replace_pipeline(
+        &mut self,
+        handle: PipelineHandle,
+        pipeline: Arc<dyn PipelineAccess>,
+    ) -> Result<(), RegistryError> {
+        let old_pipeline = self.pipelines.replace(handle, pipeline)?;
+        self.deferred_drop.defer_drop_boxed(Box::new(old_pipeline));
+        Ok(())
+    }
+
+    pub fn remove_pipeline(&mut self, handle: PipelineHandle) -> Result<(), RegistryError> {
+        let pipeline = self.pipelines.remove(handle)?;
+        self.deferred_drop.defer_drop_boxed(Box::new(pipeline));
+        Ok(())
+    }
+
+    // Descriptor Sets
+
+    pub fn insert_descriptor_set(
+        &mut self,
+        descriptor_set: Arc<DescriptorSet>,
+    ) -> DescriptorSetHandle {
+        self.descriptor_sets.insert(descriptor_set)
+    }
+
+    #[inline]
+    pub fn descriptor_set(&self, handle: DescriptorSetHandle) -> Option<&Arc<DescriptorSet>> {
+        self.descriptor_sets.get(handle)
+    }
+
+    /// Swaps the descriptor set referenced by `handle`. The old set is dropped once no frame in
+    /// flight can be using it.
+    pub fn replace_descriptor_set(
+        &mut self,
+        handle: DescriptorSetHandle,
+        descriptor_set: Arc<DescriptorSet>,
+    ) -> Result<(), RegistryError> {
+        let old_descriptor_set = self.descriptor_sets.replace(handle, descriptor_set)?;
+        self.deferred_drop
+            .defer_drop_boxed(Box::new(old_descriptor_set));
+        Ok(())
+    }
+
+    pub fn remove_descriptor_set(
+        &mut self,
+        handle: DescriptorSetHandle,
+    ) -> Result<(), RegistryError> {
+        let descriptor_set = self.descriptor_sets.remove(handle)?;
+        self.deferred_drop
+            .defer_drop_boxed(Box::new(descriptor_set));
+        Ok(())
+    }
+
+    // Buffers
+
+    pub fn insert_buffer(&mut self, buffer: Arc<Buffer>) -> BufferHandle {
+        self.buffers.insert(buffer)
+    }
+
+    #[inline]
+    pub fn buffer(&self, handle: BufferHandle) -> Option<&Arc<Buffer>> {
+        self.buffers.get(handle)
+    }
+
+    /// Swaps the buffer referenced by `handle` e.g. after growing it. The old buffer is dropped
+    /// once no frame in flight can be using it.
+    pub fn replace_buffer(
+        &mut self,
+        handle: BufferHandle,
+        buffer: Arc<Buffer>,
+    ) -> Result<(), RegistryError> {
+        let old_buffer = self.buffers.replace(handle, buffer)?;
+        self.deferred_drop.defer_drop_boxed(Box::new(old_buffer));
+        Ok(())
+    }
+
+    pub fn remove_buffer(&mut self, handle: BufferHandle) -> Result<(), RegistryError> {
+        let buffer = self.buffers.remove(handle)?;
+        self.deferred_drop.defer_drop_boxed(Box::new(buffer));
+        Ok(())
+    }
+
+    /// Removes every resource, invalidating all handles. The resources are deferred like
+    /// individual removals.
+    pub fn clear(&mut self) {
+        for pipeline in self.pipelines.clear() {
+            self.deferred_drop.defer_drop_boxed(Box::new(pipeline));
+        }
+        for descriptor_set in self.descriptor_sets.clear() {
+            self.deferred_drop
+                .defer_drop_boxed(Box::new(descriptor_set));
+        }
+        for buffer in self.buffers.clear() {
+            self.deferred_drop.defer_drop_boxed(Box::new(buffer));
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipelines(&self) -> &RegistrySlots<dyn PipelineAccess> {
+        &self.pipelines
+    }
+
+    #[inline]
+    pub fn descriptor_sets(&self) -> &RegistrySlots<DescriptorSet> {
+        &self.descriptor_sets
+    }
+
+    #[inline]
+    pub fn buffers(&self) -> &RegistrySlots<Buffer> {
+        &self.buffers
+    }
+
+    #[inline]
+    pub fn deferred_drop(&self) -> &Arc<dyn DeferDrop> {
+        &self.deferred_drop
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+pub enum RegistryError {
+    /// The resource was removed (or the handle never came from this registry).
+    StaleHandle { index: u32, generation: u32 },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StaleHandle { index, generation } => write!(
+                f,
+                "registry handle (index {}, generation {}) doesn't refer to a live resource",
+                index, generation
+            ),
+        }
+    }
+}
+
+impl error::Error for RegistryError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn registry_slots_detect_stale_handles() {
+    let mut slots = RegistrySlots::<u32>::new();
+    let first = slots.insert(Arc::new(1));
+    let second = slots.insert(Arc::new(2));
+    assert_eq!(slots.len(), 2);
+    assert_eq!(slots.get(first).map(|r| **r), Some(1));
+
+    // hot-swap keeps the handle valid
+    let old = slots.replace(second, Arc::new(20)).unwrap();
+    assert_eq!(*old, 2);
+    assert_eq!(slots.get(second).map(|r| **r), Some(20));
+
+    // the freed slot is reused with a new generation
+    slots.remove(first).unwrap();
+    assert!(!slots.contains(first));
+    assert!(slots.remove(first).is_err());
+    assert!(slots.replace(first, Arc::new(0)).is_err());
+    let third = slots.insert(Arc::new(3));
+    assert_eq!(third.index(), first.index());
+    assert_ne!(third, first);
+    assert_eq!(slots.get(first), None);
+    assert_eq!(slots.get(third).map(|r| **r), Some(3));
+
+    let bits = third.to_bits();
+    assert_eq!(RegistryHandle::<u32>::from_bits(bits), third);
+    assert_eq!(RegistryHandle::<u32>::from_bits(u64::MAX).index(), u32::MAX);
+    assert!(!slots.contains(RegistryHandle::from_bits(u64::MAX)));
+
+    let mut live: Vec<u32> = slots.iter().map(|(_, r)| **r).collect();
+    live.sort();
+    assert_eq!(live, vec![3, 20]);
+
+    assert_eq!(slots.clear().len(), 2);
+    assert!(slots.is_empty());
+    assert!(!slots.contains(second));
+}
@@ -124,7 +124,7 @@ impl SamplerProperties {
     pub fn write_create_info<'a>(
         &'a self,
         create_info: vk::SamplerCreateInfo<'a>,
-    ) -> vk::SamplerCreateInfo {
+    ) -> vk::SamplerCreateInfo<'a> {
         create_info
             .flags(self.flags)
             .mag_filter(self.mag_filter)
@@ -144,7 +144,7 @@ impl SamplerProperties {
             .unnormalized_coordinates(self.unnormalized_coordinates)
     }
 
-    pub fn create_info(&self) -> vk::SamplerCreateInfo {
+    pub fn create_info(&self) -> vk::SamplerCreateInfo<'_> {
         self.write_create_info(vk::SamplerCreateInfo::default())
     }
 
@@ -177,3 +177,9 @@ impl SamplerProperties {
         }
     }
 }
+
+impl From<&vk::SamplerCreateInfo<'_>> for SamplerProperties {
+    fn from(value: &vk::SamplerCreateInfo<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
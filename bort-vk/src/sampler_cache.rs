@@ -0,0 +1,145 @@
+use crate::{Device, Sampler, SamplerProperties};
+use ash::{prelude::VkResult, vk};
+use std::{collections::HashMap, sync::Arc};
+
+/// Identifies a sampler by its [`SamplerProperties`]. Floats are compared bitwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerKey {
+    pub flags: vk::SamplerCreateFlags,
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode: [vk::SamplerAddressMode; 3],
+    pub mip_lod_bias_bits: u32,
+    pub max_anisotropy_bits: Option<u32>,
+    pub compare_op: Option<vk::CompareOp>,
+    pub min_lod_bits: u32,
+    pub max_lod_bits: u32,
+    pub border_color: vk::BorderColor,
+    pub unnormalized_coordinates: bool,
+}
+
+impl SamplerKey {
+    pub fn new(properties: &SamplerProperties) -> Self {
+        Self {
+            flags: properties.flags,
+            mag_filter: properties.mag_filter,
+            min_filter: properties.min_filter,
+            mipmap_mode: properties.mipmap_mode,
+            address_mode: properties.address_mode,
+            mip_lod_bias_bits: properties.mip_lod_bias.to_bits(),
+            max_anisotropy_bits: properties.max_anisotropy.map(f32::to_bits),
+            compare_op: properties.compare_op,
+            min_lod_bits: properties.min_lod.to_bits(),
+            max_lod_bits: properties.max_lod.to_bits(),
+            border_color: properties.border_color,
+            unnormalized_coordinates: properties.unnormalized_coordinates,
+        }
+    }
+}
+
+/// Creates samplers on demand and shares them between callers requesting the same properties.
+/// Implementations limit the number of samplers that can exist at once (`maxSamplerAllocationCount`)
+/// so deduplicating them is worthwhile.
+pub struct SamplerCache {
+    entries: HashMap<SamplerKey, Arc<Sampler>>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl SamplerCache {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self {
+            entries: HashMap::new(),
+            device,
+        }
+    }
+
+    /// Returns the cached sampler matching `properties` or creates (and caches) a new one.
+    pub fn get_or_create(&mut self, properties: SamplerProperties) -> VkResult<Arc<Sampler>> {
+        let key = SamplerKey::new(&properties);
+        if let Some(sampler) = self.entries.get(&key) {
+            return Ok(sampler.clone());
+        }
+
+        let sampler = Arc::new(Sampler::new(self.device.clone(), properties)?);
+        self.entries.insert(key, sampler.clone());
+        Ok(sampler)
+    }
+
+    /// Removes entries that aren't referenced outside of the cache. Returns the number of
+    /// evicted entries.
+    ///
+    /// The evicted entries are destroyed immediately so command buffers recorded with them must
+    /// have completed. See [`Self::take_unused`] to defer that.
+    pub fn evict_unused(&mut self) -> usize {
+        self.take_unused().len()
+    }
+
+    /// Removes and returns entries that aren't referenced outside of the cache e.g. to push them
+    /// to a [`DeletionQueue`](crate::DeletionQueue).
+    pub fn take_unused(&mut self) -> Vec<Arc<Sampler>> {
+        let unused_keys: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, sampler)| Arc::strong_count(sampler) == 1)
+            .map(|(key, _)| *key)
+            .collect();
+        unused_keys
+            .iter()
+            .filter_map(|key| self.entries.remove(key))
+            .collect()
+    }
+
+    /// Removes and returns all entries.
+    pub fn take_all(&mut self) -> Vec<Arc<Sampler>> {
+        self.entries.drain().map(|(_, sampler)| sampler).collect()
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn sampler_key_compares_floats_bitwise() {
+    let linear = SamplerProperties {
+        mag_filter: vk::Filter::LINEAR,
+        min_filter: vk::Filter::LINEAR,
+        ..Default::default()
+    };
+    assert_eq!(SamplerKey::new(&linear), SamplerKey::new(&linear));
+
+    let anisotropic = SamplerProperties {
+        max_anisotropy: Some(16.),
+        ..linear
+    };
+    assert_ne!(SamplerKey::new(&linear), SamplerKey::new(&anisotropic));
+
+    let biased = SamplerProperties {
+        mip_lod_bias: -0.5,
+        ..linear
+    };
+    assert_ne!(SamplerKey::new(&linear), SamplerKey::new(&biased));
+}
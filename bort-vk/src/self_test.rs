@@ -0,0 +1,758 @@
+use crate::{
+    allocation_info_cpu_accessible, allocation_info_device_local, default_subresource_layers,
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, BufferProperties, ClearValue,
+    CommandBuffer, CommandPool, CommandPoolProperties, ComputePipeline, ComputePipelineProperties,
+    DescriptorPool, DescriptorPoolProperties, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device, DeviceOwned, Framebuffer,
+    FramebufferProperties, Image, ImageDimensions, ImageViewAccess, MemoryAllocator, MemoryError,
+    MemoryPool, MemoryPoolPropeties, PipelineAccess, PipelineLayout, PipelineLayoutProperties,
+    QueryPool, QueryPoolError, QueryPoolProperties, Queue, RenderPass, RenderPassBeginInfoBuilder,
+    RenderPassError, ShaderError, ShaderModule, ShaderStage, Subpass,
+};
+use ash::vk;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::{
+    error, fmt,
+    io::Cursor,
+    mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Spir-V compiled from `shaders/self_test.comp`.
+const SELF_TEST_SPIRV: &[u8] = include_bytes!("./shaders/self_test.comp.spv");
+/// Must match the `local_size_x` of `shaders/self_test.comp`.
+const SELF_TEST_WORKGROUP_SIZE: usize = 64;
+
+const ROUND_TRIP_VALUE_COUNT: usize = 64;
+const RENDER_TARGET_SIZE: u32 = 4;
+const RENDER_TARGET_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+const POOL_BUFFER_SIZE: vk::DeviceSize = 64 * 1024;
+const POOL_BLOCK_SIZE: vk::DeviceSize = 4 * POOL_BUFFER_SIZE;
+/// Opaque magenta, which is exactly representable in `R8G8B8A8_UNORM`.
+const CLEAR_COLOR: [f32; 4] = [1., 0., 1., 1.];
+const CLEAR_COLOR_TEXEL: [u8; 4] = [255, 0, 255, 255];
+
+impl Device {
+    /// Runs a quick battery of checks on `queue` to catch broken driver/layer configurations at
+    /// startup rather than deep into the first frame:
+    /// - a buffer upload and readback through a device local buffer
+    /// - allocating from a fixed budget memory pool until it runs out
+    /// - a trivial compute dispatch
+    /// - a timestamp query round trip
+    /// - clearing a color attachment in a render pass (no swapchain) and reading it back
+    ///
+    /// Checks the queue family doesn't support are skipped. Blocks until all the work has
+    /// completed. Only returns an error if the resources shared by the checks (a memory allocator
+    /// and a command pool) couldn't be created; failed checks are listed in the report.
+    pub fn run_self_test(self: &Arc<Self>, queue: &Queue) -> Result<SelfTestReport, SelfTestError> {
+        let memory_allocator =
+            Arc::new(MemoryAllocator::new(self.clone()).map_err(SelfTestError::Vulkan)?);
+        let command_pool = Arc::new(
+            CommandPool::new(
+                self.clone(),
+                CommandPoolProperties::new_default(queue.family_index()),
+            )
+            .map_err(SelfTestError::Vulkan)?,
+        );
+        let queue_family_properties = self
+            .physical_device()
+            .queue_family_properties()
+            .get(queue.family_index() as usize)
+            .copied()
+            .unwrap_or_default();
+        let queue_flags = queue_family_properties.queue_flags;
+
+        let mut report = SelfTestReport::default();
+        report.run(SelfTestCheck::BufferRoundTrip, || {
+            test_buffer_round_trip(&memory_allocator, &command_pool, queue)
+        });
+        report.run(SelfTestCheck::MemoryPoolExhaustion, || {
+            test_memory_pool_exhaustion(&memory_allocator)
+        });
+
+        if queue_flags.contains(vk::QueueFlags::COMPUTE) {
+            report.run(SelfTestCheck::ComputeDispatch, || {
+                test_compute_dispatch(&memory_allocator, &command_pool, queue)
+            });
+        } else {
+            report.skip(
+                SelfTestCheck::ComputeDispatch,
+                "queue family doesn't support compute",
+            );
+        }
+
+        if queue_family_properties.timestamp_valid_bits > 0 {
+            report.run(SelfTestCheck::TimestampQuery, || {
+                test_timestamp_query(
+                    &command_pool,
+                    queue,
+                    queue_family_properties.timestamp_valid_bits,
+                )
+            });
+        } else {
+            report.skip(
+                SelfTestCheck::TimestampQuery,
+                "queue family doesn't support timestamps",
+            );
+        }
+
+        if queue_flags.contains(vk::QueueFlags::GRAPHICS) {
+            report.run(SelfTestCheck::RenderToTexture, || {
+                test_render_to_texture(&memory_allocator, &command_pool, queue)
+            });
+        } else {
+            report.skip(
+                SelfTestCheck::RenderToTexture,
+                "queue family doesn't support graphics",
+            );
+        }
+
+        Ok(report)
+    }
+}
+
+/// One of the checks performed by [`Device::run_self_test`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestCheck {
+    BufferRoundTrip,
+    MemoryPoolExhaustion,
+    ComputeDispatch,
+    TimestampQuery,
+    RenderToTexture,
+}
+
+impl fmt::Display for SelfTestCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferRoundTrip => write!(f, "buffer upload/readback"),
+            Self::MemoryPoolExhaustion => write!(f, "memory pool exhaustion"),
+            Self::ComputeDispatch => write!(f, "compute dispatch"),
+            Self::TimestampQuery => write!(f, "timestamp query"),
+            Self::RenderToTexture => write!(f, "render to texture"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SelfTestOutcome {
+    Passed,
+    /// The check isn't supported by the queue (family). Contains the reason.
+    Skipped(&'static str),
+    Failed(SelfTestError),
+}
+
+#[derive(Debug)]
+pub struct SelfTestResult {
+    pub check: SelfTestCheck,
+    pub outcome: SelfTestOutcome,
+    /// Cpu time spent recording, submitting and waiting on the check.
+    pub duration: Duration,
+}
+
+/// Returned by [`Device::run_self_test`]. Displays as one line per check.
+#[derive(Debug, Default)]
+pub struct SelfTestReport {
+    pub results: Vec<SelfTestResult>,
+}
+
+impl SelfTestReport {
+    /// `true` if no checks failed (some may have been skipped).
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (SelfTestCheck, &SelfTestError)> {
+        self.results
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                SelfTestOutcome::Failed(e) => Some((result.check, e)),
+                _ => None,
+            })
+    }
+
+    fn run(&mut self, check: SelfTestCheck, test: impl FnOnce() -> Result<(), SelfTestError>) {
+        let start = Instant::now();
+        let outcome = match test() {
+            Ok(()) => SelfTestOutcome::Passed,
+            Err(e) => {
+                warn!("device self test '{}' failed: {}", check, e);
+                SelfTestOutcome::Failed(e)
+            }
+        };
+        self.results.push(SelfTestResult {
+            check,
+            outcome,
+            duration: start.elapsed(),
+        });
+    }
+
+    fn skip(&mut self, check: SelfTestCheck, reason: &'static str) {
+        self.results.push(SelfTestResult {
+            check,
+            outcome: SelfTestOutcome::Skipped(reason),
+            duration: Duration::ZERO,
+        });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                SelfTestOutcome::Passed => {
+                    writeln!(f, "{}: passed ({:?})", result.check, result.duration)?
+                }
+                SelfTestOutcome::Skipped(reason) => {
+                    writeln!(f, "{}: skipped ({})", result.check, reason)?
+                }
+                SelfTestOutcome::Failed(e) => writeln!(f, "{}: FAILED: {}", result.check, e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Checks
+
+fn test_buffer_round_trip(
+    memory_allocator: &Arc<MemoryAllocator>,
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+) -> Result<(), SelfTestError> {
+    let expected: [u32; ROUND_TRIP_VALUE_COUNT] =
+        std::array::from_fn(|index| 0xB0A7_0000 | index as u32);
+    let size = mem::size_of_val(&expected) as vk::DeviceSize;
+
+    let mut staging_buffer = Buffer::new_staging(memory_allocator.clone(), size)
+        .map_err(SelfTestError::BufferCreation)?;
+    staging_buffer
+        .memory_allocation_mut()
+        .write_struct(expected, 0)
+        .map_err(SelfTestError::Memory)?;
+    let device_buffer = Buffer::new_storage(memory_allocator.clone(), size)
+        .map_err(SelfTestError::BufferCreation)?;
+    let mut readback_buffer = new_readback_buffer(memory_allocator, size)?;
+
+    let copy_region = vk::BufferCopy {
+        src_offset: 0,
+        dst_offset: 0,
+        size,
+    };
+    queue
+        .submit_one_time(command_pool, |command_buffer| {
+            command_buffer.copy_buffer(&staging_buffer, &device_buffer, &[copy_region]);
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[vk::MemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)],
+                &[],
+                &[],
+            );
+            command_buffer.copy_buffer(&device_buffer, &readback_buffer, &[copy_region]);
+            record_host_read_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            );
+            Ok(())
+        })
+        .map_err(SelfTestError::Vulkan)?;
+
+    let actual: [u32; ROUND_TRIP_VALUE_COUNT] = readback_buffer
+        .memory_allocation_mut()
+        .read_struct(0)
+        .map_err(SelfTestError::Memory)?;
+    check_values(&expected, &actual)
+}
+
+/// Fills a one block pool with buffers and checks that it runs out of memory within its budget
+/// (rather than falling back to other memory) and that freeing a buffer makes room for another.
+fn test_memory_pool_exhaustion(
+    memory_allocator: &Arc<MemoryAllocator>,
+) -> Result<(), SelfTestError> {
+    let buffer_properties =
+        BufferProperties::new_default(POOL_BUFFER_SIZE, vk::BufferUsageFlags::STORAGE_BUFFER);
+    let pool_properties = MemoryPoolPropeties::new_fixed_budget(POOL_BLOCK_SIZE, 1);
+    let max_buffer_count = pool_properties
+        .budget()
+        .map_or(0, |budget| budget / POOL_BUFFER_SIZE) as usize;
+    let pool = Arc::new(
+        MemoryPool::new_for_buffers(
+            memory_allocator.clone(),
+            &buffer_properties,
+            &allocation_info_device_local(),
+            pool_properties,
+        )
+        .map_err(SelfTestError::Vulkan)?,
+    );
+    let new_buffer = || {
+        Buffer::new(
+            pool.clone(),
+            buffer_properties.clone(),
+            allocation_info_device_local(),
+        )
+    };
+
+    let mut buffers = Vec::new();
+    let exhaustion_error = loop {
+        if buffers.len() > max_buffer_count {
+            return Err(SelfTestError::UnexpectedPoolExhaustion {
+                buffer_count: buffers.len(),
+                max_buffer_count,
+            });
+        }
+        match new_buffer() {
+            Ok(buffer) => buffers.push(buffer),
+            Err(e) => break e,
+        }
+    };
+    if exhaustion_error.result != vk::Result::ERROR_OUT_OF_DEVICE_MEMORY {
+        return Err(SelfTestError::BufferCreation(exhaustion_error));
+    }
+    if buffers.is_empty() {
+        return Err(SelfTestError::UnexpectedPoolExhaustion {
+            buffer_count: 0,
+            max_buffer_count,
+        });
+    }
+
+    buffers.pop();
+    buffers.push(new_buffer().map_err(SelfTestError::BufferCreation)?);
+    Ok(())
+}
+
+fn test_compute_dispatch(
+    memory_allocator: &Arc<MemoryAllocator>,
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+) -> Result<(), SelfTestError> {
+    let device = command_pool.device();
+
+    let binding = DescriptorSetLayoutBinding {
+        binding: 0,
+        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+        descriptor_count: 1,
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        ..Default::default()
+    };
+    let descriptor_set_layout = Arc::new(
+        DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutProperties::new_default(vec![binding]),
+        )
+        .map_err(SelfTestError::DescriptorSetLayout)?,
+    );
+    let pipeline_layout = Arc::new(
+        PipelineLayout::new(
+            device.clone(),
+            PipelineLayoutProperties::new(vec![descriptor_set_layout.clone()], Vec::new()),
+        )
+        .map_err(SelfTestError::Vulkan)?,
+    );
+    let shader_module = Arc::new(
+        ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(SELF_TEST_SPIRV))
+            .map_err(SelfTestError::Shader)?,
+    );
+    let shader_stage = ShaderStage::compute(shader_module).map_err(SelfTestError::Shader)?;
+    let pipeline = ComputePipeline::new(
+        pipeline_layout,
+        ComputePipelineProperties::default(),
+        &shader_stage,
+        None,
+    )
+    .map_err(SelfTestError::Vulkan)?;
+
+    let descriptor_pool = Arc::new(
+        DescriptorPool::new(
+            device.clone(),
+            DescriptorPoolProperties {
+                flags: vk::DescriptorPoolCreateFlags::empty(),
+                max_sets: 1,
+                pool_sizes: vec![vk::DescriptorPoolSize {
+                    ty: vk::DescriptorType::STORAGE_BUFFER,
+                    descriptor_count: 1,
+                }],
+            },
+        )
+        .map_err(SelfTestError::Vulkan)?,
+    );
+    let descriptor_set = descriptor_pool
+        .allocate_descriptor_set(descriptor_set_layout)
+        .map_err(SelfTestError::Vulkan)?;
+
+    let size = (SELF_TEST_WORKGROUP_SIZE * mem::size_of::<u32>()) as vk::DeviceSize;
+    let mut values_buffer = Buffer::new(
+        memory_allocator.clone(),
+        BufferProperties::new_default(size, vk::BufferUsageFlags::STORAGE_BUFFER),
+        allocation_info_cpu_accessible(),
+    )
+    .map_err(SelfTestError::BufferCreation)?;
+
+    let buffer_info = [vk::DescriptorBufferInfo {
+        buffer: values_buffer.handle(),
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    }];
+    let descriptor_write = vk::WriteDescriptorSet::default()
+        .dst_set(descriptor_set.handle())
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+        .buffer_info(&buffer_info);
+    device.update_descriptor_sets([descriptor_write], []);
+
+    queue
+        .submit_one_time(command_pool, |command_buffer| {
+            command_buffer.bind_pipeline(&pipeline);
+            command_buffer.bind_descriptor_sets(
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.pipeline_layout(),
+                0,
+                [&descriptor_set],
+                &[],
+            );
+            command_buffer.dispatch(1, 1, 1);
+            record_host_read_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+            );
+            Ok(())
+        })
+        .map_err(SelfTestError::Vulkan)?;
+
+    let expected: [u32; SELF_TEST_WORKGROUP_SIZE] =
+        std::array::from_fn(|index| index as u32 * 3 + 1);
+    let actual: [u32; SELF_TEST_WORKGROUP_SIZE] = values_buffer
+        .memory_allocation_mut()
+        .read_struct(0)
+        .map_err(SelfTestError::Memory)?;
+    check_values(&expected, &actual)
+}
+
+fn test_timestamp_query(
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+    timestamp_valid_bits: u32,
+) -> Result<(), SelfTestError> {
+    let query_pool = QueryPool::new(
+        command_pool.device().clone(),
+        QueryPoolProperties::new_timestamp(2),
+    )
+    .map_err(SelfTestError::Vulkan)?;
+
+    queue
+        .submit_one_time(command_pool, |command_buffer| {
+            command_buffer
+                .reset_query_pool(&query_pool, 0..2)
+                .expect("the pool has 2 queries");
+            command_buffer.write_timestamp(vk::PipelineStageFlags::TOP_OF_PIPE, &query_pool, 0);
+            command_buffer.write_timestamp(vk::PipelineStageFlags::BOTTOM_OF_PIPE, &query_pool, 1);
+            Ok(())
+        })
+        .map_err(SelfTestError::Vulkan)?;
+
+    let mut timestamps = [0u64; 2];
+    query_pool
+        .get_results(
+            0..2,
+            &mut timestamps,
+            vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+        )
+        .map_err(SelfTestError::QueryPool)?;
+    check_timestamps(timestamps[0], timestamps[1], timestamp_valid_bits)
+}
+
+fn test_render_to_texture(
+    memory_allocator: &Arc<MemoryAllocator>,
+    command_pool: &Arc<CommandPool>,
+    queue: &Queue,
+) -> Result<(), SelfTestError> {
+    let device = command_pool.device();
+    let dimensions = ImageDimensions::new_2d(RENDER_TARGET_SIZE, RENDER_TARGET_SIZE);
+    let (image, image_view) = Image::new_color_attachment(
+        memory_allocator.clone(),
+        dimensions,
+        RENDER_TARGET_FORMAT,
+        vk::ImageUsageFlags::TRANSFER_SRC,
+    )
+    .map_err(SelfTestError::Vulkan)?;
+
+    let attachment_description = vk::AttachmentDescription::default()
+        .format(RENDER_TARGET_FORMAT)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .initial_layout(vk::ImageLayout::UNDEFINED)
+        .final_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+    let color_reference = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    // make the attachment store available to the copy
+    let subpass_dependency = vk::SubpassDependency::default()
+        .src_subpass(0)
+        .dst_subpass(vk::SUBPASS_EXTERNAL)
+        .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+        .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+    let render_pass = Arc::new(
+        RenderPass::new(
+            device.clone(),
+            vec![attachment_description],
+            vec![Subpass::new(&[color_reference], None, &[])],
+            vec![subpass_dependency],
+        )
+        .map_err(SelfTestError::RenderPass)?,
+    );
+    let attachments: Vec<Arc<dyn ImageViewAccess>> = vec![image_view];
+    let framebuffer = Framebuffer::new(
+        render_pass.clone(),
+        FramebufferProperties::new_default(attachments, dimensions),
+    )
+    .map_err(SelfTestError::Vulkan)?;
+
+    let texel_count = (RENDER_TARGET_SIZE * RENDER_TARGET_SIZE) as usize;
+    let mut readback_buffer =
+        new_readback_buffer(memory_allocator, (texel_count * 4) as vk::DeviceSize)?;
+
+    let mut begin_info_builder = RenderPassBeginInfoBuilder::new(&render_pass, &framebuffer)
+        .clear_value(0, ClearValue::ColorF32(CLEAR_COLOR));
+    let begin_info = begin_info_builder
+        .build()
+        .map_err(SelfTestError::RenderPass)?;
+    let copy_region = vk::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: default_subresource_layers(vk::ImageAspectFlags::COLOR),
+        image_offset: vk::Offset3D::default(),
+        image_extent: dimensions.extent_3d(),
+    };
+
+    queue
+        .submit_one_time(command_pool, |command_buffer| {
+            command_buffer.begin_render_pass(&begin_info, vk::SubpassContents::INLINE);
+            command_buffer.end_render_pass();
+            command_buffer.copy_image_to_buffer(
+                image.as_ref(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                &readback_buffer,
+                &[copy_region],
+            );
+            record_host_read_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            );
+            Ok(())
+        })
+        .map_err(SelfTestError::Vulkan)?;
+
+    let expected = vec![u32::from_ne_bytes(CLEAR_COLOR_TEXEL); texel_count];
+    let mut actual = Vec::with_capacity(texel_count);
+    for texel_index in 0..texel_count {
+        let texel: u32 = readback_buffer
+            .memory_allocation_mut()
+            .read_struct(texel_index * 4)
+            .map_err(SelfTestError::Memory)?;
+        actual.push(texel);
+    }
+    check_values(&expected, &actual)
+}
+
+// Helper Functions
+
+/// Host visible buffer with `TRANSFER_DST` usage.
+fn new_readback_buffer(
+    memory_allocator: &Arc<MemoryAllocator>,
+    size: vk::DeviceSize,
+) -> Result<Buffer, SelfTestError> {
+    Buffer::new(
+        memory_allocator.clone() as Arc<dyn AllocatorAccess>,
+        BufferProperties::new_default(size, vk::BufferUsageFlags::TRANSFER_DST),
+        allocation_info_cpu_accessible(),
+    )
+    .map_err(SelfTestError::BufferCreation)
+}
+
+fn record_host_read_barrier(
+    command_buffer: &CommandBuffer,
+    src_stage_mask: vk::PipelineStageFlags,
+    src_access_mask: vk::AccessFlags,
+) {
+    command_buffer.pipeline_barrier(
+        src_stage_mask,
+        vk::PipelineStageFlags::HOST,
+        vk::DependencyFlags::empty(),
+        &[vk::MemoryBarrier::default()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)],
+        &[],
+        &[],
+    );
+}
+
+fn check_values(expected: &[u32], actual: &[u32]) -> Result<(), SelfTestError> {
+    match expected
+        .iter()
+        .zip(actual)
+        .position(|(expected, actual)| expected != actual)
+    {
+        Some(index) => Err(SelfTestError::DataMismatch {
+            index,
+            expected: expected[index],
+            actual: actual[index],
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Checks the end timestamp isn't before the start, accounting for wrap around within
+/// `timestamp_valid_bits`.
+fn check_timestamps(start: u64, end: u64, timestamp_valid_bits: u32) -> Result<(), SelfTestError> {
+    let valid_mask = if timestamp_valid_bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << timestamp_valid_bits) - 1
+    };
+    let (start, end) = (start & valid_mask, end & valid_mask);
+    let elapsed = end.wrapping_sub(start) & valid_mask;
+    // a wrapped (i.e. negative) difference shows up as more than half the valid range
+    if elapsed > valid_mask / 2 {
+        return Err(SelfTestError::TimestampsOutOfOrder { start, end });
+    }
+    Ok(())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum SelfTestError {
+    DescriptorSetLayout(DescriptorSetLayoutError),
+    Vulkan(vk::Result),
+    BufferCreation(AllocationError),
+    Memory(MemoryError),
+    Shader(ShaderError),
+    QueryPool(QueryPoolError),
+    RenderPass(RenderPassError),
+    /// The device produced different values than expected.
+    DataMismatch {
+        index: usize,
+        expected: u32,
+        actual: u32,
+    },
+    TimestampsOutOfOrder {
+        start: u64,
+        end: u64,
+    },
+    /// A fixed budget memory pool ran out of memory with none or more than `max_buffer_count`
+    /// buffers allocated.
+    UnexpectedPoolExhaustion {
+        buffer_count: usize,
+        max_buffer_count: usize,
+    },
+}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => write!(f, "vulkan call failed: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create self test buffer: {}", e),
+            Self::DescriptorSetLayout(e) => {
+                write!(f, "descriptor set layout creation failed: {}", e)
+            }
+            Self::Memory(e) => write!(f, "failed to access buffer memory: {}", e),
+            Self::Shader(e) => write!(f, "failed to create self test shader: {}", e),
+            Self::QueryPool(e) => write!(f, "failed to read query results: {}", e),
+            Self::RenderPass(e) => write!(f, "render pass error: {}", e),
+            Self::DataMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "read back {:#010x} at index {} but expected {:#010x}",
+                actual, index, expected
+            ),
+            Self::TimestampsOutOfOrder { start, end } => write!(
+                f,
+                "end timestamp {} is before start timestamp {}",
+                end, start
+            ),
+            Self::UnexpectedPoolExhaustion {
+                buffer_count,
+                max_buffer_count,
+            } => write!(
+                f,
+                "memory pool with room for {} buffers had {} allocated when it ran out of memory",
+                max_buffer_count, buffer_count
+            ),
+        }
+    }
+}
+
+impl error::Error for SelfTestError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::DescriptorSetLayout(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            Self::Shader(e) => Some(e),
+            Self::QueryPool(e) => Some(e),
+            Self::RenderPass(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn self_test_spirv_has_compute_entry_point() {
+    let spirv = ash::util::read_spv(&mut Cursor::new(SELF_TEST_SPIRV)).unwrap();
+    let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+    assert_eq!(entry_points.len(), 1);
+    assert_eq!(entry_points[0].name.to_str(), Ok("main"));
+    assert_eq!(entry_points[0].stage, vk::ShaderStageFlags::COMPUTE);
+}
+
+#[test]
+fn self_test_result_checks() {
+    assert!(check_values(&[1, 2, 3], &[1, 2, 3]).is_ok());
+    assert!(matches!(
+        check_values(&[1, 2, 3], &[1, 5, 3]),
+        Err(SelfTestError::DataMismatch {
+            index: 1,
+            expected: 2,
+            actual: 5
+        })
+    ));
+
+    assert!(check_timestamps(100, 250, 64).is_ok());
+    assert!(check_timestamps(250, 100, 64).is_err());
+    // wraps around within 36 valid bits
+    assert!(check_timestamps((1 << 36) - 10, 5, 36).is_ok());
+
+    let mut report = SelfTestReport::default();
+    report.run(SelfTestCheck::BufferRoundTrip, || Ok(()));
+    report.skip(SelfTestCheck::TimestampQuery, "no timestamps");
+    assert!(report.passed());
+    report.run(SelfTestCheck::ComputeDispatch, || {
+        Err(SelfTestError::Vulkan(vk::Result::ERROR_DEVICE_LOST))
+    });
+    assert!(!report.passed());
+    assert_eq!(
+        report
+            .failures()
+            .map(|(check, _)| check)
+            .collect::<Vec<_>>(),
+        vec![SelfTestCheck::ComputeDispatch]
+    );
+}
@@ -1,4 +1,4 @@
-use crate::{Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
+use crate::{Deadline, Device, DeviceOwned, SubmissionEvent, WaitStatus, ALLOCATION_CALLBACK_NONE};
 use ash::prelude::VkResult;
 use ash::vk::{self, Handle};
 use std::sync::Arc;
@@ -16,6 +16,16 @@ impl Semaphore {
         unsafe { Self::new_from_create_info(device, create_info) }
     }
 
+    /// Creates a timeline semaphore with an initial counter value of `initial_value`. Requires
+    /// Vulkan 1.2 and the `timelineSemaphore` feature.
+    pub fn new_timeline(device: Arc<Device>, initial_value: u64) -> VkResult<Self> {
+        let mut type_create_info = vk::SemaphoreTypeCreateInfo::default()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(initial_value);
+        let create_info = vk::SemaphoreCreateInfo::default().push_next(&mut type_create_info);
+        unsafe { Self::new_from_create_info(device, create_info) }
+    }
+
     /// # Safety
     /// Make sure your `p_next` chain contains valid pointers.
     pub unsafe fn new_from_create_info(
@@ -31,6 +41,54 @@ impl Semaphore {
         Ok(Self { handle, device })
     }
 
+    /// Current counter value of a timeline semaphore.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkGetSemaphoreCounterValue.html>
+    pub fn counter_value(&self) -> VkResult<u64> {
+        unsafe { self.device.inner().get_semaphore_counter_value(self.handle) }
+    }
+
+    /// Blocks until the counter of a timeline semaphore reaches `value`.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkWaitSemaphores.html>
+    pub fn wait_value(&self, value: u64, timeout_nanoseconds: u64) -> VkResult<()> {
+        let semaphores = [self.handle];
+        let values = [value];
+        let wait_info = vk::SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+        unsafe {
+            self.device
+                .inner()
+                .wait_semaphores(&wait_info, timeout_nanoseconds)
+        }
+    }
+
+    /// Same as [`Self::wait_value`] but gives up at `deadline`, returning [`WaitStatus::TimedOut`].
+    pub fn wait_value_until(
+        &self,
+        value: u64,
+        deadline: impl Into<Deadline>,
+    ) -> VkResult<WaitStatus> {
+        WaitStatus::from_vk_result(self.wait_value(value, deadline.into().timeout_nanoseconds()))
+    }
+
+    /// Sets the counter of a timeline semaphore to `value` from the host.
+    ///
+    /// <https://www.khronos.org/registry/vulkan/specs/1.3-extensions/man/html/vkSignalSemaphore.html>
+    pub fn signal_value(&self, value: u64) -> VkResult<()> {
+        let signal_info = vk::SemaphoreSignalInfo::default()
+            .semaphore(self.handle)
+            .value(value);
+        self.device
+            .submission_recorder()
+            .record(|| SubmissionEvent::HostSignal {
+                semaphore: self.handle,
+                value,
+            });
+        unsafe { self.device.inner().signal_semaphore(&signal_info) }
+    }
+
     // Getters
 
     #[inline]
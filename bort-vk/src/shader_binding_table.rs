@@ -0,0 +1,465 @@
+use crate::{
+    allocation_info_device_local, new_staging_buffer, AllocationError, AllocatorAccess, Buffer,
+    BufferProperties, CommandBuffer, DeletionQueue, Device, ResourceInitError,
+};
+use ash::{khr, vk};
+use std::{error, fmt, sync::Arc};
+
+/// The shader binding table region a shader group record is placed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbtRegion {
+    RayGen,
+    Miss,
+    Hit,
+    Callable,
+}
+
+/// Placement of a region within a shader binding table buffer. See [`SbtLayout`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SbtRegionLayout {
+    /// Bytes from the start of the buffer. A multiple of `shaderGroupBaseAlignment`.
+    pub offset: vk::DeviceSize,
+    /// Bytes between records. A multiple of `shaderGroupHandleAlignment`.
+    pub stride: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+}
+
+impl SbtRegionLayout {
+    /// The region for `vkCmdTraceRaysKHR` in a buffer at `buffer_address`. Empty regions are all
+    /// zeros.
+    pub fn device_address_region(
+        &self,
+        buffer_address: vk::DeviceAddress,
+    ) -> vk::StridedDeviceAddressRegionKHR {
+        if self.size == 0 {
+            return vk::StridedDeviceAddressRegionKHR::default();
+        }
+        vk::StridedDeviceAddressRegionKHR {
+            device_address: buffer_address + self.offset,
+            stride: self.stride,
+            size: self.size,
+        }
+    }
+}
+
+/// Byte layout of a shader binding table built by an [`SbtBuilder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SbtLayout {
+    pub raygen: SbtRegionLayout,
+    pub miss: SbtRegionLayout,
+    pub hit: SbtRegionLayout,
+    pub callable: SbtRegionLayout,
+    pub total_size: vk::DeviceSize,
+}
+
+/// The shader binding table regions passed to
+/// [`CommandBuffer::trace_rays`](crate::CommandBuffer::trace_rays).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShaderBindingTableRegions {
+    pub raygen: vk::StridedDeviceAddressRegionKHR,
+    pub miss: vk::StridedDeviceAddressRegionKHR,
+    pub hit: vk::StridedDeviceAddressRegionKHR,
+    pub callable: vk::StridedDeviceAddressRegionKHR,
+}
+
+/// A shader group handle and optional shader record data (e.g. per-geometry buffer addresses)
+/// placed after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SbtRecord {
+    group_index: u32,
+    data: Vec<u8>,
+}
+
+/// Lays out the raygen, miss, hit and callable regions of a shader binding table for a ray
+/// tracing pipeline and uploads the shader group handles.
+///
+/// Each record is a shader group handle followed by its shader record data. Record strides are
+/// rounded up to `shaderGroupHandleAlignment` and each region starts at a multiple of
+/// `shaderGroupBaseAlignment`. The raygen region has a single record with a size equal to its
+/// stride as required by `vkCmdTraceRaysKHR`.
+///
+/// Requires the `VK_KHR_ray_tracing_pipeline` extension, the `bufferDeviceAddress` feature and an
+/// allocator created with `BUFFER_DEVICE_ADDRESS`.
+#[derive(Debug, Clone)]
+pub struct SbtBuilder {
+    handle_size: u32,
+    handle_alignment: u32,
+    base_alignment: u32,
+    max_stride: u32,
+    raygen: Option<SbtRecord>,
+    miss: Vec<SbtRecord>,
+    hit: Vec<SbtRecord>,
+    callable: Vec<SbtRecord>,
+}
+
+impl SbtBuilder {
+    pub fn new(properties: &vk::PhysicalDeviceRayTracingPipelinePropertiesKHR) -> Self {
+        Self {
+            handle_size: properties.shader_group_handle_size,
+            handle_alignment: properties.shader_group_handle_alignment.max(1),
+            base_alignment: properties.shader_group_base_alignment.max(1),
+            max_stride: properties.max_shader_group_stride,
+            raygen: None,
+            miss: Vec::new(),
+            hit: Vec::new(),
+            callable: Vec::new(),
+        }
+    }
+
+    /// `None` if the physical device doesn't support `VK_KHR_ray_tracing_pipeline`.
+    pub fn new_for_device(device: &Device) -> Option<Self> {
+        let properties = device
+            .instance()
+            .physical_device_ray_tracing_pipeline_properties(device.physical_device())?;
+        Some(Self::new(&properties))
+    }
+
+    /// Adds a record for the shader group at `group_index` of the pipeline followed by
+    /// `record_data`. Records are placed in the order they're added. Setting the raygen record
+    /// replaces the previous one.
+    pub fn add_group(
+        &mut self,
+        region: SbtRegion,
+        group_index: u32,
+        record_data: &[u8],
+    ) -> &mut Self {
+        let record = SbtRecord {
+            group_index,
+            data: record_data.to_vec(),
+        };
+        match region {
+            SbtRegion::RayGen => self.raygen = Some(record),
+            SbtRegion::Miss => self.miss.push(record),
+            SbtRegion::Hit => self.hit.push(record),
+            SbtRegion::Callable => self.callable.push(record),
+        }
+        self
+    }
+
+    #[inline]
+    pub fn raygen(&mut self, group_index: u32) -> &mut Self {
+        self.add_group(SbtRegion::RayGen, group_index, &[])
+    }
+
+    #[inline]
+    pub fn miss(&mut self, group_index: u32) -> &mut Self {
+        self.add_group(SbtRegion::Miss, group_index, &[])
+    }
+
+    #[inline]
+    pub fn hit(&mut self, group_index: u32) -> &mut Self {
+        self.add_group(SbtRegion::Hit, group_index, &[])
+    }
+
+    #[inline]
+    pub fn callable(&mut self, group_index: u32) -> &mut Self {
+        self.add_group(SbtRegion::Callable, group_index, &[])
+    }
+
+    pub fn layout(&self) -> Result<SbtLayout, SbtError> {
+        if self.raygen.is_none() {
+            return Err(SbtError::NoRayGenGroup);
+        }
+
+        let mut offset: vk::DeviceSize = 0;
+        let mut region_layout = |records: &[SbtRecord]| -> Result<SbtRegionLayout, SbtError> {
+            if records.is_empty() {
+                return Ok(SbtRegionLayout::default());
+            }
+            let max_data_size = records.iter().map(|record| record.data.len()).max();
+            let stride = align_up(
+                self.handle_size as vk::DeviceSize + max_data_size.unwrap_or(0) as vk::DeviceSize,
+                self.handle_alignment as vk::DeviceSize,
+            );
+            if stride > self.max_stride as vk::DeviceSize {
+                return Err(SbtError::StrideTooLarge {
+                    stride,
+                    max_stride: self.max_stride,
+                });
+            }
+            let region = SbtRegionLayout {
+                offset: align_up(offset, self.base_alignment as vk::DeviceSize),
+                stride,
+                size: stride * records.len() as vk::DeviceSize,
+            };
+            offset = region.offset + region.size;
+            Ok(region)
+        };
+
+        let raygen = region_layout(self.raygen.as_slice())?;
+        let miss = region_layout(&self.miss)?;
+        let hit = region_layout(&self.hit)?;
+        let callable = region_layout(&self.callable)?;
+        Ok(SbtLayout {
+            raygen,
+            miss,
+            hit,
+            callable,
+            total_size: offset,
+        })
+    }
+
+    /// The contents of the shader binding table buffer. `group_handles` are the handles of all
+    /// of the pipeline's shader groups from `vkGetRayTracingShaderGroupHandlesKHR`.
+    pub fn write_table(&self, group_handles: &[u8]) -> Result<(SbtLayout, Vec<u8>), SbtError> {
+        let layout = self.layout()?;
+        let mut table = vec![0_u8; layout.total_size as usize];
+        let handle_size = self.handle_size as usize;
+
+        let regions = [
+            (&layout.raygen, self.raygen.as_slice()),
+            (&layout.miss, self.miss.as_slice()),
+            (&layout.hit, self.hit.as_slice()),
+            (&layout.callable, self.callable.as_slice()),
+        ];
+        for (region, records) in regions {
+            for (record_index, record) in records.iter().enumerate() {
+                let handle_start = record.group_index as usize * handle_size;
+                let handle = group_handles
+                    .get(handle_start..handle_start + handle_size)
+                    .ok_or(SbtError::GroupIndexOutOfBounds {
+                        group_index: record.group_index,
+                        group_count: (group_handles.len() / handle_size.max(1)) as u32,
+                    })?;
+
+                let record_start = (region.offset + region.stride * record_index as u64) as usize;
+                table[record_start..record_start + handle_size].copy_from_slice(handle);
+                let data_start = record_start + handle_size;
+                table[data_start..data_start + record.data.len()].copy_from_slice(&record.data);
+            }
+        }
+
+        Ok((layout, table))
+    }
+
+    /// Creates a device local shader binding table buffer for the `group_count` shader groups of
+    /// `pipeline` and records its upload from a staging buffer, which is pushed to
+    /// `deletion_queue`. A barrier makes the table visible to ray tracing shaders.
+    pub fn build(
+        &self,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        command_buffer: &CommandBuffer,
+        pipeline: vk::Pipeline,
+        group_count: u32,
+        deletion_queue: &mut DeletionQueue,
+    ) -> Result<ShaderBindingTable, SbtError> {
+        let ray_tracing_fns = alloc_access
+            .device()
+            .extension_loader::<khr::ray_tracing_pipeline::Device>();
+        let group_handles = unsafe {
+            ray_tracing_fns.get_ray_tracing_shader_group_handles(
+                pipeline,
+                0,
+                group_count,
+                (group_count * self.handle_size) as usize,
+            )
+        }
+        .map_err(SbtError::GroupHandles)?;
+        let (layout, table) = self.write_table(&group_handles)?;
+
+        let buffer_properties = BufferProperties::new_default(
+            layout.total_size,
+            vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        let buffer = Buffer::new_with_alignment(
+            alloc_access.clone(),
+            buffer_properties,
+            allocation_info_device_local(),
+            self.base_alignment as vk::DeviceSize,
+        )
+        .map_err(SbtError::BufferCreation)?;
+
+        let staging_buffer = new_staging_buffer(alloc_access, &table).map_err(SbtError::Upload)?;
+        let copy_region = vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 0,
+            size: layout.total_size,
+        };
+        command_buffer.copy_buffer(&staging_buffer, &buffer, &[copy_region]);
+        deletion_queue.push(staging_buffer);
+
+        let upload_barrier = vk::MemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ);
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
+            vk::DependencyFlags::empty(),
+            &[upload_barrier],
+            &[],
+            &[],
+        );
+
+        let buffer_address = buffer.device_address();
+        debug_assert!(
+            buffer_address.is_multiple_of(self.base_alignment as vk::DeviceAddress),
+            "shader binding table buffer isn't aligned to shaderGroupBaseAlignment"
+        );
+        Ok(ShaderBindingTable {
+            regions: ShaderBindingTableRegions {
+                raygen: layout.raygen.device_address_region(buffer_address),
+                miss: layout.miss.device_address_region(buffer_address),
+                hit: layout.hit.device_address_region(buffer_address),
+                callable: layout.callable.device_address_region(buffer_address),
+            },
+            layout,
+            buffer,
+        })
+    }
+}
+
+/// A shader binding table buffer created by [`SbtBuilder::build`].
+pub struct ShaderBindingTable {
+    buffer: Buffer,
+    layout: SbtLayout,
+    regions: ShaderBindingTableRegions,
+}
+
+impl ShaderBindingTable {
+    // Getters
+
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    #[inline]
+    pub fn layout(&self) -> SbtLayout {
+        self.layout
+    }
+
+    /// The regions to pass to [`CommandBuffer::trace_rays`](crate::CommandBuffer::trace_rays).
+    #[inline]
+    pub fn regions(&self) -> &ShaderBindingTableRegions {
+        &self.regions
+    }
+}
+
+// Helper Functions
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum SbtError {
+    NoRayGenGroup,
+    /// A record (handle plus shader record data) exceeds `maxShaderGroupStride`.
+    StrideTooLarge {
+        stride: vk::DeviceSize,
+        max_stride: u32,
+    },
+    GroupIndexOutOfBounds {
+        group_index: u32,
+        group_count: u32,
+    },
+    GroupHandles(vk::Result),
+    BufferCreation(AllocationError),
+    Upload(ResourceInitError),
+}
+
+impl fmt::Display for SbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRayGenGroup => write!(f, "shader binding table has no raygen group"),
+            Self::StrideTooLarge { stride, max_stride } => write!(
+                f,
+                "shader binding table record stride {} exceeds maxShaderGroupStride {}",
+                stride, max_stride
+            ),
+            Self::GroupIndexOutOfBounds {
+                group_index,
+                group_count,
+            } => write!(
+                f,
+                "shader group index {} is out of bounds for a pipeline with {} groups",
+                group_index, group_count
+            ),
+            Self::GroupHandles(e) => write!(f, "failed to get shader group handles: {}", e),
+            Self::BufferCreation(e) => {
+                write!(f, "failed to create shader binding table buffer: {}", e)
+            }
+            Self::Upload(e) => write!(f, "failed to upload shader binding table: {}", e),
+        }
+    }
+}
+
+impl error::Error for SbtError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::GroupHandles(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::Upload(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn sbt_layout_alignment() {
+    let properties = vk::PhysicalDeviceRayTracingPipelinePropertiesKHR {
+        shader_group_handle_size: 32,
+        shader_group_handle_alignment: 32,
+        shader_group_base_alignment: 64,
+        max_shader_group_stride: 4096,
+        ..Default::default()
+    };
+    let mut builder = SbtBuilder::new(&properties);
+    assert!(matches!(builder.layout(), Err(SbtError::NoRayGenGroup)));
+
+    builder
+        .raygen(0)
+        .miss(1)
+        .miss(2)
+        .add_group(SbtRegion::Hit, 3, &[7; 8]);
+    let layout = builder.layout().unwrap();
+    assert_eq!(
+        layout.raygen,
+        SbtRegionLayout {
+            offset: 0,
+            stride: 32,
+            size: 32
+        }
+    );
+    assert_eq!(
+        layout.miss,
+        SbtRegionLayout {
+            offset: 64,
+            stride: 32,
+            size: 64
+        }
+    );
+    // handle + 8 bytes of record data rounded up to the handle alignment
+    assert_eq!(
+        layout.hit,
+        SbtRegionLayout {
+            offset: 128,
+            stride: 64,
+            size: 64
+        }
+    );
+    assert_eq!(layout.callable, SbtRegionLayout::default());
+    assert_eq!(layout.total_size, 192);
+    assert_eq!(
+        layout.callable.device_address_region(0x1000).device_address,
+        0
+    );
+    assert_eq!(
+        layout.hit.device_address_region(0x1000).device_address,
+        0x1080
+    );
+
+    let group_handles: Vec<u8> = (0..4_u8).flat_map(|group| [group + 1; 32]).collect();
+    let (_, table) = builder.write_table(&group_handles).unwrap();
+    assert_eq!(table[0], 1);
+    assert_eq!(table[96], 3);
+    assert_eq!(table[128], 4);
+    assert_eq!(table[160..168], [7; 8]);
+    assert!(builder.write_table(&group_handles[..64]).is_err());
+}
@@ -1,18 +1,31 @@
+#[cfg(all(feature = "spirv-val", debug_assertions))]
+use crate::ApiVersion;
 use crate::{Device, DeviceOwned, ALLOCATION_CALLBACK_NONE};
 use ash::{
+    prelude::VkResult,
     util::read_spv,
     vk::{self, Handle},
 };
 use std::{
+    collections::HashMap,
     error,
-    ffi::CString,
+    ffi::{CStr, CString, NulError},
     fmt, fs,
     io::{self, Cursor},
-    sync::Arc,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
 };
 
+/// A `vk::ShaderModule` along with entry point and push constant reflection data.
+///
+/// With the `spirv-val` feature, SPIR-V passed to [`Self::new_from_spirv`] and [`Self::new_lazy`]
+/// is validated with spirv-tools in debug builds.
 pub struct ShaderModule {
-    handle: vk::ShaderModule,
+    /// Set on creation, or on first use for lazy modules.
+    handle: OnceLock<vk::ShaderModule>,
+    /// `Some` for modules created with [`Self::new_lazy`].
+    lazy_code: Option<LazyCode>,
+    entry_points: Option<Vec<ShaderEntryPoint>>,
+    push_constant_members: Option<Vec<PushConstantMember>>,
 
     // dependencies
     device: Arc<Device>,
@@ -34,11 +47,37 @@ impl ShaderModule {
         spirv: &mut R,
     ) -> Result<Self, ShaderError> {
         let code = read_spv(spirv).map_err(ShaderError::SpirVDecode)?;
+        #[cfg(all(feature = "spirv-val", debug_assertions))]
+        validate_spirv(&device, &code)?;
         let create_info = vk::ShaderModuleCreateInfo::default().code(&code);
 
         unsafe { Self::new_from_create_info(device, create_info) }
     }
 
+    /// Stores the SPIR-V words and defers `vkCreateShaderModule` until the handle is first
+    /// needed (see [`Self::try_handle`]). Handy when the module may never be created, e.g. when
+    /// pipelines are created by chaining [`Self::code`] as a `vk::ShaderModuleCreateInfo` to
+    /// their shader stages (`VK_KHR_maintenance5` or `VK_EXT_graphics_pipeline_library`) instead.
+    pub fn new_lazy<R: io::Read + io::Seek>(
+        device: Arc<Device>,
+        spirv: &mut R,
+    ) -> Result<Self, ShaderError> {
+        let code = read_spv(spirv).map_err(ShaderError::SpirVDecode)?;
+        #[cfg(all(feature = "spirv-val", debug_assertions))]
+        validate_spirv(&device, &code)?;
+
+        Ok(Self {
+            handle: OnceLock::new(),
+            entry_points: spirv_entry_points(&code),
+            push_constant_members: spirv_push_constant_members(&code),
+            lazy_code: Some(LazyCode {
+                code,
+                creation_lock: Mutex::new(()),
+            }),
+            device,
+        })
+    }
+
     /// # Safety
     /// Make sure your `p_next` chain contains valid pointers.
     pub unsafe fn new_from_create_info(
@@ -52,14 +91,120 @@ impl ShaderModule {
         }
         .map_err(ShaderError::Creation)?;
 
-        Ok(Self { handle, device })
+        let (entry_points, push_constant_members) = if create_info.p_code.is_null() {
+            (None, None)
+        } else {
+            let code = unsafe {
+                std::slice::from_raw_parts(create_info.p_code, create_info.code_size / 4)
+            };
+            (spirv_entry_points(code), spirv_push_constant_members(code))
+        };
+
+        Ok(Self {
+            handle: OnceLock::from(handle),
+            lazy_code: None,
+            entry_points,
+            push_constant_members,
+            device,
+        })
+    }
+
+    /// The module handle, creating the module first if it's lazy and hasn't been used yet.
+    pub fn try_handle(&self) -> Result<vk::ShaderModule, ShaderError> {
+        self.try_handle_vk().map_err(ShaderError::Creation)
+    }
+
+    /// [`Self::try_handle`] for callers returning `VkResult`.
+    pub(crate) fn try_handle_vk(&self) -> VkResult<vk::ShaderModule> {
+        if let Some(&handle) = self.handle.get() {
+            return Ok(handle);
+        }
+        let lazy_code = self
+            .lazy_code
+            .as_ref()
+            .expect("non-lazy shader modules are created on construction");
+
+        let _creation_lock = lazy_code
+            .creation_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // another thread may have created it while we were waiting for the lock
+        if let Some(&handle) = self.handle.get() {
+            return Ok(handle);
+        }
+
+        let create_info = vk::ShaderModuleCreateInfo::default().code(&lazy_code.code);
+        let handle = unsafe {
+            self.device
+                .inner()
+                .create_shader_module(&create_info, ALLOCATION_CALLBACK_NONE)
+        }?;
+        Ok(*self.handle.get_or_init(|| handle))
+    }
+
+    /// Returns `true` if the module has an entry point called `name` for `stage`. Also returns
+    /// `true` if the entry points couldn't be read from the SPIR-V code.
+    pub fn has_entry_point(&self, name: &CStr, stage: vk::ShaderStageFlags) -> bool {
+        match &self.entry_points {
+            Some(entry_points) => entry_points.iter().any(|entry_point| {
+                entry_point.name.as_c_str() == name && entry_point.stage == stage
+            }),
+            None => true,
+        }
+    }
+
+    /// Entry points called `name` declared in the SPIR-V code (one per stage). Empty if there are
+    /// none or the code couldn't be parsed.
+    pub fn entry_points_named<'s>(
+        &'s self,
+        name: &'s str,
+    ) -> impl Iterator<Item = &'s ShaderEntryPoint> + 's {
+        self.entry_points
+            .iter()
+            .flatten()
+            .filter(move |entry_point| entry_point.name.as_bytes() == name.as_bytes())
     }
 
     // Getters
 
+    /// Lazy modules are created on first call. Panics if that fails; call [`Self::try_handle`]
+    /// beforehand to handle the error.
     #[inline]
     pub fn handle(&self) -> vk::ShaderModule {
-        self.handle
+        match self.handle.get() {
+            Some(&handle) => handle,
+            None => self
+                .try_handle()
+                .expect("failed to create lazy shader module"),
+        }
+    }
+
+    /// Whether `vkCreateShaderModule` has been called. Always `true` unless created with
+    /// [`Self::new_lazy`].
+    #[inline]
+    pub fn is_created(&self) -> bool {
+        self.handle.get().is_some()
+    }
+
+    /// The SPIR-V words of a lazy module. `None` unless created with [`Self::new_lazy`].
+    #[inline]
+    pub fn code(&self) -> Option<&[u32]> {
+        self.lazy_code
+            .as_ref()
+            .map(|lazy_code| lazy_code.code.as_slice())
+    }
+
+    /// Entry points declared in the SPIR-V code. `None` if the code couldn't be parsed.
+    #[inline]
+    pub fn entry_points(&self) -> Option<&[ShaderEntryPoint]> {
+        self.entry_points.as_deref()
+    }
+
+    /// Named members of the push constant block(s) declared in the SPIR-V code. `None` if the
+    /// code couldn't be parsed. Empty if there are no push constants or the names were stripped.
+    #[inline]
+    pub fn push_constant_members(&self) -> Option<&[PushConstantMember]> {
+        self.push_constant_members.as_deref()
     }
 }
 
@@ -69,19 +214,224 @@ impl DeviceOwned for ShaderModule {
         &self.device
     }
 
+    /// Null for lazy modules which haven't been created yet.
     #[inline]
     fn handle_raw(&self) -> u64 {
-        self.handle.as_raw()
+        self.handle.get().copied().unwrap_or_default().as_raw()
     }
 }
 
 impl Drop for ShaderModule {
     fn drop(&mut self) {
+        let Some(&handle) = self.handle.get() else {
+            return;
+        };
         unsafe {
             self.device
                 .inner()
-                .destroy_shader_module(self.handle, ALLOCATION_CALLBACK_NONE);
+                .destroy_shader_module(handle, ALLOCATION_CALLBACK_NONE);
+        }
+    }
+}
+
+struct LazyCode {
+    code: Vec<u32>,
+    /// Held while creating the module so it's only created once.
+    creation_lock: Mutex<()>,
+}
+
+// Validation
+
+/// Newest SPIR-V version the spirv-tools validator can target (1.5, via vulkan 1.2).
+#[cfg(all(feature = "spirv-val", debug_assertions))]
+const MAX_VALIDATED_SPIRV_VERSION: u32 = 0x0001_0500;
+
+/// Runs the spirv-tools validator on `code` for the vulkan version of `device`. The validator
+/// doesn't know vulkan 1.3 so newer devices are validated as 1.2, except for SPIR-V 1.6 (or
+/// newer) modules which vulkan 1.3 accepts but the validator can't check. Those are skipped.
+#[cfg(all(feature = "spirv-val", debug_assertions))]
+fn validate_spirv(device: &Device, code: &[u32]) -> Result<(), ShaderError> {
+    use spirv_tools::{
+        val::{self, Validator},
+        TargetEnv,
+    };
+
+    let api_version = device.effective_api_version();
+    // word 1 of the header is the version number
+    let spirv_version = code.get(1).copied().unwrap_or_default();
+    if api_version >= ApiVersion::V1_3 && spirv_version > MAX_VALIDATED_SPIRV_VERSION {
+        return Ok(());
+    }
+    let target_env = if api_version >= ApiVersion::V1_2 {
+        TargetEnv::Vulkan_1_2
+    } else if api_version >= ApiVersion::V1_1 {
+        TargetEnv::Vulkan_1_1
+    } else {
+        TargetEnv::Vulkan_1_0
+    };
+    val::create(Some(target_env))
+        .validate(code, None)
+        .map_err(|e| ShaderError::Validation(e.to_string()))
+}
+
+// Entry Points
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderEntryPoint {
+    pub name: CString,
+    /// Empty if the SPIR-V execution model doesn't correspond to a vulkan shader stage.
+    pub stage: vk::ShaderStageFlags,
+}
+
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+const SPIRV_HEADER_WORD_COUNT: usize = 5;
+const SPIRV_OP_MEMBER_NAME: u32 = 6;
+const SPIRV_OP_ENTRY_POINT: u32 = 15;
+const SPIRV_OP_TYPE_POINTER: u32 = 32;
+const SPIRV_OP_FUNCTION: u32 = 54;
+const SPIRV_OP_VARIABLE: u32 = 59;
+const SPIRV_OP_MEMBER_DECORATE: u32 = 72;
+const SPIRV_DECORATION_OFFSET: u32 = 35;
+const SPIRV_STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+
+/// Reads the `OpEntryPoint` instructions from SPIR-V `code`. Returns `None` if the code is
+/// malformed.
+pub fn spirv_entry_points(code: &[u32]) -> Option<Vec<ShaderEntryPoint>> {
+    if code.len() < SPIRV_HEADER_WORD_COUNT || code[0] != SPIRV_MAGIC_NUMBER {
+        return None;
+    }
+
+    let mut entry_points = Vec::new();
+    let mut word_index = SPIRV_HEADER_WORD_COUNT;
+    while word_index < code.len() {
+        let word_count = (code[word_index] >> 16) as usize;
+        let opcode = code[word_index] & 0xFFFF;
+        if word_count == 0 || word_index + word_count > code.len() {
+            return None;
         }
+
+        match opcode {
+            // entry points are declared before any function definitions
+            SPIRV_OP_FUNCTION => break,
+            SPIRV_OP_ENTRY_POINT if word_count >= 4 => {
+                let instruction = &code[word_index..word_index + word_count];
+                entry_points.push(ShaderEntryPoint {
+                    name: CString::new(spirv_string_bytes(&instruction[3..])).ok()?,
+                    stage: stage_from_execution_model(instruction[1]),
+                });
+            }
+            _ => (),
+        }
+
+        word_index += word_count;
+    }
+
+    Some(entry_points)
+}
+
+/// A named member of a push constant block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushConstantMember {
+    pub name: String,
+    /// Byte offset from the start of the push constant block.
+    pub offset: u32,
+}
+
+/// Reads the names and offsets of the members of push constant blocks from SPIR-V `code`.
+/// Members without a name or offset decoration are skipped. Returns `None` if the code is
+/// malformed.
+pub fn spirv_push_constant_members(code: &[u32]) -> Option<Vec<PushConstantMember>> {
+    if code.len() < SPIRV_HEADER_WORD_COUNT || code[0] != SPIRV_MAGIC_NUMBER {
+        return None;
+    }
+
+    let mut member_names = HashMap::<(u32, u32), String>::new();
+    let mut member_offsets = HashMap::<(u32, u32), u32>::new();
+    let mut pointee_types = HashMap::<u32, u32>::new();
+    let mut push_constant_pointer_types = Vec::<u32>::new();
+
+    let mut word_index = SPIRV_HEADER_WORD_COUNT;
+    while word_index < code.len() {
+        let word_count = (code[word_index] >> 16) as usize;
+        let opcode = code[word_index] & 0xFFFF;
+        if word_count == 0 || word_index + word_count > code.len() {
+            return None;
+        }
+        let instruction = &code[word_index..word_index + word_count];
+
+        match opcode {
+            // types and global variables are declared before any function definitions
+            SPIRV_OP_FUNCTION => break,
+            SPIRV_OP_MEMBER_NAME if word_count >= 4 => {
+                let name = String::from_utf8(spirv_string_bytes(&instruction[3..])).ok()?;
+                member_names.insert((instruction[1], instruction[2]), name);
+            }
+            SPIRV_OP_MEMBER_DECORATE
+                if word_count >= 5 && instruction[3] == SPIRV_DECORATION_OFFSET =>
+            {
+                member_offsets.insert((instruction[1], instruction[2]), instruction[4]);
+            }
+            SPIRV_OP_TYPE_POINTER if word_count >= 4 => {
+                pointee_types.insert(instruction[1], instruction[3]);
+            }
+            SPIRV_OP_VARIABLE
+                if word_count >= 4 && instruction[3] == SPIRV_STORAGE_CLASS_PUSH_CONSTANT =>
+            {
+                push_constant_pointer_types.push(instruction[1]);
+            }
+            _ => (),
+        }
+
+        word_index += word_count;
+    }
+
+    let mut members = Vec::<PushConstantMember>::new();
+    for pointer_type in push_constant_pointer_types {
+        let Some(&block_type) = pointee_types.get(&pointer_type) else {
+            continue;
+        };
+        let mut block_members: Vec<PushConstantMember> = member_offsets
+            .iter()
+            .filter(|((struct_type, _), _)| *struct_type == block_type)
+            .filter_map(|(key, &offset)| {
+                let name = member_names.get(key)?.clone();
+                Some(PushConstantMember { name, offset })
+            })
+            .filter(|member| !members.contains(member))
+            .collect();
+        block_members.sort_by_key(|member| member.offset);
+        members.extend(block_members);
+    }
+
+    Some(members)
+}
+
+/// Nul terminated SPIR-V literal string.
+fn spirv_string_bytes(words: &[u32]) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect()
+}
+
+fn stage_from_execution_model(execution_model: u32) -> vk::ShaderStageFlags {
+    match execution_model {
+        0 => vk::ShaderStageFlags::VERTEX,
+        1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        3 => vk::ShaderStageFlags::GEOMETRY,
+        4 => vk::ShaderStageFlags::FRAGMENT,
+        5 => vk::ShaderStageFlags::COMPUTE,
+        5267 | 5364 => vk::ShaderStageFlags::TASK_EXT,
+        5268 | 5365 => vk::ShaderStageFlags::MESH_EXT,
+        5313 => vk::ShaderStageFlags::RAYGEN_KHR,
+        5314 => vk::ShaderStageFlags::INTERSECTION_KHR,
+        5315 => vk::ShaderStageFlags::ANY_HIT_KHR,
+        5316 => vk::ShaderStageFlags::CLOSEST_HIT_KHR,
+        5317 => vk::ShaderStageFlags::MISS_KHR,
+        5318 => vk::ShaderStageFlags::CALLABLE_KHR,
+        _ => vk::ShaderStageFlags::empty(),
     }
 }
 
@@ -100,26 +450,100 @@ pub struct ShaderStage<'a> {
 }
 
 impl<'a> ShaderStage<'a> {
+    /// Checks that `stage` is a single shader stage bit and that `module` has a matching entry
+    /// point called `entry_point` (when the module's entry points could be read).
     pub fn new(
         stage: vk::ShaderStageFlags,
         module: Arc<ShaderModule>,
         entry_point: CString,
         specialization_info: Option<vk::SpecializationInfo<'a>>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, ShaderError> {
+        if stage.as_raw().count_ones() != 1 {
+            return Err(ShaderError::InvalidStageFlags(stage));
+        }
+        if !module.has_entry_point(&entry_point, stage) {
+            return Err(ShaderError::EntryPointNotFound { entry_point, stage });
+        }
+
+        Ok(Self {
             flags: vk::PipelineShaderStageCreateFlags::empty(),
             stage,
             module,
             entry_point,
             write_specialization_info: specialization_info.is_some(),
             specialization_info: specialization_info.unwrap_or_default(),
-        }
+        })
+    }
+
+    /// Uses the entry point called `entry_point` of a module which may contain several (e.g. a
+    /// vertex and fragment shader in one SPIR-V file as commonly emitted by slang or naga). The
+    /// stage is taken from the module's reflection data which also provides the name as a
+    /// `CString` so no conversion is needed.
+    ///
+    /// Fails if the module has no such entry point, declares it for multiple stages (use
+    /// [`Self::with_stage_entry`] instead) or the SPIR-V code couldn't be parsed.
+    pub fn with_entry(module: Arc<ShaderModule>, entry_point: &str) -> Result<Self, ShaderError> {
+        let entry_points = module
+            .entry_points()
+            .ok_or_else(|| ShaderError::EntryPointStageUnknown(entry_point.to_string()))?;
+        let reflected = unique_entry_point(entry_points, entry_point)?;
+
+        let stage = reflected.stage;
+        let entry_point = reflected.name.clone();
+        Self::new(stage, module, entry_point, None)
+    }
+
+    /// Same as [`Self::new`] but takes the entry point name as a `&str`. The `CString` is taken
+    /// from the module's reflection data when available.
+    pub fn with_stage_entry(
+        stage: vk::ShaderStageFlags,
+        module: Arc<ShaderModule>,
+        entry_point: &str,
+    ) -> Result<Self, ShaderError> {
+        let entry_point_cstring = match module
+            .entry_points_named(entry_point)
+            .find(|reflected| reflected.stage == stage)
+        {
+            Some(reflected) => reflected.name.clone(),
+            None => entry_point_cstring(entry_point)?,
+        };
+        Self::new(stage, module, entry_point_cstring, None)
+    }
+
+    /// Vertex stage with the entry point "main".
+    pub fn vertex(module: Arc<ShaderModule>) -> Result<Self, ShaderError> {
+        Self::new(
+            vk::ShaderStageFlags::VERTEX,
+            module,
+            default_entry_point(),
+            None,
+        )
+    }
+
+    /// Fragment stage with the entry point "main".
+    pub fn fragment(module: Arc<ShaderModule>) -> Result<Self, ShaderError> {
+        Self::new(
+            vk::ShaderStageFlags::FRAGMENT,
+            module,
+            default_entry_point(),
+            None,
+        )
+    }
+
+    /// Compute stage with the entry point "main".
+    pub fn compute(module: Arc<ShaderModule>) -> Result<Self, ShaderError> {
+        Self::new(
+            vk::ShaderStageFlags::COMPUTE,
+            module,
+            default_entry_point(),
+            None,
+        )
     }
 
     pub fn write_create_info<'b>(
         &'b self,
         create_info: vk::PipelineShaderStageCreateInfo<'b>,
-    ) -> vk::PipelineShaderStageCreateInfo {
+    ) -> vk::PipelineShaderStageCreateInfo<'b> {
         let create_info = create_info
             .flags(self.flags)
             .module(self.module.handle())
@@ -132,18 +556,131 @@ impl<'a> ShaderStage<'a> {
         }
     }
 
-    pub fn create_info(&self) -> vk::PipelineShaderStageCreateInfo {
+    /// Panics if the module is lazy and fails to be created. See [`Self::try_create_info`].
+    pub fn create_info(&self) -> vk::PipelineShaderStageCreateInfo<'_> {
         self.write_create_info(vk::PipelineShaderStageCreateInfo::default())
     }
+
+    /// Same as [`Self::create_info`] but returns the error if the module is lazy and
+    /// `vkCreateShaderModule` fails. Used by pipeline creation.
+    pub fn try_create_info(&self) -> VkResult<vk::PipelineShaderStageCreateInfo<'_>> {
+        self.module.try_handle_vk()?;
+        Ok(self.create_info())
+    }
+}
+
+/// An owned copy of a [`ShaderStage`] (including its specialization constants) that keeps the
+/// shader module alive so pipelines can be recreated from it later.
+#[derive(Clone)]
+pub struct RetainedShaderStage {
+    pub flags: vk::PipelineShaderStageCreateFlags,
+    pub stage: vk::ShaderStageFlags,
+    pub module: Arc<ShaderModule>,
+    pub entry_point: CString,
+    /// `None` if the shader stage had no specialization info.
+    pub specialization: Option<(Vec<vk::SpecializationMapEntry>, Vec<u8>)>,
+}
+
+impl RetainedShaderStage {
+    pub fn shader_stage(&self) -> ShaderStage<'_> {
+        let specialization_info = self.specialization.as_ref().map(|(map_entries, data)| {
+            vk::SpecializationInfo::default()
+                .map_entries(map_entries)
+                .data(data)
+        });
+        ShaderStage {
+            flags: self.flags,
+            stage: self.stage,
+            module: self.module.clone(),
+            entry_point: self.entry_point.clone(),
+            write_specialization_info: specialization_info.is_some(),
+            specialization_info: specialization_info.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&ShaderStage<'_>> for RetainedShaderStage {
+    fn from(shader_stage: &ShaderStage<'_>) -> Self {
+        let specialization = shader_stage.write_specialization_info.then(|| {
+            let info = &shader_stage.specialization_info;
+            // safety: the pointers are valid for the lifetime of `shader_stage`
+            let map_entries = if info.p_map_entries.is_null() {
+                Vec::new()
+            } else {
+                unsafe {
+                    std::slice::from_raw_parts(info.p_map_entries, info.map_entry_count as usize)
+                }
+                .to_vec()
+            };
+            let data = if info.p_data.is_null() {
+                Vec::new()
+            } else {
+                unsafe { std::slice::from_raw_parts(info.p_data as *const u8, info.data_size) }
+                    .to_vec()
+            };
+            (map_entries, data)
+        });
+
+        Self {
+            flags: shader_stage.flags,
+            stage: shader_stage.stage,
+            module: shader_stage.module.clone(),
+            entry_point: shader_stage.entry_point.clone(),
+            specialization,
+        }
+    }
+}
+
+fn default_entry_point() -> CString {
+    CString::new("main").expect("no interior nul bytes")
+}
+
+/// The only entry point called `name` in `entry_points`.
+fn unique_entry_point<'e>(
+    entry_points: &'e [ShaderEntryPoint],
+    name: &str,
+) -> Result<&'e ShaderEntryPoint, ShaderError> {
+    let mut matching_entry_points = entry_points
+        .iter()
+        .filter(|entry_point| entry_point.name.as_bytes() == name.as_bytes());
+    let Some(first_match) = matching_entry_points.next() else {
+        return Err(ShaderError::EntryPointNotFound {
+            entry_point: entry_point_cstring(name)?,
+            stage: vk::ShaderStageFlags::ALL,
+        });
+    };
+    if matching_entry_points.next().is_some() {
+        return Err(ShaderError::EntryPointStageUnknown(name.to_string()));
+    }
+    Ok(first_match)
+}
+
+fn entry_point_cstring(entry_point: &str) -> Result<CString, ShaderError> {
+    CString::new(entry_point).map_err(ShaderError::EntryPointNameConversion)
 }
 
 // Errors
 
 #[derive(Debug)]
 pub enum ShaderError {
-    FileRead { e: io::Error, path: String },
+    FileRead {
+        e: io::Error,
+        path: String,
+    },
     SpirVDecode(io::Error),
     Creation(vk::Result),
+    /// Shader stages must have exactly one stage bit set.
+    InvalidStageFlags(vk::ShaderStageFlags),
+    EntryPointNotFound {
+        entry_point: CString,
+        stage: vk::ShaderStageFlags,
+    },
+    /// The stage of the entry point couldn't be determined because the module declares it for
+    /// multiple stages or its entry points couldn't be read.
+    EntryPointStageUnknown(String),
+    EntryPointNameConversion(NulError),
+    /// SPIR-V validation failed. Only returned with the `spirv-val` feature in debug builds.
+    Validation(String),
 }
 
 impl fmt::Display for ShaderError {
@@ -154,6 +691,25 @@ impl fmt::Display for ShaderError {
             }
             Self::SpirVDecode(e) => write!(f, "failed to decode spirv: {}", e),
             Self::Creation(e) => write!(f, "shader module creation failed: {}", e),
+            Self::InvalidStageFlags(stage) => write!(
+                f,
+                "shader stage flags {:?} must contain exactly one stage",
+                stage
+            ),
+            Self::EntryPointNotFound { entry_point, stage } => write!(
+                f,
+                "shader module has no {:?} entry point called {:?}",
+                stage, entry_point
+            ),
+            Self::EntryPointStageUnknown(entry_point) => write!(
+                f,
+                "couldn't determine the shader stage of entry point {:?}",
+                entry_point
+            ),
+            Self::EntryPointNameConversion(e) => {
+                write!(f, "failed to convert entry point name to c string: {}", e)
+            }
+            Self::Validation(message) => write!(f, "spirv validation failed: {}", message),
         }
     }
 }
@@ -164,6 +720,124 @@ impl error::Error for ShaderError {
             Self::FileRead { e, .. } => Some(e),
             Self::SpirVDecode(e) => Some(e),
             Self::Creation(e) => Some(e),
+            Self::InvalidStageFlags(_) => None,
+            Self::EntryPointNotFound { .. } => None,
+            Self::EntryPointStageUnknown(_) => None,
+            Self::EntryPointNameConversion(e) => Some(e),
+            Self::Validation(_) => None,
         }
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn spirv_entry_points_reads_name_and_stage() {
+    let code = [
+        SPIRV_MAGIC_NUMBER,
+        0x0001_0000,
+        0,
+        8,
+        0,
+        // OpEntryPoint Fragment %4 "main" (the name is followed by a nul terminator word)
+        (5 << 16) | SPIRV_OP_ENTRY_POINT,
+        4,
+        4,
+        u32::from_le_bytes(*b"main"),
+        0,
+    ];
+
+    let entry_points = spirv_entry_points(&code).unwrap();
+    assert_eq!(
+        entry_points,
+        vec![ShaderEntryPoint {
+            name: CString::new("main").unwrap(),
+            stage: vk::ShaderStageFlags::FRAGMENT,
+        }]
+    );
+    assert!(spirv_entry_points(&code[..4]).is_none());
+}
+
+#[test]
+fn spirv_push_constant_members_reads_names_and_offsets() {
+    let code = [
+        SPIRV_MAGIC_NUMBER,
+        0x0001_0000,
+        0,
+        8,
+        0,
+        // OpMemberName %2 1 "tint"
+        (5 << 16) | SPIRV_OP_MEMBER_NAME,
+        2,
+        1,
+        u32::from_le_bytes(*b"tint"),
+        0,
+        // OpMemberName %2 0 "mvp"
+        (4 << 16) | SPIRV_OP_MEMBER_NAME,
+        2,
+        0,
+        u32::from_le_bytes(*b"mvp\0"),
+        // OpMemberDecorate %2 0 Offset 0
+        (5 << 16) | SPIRV_OP_MEMBER_DECORATE,
+        2,
+        0,
+        SPIRV_DECORATION_OFFSET,
+        0,
+        // OpMemberDecorate %2 1 Offset 64
+        (5 << 16) | SPIRV_OP_MEMBER_DECORATE,
+        2,
+        1,
+        SPIRV_DECORATION_OFFSET,
+        64,
+        // %3 = OpTypePointer PushConstant %2
+        (4 << 16) | SPIRV_OP_TYPE_POINTER,
+        3,
+        SPIRV_STORAGE_CLASS_PUSH_CONSTANT,
+        2,
+        // %4 = OpVariable %3 PushConstant
+        (4 << 16) | SPIRV_OP_VARIABLE,
+        3,
+        4,
+        SPIRV_STORAGE_CLASS_PUSH_CONSTANT,
+    ];
+
+    let members = spirv_push_constant_members(&code).unwrap();
+    assert_eq!(
+        members,
+        vec![
+            PushConstantMember {
+                name: "mvp".to_string(),
+                offset: 0
+            },
+            PushConstantMember {
+                name: "tint".to_string(),
+                offset: 64
+            },
+        ]
+    );
+}
+
+#[test]
+fn unique_entry_point_picks_stage_by_name() {
+    let entry_point = |name: &str, stage| ShaderEntryPoint {
+        name: CString::new(name).unwrap(),
+        stage,
+    };
+    let entry_points = [
+        entry_point("vs_main", vk::ShaderStageFlags::VERTEX),
+        entry_point("fs_main", vk::ShaderStageFlags::FRAGMENT),
+        entry_point("main", vk::ShaderStageFlags::VERTEX),
+        entry_point("main", vk::ShaderStageFlags::FRAGMENT),
+    ];
+
+    let fragment = unique_entry_point(&entry_points, "fs_main").unwrap();
+    assert_eq!(fragment.stage, vk::ShaderStageFlags::FRAGMENT);
+    assert!(matches!(
+        unique_entry_point(&entry_points, "main"),
+        Err(ShaderError::EntryPointStageUnknown(_))
+    ));
+    assert!(matches!(
+        unique_entry_point(&entry_points, "cs_main"),
+        Err(ShaderError::EntryPointNotFound { .. })
+    ));
+}
@@ -0,0 +1,16 @@
+#version 450
+
+// Fragment shader for `UiRenderer`. Vertex colors and textures are premultiplied alpha.
+//
+// Compile with: glslc -O ui.frag -o ui.frag.spv
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec4 in_color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+void main() {
+	out_color = in_color * texture(tex, in_uv);
+}
@@ -0,0 +1,22 @@
+#version 450
+
+// Vertex shader for `UiRenderer`. Positions are in points with the origin at the top left.
+//
+// Compile with: glslc -O ui.vert -o ui.vert.spv
+
+layout(location = 0) in vec2 in_pos;
+layout(location = 1) in vec2 in_uv;
+layout(location = 2) in vec4 in_color;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_color;
+
+layout(push_constant) uniform PushConstants {
+	vec2 screen_size_points;
+} pc;
+
+void main() {
+	gl_Position = vec4(in_pos * 2.0 / pc.screen_size_points - 1.0, 0.0, 1.0);
+	out_uv = in_uv;
+	out_color = in_color;
+}
@@ -0,0 +1,185 @@
+use crate::{Device, DeviceError, DeviceOwned};
+use log::warn;
+use std::{
+    any::Any,
+    mem,
+    sync::{Arc, Weak},
+};
+
+/// Helps to shut down in the right order: the device is waited on before anything registered
+/// here is destroyed. Pass this to [`Device::shutdown`] before dropping your remaining resources.
+///
+/// - Resources that may still be in use by the GPU can be handed over with [`Self::defer_drop`]
+///   rather than being dropped immediately. They are dropped after the device is idle.
+/// - Resources registered with [`Self::track`] are checked at shutdown and any that are still
+///   alive (i.e. something is holding onto an `Arc`) are logged with their name.
+///
+/// [`DeviceResources::defer_to_shutdown`](crate::DeviceResources::defer_to_shutdown) moves the
+/// contents of a [`DeviceResources`](crate::DeviceResources) deletion queue here.
+///
+/// If this is dropped without calling [`Device::shutdown`], the deferred resources are dropped
+/// without waiting for the device and a warning is logged.
+#[derive(Default)]
+pub struct ShutdownGuard {
+    deferred_drops: Vec<Box<dyn Any + Send>>,
+    tracked_resources: Vec<TrackedResource>,
+    finished: bool,
+}
+
+struct TrackedResource {
+    name: String,
+    handle_raw: u64,
+    resource: Weak<dyn DeviceOwned + Send + Sync>,
+}
+
+impl ShutdownGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold onto `resource` until [`Device::shutdown`] has waited for the device to be idle.
+    pub fn defer_drop<T: Send + 'static>(&mut self, resource: T) {
+        self.deferred_drops.push(Box::new(resource));
+    }
+
+    /// Register `resource` to be checked for leaks at shutdown. Only a weak reference is kept so
+    /// this doesn't affect the lifetime of `resource`.
+    pub fn track<T>(&mut self, name: impl Into<String>, resource: &Arc<T>)
+    where
+        T: DeviceOwned + Send + Sync + 'static,
+    {
+        let handle_raw = resource.handle_raw();
+        let resource: Arc<dyn DeviceOwned + Send + Sync> = resource.clone();
+        self.tracked_resources.push(TrackedResource {
+            name: name.into(),
+            handle_raw,
+            resource: Arc::downgrade(&resource),
+        });
+    }
+
+    /// Tracked resources that still have strong references.
+    pub fn live_resources(&self) -> Vec<LiveResource> {
+        self.tracked_resources
+            .iter()
+            .filter_map(|tracked| {
+                let strong_count = tracked.resource.strong_count();
+                if strong_count == 0 {
+                    return None;
+                }
+                Some(LiveResource {
+                    name: tracked.name.clone(),
+                    handle_raw: tracked.handle_raw,
+                    strong_count,
+                })
+            })
+            .collect()
+    }
+
+    fn finish(&mut self) -> ShutdownReport {
+        self.finished = true;
+
+        let deferred_drop_count = self.deferred_drops.len();
+        self.deferred_drops.clear();
+
+        let live_resources = self.live_resources();
+        for live_resource in &live_resources {
+            warn!(
+                "resource '{}' (handle = {:#x}) is still alive at shutdown with {} strong reference(s)",
+                live_resource.name, live_resource.handle_raw, live_resource.strong_count
+            );
+        }
+        self.tracked_resources.clear();
+
+        ShutdownReport {
+            deferred_drop_count,
+            live_resources,
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn deferred_drop_count(&self) -> usize {
+        self.deferred_drops.len()
+    }
+
+    #[inline]
+    pub fn tracked_resource_count(&self) -> usize {
+        self.tracked_resources.len()
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        if !self.finished && !self.deferred_drops.is_empty() {
+            warn!(
+                "shutdown guard dropped without calling Device::shutdown. {} deferred resource(s) are being dropped without waiting for the device to be idle",
+                self.deferred_drops.len()
+            );
+        }
+    }
+}
+
+/// Returned by [`Device::shutdown`].
+#[derive(Debug, Clone)]
+pub struct ShutdownReport {
+    /// Number of resources passed to [`ShutdownGuard::defer_drop`] that were dropped.
+    pub deferred_drop_count: usize,
+    /// Tracked resources that were still alive after the deferred resources were dropped.
+    pub live_resources: Vec<LiveResource>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LiveResource {
+    pub name: String,
+    pub handle_raw: u64,
+    pub strong_count: usize,
+}
+
+impl Device {
+    /// Waits for the device to be idle, drops the resources deferred in `guard` and then logs a
+    /// warning for each tracked resource that is still alive. Call this before dropping any other
+    /// device resources.
+    ///
+    /// If waiting for the device fails (e.g. device lost) the deferred resources are leaked rather
+    /// than destroyed while they may still be in use.
+    pub fn shutdown(&self, mut guard: ShutdownGuard) -> Result<ShutdownReport, DeviceError> {
+        if let Err(e) = self.wait_idle() {
+            guard.finished = true;
+            for deferred in guard.deferred_drops.drain(..) {
+                mem::forget(deferred);
+            }
+            return Err(e);
+        }
+        Ok(guard.finish())
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn shutdown_guard_reports_live_resources() {
+    struct DummyResource(u64);
+    impl DeviceOwned for DummyResource {
+        fn device(&self) -> &Arc<Device> {
+            unimplemented!()
+        }
+        fn handle_raw(&self) -> u64 {
+            self.0
+        }
+    }
+
+    let leaked = Arc::new(DummyResource(1));
+    let released = Arc::new(DummyResource(2));
+
+    let mut guard = ShutdownGuard::new();
+    guard.track("leaked", &leaked);
+    guard.track("released", &released);
+    guard.defer_drop(released);
+
+    let report = guard.finish();
+    assert_eq!(report.deferred_drop_count, 1);
+    assert_eq!(report.live_resources.len(), 1);
+    assert_eq!(report.live_resources[0].name, "leaked");
+    assert_eq!(report.live_resources[0].strong_count, 1);
+}
@@ -0,0 +1,586 @@
+use ash::vk::{self, Handle};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+/// A semaphore waited on or signalled by a [`SubmissionEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemaphoreOperation {
+    pub semaphore: vk::Semaphore,
+    /// Timeline value. `None` for binary semaphores.
+    pub value: Option<u64>,
+    /// Wait stage mask. Empty for signal operations.
+    pub stage_mask: vk::PipelineStageFlags,
+}
+
+/// A queue or swapchain operation captured by a [`SubmissionRecorder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmissionEvent {
+    Submit {
+        queue: vk::Queue,
+        command_buffers: Vec<vk::CommandBuffer>,
+        waits: Vec<SemaphoreOperation>,
+        signals: Vec<SemaphoreOperation>,
+        fence: Option<vk::Fence>,
+    },
+    Acquire {
+        swapchain: vk::SwapchainKHR,
+        /// `None` if the acquire failed or timed out.
+        image_index: Option<u32>,
+        /// The semaphore passed to the acquire. Only signalled if an image was acquired.
+        signal: Option<vk::Semaphore>,
+        fence: Option<vk::Fence>,
+    },
+    Present {
+        queue: vk::Queue,
+        swapchains: Vec<(vk::SwapchainKHR, u32)>,
+        waits: Vec<vk::Semaphore>,
+    },
+    /// Timeline semaphore signalled from the host.
+    HostSignal {
+        semaphore: vk::Semaphore,
+        value: u64,
+    },
+}
+
+impl SubmissionEvent {
+    fn waits(&self) -> Vec<SemaphoreOperation> {
+        match self {
+            Self::Submit { waits, .. } => waits.clone(),
+            Self::Present { waits, .. } => waits
+                .iter()
+                .map(|&semaphore| SemaphoreOperation {
+                    semaphore,
+                    value: None,
+                    stage_mask: vk::PipelineStageFlags::empty(),
+                })
+                .collect(),
+            Self::Acquire { .. } | Self::HostSignal { .. } => Vec::new(),
+        }
+    }
+
+    fn signals(&self) -> Vec<SemaphoreOperation> {
+        match self {
+            Self::Submit { signals, .. } => signals.clone(),
+            Self::Acquire {
+                image_index: Some(_),
+                signal: Some(semaphore),
+                ..
+            } => vec![SemaphoreOperation {
+                semaphore: *semaphore,
+                value: None,
+                stage_mask: vk::PipelineStageFlags::empty(),
+            }],
+            Self::HostSignal { semaphore, value } => vec![SemaphoreOperation {
+                semaphore: *semaphore,
+                value: Some(*value),
+                stage_mask: vk::PipelineStageFlags::empty(),
+            }],
+            // a failed or timed out acquire doesn't signal anything
+            Self::Acquire { .. } | Self::Present { .. } => Vec::new(),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Submit {
+                queue,
+                command_buffers,
+                fence,
+                ..
+            } => {
+                let mut label = format!(
+                    "submit\\nqueue {:#x}\\n{} command buffer(s)",
+                    queue.as_raw(),
+                    command_buffers.len()
+                );
+                if let Some(fence) = fence {
+                    let _ = write!(label, "\\nfence {:#x}", fence.as_raw());
+                }
+                label
+            }
+            Self::Acquire {
+                swapchain,
+                image_index,
+                ..
+            } => match image_index {
+                Some(image_index) => format!(
+                    "acquire\\nswapchain {:#x}\\nimage {}",
+                    swapchain.as_raw(),
+                    image_index
+                ),
+                None => format!("acquire (failed)\\nswapchain {:#x}", swapchain.as_raw()),
+            },
+            Self::Present {
+                queue, swapchains, ..
+            } => {
+                let mut label = format!("present\\nqueue {:#x}", queue.as_raw());
+                for (swapchain, image_index) in swapchains {
+                    let _ = write!(
+                        label,
+                        "\\nswapchain {:#x} image {}",
+                        swapchain.as_raw(),
+                        image_index
+                    );
+                }
+                label
+            }
+            Self::HostSignal { semaphore, value } => format!(
+                "host signal\\nsemaphore {:#x} = {}",
+                semaphore.as_raw(),
+                value
+            ),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Submit { .. } => "submit",
+            Self::Acquire { .. } => "acquire",
+            Self::Present { .. } => "present",
+            Self::HostSignal { .. } => "host_signal",
+        }
+    }
+}
+
+/// A semaphore dependency between two [`SubmissionEvent`]s, by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubmissionEdge {
+    pub signal_event: usize,
+    pub wait_event: usize,
+    pub semaphore: vk::Semaphore,
+    pub value: Option<u64>,
+}
+
+/// Queue submits, swapchain acquires/presents and host timeline signals recorded by a
+/// [`SubmissionRecorder`], with the semaphore dependencies between them. Export with
+/// [`Self::to_dot`] (graphviz) or [`Self::to_json`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubmissionGraph {
+    pub events: Vec<SubmissionEvent>,
+}
+
+impl SubmissionGraph {
+    /// Matches each wait with the signal it depends on: the latest preceding signal of a binary
+    /// semaphore or the first signal of a timeline semaphore reaching the waited value. Waits on
+    /// semaphores signalled before recording started have no edge.
+    pub fn edges(&self) -> Vec<SubmissionEdge> {
+        // semaphore -> (event index, value) of each signal so far
+        let mut signals = HashMap::<vk::Semaphore, Vec<(usize, Option<u64>)>>::new();
+        let mut edges = Vec::new();
+
+        for (event_index, event) in self.events.iter().enumerate() {
+            for wait in event.waits() {
+                let Some(semaphore_signals) = signals.get(&wait.semaphore) else {
+                    continue;
+                };
+                let signal = match wait.value {
+                    Some(wait_value) => semaphore_signals
+                        .iter()
+                        .find(|(_, signal_value)| signal_value.unwrap_or(0) >= wait_value),
+                    None => semaphore_signals.last(),
+                };
+                if let Some(&(signal_event, _)) = signal {
+                    edges.push(SubmissionEdge {
+                        signal_event,
+                        wait_event: event_index,
+                        semaphore: wait.semaphore,
+                        value: wait.value,
+                    });
+                }
+            }
+            for signal in event.signals() {
+                signals
+                    .entry(signal.semaphore)
+                    .or_default()
+                    .push((event_index, signal.value));
+            }
+        }
+
+        edges
+    }
+
+    /// Graphviz representation. Render with e.g. `dot -Tsvg graph.dot -o graph.svg`.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph submissions {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (event_index, event) in self.events.iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "    e{} [label=\"#{} {}\"];",
+                event_index,
+                event_index,
+                event.label()
+            );
+        }
+        for edge in self.edges() {
+            let mut label = format!("{:#x}", edge.semaphore.as_raw());
+            if let Some(value) = edge.value {
+                let _ = write!(label, " >= {}", value);
+            }
+            let _ = writeln!(
+                dot,
+                "    e{} -> e{} [label=\"{}\"];",
+                edge.signal_event, edge.wait_event, label
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// JSON object with `events` and `edges` arrays. Handles are written as numbers.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"events\":[");
+        for (event_index, event) in self.events.iter().enumerate() {
+            if event_index > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"index\":{},\"kind\":\"{}\"",
+                event_index,
+                event.kind()
+            );
+            match event {
+                SubmissionEvent::Submit {
+                    queue,
+                    command_buffers,
+                    fence,
+                    ..
+                } => {
+                    let _ = write!(
+                        json,
+                        ",\"queue\":{},\"command_buffers\":{},\"fence\":{}",
+                        queue.as_raw(),
+                        json_array(command_buffers.iter().map(|c| c.as_raw())),
+                        json_option(fence.map(|f| f.as_raw()))
+                    );
+                }
+                SubmissionEvent::Acquire {
+                    swapchain,
+                    image_index,
+                    fence,
+                    ..
+                } => {
+                    let _ = write!(
+                        json,
+                        ",\"swapchain\":{},\"image_index\":{},\"fence\":{}",
+                        swapchain.as_raw(),
+                        json_option(*image_index),
+                        json_option(fence.map(|f| f.as_raw()))
+                    );
+                }
+                SubmissionEvent::Present {
+                    queue, swapchains, ..
+                } => {
+                    let _ = write!(
+                        json,
+                        ",\"queue\":{},\"swapchains\":{},\"image_indices\":{}",
+                        queue.as_raw(),
+                        json_array(swapchains.iter().map(|(s, _)| s.as_raw())),
+                        json_array(swapchains.iter().map(|(_, i)| *i))
+                    );
+                }
+                SubmissionEvent::HostSignal { .. } => (),
+            }
+            let _ = write!(
+                json,
+                ",\"waits\":{},\"signals\":{}}}",
+                json_semaphore_operations(&event.waits()),
+                json_semaphore_operations(&event.signals())
+            );
+        }
+        json.push_str("],\"edges\":[");
+        for (edge_index, edge) in self.edges().iter().enumerate() {
+            if edge_index > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"from\":{},\"to\":{},\"semaphore\":{},\"value\":{}}}",
+                edge.signal_event,
+                edge.wait_event,
+                edge.semaphore.as_raw(),
+                json_option(edge.value)
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Opt-in per-device recorder of queue submits, swapchain acquires/presents and host timeline
+/// signals made through bort, for debugging frame synchronization. Disabled by default; enable
+/// with [`Device::set_submission_recording`](crate::Device::set_submission_recording) and
+/// collect a frame with [`Device::take_submission_graph`](crate::Device::take_submission_graph).
+#[derive(Debug, Default)]
+pub struct SubmissionRecorder {
+    enabled: AtomicBool,
+    events: Mutex<Vec<SubmissionEvent>>,
+}
+
+impl SubmissionRecorder {
+    #[inline]
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the events recorded so far and starts a new graph.
+    pub fn take_graph(&self) -> SubmissionGraph {
+        let events = std::mem::take(&mut *self.lock_events());
+        SubmissionGraph { events }
+    }
+
+    pub(crate) fn record(&self, event: impl FnOnce() -> SubmissionEvent) {
+        if self.is_enabled() {
+            self.lock_events().push(event());
+        }
+    }
+
+    pub(crate) fn record_submits(
+        &self,
+        queue: vk::Queue,
+        submit_infos: &[vk::SubmitInfo<'_>],
+        fence: Option<vk::Fence>,
+    ) {
+        for submit_info in submit_infos {
+            self.record(|| submit_event(queue, submit_info, fence));
+        }
+    }
+
+    /// Only called by [`Swapchain::queue_present`](crate::Swapchain::queue_present).
+    #[cfg_attr(not(feature = "presentation"), allow(dead_code))]
+    pub(crate) fn record_present(&self, queue: vk::Queue, present_info: &vk::PresentInfoKHR<'_>) {
+        self.record(|| {
+            let swapchains = raw_slice(present_info.p_swapchains, present_info.swapchain_count);
+            let image_indices =
+                raw_slice(present_info.p_image_indices, present_info.swapchain_count);
+            SubmissionEvent::Present {
+                queue,
+                swapchains: swapchains
+                    .iter()
+                    .copied()
+                    .zip(image_indices.iter().copied())
+                    .collect(),
+                waits: raw_slice(
+                    present_info.p_wait_semaphores,
+                    present_info.wait_semaphore_count,
+                )
+                .to_vec(),
+            }
+        });
+    }
+
+    fn lock_events(&self) -> std::sync::MutexGuard<'_, Vec<SubmissionEvent>> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Helper Functions
+
+pub(crate) fn submit_event(
+    queue: vk::Queue,
+    submit_info: &vk::SubmitInfo<'_>,
+    fence: Option<vk::Fence>,
+) -> SubmissionEvent {
+    let timeline_info = timeline_semaphore_submit_info(submit_info);
+    let wait_values = timeline_info
+        .map(|info| {
+            raw_slice(
+                info.p_wait_semaphore_values,
+                info.wait_semaphore_value_count,
+            )
+        })
+        .unwrap_or_default();
+    let signal_values = timeline_info
+        .map(|info| {
+            raw_slice(
+                info.p_signal_semaphore_values,
+                info.signal_semaphore_value_count,
+            )
+        })
+        .unwrap_or_default();
+
+    let wait_stage_masks = raw_slice(
+        submit_info.p_wait_dst_stage_mask,
+        submit_info.wait_semaphore_count,
+    );
+    let waits = raw_slice(
+        submit_info.p_wait_semaphores,
+        submit_info.wait_semaphore_count,
+    )
+    .iter()
+    .enumerate()
+    .map(|(i, &semaphore)| SemaphoreOperation {
+        semaphore,
+        value: timeline_value(wait_values, i),
+        stage_mask: wait_stage_masks.get(i).copied().unwrap_or_default(),
+    })
+    .collect();
+    let signals = raw_slice(
+        submit_info.p_signal_semaphores,
+        submit_info.signal_semaphore_count,
+    )
+    .iter()
+    .enumerate()
+    .map(|(i, &semaphore)| SemaphoreOperation {
+        semaphore,
+        value: timeline_value(signal_values, i),
+        stage_mask: vk::PipelineStageFlags::empty(),
+    })
+    .collect();
+
+    SubmissionEvent::Submit {
+        queue,
+        command_buffers: raw_slice(
+            submit_info.p_command_buffers,
+            submit_info.command_buffer_count,
+        )
+        .to_vec(),
+        waits,
+        signals,
+        fence,
+    }
+}
+
+/// Binary semaphores have a value of 0 (ignored) in `VkTimelineSemaphoreSubmitInfo`.
+fn timeline_value(values: &[u64], index: usize) -> Option<u64> {
+    values.get(index).copied().filter(|&value| value != 0)
+}
+
+fn timeline_semaphore_submit_info<'a>(
+    submit_info: &'a vk::SubmitInfo<'_>,
+) -> Option<&'a vk::TimelineSemaphoreSubmitInfo<'a>> {
+    let mut next = submit_info.p_next as *const vk::BaseInStructure<'_>;
+    while !next.is_null() {
+        let structure = unsafe { &*next };
+        if structure.s_type == vk::StructureType::TIMELINE_SEMAPHORE_SUBMIT_INFO {
+            return Some(unsafe { &*(next as *const vk::TimelineSemaphoreSubmitInfo<'_>) });
+        }
+        next = structure.p_next;
+    }
+    None
+}
+
+fn raw_slice<'a, T>(pointer: *const T, count: u32) -> &'a [T] {
+    if pointer.is_null() || count == 0 {
+        return &[];
+    }
+    unsafe { std::slice::from_raw_parts(pointer, count as usize) }
+}
+
+fn json_option(value: Option<impl std::fmt::Display>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "null".to_string(),
+    }
+}
+
+fn json_array(values: impl Iterator<Item = impl std::fmt::Display>) -> String {
+    let values: Vec<String> = values.map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_semaphore_operations(operations: &[SemaphoreOperation]) -> String {
+    let operations: Vec<String> = operations
+        .iter()
+        .map(|operation| {
+            format!(
+                "{{\"semaphore\":{},\"value\":{},\"stage_mask\":{}}}",
+                operation.semaphore.as_raw(),
+                json_option(operation.value),
+                operation.stage_mask.as_raw()
+            )
+        })
+        .collect();
+    format!("[{}]", operations.join(","))
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn submission_graph_edges() {
+    let image_available = vk::Semaphore::from_raw(1);
+    let render_finished = vk::Semaphore::from_raw(2);
+    let timeline = vk::Semaphore::from_raw(3);
+    let swapchain = vk::SwapchainKHR::from_raw(4);
+    let queue = vk::Queue::from_raw(5);
+
+    let recorder = SubmissionRecorder::default();
+    recorder.record(|| SubmissionEvent::HostSignal {
+        semaphore: timeline,
+        value: 1,
+    });
+    recorder.set_enabled(true);
+    recorder.record(|| SubmissionEvent::Acquire {
+        swapchain,
+        image_index: Some(0),
+        signal: Some(image_available),
+        fence: None,
+    });
+
+    let wait_semaphores = [image_available, timeline];
+    let wait_stages = [
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+    ];
+    let signal_semaphores = [render_finished];
+    let wait_values = [0, 7];
+    let mut timeline_info =
+        vk::TimelineSemaphoreSubmitInfo::default().wait_semaphore_values(&wait_values);
+    let submit_info = vk::SubmitInfo::default()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .signal_semaphores(&signal_semaphores)
+        .push_next(&mut timeline_info);
+    recorder.record(|| SubmissionEvent::HostSignal {
+        semaphore: timeline,
+        value: 7,
+    });
+    recorder.record_submits(queue, &[submit_info], None);
+
+    let swapchains = [swapchain];
+    let image_indices = [0];
+    let present_info = vk::PresentInfoKHR::default()
+        .wait_semaphores(&signal_semaphores)
+        .swapchains(&swapchains)
+        .image_indices(&image_indices);
+    recorder.record_present(queue, &present_info);
+
+    let graph = recorder.take_graph();
+    // the first host signal was before recording was enabled
+    assert_eq!(graph.events.len(), 4);
+    let edges = graph.edges();
+    assert_eq!(edges.len(), 3);
+    assert_eq!((edges[0].signal_event, edges[0].wait_event), (0, 2));
+    assert_eq!(edges[1].value, Some(7));
+    assert_eq!((edges[1].signal_event, edges[1].wait_event), (1, 2));
+    assert_eq!((edges[2].signal_event, edges[2].wait_event), (2, 3));
+
+    assert!(graph.to_dot().contains("e2 -> e3"));
+    assert!(graph.to_json().contains("\"kind\":\"present\""));
+    assert!(recorder.take_graph().events.is_empty());
+
+    // a timed out acquire doesn't signal its semaphore so nothing can wait on it
+    recorder.record(|| SubmissionEvent::Acquire {
+        swapchain,
+        image_index: None,
+        signal: Some(image_available),
+        fence: None,
+    });
+    let submit_info = vk::SubmitInfo::default()
+        .wait_semaphores(&wait_semaphores[..1])
+        .wait_dst_stage_mask(&wait_stages[..1]);
+    recorder.record_submits(queue, &[submit_info], None);
+    assert!(recorder.take_graph().edges().is_empty());
+}
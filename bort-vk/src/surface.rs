@@ -1,29 +1,39 @@
 //! Uses code from `ash-window` for surface creation from raw window handle.
 //! Original source found (here)[https://github.com/ash-rs/ash/blob/master/ash-window/src/lib.rs]
 
-use crate::{is_format_linear, is_format_srgb, Instance, PhysicalDevice, ALLOCATION_CALLBACK_NONE};
+use crate::{
+    is_format_10bit, is_format_linear, is_format_srgb, Instance, PhysicalDevice,
+    ALLOCATION_CALLBACK_NONE,
+};
 #[cfg(feature = "raw-window-handle-06")]
 use ash::vk::{HINSTANCE, HWND};
-use ash::{
-    khr::{self, android_surface, wayland_surface, win32_surface, xcb_surface, xlib_surface},
-    prelude::VkResult,
-    vk, Entry,
-};
+#[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
+use ash::khr::{android_surface, wayland_surface, win32_surface, xcb_surface, xlib_surface};
+use ash::{ext::headless_surface, khr, prelude::VkResult, vk, Entry};
 #[cfg(feature = "raw-window-handle-05")]
 use raw_window_handle_05::{RawDisplayHandle, RawWindowHandle};
 #[cfg(feature = "raw-window-handle-06")]
 use raw_window_handle_06::{RawDisplayHandle, RawWindowHandle};
-use std::{error, fmt, sync::Arc};
+use std::{
+    error, fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 pub struct Surface {
     handle: vk::SurfaceKHR,
     surface_fns: khr::surface::Instance,
+    /// Set by [`Self::invalidate`].
+    invalidated: AtomicBool,
 
     // dependencies
     instance: Arc<Instance>,
 }
 
 impl Surface {
+    #[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
     pub fn new(
         entry: &Entry,
         instance: Arc<Instance>,
@@ -45,6 +55,32 @@ impl Surface {
         Ok(Self {
             handle,
             surface_fns,
+            invalidated: AtomicBool::new(false),
+
+            instance,
+        })
+    }
+
+    /// Creates a `VK_EXT_headless_surface` surface which isn't tied to a window, e.g. for
+    /// exercising swapchain code in tests and ci. The surface extent is determined by the
+    /// swapchain. The instance must have been created with
+    /// [`Instance::SURFACE_EXTS_HEADLESS`](crate::Instance::SURFACE_EXTS_HEADLESS).
+    pub fn new_headless(
+        entry: &Entry,
+        instance: Arc<Instance>,
+    ) -> Result<Self, SurfaceCreationError> {
+        let headless_surface_fns = headless_surface::Instance::new(entry, instance.inner());
+        let create_info = vk::HeadlessSurfaceCreateInfoEXT::default();
+        let handle = unsafe {
+            headless_surface_fns.create_headless_surface(&create_info, ALLOCATION_CALLBACK_NONE)
+        }?;
+
+        let surface_fns = khr::surface::Instance::new(entry, instance.inner());
+
+        Ok(Self {
+            handle,
+            surface_fns,
+            invalidated: AtomicBool::new(false),
 
             instance,
         })
@@ -94,6 +130,59 @@ impl Surface {
         }
     }
 
+    /// Scaling and gravity supported when presenting with `present_mode`, for
+    /// [`SwapchainProperties::present_scaling`](crate::SwapchainProperties::present_scaling).
+    /// Requires the `VK_KHR_get_surface_capabilities2` and `VK_EXT_surface_maintenance1`
+    /// instance extensions.
+    pub fn present_scaling_capabilities(
+        &self,
+        physical_device: &PhysicalDevice,
+        present_mode: vk::PresentModeKHR,
+    ) -> VkResult<vk::SurfacePresentScalingCapabilitiesEXT<'static>> {
+        let capabilities2_fns = self
+            .instance
+            .extension_loader::<khr::get_surface_capabilities2::Instance>();
+
+        let mut present_mode_info = vk::SurfacePresentModeEXT::default().present_mode(present_mode);
+        let surface_info = vk::PhysicalDeviceSurfaceInfo2KHR::default()
+            .surface(self.handle)
+            .push_next(&mut present_mode_info);
+        let mut scaling_capabilities = vk::SurfacePresentScalingCapabilitiesEXT::default();
+        let mut surface_capabilities =
+            vk::SurfaceCapabilities2KHR::default().push_next(&mut scaling_capabilities);
+        unsafe {
+            capabilities2_fns.get_physical_device_surface_capabilities2(
+                physical_device.handle(),
+                &surface_info,
+                &mut surface_capabilities,
+            )
+        }?;
+
+        Ok(vk::SurfacePresentScalingCapabilitiesEXT::default()
+            .supported_present_scaling(scaling_capabilities.supported_present_scaling)
+            .supported_present_gravity_x(scaling_capabilities.supported_present_gravity_x)
+            .supported_present_gravity_y(scaling_capabilities.supported_present_gravity_y)
+            .min_scaled_image_extent(scaling_capabilities.min_scaled_image_extent)
+            .max_scaled_image_extent(scaling_capabilities.max_scaled_image_extent))
+    }
+
+    /// Marks the surface as no longer backed by a native window e.g. when an Android app is
+    /// paused (`APP_CMD_TERM_WINDOW`) and the `ANativeWindow` is about to be destroyed.
+    /// [`Swapchain::needs_recreation`](crate::Swapchain::needs_recreation) then reports
+    /// `SurfaceLost`.
+    ///
+    /// This doesn't destroy the `vk::SurfaceKHR`, which happens once every `Arc` of this surface
+    /// (including those held by swapchains) is dropped. That must happen before the native
+    /// window is released, see [`Swapchain::suspend`](crate::Swapchain::suspend).
+    pub fn invalidate(&self) {
+        self.invalidated.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_invalidated(&self) -> bool {
+        self.invalidated.load(Ordering::Acquire)
+    }
+
     // Getters
 
     pub fn handle(&self) -> vk::SurfaceKHR {
@@ -135,6 +224,7 @@ impl Surface {
 /// connection, which must not be destroyed for the lifetime of the returned [`vk::SurfaceKHR`].
 ///
 /// [parent/child relation]: https://registry.khronos.org/vulkan/specs/1.3-extensions/html/vkspec.html#fundamentals-objectmodel-lifetime
+#[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
 unsafe fn create_vk_surface(
     entry: &Entry,
     instance: &ash::Instance,
@@ -324,6 +414,149 @@ pub fn get_first_linear_surface_format(
         .find(|vk::SurfaceFormatKHR { format, .. }| is_format_linear(*format))
 }
 
+/// Returns the first surface format with a 10-bit image format (e.g. `A2B10G10R10_UNORM_PACK32`)
+/// in the vec. Returns `None` is there's none.
+pub fn get_first_10bit_surface_format(
+    surface_formats: &[vk::SurfaceFormatKHR],
+) -> Option<vk::SurfaceFormatKHR> {
+    surface_formats
+        .iter()
+        .cloned()
+        .find(|vk::SurfaceFormatKHR { format, .. }| is_format_10bit(*format))
+}
+
+/// Returns the surface format with the highest score according to `score`. If multiple formats
+/// share the highest score, the first one is returned. Returns `None` if `surface_formats` is
+/// empty.
+///
+/// E.g. to prefer 10-bit formats, then SRGB formats:
+/// ```
+/// # use ash::vk;
+/// # use bort_vk::{choose_surface_format, is_format_10bit, is_format_srgb};
+/// # let surface_formats = [vk::SurfaceFormatKHR::default()];
+/// let surface_format = choose_surface_format(&surface_formats, |surface_format| {
+///     match surface_format.format {
+///         format if is_format_10bit(format) => 2,
+///         format if is_format_srgb(format) => 1,
+///         _ => 0,
+///     }
+/// });
+/// # assert!(surface_format.is_some());
+/// ```
+pub fn choose_surface_format<S: Ord>(
+    surface_formats: &[vk::SurfaceFormatKHR],
+    mut score: impl FnMut(&vk::SurfaceFormatKHR) -> S,
+) -> Option<vk::SurfaceFormatKHR> {
+    surface_formats
+        .iter()
+        // reversed so the first of equally scored formats wins
+        .rev()
+        .max_by_key(|surface_format| score(surface_format))
+        .copied()
+}
+
+/// The transfer function (encoding) of a swapchain color space i.e. how the presentation engine
+/// interprets the values written to the swapchain images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferFunction {
+    /// The sRGB curve.
+    Srgb,
+    /// No encoding.
+    Linear,
+    /// SMPTE ST 2084 perceptual quantizer (HDR10, Dolby Vision).
+    Pq,
+    /// Hybrid log-gamma.
+    Hlg,
+    /// Some other curve (e.g. BT.709, gamma 2.2) or pass-through.
+    Other,
+}
+
+/// How shaders should encode colors written to a swapchain image with a given surface format.
+/// See [`Swapchain::color_space_info`](crate::Swapchain::color_space_info).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColorSpaceInfo {
+    pub surface_format: vk::SurfaceFormatKHR,
+    /// The image format is an `_SRGB` format so the hardware applies the sRGB curve on write
+    /// (and blending happens in linear space).
+    pub is_srgb_format: bool,
+    /// The color space supports values outside of the sRGB gamut/brightness range.
+    pub is_hdr: bool,
+    pub transfer_function: TransferFunction,
+}
+
+impl ColorSpaceInfo {
+    pub fn from_surface_format(surface_format: vk::SurfaceFormatKHR) -> Self {
+        let color_space = surface_format.color_space;
+        let transfer_function = match color_space {
+            vk::ColorSpaceKHR::SRGB_NONLINEAR
+            | vk::ColorSpaceKHR::EXTENDED_SRGB_NONLINEAR_EXT
+            | vk::ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT => TransferFunction::Srgb,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT
+            | vk::ColorSpaceKHR::DISPLAY_P3_LINEAR_EXT
+            | vk::ColorSpaceKHR::BT709_LINEAR_EXT
+            | vk::ColorSpaceKHR::BT2020_LINEAR_EXT
+            | vk::ColorSpaceKHR::ADOBERGB_LINEAR_EXT => TransferFunction::Linear,
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT | vk::ColorSpaceKHR::DOLBYVISION_EXT => {
+                TransferFunction::Pq
+            }
+            vk::ColorSpaceKHR::HDR10_HLG_EXT => TransferFunction::Hlg,
+            _ => TransferFunction::Other,
+        };
+        let is_hdr = matches!(
+            color_space,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT
+                | vk::ColorSpaceKHR::EXTENDED_SRGB_NONLINEAR_EXT
+                | vk::ColorSpaceKHR::BT2020_LINEAR_EXT
+                | vk::ColorSpaceKHR::HDR10_ST2084_EXT
+                | vk::ColorSpaceKHR::HDR10_HLG_EXT
+                | vk::ColorSpaceKHR::DOLBYVISION_EXT
+        );
+
+        Self {
+            surface_format,
+            is_srgb_format: is_format_srgb(surface_format.format),
+            is_hdr,
+            transfer_function,
+        }
+    }
+
+    /// Whether fragment shaders should output linear values. `false` means the shader must apply
+    /// [`Self::transfer_function`] itself e.g. sRGB encode before writing to a `_UNORM` swapchain
+    /// image with an sRGB color space.
+    pub fn shader_should_write_linear(&self) -> bool {
+        self.is_srgb_format || self.transfer_function == TransferFunction::Linear
+    }
+
+    /// Whether fixed function blending (see [`ColorBlendState`](crate::ColorBlendState)) operates
+    /// on linear values. If `false`, blending happens on encoded values which will look off for
+    /// e.g. alpha blended gradients, consider rendering to an intermediate linear image instead.
+    pub fn blending_is_linear(&self) -> bool {
+        self.shader_should_write_linear()
+    }
+
+    /// Preprocessor defines describing the required shader output encoding, to be passed to your
+    /// shader compiler (e.g. `glslc -DOUTPUT_ENCODE_SRGB=1`):
+    /// - `OUTPUT_ENCODE_SRGB`: 1 if the shader must apply the sRGB curve itself.
+    /// - `OUTPUT_ENCODE_PQ`: 1 if the shader must apply the ST 2084 PQ curve itself.
+    /// - `OUTPUT_ENCODE_HLG`: 1 if the shader must apply the HLG curve itself.
+    /// - `OUTPUT_HDR`: 1 if the color space is HDR.
+    pub fn shader_defines(&self) -> [(&'static str, &'static str); 4] {
+        let encode = |transfer_function| {
+            if !self.is_srgb_format && self.transfer_function == transfer_function {
+                "1"
+            } else {
+                "0"
+            }
+        };
+        [
+            ("OUTPUT_ENCODE_SRGB", encode(TransferFunction::Srgb)),
+            ("OUTPUT_ENCODE_PQ", encode(TransferFunction::Pq)),
+            ("OUTPUT_ENCODE_HLG", encode(TransferFunction::Hlg)),
+            ("OUTPUT_HDR", if self.is_hdr { "1" } else { "0" }),
+        ]
+    }
+}
+
 // ~~ Errors ~~
 
 #[derive(Clone, Copy, Debug)]
@@ -369,3 +602,70 @@ impl From<vk::Result> for SurfaceCreationError {
         Self::VkResult(res)
     }
 }
+
+// ~~ Tests ~~
+
+#[test]
+fn choose_surface_format_scoring() {
+    let surface_format = |format| vk::SurfaceFormatKHR {
+        format,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    };
+    let unorm = surface_format(vk::Format::B8G8R8A8_UNORM);
+    let srgb = surface_format(vk::Format::B8G8R8A8_SRGB);
+    let ten_bit = surface_format(vk::Format::A2B10G10R10_UNORM_PACK32);
+    let surface_formats = [unorm, srgb, ten_bit];
+
+    assert_eq!(
+        get_first_10bit_surface_format(&surface_formats),
+        Some(ten_bit)
+    );
+    assert_eq!(get_first_10bit_surface_format(&[unorm, srgb]), None);
+
+    let prefer_10bit =
+        |surface_format: &vk::SurfaceFormatKHR| is_format_10bit(surface_format.format);
+    assert_eq!(
+        choose_surface_format(&surface_formats, prefer_10bit),
+        Some(ten_bit)
+    );
+    // ties go to the first format
+    assert_eq!(choose_surface_format(&surface_formats, |_| 0), Some(unorm));
+    assert_eq!(choose_surface_format(&[], |_| 0), None);
+}
+
+#[test]
+fn color_space_info_shader_encoding() {
+    let srgb_format = ColorSpaceInfo::from_surface_format(vk::SurfaceFormatKHR {
+        format: vk::Format::B8G8R8A8_SRGB,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    });
+    assert!(srgb_format.shader_should_write_linear());
+    assert!(!srgb_format.is_hdr);
+    assert_eq!(srgb_format.shader_defines()[0], ("OUTPUT_ENCODE_SRGB", "0"));
+
+    let unorm_format = ColorSpaceInfo::from_surface_format(vk::SurfaceFormatKHR {
+        format: vk::Format::B8G8R8A8_UNORM,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    });
+    assert!(!unorm_format.shader_should_write_linear());
+    assert_eq!(unorm_format.transfer_function, TransferFunction::Srgb);
+    assert_eq!(
+        unorm_format.shader_defines()[0],
+        ("OUTPUT_ENCODE_SRGB", "1")
+    );
+
+    let hdr10 = ColorSpaceInfo::from_surface_format(vk::SurfaceFormatKHR {
+        format: vk::Format::A2B10G10R10_UNORM_PACK32,
+        color_space: vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    });
+    assert!(hdr10.is_hdr);
+    assert!(!hdr10.shader_should_write_linear());
+    assert_eq!(hdr10.shader_defines()[1], ("OUTPUT_ENCODE_PQ", "1"));
+
+    let scrgb = ColorSpaceInfo::from_surface_format(vk::SurfaceFormatKHR {
+        format: vk::Format::R16G16B16A16_SFLOAT,
+        color_space: vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+    });
+    assert!(scrgb.is_hdr);
+    assert!(scrgb.shader_should_write_linear());
+}
@@ -1,26 +1,52 @@
 use crate::{
-    default_component_mapping, default_subresource_range, extent_2d_from_width_height, Device,
-    DeviceOwned, Fence, ImageAccess, ImageDimensions, ImageViewProperties, Queue, Semaphore,
-    Surface, ALLOCATION_CALLBACK_NONE,
+    default_component_mapping, default_subresource_range, extent_2d_from_width_height,
+    instrumentation::trace_span, srgb_unorm_pair, ColorSpaceInfo, Deadline, DeletionQueue, Device,
+    DeviceError, DeviceOwned, Fence, FencePool, FrameOutcome, ImageAccess, ImageDimensions,
+    ImageViewProperties, Queue, Semaphore, SubmissionEvent, Surface, SurfaceCreationError,
+    SwapchainImageIndex, ALLOCATION_CALLBACK_NONE,
 };
+#[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
+use ash::Entry;
 use ash::{
-    khr,
+    ext, khr,
     prelude::VkResult,
     vk::{self, Handle},
 };
+use log::warn;
+#[cfg(feature = "raw-window-handle-05")]
+use raw_window_handle_05::{RawDisplayHandle, RawWindowHandle};
+#[cfg(feature = "raw-window-handle-06")]
+use raw_window_handle_06::{RawDisplayHandle, RawWindowHandle};
 use std::{
     cmp::{max, min},
     error, fmt,
-    sync::Arc,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
 };
 
+/// Longest time the swapchain lock is held by a single `vkAcquireNextImageKHR` call. Blocking
+/// acquires are split into calls of at most this long so a present from another thread (which
+/// the acquire may be waiting on) can take the lock in between.
+const ACQUIRE_LOCK_TIMEOUT_NANOSECONDS: u64 = 1_000_000;
+
 // Swapchain
 
+/// A `VK_KHR_swapchain` swapchain. Acquiring, presenting and recreating lock an internal mutex
+/// (the swapchain must be externally synchronized for these) so images can be acquired on one
+/// thread and presented on another, see [`PresentThread`](crate::PresentThread).
+///
+/// When the `VK_EXT_swapchain_maintenance1` device extension is enabled every present is given a
+/// fence (see [`Self::queue_present`]) and dropping the swapchain waits on those rather than
+/// requiring the device to be idle.
 pub struct Swapchain {
     handle: vk::SwapchainKHR,
     swapchain_fns: khr::swapchain::Device,
     properties: SwapchainProperties,
     swapchain_images: Vec<Arc<SwapchainImage>>,
+    /// Held during calls which require the swapchain to be externally synchronized.
+    external_sync: Mutex<()>,
+    /// `Some` if `VK_EXT_swapchain_maintenance1` is enabled.
+    present_fences: Option<Mutex<PresentFences>>,
 
     // dependencies
     device: Arc<Device>,
@@ -33,10 +59,24 @@ impl Swapchain {
         surface: Arc<Surface>,
         properties: SwapchainProperties,
     ) -> Result<Self, SwapchainError> {
+        properties.check_extent()?;
         let swapchain_fns = khr::swapchain::Device::new(device.instance().inner(), device.inner());
 
-        let swapchain_create_info =
+        let mut latency_create_info = low_latency_create_info();
+        let view_formats = properties.all_view_formats();
+        let mut format_list = format_list_create_info(&view_formats);
+        let mut scaling_create_info = properties.present_scaling_create_info();
+        let mut swapchain_create_info =
             properties.create_info(surface.handle(), vk::SwapchainKHR::null());
+        if properties.low_latency_mode {
+            swapchain_create_info = swapchain_create_info.push_next(&mut latency_create_info);
+        }
+        if let Some(format_list) = format_list.as_mut() {
+            swapchain_create_info = swapchain_create_info.push_next(format_list);
+        }
+        if let Some(scaling_create_info) = scaling_create_info.as_mut() {
+            swapchain_create_info = swapchain_create_info.push_next(scaling_create_info);
+        }
         let handle = unsafe {
             swapchain_fns.create_swapchain(&swapchain_create_info, ALLOCATION_CALLBACK_NONE)
         }
@@ -61,6 +101,8 @@ impl Swapchain {
             swapchain_fns,
             properties,
             swapchain_images,
+            external_sync: Mutex::new(()),
+            present_fences: PresentFences::new_if_enabled(&device),
 
             device,
             surface,
@@ -68,6 +110,9 @@ impl Swapchain {
     }
 
     /// On success, returns the next image's index and whether the swapchain is suboptimal for the surface.
+    ///
+    /// The swapchain lock isn't held for the whole `timeout`: the wait is split into acquires of
+    /// at most 1ms so presents from other threads aren't blocked by it.
     pub fn aquire_next_image(
         &self,
         timeout: u64,
@@ -85,25 +130,97 @@ impl Swapchain {
             vk::Fence::null()
         };
 
-        unsafe {
-            self.swapchain_fns.acquire_next_image(
-                self.handle,
-                timeout,
-                semaphore_handle,
-                fence_handle,
-            )
+        // `None` if the deadline is too far away to represent i.e. wait forever
+        let deadline = Instant::now().checked_add(Duration::from_nanos(timeout));
+        let res = loop {
+            let remaining = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    u64::try_from(remaining.as_nanos()).unwrap_or(u64::MAX)
+                }
+                None => u64::MAX,
+            };
+            let acquire_timeout = remaining.min(ACQUIRE_LOCK_TIMEOUT_NANOSECONDS);
+
+            let res = {
+                let _external_sync = self.lock_external_sync();
+                unsafe {
+                    self.swapchain_fns.acquire_next_image(
+                        self.handle,
+                        acquire_timeout,
+                        semaphore_handle,
+                        fence_handle,
+                    )
+                }
+            };
+            match res {
+                Err(vk::Result::TIMEOUT) | Err(vk::Result::NOT_READY)
+                    if remaining > acquire_timeout => {}
+                res => break res,
+            }
+        };
+
+        self.device
+            .submission_recorder()
+            .record(|| SubmissionEvent::Acquire {
+                swapchain: self.handle,
+                image_index: res.as_ref().ok().map(|(image_index, _)| *image_index),
+                signal: semaphore.map(|semaphore| semaphore.handle()),
+                fence: fence.map(|fence| fence.handle()),
+            });
+        res
+    }
+
+    /// Same as [`Self::aquire_next_image`] but doesn't block. Returns [`AcquireStatus::NotReady`]
+    /// if no image is available yet so render loops can skip the frame (or do other work)
+    /// instead of waiting.
+    pub fn try_acquire_next_image(
+        &self,
+        semaphore: Option<&Semaphore>,
+        fence: Option<&Fence>,
+    ) -> VkResult<AcquireStatus> {
+        self.acquire_next_image_until(Deadline::immediate(), semaphore, fence)
+    }
+
+    /// Same as [`Self::aquire_next_image`] but gives up at `deadline`, returning
+    /// [`AcquireStatus::NotReady`]. Returns [`AcquireStatus::Suspended`] without acquiring if the
+    /// surface has been invalidated (see [`Self::suspend`]).
+    pub fn acquire_next_image_until(
+        &self,
+        deadline: impl Into<Deadline>,
+        semaphore: Option<&Semaphore>,
+        fence: Option<&Fence>,
+    ) -> VkResult<AcquireStatus> {
+        if self.surface.is_invalidated() {
+            return Ok(AcquireStatus::Suspended);
+        }
+        let timeout = deadline.into().timeout_nanoseconds();
+        match self.aquire_next_image(timeout, semaphore, fence) {
+            Ok((image_index, is_suboptimal)) => Ok(AcquireStatus::Acquired {
+                image_index: SwapchainImageIndex::new(image_index),
+                is_suboptimal,
+            }),
+            Err(vk::Result::NOT_READY) | Err(vk::Result::TIMEOUT) => Ok(AcquireStatus::NotReady),
+            Err(e) => Err(e),
         }
     }
 
+    /// Returns [`SwapchainError::ZeroExtent`] without touching the current swapchain if
+    /// `properties` has a zero width or height.
+    ///
     /// Also destroys the old swapchain so make sure any resources depending on the swapchain and
     /// swapchain images are dropped before calling this! E.g. swapchain image views and framebuffers...
     pub fn recreate(&mut self, properties: SwapchainProperties) -> Result<(), SwapchainError> {
         let (new_handle, swapchain_images) = self.recreate_common(&properties)?;
 
+        if let Err(e) = self.wait_for_presents(u64::MAX) {
+            warn!("failed to wait for present fences of old swapchain: {}", e);
+        }
         unsafe {
             self.swapchain_fns
                 .destroy_swapchain(self.handle, ALLOCATION_CALLBACK_NONE)
         };
+        self.device.debug_names().remove(self.handle);
 
         self.handle = new_handle;
         self.properties = properties;
@@ -129,23 +246,83 @@ impl Swapchain {
             swapchain_fns: self.swapchain_fns.clone(),
             properties,
             swapchain_images,
+            external_sync: Mutex::new(()),
+            present_fences: PresentFences::new_if_enabled(&self.device),
             device: self.device.clone(),
             surface: self.surface.clone(),
         }))
     }
 
+    /// Same as `Self::recreate_replace` but doesn't require waiting for the device to idle first.
+    /// The new swapchain is created with this one as its `old_swapchain` which retires it, and
+    /// this swapchain is pushed to `deletion_queue` along with `retired_resources` (e.g. the image
+    /// views and framebuffers of the old swapchain images, or an old render pass and pipeline).
+    /// They're destroyed once the frames which may still be rendering to or presenting the old
+    /// swapchain images have completed.
+    ///
+    /// Images already acquired from this swapchain can still be rendered to and presented after
+    /// calling this. `deletion_queue` must be advanced each time a frame slot is reused (after
+    /// waiting on its fence) for this to be safe. With `VK_EXT_swapchain_maintenance1` the old
+    /// swapchain is additionally kept in the queue until its last present has completed (see
+    /// [`Self::presents_complete`]) so it's never destroyed while the presentation engine is
+    /// still using it.
+    pub fn recreate_deferred<R: Send + 'static>(
+        self: &Arc<Self>,
+        properties: SwapchainProperties,
+        deletion_queue: &mut DeletionQueue,
+        retired_resources: R,
+    ) -> Result<Arc<Self>, SwapchainError> {
+        let new_swapchain = self.recreate_replace(properties)?;
+        let retired_swapchain = self.clone();
+        deletion_queue.push_when((self.clone(), retired_resources), move || {
+            // on error (e.g. device lost) there's nothing left to wait for
+            retired_swapchain.presents_complete().unwrap_or(true)
+        });
+        Ok(new_swapchain)
+    }
+
     fn recreate_common(
         &self,
         properties: &SwapchainProperties,
     ) -> Result<(vk::SwapchainKHR, Vec<Arc<SwapchainImage>>), SwapchainError> {
-        let swapchain_create_info = properties.create_info(self.surface.handle(), self.handle);
+        properties.check_extent()?;
+        let _span = trace_span!(
+            "swapchain_recreate",
+            old_swapchain = ?self.handle,
+            swapchain_name = self.device.debug_name(self.handle).as_deref(),
+            width = properties.width_height[0],
+            height = properties.width_height[1],
+            image_count = properties.image_count,
+        );
+        let mut latency_create_info = low_latency_create_info();
+        let view_formats = properties.all_view_formats();
+        let mut format_list = format_list_create_info(&view_formats);
+        let mut scaling_create_info = properties.present_scaling_create_info();
+        let mut swapchain_create_info = properties.create_info(self.surface.handle(), self.handle);
+        if properties.low_latency_mode {
+            swapchain_create_info = swapchain_create_info.push_next(&mut latency_create_info);
+        }
+        if let Some(format_list) = format_list.as_mut() {
+            swapchain_create_info = swapchain_create_info.push_next(format_list);
+        }
+        if let Some(scaling_create_info) = scaling_create_info.as_mut() {
+            swapchain_create_info = swapchain_create_info.push_next(scaling_create_info);
+        }
 
+        let _external_sync = self.lock_external_sync();
         let new_handle = unsafe {
             self.swapchain_fns
                 .create_swapchain(&swapchain_create_info, ALLOCATION_CALLBACK_NONE)
         }
         .map_err(SwapchainError::Creation)?;
 
+        // the recreated swapchain keeps the debug name of this one
+        if let Some(name) = self.device.debug_name(self.handle) {
+            if let Err(e) = self.device.set_debug_name(new_handle, &name) {
+                warn!("failed to name recreated swapchain {:?}: {}", name, e);
+            }
+        }
+
         let vk_swapchain_images = unsafe { self.swapchain_fns.get_swapchain_images(new_handle) }
             .map_err(SwapchainError::GetSwapchainImages)?;
 
@@ -163,8 +340,51 @@ impl Swapchain {
         Ok((new_handle, swapchain_images))
     }
 
+    /// Compares the properties this swapchain was created with against the current surface
+    /// capabilities. Returns the first reason found for the swapchain to be recreated, or `None`
+    /// if it still matches the surface. Call this on window resize events to recreate proactively
+    /// rather than waiting for `ERROR_OUT_OF_DATE_KHR` mid-frame.
+    ///
+    /// Note: a minimized window typically reports a `[0, 0]` extent which can't be used to create
+    /// a swapchain, so check for that before recreating.
+    pub fn needs_recreation(&self) -> Option<SwapchainRecreationReason> {
+        if self.surface.is_invalidated() {
+            return Some(SwapchainRecreationReason::SurfaceLost);
+        }
+        let surface_capabilities = match self
+            .surface
+            .get_physical_device_surface_capabilities(self.device.physical_device())
+        {
+            Ok(surface_capabilities) => surface_capabilities,
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR) => {
+                return Some(SwapchainRecreationReason::SurfaceLost)
+            }
+            Err(e) => return Some(SwapchainRecreationReason::GetSurfaceCapabilities(e)),
+        };
+
+        recreation_reason(&self.properties, &surface_capabilities)
+    }
+
     pub fn image_view_properties(&self) -> ImageViewProperties {
-        let format = self.properties().surface_format.format;
+        self.image_view_properties_unchecked(self.properties.surface_format.format)
+    }
+
+    /// View properties for the swapchain images with `format` instead of the surface format
+    /// e.g. a UNORM view for UI passes while the rest of the frame renders to sRGB views. Returns
+    /// `None` unless `format` is the surface format or, for a `MUTABLE_FORMAT` swapchain (see
+    /// [`SwapchainProperties::with_mutable_format`]), in
+    /// [`SwapchainProperties::all_view_formats`].
+    pub fn image_view_properties_for_format(
+        &self,
+        format: vk::Format,
+    ) -> Option<ImageViewProperties> {
+        if !self.properties.supports_view_format(format) {
+            return None;
+        }
+        Some(self.image_view_properties_unchecked(format))
+    }
+
+    fn image_view_properties_unchecked(&self, format: vk::Format) -> ImageViewProperties {
         let component_mapping = default_component_mapping();
 
         let layer_count = self.properties().array_layers;
@@ -187,17 +407,153 @@ impl Swapchain {
         }
     }
 
+    /// Releases this swapchain and its surface for an Android app pause (`APP_CMD_TERM_WINDOW`),
+    /// after which the native window may be destroyed. The surface is marked with
+    /// [`Surface::invalidate`]. Call [`SuspendedSwapchain::resume`] with the new window once the
+    /// app resumes (`APP_CMD_INIT_WINDOW`).
+    ///
+    /// The swapchain and surface are only destroyed once every `Arc` of them is dropped, so
+    /// first wait for the device to be idle and drop anything holding them (e.g. swapchain image
+    /// views, framebuffers or a [`PresentThread`](crate::PresentThread)).
+    pub fn suspend(self: Arc<Self>) -> SuspendedSwapchain {
+        self.surface.invalidate();
+        if Arc::strong_count(&self) > 1 || Arc::strong_count(&self.surface) > 1 {
+            warn!("suspending a swapchain or surface which is still referenced elsewhere. it won't be destroyed until those references are dropped");
+        }
+        SuspendedSwapchain {
+            properties: self.properties.clone(),
+            device: self.device.clone(),
+        }
+    }
+
+    /// `present_info` should only reference this swapchain. Locks this swapchain and `queue`.
+    ///
+    /// If `VK_EXT_swapchain_maintenance1` is enabled, a present fence is chained to
+    /// `present_info` (so don't chain your own `vk::SwapchainPresentFenceInfoEXT`) which is
+    /// signalled once the presentation engine no longer needs the wait semaphores and image.
+    /// See [`Self::wait_for_presents`].
+    ///
+    /// On success, returns whether the swapchain is suboptimal for the surface.
     pub fn queue_present(
         &self,
         queue: &Queue,
         present_info: &vk::PresentInfoKHR,
     ) -> VkResult<bool> {
-        unsafe {
+        self.device
+            .submission_recorder()
+            .record_present(queue.handle(), present_info);
+        let _swapchain_sync = self.lock_external_sync();
+
+        let Some(present_fences) = self.present_fences.as_ref() else {
+            let _queue_sync = queue.lock_external_sync();
+            return unsafe {
+                self.swapchain_fns
+                    .queue_present(queue.handle(), present_info)
+            };
+        };
+
+        // held until the present is queued so the fence isn't waited on before it's submitted
+        let mut present_fences = lock_present_fences(present_fences);
+        let fences = [present_fences.next_fence()?];
+        let mut fence_info = vk::SwapchainPresentFenceInfoEXT::default().fences(&fences);
+        let present_info = present_info.push_next(&mut fence_info);
+
+        let _queue_sync = queue.lock_external_sync();
+        let res = unsafe {
             self.swapchain_fns
-                .queue_present(queue.handle(), present_info)
+                .queue_present(queue.handle(), &present_info)
+        };
+        if !present_was_queued(&res) {
+            present_fences.discard_last();
+        }
+        res
+    }
+
+    /// Waits until the presentation engine has finished with every image presented by this
+    /// swapchain so far. Does nothing if `VK_EXT_swapchain_maintenance1` isn't enabled.
+    pub fn wait_for_presents(&self, timeout_nanoseconds: u64) -> VkResult<()> {
+        match self.present_fences.as_ref() {
+            Some(present_fences) => {
+                lock_present_fences(present_fences).wait_all(timeout_nanoseconds)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Waits until this swapchain, and the frames rendering to it, are no longer in use so they
+    /// can be dropped without idling the whole device: waits for `frame_fences` (the fences of
+    /// the submissions rendering to this swapchain's images) and then for the present fences.
+    /// Without [present fences](Self::has_present_fences) this falls back to waiting for
+    /// `present_queue` to be idle.
+    pub fn wait_for_teardown<'a>(
+        &self,
+        frame_fences: impl IntoIterator<Item = &'a Fence>,
+        present_queue: &Queue,
+        timeout_nanoseconds: u64,
+    ) -> Result<(), DeviceError> {
+        for fence in frame_fences {
+            fence
+                .wait(timeout_nanoseconds)
+                .map_err(DeviceError::WaitIdle)?;
+        }
+        if self.has_present_fences() {
+            self.wait_for_presents(timeout_nanoseconds)
+                .map_err(DeviceError::WaitIdle)
+        } else {
+            present_queue.wait_idle()
+        }
+    }
+
+    /// Whether the presentation engine has finished with every image presented by this
+    /// swapchain so far, without blocking. Always true if `VK_EXT_swapchain_maintenance1` isn't
+    /// enabled as there's no way to tell.
+    pub fn presents_complete(&self) -> VkResult<bool> {
+        match self.present_fences.as_ref() {
+            Some(present_fences) => {
+                let mut present_fences = lock_present_fences(present_fences);
+                present_fences.recycle_completed()?;
+                Ok(present_fences.in_flight.is_empty())
+            }
+            None => Ok(true),
         }
     }
 
+    /// Releases images which were acquired but won't be presented e.g. when skipping a frame to
+    /// recreate the swapchain. Requires `VK_EXT_swapchain_maintenance1`.
+    ///
+    /// <https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkReleaseSwapchainImagesEXT.html>
+    pub fn release_images(&self, image_indices: &[SwapchainImageIndex]) -> VkResult<()> {
+        let maintenance1_fns = self
+            .device
+            .extension_loader::<ext::swapchain_maintenance1::Device>();
+        let image_indices: Vec<u32> = image_indices
+            .iter()
+            .map(|image_index| image_index.value())
+            .collect();
+        let release_info = vk::ReleaseSwapchainImagesInfoEXT::default()
+            .swapchain(self.handle)
+            .image_indices(&image_indices);
+
+        let _external_sync = self.lock_external_sync();
+        unsafe { maintenance1_fns.release_swapchain_images(&release_info) }
+    }
+
+    /// Whether presents are given fences i.e. `VK_EXT_swapchain_maintenance1` is enabled.
+    #[inline]
+    pub fn has_present_fences(&self) -> bool {
+        self.present_fences.is_some()
+    }
+
+    /// Locks the mutex guarding calls which require this swapchain to be externally
+    /// synchronized. Hold the guard while calling such vulkan functions directly with
+    /// [`Self::handle`]. Don't call the acquire/present/recreate functions of this swapchain while
+    /// holding the guard.
+    pub fn lock_external_sync(&self) -> MutexGuard<'_, ()> {
+        self.external_sync
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     // Getters
 
     #[inline]
@@ -224,6 +580,21 @@ impl Swapchain {
     pub fn swapchain_images(&self) -> &Vec<Arc<SwapchainImage>> {
         &self.swapchain_images
     }
+
+    /// Describes how shaders should encode colors written to the swapchain images.
+    pub fn color_space_info(&self) -> ColorSpaceInfo {
+        ColorSpaceInfo::from_surface_format(self.properties.surface_format)
+    }
+
+    /// Viewport covering the whole swapchain image extent.
+    pub fn whole_viewport(&self) -> vk::Viewport {
+        self.properties.dimensions().whole_viewport()
+    }
+
+    /// Rect covering the whole swapchain image extent.
+    pub fn whole_rect(&self) -> vk::Rect2D {
+        self.properties.dimensions().whole_rect()
+    }
 }
 
 impl DeviceOwned for Swapchain {
@@ -240,10 +611,240 @@ impl DeviceOwned for Swapchain {
 
 impl Drop for Swapchain {
     fn drop(&mut self) {
+        if let Err(e) = self.wait_for_presents(u64::MAX) {
+            warn!(
+                "failed to wait for present fences before destroying swapchain: {}",
+                e
+            );
+        }
         unsafe {
             self.swapchain_fns
                 .destroy_swapchain(self.handle, ALLOCATION_CALLBACK_NONE)
         };
+        self.device.debug_names().remove(self.handle);
+    }
+}
+
+/// Result of [`Swapchain::try_acquire_next_image`] and [`Swapchain::acquire_next_image_until`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquireStatus {
+    /// The semaphore and/or fence will be signalled once the image is ready to be rendered to.
+    Acquired {
+        image_index: SwapchainImageIndex,
+        /// The swapchain no longer matches the surface exactly and should be recreated.
+        is_suboptimal: bool,
+    },
+    /// No image became available before the deadline. The semaphore and fence are left untouched.
+    NotReady,
+    /// The surface has been invalidated (e.g. the app was paused) so no image was acquired. The
+    /// semaphore and fence are left untouched. Suspend the swapchain and skip frames until it's
+    /// resumed.
+    Suspended,
+}
+
+impl AcquireStatus {
+    #[inline]
+    pub fn image_index(&self) -> Option<SwapchainImageIndex> {
+        match *self {
+            Self::Acquired { image_index, .. } => Some(image_index),
+            Self::NotReady | Self::Suspended => None,
+        }
+    }
+}
+
+// Present Fences
+
+/// Fences of presents which may not have completed yet, recycled once signalled.
+struct PresentFences {
+    in_flight: Vec<Fence>,
+    fence_pool: FencePool,
+}
+
+impl PresentFences {
+    fn new_if_enabled(device: &Arc<Device>) -> Option<Mutex<Self>> {
+        let enabled = device
+            .enabled_extensions()
+            .contains(&ext::swapchain_maintenance1::NAME.to_owned());
+        enabled.then(|| {
+            Mutex::new(Self {
+                in_flight: Vec::new(),
+                fence_pool: FencePool::new(device.clone()),
+            })
+        })
+    }
+
+    /// Returns an unsignalled fence for the next present, recycling those of completed presents.
+    fn next_fence(&mut self) -> VkResult<vk::Fence> {
+        self.recycle_completed()?;
+        let fence = self.fence_pool.acquire()?;
+        let fence_handle = fence.handle();
+        self.in_flight.push(fence);
+        Ok(fence_handle)
+    }
+
+    /// Drops the fence from [`Self::next_fence`] when the present wasn't queued.
+    fn discard_last(&mut self) {
+        self.in_flight.pop();
+    }
+
+    fn wait_all(&mut self, timeout_nanoseconds: u64) -> VkResult<()> {
+        for fence in &self.in_flight {
+            fence.wait(timeout_nanoseconds)?;
+        }
+        self.recycle_completed()
+    }
+
+    fn recycle_completed(&mut self) -> VkResult<()> {
+        let mut index = 0;
+        while index < self.in_flight.len() {
+            if self.in_flight[index]
+                .wait_until(Deadline::immediate())?
+                .is_complete()
+            {
+                let fence = self.in_flight.swap_remove(index);
+                self.fence_pool.release(fence)?;
+            } else {
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Presents rejected with out of date, surface lost or full screen exclusive mode lost errors are
+/// still queued (and signal their fence). Other errors mean nothing was queued.
+fn present_was_queued(present_res: &VkResult<bool>) -> bool {
+    match present_res {
+        Ok(_) => true,
+        Err(e) => matches!(
+            *e,
+            vk::Result::ERROR_OUT_OF_DATE_KHR
+                | vk::Result::ERROR_SURFACE_LOST_KHR
+                | vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT
+        ),
+    }
+}
+
+fn lock_present_fences(present_fences: &Mutex<PresentFences>) -> MutexGuard<'_, PresentFences> {
+    present_fences
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+// Suspension
+
+/// A swapchain released by [`Swapchain::suspend`]. Remembers the swapchain properties so it can
+/// be recreated for a new window.
+pub struct SuspendedSwapchain {
+    properties: SwapchainProperties,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl SuspendedSwapchain {
+    /// Creates a surface for the new window and a swapchain with the same properties as the
+    /// suspended one, except the extent, pre-transform and image count which are updated for
+    /// the new surface. `window_dimensions` is used if the surface doesn't report an extent.
+    #[cfg(any(feature = "raw-window-handle-05", feature = "raw-window-handle-06"))]
+    pub fn resume(
+        &self,
+        entry: &Entry,
+        raw_display_handle: RawDisplayHandle,
+        raw_window_handle: RawWindowHandle,
+        window_dimensions: [u32; 2],
+        present_queue_family_index: u32,
+    ) -> Result<Arc<Swapchain>, SwapchainResumeError> {
+        let surface = Surface::new(
+            entry,
+            self.device.instance().clone(),
+            raw_display_handle,
+            raw_window_handle,
+        )
+        .map_err(SwapchainResumeError::SurfaceCreation)?;
+        self.resume_with_surface(
+            Arc::new(surface),
+            window_dimensions,
+            present_queue_family_index,
+        )
+    }
+
+    /// Same as [`Self::resume`] with a surface created by the caller.
+    ///
+    /// Fails if queue family `present_queue_family_index` can't present to the new surface or
+    /// the surface doesn't support the suspended surface format. Falls back to
+    /// `vk::PresentModeKHR::FIFO` if the suspended present mode isn't supported.
+    pub fn resume_with_surface(
+        &self,
+        surface: Arc<Surface>,
+        window_dimensions: [u32; 2],
+        present_queue_family_index: u32,
+    ) -> Result<Arc<Swapchain>, SwapchainResumeError> {
+        let physical_device = self.device.physical_device();
+        let surface_supported = surface
+            .get_physical_device_surface_support(physical_device, present_queue_family_index)
+            .map_err(SwapchainResumeError::GetSurfaceSupport)?;
+        if !surface_supported {
+            return Err(SwapchainResumeError::SurfaceUnsupported {
+                present_queue_family_index,
+            });
+        }
+
+        let surface_formats = surface
+            .get_physical_device_surface_formats(physical_device)
+            .map_err(SwapchainResumeError::GetSurfaceFormats)?;
+        if !surface_formats.contains(&self.properties.surface_format) {
+            return Err(SwapchainResumeError::SurfaceFormatUnsupported(
+                self.properties.surface_format,
+            ));
+        }
+
+        let surface_capabilities = surface
+            .get_physical_device_surface_capabilities(self.device.physical_device())
+            .map_err(|e| {
+                SwapchainResumeError::Swapchain(
+                    SwapchainError::GetPhysicalDeviceSurfaceCapabilities(e),
+                )
+            })?;
+        let mut properties =
+            properties_for_surface(&self.properties, &surface_capabilities, window_dimensions);
+
+        let present_modes = surface
+            .get_physical_device_surface_present_modes(physical_device)
+            .map_err(|e| {
+                SwapchainResumeError::Swapchain(
+                    SwapchainError::GetPhysicalDeviceSurfacePresentModes(e),
+                )
+            })?;
+        if !present_modes.contains(&properties.present_mode) {
+            warn!(
+                "present mode {:?} isn't supported by the resumed surface. falling back to FIFO",
+                properties.present_mode
+            );
+            properties.present_mode = vk::PresentModeKHR::FIFO;
+        }
+
+        let swapchain = Swapchain::new(self.device.clone(), surface, properties)
+            .map_err(SwapchainResumeError::Swapchain)?;
+        Ok(Arc::new(swapchain))
+    }
+
+    /// What the render loop should do while suspended: skip frames until resumed.
+    #[inline]
+    pub fn frame_outcome(&self) -> FrameOutcome {
+        FrameOutcome::Suspended
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn properties(&self) -> &SwapchainProperties {
+        &self.properties
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
     }
 }
 
@@ -263,6 +864,12 @@ pub struct SwapchainProperties {
     pub composite_alpha: vk::CompositeAlphaFlagsKHR,
     pub present_mode: vk::PresentModeKHR,
     pub clipping_enabled: bool,
+    /// Chains a `vk::SwapchainLatencyCreateInfoNV` to enable `VK_NV_low_latency2` for this
+    /// swapchain. See [`LowLatency`](crate::LowLatency). Requires the device extension.
+    pub low_latency_mode: bool,
+    /// Chains a `vk::SwapchainPresentScalingCreateInfoEXT` when `Some`. Requires
+    /// `VK_EXT_swapchain_maintenance1`.
+    pub present_scaling: Option<PresentScaling>,
 
     // image properties
     pub surface_format: vk::SurfaceFormatKHR,
@@ -271,6 +878,12 @@ pub struct SwapchainProperties {
     pub image_usage: vk::ImageUsageFlags,
     pub sharing_mode: vk::SharingMode,
     pub queue_family_indices: Vec<u32>,
+    /// Formats that views of the images of a `MUTABLE_FORMAT` swapchain can have in addition to
+    /// the surface format and its sRGB/UNORM counterpart, which are always included. The full list
+    /// (see [`Self::all_view_formats`]) is derived from `surface_format` at creation so it stays
+    /// correct when the surface format changes between recreations. Ignored without
+    /// `MUTABLE_FORMAT`. See [`Self::with_mutable_format`].
+    pub view_formats: Vec<vk::Format>,
 }
 
 impl Default for SwapchainProperties {
@@ -280,7 +893,10 @@ impl Default for SwapchainProperties {
             array_layers: 1,
             sharing_mode: vk::SharingMode::EXCLUSIVE,
             queue_family_indices: Vec::new(),
+            view_formats: Vec::new(),
             clipping_enabled: true,
+            low_latency_mode: false,
+            present_scaling: None,
             present_mode: vk::PresentModeKHR::MAILBOX,
             flags: vk::SwapchainCreateFlagsKHR::empty(),
 
@@ -304,6 +920,11 @@ impl SwapchainProperties {
     /// `surface_format`, `composite_alpha` and `image_usage` are unchecked.
     ///
     /// Sharing mode is set to `vk::SharingMode::EXCLUSIVE`, only 1 array layer, and clipping is enabled.
+    ///
+    /// Returns [`SwapchainError::ZeroExtent`] if the surface (or `window_dimensions` when the
+    /// surface doesn't report an extent) has a zero width or height, which is typical of
+    /// minimized windows on Windows, X11 and Wayland. Skip rendering until the window is
+    /// restored rather than creating a swapchain.
     pub fn new_default(
         device: &Device,
         surface: &Surface,
@@ -330,6 +951,11 @@ impl SwapchainProperties {
             },
             _ => surface_capabilities.current_extent,
         };
+        if extent.width == 0 || extent.height == 0 {
+            return Err(SwapchainError::ZeroExtent {
+                width_height: [extent.width, extent.height],
+            });
+        }
 
         let present_modes = surface
             .get_physical_device_surface_present_modes(device.physical_device())
@@ -363,11 +989,63 @@ impl SwapchainProperties {
         })
     }
 
+    /// Sets `MUTABLE_FORMAT` so the swapchain images can be viewed with the sRGB/UNORM
+    /// counterpart of the surface format (see [`srgb_unorm_pair`]) as well as any
+    /// `additional_view_formats`. Requires the `VK_KHR_swapchain_mutable_format` device
+    /// extension. Use [`Swapchain::image_view_properties_for_format`] to create the views.
+    pub fn with_mutable_format(mut self, additional_view_formats: &[vk::Format]) -> Self {
+        self.flags |= vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT;
+        self.view_formats = additional_view_formats.to_vec();
+        self
+    }
+
+    /// Every format views of the swapchain images can have: the surface format, its sRGB/UNORM
+    /// counterpart and [`Self::view_formats`] for a `MUTABLE_FORMAT` swapchain, otherwise empty.
+    /// Chained as a `vk::ImageFormatListCreateInfo` when not empty.
+    pub fn all_view_formats(&self) -> Vec<vk::Format> {
+        if !self
+            .flags
+            .contains(vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT)
+        {
+            return Vec::new();
+        }
+        let surface_format = self.surface_format.format;
+        let mut view_formats = vec![surface_format];
+        view_formats.extend(srgb_unorm_pair(surface_format));
+        for &format in &self.view_formats {
+            if !view_formats.contains(&format) {
+                view_formats.push(format);
+            }
+        }
+        view_formats
+    }
+
+    /// Sets `DEFERRED_MEMORY_ALLOCATION_EXT` so the memory of each swapchain image is only
+    /// allocated when it's first acquired, which speeds up swapchain (re)creation. Requires
+    /// `VK_EXT_swapchain_maintenance1`.
+    pub fn with_deferred_memory_allocation(mut self) -> Self {
+        self.flags |= vk::SwapchainCreateFlagsKHR::DEFERRED_MEMORY_ALLOCATION_EXT;
+        self
+    }
+
+    /// Whether views of the swapchain images can have `format`.
+    pub fn supports_view_format(&self, format: vk::Format) -> bool {
+        format == self.surface_format.format || self.all_view_formats().contains(&format)
+    }
+
+    /// Chain this to [`Self::create_info`] when `present_scaling` is `Some`.
+    pub fn present_scaling_create_info(
+        &self,
+    ) -> Option<vk::SwapchainPresentScalingCreateInfoEXT<'static>> {
+        self.present_scaling
+            .map(|present_scaling| present_scaling.create_info())
+    }
+
     pub fn create_info(
         &self,
         surface_handle: vk::SurfaceKHR,
         old_swapchain_handle: vk::SwapchainKHR,
-    ) -> vk::SwapchainCreateInfoKHR {
+    ) -> vk::SwapchainCreateInfoKHR<'_> {
         vk::SwapchainCreateInfoKHR::default()
             .flags(self.flags)
             .surface(surface_handle)
@@ -386,6 +1064,87 @@ impl SwapchainProperties {
             .queue_family_indices(&self.queue_family_indices)
     }
 
+    /// Note: the surface and old swapchain handles are ignored.
+    pub fn from_create_info(value: &vk::SwapchainCreateInfoKHR) -> Self {
+        let mut queue_family_indices = Vec::<u32>::new();
+        for i in 0..value.queue_family_index_count {
+            let queue_family_index = unsafe { *value.p_queue_family_indices.offset(i as isize) };
+            queue_family_indices.push(queue_family_index);
+        }
+
+        let mut low_latency_mode = false;
+        let mut present_scaling = None;
+        let mut view_formats = Vec::<vk::Format>::new();
+        let mut next_ptr = value.p_next as *const vk::BaseInStructure;
+        while !next_ptr.is_null() {
+            let next = unsafe { &*next_ptr };
+            if next.s_type == vk::StructureType::SWAPCHAIN_LATENCY_CREATE_INFO_NV {
+                let latency_create_info =
+                    unsafe { &*(next_ptr as *const vk::SwapchainLatencyCreateInfoNV) };
+                low_latency_mode = latency_create_info.latency_mode_enable == vk::TRUE;
+            }
+            if next.s_type == vk::StructureType::SWAPCHAIN_PRESENT_SCALING_CREATE_INFO_EXT {
+                let scaling_create_info =
+                    unsafe { &*(next_ptr as *const vk::SwapchainPresentScalingCreateInfoEXT) };
+                present_scaling = Some(PresentScaling::from(scaling_create_info));
+            }
+            if next.s_type == vk::StructureType::IMAGE_FORMAT_LIST_CREATE_INFO {
+                let format_list = unsafe { &*(next_ptr as *const vk::ImageFormatListCreateInfo) };
+                if !format_list.p_view_formats.is_null() {
+                    view_formats = unsafe {
+                        std::slice::from_raw_parts(
+                            format_list.p_view_formats,
+                            format_list.view_format_count as usize,
+                        )
+                    }
+                    .to_vec();
+                }
+            }
+            next_ptr = next.p_next;
+        }
+
+        // the surface format and its counterpart are implied by `MUTABLE_FORMAT`
+        let surface_format_pair = srgb_unorm_pair(value.image_format);
+        view_formats
+            .retain(|&format| format != value.image_format && Some(format) != surface_format_pair);
+
+        Self {
+            flags: value.flags,
+            image_count: value.min_image_count,
+            pre_transform: value.pre_transform,
+            composite_alpha: value.composite_alpha,
+            present_mode: value.present_mode,
+            clipping_enabled: value.clipped != 0,
+            low_latency_mode,
+            present_scaling,
+            surface_format: vk::SurfaceFormatKHR {
+                format: value.image_format,
+                color_space: value.image_color_space,
+            },
+            width_height: [value.image_extent.width, value.image_extent.height],
+            array_layers: value.image_array_layers,
+            image_usage: value.image_usage,
+            sharing_mode: value.image_sharing_mode,
+            queue_family_indices,
+            view_formats,
+        }
+    }
+
+    /// Whether the width or height is zero. A swapchain can't be created with a zero extent.
+    #[inline]
+    pub fn has_zero_extent(&self) -> bool {
+        self.width_height[0] == 0 || self.width_height[1] == 0
+    }
+
+    fn check_extent(&self) -> Result<(), SwapchainError> {
+        if self.has_zero_extent() {
+            return Err(SwapchainError::ZeroExtent {
+                width_height: self.width_height,
+            });
+        }
+        Ok(())
+    }
+
     pub fn dimensions(&self) -> ImageDimensions {
         ImageDimensions::Dim2d {
             width: self.width_height[0],
@@ -395,11 +1154,78 @@ impl SwapchainProperties {
     }
 }
 
+impl From<&vk::SwapchainCreateInfoKHR<'_>> for SwapchainProperties {
+    fn from(value: &vk::SwapchainCreateInfoKHR<'_>) -> Self {
+        Self::from_create_info(value)
+    }
+}
+
+// Present Scaling
+
+/// How swapchain images are scaled and positioned when their extent doesn't match the surface,
+/// e.g. while a window is being resized, instead of the platform's default behaviour. See
+/// [`Surface::present_scaling_capabilities`] for what's supported.
+///
+/// Empty flags leave the behaviour to the platform. The gravities must either both be empty or
+/// both be set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PresentScaling {
+    pub scaling_behavior: vk::PresentScalingFlagsEXT,
+    pub gravity_x: vk::PresentGravityFlagsEXT,
+    pub gravity_y: vk::PresentGravityFlagsEXT,
+}
+
+impl PresentScaling {
+    /// Scales the image to fit the surface while preserving its aspect ratio, centered.
+    pub fn aspect_ratio_stretch_centered() -> Self {
+        Self {
+            scaling_behavior: vk::PresentScalingFlagsEXT::ASPECT_RATIO_STRETCH,
+            gravity_x: vk::PresentGravityFlagsEXT::CENTERED,
+            gravity_y: vk::PresentGravityFlagsEXT::CENTERED,
+        }
+    }
+
+    /// Whether the surface supports this scaling and gravity. See
+    /// [`Surface::present_scaling_capabilities`].
+    pub fn is_supported(&self, capabilities: &vk::SurfacePresentScalingCapabilitiesEXT) -> bool {
+        capabilities
+            .supported_present_scaling
+            .contains(self.scaling_behavior)
+            && capabilities
+                .supported_present_gravity_x
+                .contains(self.gravity_x)
+            && capabilities
+                .supported_present_gravity_y
+                .contains(self.gravity_y)
+            && self.gravity_x.is_empty() == self.gravity_y.is_empty()
+    }
+
+    pub fn create_info(&self) -> vk::SwapchainPresentScalingCreateInfoEXT<'static> {
+        vk::SwapchainPresentScalingCreateInfoEXT::default()
+            .scaling_behavior(self.scaling_behavior)
+            .present_gravity_x(self.gravity_x)
+            .present_gravity_y(self.gravity_y)
+    }
+}
+
+impl From<&vk::SwapchainPresentScalingCreateInfoEXT<'_>> for PresentScaling {
+    fn from(value: &vk::SwapchainPresentScalingCreateInfoEXT<'_>) -> Self {
+        Self {
+            scaling_behavior: value.scaling_behavior,
+            gravity_x: value.present_gravity_x,
+            gravity_y: value.present_gravity_y,
+        }
+    }
+}
+
 // Swapchain Image
 
 pub struct SwapchainImage {
     handle: vk::Image,
     dimensions: ImageDimensions,
+    format: vk::Format,
+    /// Empty unless the swapchain has `MUTABLE_FORMAT`.
+    view_formats: Vec<vk::Format>,
 
     // dependencies
     device: Arc<Device>,
@@ -412,9 +1238,12 @@ impl SwapchainImage {
         handle: vk::Image,
         swapchain_properties: &SwapchainProperties,
     ) -> Self {
+        let view_formats = swapchain_properties.all_view_formats();
         Self {
             handle,
             dimensions: swapchain_properties.dimensions(),
+            format: swapchain_properties.surface_format.format,
+            view_formats,
             device,
         }
     }
@@ -437,6 +1266,10 @@ impl ImageAccess for SwapchainImage {
     fn dimensions(&self) -> ImageDimensions {
         self.dimensions
     }
+
+    fn supports_view_format(&self, format: vk::Format) -> bool {
+        format == self.format || self.view_formats.contains(&format)
+    }
 }
 
 impl DeviceOwned for SwapchainImage {
@@ -453,6 +1286,18 @@ impl DeviceOwned for SwapchainImage {
 
 // Helper
 
+/// `None` if `view_formats` is empty. `view_formats` should come from
+/// [`SwapchainProperties::all_view_formats`] which is only non-empty with `MUTABLE_FORMAT`, as a
+/// format list with other formats is invalid without it (VUID-VkSwapchainCreateInfoKHR-pNext-04099).
+fn format_list_create_info(
+    view_formats: &[vk::Format],
+) -> Option<vk::ImageFormatListCreateInfo<'_>> {
+    if view_formats.is_empty() {
+        return None;
+    }
+    Some(vk::ImageFormatListCreateInfo::default().view_formats(view_formats))
+}
+
 /// Checks surface support for the first compositie alpha flag in order of preference:
 /// 1. `vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED`
 /// 2. `vk::CompositeAlphaFlagsKHR::OPAQUE`
@@ -474,12 +1319,180 @@ pub fn choose_composite_alpha(
         .expect("driver should support at least one type of composite alpha!")
 }
 
+fn recreation_reason(
+    properties: &SwapchainProperties,
+    surface_capabilities: &vk::SurfaceCapabilitiesKHR,
+) -> Option<SwapchainRecreationReason> {
+    // u32::MAX means the extent is determined by the swapchain
+    let current_extent = surface_capabilities.current_extent;
+    if current_extent.width != u32::MAX {
+        let new_width_height = [current_extent.width, current_extent.height];
+        if new_width_height != properties.width_height {
+            return Some(SwapchainRecreationReason::ExtentChanged {
+                old_width_height: properties.width_height,
+                new_width_height,
+            });
+        }
+    }
+
+    // an identity pre-transform means the presentation engine handles any rotation
+    if properties.pre_transform != vk::SurfaceTransformFlagsKHR::IDENTITY
+        && properties.pre_transform != surface_capabilities.current_transform
+    {
+        return Some(SwapchainRecreationReason::TransformChanged {
+            old_transform: properties.pre_transform,
+            new_transform: surface_capabilities.current_transform,
+        });
+    }
+
+    // max_image_count == 0 when there is no upper limit
+    let image_count_unsupported = properties.image_count < surface_capabilities.min_image_count
+        || (surface_capabilities.max_image_count != 0
+            && properties.image_count > surface_capabilities.max_image_count);
+    if image_count_unsupported {
+        return Some(SwapchainRecreationReason::ImageCountUnsupported {
+            image_count: properties.image_count,
+            min_image_count: surface_capabilities.min_image_count,
+            max_image_count: surface_capabilities.max_image_count,
+        });
+    }
+
+    None
+}
+
+fn low_latency_create_info() -> vk::SwapchainLatencyCreateInfoNV<'static> {
+    vk::SwapchainLatencyCreateInfoNV::default().latency_mode_enable(true)
+}
+
+/// `properties` adjusted for a (new) surface's extent, transform and image count limits.
+fn properties_for_surface(
+    properties: &SwapchainProperties,
+    surface_capabilities: &vk::SurfaceCapabilitiesKHR,
+    window_dimensions: [u32; 2],
+) -> SwapchainProperties {
+    // u32::MAX means the extent is determined by the swapchain
+    let width_height = match surface_capabilities.current_extent.width {
+        u32::MAX => window_dimensions,
+        _ => [
+            surface_capabilities.current_extent.width,
+            surface_capabilities.current_extent.height,
+        ],
+    };
+
+    let pre_transform = if surface_capabilities
+        .supported_transforms
+        .contains(properties.pre_transform)
+    {
+        properties.pre_transform
+    } else {
+        surface_capabilities.current_transform
+    };
+
+    let mut image_count = max(properties.image_count, surface_capabilities.min_image_count);
+    // max_image_count == 0 when there is no upper limit
+    if surface_capabilities.max_image_count != 0 {
+        image_count = min(image_count, surface_capabilities.max_image_count);
+    }
+
+    SwapchainProperties {
+        width_height,
+        pre_transform,
+        image_count,
+        ..properties.clone()
+    }
+}
+
+/// Why a swapchain no longer matches its surface. See [`Swapchain::needs_recreation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapchainRecreationReason {
+    ExtentChanged {
+        old_width_height: [u32; 2],
+        new_width_height: [u32; 2],
+    },
+    TransformChanged {
+        old_transform: vk::SurfaceTransformFlagsKHR,
+        new_transform: vk::SurfaceTransformFlagsKHR,
+    },
+    /// The swapchain image count is outside the range currently supported by the surface.
+    /// `max_image_count` is 0 when there is no upper limit.
+    ImageCountUnsupported {
+        image_count: u32,
+        min_image_count: u32,
+        max_image_count: u32,
+    },
+    /// The surface must be recreated before the swapchain.
+    SurfaceLost,
+    GetSurfaceCapabilities(vk::Result),
+}
+
+impl fmt::Display for SwapchainRecreationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExtentChanged {
+                old_width_height,
+                new_width_height,
+            } => write!(
+                f,
+                "surface extent changed from {:?} to {:?}",
+                old_width_height, new_width_height
+            ),
+            Self::TransformChanged {
+                old_transform,
+                new_transform,
+            } => write!(
+                f,
+                "surface transform changed from {:?} to {:?}",
+                old_transform, new_transform
+            ),
+            Self::ImageCountUnsupported {
+                image_count,
+                min_image_count,
+                max_image_count,
+            } => write!(
+                f,
+                "swapchain image count {} is outside of the supported range [{}, {}]",
+                image_count, min_image_count, max_image_count
+            ),
+            Self::SurfaceLost => write!(f, "surface was lost"),
+            Self::GetSurfaceCapabilities(e) => write!(
+                f,
+                "call to vkGetPhysicalDeviceSurfaceCapabilitiesKHR failed: {}",
+                e
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum SwapchainError {
     GetPhysicalDeviceSurfaceCapabilities(vk::Result),
     GetPhysicalDeviceSurfacePresentModes(vk::Result),
     Creation(vk::Result),
     GetSwapchainImages(vk::Result),
+    /// The surface extent has a zero width or height, e.g. the window is minimized. Idle until
+    /// the window is restored and try again.
+    ZeroExtent {
+        width_height: [u32; 2],
+    },
+}
+
+impl SwapchainError {
+    #[inline]
+    pub fn is_zero_extent(&self) -> bool {
+        matches!(self, Self::ZeroExtent { .. })
+    }
+
+    /// What the render loop should do about a failed swapchain (re)creation. A zero extent
+    /// means [`FrameOutcome::Suspended`] rather than an error.
+    pub fn frame_outcome(&self) -> FrameOutcome {
+        match self {
+            Self::ZeroExtent { .. } => FrameOutcome::Suspended,
+            Self::GetPhysicalDeviceSurfaceCapabilities(e)
+            | Self::GetPhysicalDeviceSurfacePresentModes(e)
+            | Self::Creation(e)
+            | Self::GetSwapchainImages(e) => FrameOutcome::from_vk_result(*e),
+        }
+    }
 }
 
 impl fmt::Display for SwapchainError {
@@ -500,6 +1513,11 @@ impl fmt::Display for SwapchainError {
             Self::GetSwapchainImages(e) => {
                 write!(f, "call to vkGetSwapchainImagesKHR failed: {}", e)
             }
+            Self::ZeroExtent { width_height } => write!(
+                f,
+                "can't create a swapchain with a zero extent {:?} (is the window minimized?)",
+                width_height
+            ),
         }
     }
 }
@@ -511,6 +1529,307 @@ impl error::Error for SwapchainError {
             Self::GetPhysicalDeviceSurfacePresentModes(e) => Some(e),
             Self::Creation(e) => Some(e),
             Self::GetSwapchainImages(e) => Some(e),
+            Self::ZeroExtent { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SwapchainResumeError {
+    SurfaceCreation(SurfaceCreationError),
+    GetSurfaceSupport(vk::Result),
+    SurfaceUnsupported {
+        present_queue_family_index: u32,
+    },
+    GetSurfaceFormats(vk::Result),
+    /// The new surface doesn't support the surface format of the suspended swapchain so
+    /// anything depending on it (e.g. render passes and pipelines) must be recreated along with
+    /// a new swapchain.
+    SurfaceFormatUnsupported(vk::SurfaceFormatKHR),
+    Swapchain(SwapchainError),
+}
+
+impl SwapchainResumeError {
+    /// What the render loop should do about a failed resume. A zero extent (the new window isn't
+    /// ready yet) means [`FrameOutcome::Suspended`].
+    pub fn frame_outcome(&self) -> FrameOutcome {
+        match self {
+            Self::Swapchain(e) => e.frame_outcome(),
+            Self::GetSurfaceSupport(e) | Self::GetSurfaceFormats(e) => {
+                FrameOutcome::from_vk_result(*e)
+            }
+            Self::SurfaceCreation(_)
+            | Self::SurfaceUnsupported { .. }
+            | Self::SurfaceFormatUnsupported(_) => FrameOutcome::RecreateSurface,
         }
     }
 }
+
+impl fmt::Display for SwapchainResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SurfaceCreation(e) => {
+                write!(f, "failed to create surface for resumed swapchain: {}", e)
+            }
+            Self::GetSurfaceSupport(e) => write!(
+                f,
+                "call to vkGetPhysicalDeviceSurfaceSupportKHR failed: {}",
+                e
+            ),
+            Self::SurfaceUnsupported {
+                present_queue_family_index,
+            } => write!(
+                f,
+                "queue family {} can't present to the resumed surface",
+                present_queue_family_index
+            ),
+            Self::GetSurfaceFormats(e) => write!(
+                f,
+                "call to vkGetPhysicalDeviceSurfaceFormatsKHR failed: {}",
+                e
+            ),
+            Self::SurfaceFormatUnsupported(surface_format) => write!(
+                f,
+                "resumed surface doesn't support the suspended surface format {:?}",
+                surface_format
+            ),
+            Self::Swapchain(e) => write!(f, "failed to resume swapchain: {}", e),
+        }
+    }
+}
+
+impl error::Error for SwapchainResumeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::SurfaceCreation(e) => Some(e),
+            Self::GetSurfaceSupport(e) | Self::GetSurfaceFormats(e) => Some(e),
+            Self::Swapchain(e) => Some(e),
+            Self::SurfaceUnsupported { .. } | Self::SurfaceFormatUnsupported(_) => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn recreation_reason_detects_extent_and_image_count() {
+    let properties = SwapchainProperties {
+        image_count: 3,
+        width_height: [800, 600],
+        pre_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+        ..SwapchainProperties::default()
+    };
+    let surface_capabilities = vk::SurfaceCapabilitiesKHR {
+        min_image_count: 2,
+        max_image_count: 0,
+        current_extent: vk::Extent2D {
+            width: 800,
+            height: 600,
+        },
+        current_transform: vk::SurfaceTransformFlagsKHR::ROTATE_90,
+        ..Default::default()
+    };
+    assert_eq!(recreation_reason(&properties, &surface_capabilities), None);
+
+    let resized_capabilities = vk::SurfaceCapabilitiesKHR {
+        current_extent: vk::Extent2D {
+            width: 1024,
+            height: 768,
+        },
+        ..surface_capabilities
+    };
+    assert_eq!(
+        recreation_reason(&properties, &resized_capabilities),
+        Some(SwapchainRecreationReason::ExtentChanged {
+            old_width_height: [800, 600],
+            new_width_height: [1024, 768],
+        })
+    );
+
+    let limited_capabilities = vk::SurfaceCapabilitiesKHR {
+        max_image_count: 2,
+        ..surface_capabilities
+    };
+    assert!(matches!(
+        recreation_reason(&properties, &limited_capabilities),
+        Some(SwapchainRecreationReason::ImageCountUnsupported { .. })
+    ));
+}
+
+#[test]
+fn resumed_properties_follow_new_surface() {
+    let properties = SwapchainProperties {
+        image_count: 3,
+        width_height: [1080, 2400],
+        pre_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+        ..SwapchainProperties::default()
+    };
+    let surface_capabilities = vk::SurfaceCapabilitiesKHR {
+        min_image_count: 2,
+        max_image_count: 2,
+        current_extent: vk::Extent2D {
+            width: 2400,
+            height: 1080,
+        },
+        current_transform: vk::SurfaceTransformFlagsKHR::ROTATE_90,
+        supported_transforms: vk::SurfaceTransformFlagsKHR::ROTATE_90,
+        ..Default::default()
+    };
+    let resumed = properties_for_surface(&properties, &surface_capabilities, [0, 0]);
+    assert_eq!(resumed.width_height, [2400, 1080]);
+    assert_eq!(
+        resumed.pre_transform,
+        vk::SurfaceTransformFlagsKHR::ROTATE_90
+    );
+    assert_eq!(resumed.image_count, 2);
+
+    let undefined_extent_capabilities = vk::SurfaceCapabilitiesKHR {
+        current_extent: vk::Extent2D {
+            width: u32::MAX,
+            height: u32::MAX,
+        },
+        ..surface_capabilities
+    };
+    let resumed = properties_for_surface(&properties, &undefined_extent_capabilities, [640, 480]);
+    assert_eq!(resumed.width_height, [640, 480]);
+}
+
+#[test]
+fn zero_extent_is_rejected_before_creation() {
+    let properties = SwapchainProperties {
+        width_height: [1280, 0],
+        ..SwapchainProperties::default()
+    };
+    assert!(properties.has_zero_extent());
+    let error = properties.check_extent().unwrap_err();
+    assert!(error.is_zero_extent());
+    assert_eq!(error.frame_outcome(), FrameOutcome::Suspended);
+    assert!(SwapchainProperties::default().check_extent().is_ok());
+
+    let resume_error = SwapchainResumeError::Swapchain(error);
+    assert_eq!(resume_error.frame_outcome(), FrameOutcome::Suspended);
+    let resume_error = SwapchainResumeError::SurfaceFormatUnsupported(
+        SwapchainProperties::default().surface_format,
+    );
+    assert_eq!(resume_error.frame_outcome(), FrameOutcome::RecreateSurface);
+}
+
+#[test]
+fn swapchain_properties_create_info_round_trip() {
+    let properties = SwapchainProperties {
+        image_count: 3,
+        surface_format: vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        },
+        width_height: [1280, 720],
+        image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        sharing_mode: vk::SharingMode::CONCURRENT,
+        queue_family_indices: vec![0, 2],
+        ..SwapchainProperties::default()
+    };
+    let create_info = properties.create_info(vk::SurfaceKHR::null(), vk::SwapchainKHR::null());
+    let round_trip = SwapchainProperties::from(&create_info);
+
+    assert_eq!(round_trip.image_count, 3);
+    assert_eq!(round_trip.surface_format, properties.surface_format);
+    assert_eq!(round_trip.width_height, [1280, 720]);
+    assert_eq!(round_trip.queue_family_indices, vec![0, 2]);
+    assert_eq!(round_trip.present_mode, properties.present_mode);
+}
+
+#[test]
+fn mutable_format_swapchain_view_formats() {
+    let properties = SwapchainProperties {
+        surface_format: vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        },
+        ..Default::default()
+    };
+    assert!(!properties.supports_view_format(vk::Format::B8G8R8A8_UNORM));
+    // a format list without `MUTABLE_FORMAT` is invalid
+    let unflagged = SwapchainProperties {
+        view_formats: vec![vk::Format::B8G8R8A8_UNORM],
+        ..properties.clone()
+    };
+    assert!(unflagged.all_view_formats().is_empty());
+    assert!(!unflagged.supports_view_format(vk::Format::B8G8R8A8_UNORM));
+
+    let mut properties = properties.with_mutable_format(&[vk::Format::R32_UINT]);
+    assert!(properties
+        .flags
+        .contains(vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT));
+    assert_eq!(
+        properties.all_view_formats(),
+        vec![
+            vk::Format::B8G8R8A8_SRGB,
+            vk::Format::B8G8R8A8_UNORM,
+            vk::Format::R32_UINT
+        ]
+    );
+    assert!(properties.supports_view_format(vk::Format::B8G8R8A8_UNORM));
+    assert!(!properties.supports_view_format(vk::Format::R8G8B8A8_UNORM));
+
+    let view_formats = properties.all_view_formats();
+    let mut format_list = format_list_create_info(&view_formats).unwrap();
+    let create_info = properties
+        .create_info(vk::SurfaceKHR::null(), vk::SwapchainKHR::null())
+        .push_next(&mut format_list);
+    let round_trip = SwapchainProperties::from_create_info(&create_info);
+    assert_eq!(round_trip.view_formats, properties.view_formats);
+
+    // the implied formats follow the surface format
+    properties.surface_format.format = vk::Format::R8G8B8A8_UNORM;
+    assert_eq!(
+        properties.all_view_formats(),
+        vec![
+            vk::Format::R8G8B8A8_UNORM,
+            vk::Format::R8G8B8A8_SRGB,
+            vk::Format::R32_UINT
+        ]
+    );
+    assert!(!properties.supports_view_format(vk::Format::B8G8R8A8_UNORM));
+}
+
+#[test]
+fn present_scaling_create_info_round_trip() {
+    let properties = SwapchainProperties {
+        present_scaling: Some(PresentScaling::aspect_ratio_stretch_centered()),
+        ..Default::default()
+    }
+    .with_deferred_memory_allocation();
+
+    let mut scaling_create_info = properties.present_scaling_create_info().unwrap();
+    let create_info = properties
+        .create_info(vk::SurfaceKHR::null(), vk::SwapchainKHR::null())
+        .push_next(&mut scaling_create_info);
+    let round_trip = SwapchainProperties::from_create_info(&create_info);
+    assert_eq!(round_trip.present_scaling, properties.present_scaling);
+    assert!(round_trip
+        .flags
+        .contains(vk::SwapchainCreateFlagsKHR::DEFERRED_MEMORY_ALLOCATION_EXT));
+
+    let capabilities = vk::SurfacePresentScalingCapabilitiesEXT::default()
+        .supported_present_scaling(vk::PresentScalingFlagsEXT::ASPECT_RATIO_STRETCH)
+        .supported_present_gravity_x(vk::PresentGravityFlagsEXT::CENTERED)
+        .supported_present_gravity_y(vk::PresentGravityFlagsEXT::MIN);
+    assert!(PresentScaling::default().is_supported(&capabilities));
+    assert!(!PresentScaling::aspect_ratio_stretch_centered().is_supported(&capabilities));
+    let one_sided_gravity = PresentScaling {
+        gravity_x: vk::PresentGravityFlagsEXT::CENTERED,
+        ..Default::default()
+    };
+    assert!(!one_sided_gravity.is_supported(&capabilities));
+}
+
+#[test]
+fn present_was_queued_for_rejected_presents() {
+    assert!(present_was_queued(&Ok(true)));
+    assert!(present_was_queued(&Err(vk::Result::ERROR_OUT_OF_DATE_KHR)));
+    assert!(present_was_queued(&Err(vk::Result::ERROR_SURFACE_LOST_KHR)));
+    assert!(present_was_queued(&Err(
+        vk::Result::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT
+    )));
+    assert!(!present_was_queued(&Err(vk::Result::ERROR_DEVICE_LOST)));
+}
@@ -0,0 +1,434 @@
+use crate::{submit_event, Device, DeviceOwned, SemaphoreOperation, SubmissionEvent};
+use ash::{
+    prelude::VkResult,
+    vk::{self, Handle},
+};
+use log::warn;
+use std::fmt;
+
+/// Debug helper which tracks the resources accessed by in-flight submissions and the
+/// fences/semaphores they signal, and flags submissions that access the same resource (with at
+/// least one write) without waiting on the earlier submission.
+///
+/// Usage:
+/// 1. submit with [`Queue::submit_validated`](crate::Queue::submit_validated), passing the
+///    resources the submission reads/writes. It checks the submission against the pending ones
+///    (hazards are logged as warnings and returned) and then tracks it until the fence or
+///    semaphores it signals are reached. To submit some other way, call
+///    [`Self::validate_submission`] before and [`Self::record_submission`] after submitting.
+/// 2. call [`Self::fence_signalled`], [`Self::timeline_reached`] or [`Self::poll`] to retire
+///    completed submissions.
+///
+/// Submissions to the same queue are assumed to be synchronized with pipeline barriers and
+/// aren't checked against each other. Disabled (all methods are no-ops) unless
+/// `debug_assertions` are on, see [`Self::set_enabled`].
+pub struct SyncValidator {
+    enabled: bool,
+    pending: Vec<PendingSubmission>,
+}
+
+struct PendingSubmission {
+    queue: vk::Queue,
+    accesses: Vec<ResourceAccess>,
+    signals: Vec<SyncGuard>,
+}
+
+impl SyncValidator {
+    pub fn new() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the hazards between a submission to `queue` with `accesses` and waiting on `waits`,
+    /// and the pending submissions.
+    pub fn validate_submission(
+        &self,
+        queue: vk::Queue,
+        accesses: &[ResourceAccess],
+        waits: &[SyncGuard],
+    ) -> Vec<SyncHazard> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        let mut hazards = Vec::new();
+        for pending in self.pending.iter().filter(|pending| pending.queue != queue) {
+            let waits_on_pending = waits
+                .iter()
+                .any(|wait| pending.signals.iter().any(|signal| wait.covers(signal)));
+            if waits_on_pending {
+                continue;
+            }
+
+            for access in accesses {
+                let conflicting_access = pending.accesses.iter().find(|pending_access| {
+                    pending_access.resource == access.resource
+                        && (pending_access.write || access.write)
+                });
+                if let Some(pending_access) = conflicting_access {
+                    hazards.push(SyncHazard {
+                        resource: access.resource,
+                        pending_write: pending_access.write,
+                        new_write: access.write,
+                        pending_queue: pending.queue,
+                    });
+                }
+            }
+        }
+
+        for hazard in &hazards {
+            warn!("sync validation: {}", hazard);
+        }
+        hazards
+    }
+
+    /// Starts tracking a submission to `queue` which signals `signals` (its fence and/or
+    /// semaphores) when complete.
+    pub fn record_submission(
+        &mut self,
+        queue: vk::Queue,
+        accesses: Vec<ResourceAccess>,
+        signals: Vec<SyncGuard>,
+    ) {
+        if !self.enabled || accesses.is_empty() {
+            return;
+        }
+        if signals.is_empty() {
+            warn!("sync validation: submission signals nothing so it will never be retired");
+        }
+        self.pending.push(PendingSubmission {
+            queue,
+            accesses,
+            signals,
+        });
+    }
+
+    /// Retires submissions which signal `fence`.
+    pub fn fence_signalled(&mut self, fence: vk::Fence) {
+        self.pending
+            .retain(|pending| !pending.signals.contains(&SyncGuard::Fence(fence)));
+    }
+
+    /// Retires submissions which signal `semaphore` with a value less than or equal to `value`.
+    pub fn timeline_reached(&mut self, semaphore: vk::Semaphore, value: u64) {
+        let reached = SyncGuard::TimelineSemaphore { semaphore, value };
+        self.pending
+            .retain(|pending| !pending.signals.iter().any(|signal| reached.covers(signal)));
+    }
+
+    /// Queries the status of the fences and timeline semaphores signalled by pending submissions
+    /// and retires completed ones.
+    pub fn poll(&mut self, device: &Device) -> VkResult<()> {
+        let mut completed_fences = Vec::new();
+        let mut reached_timelines = Vec::new();
+
+        for signal in self.pending.iter().flat_map(|pending| &pending.signals) {
+            match *signal {
+                SyncGuard::Fence(fence) => {
+                    if unsafe { device.inner().get_fence_status(fence) }? {
+                        completed_fences.push(fence);
+                    }
+                }
+                SyncGuard::TimelineSemaphore { semaphore, .. } => {
+                    let value = unsafe { device.inner().get_semaphore_counter_value(semaphore) }?;
+                    reached_timelines.push((semaphore, value));
+                }
+                SyncGuard::Semaphore(_) => (),
+            }
+        }
+
+        for fence in completed_fences {
+            self.fence_signalled(fence);
+        }
+        for (semaphore, value) in reached_timelines {
+            self.timeline_reached(semaphore, value);
+        }
+        Ok(())
+    }
+
+    /// Stops tracking all submissions e.g. after `vkDeviceWaitIdle`.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.pending.clear();
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn pending_submission_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl Default for SyncValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The semaphores waited on and signalled by `submit_infos`, with timeline values read from
+/// chained `vk::TimelineSemaphoreSubmitInfo`s, followed by `fence` in the signals.
+pub(crate) fn submission_sync_guards(
+    queue: vk::Queue,
+    submit_infos: &[vk::SubmitInfo<'_>],
+    fence: Option<vk::Fence>,
+) -> (Vec<SyncGuard>, Vec<SyncGuard>) {
+    let mut waits = Vec::new();
+    let mut signals = Vec::new();
+    for submit_info in submit_infos {
+        if let SubmissionEvent::Submit {
+            waits: submit_waits,
+            signals: submit_signals,
+            ..
+        } = submit_event(queue, submit_info, None)
+        {
+            waits.extend(submit_waits.iter().map(SyncGuard::from));
+            signals.extend(submit_signals.iter().map(SyncGuard::from));
+        }
+    }
+    signals.extend(fence.map(SyncGuard::Fence));
+    (waits, signals)
+}
+
+/// A resource handle tracked by [`SyncValidator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyncResource {
+    pub object_type: vk::ObjectType,
+    pub handle: u64,
+}
+
+impl SyncResource {
+    pub fn buffer(buffer: vk::Buffer) -> Self {
+        Self {
+            object_type: vk::ObjectType::BUFFER,
+            handle: buffer.as_raw(),
+        }
+    }
+
+    pub fn image(image: vk::Image) -> Self {
+        Self {
+            object_type: vk::ObjectType::IMAGE,
+            handle: image.as_raw(),
+        }
+    }
+
+    pub fn from_device_owned(object_type: vk::ObjectType, object: &impl DeviceOwned) -> Self {
+        Self {
+            object_type,
+            handle: object.handle_raw(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceAccess {
+    pub resource: SyncResource,
+    pub write: bool,
+}
+
+impl ResourceAccess {
+    pub fn read(resource: SyncResource) -> Self {
+        Self {
+            resource,
+            write: false,
+        }
+    }
+
+    pub fn write(resource: SyncResource) -> Self {
+        Self {
+            resource,
+            write: true,
+        }
+    }
+}
+
+/// A synchronization primitive signalled by a submission or waited on by a later one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncGuard {
+    Fence(vk::Fence),
+    Semaphore(vk::Semaphore),
+    TimelineSemaphore {
+        semaphore: vk::Semaphore,
+        value: u64,
+    },
+}
+
+impl From<&SemaphoreOperation> for SyncGuard {
+    fn from(operation: &SemaphoreOperation) -> Self {
+        match operation.value {
+            Some(value) => Self::TimelineSemaphore {
+                semaphore: operation.semaphore,
+                value,
+            },
+            None => Self::Semaphore(operation.semaphore),
+        }
+    }
+}
+
+impl SyncGuard {
+    /// Whether waiting on `self` guarantees `signal` has been signalled.
+    fn covers(&self, signal: &SyncGuard) -> bool {
+        match (*self, *signal) {
+            (Self::Semaphore(wait), Self::Semaphore(signal)) => wait == signal,
+            (
+                Self::TimelineSemaphore {
+                    semaphore: wait_semaphore,
+                    value: wait_value,
+                },
+                Self::TimelineSemaphore {
+                    semaphore: signal_semaphore,
+                    value: signal_value,
+                },
+            ) => wait_semaphore == signal_semaphore && wait_value >= signal_value,
+            _ => false,
+        }
+    }
+}
+
+/// A resource accessed by a new submission while a pending submission on another queue accesses
+/// it, with at least one of the accesses being a write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncHazard {
+    pub resource: SyncResource,
+    pub pending_write: bool,
+    pub new_write: bool,
+    pub pending_queue: vk::Queue,
+}
+
+impl fmt::Display for SyncHazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access_name = |write: bool| if write { "writes" } else { "reads" };
+        write!(
+            f,
+            "submission {} {:?} {:#x} which a pending submission on queue {:?} {} without waiting on it",
+            access_name(self.new_write),
+            self.resource.object_type,
+            self.resource.handle,
+            self.pending_queue,
+            access_name(self.pending_write),
+        )
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn sync_validator_flags_missing_waits() {
+    let queue_graphics = vk::Queue::from_raw(1);
+    let queue_compute = vk::Queue::from_raw(2);
+    let buffer = SyncResource::buffer(vk::Buffer::from_raw(10));
+    let fence = vk::Fence::from_raw(20);
+    let timeline = vk::Semaphore::from_raw(30);
+
+    let mut validator = SyncValidator::new();
+    validator.set_enabled(true);
+    validator.record_submission(
+        queue_compute,
+        vec![ResourceAccess::read(buffer)],
+        vec![
+            SyncGuard::Fence(fence),
+            SyncGuard::TimelineSemaphore {
+                semaphore: timeline,
+                value: 2,
+            },
+        ],
+    );
+
+    // read after read is fine
+    let reads = [ResourceAccess::read(buffer)];
+    assert!(validator
+        .validate_submission(queue_graphics, &reads, &[])
+        .is_empty());
+
+    // write while a pending submission reads
+    let writes = [ResourceAccess::write(buffer)];
+    assert_eq!(
+        validator
+            .validate_submission(queue_graphics, &writes, &[])
+            .len(),
+        1
+    );
+    let early_wait = SyncGuard::TimelineSemaphore {
+        semaphore: timeline,
+        value: 1,
+    };
+    assert_eq!(
+        validator
+            .validate_submission(queue_graphics, &writes, &[early_wait])
+            .len(),
+        1
+    );
+    let wait = SyncGuard::TimelineSemaphore {
+        semaphore: timeline,
+        value: 2,
+    };
+    assert!(validator
+        .validate_submission(queue_graphics, &writes, &[wait])
+        .is_empty());
+
+    // same queue is assumed to use barriers
+    assert!(validator
+        .validate_submission(queue_compute, &writes, &[])
+        .is_empty());
+
+    validator.fence_signalled(fence);
+    assert_eq!(validator.pending_submission_count(), 0);
+}
+
+#[test]
+fn submission_sync_guards_read_timeline_values() {
+    let queue = vk::Queue::from_raw(1);
+    let binary = vk::Semaphore::from_raw(2);
+    let timeline = vk::Semaphore::from_raw(3);
+    let fence = vk::Fence::from_raw(4);
+
+    let wait_semaphores = [binary, timeline];
+    let wait_stages = [vk::PipelineStageFlags::ALL_COMMANDS; 2];
+    let wait_values = [0, 5];
+    let signal_semaphores = [timeline];
+    let signal_values = [6];
+    let mut timeline_info = vk::TimelineSemaphoreSubmitInfo::default()
+        .wait_semaphore_values(&wait_values)
+        .signal_semaphore_values(&signal_values);
+    let submit_info = vk::SubmitInfo::default()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .signal_semaphores(&signal_semaphores)
+        .push_next(&mut timeline_info);
+
+    let (waits, signals) = submission_sync_guards(queue, &[submit_info], Some(fence));
+    assert_eq!(
+        waits,
+        [
+            SyncGuard::Semaphore(binary),
+            SyncGuard::TimelineSemaphore {
+                semaphore: timeline,
+                value: 5
+            }
+        ]
+    );
+    assert_eq!(
+        signals,
+        [
+            SyncGuard::TimelineSemaphore {
+                semaphore: timeline,
+                value: 6
+            },
+            SyncGuard::Fence(fence)
+        ]
+    );
+}
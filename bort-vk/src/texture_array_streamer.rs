@@ -0,0 +1,702 @@
+use crate::{
+    default_subresource_layers, image_copy_data_size, new_staging_buffer, single_copy_aspect,
+    AllocationError, AllocatorAccess, Buffer, CommandBuffer, DeferDrop, Gpu, Image, ImageAccess,
+    ImageDimensions, ImageProperties, ResourceInitError,
+};
+use ash::vk;
+use bort_vma::AllocationCreateInfo;
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt,
+    sync::Arc,
+};
+
+/// Identifies a logical texture managed by a [`TextureArrayStreamer`]. Chosen by the caller e.g.
+/// a hash of the asset path.
+pub type StreamedTextureKey = u64;
+
+/// Manages a 2D array image where each layer holds one logical texture. Texture data is streamed
+/// in through staging buffers and least-recently-used textures are evicted when every layer is
+/// taken.
+///
+/// The image only has as many layers as fit in the memory budget (up to `max_layers`) so the
+/// budget limits the memory actually allocated. Changing the budget with
+/// [`Self::set_memory_budget`] reallocates the image at the next [`Self::record_uploads`],
+/// copying the resident textures over. Their layers may change so look them up again with
+/// [`Self::touch`] and rebind [`Self::image`] afterwards.
+///
+/// Typical frame usage:
+/// 1. [`Self::begin_frame`]
+/// 2. [`Self::touch`] resident textures that are drawn this frame or [`Self::stream_in`] new ones
+/// 3. [`Self::record_uploads`] before the commands that sample the array
+///
+/// Textures used in the last `frames_in_flight` frames are never evicted and staging buffers are
+/// kept alive for `frames_in_flight` frames after their last use was recorded, so the caller must
+/// wait on its per-frame fences as usual before calling `begin_frame`. The image is handed out as
+/// a [`Gpu`] pointer so one replaced by a reallocation is retired to `deferred_drop` (e.g. a
+/// [`DeviceResources`](crate::DeviceResources)) once the last pointer to it is dropped.
+///
+/// Note: layers that have never been uploaded to are in `vk::ImageLayout::UNDEFINED` layout so
+/// shaders must only sample layers returned by this streamer.
+pub struct TextureArrayStreamer {
+    image: Gpu<Image>,
+    allocation_info: AllocationCreateInfo,
+    properties: TextureArrayStreamerProperties,
+    aspect_mask: vk::ImageAspectFlags,
+    /// Exact size of the texel data of one layer.
+    upload_size: vk::DeviceSize,
+    /// Memory used by one layer of the image.
+    layer_size: vk::DeviceSize,
+    frames_in_flight: u64,
+
+    entries: HashMap<StreamedTextureKey, StreamedTexture>,
+    free_layers: Vec<u32>,
+    pending_uploads: Vec<PendingUpload>,
+    in_flight_staging_buffers: Vec<(u64, Buffer)>,
+    current_frame: u64,
+
+    // dependencies
+    alloc_access: Arc<dyn AllocatorAccess>,
+    deferred_drop: Arc<dyn DeferDrop>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StreamedTexture {
+    layer: u32,
+    last_used_frame: u64,
+}
+
+struct PendingUpload {
+    layer: u32,
+    staging_buffer: Buffer,
+}
+
+impl TextureArrayStreamer {
+    /// Creates a 2D array image with `SAMPLED | TRANSFER_DST | TRANSFER_SRC` usage and as many
+    /// layers as fit in `properties.memory_budget` (at least one, at most
+    /// `properties.max_layers`).
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        deferred_drop: Arc<dyn DeferDrop>,
+        properties: TextureArrayStreamerProperties,
+        allocation_info: AllocationCreateInfo,
+    ) -> Result<Self, TextureStreamerError> {
+        let format = properties.format;
+        let aspect_mask = single_copy_aspect(format)
+            .ok_or(TextureStreamerError::CombinedDepthStencilFormat(format))?;
+        let layer_extent = vk::Extent3D {
+            width: properties.width,
+            height: properties.height,
+            depth: 1,
+        };
+        let upload_size = image_copy_data_size(format, layer_extent, 1)
+            .ok_or(TextureStreamerError::UnknownTexelSize(format))?;
+        let max_layers = properties.max_layers.max(1);
+
+        // the tightly packed data size is a lower bound for the memory of a layer. if the actual
+        // size pushes the image over budget, create it again with fewer layers
+        let estimated_layer_count =
+            resident_layer_limit(properties.memory_budget, upload_size, max_layers);
+        let mut image = create_array_image(
+            &alloc_access,
+            &properties,
+            estimated_layer_count,
+            &allocation_info,
+        )?;
+        let layer_size = image.memory_allocation().size() / estimated_layer_count as u64;
+        let layer_count = resident_layer_limit(properties.memory_budget, layer_size, max_layers);
+        if layer_count < estimated_layer_count {
+            image = create_array_image(&alloc_access, &properties, layer_count, &allocation_info)?;
+        }
+
+        Ok(Self {
+            image: Gpu::new(deferred_drop.clone(), image),
+            allocation_info,
+            properties,
+            aspect_mask,
+            upload_size,
+            layer_size,
+            frames_in_flight: properties.frames_in_flight as u64,
+
+            entries: HashMap::new(),
+            free_layers: (0..layer_count).rev().collect(),
+            pending_uploads: Vec::new(),
+            in_flight_staging_buffers: Vec::new(),
+            current_frame: 0,
+
+            alloc_access,
+            deferred_drop,
+        })
+    }
+
+    /// Advances the frame counter and frees staging buffers whose last use has completed.
+    pub fn begin_frame(&mut self) {
+        self.current_frame += 1;
+        let current_frame = self.current_frame;
+        let frames_in_flight = self.frames_in_flight;
+        self.in_flight_staging_buffers
+            .retain(|(recorded_frame, _)| recorded_frame + frames_in_flight > current_frame);
+    }
+
+    /// Marks a resident texture as used this frame and returns its array layer. Returns `None`
+    /// if the texture isn't resident.
+    pub fn touch(&mut self, key: StreamedTextureKey) -> Option<u32> {
+        let current_frame = self.current_frame;
+        self.entries.get_mut(&key).map(|entry| {
+            entry.last_used_frame = current_frame;
+            entry.layer
+        })
+    }
+
+    /// Returns the array layer of `key`. If it isn't resident, a layer is allocated (evicting
+    /// the least-recently-used texture if required) and `data` is copied to a staging buffer to
+    /// be uploaded by the next call to [`Self::record_uploads`]. `data` must be tightly packed
+    /// texel data for exactly one layer (see [`Self::upload_size`]).
+    pub fn stream_in(
+        &mut self,
+        key: StreamedTextureKey,
+        data: &[u8],
+    ) -> Result<u32, TextureStreamerError> {
+        if let Some(layer) = self.touch(key) {
+            return Ok(layer);
+        }
+
+        if data.len() as vk::DeviceSize != self.upload_size {
+            return Err(TextureStreamerError::DataSizeMismatch {
+                data_size: data.len(),
+                expected_size: self.upload_size,
+            });
+        }
+
+        let layer = self.allocate_layer()?;
+        let staging_buffer = match new_staging_buffer(self.alloc_access.clone(), data) {
+            Ok(staging_buffer) => staging_buffer,
+            Err(e) => {
+                self.free_layers.push(layer);
+                return Err(TextureStreamerError::Staging(e));
+            }
+        };
+
+        self.pending_uploads.push(PendingUpload {
+            layer,
+            staging_buffer,
+        });
+        self.entries.insert(
+            key,
+            StreamedTexture {
+                layer,
+                last_used_frame: self.current_frame,
+            },
+        );
+        Ok(layer)
+    }
+
+    /// Evicts `key` if it's resident and not used by a frame in flight. Returns true if the
+    /// texture was evicted.
+    pub fn evict(&mut self, key: StreamedTextureKey) -> bool {
+        let evictable = self
+            .entries
+            .get(&key)
+            .map(|entry| self.is_evictable(entry))
+            .unwrap_or(false);
+        if !evictable {
+            return false;
+        }
+
+        if let Some(entry) = self.entries.remove(&key) {
+            self.release_layer(entry.layer);
+        }
+        true
+    }
+
+    /// Changes the memory budget. Least-recently-used textures that aren't in flight are evicted
+    /// until the resident textures fit (or nothing else can be evicted) and the image is
+    /// reallocated with the new number of layers by the next [`Self::record_uploads`].
+    pub fn set_memory_budget(&mut self, memory_budget: vk::DeviceSize) {
+        self.properties.memory_budget = memory_budget;
+        while self.entries.len() > self.resident_layer_limit() as usize {
+            if self.evict_least_recently_used().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Reallocates the image if the memory budget changed, then records copies from the staging
+    /// buffers of all textures streamed in since the last call with a single set of layout
+    /// transitions before and after. Uploaded layers end up in
+    /// `vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL` layout. Returns the number of layers uploaded.
+    pub fn record_uploads(
+        &mut self,
+        command_buffer: &CommandBuffer,
+    ) -> Result<usize, TextureStreamerError> {
+        self.reallocate_if_required(command_buffer)?;
+
+        if self.pending_uploads.is_empty() {
+            return Ok(0);
+        }
+
+        let to_transfer_dst_barriers: Vec<vk::ImageMemoryBarrier> = self
+            .pending_uploads
+            .iter()
+            .map(|upload| {
+                layer_barrier(&self.image, self.aspect_mask, upload.layer)
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            })
+            .collect();
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_transfer_dst_barriers,
+        );
+
+        let extent = self.image.dimensions().extent_3d();
+        for upload in &self.pending_uploads {
+            let copy_region = vk::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: vk::ImageSubresourceLayers {
+                    base_array_layer: upload.layer,
+                    ..default_subresource_layers(self.aspect_mask)
+                },
+                image_offset: vk::Offset3D::default(),
+                image_extent: vk::Extent3D { depth: 1, ..extent },
+            };
+            command_buffer.copy_buffer_to_image(
+                &upload.staging_buffer,
+                self.image.as_ref(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+        }
+
+        let to_shader_read_barriers: Vec<vk::ImageMemoryBarrier> = self
+            .pending_uploads
+            .iter()
+            .map(|upload| to_shader_read_barrier(&self.image, self.aspect_mask, upload.layer))
+            .collect();
+        command_buffer.pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_shader_read_barriers,
+        );
+
+        let upload_count = self.pending_uploads.len();
+        let current_frame = self.current_frame;
+        self.in_flight_staging_buffers.extend(
+            self.pending_uploads
+                .drain(..)
+                .map(|upload| (current_frame, upload.staging_buffer)),
+        );
+        Ok(upload_count)
+    }
+
+    /// Creates an image with the layer count allowed by the memory budget (or the number of
+    /// resident textures if more of them are still in flight) and copies the uploaded resident
+    /// textures over. Textures on layers past the new layer count move to free layers.
+    fn reallocate_if_required(
+        &mut self,
+        command_buffer: &CommandBuffer,
+    ) -> Result<(), TextureStreamerError> {
+        let new_layer_count = self.resident_layer_limit().max(self.entries.len() as u32);
+        if new_layer_count == self.layer_count() {
+            return Ok(());
+        }
+
+        let new_image = Gpu::new(
+            self.deferred_drop.clone(),
+            create_array_image(
+                &self.alloc_access,
+                &self.properties,
+                new_layer_count,
+                &self.allocation_info,
+            )?,
+        );
+
+        let occupied_layers: HashSet<u32> =
+            self.entries.values().map(|entry| entry.layer).collect();
+        let mut free_layers: Vec<u32> = (0..new_layer_count)
+            .rev()
+            .filter(|layer| !occupied_layers.contains(layer))
+            .collect();
+
+        // (old layer, new layer) of textures which have already been uploaded
+        let mut layer_copies = Vec::new();
+        for entry in self.entries.values_mut() {
+            let old_layer = entry.layer;
+            if old_layer >= new_layer_count {
+                entry.layer = free_layers
+                    .pop()
+                    .expect("there's a layer for every resident texture");
+            }
+            let pending_upload = self
+                .pending_uploads
+                .iter_mut()
+                .find(|upload| upload.layer == old_layer);
+            match pending_upload {
+                Some(pending_upload) => pending_upload.layer = entry.layer,
+                None => layer_copies.push((old_layer, entry.layer)),
+            }
+        }
+
+        if !layer_copies.is_empty() {
+            let aspect_mask = self.aspect_mask;
+            let mut before_copy_barriers = Vec::with_capacity(layer_copies.len() * 2);
+            for &(old_layer, new_layer) in &layer_copies {
+                before_copy_barriers.push(
+                    layer_barrier(&self.image, aspect_mask, old_layer)
+                        .src_access_mask(vk::AccessFlags::empty())
+                        .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                        .old_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+                );
+                before_copy_barriers.push(
+                    layer_barrier(&new_image, aspect_mask, new_layer)
+                        .src_access_mask(vk::AccessFlags::empty())
+                        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+                );
+            }
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &before_copy_barriers,
+            );
+
+            let extent = vk::Extent3D {
+                depth: 1,
+                ..self.image.dimensions().extent_3d()
+            };
+            let copy_regions: Vec<vk::ImageCopy> = layer_copies
+                .iter()
+                .map(|&(old_layer, new_layer)| vk::ImageCopy {
+                    src_subresource: vk::ImageSubresourceLayers {
+                        base_array_layer: old_layer,
+                        ..default_subresource_layers(aspect_mask)
+                    },
+                    src_offset: vk::Offset3D::default(),
+                    dst_subresource: vk::ImageSubresourceLayers {
+                        base_array_layer: new_layer,
+                        ..default_subresource_layers(aspect_mask)
+                    },
+                    dst_offset: vk::Offset3D::default(),
+                    extent,
+                })
+                .collect();
+            command_buffer.copy_image(
+                self.image.as_ref(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                new_image.as_ref(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copy_regions,
+            );
+
+            let after_copy_barriers: Vec<vk::ImageMemoryBarrier> = layer_copies
+                .iter()
+                .map(|&(_, new_layer)| to_shader_read_barrier(&new_image, aspect_mask, new_layer))
+                .collect();
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &after_copy_barriers,
+            );
+        }
+
+        // retired to `deferred_drop` once the caller has dropped its pointers to it too
+        self.image = new_image;
+        self.free_layers = free_layers;
+        Ok(())
+    }
+
+    fn allocate_layer(&mut self) -> Result<u32, TextureStreamerError> {
+        if let Some(layer) = self.free_layers.pop() {
+            return Ok(layer);
+        }
+
+        self.evict_least_recently_used()
+            .ok_or(TextureStreamerError::BudgetExhausted {
+                resident_layer_limit: self.layer_count(),
+            })?;
+        self.free_layers
+            .pop()
+            .ok_or(TextureStreamerError::BudgetExhausted {
+                resident_layer_limit: self.layer_count(),
+            })
+    }
+
+    /// Returns the evicted key.
+    fn evict_least_recently_used(&mut self) -> Option<StreamedTextureKey> {
+        let (&lru_key, _) = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.is_evictable(entry))
+            .min_by_key(|(_, entry)| entry.last_used_frame)?;
+        let entry = self.entries.remove(&lru_key)?;
+        self.release_layer(entry.layer);
+        Some(lru_key)
+    }
+
+    /// Drops the pending upload of `layer`, if any, and makes it available again.
+    fn release_layer(&mut self, layer: u32) {
+        self.pending_uploads.retain(|upload| upload.layer != layer);
+        if layer < self.layer_count() {
+            self.free_layers.push(layer);
+        }
+    }
+
+    fn is_evictable(&self, entry: &StreamedTexture) -> bool {
+        entry.last_used_frame + self.frames_in_flight <= self.current_frame
+    }
+
+    // Getters
+
+    /// Changes when the image is reallocated by [`Self::record_uploads`] after
+    /// [`Self::set_memory_budget`].
+    #[inline]
+    pub fn image(&self) -> &Gpu<Image> {
+        &self.image
+    }
+
+    /// Memory used by a single layer (the image allocation size divided by its layer count).
+    #[inline]
+    pub fn layer_size(&self) -> vk::DeviceSize {
+        self.layer_size
+    }
+
+    /// Size of the data [`Self::stream_in`] expects for one texture.
+    #[inline]
+    pub fn upload_size(&self) -> vk::DeviceSize {
+        self.upload_size
+    }
+
+    #[inline]
+    pub fn max_layers(&self) -> u32 {
+        self.properties.max_layers.max(1)
+    }
+
+    /// Number of layers of the current image.
+    #[inline]
+    pub fn layer_count(&self) -> u32 {
+        self.image.dimensions().array_layers()
+    }
+
+    /// Number of layers that fit in the memory budget.
+    #[inline]
+    pub fn resident_layer_limit(&self) -> u32 {
+        resident_layer_limit(
+            self.properties.memory_budget,
+            self.layer_size,
+            self.max_layers(),
+        )
+    }
+
+    #[inline]
+    pub fn memory_budget(&self) -> vk::DeviceSize {
+        self.properties.memory_budget
+    }
+
+    #[inline]
+    pub fn resident_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_resident(&self, key: StreamedTextureKey) -> bool {
+        self.entries.contains_key(&key)
+    }
+
+    #[inline]
+    pub fn current_frame(&self) -> u64 {
+        self.current_frame
+    }
+}
+
+// Properties
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextureArrayStreamerProperties {
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    pub max_layers: u32,
+    /// Limits the number of image layers (and therefore resident textures) to
+    /// `memory_budget / layer_size`.
+    pub memory_budget: vk::DeviceSize,
+    pub frames_in_flight: u32,
+}
+
+impl TextureArrayStreamerProperties {
+    /// No memory budget limit besides `max_layers`.
+    pub fn new_default(
+        format: vk::Format,
+        width: u32,
+        height: u32,
+        max_layers: u32,
+        frames_in_flight: u32,
+    ) -> Self {
+        Self {
+            format,
+            width,
+            height,
+            max_layers,
+            memory_budget: vk::DeviceSize::MAX,
+            frames_in_flight,
+        }
+    }
+}
+
+// Helper Functions
+
+fn create_array_image(
+    alloc_access: &Arc<dyn AllocatorAccess>,
+    properties: &TextureArrayStreamerProperties,
+    layer_count: u32,
+    allocation_info: &AllocationCreateInfo,
+) -> Result<Image, TextureStreamerError> {
+    let image_properties = ImageProperties::new_default(
+        properties.format,
+        ImageDimensions::new_2d_array(properties.width, properties.height, layer_count),
+        vk::ImageUsageFlags::SAMPLED
+            | vk::ImageUsageFlags::TRANSFER_DST
+            | vk::ImageUsageFlags::TRANSFER_SRC,
+    );
+    Image::new(
+        alloc_access.clone(),
+        image_properties,
+        allocation_info.clone(),
+    )
+    .map_err(TextureStreamerError::ImageCreation)
+}
+
+/// Barrier for one layer of `image` without access masks or layouts set.
+fn layer_barrier(
+    image: &Image,
+    aspect_mask: vk::ImageAspectFlags,
+    layer: u32,
+) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image.handle())
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: layer,
+            layer_count: 1,
+        })
+}
+
+fn to_shader_read_barrier(
+    image: &Image,
+    aspect_mask: vk::ImageAspectFlags,
+    layer: u32,
+) -> vk::ImageMemoryBarrier<'static> {
+    layer_barrier(image, aspect_mask, layer)
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+}
+
+fn resident_layer_limit(
+    memory_budget: vk::DeviceSize,
+    layer_size: vk::DeviceSize,
+    max_layers: u32,
+) -> u32 {
+    let budget_layers = memory_budget / layer_size.max(1);
+    budget_layers.clamp(1, max_layers as u64) as u32
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum TextureStreamerError {
+    ImageCreation(AllocationError),
+    Staging(ResourceInitError),
+    DataSizeMismatch {
+        data_size: usize,
+        expected_size: vk::DeviceSize,
+    },
+    /// The texel size of the format isn't known so the layer data size can't be checked.
+    UnknownTexelSize(vk::Format),
+    /// Buffer to image copies can only copy one aspect at a time.
+    CombinedDepthStencilFormat(vk::Format),
+    /// Every resident texture is in use by a frame in flight.
+    BudgetExhausted {
+        resident_layer_limit: u32,
+    },
+}
+
+impl fmt::Display for TextureStreamerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImageCreation(e) => write!(f, "failed to create texture array image: {}", e),
+            Self::Staging(e) => write!(f, "failed to create staging buffer: {}", e),
+            Self::DataSizeMismatch {
+                data_size,
+                expected_size,
+            } => write!(
+                f,
+                "texture data size {} doesn't match the layer data size {}",
+                data_size, expected_size
+            ),
+            Self::UnknownTexelSize(format) => write!(
+                f,
+                "texel size of texture array format {:?} is unknown",
+                format
+            ),
+            Self::CombinedDepthStencilFormat(format) => write!(
+                f,
+                "texture array format {:?} has both depth and stencil aspects",
+                format
+            ),
+            Self::BudgetExhausted {
+                resident_layer_limit,
+            } => write!(
+                f,
+                "all {} resident texture layers are in use by frames in flight",
+                resident_layer_limit
+            ),
+        }
+    }
+}
+
+impl error::Error for TextureStreamerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::ImageCreation(e) => Some(e),
+            Self::Staging(e) => Some(e),
+            Self::DataSizeMismatch { .. } => None,
+            Self::UnknownTexelSize(_) => None,
+            Self::CombinedDepthStencilFormat(_) => None,
+            Self::BudgetExhausted { .. } => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn resident_layer_limit_clamps_to_budget() {
+    assert_eq!(resident_layer_limit(vk::DeviceSize::MAX, 1024, 16), 16);
+    assert_eq!(resident_layer_limit(4096, 1024, 16), 4);
+    // always allow at least one resident layer
+    assert_eq!(resident_layer_limit(100, 1024, 16), 1);
+}
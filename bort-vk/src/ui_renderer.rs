@@ -0,0 +1,847 @@
+use crate::{
+    allocation_info_cpu_accessible, allocation_info_device_local, default_subresource_layers,
+    resource_init::{new_staging_buffer, record_submit_and_wait},
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, BufferProperties, ColorBlendState,
+    CommandBuffer, CommandPool, DeferDrop, DescriptorPool, DescriptorPoolProperties, DescriptorSet,
+    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutError,
+    DescriptorSetLayoutProperties, DeviceOwned, DynamicState, Gpu, GpuDropPolicy, GraphicsPipeline,
+    GraphicsPipelineProperties, Image, ImageAccess, ImageDimensions, ImageProperties, ImageView,
+    ImageViewAccess, ImageViewError, ImageViewProperties, MemoryError, MultisampleState,
+    PipelineAccess, PipelineLayout, PipelineLayoutProperties, Queue, RenderPass, ResourceInitError,
+    Sampler, SamplerProperties, ShaderError, ShaderModule, ShaderStage, VertexInputState,
+    ViewportState,
+};
+use ash::vk;
+use std::{collections::HashMap, error, fmt, io::Cursor, mem, slice, sync::Arc};
+
+/// Spir-V compiled from `shaders/ui.vert`.
+const UI_VERT_SPIRV: &[u8] = include_bytes!("./shaders/ui.vert.spv");
+/// Spir-V compiled from `shaders/ui.frag`.
+const UI_FRAG_SPIRV: &[u8] = include_bytes!("./shaders/ui.frag.spv");
+
+const UI_TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+/// Vertex of a [`UiMesh`]. Same layout as `egui::epaint::Vertex` so egui meshes can be copied (or
+/// cast with bytemuck) directly.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UiVertex {
+    /// Logical pixels (points) with the origin at the top left.
+    pub pos: [f32; 2],
+    /// Normalized texture coordinates.
+    pub uv: [f32; 2],
+    /// sRGBA with premultiplied alpha.
+    pub color: [u8; 4],
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for UiVertex {}
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for UiVertex {}
+
+/// Identifies a texture uploaded with [`UiRenderer::set_texture`]. egui texture ids can be mapped
+/// to this e.g. `TextureId::Managed(id)` to `UiTextureId(id)` and `TextureId::User(id)` to
+/// `UiTextureId(id | 1 << 63)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UiTextureId(pub u64);
+
+/// Axis aligned rectangle in points.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UiRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+/// A triangle list clipped to `clip_rect`. Corresponds to an egui `ClippedPrimitive` containing a
+/// `Primitive::Mesh`.
+#[derive(Debug, Clone, Default)]
+pub struct UiMesh {
+    pub clip_rect: UiRect,
+    pub texture_id: Option<UiTextureId>,
+    pub vertices: Vec<UiVertex>,
+    pub indices: Vec<u32>,
+}
+
+/// A texture upload. Corresponds to an egui `ImageDelta` converted to premultiplied sRGBA8.
+#[derive(Debug, Clone, Copy)]
+pub struct UiImageDelta<'a> {
+    /// `None` to (re)create the whole texture, `Some` to update a region of an existing texture
+    /// starting at this texel.
+    pub pos: Option<[u32; 2]>,
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed premultiplied sRGBA8 texels.
+    pub pixels: &'a [u8],
+    /// Only used when the whole texture is (re)created.
+    pub filter: vk::Filter,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UiRendererProperties {
+    /// Number of frames that can be recorded before the first one has finished executing. Vertex
+    /// and index buffers are allocated per frame.
+    pub frames_in_flight: usize,
+    /// Maximum number of textures alive at once (including freed ones which haven't been dropped
+    /// by the deferred drop queue yet).
+    pub max_textures: u32,
+    /// Must match the sample count of the color attachment in the target subpass.
+    pub rasterization_samples: vk::SampleCountFlags,
+}
+
+impl Default for UiRendererProperties {
+    fn default() -> Self {
+        Self {
+            frames_in_flight: 2,
+            max_textures: 64,
+            rasterization_samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+}
+
+/// Renders textured, clipped triangle meshes e.g. egui paint jobs. Alpha blending assumes
+/// premultiplied colors in gamma space, so render to a `UNORM` color attachment for results that
+/// match egui's reference renderers.
+///
+/// Per frame usage:
+/// 1. apply texture uploads and frees with [`Self::set_texture`] and [`Self::free_texture`].
+/// 2. call [`Self::draw`] once inside the render pass.
+pub struct UiRenderer {
+    pipeline: GraphicsPipeline,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    descriptor_pool: Arc<DescriptorPool>,
+    linear_sampler: Arc<Sampler>,
+    nearest_sampler: Arc<Sampler>,
+
+    /// Freed and replaced textures are retired to `deferred_drop`.
+    textures: HashMap<UiTextureId, Gpu<UiTexture>>,
+
+    frames: Vec<UiFrameBuffers>,
+    frame_index: usize,
+
+    // dependencies
+    alloc_access: Arc<dyn AllocatorAccess>,
+    deferred_drop: Arc<dyn DeferDrop>,
+}
+
+struct UiTexture {
+    image_view: Arc<ImageView<Image>>,
+    descriptor_set: DescriptorSet,
+    // the descriptor set references this sampler
+    _sampler: Arc<Sampler>,
+}
+
+impl GpuDropPolicy for UiTexture {}
+
+#[derive(Default)]
+struct UiFrameBuffers {
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl UiRenderer {
+    /// Textures that are freed or replaced are handed to `deferred_drop` (e.g. a
+    /// [`DeviceResources`](crate::DeviceResources)) as frames in flight may still be using them.
+    pub fn new(
+        alloc_access: Arc<dyn AllocatorAccess>,
+        deferred_drop: Arc<dyn DeferDrop>,
+        render_pass: &RenderPass,
+        subpass_index: u32,
+        properties: UiRendererProperties,
+    ) -> Result<Self, UiRendererError> {
+        let device = alloc_access.device().clone();
+
+        let descriptor_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(vec![DescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    descriptor_count: 1,
+                    stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                    ..Default::default()
+                }]),
+            )
+            .map_err(UiRendererError::DescriptorSetLayout)?,
+        );
+
+        let push_constant_range = vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::VERTEX,
+            offset: 0,
+            size: mem::size_of::<[f32; 2]>() as u32,
+        };
+        let pipeline_layout = Arc::new(
+            PipelineLayout::new(
+                device.clone(),
+                PipelineLayoutProperties::new(
+                    vec![descriptor_set_layout.clone()],
+                    vec![push_constant_range],
+                ),
+            )
+            .map_err(UiRendererError::Vulkan)?,
+        );
+
+        let vert_shader = Arc::new(
+            ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(UI_VERT_SPIRV))
+                .map_err(UiRendererError::Shader)?,
+        );
+        let vert_stage = ShaderStage::vertex(vert_shader).map_err(UiRendererError::Shader)?;
+        let frag_shader = Arc::new(
+            ShaderModule::new_from_spirv(device.clone(), &mut Cursor::new(UI_FRAG_SPIRV))
+                .map_err(UiRendererError::Shader)?,
+        );
+        let frag_stage = ShaderStage::fragment(frag_shader).map_err(UiRendererError::Shader)?;
+
+        let pipeline_properties = GraphicsPipelineProperties {
+            subpass_index,
+            vertex_input_state: ui_vertex_input_state(),
+            viewport_state: ViewportState::new_dynamic(1, 1),
+            multisample_state: MultisampleState {
+                rasterization_samples: properties.rasterization_samples,
+                ..Default::default()
+            },
+            color_blend_state: ColorBlendState::new_default(vec![blend_state_premultiplied()]),
+            dynamic_state: DynamicState::new_default(vec![
+                vk::DynamicState::VIEWPORT,
+                vk::DynamicState::SCISSOR,
+            ]),
+            ..Default::default()
+        };
+        let pipeline = GraphicsPipeline::new(
+            pipeline_layout,
+            pipeline_properties,
+            &[vert_stage, frag_stage],
+            render_pass,
+            None,
+        )
+        .map_err(UiRendererError::Vulkan)?;
+
+        let max_textures = properties.max_textures.max(1);
+        let descriptor_pool = Arc::new(
+            DescriptorPool::new(
+                device.clone(),
+                DescriptorPoolProperties {
+                    flags: vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
+                    max_sets: max_textures,
+                    pool_sizes: vec![vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: max_textures,
+                    }],
+                },
+            )
+            .map_err(UiRendererError::Vulkan)?,
+        );
+
+        let linear_sampler = Arc::new(
+            Sampler::new(
+                device.clone(),
+                SamplerProperties {
+                    mag_filter: vk::Filter::LINEAR,
+                    min_filter: vk::Filter::LINEAR,
+                    ..Default::default()
+                },
+            )
+            .map_err(UiRendererError::Vulkan)?,
+        );
+        let nearest_sampler = Arc::new(
+            Sampler::new(device, SamplerProperties::default()).map_err(UiRendererError::Vulkan)?,
+        );
+
+        let frames = (0..properties.frames_in_flight.max(1))
+            .map(|_| UiFrameBuffers::default())
+            .collect();
+
+        Ok(Self {
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+            linear_sampler,
+            nearest_sampler,
+            textures: HashMap::new(),
+            frames,
+            frame_index: 0,
+            alloc_access,
+            deferred_drop,
+        })
+    }
+
+    /// Creates, replaces or partially updates the texture `texture_id`. Blocks until the upload
+    /// has completed on `queue`. Partial updates are ordered after previously submitted work on
+    /// `queue`, so `queue` should be the queue that draw commands are submitted to.
+    pub fn set_texture(
+        &mut self,
+        texture_id: UiTextureId,
+        image_delta: UiImageDelta,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+    ) -> Result<(), UiRendererError> {
+        let expected_size = image_delta.width as usize * image_delta.height as usize * 4;
+        if image_delta.pixels.len() != expected_size {
+            return Err(UiRendererError::PixelDataSizeMismatch {
+                expected_size,
+                data_size: image_delta.pixels.len(),
+            });
+        }
+
+        match image_delta.pos {
+            None => self.create_texture(texture_id, image_delta, command_pool, queue),
+            Some(pos) => {
+                self.update_texture_region(texture_id, pos, image_delta, command_pool, queue)
+            }
+        }
+    }
+
+    /// The texture is retired to the deferred drop queue given to [`Self::new`] so it's kept alive
+    /// until it can no longer be in use by frames in flight.
+    pub fn free_texture(&mut self, texture_id: UiTextureId) {
+        self.textures.remove(&texture_id);
+    }
+
+    /// Records draw commands for `meshes`. Must be called inside the render pass/subpass this was
+    /// created for, once per frame. Meshes without a texture id or with an unknown one are
+    /// skipped.
+    ///
+    /// `screen_size_pixels` is the size of the render target and `pixels_per_point` the UI scale
+    /// factor.
+    pub fn draw(
+        &mut self,
+        command_buffer: &CommandBuffer,
+        meshes: &[UiMesh],
+        screen_size_pixels: [u32; 2],
+        pixels_per_point: f32,
+    ) -> Result<(), UiRendererError> {
+        let frame_index = self.frame_index;
+        self.frame_index = (self.frame_index + 1) % self.frames.len();
+
+        let vertex_count: usize = meshes.iter().map(|mesh| mesh.vertices.len()).sum();
+        let index_count: usize = meshes.iter().map(|mesh| mesh.indices.len()).sum();
+        if vertex_count == 0 || index_count == 0 {
+            return Ok(());
+        }
+
+        let mut vertices = Vec::<UiVertex>::with_capacity(vertex_count);
+        let mut indices = Vec::<u32>::with_capacity(index_count);
+        for mesh in meshes {
+            vertices.extend_from_slice(&mesh.vertices);
+            indices.extend_from_slice(&mesh.indices);
+        }
+        let vertex_bytes = unsafe {
+            slice::from_raw_parts(
+                vertices.as_ptr() as *const u8,
+                mem::size_of_val(vertices.as_slice()),
+            )
+        };
+        let index_bytes = unsafe {
+            slice::from_raw_parts(
+                indices.as_ptr() as *const u8,
+                mem::size_of_val(indices.as_slice()),
+            )
+        };
+
+        let alloc_access = self.alloc_access.clone();
+        let frame = &mut self.frames[frame_index];
+        let vertex_buffer = write_frame_buffer(
+            &mut frame.vertex_buffer,
+            &alloc_access,
+            vertex_bytes,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+        )?;
+        let index_buffer = write_frame_buffer(
+            &mut frame.index_buffer,
+            &alloc_access,
+            index_bytes,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+        )?;
+
+        let pipeline_layout = self.pipeline.pipeline_layout();
+        command_buffer.bind_pipeline(&self.pipeline);
+        command_buffer.bind_vertex_buffers(0, [vertex_buffer], &[0]);
+        command_buffer.bind_index_buffer(index_buffer, 0, vk::IndexType::UINT32);
+        command_buffer.set_viewport(
+            0,
+            &[
+                ImageDimensions::new_2d(screen_size_pixels[0], screen_size_pixels[1])
+                    .whole_viewport(),
+            ],
+        );
+
+        let screen_size_points = [
+            screen_size_pixels[0] as f32 / pixels_per_point,
+            screen_size_pixels[1] as f32 / pixels_per_point,
+        ];
+        let push_constant_bytes: Vec<u8> = screen_size_points
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        command_buffer.push_constants(
+            pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            &push_constant_bytes,
+        );
+
+        let mut first_index = 0_u32;
+        let mut vertex_offset = 0_i32;
+        for mesh in meshes {
+            let mesh_first_index = first_index;
+            let mesh_vertex_offset = vertex_offset;
+            first_index += mesh.indices.len() as u32;
+            vertex_offset += mesh.vertices.len() as i32;
+
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let Some(texture) = mesh
+                .texture_id
+                .and_then(|texture_id| self.textures.get(&texture_id))
+            else {
+                continue;
+            };
+            let Some(scissor) =
+                ui_scissor_rect(mesh.clip_rect, pixels_per_point, screen_size_pixels)
+            else {
+                continue;
+            };
+
+            command_buffer.set_scissor(0, &[scissor]);
+            command_buffer.bind_descriptor_sets(
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_layout,
+                0,
+                [&texture.descriptor_set],
+                &[],
+            );
+            command_buffer.draw_indexed(
+                mesh.indices.len() as u32,
+                1,
+                mesh_first_index,
+                mesh_vertex_offset,
+                0,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn create_texture(
+        &mut self,
+        texture_id: UiTextureId,
+        image_delta: UiImageDelta,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+    ) -> Result<(), UiRendererError> {
+        let image_properties = ImageProperties::new_default(
+            UI_TEXTURE_FORMAT,
+            ImageDimensions::new_2d(image_delta.width, image_delta.height),
+            vk::ImageUsageFlags::SAMPLED,
+        );
+        let image = Arc::new(
+            Image::new_with_data(
+                self.alloc_access.clone(),
+                image_properties,
+                allocation_info_device_local(),
+                command_pool,
+                queue,
+                image_delta.pixels,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+            .map_err(UiRendererError::ResourceInit)?,
+        );
+        let image_view_properties =
+            ImageViewProperties::from_image_properties_default(image.properties());
+        let image_view = Arc::new(
+            ImageView::new(image, image_view_properties).map_err(UiRendererError::ImageView)?,
+        );
+
+        let sampler = match image_delta.filter {
+            vk::Filter::NEAREST => self.nearest_sampler.clone(),
+            _ => self.linear_sampler.clone(),
+        };
+
+        // free the replaced texture before allocating a new descriptor set so a full pool
+        // doesn't fail
+        self.free_texture(texture_id);
+
+        let descriptor_set = self
+            .descriptor_pool
+            .allocate_descriptor_set(self.descriptor_set_layout.clone())
+            .map_err(UiRendererError::Vulkan)?;
+        let image_info = [vk::DescriptorImageInfo {
+            sampler: sampler.handle(),
+            image_view: image_view.handle(),
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }];
+        let descriptor_write = vk::WriteDescriptorSet::default()
+            .dst_set(descriptor_set.handle())
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        self.pipeline
+            .device()
+            .update_descriptor_sets([descriptor_write], []);
+
+        let texture = Gpu::new(
+            self.deferred_drop.clone(),
+            UiTexture {
+                image_view,
+                descriptor_set,
+                _sampler: sampler,
+            },
+        );
+        self.textures.insert(texture_id, texture);
+        Ok(())
+    }
+
+    fn update_texture_region(
+        &mut self,
+        texture_id: UiTextureId,
+        pos: [u32; 2],
+        image_delta: UiImageDelta,
+        command_pool: &Arc<CommandPool>,
+        queue: &Queue,
+    ) -> Result<(), UiRendererError> {
+        let texture = self
+            .textures
+            .get(&texture_id)
+            .ok_or(UiRendererError::UnknownTexture(texture_id))?;
+        let image = texture.image_view.image();
+
+        let texture_dimensions = image.dimensions();
+        if pos[0] + image_delta.width > texture_dimensions.width()
+            || pos[1] + image_delta.height > texture_dimensions.height()
+        {
+            return Err(UiRendererError::RegionOutOfBounds {
+                pos,
+                width: image_delta.width,
+                height: image_delta.height,
+                texture_width: texture_dimensions.width(),
+                texture_height: texture_dimensions.height(),
+            });
+        }
+        if image_delta.pixels.is_empty() {
+            return Ok(());
+        }
+
+        let staging_buffer = new_staging_buffer(self.alloc_access.clone(), image_delta.pixels)
+            .map_err(UiRendererError::ResourceInit)?;
+
+        let subresource_range = image.properties().subresource_range();
+        let layout_transition = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::default()
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image.handle())
+                .subresource_range(subresource_range)
+        };
+        let copy_region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: default_subresource_layers(vk::ImageAspectFlags::COLOR),
+            image_offset: vk::Offset3D {
+                x: pos[0] as i32,
+                y: pos[1] as i32,
+                z: 0,
+            },
+            image_extent: vk::Extent3D {
+                width: image_delta.width,
+                height: image_delta.height,
+                depth: 1,
+            },
+        };
+
+        record_submit_and_wait(command_pool, queue, |command_buffer| {
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[layout_transition(
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::SHADER_READ,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )],
+            );
+            command_buffer.copy_buffer_to_image(
+                &staging_buffer,
+                image.as_ref(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy_region],
+            );
+            command_buffer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[layout_transition(
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        })
+        .map_err(UiRendererError::ResourceInit)
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn pipeline(&self) -> &GraphicsPipeline {
+        &self.pipeline
+    }
+
+    #[inline]
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    #[inline]
+    pub fn has_texture(&self, texture_id: UiTextureId) -> bool {
+        self.textures.contains_key(&texture_id)
+    }
+}
+
+// Helper Functions
+
+fn ui_vertex_input_state() -> VertexInputState {
+    VertexInputState {
+        vertex_binding_descriptions: vec![vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: mem::size_of::<UiVertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }],
+        vertex_attribute_descriptions: vec![
+            vk::VertexInputAttributeDescription {
+                location: 0,
+                binding: 0,
+                format: vk::Format::R32G32_SFLOAT,
+                offset: mem::offset_of!(UiVertex, pos) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 1,
+                binding: 0,
+                format: vk::Format::R32G32_SFLOAT,
+                offset: mem::offset_of!(UiVertex, uv) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                location: 2,
+                binding: 0,
+                format: vk::Format::R8G8B8A8_UNORM,
+                offset: mem::offset_of!(UiVertex, color) as u32,
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+/// Blending for premultiplied alpha colors.
+fn blend_state_premultiplied() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        blend_enable: 1,
+        color_blend_op: vk::BlendOp::ADD,
+        src_color_blend_factor: vk::BlendFactor::ONE,
+        dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        alpha_blend_op: vk::BlendOp::ADD,
+        src_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+        dst_alpha_blend_factor: vk::BlendFactor::ONE,
+        color_write_mask: vk::ColorComponentFlags::RGBA,
+    }
+}
+
+/// Writes `data` to the start of `buffer`, (re)creating it if it's too small. Buffers grow to the
+/// next power of two to avoid recreating them every frame.
+fn write_frame_buffer<'a>(
+    buffer: &'a mut Option<Buffer>,
+    alloc_access: &Arc<dyn AllocatorAccess>,
+    data: &[u8],
+    usage: vk::BufferUsageFlags,
+) -> Result<&'a Buffer, UiRendererError> {
+    let required_size = data.len() as vk::DeviceSize;
+    let too_small = buffer
+        .as_ref()
+        .map(|buffer| buffer.properties().size < required_size)
+        .unwrap_or(true);
+    if too_small {
+        let buffer_properties =
+            BufferProperties::new_default(required_size.next_power_of_two(), usage);
+        *buffer = Some(
+            Buffer::new(
+                alloc_access.clone(),
+                buffer_properties,
+                allocation_info_cpu_accessible(),
+            )
+            .map_err(UiRendererError::BufferCreation)?,
+        );
+    }
+
+    let buffer = buffer.as_mut().expect("buffer created above");
+    buffer
+        .memory_allocation_mut()
+        .write_bytes(data, 0)
+        .map_err(UiRendererError::Memory)?;
+    Ok(buffer)
+}
+
+/// Converts a clip rect in points to a scissor rect in pixels clamped to the screen. Returns
+/// `None` if the result is empty.
+pub fn ui_scissor_rect(
+    clip_rect: UiRect,
+    pixels_per_point: f32,
+    screen_size_pixels: [u32; 2],
+) -> Option<vk::Rect2D> {
+    let to_pixels = |points: f32, screen_size: u32| {
+        (points * pixels_per_point)
+            .round()
+            .clamp(0., screen_size as f32) as u32
+    };
+    let min_x = to_pixels(clip_rect.min[0], screen_size_pixels[0]);
+    let min_y = to_pixels(clip_rect.min[1], screen_size_pixels[1]);
+    let max_x = to_pixels(clip_rect.max[0], screen_size_pixels[0]);
+    let max_y = to_pixels(clip_rect.max[1], screen_size_pixels[1]);
+
+    if max_x <= min_x || max_y <= min_y {
+        return None;
+    }
+    Some(vk::Rect2D {
+        offset: vk::Offset2D {
+            x: min_x as i32,
+            y: min_y as i32,
+        },
+        extent: vk::Extent2D {
+            width: max_x - min_x,
+            height: max_y - min_y,
+        },
+    })
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum UiRendererError {
+    DescriptorSetLayout(DescriptorSetLayoutError),
+    Vulkan(vk::Result),
+    BufferCreation(AllocationError),
+    ImageView(ImageViewError),
+    Shader(ShaderError),
+    Memory(MemoryError),
+    ResourceInit(ResourceInitError),
+    UnknownTexture(UiTextureId),
+    PixelDataSizeMismatch {
+        expected_size: usize,
+        data_size: usize,
+    },
+    RegionOutOfBounds {
+        pos: [u32; 2],
+        width: u32,
+        height: u32,
+        texture_width: u32,
+        texture_height: u32,
+    },
+}
+
+impl fmt::Display for UiRendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vulkan(e) => write!(f, "ui renderer vulkan call failed: {}", e),
+            Self::BufferCreation(e) => write!(f, "failed to create ui renderer buffer: {}", e),
+            Self::ImageView(e) => write!(f, "failed to create ui texture view: {}", e),
+            Self::DescriptorSetLayout(e) => write!(
+                f,
+                "ui renderer descriptor set layout creation failed: {}",
+                e
+            ),
+            Self::Shader(e) => write!(f, "failed to create ui renderer shader: {}", e),
+            Self::Memory(e) => write!(f, "failed to write ui vertex data: {}", e),
+            Self::ResourceInit(e) => write!(f, "failed to upload ui texture: {}", e),
+            Self::UnknownTexture(texture_id) => {
+                write!(f, "ui texture {} doesn't exist", texture_id.0)
+            }
+            Self::PixelDataSizeMismatch {
+                expected_size,
+                data_size,
+            } => write!(
+                f,
+                "ui texture pixel data is {} bytes but {} bytes were expected",
+                data_size, expected_size
+            ),
+            Self::RegionOutOfBounds {
+                pos,
+                width,
+                height,
+                texture_width,
+                texture_height,
+            } => write!(
+                f,
+                "ui texture update region {}x{} at {:?} exceeds the texture size {}x{}",
+                width, height, pos, texture_width, texture_height
+            ),
+        }
+    }
+}
+
+impl error::Error for UiRendererError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            Self::BufferCreation(e) => Some(e),
+            Self::ImageView(e) => Some(e),
+            Self::DescriptorSetLayout(e) => Some(e),
+            Self::Shader(e) => Some(e),
+            Self::Memory(e) => Some(e),
+            Self::ResourceInit(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn ui_vertex_matches_egui_layout() {
+    assert_eq!(mem::size_of::<UiVertex>(), 20);
+    assert_eq!(mem::offset_of!(UiVertex, uv), 8);
+    assert_eq!(mem::offset_of!(UiVertex, color), 16);
+}
+
+#[test]
+fn ui_scissor_rect_scales_and_clamps() {
+    let clip_rect = UiRect {
+        min: [-10., 5.],
+        max: [50., 1000.],
+    };
+    let scissor = ui_scissor_rect(clip_rect, 2., [80, 600]).unwrap();
+    assert_eq!(scissor.offset, vk::Offset2D { x: 0, y: 10 });
+    assert_eq!(
+        scissor.extent,
+        vk::Extent2D {
+            width: 80,
+            height: 590
+        }
+    );
+
+    let offscreen = UiRect {
+        min: [100., 0.],
+        max: [200., 10.],
+    };
+    assert!(ui_scissor_rect(offscreen, 1., [80, 600]).is_none());
+}
+
+#[test]
+fn ui_shaders_have_entry_points() {
+    for (spirv_bytes, stage) in [
+        (UI_VERT_SPIRV, vk::ShaderStageFlags::VERTEX),
+        (UI_FRAG_SPIRV, vk::ShaderStageFlags::FRAGMENT),
+    ] {
+        let spirv = ash::util::read_spv(&mut Cursor::new(spirv_bytes)).unwrap();
+        let entry_points = crate::spirv_entry_points(&spirv).unwrap();
+        assert_eq!(entry_points.len(), 1);
+        assert_eq!(entry_points[0].stage, stage);
+    }
+}
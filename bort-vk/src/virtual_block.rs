@@ -0,0 +1,291 @@
+use ash::vk;
+use bort_vma::{ffi, Statistics};
+use std::{
+    error, fmt, mem, ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A VMA virtual block: VMA's allocation algorithm without any vulkan memory behind it, for
+/// suballocating ranges of your own resources e.g. a large buffer (see [`GpuHeap`](crate::GpuHeap)).
+/// Sizes and offsets are in whatever unit you're consistent with (bytes, elements etc.).
+///
+/// Not thread safe, hence `&mut self` for allocating and freeing.
+///
+/// Allocations are tagged with the block they came from and the number of times it had been
+/// cleared, so [`Self::free`] rejects allocations from another block or from before a
+/// [`Self::clear`] instead of passing them on to VMA.
+pub struct VirtualBlock {
+    handle: ffi::VmaVirtualBlock,
+    size: vk::DeviceSize,
+    id: u64,
+    /// Incremented by [`Self::clear`].
+    generation: u64,
+}
+
+static NEXT_VIRTUAL_BLOCK_ID: AtomicU64 = AtomicU64::new(1);
+
+impl VirtualBlock {
+    /// `linear` selects the linear allocation algorithm which is faster but doesn't reuse freed
+    /// space until everything after it is freed too (ring buffer/stack behaviour). `size` must
+    /// be nonzero.
+    pub fn new(size: vk::DeviceSize, linear: bool) -> Result<Self, VirtualBlockError> {
+        if size == 0 {
+            return Err(VirtualBlockError::ZeroSize);
+        }
+        let flags = if linear {
+            ffi::VmaVirtualBlockCreateFlagBits::VMA_VIRTUAL_BLOCK_CREATE_LINEAR_ALGORITHM_BIT as u32
+        } else {
+            0
+        };
+        let create_info = ffi::VmaVirtualBlockCreateInfo {
+            size,
+            flags,
+            pAllocationCallbacks: ptr::null(),
+        };
+
+        let handle = unsafe {
+            let mut handle: ffi::VmaVirtualBlock = mem::zeroed();
+            ffi::vmaCreateVirtualBlock(&create_info, &mut handle)
+                .result()
+                .map_err(VirtualBlockError::Vulkan)?;
+            handle
+        };
+        Ok(Self {
+            handle,
+            size,
+            id: NEXT_VIRTUAL_BLOCK_ID.fetch_add(1, Ordering::Relaxed),
+            generation: 0,
+        })
+    }
+
+    /// Returns [`VirtualBlockError::Vulkan`] with `ERROR_OUT_OF_DEVICE_MEMORY` if there isn't a
+    /// large enough free range. `size` must be nonzero and `alignment` must be a power of two (or
+    /// 0 for none).
+    pub fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Result<VirtualAllocation, VirtualBlockError> {
+        if size == 0 {
+            return Err(VirtualBlockError::ZeroSize);
+        }
+        if alignment != 0 && !alignment.is_power_of_two() {
+            return Err(VirtualBlockError::InvalidAlignment(alignment));
+        }
+
+        let create_info = ffi::VmaVirtualAllocationCreateInfo {
+            size,
+            alignment,
+            flags: 0,
+            pUserData: ptr::null_mut(),
+        };
+
+        let mut handle: ffi::VmaVirtualAllocation = ptr::null_mut();
+        let mut offset: vk::DeviceSize = 0;
+        unsafe {
+            ffi::vmaVirtualAllocate(self.handle, &create_info, &mut handle, &mut offset)
+                .result()
+                .map_err(VirtualBlockError::Vulkan)?;
+        }
+        Ok(VirtualAllocation {
+            handle,
+            offset,
+            size,
+            block_id: self.id,
+            generation: self.generation,
+        })
+    }
+
+    /// Returns an error without freeing anything if `allocation` wasn't allocated from this block
+    /// or was already freed by [`Self::clear`].
+    pub fn free(&mut self, allocation: VirtualAllocation) -> Result<(), VirtualBlockError> {
+        if allocation.block_id != self.id {
+            return Err(VirtualBlockError::ForeignAllocation);
+        }
+        if allocation.generation != self.generation {
+            return Err(VirtualBlockError::ClearedAllocation);
+        }
+        unsafe { ffi::vmaVirtualFree(self.handle, allocation.handle) };
+        Ok(())
+    }
+
+    /// Frees all allocations at once. Outstanding [`VirtualAllocation`]s are rejected by
+    /// [`Self::free`] afterwards.
+    pub fn clear(&mut self) {
+        unsafe { ffi::vmaClearVirtualBlock(self.handle) };
+        self.generation += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        unsafe { ffi::vmaIsVirtualBlockEmpty(self.handle) == vk::TRUE }
+    }
+
+    /// Allocation count and allocated size (`allocation_bytes`, in the block's units).
+    pub fn statistics(&self) -> Statistics {
+        unsafe {
+            let mut statistics: ffi::VmaStatistics = mem::zeroed();
+            ffi::vmaGetVirtualBlockStatistics(self.handle, &mut statistics);
+            statistics.into()
+        }
+    }
+
+    // Getters
+
+    #[inline]
+    pub fn handle(&self) -> ffi::VmaVirtualBlock {
+        self.handle
+    }
+
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+}
+
+/// Outstanding allocations are freed when the block is dropped.
+impl Drop for VirtualBlock {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::vmaClearVirtualBlock(self.handle);
+            ffi::vmaDestroyVirtualBlock(self.handle);
+        }
+    }
+}
+
+unsafe impl Send for VirtualBlock {}
+unsafe impl Sync for VirtualBlock {}
+
+/// A range allocated from a [`VirtualBlock`]. Not `Clone` so that it can only be freed once.
+#[derive(Debug)]
+pub struct VirtualAllocation {
+    handle: ffi::VmaVirtualAllocation,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    block_id: u64,
+    generation: u64,
+}
+
+impl VirtualAllocation {
+    #[inline]
+    pub fn handle(&self) -> ffi::VmaVirtualAllocation {
+        self.handle
+    }
+
+    #[inline]
+    pub fn offset(&self) -> vk::DeviceSize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+}
+
+unsafe impl Send for VirtualAllocation {}
+unsafe impl Sync for VirtualAllocation {}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualBlockError {
+    /// Zero block size or allocation size.
+    ZeroSize,
+    InvalidAlignment(vk::DeviceSize),
+    /// The allocation passed to [`VirtualBlock::free`] belongs to a different block.
+    ForeignAllocation,
+    /// The allocation passed to [`VirtualBlock::free`] was already freed by
+    /// [`VirtualBlock::clear`].
+    ClearedAllocation,
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for VirtualBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroSize => write!(f, "virtual block and allocation sizes can't be zero"),
+            Self::InvalidAlignment(alignment) => write!(
+                f,
+                "virtual allocation alignment {} isn't a power of two",
+                alignment
+            ),
+            Self::ForeignAllocation => write!(
+                f,
+                "attempted to free a virtual allocation from a different virtual block"
+            ),
+            Self::ClearedAllocation => write!(
+                f,
+                "attempted to free a virtual allocation which was already freed by clearing its block"
+            ),
+            Self::Vulkan(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for VirtualBlockError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Vulkan(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn virtual_block_allocate_and_free() {
+    let mut block = VirtualBlock::new(16, false).unwrap();
+    let first = block.allocate(8, 4).unwrap();
+    let second = block.allocate(8, 0).unwrap();
+    assert_ne!(first.offset(), second.offset());
+    assert_eq!(block.statistics().allocation_bytes, 16);
+    assert_eq!(
+        block.allocate(1, 0).unwrap_err(),
+        VirtualBlockError::Vulkan(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)
+    );
+
+    block.free(first).unwrap();
+    let third = block.allocate(4, 0).unwrap();
+    assert!(third.offset() < 8);
+    block.free(second).unwrap();
+    block.free(third).unwrap();
+    assert!(block.is_empty());
+}
+
+#[test]
+fn virtual_block_rejects_invalid_frees() {
+    assert_eq!(
+        VirtualBlock::new(0, false).err(),
+        Some(VirtualBlockError::ZeroSize)
+    );
+
+    let mut block = VirtualBlock::new(16, false).unwrap();
+    let mut other_block = VirtualBlock::new(16, false).unwrap();
+    assert_eq!(
+        block.allocate(0, 0).err(),
+        Some(VirtualBlockError::ZeroSize)
+    );
+    assert_eq!(
+        block.allocate(4, 3).err(),
+        Some(VirtualBlockError::InvalidAlignment(3))
+    );
+
+    let foreign = other_block.allocate(4, 0).unwrap();
+    assert_eq!(
+        block.free(foreign),
+        Err(VirtualBlockError::ForeignAllocation)
+    );
+    assert_eq!(other_block.statistics().allocation_bytes, 4);
+
+    let cleared = block.allocate(4, 0).unwrap();
+    block.clear();
+    let reused = block.allocate(4, 0).unwrap();
+    assert_eq!(
+        block.free(cleared),
+        Err(VirtualBlockError::ClearedAllocation)
+    );
+    assert_eq!(block.statistics().allocation_bytes, 4);
+    block.free(reused).unwrap();
+    assert!(block.is_empty());
+}
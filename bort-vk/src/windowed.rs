@@ -0,0 +1,720 @@
+//! Sensible-defaults setup for rendering to a window: creates the entry, instance, (optional)
+//! validation debug callback, surface, physical device, device, graphics/present queue and
+//! swapchain in one call. Use [`init`] with a [`WindowedInitConfig`] to override layers,
+//! extensions, device features and swapchain preferences.
+
+use crate::{
+    choose_composite_alpha, get_first_linear_surface_format, get_first_srgb_surface_format,
+    present_queue_family_index, ApiVersion, DebugCallback, DebugCallbackProperties, Device,
+    DeviceError, Instance, InstanceDebugMessenger, InstanceError, PhysicalDevice,
+    PhysicalDeviceError, PhysicalDeviceFeatures, PresentQueueSharing, Queue, QueueError, Surface,
+    SurfaceCreationError, Swapchain, SwapchainError, SwapchainProperties,
+};
+use ash::{
+    vk::{self, EXT_DEBUG_UTILS_NAME, KHR_SWAPCHAIN_NAME},
+    Entry,
+};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+#[cfg(feature = "raw-window-handle-05")]
+use raw_window_handle_05::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+#[cfg(feature = "raw-window-handle-06")]
+use raw_window_handle_06::{
+    HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+use std::{
+    error,
+    ffi::{CStr, CString},
+    fmt, mem,
+    sync::Arc,
+};
+
+pub use crate::{log_vulkan_debug_callback, VALIDATION_LAYER_NAME};
+
+/// Everything created by [`init`]. Additional windows can share the same instance, device and
+/// queues via [`WindowedContext::add_window`].
+pub struct WindowedContext {
+    pub entry: Arc<Entry>,
+    pub instance: Arc<Instance>,
+    /// `Some` if validation was requested and the validation layer and `VK_EXT_debug_utils` are
+    /// available.
+    pub debug_callback: Option<Arc<DebugCallback>>,
+    pub surface: Arc<Surface>,
+    pub physical_device: Arc<PhysicalDevice>,
+    pub device: Arc<Device>,
+    /// Supports graphics operations, and presentation to `surface` if no separate present queue
+    /// family was needed.
+    pub queue: Arc<Queue>,
+    /// Supports presentation to `surface`. Same as `queue` unless the physical device has no
+    /// queue family supporting both graphics and presentation.
+    pub present_queue: Arc<Queue>,
+    /// How swapchain images are shared between `queue` and `present_queue`. The swapchain is
+    /// created with concurrent sharing when the queue families differ.
+    pub present_queue_sharing: PresentQueueSharing,
+    pub swapchain: Arc<Swapchain>,
+}
+
+/// Surface and swapchain for an additional window created with [`WindowedContext::add_window`].
+/// Owned independently of the context's own surface and swapchain so each window can be
+/// resized, recreated or dropped on its own. Presents on the context's `present_queue`.
+pub struct WindowSurface {
+    pub surface: Arc<Surface>,
+    pub swapchain: Arc<Swapchain>,
+}
+
+#[derive(Clone)]
+pub struct WindowedInitConfig {
+    /// If `None`, vulkan is loaded with `Entry::load` (`loaded` feature) or `Entry::linked`
+    /// (`linked` feature). Provide your own entry on platforms that need a custom loader e.g.
+    /// `ash_molten` on macOS.
+    pub entry: Option<Arc<Entry>>,
+    pub max_api_version: ApiVersion,
+
+    /// Enables `VK_LAYER_KHRONOS_validation` and a debug callback if they are available.
+    /// Silently skipped otherwise.
+    pub enable_validation: bool,
+    pub debug_callback: vk::PFN_vkDebugUtilsMessengerCallbackEXT,
+    pub debug_callback_properties: DebugCallbackProperties,
+
+    /// Additional instance layers. The validation layer is added if `enable_validation` is set.
+    pub instance_layers: Vec<CString>,
+    /// Additional instance extensions. Surface extensions for the window system are always
+    /// added.
+    pub instance_extensions: Vec<CString>,
+    /// Additional device extensions. `VK_KHR_swapchain` is always added.
+    pub device_extensions: Vec<CString>,
+    pub device_features: PhysicalDeviceFeatures<'static>,
+    /// Physical devices of this type are chosen over others. Otherwise discrete GPUs are preferred
+    /// over integrated, virtual and CPU devices.
+    pub preferred_device_type: vk::PhysicalDeviceType,
+
+    /// `None` requests one more than the surface minimum.
+    pub preferred_image_count: Option<u32>,
+    /// Used if the surface supports it. Otherwise see `prefer_srgb`.
+    pub preferred_surface_format: Option<vk::SurfaceFormatKHR>,
+    /// Whether to prefer an sRGB over a linear swapchain format.
+    pub prefer_srgb: bool,
+    pub image_usage: vk::ImageUsageFlags,
+}
+
+impl Default for WindowedInitConfig {
+    fn default() -> Self {
+        Self {
+            entry: None,
+            max_api_version: ApiVersion::V1_3,
+
+            enable_validation: cfg!(debug_assertions),
+            debug_callback: Some(log_vulkan_debug_callback),
+            debug_callback_properties: DebugCallbackProperties::default(),
+
+            instance_layers: Vec::new(),
+            instance_extensions: Vec::new(),
+            device_extensions: Vec::new(),
+            device_features: PhysicalDeviceFeatures::default(),
+            preferred_device_type: vk::PhysicalDeviceType::DISCRETE_GPU,
+
+            preferred_image_count: None,
+            preferred_surface_format: None,
+            prefer_srgb: true,
+            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        }
+    }
+}
+
+/// Creates everything needed to start rendering to `window`. `window_dimensions` is only used
+/// if the surface doesn't dictate the swapchain extent (e.g. on Wayland).
+#[cfg(feature = "raw-window-handle-06")]
+pub fn init<W>(
+    window: &W,
+    window_dimensions: [u32; 2],
+    config: WindowedInitConfig,
+) -> Result<WindowedContext, WindowedInitError>
+where
+    W: HasDisplayHandle + HasWindowHandle + ?Sized,
+{
+    let display_handle = window
+        .display_handle()
+        .map_err(WindowedInitError::WindowHandle)?
+        .as_raw();
+    let window_handle = window
+        .window_handle()
+        .map_err(WindowedInitError::WindowHandle)?
+        .as_raw();
+    init_from_raw_handles(display_handle, window_handle, window_dimensions, config)
+}
+
+/// Creates everything needed to start rendering to `window`. `window_dimensions` is only used
+/// if the surface doesn't dictate the swapchain extent (e.g. on Wayland).
+#[cfg(feature = "raw-window-handle-05")]
+pub fn init<W>(
+    window: &W,
+    window_dimensions: [u32; 2],
+    config: WindowedInitConfig,
+) -> Result<WindowedContext, WindowedInitError>
+where
+    W: HasRawDisplayHandle + HasRawWindowHandle + ?Sized,
+{
+    init_from_raw_handles(
+        window.raw_display_handle(),
+        window.raw_window_handle(),
+        window_dimensions,
+        config,
+    )
+}
+
+/// Same as [`init`] but takes the raw display and window handles directly.
+pub fn init_from_raw_handles(
+    display_handle: RawDisplayHandle,
+    window_handle: RawWindowHandle,
+    window_dimensions: [u32; 2],
+    mut config: WindowedInitConfig,
+) -> Result<WindowedContext, WindowedInitError> {
+    let entry = match config.entry.clone() {
+        Some(entry) => entry,
+        None => Arc::new(load_entry()?),
+    };
+
+    let mut instance_layers = mem::take(&mut config.instance_layers);
+    let mut instance_extensions = mem::take(&mut config.instance_extensions);
+
+    let mut enable_validation = config.enable_validation;
+    if enable_validation {
+        let validation_layer_installed =
+            Instance::layer_avilable(&entry, VALIDATION_LAYER_NAME.to_owned())
+                .map_err(WindowedInitError::Vulkan)?;
+        let debug_utils_supported =
+            Instance::supports_extension(&entry, None, EXT_DEBUG_UTILS_NAME.to_owned())
+                .map_err(WindowedInitError::Vulkan)?;
+
+        if validation_layer_installed && debug_utils_supported {
+            push_unique(&mut instance_layers, VALIDATION_LAYER_NAME);
+            push_unique(&mut instance_extensions, EXT_DEBUG_UTILS_NAME);
+            info!("vulkan validation layers enabled");
+        } else {
+            enable_validation = false;
+            warn!("vulkan validation layers requested but not available");
+        }
+    }
+
+    let instance_extensions =
+        Instance::with_display_extensions(&entry, display_handle, instance_extensions)
+            .map_err(WindowedInitError::Instance)?;
+    let instance = if enable_validation {
+        // also catch messages during instance creation and destruction
+        let debug_messenger = InstanceDebugMessenger {
+            debug_callback: config.debug_callback,
+            properties: config.debug_callback_properties,
+        };
+        Instance::new_with_debug_messenger(
+            entry.clone(),
+            config.max_api_version,
+            instance_layers,
+            instance_extensions,
+            debug_messenger,
+        )
+    } else {
+        Instance::new(
+            entry.clone(),
+            config.max_api_version,
+            instance_layers,
+            instance_extensions,
+        )
+    };
+    let instance = Arc::new(instance.map_err(WindowedInitError::Instance)?);
+
+    let debug_callback = if enable_validation {
+        let debug_callback = DebugCallback::new(
+            instance.clone(),
+            config.debug_callback,
+            config.debug_callback_properties,
+        )
+        .map_err(WindowedInitError::Vulkan)?;
+        Some(Arc::new(debug_callback))
+    } else {
+        None
+    };
+
+    let surface = Arc::new(
+        Surface::new(&entry, instance.clone(), display_handle, window_handle)
+            .map_err(WindowedInitError::Surface)?,
+    );
+
+    let mut device_extensions = mem::take(&mut config.device_extensions);
+    push_unique(&mut device_extensions, KHR_SWAPCHAIN_NAME);
+
+    let (physical_device, queue_family_index, present_family_index) = choose_physical_device(
+        &instance,
+        &surface,
+        &device_extensions,
+        config.preferred_device_type,
+    )?;
+    let physical_device = Arc::new(physical_device);
+    info!("chosen physical device: {}", physical_device.name());
+
+    let queue_priorities = [1.0];
+    let mut queue_create_infos = vec![vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(queue_family_index)
+        .queue_priorities(&queue_priorities)];
+    if present_family_index != queue_family_index {
+        info!(
+            "using separate graphics ({}) and present ({}) queue families",
+            queue_family_index, present_family_index
+        );
+        queue_create_infos.push(
+            vk::DeviceQueueCreateInfo::default()
+                .queue_family_index(present_family_index)
+                .queue_priorities(&queue_priorities),
+        );
+    }
+
+    let device = Arc::new(
+        Device::new(
+            physical_device.clone(),
+            queue_create_infos,
+            config.device_features,
+            device_extensions,
+            vec![],
+            debug_callback.clone(),
+        )
+        .map_err(WindowedInitError::Device)?,
+    );
+
+    let queue = Arc::new(
+        Queue::new(device.clone(), queue_family_index, 0).map_err(WindowedInitError::Queue)?,
+    );
+    let present_queue = if present_family_index == queue_family_index {
+        queue.clone()
+    } else {
+        Arc::new(
+            Queue::new(device.clone(), present_family_index, 0)
+                .map_err(WindowedInitError::Queue)?,
+        )
+    };
+    let present_queue_sharing = PresentQueueSharing::new(&queue, &present_queue);
+
+    let swapchain = create_swapchain(
+        &device,
+        &surface,
+        present_queue_sharing,
+        window_dimensions,
+        &config,
+    )?;
+
+    Ok(WindowedContext {
+        entry,
+        instance,
+        debug_callback,
+        surface,
+        physical_device,
+        device,
+        queue,
+        present_queue,
+        present_queue_sharing,
+        swapchain,
+    })
+}
+
+impl WindowedContext {
+    /// Creates a surface and swapchain for another window using the same instance, device and
+    /// queues. Only the swapchain preferences of `config` (image count, surface format, sRGB and
+    /// image usage) are used. `window_dimensions` is only used if the surface doesn't dictate the
+    /// swapchain extent.
+    #[cfg(feature = "raw-window-handle-06")]
+    pub fn add_window<W>(
+        &self,
+        window: &W,
+        window_dimensions: [u32; 2],
+        config: &WindowedInitConfig,
+    ) -> Result<WindowSurface, WindowedInitError>
+    where
+        W: HasDisplayHandle + HasWindowHandle + ?Sized,
+    {
+        let display_handle = window
+            .display_handle()
+            .map_err(WindowedInitError::WindowHandle)?
+            .as_raw();
+        let window_handle = window
+            .window_handle()
+            .map_err(WindowedInitError::WindowHandle)?
+            .as_raw();
+        self.add_window_from_raw_handles(display_handle, window_handle, window_dimensions, config)
+    }
+
+    /// Creates a surface and swapchain for another window using the same instance, device and
+    /// queues. Only the swapchain preferences of `config` (image count, surface format, sRGB and
+    /// image usage) are used. `window_dimensions` is only used if the surface doesn't dictate the
+    /// swapchain extent.
+    #[cfg(feature = "raw-window-handle-05")]
+    pub fn add_window<W>(
+        &self,
+        window: &W,
+        window_dimensions: [u32; 2],
+        config: &WindowedInitConfig,
+    ) -> Result<WindowSurface, WindowedInitError>
+    where
+        W: HasRawDisplayHandle + HasRawWindowHandle + ?Sized,
+    {
+        self.add_window_from_raw_handles(
+            window.raw_display_handle(),
+            window.raw_window_handle(),
+            window_dimensions,
+            config,
+        )
+    }
+
+    /// Same as [`Self::add_window`] but takes the raw display and window handles directly.
+    ///
+    /// Returns [`WindowedInitError::PresentNotSupported`] if `present_queue` can't present to the
+    /// new surface (e.g. the window is on a display driven by a different GPU).
+    pub fn add_window_from_raw_handles(
+        &self,
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        window_dimensions: [u32; 2],
+        config: &WindowedInitConfig,
+    ) -> Result<WindowSurface, WindowedInitError> {
+        let surface = Arc::new(
+            Surface::new(
+                &self.entry,
+                self.instance.clone(),
+                display_handle,
+                window_handle,
+            )
+            .map_err(WindowedInitError::Surface)?,
+        );
+
+        let present_supported = surface
+            .get_physical_device_surface_support(
+                &self.physical_device,
+                self.present_queue.family_index(),
+            )
+            .map_err(WindowedInitError::Vulkan)?;
+        if !present_supported {
+            return Err(WindowedInitError::PresentNotSupported {
+                present_family_index: self.present_queue.family_index(),
+            });
+        }
+
+        let swapchain = create_swapchain(
+            &self.device,
+            &surface,
+            self.present_queue_sharing,
+            window_dimensions,
+            config,
+        )?;
+
+        Ok(WindowSurface { surface, swapchain })
+    }
+}
+
+// Helper Functions
+
+#[cfg(feature = "loaded")]
+fn load_entry() -> Result<Entry, WindowedInitError> {
+    unsafe { Entry::load() }.map_err(WindowedInitError::EntryLoading)
+}
+
+#[cfg(all(feature = "linked", not(feature = "loaded")))]
+fn load_entry() -> Result<Entry, WindowedInitError> {
+    Ok(Entry::linked())
+}
+
+#[cfg(not(any(feature = "linked", feature = "loaded")))]
+fn load_entry() -> Result<Entry, WindowedInitError> {
+    Err(WindowedInitError::NoEntry)
+}
+
+fn push_unique(names: &mut Vec<CString>, name: &CStr) {
+    if !names.iter().any(|existing| existing.as_c_str() == name) {
+        names.push(name.to_owned());
+    }
+}
+
+/// Returns the best physical device supporting `device_extensions` with queue families that
+/// support graphics and presentation to `surface`, along with the graphics and present queue
+/// family indices. A single family supporting both is preferred.
+fn choose_physical_device(
+    instance: &Arc<Instance>,
+    surface: &Surface,
+    device_extensions: &[CString],
+    preferred_device_type: vk::PhysicalDeviceType,
+) -> Result<(PhysicalDevice, u32, u32), WindowedInitError> {
+    let physical_device_handles = instance
+        .enumerate_physical_devices()
+        .map_err(WindowedInitError::Vulkan)?;
+
+    let mut best: Option<(u32, PhysicalDevice, u32, u32)> = None;
+    for handle in physical_device_handles {
+        let physical_device = PhysicalDevice::new(instance.clone(), handle)
+            .map_err(WindowedInitError::PhysicalDevice)?;
+
+        if !physical_device
+            .any_unsupported_extensions(device_extensions.to_vec())
+            .is_empty()
+        {
+            continue;
+        }
+
+        let graphics_family_indices: Vec<u32> = physical_device
+            .queue_family_properties()
+            .iter()
+            .enumerate()
+            .filter(|(_, queue_family_properties)| {
+                queue_family_properties
+                    .queue_flags
+                    .contains(vk::QueueFlags::GRAPHICS)
+            })
+            .map(|(queue_family_index, _)| queue_family_index as u32)
+            .collect();
+        let combined_family_index = graphics_family_indices.iter().copied().find(|&index| {
+            surface
+                .get_physical_device_surface_support(&physical_device, index)
+                .unwrap_or(false)
+        });
+        let queue_family_indices = match combined_family_index {
+            Some(index) => Some((index, index)),
+            None => graphics_family_indices.first().and_then(|&graphics_index| {
+                present_queue_family_index(&physical_device, surface, graphics_index)
+                    .map(|present_index| (graphics_index, present_index))
+            }),
+        };
+        let Some((queue_family_index, present_family_index)) = queue_family_indices else {
+            continue;
+        };
+
+        let rank = physical_device_type_rank(
+            physical_device.properties().device_type,
+            preferred_device_type,
+        );
+        if best
+            .as_ref()
+            .is_none_or(|(best_rank, ..)| rank < *best_rank)
+        {
+            best = Some((
+                rank,
+                physical_device,
+                queue_family_index,
+                present_family_index,
+            ));
+        }
+    }
+
+    best.map(
+        |(_, physical_device, queue_family_index, present_family_index)| {
+            (physical_device, queue_family_index, present_family_index)
+        },
+    )
+    .ok_or(WindowedInitError::NoSuitablePhysicalDevice)
+}
+
+/// Lower is better.
+fn physical_device_type_rank(
+    device_type: vk::PhysicalDeviceType,
+    preferred_device_type: vk::PhysicalDeviceType,
+) -> u32 {
+    if device_type == preferred_device_type {
+        return 0;
+    }
+    match device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 1,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 2,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 3,
+        vk::PhysicalDeviceType::CPU => 4,
+        _ => 5,
+    }
+}
+
+fn create_swapchain(
+    device: &Arc<Device>,
+    surface: &Arc<Surface>,
+    present_queue_sharing: PresentQueueSharing,
+    window_dimensions: [u32; 2],
+    config: &WindowedInitConfig,
+) -> Result<Arc<Swapchain>, WindowedInitError> {
+    let physical_device = device.physical_device();
+    let surface_capabilities = surface
+        .get_physical_device_surface_capabilities(physical_device)
+        .map_err(WindowedInitError::Vulkan)?;
+    let surface_formats = surface
+        .get_physical_device_surface_formats(physical_device)
+        .map_err(WindowedInitError::Vulkan)?;
+    let surface_format = choose_surface_format(
+        &surface_formats,
+        config.preferred_surface_format,
+        config.prefer_srgb,
+    )
+    .ok_or(WindowedInitError::NoSurfaceFormat)?;
+
+    let preferred_image_count = config
+        .preferred_image_count
+        .unwrap_or(surface_capabilities.min_image_count + 1);
+
+    let mut swapchain_properties = SwapchainProperties::new_default(
+        device,
+        surface,
+        preferred_image_count,
+        surface_format,
+        choose_composite_alpha(surface_capabilities),
+        config.image_usage,
+        window_dimensions,
+    )
+    .map_err(WindowedInitError::Swapchain)?;
+    present_queue_sharing.apply(&mut swapchain_properties);
+    let swapchain = Swapchain::new(device.clone(), surface.clone(), swapchain_properties)
+        .map_err(WindowedInitError::Swapchain)?;
+    Ok(Arc::new(swapchain))
+}
+
+fn choose_surface_format(
+    surface_formats: &[vk::SurfaceFormatKHR],
+    preferred_surface_format: Option<vk::SurfaceFormatKHR>,
+    prefer_srgb: bool,
+) -> Option<vk::SurfaceFormatKHR> {
+    if let Some(preferred_surface_format) = preferred_surface_format {
+        if surface_formats.contains(&preferred_surface_format) {
+            return Some(preferred_surface_format);
+        }
+    }
+
+    let preferred_format = if prefer_srgb {
+        get_first_srgb_surface_format(surface_formats)
+    } else {
+        get_first_linear_surface_format(surface_formats)
+    };
+    preferred_format.or_else(|| surface_formats.first().copied())
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug)]
+pub enum WindowedInitError {
+    #[cfg(feature = "loaded")]
+    EntryLoading(ash::LoadingError),
+    /// Neither the `loaded` nor `linked` feature is enabled and no entry was provided.
+    NoEntry,
+    #[cfg(feature = "raw-window-handle-06")]
+    WindowHandle(HandleError),
+    Instance(InstanceError),
+    Surface(SurfaceCreationError),
+    PhysicalDevice(PhysicalDeviceError),
+    NoSuitablePhysicalDevice,
+    Device(DeviceError),
+    Queue(QueueError),
+    NoSurfaceFormat,
+    /// The present queue of the [`WindowedContext`] can't present to an additional window's
+    /// surface.
+    PresentNotSupported {
+        present_family_index: u32,
+    },
+    Swapchain(SwapchainError),
+    Vulkan(vk::Result),
+}
+
+impl fmt::Display for WindowedInitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "loaded")]
+            Self::EntryLoading(e) => write!(f, "failed to load vulkan: {}", e),
+            Self::NoEntry => write!(
+                f,
+                "no vulkan entry provided and neither the `loaded` nor `linked` feature is enabled"
+            ),
+            #[cfg(feature = "raw-window-handle-06")]
+            Self::WindowHandle(e) => write!(f, "failed to get window handles: {}", e),
+            Self::Instance(e) => write!(f, "failed to create instance: {}", e),
+            Self::Surface(e) => write!(f, "failed to create surface: {}", e),
+            Self::PhysicalDevice(e) => write!(f, "failed to query physical device: {}", e),
+            Self::NoSuitablePhysicalDevice => write!(
+                f,
+                "no physical device supports the required extensions and has a queue family \
+                supporting graphics and presentation to the surface"
+            ),
+            Self::Device(e) => write!(f, "failed to create device: {}", e),
+            Self::Queue(e) => write!(f, "failed to get device queue: {}", e),
+            Self::NoSurfaceFormat => write!(f, "the surface reported no supported formats"),
+            Self::PresentNotSupported {
+                present_family_index,
+            } => write!(
+                f,
+                "present queue family {} doesn't support presenting to the new window's surface",
+                present_family_index
+            ),
+            Self::Swapchain(e) => write!(f, "failed to create swapchain: {}", e),
+            Self::Vulkan(e) => write!(f, "vulkan call failed during windowed init: {}", e),
+        }
+    }
+}
+
+impl error::Error for WindowedInitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "loaded")]
+            Self::EntryLoading(e) => Some(e),
+            #[cfg(feature = "raw-window-handle-06")]
+            Self::WindowHandle(e) => Some(e),
+            Self::Instance(e) => Some(e),
+            Self::Surface(e) => Some(e),
+            Self::PhysicalDevice(e) => Some(e),
+            Self::Device(e) => Some(e),
+            Self::Queue(e) => Some(e),
+            Self::Swapchain(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+            Self::NoEntry
+            | Self::NoSuitablePhysicalDevice
+            | Self::NoSurfaceFormat
+            | Self::PresentNotSupported { .. } => None,
+        }
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn physical_device_type_rank_prefers_requested_then_discrete() {
+    let discrete = vk::PhysicalDeviceType::DISCRETE_GPU;
+    let integrated = vk::PhysicalDeviceType::INTEGRATED_GPU;
+    let cpu = vk::PhysicalDeviceType::CPU;
+
+    assert!(
+        physical_device_type_rank(discrete, discrete)
+            < physical_device_type_rank(integrated, discrete)
+    );
+    assert!(
+        physical_device_type_rank(integrated, integrated)
+            < physical_device_type_rank(discrete, integrated)
+    );
+    assert!(
+        physical_device_type_rank(integrated, discrete) < physical_device_type_rank(cpu, discrete)
+    );
+}
+
+#[test]
+fn surface_format_prefers_requested_then_srgb() {
+    let surface_format = |format| vk::SurfaceFormatKHR {
+        format,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    };
+    let unorm = surface_format(vk::Format::B8G8R8A8_UNORM);
+    let srgb = surface_format(vk::Format::B8G8R8A8_SRGB);
+    let rgba_unorm = surface_format(vk::Format::R8G8B8A8_UNORM);
+
+    assert_eq!(
+        choose_surface_format(&[unorm, srgb], None, true),
+        Some(srgb)
+    );
+    assert_eq!(
+        choose_surface_format(&[srgb, unorm], None, false),
+        Some(unorm)
+    );
+    assert_eq!(
+        choose_surface_format(&[unorm, srgb, rgba_unorm], Some(rgba_unorm), true),
+        Some(rgba_unorm)
+    );
+    assert_eq!(
+        choose_surface_format(&[unorm, srgb], Some(rgba_unorm), true),
+        Some(srgb)
+    );
+    assert_eq!(choose_surface_format(&[], None, true), None);
+}
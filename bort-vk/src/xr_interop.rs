@@ -0,0 +1,195 @@
+//! Interop with OpenXR's `XR_KHR_vulkan_enable2` extension where the OpenXR runtime creates the
+//! Vulkan instance and device (`xrCreateVulkanInstanceKHR`, `xrCreateVulkanDeviceKHR`) and
+//! the swapchain images (`xrEnumerateSwapchainImages`).
+//!
+//! This doesn't depend on an OpenXR crate: OpenXR hands out the raw Vulkan handles which can be
+//! converted with e.g. `vk::Instance::from_raw(xr_vk_instance as u64)`.
+
+use crate::{
+    default_subresource_range, ApiVersion, BorrowedImage, Device, HandleOwnership, ImageDimensions,
+    ImageView, ImageViewProperties, Instance, PhysicalDevice, PhysicalDeviceError,
+    PhysicalDeviceFeatures, Queue,
+};
+use ash::{
+    prelude::VkResult,
+    vk::{self, Handle},
+    Entry,
+};
+use std::{ffi::CString, sync::Arc};
+
+/// The Vulkan handles OpenXR created for the application, plus the extensions, layers and
+/// features that were requested in the create infos passed to OpenXR. These lists aren't
+/// queryable from the handles so they have to be supplied to keep bort's bookkeeping (e.g.
+/// [`Device::enabled_extensions`]) accurate.
+pub struct XrVulkanHandles {
+    /// From `xrCreateVulkanInstanceKHR`.
+    pub instance: vk::Instance,
+    /// `VkApplicationInfo::apiVersion` of the instance create info.
+    pub instance_api_version: ApiVersion,
+    pub instance_extensions: Vec<CString>,
+    pub instance_layers: Vec<CString>,
+
+    /// From `xrGetVulkanGraphicsDevice2KHR`.
+    pub physical_device: vk::PhysicalDevice,
+
+    /// From `xrCreateVulkanDeviceKHR`.
+    pub device: vk::Device,
+    pub device_extensions: Vec<CString>,
+    pub device_layers: Vec<CString>,
+    pub device_features: PhysicalDeviceFeatures<'static>,
+
+    /// The queue passed to OpenXR in `XrGraphicsBindingVulkan2KHR`.
+    pub queue_family_index: u32,
+    pub queue_index: u32,
+}
+
+/// bort wrappers of [`XrVulkanHandles`].
+pub struct XrVulkanObjects {
+    pub instance: Arc<Instance>,
+    pub physical_device: Arc<PhysicalDevice>,
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+}
+
+impl XrVulkanHandles {
+    /// Wraps the handles in bort types. OpenXR leaves destroying the instance and device to the
+    /// application so [`HandleOwnership::Owned`] is usually what you want, but make sure the
+    /// `XrSession` is destroyed before the returned objects are dropped. Nothing is destroyed if
+    /// this returns an error.
+    ///
+    /// # Safety
+    /// The handles must be valid and created with the supplied extensions, layers and features.
+    /// `entry` must be the loader OpenXR used to create the instance.
+    pub unsafe fn adopt(
+        self,
+        entry: Arc<Entry>,
+        ownership: HandleOwnership,
+    ) -> Result<XrVulkanObjects, PhysicalDeviceError> {
+        let instance = Arc::new(unsafe {
+            Instance::from_handle(
+                entry,
+                self.instance,
+                self.instance_api_version,
+                self.instance_extensions,
+                self.instance_layers,
+                ownership,
+            )
+        });
+
+        let physical_device = match PhysicalDevice::new(instance.clone(), self.physical_device) {
+            Ok(physical_device) => Arc::new(physical_device),
+            Err(e) => {
+                // the caller still has the instance handle (and the device created from it) on
+                // failure so don't destroy it
+                if let Ok(mut instance) = Arc::try_unwrap(instance) {
+                    instance.release_ownership();
+                }
+                return Err(e);
+            }
+        };
+
+        let device = Arc::new(unsafe {
+            Device::from_handle(
+                physical_device.clone(),
+                self.device,
+                self.device_extensions,
+                self.device_layers,
+                self.device_features,
+                ownership,
+            )
+        });
+
+        let queue = Arc::new(unsafe {
+            Queue::from_handle(
+                device.clone(),
+                device
+                    .inner()
+                    .get_device_queue(self.queue_family_index, self.queue_index),
+                self.queue_family_index,
+                self.queue_index,
+            )
+        });
+
+        Ok(XrVulkanObjects {
+            instance,
+            physical_device,
+            device,
+            queue,
+        })
+    }
+}
+
+/// An image of an `XrSwapchain` and a view of all its layers. See [`adopt_xr_swapchain_images`].
+pub struct XrSwapchainImage {
+    pub image: Arc<BorrowedImage>,
+    pub image_view: Arc<ImageView<BorrowedImage>>,
+}
+
+/// Wraps the images of an `XrSwapchain` (owned by the OpenXR runtime) and creates a view of each.
+/// `raw_images` are the `image` members of the `XrSwapchainImageVulkan2KHR` structs from
+/// `xrEnumerateSwapchainImages`. `format`, `width`, `height` and `array_size` are the values in the
+/// `XrSwapchainCreateInfo` e.g. `array_size` 2 for a multiview stereo swapchain.
+///
+/// # Safety
+/// The images must belong to a swapchain created with the given properties which outlives the
+/// returned images and views.
+pub unsafe fn adopt_xr_swapchain_images(
+    device: &Arc<Device>,
+    raw_images: impl IntoIterator<Item = u64>,
+    format: vk::Format,
+    width: u32,
+    height: u32,
+    array_size: u32,
+) -> VkResult<Vec<XrSwapchainImage>> {
+    let dimensions = ImageDimensions::new_2d_array(width, height, array_size);
+    let view_properties = xr_swapchain_image_view_properties(format, dimensions);
+
+    raw_images
+        .into_iter()
+        .map(|raw_image| {
+            let image = Arc::new(unsafe {
+                BorrowedImage::new(device.clone(), vk::Image::from_raw(raw_image), dimensions)
+            });
+            let image_view = Arc::new(ImageView::new(image.clone(), view_properties)?);
+            Ok(XrSwapchainImage { image, image_view })
+        })
+        .collect()
+}
+
+/// Color view of all layers of a swapchain image. `TYPE_2D_ARRAY` for multiview swapchains.
+pub fn xr_swapchain_image_view_properties(
+    format: vk::Format,
+    dimensions: ImageDimensions,
+) -> ImageViewProperties {
+    let subresource_range = vk::ImageSubresourceRange {
+        layer_count: dimensions.array_layers(),
+        ..default_subresource_range(vk::ImageAspectFlags::COLOR)
+    };
+
+    ImageViewProperties {
+        format,
+        view_type: dimensions.default_image_view_type(),
+        subresource_range,
+        ..ImageViewProperties::default()
+    }
+}
+
+// ~~ Tests ~~
+
+#[test]
+fn xr_swapchain_view_properties() {
+    let stereo = xr_swapchain_image_view_properties(
+        vk::Format::R8G8B8A8_SRGB,
+        ImageDimensions::new_2d_array(1832, 1920, 2),
+    );
+    assert_eq!(stereo.view_type, vk::ImageViewType::TYPE_2D_ARRAY);
+    assert_eq!(stereo.subresource_range.layer_count, 2);
+    assert_eq!(stereo.format, vk::Format::R8G8B8A8_SRGB);
+
+    let mono = xr_swapchain_image_view_properties(
+        vk::Format::R8G8B8A8_SRGB,
+        ImageDimensions::new_2d(1832, 1920),
+    );
+    assert_eq!(mono.view_type, vk::ImageViewType::TYPE_2D);
+    assert_eq!(mono.subresource_range.layer_count, 1);
+}
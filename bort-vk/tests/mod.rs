@@ -241,9 +241,9 @@ fn test_gpu_stats() {
 
     unsafe {
         let stats_1 = allocator.calculate_statistics().unwrap();
-        assert_eq!(stats_1.total.statistics.blockCount, 0);
-        assert_eq!(stats_1.total.statistics.allocationCount, 0);
-        assert_eq!(stats_1.total.statistics.allocationBytes, 0);
+        assert_eq!(stats_1.total.statistics.block_count, 0);
+        assert_eq!(stats_1.total.statistics.allocation_count, 0);
+        assert_eq!(stats_1.total.statistics.allocation_bytes, 0);
 
         let (buffer, mut allocation) = allocator
             .create_buffer(
@@ -256,15 +256,15 @@ fn test_gpu_stats() {
             .unwrap();
 
         let stats_2 = allocator.calculate_statistics().unwrap();
-        assert_eq!(stats_2.total.statistics.blockCount, 1);
-        assert_eq!(stats_2.total.statistics.allocationCount, 1);
-        assert_eq!(stats_2.total.statistics.allocationBytes, 16 * 1024);
+        assert_eq!(stats_2.total.statistics.block_count, 1);
+        assert_eq!(stats_2.total.statistics.allocation_count, 1);
+        assert_eq!(stats_2.total.statistics.allocation_bytes, 16 * 1024);
 
         allocator.destroy_buffer(buffer, &mut allocation);
 
         let stats_3 = allocator.calculate_statistics().unwrap();
-        assert_eq!(stats_3.total.statistics.blockCount, 1);
-        assert_eq!(stats_3.total.statistics.allocationCount, 0);
-        assert_eq!(stats_3.total.statistics.allocationBytes, 0);
+        assert_eq!(stats_3.total.statistics.block_count, 1);
+        assert_eq!(stats_3.total.statistics.allocation_count, 0);
+        assert_eq!(stats_3.total.statistics.allocation_bytes, 0);
     }
 }
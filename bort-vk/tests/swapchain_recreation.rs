@@ -0,0 +1,541 @@
+//! Swapchain recreation stress test. Renders to a `VK_EXT_headless_surface` swapchain while
+//! randomly resizing, "minimizing" (zero extent), switching present modes and switching surface
+//! formats, then checks that no validation errors were reported and that no images or command
+//! buffers leaked (via the device's object counters).
+//!
+//! Skipped with a message when there's no vulkan loader, no headless surface support or no
+//! suitable device (e.g. on ci machines without a gpu or lavapipe). Windowed surfaces aren't
+//! covered because the test harness can't own a winit event loop.
+//!
+//! Environment variables:
+//! - `BORT_SWAPCHAIN_STRESS_ITERATIONS`: number of random actions (default 200)
+//! - `BORT_SWAPCHAIN_STRESS_SEED`: rng seed (default 1) to reproduce a failing sequence
+
+#![cfg(feature = "presentation")]
+
+extern crate ash;
+extern crate bort_vk;
+
+use ash::vk;
+use bort_vk::{
+    ApiVersion, CommandBuffer, CommandPool, CommandPoolProperties, CountedObjectType,
+    DebugCallback, DebugCallbackProperties, Device, Fence, FrameOutcome, Image, ImageAccess,
+    ImageView, Instance, MemoryAllocator, PerFrame, PerSwapchainImage, PhysicalDevice, Queue,
+    Semaphore, Surface, Swapchain, SwapchainError, SwapchainImageIndex, SwapchainProperties,
+    VALIDATION_LAYER_NAME,
+};
+use std::{
+    env,
+    ffi::{CStr, CString},
+    os::raw::c_void,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+const FRAMES_IN_FLIGHT: usize = 2;
+const FRAMES_PER_ACTION: usize = 2;
+const TIMEOUT_NANOSECONDS: u64 = 5_000_000_000;
+const MAX_EXTENT: u32 = 512;
+const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+static VALIDATION_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "system" fn count_validation_errors(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    _message_types: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _p_user_data: *mut c_void,
+) -> vk::Bool32 {
+    if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        VALIDATION_ERRORS.fetch_add(1, Ordering::Relaxed);
+        let message = CStr::from_ptr((*p_callback_data).p_message);
+        eprintln!("validation error: {:?}", message);
+    }
+    vk::FALSE
+}
+
+/// Deterministic xorshift so failures can be reproduced with `BORT_SWAPCHAIN_STRESS_SEED`.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u32) -> u32 {
+        (self.next() % bound as u64) as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    Resize([u32; 2]),
+    Minimize,
+    NextPresentMode,
+    NextSurfaceFormat,
+}
+
+impl Action {
+    fn random(rng: &mut Rng) -> Self {
+        match rng.below(4) {
+            0 => Self::Minimize,
+            1 => Self::NextPresentMode,
+            2 => Self::NextSurfaceFormat,
+            _ => Self::Resize([1 + rng.below(MAX_EXTENT), 1 + rng.below(MAX_EXTENT)]),
+        }
+    }
+}
+
+struct StressContext {
+    surface: Arc<Surface>,
+    device: Arc<Device>,
+    queue: Queue,
+    memory_allocator: Arc<MemoryAllocator>,
+    command_pool: Arc<CommandPool>,
+    present_modes: Vec<vk::PresentModeKHR>,
+    surface_formats: Vec<vk::SurfaceFormatKHR>,
+    image_usage: vk::ImageUsageFlags,
+    min_image_count: u32,
+}
+
+struct FrameSync {
+    command_buffer: CommandBuffer,
+    image_available: Semaphore,
+    in_flight: Fence,
+}
+
+/// Resources which are recreated along with the swapchain.
+struct SwapchainResources {
+    swapchain: Arc<Swapchain>,
+    render_finished: PerSwapchainImage<Semaphore>,
+    offscreen_image: Arc<Image>,
+    _offscreen_view: Arc<ImageView<Image>>,
+}
+
+impl SwapchainResources {
+    fn new(context: &StressContext, swapchain: Arc<Swapchain>) -> Self {
+        let render_finished =
+            PerSwapchainImage::try_from_fn(swapchain.swapchain_images().len() as u32, |_| {
+                Semaphore::new(context.device.clone())
+            })
+            .unwrap();
+        let (offscreen_image, offscreen_view) = Image::new_color_attachment(
+            context.memory_allocator.clone(),
+            swapchain.properties().dimensions(),
+            OFFSCREEN_FORMAT,
+            vk::ImageUsageFlags::TRANSFER_DST,
+        )
+        .unwrap();
+        Self {
+            swapchain,
+            render_finished,
+            offscreen_image,
+            _offscreen_view: offscreen_view,
+        }
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn create_context() -> Option<StressContext> {
+    let entry = match unsafe { ash::Entry::load() } {
+        Ok(entry) => Arc::new(entry),
+        Err(e) => {
+            eprintln!(
+                "skipping swapchain stress test: failed to load vulkan ({})",
+                e
+            );
+            return None;
+        }
+    };
+
+    let mut extension_names: Vec<CString> = Instance::SURFACE_EXTS_HEADLESS
+        .iter()
+        .map(|&name| name.to_owned())
+        .collect();
+    for extension_name in &extension_names {
+        if !Instance::supports_extension(&entry, None, extension_name.clone()).unwrap_or(false) {
+            eprintln!(
+                "skipping swapchain stress test: {:?} isn't supported",
+                extension_name
+            );
+            return None;
+        }
+    }
+
+    let mut layer_names = Vec::new();
+    let enable_validation = Instance::layer_avilable(&entry, VALIDATION_LAYER_NAME.to_owned())
+        .unwrap_or(false)
+        && Instance::supports_extension(&entry, None, vk::EXT_DEBUG_UTILS_NAME.to_owned())
+            .unwrap_or(false);
+    if enable_validation {
+        layer_names.push(VALIDATION_LAYER_NAME.to_owned());
+        extension_names.push(vk::EXT_DEBUG_UTILS_NAME.to_owned());
+    } else {
+        eprintln!("validation layers unavailable. only checking for leaks");
+    }
+
+    let instance = Arc::new(
+        Instance::new(
+            entry.clone(),
+            ApiVersion::V1_2,
+            layer_names,
+            extension_names,
+        )
+        .unwrap(),
+    );
+    let debug_callback = enable_validation.then(|| {
+        Arc::new(
+            DebugCallback::new(
+                instance.clone(),
+                Some(count_validation_errors),
+                DebugCallbackProperties::default(),
+            )
+            .unwrap(),
+        )
+    });
+    let surface = Arc::new(Surface::new_headless(&entry, instance.clone()).unwrap());
+
+    let physical_device_handles = instance.enumerate_physical_devices().unwrap();
+    let suitable_device = physical_device_handles.into_iter().find_map(|handle| {
+        let physical_device = PhysicalDevice::new(instance.clone(), handle).ok()?;
+        if !physical_device.supports_extension(vk::KHR_SWAPCHAIN_NAME.to_owned()) {
+            return None;
+        }
+        let queue_family_index =
+            (0..physical_device.queue_family_properties().len() as u32).find(|&index| {
+                let graphics_support = physical_device.queue_family_properties()[index as usize]
+                    .queue_flags
+                    .contains(vk::QueueFlags::GRAPHICS);
+                graphics_support
+                    && surface
+                        .get_physical_device_surface_support(&physical_device, index)
+                        .unwrap_or(false)
+            })?;
+        Some((Arc::new(physical_device), queue_family_index))
+    });
+    let Some((physical_device, queue_family_index)) = suitable_device else {
+        eprintln!("skipping swapchain stress test: no device can present to a headless surface");
+        return None;
+    };
+
+    let queue_priorities = [1.0];
+    let queue_create_info = vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(queue_family_index)
+        .queue_priorities(&queue_priorities);
+    let device = Arc::new(
+        Device::new(
+            physical_device.clone(),
+            [queue_create_info],
+            Default::default(),
+            vec![vk::KHR_SWAPCHAIN_NAME.to_owned()],
+            Vec::new(),
+            debug_callback,
+        )
+        .unwrap(),
+    );
+    device.set_object_counting(true);
+
+    let surface_capabilities = surface
+        .get_physical_device_surface_capabilities(&physical_device)
+        .unwrap();
+    let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST;
+    if !surface_capabilities
+        .supported_usage_flags
+        .contains(image_usage)
+    {
+        eprintln!("skipping swapchain stress test: headless surface doesn't support TRANSFER_DST");
+        return None;
+    }
+    let present_modes = surface
+        .get_physical_device_surface_present_modes(&physical_device)
+        .unwrap();
+    let surface_formats = surface
+        .get_physical_device_surface_formats(&physical_device)
+        .unwrap();
+
+    let queue = Queue::new(device.clone(), queue_family_index, 0).unwrap();
+    let memory_allocator = Arc::new(MemoryAllocator::new(device.clone()).unwrap());
+    let command_pool = Arc::new(
+        CommandPool::new(
+            device.clone(),
+            CommandPoolProperties {
+                flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+                queue_family_index,
+            },
+        )
+        .unwrap(),
+    );
+
+    Some(StressContext {
+        surface,
+        device,
+        queue,
+        memory_allocator,
+        command_pool,
+        present_modes,
+        surface_formats,
+        image_usage,
+        min_image_count: surface_capabilities.min_image_count,
+    })
+}
+
+fn swapchain_properties(
+    context: &StressContext,
+    width_height: [u32; 2],
+    present_mode: vk::PresentModeKHR,
+    surface_format: vk::SurfaceFormatKHR,
+) -> SwapchainProperties {
+    SwapchainProperties {
+        image_count: context.min_image_count + 1,
+        surface_format,
+        width_height,
+        image_usage: context.image_usage,
+        pre_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+        composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+        present_mode,
+        ..SwapchainProperties::default()
+    }
+}
+
+fn whole_color_range() -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+fn record_clears(
+    command_buffer: &CommandBuffer,
+    resources: &SwapchainResources,
+    image_index: SwapchainImageIndex,
+) {
+    let swapchain_image = &resources.swapchain.swapchain_images()[image_index.value() as usize];
+    let to_transfer_dst = |image: vk::Image| {
+        vk::ImageMemoryBarrier::default()
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .image(image)
+            .subresource_range(whole_color_range())
+    };
+    command_buffer.pipeline_barrier(
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[
+            to_transfer_dst(swapchain_image.handle()),
+            to_transfer_dst(resources.offscreen_image.handle()),
+        ],
+    );
+
+    let clear_color = vk::ClearColorValue {
+        float32: [0.1, 0.2, 0.3, 1.0],
+    };
+    command_buffer.clear_color_image(
+        swapchain_image.as_ref(),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &clear_color,
+        &[whole_color_range()],
+    );
+    command_buffer.clear_color_image(
+        resources.offscreen_image.as_ref(),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &clear_color,
+        &[whole_color_range()],
+    );
+
+    let to_present = vk::ImageMemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+        .image(swapchain_image.handle())
+        .subresource_range(whole_color_range());
+    command_buffer.pipeline_barrier(
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[to_present],
+    );
+}
+
+fn render_frame(
+    context: &StressContext,
+    frame: &FrameSync,
+    resources: &SwapchainResources,
+) -> FrameOutcome {
+    frame.in_flight.wait(TIMEOUT_NANOSECONDS).unwrap();
+
+    let image_index = match resources.swapchain.aquire_next_image(
+        TIMEOUT_NANOSECONDS,
+        Some(&frame.image_available),
+        None,
+    ) {
+        Ok((image_index, _is_suboptimal)) => SwapchainImageIndex::new(image_index),
+        Err(e) => return FrameOutcome::from_vk_result(e),
+    };
+    frame.in_flight.reset().unwrap();
+
+    let command_buffer = &frame.command_buffer;
+    command_buffer
+        .reset(vk::CommandBufferResetFlags::empty())
+        .unwrap();
+    command_buffer
+        .begin(&vk::CommandBufferBeginInfo::default())
+        .unwrap();
+    record_clears(command_buffer, resources, image_index);
+    command_buffer.end().unwrap();
+
+    let wait_semaphores = [frame.image_available.handle()];
+    let wait_stages = [vk::PipelineStageFlags::TRANSFER];
+    let signal_semaphores = [resources.render_finished[image_index].handle()];
+    let command_buffers = [command_buffer.handle()];
+    let submit_info = vk::SubmitInfo::default()
+        .wait_semaphores(&wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages)
+        .signal_semaphores(&signal_semaphores)
+        .command_buffers(&command_buffers);
+    context
+        .queue
+        .submit(&[submit_info], Some(&frame.in_flight))
+        .unwrap();
+
+    let swapchains = [resources.swapchain.handle()];
+    let image_indices = [image_index.value()];
+    let present_info = vk::PresentInfoKHR::default()
+        .wait_semaphores(&signal_semaphores)
+        .swapchains(&swapchains)
+        .image_indices(&image_indices);
+    FrameOutcome::from_present_result(
+        resources
+            .swapchain
+            .queue_present(&context.queue, &present_info),
+    )
+}
+
+#[test]
+fn swapchain_recreation_stress() {
+    let Some(context) = create_context() else {
+        return;
+    };
+    let iterations = env_u64("BORT_SWAPCHAIN_STRESS_ITERATIONS", 200);
+    let mut rng = Rng(env_u64("BORT_SWAPCHAIN_STRESS_SEED", 1).max(1));
+
+    let mut present_mode_index = 0;
+    let mut surface_format_index = 0;
+    let mut width_height = [256, 256];
+    let swapchain = Swapchain::new(
+        context.device.clone(),
+        context.surface.clone(),
+        swapchain_properties(
+            &context,
+            width_height,
+            context.present_modes[present_mode_index],
+            context.surface_formats[surface_format_index],
+        ),
+    )
+    .unwrap();
+    let mut resources = SwapchainResources::new(&context, Arc::new(swapchain));
+
+    let mut command_buffers = context
+        .command_pool
+        .allocate_command_buffers(vk::CommandBufferLevel::PRIMARY, FRAMES_IN_FLIGHT as u32)
+        .unwrap();
+    let mut frames = PerFrame::from_fn(FRAMES_IN_FLIGHT, |_| FrameSync {
+        command_buffer: command_buffers.pop().unwrap(),
+        image_available: Semaphore::new(context.device.clone()).unwrap(),
+        in_flight: Fence::new_signalled(context.device.clone()).unwrap(),
+    });
+
+    for iteration in 0..iterations {
+        let action = Action::random(&mut rng);
+        match action {
+            Action::Resize(new_width_height) => width_height = new_width_height,
+            Action::Minimize => {
+                let properties = swapchain_properties(
+                    &context,
+                    [0, width_height[1]],
+                    context.present_modes[present_mode_index],
+                    context.surface_formats[surface_format_index],
+                );
+                let old_handle = resources.swapchain.handle();
+                let res = resources.swapchain.recreate_replace(properties);
+                assert!(
+                    matches!(res, Err(SwapchainError::ZeroExtent { .. })),
+                    "iteration {}: zero extent recreation should be rejected",
+                    iteration
+                );
+                assert_eq!(resources.swapchain.handle(), old_handle);
+            }
+            Action::NextPresentMode => {
+                present_mode_index = (present_mode_index + 1) % context.present_modes.len()
+            }
+            Action::NextSurfaceFormat => {
+                surface_format_index = (surface_format_index + 1) % context.surface_formats.len()
+            }
+        }
+
+        // the minimized case keeps the old swapchain like a render loop would
+        if !matches!(action, Action::Minimize) {
+            context.device.wait_idle().unwrap();
+            let properties = swapchain_properties(
+                &context,
+                width_height,
+                context.present_modes[present_mode_index],
+                context.surface_formats[surface_format_index],
+            );
+            let swapchain = resources
+                .swapchain
+                .recreate_replace(properties)
+                .unwrap_or_else(|e| panic!("iteration {} ({:?}): {}", iteration, action, e));
+            resources = SwapchainResources::new(&context, swapchain);
+        }
+
+        for _ in 0..FRAMES_PER_ACTION {
+            let outcome = render_frame(&context, frames.current(), &resources);
+            assert!(
+                matches!(
+                    outcome,
+                    FrameOutcome::Presented | FrameOutcome::RecreateSwapchain
+                ),
+                "iteration {} ({:?}): unexpected frame outcome {:?}",
+                iteration,
+                action,
+                outcome
+            );
+            frames.advance();
+        }
+    }
+
+    context.device.wait_idle().unwrap();
+    drop(frames);
+    drop(resources);
+
+    let object_counters = context.device.object_counters();
+    for object_type in [CountedObjectType::Image, CountedObjectType::CommandBuffer] {
+        let counts = object_counters.counts(object_type);
+        assert_eq!(counts.live(), 0, "leaked {}s: {:?}", object_type, counts);
+    }
+    assert_eq!(
+        VALIDATION_ERRORS.load(Ordering::Relaxed),
+        0,
+        "validation errors were reported during swapchain recreation"
+    );
+}
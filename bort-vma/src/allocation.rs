@@ -0,0 +1,64 @@
+use crate::ffi;
+use std::{
+    ffi::{CStr, CString},
+    mem,
+};
+
+/// Name and user data accessors for a `VmaAllocation` handle.
+///
+/// The user data is an opaque `u64` (VMA stores it as a pointer so on 32-bit targets the upper
+/// 32 bits are discarded). Allocations created with the deprecated
+/// `AllocationCreateFlags::USER_DATA_COPY_STRING` treat the user data as a string pointer so don't
+/// use [`Self::set_user_data`] or [`Self::user_data`] with those.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Allocation(pub ffi::VmaAllocation);
+
+impl Allocation {
+    /// Sets the name of the allocation, shown in VMA's json stats and debugging tools. VMA makes a
+    /// copy of the string. Any nul bytes in `name` truncate it.
+    ///
+    /// # Safety
+    /// The allocation must be valid and created by `allocator`.
+    pub unsafe fn set_name(self, allocator: ffi::VmaAllocator, name: &str) {
+        let name_bytes = name.split('\0').next().unwrap_or_default();
+        let name_cstring = CString::new(name_bytes).unwrap_or_default();
+        ffi::vmaSetAllocationName(allocator, self.0, name_cstring.as_ptr());
+    }
+
+    /// The name set with [`Self::set_name`], if any.
+    ///
+    /// # Safety
+    /// The allocation must be valid and created by `allocator`.
+    pub unsafe fn name(self, allocator: ffi::VmaAllocator) -> Option<String> {
+        let allocation_info = self.info(allocator);
+        if allocation_info.pName.is_null() {
+            return None;
+        }
+        Some(
+            CStr::from_ptr(allocation_info.pName)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    /// # Safety
+    /// The allocation must be valid and created by `allocator`.
+    pub unsafe fn set_user_data(self, allocator: ffi::VmaAllocator, user_data: u64) {
+        ffi::vmaSetAllocationUserData(allocator, self.0, user_data as usize as *mut _);
+    }
+
+    /// The user data set with [`Self::set_user_data`] or `AllocationCreateInfo::user_data`.
+    /// Defaults to 0.
+    ///
+    /// # Safety
+    /// The allocation must be valid and created by `allocator`.
+    pub unsafe fn user_data(self, allocator: ffi::VmaAllocator) -> u64 {
+        self.info(allocator).pUserData as usize as u64
+    }
+
+    unsafe fn info(self, allocator: ffi::VmaAllocator) -> ffi::VmaAllocationInfo {
+        let mut allocation_info: ffi::VmaAllocationInfo = mem::zeroed();
+        ffi::vmaGetAllocationInfo(allocator, self.0, &mut allocation_info);
+        allocation_info
+    }
+}
@@ -550,3 +550,119 @@ impl From<&AllocationCreateInfo> for ffi::VmaAllocationCreateInfo {
         }
     }
 }
+
+/// Calculated statistics of memory usage e.g. in a specific memory type, heap, custom pool, or
+/// total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    /// Number of `vk::DeviceMemory` objects - Vulkan memory blocks allocated.
+    pub block_count: u32,
+    /// Number of `Allocation` objects allocated.
+    ///
+    /// Dedicated allocations have their own blocks, so each one adds 1 to `allocation_count` as
+    /// well as `block_count`.
+    pub allocation_count: u32,
+    /// Number of bytes allocated in `vk::DeviceMemory` blocks.
+    pub block_bytes: vk::DeviceSize,
+    /// Total number of bytes occupied by all `Allocation` objects.
+    ///
+    /// Always less or equal than `block_bytes`. Difference `block_bytes - allocation_bytes` is the
+    /// amount of memory allocated from Vulkan but unused by any `Allocation`.
+    pub allocation_bytes: vk::DeviceSize,
+}
+
+impl From<ffi::VmaStatistics> for Statistics {
+    fn from(statistics: ffi::VmaStatistics) -> Self {
+        Self {
+            block_count: statistics.blockCount,
+            allocation_count: statistics.allocationCount,
+            block_bytes: statistics.blockBytes,
+            allocation_bytes: statistics.allocationBytes,
+        }
+    }
+}
+
+/// More detailed statistics than [`Statistics`]. These are slower to calculate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetailedStatistics {
+    /// Basic statistics.
+    pub statistics: Statistics,
+    /// Number of free ranges of memory between allocations.
+    pub unused_range_count: u32,
+    /// Smallest allocation size. `vk::WHOLE_SIZE` if there are 0 allocations.
+    pub allocation_size_min: vk::DeviceSize,
+    /// Largest allocation size. 0 if there are 0 allocations.
+    pub allocation_size_max: vk::DeviceSize,
+    /// Smallest empty range size. `vk::WHOLE_SIZE` if there are 0 empty ranges.
+    pub unused_range_size_min: vk::DeviceSize,
+    /// Largest empty range size. 0 if there are 0 empty ranges.
+    pub unused_range_size_max: vk::DeviceSize,
+}
+
+impl From<ffi::VmaDetailedStatistics> for DetailedStatistics {
+    fn from(statistics: ffi::VmaDetailedStatistics) -> Self {
+        Self {
+            statistics: statistics.statistics.into(),
+            unused_range_count: statistics.unusedRangeCount,
+            allocation_size_min: statistics.allocationSizeMin,
+            allocation_size_max: statistics.allocationSizeMax,
+            unused_range_size_min: statistics.unusedRangeSizeMin,
+            unused_range_size_max: statistics.unusedRangeSizeMax,
+        }
+    }
+}
+
+/// General statistics from current state of the allocator - total memory usage across all memory
+/// heaps and types.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TotalStatistics {
+    /// Indexed by memory type index. Only the first `memory_type_count` entries are valid.
+    pub memory_type: [DetailedStatistics; vk::MAX_MEMORY_TYPES],
+    /// Indexed by memory heap index. Only the first `memory_heap_count` entries are valid.
+    pub memory_heap: [DetailedStatistics; vk::MAX_MEMORY_HEAPS],
+    pub total: DetailedStatistics,
+}
+
+impl From<ffi::VmaTotalStatistics> for TotalStatistics {
+    fn from(statistics: ffi::VmaTotalStatistics) -> Self {
+        Self {
+            memory_type: statistics.memoryType.map(DetailedStatistics::from),
+            memory_heap: statistics.memoryHeap.map(DetailedStatistics::from),
+            total: statistics.total.into(),
+        }
+    }
+}
+
+/// Statistics of current memory usage and available budget for a specific memory heap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapBudget {
+    /// Statistics fetched from the library.
+    pub statistics: Statistics,
+    /// Estimated current memory usage of the program, in bytes.
+    ///
+    /// Fetched from system using `VK_EXT_memory_budget` extension if enabled.
+    ///
+    /// It might be different than `statistics.block_bytes` (usually higher) due to additional
+    /// implicit objects also occupying the memory, like swapchain, pipelines, descriptor heaps,
+    /// command buffers, or `vk::DeviceMemory` blocks allocated outside of this library, if any.
+    pub usage: vk::DeviceSize,
+    /// Estimated amount of memory available to the program, in bytes.
+    ///
+    /// Fetched from system using `VK_EXT_memory_budget` extension if enabled.
+    ///
+    /// It might be different (most probably smaller) than the heap size due to factors external to
+    /// the program, decided by the operating system. Difference `budget - usage` is the amount of
+    /// additional memory that can probably be allocated without problems. Exceeding the budget may
+    /// result in various problems.
+    pub budget: vk::DeviceSize,
+}
+
+impl From<ffi::VmaBudget> for HeapBudget {
+    fn from(budget: ffi::VmaBudget) -> Self {
+        Self {
+            statistics: budget.statistics.into(),
+            usage: budget.usage,
+            budget: budget.budget,
+        }
+    }
+}
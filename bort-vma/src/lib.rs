@@ -2,6 +2,10 @@
 #![allow(clippy::deprecated_semver)]
 #![allow(clippy::needless_return)]
 
+mod allocation;
 mod definitions;
 pub mod ffi;
+mod pool;
+pub use allocation::*;
 pub use definitions::*;
+pub use pool::*;
@@ -0,0 +1,57 @@
+use crate::{ffi, DetailedStatistics, Statistics};
+use ash::vk;
+use std::{ffi::CStr, mem, ptr};
+
+/// Name, statistics and corruption check accessors for a `VmaPool` handle. Pass the handle as
+/// `AllocationCreateInfo::pool` (or use `bort_vk::MemoryPool`, which owns one) to allocate from
+/// the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AllocatorPool(pub ffi::VmaPool);
+
+impl AllocatorPool {
+    /// Sets the name of the pool, shown in VMA's json stats. VMA makes a copy of the string.
+    ///
+    /// # Safety
+    /// The pool must be valid and created by `allocator`.
+    pub unsafe fn set_name(self, allocator: ffi::VmaAllocator, name: Option<&CStr>) {
+        ffi::vmaSetPoolName(allocator, self.0, name.map_or(ptr::null(), CStr::as_ptr));
+    }
+
+    /// The name set with [`Self::set_name`], if any.
+    ///
+    /// # Safety
+    /// The pool must be valid and created by `allocator`. The returned string is only valid
+    /// until the name is changed or the pool is destroyed.
+    pub unsafe fn name<'a>(self, allocator: ffi::VmaAllocator) -> Option<&'a CStr> {
+        let mut name_ptr: *const std::os::raw::c_char = ptr::null();
+        ffi::vmaGetPoolName(allocator, self.0, &mut name_ptr);
+        if name_ptr.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(name_ptr))
+    }
+
+    /// # Safety
+    /// The pool must be valid and created by `allocator`.
+    pub unsafe fn statistics(self, allocator: ffi::VmaAllocator) -> Statistics {
+        let mut pool_stats: ffi::VmaStatistics = mem::zeroed();
+        ffi::vmaGetPoolStatistics(allocator, self.0, &mut pool_stats);
+        pool_stats.into()
+    }
+
+    /// # Safety
+    /// The pool must be valid and created by `allocator`.
+    pub unsafe fn calculate_statistics(self, allocator: ffi::VmaAllocator) -> DetailedStatistics {
+        let mut pool_stats: ffi::VmaDetailedStatistics = mem::zeroed();
+        ffi::vmaCalculatePoolStatistics(allocator, self.0, &mut pool_stats);
+        pool_stats.into()
+    }
+
+    /// The raw result of `vmaCheckPoolCorruption`.
+    ///
+    /// # Safety
+    /// The pool must be valid and created by `allocator`.
+    pub unsafe fn check_corruption(self, allocator: ffi::VmaAllocator) -> vk::Result {
+        ffi::vmaCheckPoolCorruption(allocator, self.0)
+    }
+}
@@ -0,0 +1,455 @@
+//! Headless example that demonstrates and stress-tests the bindless descriptor plumbing with
+//! [`MaterialDescriptorSystem`]: random texture/material registration and removal with slot
+//! recycling, stale handle detection and updating the texture array after the set is bound.
+
+mod material_descriptor_system;
+
+use ash::vk::{self, EXT_DEBUG_UTILS_NAME, EXT_ROBUSTNESS2_NAME};
+use bort_vk::{
+    allocation_info_cpu_accessible, ApiVersion, CommandBuffer, CommandPool, CommandPoolProperties,
+    DebugCallback, DebugCallbackProperties, Device, Fence, Image, ImageDimensions, ImageProperties,
+    ImageView, ImageViewAccess, ImageViewProperties, Instance, MemoryAllocator, PhysicalDevice,
+    PhysicalDeviceFeatures, PipelineLayout, PipelineLayoutProperties, Queue, Sampler,
+    SamplerProperties,
+};
+use bort_vma::AllocationCreateInfo;
+use env_logger::Env;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use material_descriptor_system::{
+    MaterialDescriptorSystem, MaterialHandle, MaterialSystemError, TextureHandle, MATERIAL_SET,
+    TEXTURE_SET,
+};
+use std::{
+    borrow::Cow,
+    error::Error,
+    ffi::{CStr, CString},
+    sync::Arc,
+};
+
+const MAX_API_VERSION: ApiVersion = ApiVersion { major: 1, minor: 3 };
+const MAX_TEXTURES: u32 = 1024;
+const MAX_MATERIALS: u32 = 256;
+const STRESS_ITERATIONS: usize = 20_000;
+const TEXTURE_COLORS: [[u8; 4]; 4] = [
+    [255, 0, 0, 255],
+    [0, 255, 0, 255],
+    [0, 0, 255, 255],
+    [255, 255, 255, 255],
+];
+const FENCE_TIMEOUT: u64 = 1_000_000_000;
+const ENABLE_VULKAN_VALIDATION: bool = cfg!(debug_assertions);
+const VALIDATION_LAYER_NAME: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = unsafe { ash::Entry::load() }?;
+    Ok(Arc::new(entry))
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = ash_molten::load();
+    Ok(Arc::new(entry))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let log_env = Env::default()
+        .filter_or("MY_LOG_LEVEL", "debug")
+        .write_style_or("MY_LOG_STYLE", "always");
+    env_logger::init_from_env(log_env);
+    info!("starting bindless materials example...");
+
+    let entry = create_entry()?;
+    info!("vulkan loaded");
+
+    let mut enable_validation = ENABLE_VULKAN_VALIDATION;
+    let mut instance_layers = Vec::<CString>::new();
+    let mut instance_extensions = Vec::<CString>::new();
+
+    if enable_validation {
+        let validation_layer_installed =
+            Instance::layer_avilable(&entry, VALIDATION_LAYER_NAME.to_owned())?;
+        let debug_utils_supported =
+            Instance::supports_extension(&entry, None, EXT_DEBUG_UTILS_NAME.to_owned())?;
+
+        if validation_layer_installed && debug_utils_supported {
+            instance_layers.push(VALIDATION_LAYER_NAME.to_owned());
+            instance_extensions.push(EXT_DEBUG_UTILS_NAME.to_owned());
+            info!("vulkan validation layers enabled");
+        } else {
+            enable_validation = false;
+            info!("vulkan validation layers disabled");
+        }
+    } else {
+        info!("vulkan validation layers disabled");
+    }
+
+    let instance = Arc::new(Instance::new(
+        entry,
+        MAX_API_VERSION,
+        instance_layers,
+        instance_extensions,
+    )?);
+    info!("created vulkan instance");
+
+    let debug_callback = if enable_validation {
+        let debug_callback = DebugCallback::new(
+            instance.clone(),
+            Some(log_vulkan_debug_callback),
+            DebugCallbackProperties::default(),
+        )?;
+        Some(Arc::new(debug_callback))
+    } else {
+        None
+    };
+
+    let physical_device_handles = instance.enumerate_physical_devices()?;
+    let physical_device_handle = physical_device_handles
+        .first()
+        .ok_or(BortExampleError::NoPhysicalDevice)?;
+    let physical_device = Arc::new(PhysicalDevice::new(
+        instance.clone(),
+        *physical_device_handle,
+    )?);
+    info!("chosen physical device: {}", physical_device.name());
+
+    let queue_family_index = physical_device
+        .queue_family_properties()
+        .iter()
+        .position(|queue_family_properties| {
+            queue_family_properties
+                .queue_flags
+                .contains(vk::QueueFlags::GRAPHICS)
+        })
+        .ok_or(BortExampleError::NoSuitableQueueFamily)? as u32;
+
+    // check descriptor indexing and null descriptor support
+
+    let features_1_2 = instance
+        .physical_device_features_1_2(&physical_device)
+        .ok_or(BortExampleError::DescriptorIndexingNotSupported)?;
+    let descriptor_indexing_supported = features_1_2.runtime_descriptor_array == vk::TRUE
+        && features_1_2.descriptor_binding_partially_bound == vk::TRUE
+        && features_1_2.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+        && features_1_2.descriptor_binding_storage_buffer_update_after_bind == vk::TRUE
+        && features_1_2.descriptor_binding_update_unused_while_pending == vk::TRUE
+        && features_1_2.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+        && features_1_2.shader_storage_buffer_array_non_uniform_indexing == vk::TRUE
+        && features_1_2.shader_uniform_buffer_array_non_uniform_indexing == vk::TRUE;
+    if !descriptor_indexing_supported {
+        return Err(BortExampleError::DescriptorIndexingNotSupported.into());
+    }
+
+    let null_descriptor_supported = instance
+        .physical_device_robustness_2_features(&physical_device)
+        .map(|robustness_2_features| robustness_2_features.null_descriptor == vk::TRUE)
+        .unwrap_or(false);
+    info!("null descriptor supported = {}", null_descriptor_supported);
+
+    let mut features = PhysicalDeviceFeatures::default().with_descriptor_indexing();
+    let mut extension_names = Vec::<CString>::new();
+    if null_descriptor_supported {
+        features = features.with_robustness_2(false, false, true);
+        extension_names.push(EXT_ROBUSTNESS2_NAME.to_owned());
+    }
+
+    let queue_priorities = [1.0];
+    let queue_create_info = vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(queue_family_index)
+        .queue_priorities(&queue_priorities);
+
+    let device = Arc::new(Device::new(
+        physical_device.clone(),
+        [queue_create_info],
+        features,
+        extension_names,
+        vec![],
+        debug_callback,
+    )?);
+    info!("created logical device");
+
+    let queue = Queue::new(device.clone(), queue_family_index, 0)?;
+
+    let command_pool = Arc::new(CommandPool::new(
+        device.clone(),
+        CommandPoolProperties::new_default(queue_family_index),
+    )?);
+    let memory_allocator = Arc::new(MemoryAllocator::new(device.clone())?);
+
+    // a few tiny single color textures to register over and over
+
+    let texture_allocation_info = AllocationCreateInfo {
+        required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        ..Default::default()
+    };
+    let mut texture_views = Vec::<Arc<dyn ImageViewAccess>>::new();
+    for color in TEXTURE_COLORS {
+        let image_properties = ImageProperties::new_default(
+            vk::Format::R8G8B8A8_UNORM,
+            ImageDimensions::new_2d(1, 1),
+            vk::ImageUsageFlags::SAMPLED,
+        );
+        let image = Arc::new(Image::new_with_data(
+            memory_allocator.clone(),
+            image_properties.clone(),
+            texture_allocation_info.clone(),
+            &command_pool,
+            &queue,
+            &color,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )?);
+        let image_view = ImageView::new(
+            image,
+            ImageViewProperties::from_image_properties_default(&image_properties),
+        )?;
+        texture_views.push(Arc::new(image_view));
+    }
+    info!("created {} textures", texture_views.len());
+
+    let sampler = Arc::new(Sampler::new(device.clone(), SamplerProperties::default())?);
+
+    let mut material_system = MaterialDescriptorSystem::new(
+        device.clone(),
+        &physical_device,
+        memory_allocator.clone(),
+        allocation_info_cpu_accessible(),
+        MAX_TEXTURES,
+        MAX_MATERIALS,
+        sampler,
+        texture_views[0].clone(),
+        null_descriptor_supported,
+    )?;
+    info!("created material descriptor system");
+
+    let stale_handles_caught = stress_test(&mut material_system, &texture_views)?;
+    info!(
+        "stress test done: {} iterations, {} stale handles caught, {} live textures, {} live materials",
+        STRESS_ITERATIONS,
+        stale_handles_caught,
+        material_system.texture_count(),
+        material_system.material_count()
+    );
+
+    // bind the set once per material then update the texture array after bind (allowed thanks
+    // to UPDATE_AFTER_BIND) before submitting
+
+    let pipeline_layout = PipelineLayout::new(
+        device.clone(),
+        PipelineLayoutProperties::new(
+            vec![
+                material_system.texture_descriptor_set().layout().clone(),
+                material_system.material_descriptor_set().layout().clone(),
+            ],
+            Vec::new(),
+        ),
+    )?;
+
+    let material = material_system.create_material([1.0; 4], None)?;
+    let command_buffer = CommandBuffer::new(command_pool.clone(), vk::CommandBufferLevel::PRIMARY)?;
+    command_buffer.begin(
+        &vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+    )?;
+    command_buffer.bind_descriptor_sets(
+        vk::PipelineBindPoint::GRAPHICS,
+        &pipeline_layout,
+        TEXTURE_SET,
+        [material_system.texture_descriptor_set()],
+        &[],
+    );
+    command_buffer.bind_descriptor_sets(
+        vk::PipelineBindPoint::GRAPHICS,
+        &pipeline_layout,
+        MATERIAL_SET,
+        [material_system.material_descriptor_set()],
+        &[material_system.dynamic_offset(material)?],
+    );
+    let late_texture = material_system.register_texture(texture_views[1].clone())?;
+    material_system.update_material(material, [0.5, 0.5, 0.5, 1.0], Some(late_texture))?;
+    command_buffer.end()?;
+
+    let fence = Fence::new_unsignalled(device.clone())?;
+    let submit_command_buffers = [command_buffer.handle()];
+    let submit_info = vk::SubmitInfo::default().command_buffers(&submit_command_buffers);
+    queue.submit(&[submit_info], Some(&fence))?;
+    fence.wait(FENCE_TIMEOUT)?;
+    info!("submitted command buffer with descriptor set updated after bind");
+
+    // the material outlives its texture but the stale texture handle is caught
+    material_system.unregister_texture(late_texture)?;
+    match material_system.material_texture(material) {
+        Err(MaterialSystemError::StaleHandle { .. }) => {}
+        Ok(_) => return Err(BortExampleError::StaleHandleAccepted)?,
+        Err(e) => return Err(e)?,
+    }
+
+    device.wait_idle()?;
+    info!("bindless materials example finished successfully");
+
+    Ok(())
+}
+
+/// Randomly registers/unregisters textures and creates/destroys materials, checking that every
+/// handle to a freed slot is rejected even after the slot has been recycled. Returns the number
+/// of stale handles caught.
+fn stress_test(
+    material_system: &mut MaterialDescriptorSystem,
+    texture_views: &[Arc<dyn ImageViewAccess>],
+) -> Result<usize, Box<dyn Error>> {
+    let mut rng = XorShift32(0x2545_f491);
+    let mut live_textures = Vec::<TextureHandle>::new();
+    let mut live_materials = Vec::<MaterialHandle>::new();
+    let mut dead_textures = Vec::<TextureHandle>::new();
+    let mut dead_materials = Vec::<MaterialHandle>::new();
+    let mut stale_handles_caught = 0_usize;
+
+    for _ in 0..STRESS_ITERATIONS {
+        match rng.next() % 6 {
+            0 | 1 => {
+                let texture_view = texture_views[rng.next() as usize % texture_views.len()].clone();
+                match material_system.register_texture(texture_view) {
+                    Ok(texture) => live_textures.push(texture),
+                    Err(MaterialSystemError::OutOfSlots { .. }) => {}
+                    Err(e) => return Err(e)?,
+                }
+            }
+            2 if !live_textures.is_empty() => {
+                let texture = live_textures.swap_remove(rng.next() as usize % live_textures.len());
+                material_system.unregister_texture(texture)?;
+                dead_textures.push(texture);
+            }
+            3 | 4 => {
+                let texture = if live_textures.is_empty() || rng.next() & 1 == 0 {
+                    None
+                } else {
+                    Some(live_textures[rng.next() as usize % live_textures.len()])
+                };
+                let color = [(rng.next() % 256) as f32 / 255.0, 0.0, 0.0, 1.0];
+                match material_system.create_material(color, texture) {
+                    Ok(material) => live_materials.push(material),
+                    Err(MaterialSystemError::OutOfSlots { .. }) => {}
+                    Err(e) => return Err(e)?,
+                }
+            }
+            5 if !live_materials.is_empty() => {
+                let material =
+                    live_materials.swap_remove(rng.next() as usize % live_materials.len());
+                material_system.destroy_material(material)?;
+                dead_materials.push(material);
+            }
+            _ => {}
+        }
+
+        // every freed handle must stay stale even after its slot gets reused
+        if let Some(&texture) = dead_textures.last() {
+            match material_system.create_material([0.0; 4], Some(texture)) {
+                Err(MaterialSystemError::StaleHandle { .. }) => stale_handles_caught += 1,
+                Ok(_) => return Err(BortExampleError::StaleHandleAccepted)?,
+                Err(e) => return Err(e)?,
+            }
+        }
+        if let Some(&material) = dead_materials.last() {
+            match material_system.dynamic_offset(material) {
+                Err(MaterialSystemError::StaleHandle { .. }) => stale_handles_caught += 1,
+                Ok(_) => return Err(BortExampleError::StaleHandleAccepted)?,
+                Err(e) => return Err(e)?,
+            }
+        }
+    }
+
+    for texture in live_textures {
+        material_system.unregister_texture(texture)?;
+    }
+
+    Ok(stale_handles_caught)
+}
+
+/// Tiny deterministic pseudo random number generator so the stress test is reproducible.
+struct XorShift32(u32);
+
+impl XorShift32 {
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+/// Callback function used in debug layers
+///
+/// # Safety
+///
+/// `p_callback_data` must point to valid callback data, as provided by the validation layers.
+pub unsafe extern "system" fn log_vulkan_debug_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut std::os::raw::c_void,
+) -> vk::Bool32 {
+    let callback_data = *p_callback_data;
+
+    let message = if callback_data.p_message.is_null() {
+        Cow::from("")
+    } else {
+        CStr::from_ptr(callback_data.p_message).to_string_lossy()
+    };
+
+    match message_severity {
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
+            error!("Vulkan [{:?}]:\n{}", message_type, message);
+        }
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => {
+            warn!("Vulkan [{:?}]: {}", message_type, message);
+        }
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => {
+            info!("Vulkan [{:?}]: {}", message_type, message);
+        }
+        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => {
+            trace!("Vulkan [{:?}]: {}", message_type, message);
+        }
+        _ => trace!(
+            "Vulkan [{:?}] (UNKONWN SEVERITY): {}",
+            message_type,
+            message
+        ),
+    }
+
+    vk::FALSE
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone, Copy)]
+enum BortExampleError {
+    NoPhysicalDevice,
+    NoSuitableQueueFamily,
+    DescriptorIndexingNotSupported,
+    StaleHandleAccepted,
+}
+
+impl std::fmt::Display for BortExampleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoPhysicalDevice => write!(f, "no vulkan physical device available"),
+            Self::NoSuitableQueueFamily => {
+                write!(
+                    f,
+                    "no queue family was found that supports graphics operations"
+                )
+            }
+            Self::DescriptorIndexingNotSupported => write!(
+                f,
+                "the physical device doesn't support the required descriptor indexing features"
+            ),
+            Self::StaleHandleAccepted => write!(
+                f,
+                "a handle to a freed slot was accepted by the material descriptor system"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BortExampleError {}
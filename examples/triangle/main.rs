@@ -3,12 +3,13 @@ use ash::{
     vk::{self, EXT_DEBUG_UTILS_NAME, KHR_SWAPCHAIN_NAME},
 };
 use bort_vk::{
-    choose_composite_alpha, is_format_srgb, ApiVersion, ColorBlendState, CommandBuffer,
-    CommandPool, CommandPoolProperties, DebugCallback, DebugCallbackProperties, Device,
-    DeviceOwned, DynamicState, Fence, Framebuffer, FramebufferProperties, GraphicsPipeline,
-    GraphicsPipelineProperties, ImageView, ImageViewAccess, Instance, PhysicalDevice,
-    PipelineLayout, PipelineLayoutProperties, Queue, RenderPass, Semaphore, ShaderModule,
-    ShaderStage, Subpass, Surface, Swapchain, SwapchainImage, SwapchainProperties, ViewportState,
+    choose_composite_alpha, ApiVersion, ColorBlendState, CommandBuffer, CommandPool,
+    CommandPoolProperties, DebugCallback, DebugCallbackProperties, DeletionQueue, Device,
+    DeviceOwned, DynamicState, Fence, FrameOutcome, Framebuffer, FramebufferProperties,
+    GraphicsPipeline, GraphicsPipelineProperties, ImageView, ImageViewAccess, Instance, PerFrame,
+    PerSwapchainImage, PhysicalDevice, PipelineLayout, PipelineLayoutProperties, Queue, RenderPass,
+    Semaphore, ShaderModule, ShaderStage, Subpass, Surface, Swapchain, SwapchainError,
+    SwapchainImage, SwapchainImageIndex, SwapchainProperties, ViewportState,
 };
 use env_logger::Env;
 #[allow(unused_imports)]
@@ -98,15 +99,22 @@ struct TriangleExample {
     pipeline: GraphicsPipeline,
     render_pass: Arc<RenderPass>,
 
-    // per swapchain image
-    framebuffers: Vec<Arc<Framebuffer>>,
-    command_buffers: Vec<CommandBuffer>,
+    framebuffers: PerSwapchainImage<Arc<Framebuffer>>,
+    /// Signalled by a frame's submission and waited on by its present. These are per swapchain
+    /// image rather than per frame because presents have no fence to say when they're done with
+    /// the semaphore, but once an image has been acquired again its previous present has been.
+    render_finished_semaphores: PerSwapchainImage<Semaphore>,
+    frames: PerFrame<FrameResources>,
+    /// Old swapchains and their framebuffers are kept alive here after a resize until the frames
+    /// using them have completed, rather than waiting for the device to idle.
+    deletion_queue: DeletionQueue,
+}
 
-    // per frame in flight
-    image_available_semaphores: Vec<Semaphore>,
-    render_finished_semaphores: Vec<Semaphore>,
-    in_flight_fences: Vec<Fence>,
-    current_frame: usize,
+/// Resources which are reused once the frame that last used them has finished.
+struct FrameResources {
+    command_buffer: CommandBuffer,
+    image_available_semaphore: Semaphore,
+    in_flight_fence: Fence,
 }
 
 impl TriangleExample {
@@ -225,7 +233,7 @@ impl TriangleExample {
             swapchain_properties,
         )?);
         let _shaders_should_write_linear_color =
-            is_format_srgb(swapchain.properties().surface_format.format);
+            swapchain.color_space_info().shader_should_write_linear();
         info!("created swapchain");
 
         let swapchain_image_views = create_swapchain_image_views(&swapchain)?;
@@ -243,24 +251,14 @@ impl TriangleExample {
             device.clone(),
             &mut vertex_spv_file,
         )?);
-        let vert_stage = ShaderStage::new(
-            vk::ShaderStageFlags::VERTEX,
-            vert_shader,
-            CString::new("main")?,
-            None,
-        );
+        let vert_stage = ShaderStage::vertex(vert_shader)?;
 
         let mut frag_spv_file = std::io::Cursor::new(&include_bytes!("./triangle.frag.spv")[..]);
         let frag_shader = Arc::new(ShaderModule::new_from_spirv(
             device.clone(),
             &mut frag_spv_file,
         )?);
-        let frag_stage = ShaderStage::new(
-            vk::ShaderStageFlags::FRAGMENT,
-            frag_shader,
-            CString::new("main")?,
-            None,
-        );
+        let frag_stage = ShaderStage::fragment(frag_shader)?;
 
         let dynamic_state =
             DynamicState::new_default(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
@@ -276,7 +274,7 @@ impl TriangleExample {
             ..Default::default()
         };
 
-        let pipeline = GraphicsPipeline::new(
+        let pipeline = GraphicsPipeline::new_recreatable(
             pipeline_layout,
             pipeline_properties,
             &[vert_stage, frag_stage],
@@ -286,6 +284,7 @@ impl TriangleExample {
         info!("created graphics pipeline");
 
         let framebuffers = create_framebuffers(swapchain_image_views, render_pass.clone())?;
+        let render_finished_semaphores = create_render_finished_semaphores(&swapchain)?;
 
         let command_pool_properties = CommandPoolProperties {
             flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
@@ -294,21 +293,20 @@ impl TriangleExample {
         let command_pool = Arc::new(CommandPool::new(device.clone(), command_pool_properties)?);
         info!("created command pool");
 
-        let command_buffers = command_pool
-            .allocate_command_buffers(vk::CommandBufferLevel::PRIMARY, framebuffers.len() as u32)?;
-        info!("allocated command buffers");
-
-        let mut image_available_semaphores: Vec<Semaphore> = Vec::new();
-        let mut render_finished_semaphores: Vec<Semaphore> = Vec::new();
-        let mut in_flight_fences: Vec<Fence> = Vec::new();
-        for _ in 0..MAX_FRAMES_IN_FLIGHT {
-            image_available_semaphores.push(Semaphore::new(device.clone())?);
-            render_finished_semaphores.push(Semaphore::new(device.clone())?);
-            in_flight_fences.push(Fence::new_signalled(device.clone())?);
-        }
-        info!("created semaphores and fences");
-
-        let current_frame = 0_usize;
+        // command buffers are re-recorded each frame so only one per frame in flight is needed,
+        // regardless of the swapchain image count
+        let mut command_buffers = command_pool.allocate_command_buffers(
+            vk::CommandBufferLevel::PRIMARY,
+            MAX_FRAMES_IN_FLIGHT as u32,
+        )?;
+        let frames = PerFrame::try_from_fn(MAX_FRAMES_IN_FLIGHT, |_| -> VkResult<_> {
+            Ok(FrameResources {
+                command_buffer: command_buffers.pop().expect("one per frame in flight"),
+                image_available_semaphore: Semaphore::new(device.clone())?,
+                in_flight_fence: Fence::new_signalled(device.clone())?,
+            })
+        })?;
+        info!("created per-frame command buffers, acquire semaphores and fences");
 
         Ok(Self {
             window: Arc::new(window),
@@ -320,26 +318,26 @@ impl TriangleExample {
             render_pass,
 
             framebuffers,
-            command_buffers,
-
-            image_available_semaphores,
             render_finished_semaphores,
-            in_flight_fences,
-            current_frame,
+            frames,
+            deletion_queue: DeletionQueue::new(MAX_FRAMES_IN_FLIGHT),
         })
     }
 
     pub fn draw_frame(&mut self) -> Result<(), Box<dyn Error>> {
-        self.in_flight_fences[self.current_frame].wait(FENCE_TIMEOUT)?;
+        let frame = self.frames.current();
+        frame.in_flight_fence.wait(FENCE_TIMEOUT)?;
 
         let aquire_res = self.swapchain.aquire_next_image(
             FENCE_TIMEOUT,
-            Some(&self.image_available_semaphores[self.current_frame]),
+            Some(&frame.image_available_semaphore),
             None,
         );
 
         let (swapchain_image_index, is_suboptimal) = match aquire_res {
-            Ok(aquire_ret) => aquire_ret,
+            Ok((image_index, is_suboptimal)) => {
+                (SwapchainImageIndex::new(image_index), is_suboptimal)
+            }
             Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
                 return self.recreate_swapchain();
             }
@@ -349,18 +347,20 @@ impl TriangleExample {
             return self.recreate_swapchain();
         }
 
-        self.in_flight_fences[self.current_frame].reset()?;
+        // this frame slot is definitely being reused now (the early returns above retry it) so
+        // resources retired `MAX_FRAMES_IN_FLIGHT` reuses ago are no longer in use
+        self.deletion_queue.next_frame();
+        frame.in_flight_fence.reset()?;
 
-        self.command_buffers[self.current_frame].reset(vk::CommandBufferResetFlags::empty())?;
-        self.record_commands(
-            &self.command_buffers[self.current_frame],
-            swapchain_image_index as usize,
-        )?;
+        frame
+            .command_buffer
+            .reset(vk::CommandBufferResetFlags::empty())?;
+        self.record_commands(&frame.command_buffer, swapchain_image_index)?;
 
-        let wait_semaphores = [self.image_available_semaphores[self.current_frame].handle()];
+        let wait_semaphores = [frame.image_available_semaphore.handle()];
         let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [self.render_finished_semaphores[self.current_frame].handle()];
-        let submit_command_buffers = [self.command_buffers[self.current_frame].handle()];
+        let signal_semaphores = [self.render_finished_semaphores[swapchain_image_index].handle()];
+        let submit_command_buffers = [frame.command_buffer.handle()];
 
         let submit_info = vk::SubmitInfo::default()
             .wait_semaphores(&wait_semaphores)
@@ -368,13 +368,11 @@ impl TriangleExample {
             .signal_semaphores(&signal_semaphores)
             .command_buffers(&submit_command_buffers);
 
-        self.queue.submit(
-            &[submit_info],
-            Some(&self.in_flight_fences[self.current_frame]),
-        )?;
+        self.queue
+            .submit(&[submit_info], Some(&frame.in_flight_fence))?;
 
         let present_swapchains = [self.swapchain.handle()];
-        let present_indices = [swapchain_image_index];
+        let present_indices = [swapchain_image_index.value()];
         let present_info = vk::PresentInfoKHR::default()
             .wait_semaphores(&signal_semaphores)
             .swapchains(&present_swapchains)
@@ -382,15 +380,15 @@ impl TriangleExample {
 
         let present_res = self.swapchain.queue_present(&self.queue, &present_info);
 
-        match present_res {
-            Ok(false) => (),
-            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) | Err(vk::Result::SUBOPTIMAL_KHR) | Ok(true) => {
-                self.recreate_swapchain()?
-            }
-            Err(e) => return Err(e)?,
+        match FrameOutcome::from_present_result(present_res) {
+            FrameOutcome::Presented | FrameOutcome::Suspended => (),
+            FrameOutcome::RecreateSwapchain => self.recreate_swapchain()?,
+            FrameOutcome::RecreateSurface => return Err("surface lost")?,
+            FrameOutcome::DeviceLost => return Err("device lost")?,
+            FrameOutcome::Error(e) => return Err(e)?,
         };
 
-        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+        self.frames.advance();
 
         Ok(())
     }
@@ -398,14 +396,14 @@ impl TriangleExample {
     fn record_commands(
         &self,
         command_buffer: &CommandBuffer,
-        swapchain_image_index: usize,
+        swapchain_image_index: SwapchainImageIndex,
     ) -> VkResult<()> {
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::default();
         command_buffer.begin(&command_buffer_begin_info)?;
 
         let clear_values = [vk::ClearValue::default()];
-        let render_extent = self.framebuffers[swapchain_image_index].whole_rect();
-        let viewport = self.framebuffers[self.current_frame].whole_viewport();
+        let render_extent = self.swapchain.whole_rect();
+        let viewport = self.swapchain.whole_viewport();
 
         let render_pass_begin_info = vk::RenderPassBeginInfo::default()
             .render_pass(self.render_pass.handle())
@@ -429,20 +427,50 @@ impl TriangleExample {
     }
 
     pub fn recreate_swapchain(&mut self) -> Result<(), Box<dyn Error>> {
+        let swapchain_properties =
+            match swapchain_properties(&self.surface, self.queue.device(), &self.window) {
+                Ok(swapchain_properties) => swapchain_properties,
+                // minimized window. keep the old swapchain and try again next frame
+                Err(e)
+                    if e.downcast_ref::<SwapchainError>()
+                        .is_some_and(SwapchainError::is_zero_extent) =>
+                {
+                    return Ok(())
+                }
+                Err(e) => return Err(e),
+            };
         info!("recreating swapchain...");
 
-        self.queue.device().wait_idle()?;
-        self.framebuffers.clear();
-
-        let swapchain_properties =
-            swapchain_properties(&self.surface, self.queue.device(), &self.window)?;
+        // no need to wait for the device to idle. the old swapchain is retired by the new one and
+        // it, along with anything rendering to its images, is dropped by the deletion queue once
+        // the frames using them have completed
+        let old_framebuffers = std::mem::take(&mut self.framebuffers);
+        let old_render_finished_semaphores = std::mem::take(&mut self.render_finished_semaphores);
         let surface_format = swapchain_properties.surface_format;
+        let format_changed = surface_format != self.swapchain.properties().surface_format;
+
+        let mut retired_render_pass = None;
+        if format_changed {
+            let render_pass =
+                create_render_pass(self.render_pass.device().clone(), surface_format)?;
+            let pipeline = self.pipeline.recreate_for_render_pass(&render_pass, None)?;
+            let old_pipeline = std::mem::replace(&mut self.pipeline, pipeline);
+            let old_render_pass = std::mem::replace(&mut self.render_pass, render_pass);
+            retired_render_pass = Some((old_pipeline, old_render_pass));
+        }
 
-        self.swapchain = self.swapchain.recreate_replace(swapchain_properties)?;
+        self.swapchain = self.swapchain.recreate_deferred(
+            swapchain_properties,
+            &mut self.deletion_queue,
+            (
+                old_framebuffers,
+                old_render_finished_semaphores,
+                retired_render_pass,
+            ),
+        )?;
         let swapchain_image_views = create_swapchain_image_views(&self.swapchain)?;
-
-        self.render_pass = create_render_pass(self.render_pass.device().clone(), surface_format)?;
         self.framebuffers = create_framebuffers(swapchain_image_views, self.render_pass.clone())?;
+        self.render_finished_semaphores = create_render_finished_semaphores(&self.swapchain)?;
 
         Ok(())
     }
@@ -452,10 +480,16 @@ impl Drop for TriangleExample {
     fn drop(&mut self) {
         info!("dropping main class...");
 
-        let wait_res = self.queue.device().wait_idle();
+        // wait for this example's frames and presents rather than the whole device
+        let wait_res = self.swapchain.wait_for_teardown(
+            self.frames.iter().map(|frame| &frame.in_flight_fence),
+            &self.queue,
+            FENCE_TIMEOUT,
+        );
         if let Err(e) = wait_res {
             error!("{}", e);
         }
+        self.deletion_queue.flush();
     }
 }
 
@@ -550,7 +584,7 @@ fn create_swapchain_image_views(
 fn create_framebuffers(
     swapchain_image_views: Vec<Arc<ImageView<SwapchainImage>>>,
     render_pass: Arc<RenderPass>,
-) -> Result<Vec<Arc<Framebuffer>>, Box<dyn Error>> {
+) -> Result<PerSwapchainImage<Arc<Framebuffer>>, Box<dyn Error>> {
     let framebuffers = swapchain_image_views
         .into_iter()
         .map(|swapchain_image_view| {
@@ -567,7 +601,14 @@ fn create_framebuffers(
         .collect::<VkResult<Vec<_>>>()?;
 
     info!("created {} framebuffers", framebuffers.len());
-    Ok(framebuffers)
+    Ok(PerSwapchainImage::from_vec(framebuffers))
+}
+
+fn create_render_finished_semaphores(
+    swapchain: &Swapchain,
+) -> VkResult<PerSwapchainImage<Semaphore>> {
+    let image_count = swapchain.swapchain_images().len() as u32;
+    PerSwapchainImage::try_from_fn(image_count, |_| Semaphore::new(swapchain.device().clone()))
 }
 
 /// # Safety
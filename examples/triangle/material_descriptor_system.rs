@@ -0,0 +1,526 @@
+//! Example-level bindless material system. A global `UPDATE_AFTER_BIND` descriptor set holds a
+//! partially bound array of combined image samplers (indexed by texture slot in the shader) and a
+//! second, regular, descriptor set holds a dynamic uniform buffer binding where each material gets
+//! its own slice (selected with a dynamic offset). Dynamic uniform buffers aren't allowed in
+//! `UPDATE_AFTER_BIND_POOL` layouts, hence the separate set.
+//!
+//! Texture and material slots are recycled and each slot has a generation counter which is
+//! incremented every time the slot is freed, so stale handles are caught instead of silently
+//! referring to whatever now occupies the slot.
+
+use ash::vk;
+use bort_vk::{
+    AllocationAccess, AllocationError, AllocatorAccess, Buffer, BufferProperties, DescriptorPool,
+    DescriptorPoolProperties, DescriptorSet, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutError, DescriptorSetLayoutProperties, Device, ImageViewAccess, MemoryError,
+    PhysicalDevice, Sampler,
+};
+use bort_vma::AllocationCreateInfo;
+use bytemuck::{Pod, Zeroable};
+use std::{error, fmt, sync::Arc};
+
+/// Set index of [`MaterialDescriptorSystem::texture_descriptor_set`].
+pub const TEXTURE_SET: u32 = 0;
+/// Set index of [`MaterialDescriptorSystem::material_descriptor_set`].
+pub const MATERIAL_SET: u32 = 1;
+
+pub const TEXTURE_ARRAY_BINDING: u32 = 0;
+pub const MATERIAL_UNIFORM_BINDING: u32 = 0;
+
+/// Sentinel texture index written to material uniforms that don't reference a texture.
+pub const NO_TEXTURE_INDEX: u32 = u32::MAX;
+
+/// Per-material uniform data. The shader indexes the texture array with `base_color_texture`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaterialUniforms {
+    pub base_color: [f32; 4],
+    pub base_color_texture: u32,
+    pub _padding: [u32; 3],
+}
+
+// safe because `MaterialUniforms` is `repr(C)` with no implicit padding
+unsafe impl Zeroable for MaterialUniforms {}
+unsafe impl Pod for MaterialUniforms {}
+
+// ~~ Slot Allocator ~~
+
+/// Identifies a slot in a [`SlotAllocator`]. Only valid while the slot generation matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SlotHandle {
+    pub index: u32,
+    pub generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Fixed capacity slot storage with index recycling and generation counters.
+pub struct SlotAllocator<T> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<u32>,
+    capacity: u32,
+}
+
+impl<T> SlotAllocator<T> {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+            capacity,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Result<SlotHandle, MaterialSystemError> {
+        if let Some(index) = self.free_indices.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            return Ok(SlotHandle {
+                index,
+                generation: slot.generation,
+            });
+        }
+
+        let index = self.slots.len() as u32;
+        if index >= self.capacity {
+            return Err(MaterialSystemError::OutOfSlots {
+                capacity: self.capacity,
+            });
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+        Ok(SlotHandle {
+            index,
+            generation: 0,
+        })
+    }
+
+    /// Frees the slot and increments its generation so `handle` (and any copies) become stale.
+    pub fn remove(&mut self, handle: SlotHandle) -> Result<T, MaterialSystemError> {
+        self.check_handle(handle)?;
+        let slot = &mut self.slots[handle.index as usize];
+        let value = slot.value.take().expect("checked by check_handle");
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_indices.push(handle.index);
+        Ok(value)
+    }
+
+    pub fn get(&self, handle: SlotHandle) -> Result<&T, MaterialSystemError> {
+        self.check_handle(handle)?;
+        Ok(self.slots[handle.index as usize]
+            .value
+            .as_ref()
+            .expect("checked by check_handle"))
+    }
+
+    pub fn get_mut(&mut self, handle: SlotHandle) -> Result<&mut T, MaterialSystemError> {
+        self.check_handle(handle)?;
+        Ok(self.slots[handle.index as usize]
+            .value
+            .as_mut()
+            .expect("checked by check_handle"))
+    }
+
+    fn check_handle(&self, handle: SlotHandle) -> Result<(), MaterialSystemError> {
+        let slot = self
+            .slots
+            .get(handle.index as usize)
+            .ok_or(MaterialSystemError::InvalidIndex(handle.index))?;
+        if slot.generation != handle.generation || slot.value.is_none() {
+            return Err(MaterialSystemError::StaleHandle {
+                handle,
+                current_generation: slot.generation,
+            });
+        }
+        Ok(())
+    }
+
+    /// Number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_indices.len()
+    }
+}
+
+// ~~ Material Descriptor System ~~
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub SlotHandle);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(pub SlotHandle);
+
+struct MaterialSlot {
+    texture: Option<TextureHandle>,
+}
+
+/// Manages a global bindless descriptor set with a texture array and a descriptor set with
+/// per-material uniform slices.
+///
+/// Freed texture slots are written with a null descriptor if the `VK_EXT_robustness2`
+/// `nullDescriptor` feature is enabled, otherwise with `fallback_texture`, so a shader reading a
+/// stale index never touches a destroyed image view. The caller is responsible for not freeing
+/// slots used by command buffers that are still pending.
+pub struct MaterialDescriptorSystem {
+    texture_descriptor_set: DescriptorSet,
+    material_descriptor_set: DescriptorSet,
+    uniform_buffer: Buffer,
+    uniform_slice_stride: vk::DeviceSize,
+    sampler: Arc<Sampler>,
+    fallback_texture: Arc<dyn ImageViewAccess>,
+    null_descriptor_enabled: bool,
+
+    textures: SlotAllocator<Arc<dyn ImageViewAccess>>,
+    materials: SlotAllocator<MaterialSlot>,
+
+    // dependencies
+    device: Arc<Device>,
+}
+
+impl MaterialDescriptorSystem {
+    /// `uniform_allocation_info` must result in host visible memory.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: Arc<Device>,
+        physical_device: &PhysicalDevice,
+        alloc_access: Arc<dyn AllocatorAccess>,
+        uniform_allocation_info: AllocationCreateInfo,
+        max_textures: u32,
+        max_materials: u32,
+        sampler: Arc<Sampler>,
+        fallback_texture: Arc<dyn ImageViewAccess>,
+        null_descriptor_enabled: bool,
+    ) -> Result<Self, MaterialSystemError> {
+        let texture_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_update_after_bind(vec![
+                    DescriptorSetLayoutBinding::new_bindless(
+                        TEXTURE_ARRAY_BINDING,
+                        vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        max_textures,
+                        vk::ShaderStageFlags::FRAGMENT,
+                    ),
+                ]),
+            )
+            .map_err(MaterialSystemError::DescriptorSetLayoutCreation)?,
+        );
+        let material_set_layout = Arc::new(
+            DescriptorSetLayout::new(
+                device.clone(),
+                DescriptorSetLayoutProperties::new_default(vec![DescriptorSetLayoutBinding {
+                    binding: MATERIAL_UNIFORM_BINDING,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                    descriptor_count: 1,
+                    stage_flags: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    ..Default::default()
+                }]),
+            )
+            .map_err(MaterialSystemError::DescriptorSetLayoutCreation)?,
+        );
+
+        let texture_pool = Arc::new(
+            DescriptorPool::new(
+                device.clone(),
+                DescriptorPoolProperties::new_update_after_bind(
+                    1,
+                    vec![vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: max_textures,
+                    }],
+                ),
+            )
+            .map_err(MaterialSystemError::DescriptorPoolCreation)?,
+        );
+        let material_pool = Arc::new(
+            DescriptorPool::new(
+                device.clone(),
+                DescriptorPoolProperties::new_default(
+                    1,
+                    vec![vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                        descriptor_count: 1,
+                    }],
+                ),
+            )
+            .map_err(MaterialSystemError::DescriptorPoolCreation)?,
+        );
+
+        let texture_descriptor_set = texture_pool
+            .allocate_descriptor_set(texture_set_layout)
+            .map_err(MaterialSystemError::DescriptorSetAllocation)?;
+        let material_descriptor_set = material_pool
+            .allocate_descriptor_set(material_set_layout)
+            .map_err(MaterialSystemError::DescriptorSetAllocation)?;
+
+        let min_alignment = physical_device
+            .properties()
+            .limits
+            .min_uniform_buffer_offset_alignment
+            .max(1);
+        let uniform_size = std::mem::size_of::<MaterialUniforms>() as vk::DeviceSize;
+        let uniform_slice_stride = uniform_size.div_ceil(min_alignment) * min_alignment;
+
+        let uniform_buffer = Buffer::new(
+            alloc_access,
+            BufferProperties::new_default(
+                uniform_slice_stride * max_materials as vk::DeviceSize,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+            ),
+            uniform_allocation_info,
+        )
+        .map_err(MaterialSystemError::UniformBufferCreation)?;
+
+        let buffer_info = [vk::DescriptorBufferInfo {
+            buffer: uniform_buffer.handle(),
+            offset: 0,
+            range: uniform_size,
+        }];
+        let uniform_write = vk::WriteDescriptorSet::default()
+            .dst_set(material_descriptor_set.handle())
+            .dst_binding(MATERIAL_UNIFORM_BINDING)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .buffer_info(&buffer_info);
+        device.update_descriptor_sets([uniform_write], []);
+
+        Ok(Self {
+            texture_descriptor_set,
+            material_descriptor_set,
+            uniform_buffer,
+            uniform_slice_stride,
+            sampler,
+            fallback_texture,
+            null_descriptor_enabled,
+
+            textures: SlotAllocator::new(max_textures),
+            materials: SlotAllocator::new(max_materials),
+
+            device,
+        })
+    }
+
+    /// Writes `image_view` to a free slot of the texture array. The view is expected to be in
+    /// `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn register_texture(
+        &mut self,
+        image_view: Arc<dyn ImageViewAccess>,
+    ) -> Result<TextureHandle, MaterialSystemError> {
+        let image_view_handle = image_view.handle();
+        let slot_handle = self.textures.insert(image_view)?;
+        self.write_texture_descriptor(slot_handle.index, image_view_handle);
+        Ok(TextureHandle(slot_handle))
+    }
+
+    /// Frees the texture slot and overwrites its descriptor with a null descriptor (or the
+    /// fallback texture). Returns the image view that was in the slot.
+    pub fn unregister_texture(
+        &mut self,
+        texture: TextureHandle,
+    ) -> Result<Arc<dyn ImageViewAccess>, MaterialSystemError> {
+        let image_view = self.textures.remove(texture.0)?;
+        let replacement_handle = if self.null_descriptor_enabled {
+            vk::ImageView::null()
+        } else {
+            self.fallback_texture.handle()
+        };
+        self.write_texture_descriptor(texture.0.index, replacement_handle);
+        Ok(image_view)
+    }
+
+    pub fn create_material(
+        &mut self,
+        base_color: [f32; 4],
+        texture: Option<TextureHandle>,
+    ) -> Result<MaterialHandle, MaterialSystemError> {
+        let base_color_texture = self.texture_index(texture)?;
+        let slot_handle = self.materials.insert(MaterialSlot { texture })?;
+        let material = MaterialHandle(slot_handle);
+
+        let uniforms = MaterialUniforms {
+            base_color,
+            base_color_texture,
+            ..Default::default()
+        };
+        if let Err(e) = self.write_uniforms(material, uniforms) {
+            let _ = self.materials.remove(slot_handle);
+            return Err(e);
+        }
+
+        Ok(material)
+    }
+
+    /// Note: don't call this while command buffers using `material` are pending.
+    pub fn update_material(
+        &mut self,
+        material: MaterialHandle,
+        base_color: [f32; 4],
+        texture: Option<TextureHandle>,
+    ) -> Result<(), MaterialSystemError> {
+        let base_color_texture = self.texture_index(texture)?;
+        self.materials.get_mut(material.0)?.texture = texture;
+
+        let uniforms = MaterialUniforms {
+            base_color,
+            base_color_texture,
+            ..Default::default()
+        };
+        self.write_uniforms(material, uniforms)
+    }
+
+    pub fn destroy_material(
+        &mut self,
+        material: MaterialHandle,
+    ) -> Result<(), MaterialSystemError> {
+        self.materials.remove(material.0)?;
+        Ok(())
+    }
+
+    /// The dynamic offset to pass to `vkCmdBindDescriptorSets` to select `material`'s uniforms.
+    pub fn dynamic_offset(&self, material: MaterialHandle) -> Result<u32, MaterialSystemError> {
+        self.materials.get(material.0)?;
+        Ok((material.0.index as vk::DeviceSize * self.uniform_slice_stride) as u32)
+    }
+
+    /// The texture a material was created with. Fails if the texture has since been
+    /// unregistered.
+    pub fn material_texture(
+        &self,
+        material: MaterialHandle,
+    ) -> Result<Option<TextureHandle>, MaterialSystemError> {
+        let texture = self.materials.get(material.0)?.texture;
+        if let Some(texture) = texture {
+            self.textures.get(texture.0)?;
+        }
+        Ok(texture)
+    }
+
+    fn texture_index(&self, texture: Option<TextureHandle>) -> Result<u32, MaterialSystemError> {
+        match texture {
+            Some(texture) => {
+                self.textures.get(texture.0)?;
+                Ok(texture.0.index)
+            }
+            None => Ok(NO_TEXTURE_INDEX),
+        }
+    }
+
+    fn write_uniforms(
+        &mut self,
+        material: MaterialHandle,
+        uniforms: MaterialUniforms,
+    ) -> Result<(), MaterialSystemError> {
+        let offset = material.0.index as vk::DeviceSize * self.uniform_slice_stride;
+        self.uniform_buffer
+            .write_into_bytes(uniforms, offset as usize)
+            .map_err(MaterialSystemError::UniformWrite)
+    }
+
+    fn write_texture_descriptor(&self, array_element: u32, image_view_handle: vk::ImageView) {
+        let image_info = [vk::DescriptorImageInfo {
+            sampler: self.sampler.handle(),
+            image_view: image_view_handle,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }];
+        let texture_write = vk::WriteDescriptorSet::default()
+            .dst_set(self.texture_descriptor_set.handle())
+            .dst_binding(TEXTURE_ARRAY_BINDING)
+            .dst_array_element(array_element)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        self.device.update_descriptor_sets([texture_write], []);
+    }
+
+    // Getters
+
+    /// `UPDATE_AFTER_BIND` set with the texture array. Bind at [`TEXTURE_SET`].
+    #[inline]
+    pub fn texture_descriptor_set(&self) -> &DescriptorSet {
+        &self.texture_descriptor_set
+    }
+
+    /// Set with the material uniforms. Bind at [`MATERIAL_SET`] with [`Self::dynamic_offset`].
+    #[inline]
+    pub fn material_descriptor_set(&self) -> &DescriptorSet {
+        &self.material_descriptor_set
+    }
+
+    #[inline]
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    #[inline]
+    pub fn material_count(&self) -> usize {
+        self.materials.len()
+    }
+}
+
+// ~~ Errors ~~
+
+#[derive(Debug, Clone)]
+pub enum MaterialSystemError {
+    DescriptorSetLayoutCreation(DescriptorSetLayoutError),
+    DescriptorPoolCreation(vk::Result),
+    DescriptorSetAllocation(vk::Result),
+    UniformBufferCreation(AllocationError),
+    UniformWrite(MemoryError),
+    OutOfSlots {
+        capacity: u32,
+    },
+    InvalidIndex(u32),
+    StaleHandle {
+        handle: SlotHandle,
+        current_generation: u32,
+    },
+}
+
+impl fmt::Display for MaterialSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DescriptorSetLayoutCreation(e) => {
+                write!(f, "failed to create bindless descriptor set layout: {}", e)
+            }
+            Self::DescriptorPoolCreation(e) => {
+                write!(f, "failed to create bindless descriptor pool: {}", e)
+            }
+            Self::DescriptorSetAllocation(e) => {
+                write!(f, "failed to allocate bindless descriptor set: {}", e)
+            }
+            Self::UniformBufferCreation(e) => {
+                write!(f, "failed to create material uniform buffer: {}", e)
+            }
+            Self::UniformWrite(e) => write!(f, "failed to write material uniforms: {}", e),
+            Self::OutOfSlots { capacity } => {
+                write!(f, "all {} slots are in use", capacity)
+            }
+            Self::InvalidIndex(index) => write!(f, "slot index {} was never allocated", index),
+            Self::StaleHandle {
+                handle,
+                current_generation,
+            } => write!(
+                f,
+                "stale handle for slot {}: handle generation = {}, current generation = {}",
+                handle.index, handle.generation, current_generation
+            ),
+        }
+    }
+}
+
+impl error::Error for MaterialSystemError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::DescriptorSetLayoutCreation(e) => Some(e),
+            Self::DescriptorPoolCreation(e) => Some(e),
+            Self::DescriptorSetAllocation(e) => Some(e),
+            Self::UniformBufferCreation(e) => Some(e),
+            Self::UniformWrite(e) => Some(e),
+            Self::OutOfSlots { .. } => None,
+            Self::InvalidIndex(_) => None,
+            Self::StaleHandle { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Headless example showing dedicated memory pools: a high priority pool for render targets and
+//! a fixed budget pool for streaming buffers. Fills the streaming pool until it is exhausted,
+//! then frees a buffer and checks the space can be reused.
+
+use ash::vk;
+use bort_vk::{
+    allocation_info_device_local, ApiVersion, Buffer, BufferProperties, Device, Image,
+    ImageDimensions, ImageProperties, Instance, MemoryAllocator, MemoryPool, MemoryPoolPropeties,
+    PhysicalDevice, PhysicalDeviceFeatures,
+};
+use env_logger::Env;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::{error::Error, sync::Arc};
+
+const MAX_API_VERSION: ApiVersion = ApiVersion { major: 1, minor: 3 };
+const STREAMING_BLOCK_SIZE: vk::DeviceSize = 1 << 20;
+const STREAMING_BLOCK_COUNT: usize = 2;
+const STREAMING_BUFFER_SIZE: vk::DeviceSize = 256 << 10;
+const RENDER_TARGET_SIZE: [u32; 2] = [1920, 1080];
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = unsafe { ash::Entry::load() }?;
+    Ok(Arc::new(entry))
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = ash_molten::load();
+    Ok(Arc::new(entry))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let log_env = Env::default()
+        .filter_or("MY_LOG_LEVEL", "debug")
+        .write_style_or("MY_LOG_STYLE", "always");
+    env_logger::init_from_env(log_env);
+    info!("starting memory pools example...");
+
+    let entry = create_entry()?;
+    let instance = Arc::new(Instance::new(entry, MAX_API_VERSION, vec![], vec![])?);
+
+    let physical_device_handles = instance.enumerate_physical_devices()?;
+    let physical_device_handle = physical_device_handles
+        .first()
+        .ok_or(BortExampleError::NoPhysicalDevice)?;
+    let physical_device = Arc::new(PhysicalDevice::new(
+        instance.clone(),
+        *physical_device_handle,
+    )?);
+    info!("chosen physical device: {}", physical_device.name());
+
+    let queue_priorities = [1.0];
+    let queue_create_info = vk::DeviceQueueCreateInfo::default()
+        .queue_family_index(0)
+        .queue_priorities(&queue_priorities);
+    let device = Arc::new(Device::new(
+        physical_device,
+        [queue_create_info],
+        PhysicalDeviceFeatures::default(),
+        vec![],
+        vec![],
+        None,
+    )?);
+    let memory_allocator = Arc::new(MemoryAllocator::new(device)?);
+
+    // render targets get their own pool so streaming can't starve them
+
+    let render_target_properties = ImageProperties::new_default(
+        vk::Format::R8G8B8A8_UNORM,
+        ImageDimensions::new_2d(RENDER_TARGET_SIZE[0], RENDER_TARGET_SIZE[1]),
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+    );
+    let render_target_pool = Arc::new(MemoryPool::new_for_images(
+        memory_allocator.clone(),
+        &render_target_properties,
+        &allocation_info_device_local(),
+        MemoryPoolPropeties::new_high_priority(),
+    )?);
+    let _render_target = Image::new(
+        render_target_pool.clone(),
+        render_target_properties,
+        allocation_info_device_local(),
+    )?;
+    info!(
+        "render target pool: {} bytes allocated",
+        render_target_pool.statistics()?.allocation_bytes
+    );
+
+    // streaming buffers get a fixed budget
+
+    let streaming_properties = BufferProperties::new_default(
+        STREAMING_BUFFER_SIZE,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+    );
+    let streaming_pool = Arc::new(MemoryPool::new_for_buffers(
+        memory_allocator,
+        &streaming_properties,
+        &allocation_info_device_local(),
+        MemoryPoolPropeties::new_fixed_budget(STREAMING_BLOCK_SIZE, STREAMING_BLOCK_COUNT),
+    )?);
+
+    let mut streaming_buffers = Vec::new();
+    let exhaustion_error = loop {
+        match Buffer::new(
+            streaming_pool.clone(),
+            streaming_properties.clone(),
+            allocation_info_device_local(),
+        ) {
+            Ok(buffer) => streaming_buffers.push(buffer),
+            Err(e) => break e,
+        }
+    };
+    info!(
+        "streaming pool exhausted after {} buffers: {}",
+        streaming_buffers.len(),
+        exhaustion_error
+    );
+
+    let budget = streaming_pool.properties().budget().unwrap_or_default();
+    let max_buffer_count = (budget / STREAMING_BUFFER_SIZE) as usize;
+    if exhaustion_error.result != vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+        || streaming_buffers.is_empty()
+        || streaming_buffers.len() > max_buffer_count
+    {
+        return Err(BortExampleError::UnexpectedPoolExhaustion.into());
+    }
+
+    // freeing a buffer makes room for another
+
+    streaming_buffers.pop();
+    streaming_buffers.push(Buffer::new(
+        streaming_pool.clone(),
+        streaming_properties,
+        allocation_info_device_local(),
+    )?);
+    info!(
+        "streaming pool: {} bytes allocated in {} blocks",
+        streaming_pool.statistics()?.allocation_bytes,
+        streaming_pool.statistics()?.block_count
+    );
+
+    info!("memory pools example completed");
+    Ok(())
+}
+
+#[derive(Debug)]
+enum BortExampleError {
+    NoPhysicalDevice,
+    UnexpectedPoolExhaustion,
+}
+
+impl std::fmt::Display for BortExampleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoPhysicalDevice => write!(f, "no vulkan physical device available"),
+            Self::UnexpectedPoolExhaustion => write!(
+                f,
+                "the fixed budget pool didn't run out of memory as expected"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BortExampleError {}
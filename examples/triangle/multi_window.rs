@@ -0,0 +1,576 @@
+//! Renders a triangle to two windows which share one instance, device and queue. Each window
+//! owns its own surface, swapchain, render pass, pipeline and per-frame resources so it can be
+//! resized or closed independently of the other.
+
+use ash::{prelude::VkResult, vk};
+use bort_vk::{
+    windowed::{self, WindowSurface, WindowedContext, WindowedInitConfig},
+    ColorBlendState, CommandBuffer, CommandPool, CommandPoolProperties, DeletionQueue, DeviceError,
+    DeviceOwned, DynamicState, Fence, FrameOutcome, Framebuffer, FramebufferProperties,
+    GraphicsPipeline, GraphicsPipelineProperties, ImageView, ImageViewAccess, PerFrame,
+    PerSwapchainImage, PipelineLayout, PipelineLayoutProperties, PresentQueueSharing, Queue,
+    RenderPass, Semaphore, ShaderModule, ShaderStage, Subpass, Surface, Swapchain, SwapchainImage,
+    SwapchainImageIndex, SwapchainProperties, ViewportState,
+};
+use env_logger::Env;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::{error::Error, sync::Arc};
+use winit::{
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::EventLoop,
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowBuilder, WindowId},
+};
+
+const TITLE: &str = "Multi-window (bort example)";
+const WINDOW_COUNT: usize = 2;
+const DEFAULT_WINDOW_SIZE: [u32; 2] = [500, 400];
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+const FENCE_TIMEOUT: u64 = 1_000_000_000;
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = unsafe { ash::Entry::load() }?;
+    Ok(Arc::new(entry))
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = ash_molten::load();
+    Ok(Arc::new(entry))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let log_env = Env::default()
+        .filter_or("MY_LOG_LEVEL", "debug")
+        .write_style_or("MY_LOG_STYLE", "always");
+    env_logger::init_from_env(log_env);
+    info!("starting multi-window example...");
+
+    let event_loop = EventLoop::new()?;
+    let windows = (0..WINDOW_COUNT)
+        .map(|window_number| {
+            WindowBuilder::new()
+                .with_title(format!("{} {}", TITLE, window_number + 1))
+                .with_inner_size(winit::dpi::LogicalSize::new(
+                    DEFAULT_WINDOW_SIZE[0],
+                    DEFAULT_WINDOW_SIZE[1],
+                ))
+                .build(&event_loop)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    info!("created {} windows", windows.len());
+
+    let mut engine = MultiWindowExample::new(windows)?;
+
+    event_loop.run(move |event, elwt| match event {
+        Event::WindowEvent { window_id, event } => match event {
+            WindowEvent::CloseRequested => {
+                // the remaining windows keep rendering with their own swapchains
+                engine.close_window(window_id).unwrap();
+                if engine.windows.is_empty() {
+                    elwt.exit();
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Escape),
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } => elwt.exit(),
+            _ => (),
+        },
+        Event::AboutToWait => engine.draw_frames().unwrap(),
+        _ => (),
+    })?;
+
+    Ok(())
+}
+
+struct MultiWindowExample {
+    windows: Vec<WindowRenderer>,
+
+    queue: Arc<Queue>,
+    present_queue: Arc<Queue>,
+    present_queue_sharing: PresentQueueSharing,
+}
+
+/// Everything needed to render to one window. Nothing here is shared with the other windows
+/// except the device and queues.
+struct WindowRenderer {
+    frames: PerFrame<FrameResources>,
+    /// Old swapchains and framebuffers waiting for this window's frames using them to complete.
+    deletion_queue: DeletionQueue,
+    framebuffers: PerSwapchainImage<Arc<Framebuffer>>,
+    /// Waited on by presents. Per swapchain image because a present has no fence to say when
+    /// it's done with its semaphore, see the triangle example.
+    render_finished_semaphores: PerSwapchainImage<Semaphore>,
+    // one pipeline per window because the windows' surface formats (and therefore render
+    // passes) can differ e.g. when they're on different monitors
+    pipeline: GraphicsPipeline,
+    render_pass: Arc<RenderPass>,
+    swapchain: Arc<Swapchain>,
+    surface: Arc<Surface>,
+    // declared last so the window outlives its surface
+    window: Window,
+}
+
+/// Resources which are reused once the frame that last used them has finished.
+struct FrameResources {
+    command_buffer: CommandBuffer,
+    image_available_semaphore: Semaphore,
+    in_flight_fence: Fence,
+}
+
+impl MultiWindowExample {
+    pub fn new(windows: Vec<Window>) -> Result<Self, Box<dyn Error>> {
+        let mut windows = windows.into_iter();
+        let first_window = windows.next().ok_or("at least one window is required")?;
+
+        let config = WindowedInitConfig {
+            entry: Some(create_entry()?),
+            ..Default::default()
+        };
+        let context = windowed::init(&first_window, first_window.inner_size().into(), config)?;
+        info!("created instance, device and first swapchain");
+
+        // additional windows share the instance, device and queues of the first
+        let mut window_surfaces = vec![(
+            first_window,
+            WindowSurface {
+                surface: context.surface.clone(),
+                swapchain: context.swapchain.clone(),
+            },
+        )];
+        for window in windows {
+            let window_surface = context.add_window(
+                &window,
+                window.inner_size().into(),
+                &WindowedInitConfig::default(),
+            )?;
+            window_surfaces.push((window, window_surface));
+        }
+        info!("created {} surfaces and swapchains", window_surfaces.len());
+
+        // the context's own references to the first surface/swapchain are dropped here so that
+        // window can be closed without tearing down the others
+        let WindowedContext {
+            device,
+            queue,
+            present_queue,
+            present_queue_sharing,
+            ..
+        } = context;
+
+        let command_pool_properties = CommandPoolProperties {
+            flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            queue_family_index: queue.family_index(),
+        };
+        let command_pool = Arc::new(CommandPool::new(device.clone(), command_pool_properties)?);
+        info!("created command pool");
+
+        let pipeline_layout_properties = PipelineLayoutProperties::new(Vec::new(), Vec::new());
+        let pipeline_layout = Arc::new(PipelineLayout::new(
+            device.clone(),
+            pipeline_layout_properties,
+        )?);
+
+        let mut vertex_spv_file = std::io::Cursor::new(&include_bytes!("./triangle.vert.spv")[..]);
+        let vert_shader = Arc::new(ShaderModule::new_from_spirv(
+            device.clone(),
+            &mut vertex_spv_file,
+        )?);
+        let vert_stage = ShaderStage::vertex(vert_shader)?;
+
+        let mut frag_spv_file = std::io::Cursor::new(&include_bytes!("./triangle.frag.spv")[..]);
+        let frag_shader = Arc::new(ShaderModule::new_from_spirv(
+            device.clone(),
+            &mut frag_spv_file,
+        )?);
+        let frag_stage = ShaderStage::fragment(frag_shader)?;
+        let shader_stages = [vert_stage, frag_stage];
+
+        let windows = window_surfaces
+            .into_iter()
+            .map(|(window, window_surface)| {
+                WindowRenderer::new(
+                    window,
+                    window_surface,
+                    &command_pool,
+                    &pipeline_layout,
+                    &shader_stages,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            windows,
+
+            queue,
+            present_queue,
+            present_queue_sharing,
+        })
+    }
+
+    pub fn draw_frames(&mut self) -> Result<(), Box<dyn Error>> {
+        for window in &mut self.windows {
+            window.draw_frame(&self.queue, &self.present_queue, self.present_queue_sharing)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the resources of one window. The other windows are unaffected.
+    pub fn close_window(&mut self, window_id: WindowId) -> Result<(), Box<dyn Error>> {
+        let Some(position) = self
+            .windows
+            .iter()
+            .position(|window| window.window.id() == window_id)
+        else {
+            return Ok(());
+        };
+
+        // the shared queues may still be using this window's command buffers, semaphores and
+        // swapchain images. the other windows don't need to be idle
+        let mut window = self.windows.remove(position);
+        window.wait_for_teardown(&self.present_queue)?;
+        info!("closed window. {} remaining", self.windows.len());
+
+        Ok(())
+    }
+}
+
+impl Drop for MultiWindowExample {
+    fn drop(&mut self) {
+        info!("dropping main class...");
+
+        for window in &mut self.windows {
+            if let Err(e) = window.wait_for_teardown(&self.present_queue) {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
+impl WindowRenderer {
+    fn new(
+        window: Window,
+        window_surface: WindowSurface,
+        command_pool: &Arc<CommandPool>,
+        pipeline_layout: &Arc<PipelineLayout>,
+        shader_stages: &[ShaderStage],
+    ) -> Result<Self, Box<dyn Error>> {
+        let WindowSurface { surface, swapchain } = window_surface;
+        let device = command_pool.device().clone();
+
+        let render_pass =
+            create_render_pass(device.clone(), swapchain.properties().surface_format)?;
+
+        let dynamic_state =
+            DynamicState::new_default(vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
+        let viewport_state = ViewportState::new_dynamic(1, 1);
+        let color_blend_state =
+            ColorBlendState::new_default(vec![ColorBlendState::blend_state_disabled()]);
+
+        let pipeline_properties = GraphicsPipelineProperties {
+            subpass_index: 0,
+            dynamic_state,
+            color_blend_state,
+            viewport_state,
+            ..Default::default()
+        };
+
+        let pipeline = GraphicsPipeline::new_recreatable(
+            pipeline_layout.clone(),
+            pipeline_properties,
+            shader_stages,
+            &render_pass,
+            None,
+        )?;
+        info!("created graphics pipeline");
+
+        let swapchain_image_views = create_swapchain_image_views(&swapchain)?;
+        let framebuffers = create_framebuffers(swapchain_image_views, render_pass.clone())?;
+        let render_finished_semaphores = create_render_finished_semaphores(&swapchain)?;
+
+        let mut command_buffers = command_pool.allocate_command_buffers(
+            vk::CommandBufferLevel::PRIMARY,
+            MAX_FRAMES_IN_FLIGHT as u32,
+        )?;
+        let frames = PerFrame::try_from_fn(MAX_FRAMES_IN_FLIGHT, |_| -> VkResult<_> {
+            Ok(FrameResources {
+                command_buffer: command_buffers.pop().expect("one per frame in flight"),
+                image_available_semaphore: Semaphore::new(device.clone())?,
+                in_flight_fence: Fence::new_signalled(device.clone())?,
+            })
+        })?;
+
+        Ok(Self {
+            frames,
+            deletion_queue: DeletionQueue::new(MAX_FRAMES_IN_FLIGHT),
+            framebuffers,
+            render_finished_semaphores,
+            pipeline,
+            render_pass,
+            swapchain,
+            surface,
+            window,
+        })
+    }
+
+    fn draw_frame(
+        &mut self,
+        queue: &Queue,
+        present_queue: &Queue,
+        present_queue_sharing: PresentQueueSharing,
+    ) -> Result<(), Box<dyn Error>> {
+        let frame = self.frames.current();
+        frame.in_flight_fence.wait(FENCE_TIMEOUT)?;
+
+        let aquire_res = self.swapchain.aquire_next_image(
+            FENCE_TIMEOUT,
+            Some(&frame.image_available_semaphore),
+            None,
+        );
+
+        let (swapchain_image_index, is_suboptimal) = match aquire_res {
+            Ok((image_index, is_suboptimal)) => {
+                (SwapchainImageIndex::new(image_index), is_suboptimal)
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                return self.recreate_swapchain(present_queue_sharing);
+            }
+            Err(e) => return Err(e)?,
+        };
+        if is_suboptimal {
+            return self.recreate_swapchain(present_queue_sharing);
+        }
+
+        // this frame slot is definitely being reused now (the early returns above retry it) so
+        // resources retired `MAX_FRAMES_IN_FLIGHT` reuses ago are no longer in use
+        self.deletion_queue.next_frame();
+        frame.in_flight_fence.reset()?;
+
+        frame
+            .command_buffer
+            .reset(vk::CommandBufferResetFlags::empty())?;
+        self.record_commands(&frame.command_buffer, swapchain_image_index)?;
+
+        let wait_semaphores = [frame.image_available_semaphore.handle()];
+        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let signal_semaphores = [self.render_finished_semaphores[swapchain_image_index].handle()];
+        let submit_command_buffers = [frame.command_buffer.handle()];
+
+        let submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .signal_semaphores(&signal_semaphores)
+            .command_buffers(&submit_command_buffers);
+
+        queue.submit(&[submit_info], Some(&frame.in_flight_fence))?;
+
+        let present_swapchains = [self.swapchain.handle()];
+        let present_indices = [swapchain_image_index.value()];
+        let present_info = vk::PresentInfoKHR::default()
+            .wait_semaphores(&signal_semaphores)
+            .swapchains(&present_swapchains)
+            .image_indices(&present_indices);
+
+        let present_res = self.swapchain.queue_present(present_queue, &present_info);
+
+        match FrameOutcome::from_present_result(present_res) {
+            FrameOutcome::Presented | FrameOutcome::Suspended => (),
+            FrameOutcome::RecreateSwapchain => self.recreate_swapchain(present_queue_sharing)?,
+            FrameOutcome::RecreateSurface => return Err("surface lost")?,
+            FrameOutcome::DeviceLost => return Err("device lost")?,
+            FrameOutcome::Error(e) => return Err(e)?,
+        };
+
+        self.frames.advance();
+
+        Ok(())
+    }
+
+    fn record_commands(
+        &self,
+        command_buffer: &CommandBuffer,
+        swapchain_image_index: SwapchainImageIndex,
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::default();
+        command_buffer.begin(&command_buffer_begin_info)?;
+
+        let clear_values = [vk::ClearValue::default()];
+        let render_extent = self.swapchain.whole_rect();
+        let viewport = self.swapchain.whole_viewport();
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(self.render_pass.handle())
+            .framebuffer(self.framebuffers[swapchain_image_index].handle())
+            .render_area(render_extent)
+            .clear_values(&clear_values);
+        command_buffer.begin_render_pass(&render_pass_begin_info, vk::SubpassContents::INLINE);
+
+        command_buffer.bind_pipeline(&self.pipeline);
+
+        command_buffer.set_viewport(0, &[viewport]);
+        command_buffer.set_scissor(0, &[render_extent]);
+
+        command_buffer.draw(3, 1, 0, 0);
+
+        command_buffer.end_render_pass();
+
+        command_buffer.end()?;
+
+        Ok(())
+    }
+
+    /// Recreates this window's swapchain with the same surface format so the render pass and
+    /// pipeline can be kept. Only this window's framebuffers are recreated and nothing waits for
+    /// the device to idle.
+    /// Waits for this window's frames and presents so it can be dropped.
+    fn wait_for_teardown(&mut self, present_queue: &Queue) -> Result<(), DeviceError> {
+        self.swapchain.wait_for_teardown(
+            self.frames.iter().map(|frame| &frame.in_flight_fence),
+            present_queue,
+            FENCE_TIMEOUT,
+        )?;
+        self.deletion_queue.flush();
+        Ok(())
+    }
+
+    fn recreate_swapchain(
+        &mut self,
+        present_queue_sharing: PresentQueueSharing,
+    ) -> Result<(), Box<dyn Error>> {
+        let device = self.render_pass.device().clone();
+        let old_properties = self.swapchain.properties();
+
+        let swapchain_properties = SwapchainProperties::new_default(
+            &device,
+            &self.surface,
+            old_properties.image_count,
+            old_properties.surface_format,
+            old_properties.composite_alpha,
+            old_properties.image_usage,
+            self.window.inner_size().into(),
+        );
+        let mut swapchain_properties = match swapchain_properties {
+            Ok(swapchain_properties) => swapchain_properties,
+            // minimized window. keep the old swapchain and try again next frame
+            Err(e) if e.is_zero_extent() => return Ok(()),
+            Err(e) => return Err(e)?,
+        };
+        present_queue_sharing.apply(&mut swapchain_properties);
+        info!("recreating swapchain for window {:?}...", self.window.id());
+
+        // the other windows keep rendering. this window's old swapchain, framebuffers and
+        // semaphores are dropped once its frames using them have completed
+        let old_framebuffers = std::mem::take(&mut self.framebuffers);
+        let old_render_finished_semaphores = std::mem::take(&mut self.render_finished_semaphores);
+        self.swapchain = self.swapchain.recreate_deferred(
+            swapchain_properties,
+            &mut self.deletion_queue,
+            (old_framebuffers, old_render_finished_semaphores),
+        )?;
+        let swapchain_image_views = create_swapchain_image_views(&self.swapchain)?;
+        self.framebuffers = create_framebuffers(swapchain_image_views, self.render_pass.clone())?;
+        self.render_finished_semaphores = create_render_finished_semaphores(&self.swapchain)?;
+
+        Ok(())
+    }
+}
+
+fn create_render_pass(
+    device: Arc<bort_vk::Device>,
+    surface_format: vk::SurfaceFormatKHR,
+) -> Result<Arc<RenderPass>, Box<dyn Error>> {
+    let swapchain_attachment_description = vk::AttachmentDescription {
+        format: surface_format.format,
+        samples: vk::SampleCountFlags::TYPE_1,
+        load_op: vk::AttachmentLoadOp::CLEAR,
+        store_op: vk::AttachmentStoreOp::STORE,
+        initial_layout: vk::ImageLayout::UNDEFINED,
+        final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        ..Default::default()
+    };
+
+    let swapchain_attachemnt_reference = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+
+    let subpass = Subpass::new(&[swapchain_attachemnt_reference], None, &[]);
+
+    let image_aquire_subpass_dependency = vk::SubpassDependency {
+        src_subpass: vk::SUBPASS_EXTERNAL,
+        dst_subpass: 0,
+        src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        src_access_mask: vk::AccessFlags::empty(),
+        dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ..Default::default()
+    };
+
+    let render_pass = Arc::new(RenderPass::new(
+        device,
+        vec![swapchain_attachment_description],
+        vec![subpass],
+        vec![image_aquire_subpass_dependency],
+    )?);
+
+    info!("created render pass");
+    Ok(render_pass)
+}
+
+fn create_swapchain_image_views(
+    swapchain: &Arc<Swapchain>,
+) -> Result<Vec<Arc<ImageView<SwapchainImage>>>, Box<dyn Error>> {
+    let swapchain_image_views = swapchain
+        .swapchain_images()
+        .iter()
+        .map(|swapchain_image| {
+            let image_view =
+                ImageView::new(swapchain_image.clone(), swapchain.image_view_properties())?;
+            Ok(Arc::new(image_view))
+        })
+        .collect::<VkResult<Vec<_>>>()?;
+    info!(
+        "created {} swapchain image views",
+        swapchain_image_views.len()
+    );
+
+    Ok(swapchain_image_views)
+}
+
+fn create_framebuffers(
+    swapchain_image_views: Vec<Arc<ImageView<SwapchainImage>>>,
+    render_pass: Arc<RenderPass>,
+) -> Result<PerSwapchainImage<Arc<Framebuffer>>, Box<dyn Error>> {
+    let framebuffers = swapchain_image_views
+        .into_iter()
+        .map(|swapchain_image_view| {
+            let attachments: Vec<Arc<dyn ImageViewAccess>> = vec![swapchain_image_view.clone()];
+
+            let framebuffer_properties = FramebufferProperties::new_default(
+                attachments,
+                swapchain_image_view.image().dimensions(),
+            );
+
+            let framebuffer = Framebuffer::new(render_pass.clone(), framebuffer_properties)?;
+            Ok(Arc::new(framebuffer))
+        })
+        .collect::<VkResult<Vec<_>>>()?;
+
+    info!("created {} framebuffers", framebuffers.len());
+    Ok(PerSwapchainImage::from_vec(framebuffers))
+}
+
+fn create_render_finished_semaphores(
+    swapchain: &Swapchain,
+) -> VkResult<PerSwapchainImage<Semaphore>> {
+    let image_count = swapchain.swapchain_images().len() as u32;
+    PerSwapchainImage::try_from_fn(image_count, |_| Semaphore::new(swapchain.device().clone()))
+}
@@ -0,0 +1,473 @@
+//! Headless example drawing hundreds of objects, each with its own transform and color, through a
+//! `DynamicUniformRing`: one `UNIFORM_BUFFER_DYNAMIC` descriptor set bound with a different
+//! dynamic offset for every draw, with a separate region of the buffer for each frame in flight
+//! so the CPU never overwrites uniforms the GPU is still reading. After several frames the final
+//! image is read back and the color of every object is checked.
+//!
+//! `cargo test --bin uniform_ring` runs the same check as a test (skipped if no vulkan device is
+//! available).
+
+use ash::{prelude::VkResult, vk};
+use bort_vk::{
+    ApiVersion, ColorBlendState, CommandBuffer, CommandPool, CommandPoolProperties, DescriptorPool,
+    DescriptorPoolProperties, DescriptorSetLayout, DescriptorSetLayoutProperties, Device,
+    DynamicState, DynamicUniformBuffer, DynamicUniformRing, Fence, Framebuffer,
+    FramebufferProperties, GraphicsPipeline, GraphicsPipelineProperties, Image, ImageDimensions,
+    ImageReadback, ImageViewAccess, InputAssemblyState, Instance, MemoryAllocator, PerFrame,
+    PhysicalDevice, PhysicalDeviceFeatures, PipelineLayout, PipelineLayoutProperties, Queue,
+    RenderPass, ShaderModule, ShaderStage, Subpass, ViewportState,
+};
+use env_logger::Env;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use std::{error::Error, sync::Arc};
+
+const MAX_API_VERSION: ApiVersion = ApiVersion { major: 1, minor: 3 };
+const FRAMES_IN_FLIGHT: usize = 2;
+/// Enough frames for each region of the ring to be reused a couple of times.
+const FRAME_COUNT: usize = 5;
+const GRID_SIZE: u32 = 16;
+const OBJECT_COUNT: u32 = GRID_SIZE * GRID_SIZE;
+const CELL_SIZE_PIXELS: u32 = 16;
+const RENDER_SIZE: u32 = GRID_SIZE * CELL_SIZE_PIXELS;
+const COLOR_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+const FENCE_TIMEOUT: u64 = 1_000_000_000;
+
+/// Matches `ObjectUniforms` in uniform_ring.vert.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct ObjectUniforms {
+    offset_scale: [f32; 4],
+    color: [f32; 4],
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = unsafe { ash::Entry::load() }?;
+    Ok(Arc::new(entry))
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn create_entry() -> Result<Arc<ash::Entry>, ash::LoadingError> {
+    let entry = ash_molten::load();
+    Ok(Arc::new(entry))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let log_env = Env::default()
+        .filter_or("MY_LOG_LEVEL", "debug")
+        .write_style_or("MY_LOG_STYLE", "always");
+    env_logger::init_from_env(log_env);
+    info!("starting uniform ring example...");
+
+    let context = ExampleContext::new()?;
+    render_and_verify(&context)?;
+
+    info!("uniform ring example completed");
+    Ok(())
+}
+
+struct ExampleContext {
+    device: Arc<Device>,
+    queue: Queue,
+    command_pool: Arc<CommandPool>,
+    memory_allocator: Arc<MemoryAllocator>,
+}
+
+impl ExampleContext {
+    fn new() -> Result<Self, Box<dyn Error>> {
+        let entry = create_entry()?;
+        let instance = Arc::new(Instance::new(entry, MAX_API_VERSION, vec![], vec![])?);
+
+        let physical_device_handles = instance.enumerate_physical_devices()?;
+        let physical_device_handle = physical_device_handles
+            .first()
+            .ok_or(BortExampleError::NoPhysicalDevice)?;
+        let physical_device = Arc::new(PhysicalDevice::new(
+            instance.clone(),
+            *physical_device_handle,
+        )?);
+        info!("chosen physical device: {}", physical_device.name());
+
+        let queue_family_index = physical_device
+            .queue_family_properties()
+            .iter()
+            .position(|properties| properties.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .ok_or(BortExampleError::NoGraphicsQueue)? as u32;
+
+        let queue_priorities = [1.0];
+        let queue_create_info = vk::DeviceQueueCreateInfo::default()
+            .queue_family_index(queue_family_index)
+            .queue_priorities(&queue_priorities);
+        let device = Arc::new(Device::new(
+            physical_device,
+            [queue_create_info],
+            PhysicalDeviceFeatures::default(),
+            vec![],
+            vec![],
+            None,
+        )?);
+
+        let queue = Queue::new(device.clone(), queue_family_index, 0)?;
+        let command_pool = Arc::new(CommandPool::new(
+            device.clone(),
+            CommandPoolProperties {
+                flags: vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+                queue_family_index,
+            },
+        )?);
+        let memory_allocator = Arc::new(MemoryAllocator::new(device.clone())?);
+
+        Ok(Self {
+            device,
+            queue,
+            command_pool,
+            memory_allocator,
+        })
+    }
+}
+
+struct FrameResources {
+    command_buffer: CommandBuffer,
+    fence: Fence,
+}
+
+fn render_and_verify(context: &ExampleContext) -> Result<(), Box<dyn Error>> {
+    let device = &context.device;
+
+    let render_dimensions = ImageDimensions::new_2d(RENDER_SIZE, RENDER_SIZE);
+    let (color_image, color_view) = Image::new_color_attachment(
+        context.memory_allocator.clone(),
+        render_dimensions,
+        COLOR_FORMAT,
+        vk::ImageUsageFlags::TRANSFER_SRC,
+    )?;
+    let render_pass = create_render_pass(device.clone())?;
+    let framebuffer = Framebuffer::new(
+        render_pass.clone(),
+        FramebufferProperties::new_default(
+            vec![color_view.clone() as Arc<dyn ImageViewAccess>],
+            render_dimensions,
+        ),
+    )?;
+
+    // one dynamic uniform buffer descriptor serves every object of every frame
+
+    let mut uniform_ring = DynamicUniformRing::<ObjectUniforms>::new(
+        context.memory_allocator.clone(),
+        FRAMES_IN_FLIGHT,
+        OBJECT_COUNT,
+    )?;
+    info!(
+        "created uniform ring: {} objects per frame, {} byte stride",
+        uniform_ring.capacity_per_frame(),
+        uniform_ring.uniform_buffer().element_stride()
+    );
+
+    let descriptor_set_layout = Arc::new(DescriptorSetLayout::new(
+        device.clone(),
+        DescriptorSetLayoutProperties::new_default(vec![
+            DynamicUniformBuffer::<ObjectUniforms>::layout_binding(0, vk::ShaderStageFlags::VERTEX),
+        ]),
+    )?);
+    let descriptor_pool = Arc::new(DescriptorPool::new(
+        device.clone(),
+        DescriptorPoolProperties::new_default(
+            1,
+            vec![vk::DescriptorPoolSize {
+                ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                descriptor_count: 1,
+            }],
+        ),
+    )?);
+    let descriptor_set = descriptor_pool.allocate_descriptor_set(descriptor_set_layout.clone())?;
+    let buffer_info = uniform_ring.descriptor_buffer_info();
+    let uniform_write = DynamicUniformBuffer::<ObjectUniforms>::write_descriptor_set(
+        &descriptor_set,
+        0,
+        &buffer_info,
+    );
+    device.update_descriptor_sets([uniform_write], []);
+
+    let pipeline = create_pipeline(device.clone(), descriptor_set_layout, &render_pass)?;
+
+    let mut frames = PerFrame::try_from_fn(FRAMES_IN_FLIGHT, |_| -> VkResult<_> {
+        Ok(FrameResources {
+            command_buffer: CommandBuffer::new(
+                context.command_pool.clone(),
+                vk::CommandBufferLevel::PRIMARY,
+            )?,
+            fence: Fence::new_signalled(device.clone())?,
+        })
+    })?;
+
+    for frame_number in 0..FRAME_COUNT {
+        let frame_index = frames.current_index();
+        let frame = frames.current();
+
+        // the previous submission using this frame's region of the ring must have finished
+        // before it's overwritten
+        frame.fence.wait(FENCE_TIMEOUT)?;
+        frame.fence.reset()?;
+
+        let object_uniforms: Vec<ObjectUniforms> = (0..OBJECT_COUNT)
+            .map(|object_index| object_uniforms(frame_number, object_index))
+            .collect();
+        uniform_ring.write_frame(frame_index, &object_uniforms)?;
+
+        let command_buffer = &frame.command_buffer;
+        command_buffer.begin(
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )?;
+
+        let clear_values = [vk::ClearValue::default()];
+        let render_area = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: vk::Extent2D {
+                width: RENDER_SIZE,
+                height: RENDER_SIZE,
+            },
+        };
+        let render_pass_begin_info = vk::RenderPassBeginInfo::default()
+            .render_pass(render_pass.handle())
+            .framebuffer(framebuffer.handle())
+            .render_area(render_area)
+            .clear_values(&clear_values);
+        command_buffer.begin_render_pass(&render_pass_begin_info, vk::SubpassContents::INLINE);
+
+        command_buffer.bind_pipeline(&pipeline);
+        command_buffer.set_viewport(
+            0,
+            &[vk::Viewport {
+                width: RENDER_SIZE as f32,
+                height: RENDER_SIZE as f32,
+                max_depth: 1.,
+                ..Default::default()
+            }],
+        );
+        command_buffer.set_scissor(0, &[render_area]);
+
+        for object_index in 0..OBJECT_COUNT {
+            let dynamic_offset = uniform_ring.dynamic_offset(frame_index, object_index)?;
+            command_buffer.bind_descriptor_sets_for_pipeline(
+                &pipeline,
+                0,
+                &[&descriptor_set],
+                &[dynamic_offset],
+            )?;
+            command_buffer.draw(4, 1, 0, 0);
+        }
+
+        command_buffer.end_render_pass();
+        command_buffer.end()?;
+
+        let submit_command_buffers = [command_buffer.handle()];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&submit_command_buffers);
+        context.queue.submit(&[submit_info], Some(&frame.fence))?;
+
+        frames.advance();
+    }
+
+    device.wait_idle()?;
+    info!(
+        "rendered {} frames of {} objects",
+        FRAME_COUNT, OBJECT_COUNT
+    );
+
+    let readback = ImageReadback::new_from_image(
+        context.memory_allocator.clone(),
+        color_image.as_ref(),
+        COLOR_FORMAT,
+        &context.command_pool,
+        &context.queue,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    )?;
+
+    let last_frame_number = FRAME_COUNT - 1;
+    let mut mismatch_count = 0;
+    for object_index in 0..OBJECT_COUNT {
+        let expected_color = object_uniforms(last_frame_number, object_index).color;
+        let (x, y) = object_cell(object_index);
+        let center_x = x * CELL_SIZE_PIXELS + CELL_SIZE_PIXELS / 2;
+        let center_y = y * CELL_SIZE_PIXELS + CELL_SIZE_PIXELS / 2;
+        let texel_offset = center_x as usize * 4;
+        let texel = &readback.row(center_y)[texel_offset..texel_offset + 4];
+
+        let matches = texel[..3]
+            .iter()
+            .zip(expected_color)
+            .all(|(&actual, expected)| (actual as f32 - expected * 255.).abs() <= 1.);
+        if !matches {
+            mismatch_count += 1;
+            warn!(
+                "object {} rendered as {:?} but expected {:?}",
+                object_index, texel, expected_color
+            );
+        }
+    }
+
+    if mismatch_count > 0 {
+        return Err(BortExampleError::ObjectColorMismatch { mismatch_count }.into());
+    }
+    info!("all {} objects have the expected color", OBJECT_COUNT);
+    Ok(())
+}
+
+/// Grid cell (column, row) of `object_index`.
+fn object_cell(object_index: u32) -> (u32, u32) {
+    (object_index % GRID_SIZE, object_index / GRID_SIZE)
+}
+
+/// A quad in the middle of the object's grid cell. The blue channel changes every frame so
+/// uniforms left over from an earlier frame would show up in the readback.
+fn object_uniforms(frame_number: usize, object_index: u32) -> ObjectUniforms {
+    let (x, y) = object_cell(object_index);
+    let cell_size = 2. / GRID_SIZE as f32;
+    let center_x = -1. + (x as f32 + 0.5) * cell_size;
+    let center_y = -1. + (y as f32 + 0.5) * cell_size;
+    let half_extent = cell_size / 4.;
+
+    let channel_step = 255 / (GRID_SIZE - 1);
+    ObjectUniforms {
+        offset_scale: [center_x, center_y, half_extent, half_extent],
+        color: [
+            (x * channel_step) as f32 / 255.,
+            (y * channel_step) as f32 / 255.,
+            ((frame_number * 50) % 256) as f32 / 255.,
+            1.,
+        ],
+    }
+}
+
+fn create_render_pass(device: Arc<Device>) -> Result<Arc<RenderPass>, Box<dyn Error>> {
+    let color_attachment_description = vk::AttachmentDescription {
+        format: COLOR_FORMAT,
+        samples: vk::SampleCountFlags::TYPE_1,
+        load_op: vk::AttachmentLoadOp::CLEAR,
+        store_op: vk::AttachmentStoreOp::STORE,
+        initial_layout: vk::ImageLayout::UNDEFINED,
+        final_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ..Default::default()
+    };
+
+    let color_attachment_reference = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+    let subpass = Subpass::new(&[color_attachment_reference], None, &[]);
+
+    // every frame renders to the same image so consecutive frames' writes must be ordered, and
+    // the final frame must be visible to the readback copy
+    let previous_frame_dependency = vk::SubpassDependency {
+        src_subpass: vk::SUBPASS_EXTERNAL,
+        dst_subpass: 0,
+        src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ..Default::default()
+    };
+    let readback_dependency = vk::SubpassDependency {
+        src_subpass: 0,
+        dst_subpass: vk::SUBPASS_EXTERNAL,
+        src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        dst_stage_mask: vk::PipelineStageFlags::TRANSFER,
+        src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+        ..Default::default()
+    };
+
+    let render_pass = Arc::new(RenderPass::new(
+        device,
+        vec![color_attachment_description],
+        vec![subpass],
+        vec![previous_frame_dependency, readback_dependency],
+    )?);
+    Ok(render_pass)
+}
+
+fn create_pipeline(
+    device: Arc<Device>,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    render_pass: &RenderPass,
+) -> Result<GraphicsPipeline, Box<dyn Error>> {
+    let pipeline_layout = Arc::new(PipelineLayout::new(
+        device.clone(),
+        PipelineLayoutProperties::new(vec![descriptor_set_layout], Vec::new()),
+    )?);
+
+    let mut vertex_spv_file = std::io::Cursor::new(&include_bytes!("./uniform_ring.vert.spv")[..]);
+    let vert_shader = Arc::new(ShaderModule::new_from_spirv(
+        device.clone(),
+        &mut vertex_spv_file,
+    )?);
+    let vert_stage = ShaderStage::vertex(vert_shader)?;
+
+    let mut frag_spv_file = std::io::Cursor::new(&include_bytes!("./triangle.frag.spv")[..]);
+    let frag_shader = Arc::new(ShaderModule::new_from_spirv(device, &mut frag_spv_file)?);
+    let frag_stage = ShaderStage::fragment(frag_shader)?;
+
+    let pipeline_properties = GraphicsPipelineProperties {
+        subpass_index: 0,
+        input_assembly_state: InputAssemblyState {
+            topology: vk::PrimitiveTopology::TRIANGLE_STRIP,
+            ..Default::default()
+        },
+        dynamic_state: DynamicState::new_default(vec![
+            vk::DynamicState::VIEWPORT,
+            vk::DynamicState::SCISSOR,
+        ]),
+        color_blend_state: ColorBlendState::new_default(vec![
+            ColorBlendState::blend_state_disabled(),
+        ]),
+        viewport_state: ViewportState::new_dynamic(1, 1),
+        ..Default::default()
+    };
+
+    let pipeline = GraphicsPipeline::new(
+        pipeline_layout,
+        pipeline_properties,
+        &[vert_stage, frag_stage],
+        render_pass,
+        None,
+    )?;
+    Ok(pipeline)
+}
+
+#[derive(Debug)]
+enum BortExampleError {
+    NoPhysicalDevice,
+    NoGraphicsQueue,
+    ObjectColorMismatch { mismatch_count: u32 },
+}
+
+impl std::fmt::Display for BortExampleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::NoPhysicalDevice => write!(f, "no vulkan physical device available"),
+            Self::NoGraphicsQueue => write!(f, "no queue family supports graphics"),
+            Self::ObjectColorMismatch { mismatch_count } => write!(
+                f,
+                "{} objects didn't have the expected color in the readback",
+                mismatch_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BortExampleError {}
+
+// ~~ Tests ~~
+
+#[test]
+fn uniform_ring_readback_matches() {
+    let context = match ExampleContext::new() {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("skipping uniform ring test: {}", e);
+            return;
+        }
+    };
+    render_and_verify(&context).unwrap();
+}
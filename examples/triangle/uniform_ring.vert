@@ -0,0 +1,17 @@
+#version 450
+
+// per-object data, selected for each draw with a dynamic offset
+layout(set = 0, binding = 0) uniform ObjectUniforms {
+    // xy = center, zw = half extent (normalized device coordinates)
+    vec4 offset_scale;
+    vec4 color;
+} object;
+
+layout(location = 0) out vec3 fragColor;
+
+void main() {
+    // triangle strip quad corners from the vertex index
+    vec2 corner = vec2(float(gl_VertexIndex & 1), float(gl_VertexIndex >> 1)) * 2.0 - 1.0;
+    gl_Position = vec4(object.offset_scale.xy + corner * object.offset_scale.zw, 0.0, 1.0);
+    fragColor = object.color.rgb;
+}